@@ -0,0 +1,11 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Generate the gRPC service stubs from proto/onyx.proto.
+    #[cfg(feature = "grpc")]
+    {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc not found");
+        std::env::set_var("PROTOC", protoc);
+        tonic_build::compile_protos("proto/onyx.proto").expect("failed to compile onyx.proto");
+    }
+}
@@ -4,8 +4,10 @@
 //! [`OnyxClientBuilder`]. Sub-clients for each domain area are accessible via
 //! methods on the main client.
 
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use futures_util::stream::{self, Stream, TryStreamExt};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE};
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use url::Url;
 use uuid::Uuid;
 
@@ -20,7 +22,7 @@ use crate::models::*;
 struct ClientInner {
     http: reqwest::Client,
     base_url: Url,
-    api_key: Option<String>,
+    retry: RetryPolicy,
 }
 
 impl ClientInner {
@@ -30,12 +32,39 @@ impl ClientInner {
     }
 
     /// Execute a GET request and deserialize the JSON response.
+    ///
+    /// GETs are idempotent, so transient failures are retried per the
+    /// client's [`RetryPolicy`].
     async fn get<T: serde::de::DeserializeOwned>(&self, path: &str) -> OnyxResult<T> {
         let url = self.url(path)?;
-        let resp = self.http.get(url).send().await?;
+        let resp = self.send_with_retry(|| self.http.get(url.clone())).await?;
         Self::handle_response(resp).await
     }
 
+    /// Send a request, retrying transient failures with exponential backoff.
+    ///
+    /// Only call this for idempotent requests. Once attempts are exhausted the
+    /// last response (or transport error) is returned unchanged.
+    async fn send_with_retry<F>(&self, build: F) -> OnyxResult<reqwest::Response>
+    where
+        F: Fn() -> reqwest::RequestBuilder,
+    {
+        let mut attempt = 1;
+        loop {
+            let result = build().send().await;
+            let retryable = match &result {
+                Ok(resp) => is_retryable_status(resp.status()),
+                Err(err) => err.is_connect() || err.is_timeout(),
+            };
+            if !retryable || attempt >= self.retry.max_attempts {
+                return result.map_err(OnyxError::from);
+            }
+            tracing::debug!(attempt, "retrying transient Onyx API failure");
+            tokio::time::sleep(self.retry.delay_for(attempt)).await;
+            attempt += 1;
+        }
+    }
+
     /// Execute a POST request with a JSON body.
     async fn post<B: serde::Serialize, T: serde::de::DeserializeOwned>(
         &self,
@@ -47,6 +76,30 @@ impl ClientInner {
         Self::handle_response(resp).await
     }
 
+    /// POST a JSON body and return the response once its status is known,
+    /// leaving the body unread for streaming.
+    async fn post_streaming<B: serde::Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> OnyxResult<reqwest::Response> {
+        let url = self.url(path)?;
+        let resp = self
+            .http
+            .post(url)
+            .header(ACCEPT, "text/event-stream")
+            .json(body)
+            .send()
+            .await?;
+        if resp.status().is_success() {
+            Ok(resp)
+        } else {
+            let status = resp.status().as_u16();
+            let body = resp.text().await.unwrap_or_default();
+            Err(OnyxError::from_response(status, body))
+        }
+    }
+
     /// Execute a PUT request with a JSON body.
     async fn put<B: serde::Serialize, T: serde::de::DeserializeOwned>(
         &self,
@@ -66,8 +119,8 @@ impl ClientInner {
             Ok(())
         } else {
             let status = resp.status().as_u16();
-            let message = resp.text().await.unwrap_or_default();
-            Err(OnyxError::ApiError { status, message })
+            let body = resp.text().await.unwrap_or_default();
+            Err(OnyxError::from_response(status, body))
         }
     }
 
@@ -80,16 +133,53 @@ impl ClientInner {
             let body = resp.json::<T>().await?;
             Ok(body)
         } else {
-            let code = status.as_u16();
-            let message = resp.text().await.unwrap_or_default();
-            if status == reqwest::StatusCode::NOT_FOUND {
-                Err(OnyxError::NotFound(message))
-            } else {
-                Err(OnyxError::ApiError {
-                    status: code,
-                    message,
-                })
-            }
+            let body = resp.text().await.unwrap_or_default();
+            Err(OnyxError::from_response(status.as_u16(), body))
+        }
+    }
+}
+
+/// Whether a response status indicates a transient, retryable failure.
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 502 | 503 | 504)
+}
+
+// ---------------------------------------------------------------------------
+// Retry policy
+// ---------------------------------------------------------------------------
+
+/// Retry behaviour for idempotent requests.
+///
+/// Delays grow exponentially from `base_delay` (1x, 2x, 4x, ...) with up to
+/// 50% random jitter added to avoid synchronized retries.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first (1 disables retries).
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Delay to wait after the given (1-based) failed attempt.
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(1u32 << (attempt - 1).min(16));
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0);
+        let jitter = backoff.mul_f64((nanos % 1000) as f64 / 2000.0);
+        backoff + jitter
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
         }
     }
 }
@@ -125,7 +215,10 @@ impl OnyxClient {
         OnyxClientBuilder {
             base_url: base_url.to_string(),
             api_key: None,
-            timeout_secs: 30,
+            timeout: DEFAULT_TIMEOUT,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            pool_max_idle_per_host: None,
+            retry: RetryPolicy::default(),
         }
     }
 
@@ -133,8 +226,19 @@ impl OnyxClient {
 
     /// Check whether the Onyx server is healthy.
     pub async fn health(&self) -> OnyxResult<bool> {
+        Ok(self.health_report().await?.is_ok())
+    }
+
+    /// Fetch the full health report, including store counts.
+    ///
+    /// A `503 Service Unavailable` still yields a report (with
+    /// `db_reachable: false`) rather than an error.
+    pub async fn health_report(&self) -> OnyxResult<HealthResponse> {
         let resp = self.inner.http.get(self.inner.url("/health")?).send().await?;
-        Ok(resp.status().is_success())
+        if resp.status() == reqwest::StatusCode::SERVICE_UNAVAILABLE {
+            return Ok(resp.json::<HealthResponse>().await?);
+        }
+        ClientInner::handle_response(resp).await
     }
 
     // -- Sub-clients ----------------------------------------------------------
@@ -153,6 +257,13 @@ impl OnyxClient {
         }
     }
 
+    /// Access graph traversal and impact analysis.
+    pub fn graph(&self) -> GraphClient {
+        GraphClient {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+
     /// Access semantic search.
     pub fn search(&self) -> SearchClient {
         SearchClient {
@@ -186,11 +297,20 @@ impl OnyxClient {
 // Builder
 // ---------------------------------------------------------------------------
 
+/// Default total timeout for a single request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default timeout for establishing a connection.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Builder for [`OnyxClient`].
 pub struct OnyxClientBuilder {
     base_url: String,
     api_key: Option<String>,
-    timeout_secs: u64,
+    timeout: Duration,
+    connect_timeout: Duration,
+    pool_max_idle_per_host: Option<usize>,
+    retry: RetryPolicy,
 }
 
 impl OnyxClientBuilder {
@@ -200,9 +320,33 @@ impl OnyxClientBuilder {
         self
     }
 
-    /// Set the request timeout in seconds (default: 30).
-    pub fn timeout(mut self, secs: u64) -> Self {
-        self.timeout_secs = secs;
+    /// Set the total per-request timeout (default: 30s).
+    ///
+    /// Requests exceeding it fail with [`OnyxError::Timeout`].
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the connection establishment timeout (default: 10s).
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Cap idle pooled connections kept per host (default: unlimited).
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// Retry idempotent requests up to `max_attempts` times in total,
+    /// backing off exponentially from `base_delay` (default: 3 attempts, 200ms).
+    pub fn retry(mut self, max_attempts: u32, base_delay: Duration) -> Self {
+        self.retry = RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            base_delay,
+        };
         self
     }
 
@@ -222,16 +366,20 @@ impl OnyxClientBuilder {
             headers.insert(AUTHORIZATION, value);
         }
 
-        let http = reqwest::Client::builder()
+        let mut http = reqwest::Client::builder()
             .default_headers(headers)
-            .timeout(std::time::Duration::from_secs(self.timeout_secs))
-            .build()?;
+            .timeout(self.timeout)
+            .connect_timeout(self.connect_timeout);
+        if let Some(max) = self.pool_max_idle_per_host {
+            http = http.pool_max_idle_per_host(max);
+        }
+        let http = http.build()?;
 
         Ok(OnyxClient {
             inner: Arc::new(ClientInner {
                 http,
                 base_url,
-                api_key: self.api_key,
+                retry: self.retry,
             }),
         })
     }
@@ -253,6 +401,14 @@ impl NodesClient {
         self.inner.post("/api/nodes", &req).await
     }
 
+    /// Create many nodes in one atomic request.
+    ///
+    /// If any node is rejected (e.g. a duplicate ID) none are created and the
+    /// server responds with a 422 naming the offending index.
+    pub async fn create_many(&self, reqs: Vec<CreateNodeRequest>) -> OnyxResult<Vec<Node>> {
+        self.inner.post("/api/nodes/batch", &reqs).await
+    }
+
     /// Get a node by ID.
     pub async fn get(&self, id: Uuid) -> OnyxResult<Node> {
         self.inner.get(&format!("/api/nodes/{id}")).await
@@ -268,13 +424,33 @@ impl NodesClient {
         self.inner.delete(&format!("/api/nodes/{id}")).await
     }
 
-    /// List all nodes with pagination.
-    pub async fn list(&self, page: usize, per_page: usize) -> OnyxResult<ListNodesResponse> {
+    /// List one page of nodes.
+    pub async fn list(&self, req: ListNodesRequest) -> OnyxResult<Page<Node>> {
         self.inner
-            .get(&format!("/api/nodes?page={page}&per_page={per_page}"))
+            .get(&format!("/api/nodes?{}", req.to_query()))
             .await
     }
 
+    /// Stream every node matching `req`, fetching pages on demand.
+    ///
+    /// Starts at `req.page` and follows `next_page` until the last page.
+    pub fn list_all(&self, req: ListNodesRequest) -> impl Stream<Item = OnyxResult<Node>> {
+        let client = self.clone();
+        stream::try_unfold(Some(req), move |state| {
+            let client = client.clone();
+            async move {
+                let Some(req) = state else {
+                    return Ok(None);
+                };
+                let page = client.list(req.clone()).await?;
+                let next = page.next_page.map(|page| ListNodesRequest { page, ..req });
+                let items = stream::iter(page.items.into_iter().map(Ok));
+                OnyxResult::Ok(Some((items, next)))
+            }
+        })
+        .try_flatten()
+    }
+
     /// Get the neighbors of a node.
     pub async fn neighbors(&self, id: Uuid) -> OnyxResult<Vec<NeighborResult>> {
         self.inner
@@ -320,6 +496,74 @@ impl EdgesClient {
     pub async fn list(&self) -> OnyxResult<ListEdgesResponse> {
         self.inner.get("/api/edges").await
     }
+
+    /// Nodes that `node_id` has edges to, restricted to `edge_types` unless
+    /// it is empty.
+    pub async fn neighbors(
+        &self,
+        node_id: Uuid,
+        edge_types: &[EdgeType],
+    ) -> OnyxResult<Vec<NeighborResult>> {
+        let query = edge_types_query(edge_types);
+        self.inner
+            .get(&format!("/api/nodes/{node_id}/neighbors{query}"))
+            .await
+    }
+
+    /// Nodes with edges into `node_id`, restricted to `edge_types` unless it
+    /// is empty.
+    pub async fn inbound(
+        &self,
+        node_id: Uuid,
+        edge_types: &[EdgeType],
+    ) -> OnyxResult<Vec<NeighborResult>> {
+        let query = edge_types_query(edge_types);
+        self.inner
+            .get(&format!("/api/nodes/{node_id}/inbound{query}"))
+            .await
+    }
+}
+
+/// `?edge_types=calls,imports`, or nothing when no filter is given.
+fn edge_types_query(edge_types: &[EdgeType]) -> String {
+    if edge_types.is_empty() {
+        return String::new();
+    }
+    let names: Vec<&str> = edge_types.iter().map(EdgeType::as_str).collect();
+    format!("?edge_types={}", names.join(","))
+}
+
+// ---------------------------------------------------------------------------
+// GraphClient
+// ---------------------------------------------------------------------------
+
+/// Sub-client for multi-hop graph queries.
+#[derive(Debug, Clone)]
+pub struct GraphClient {
+    inner: Arc<ClientInner>,
+}
+
+impl GraphClient {
+    /// Traverse the graph from a node.
+    pub async fn traverse(&self, req: TraverseRequest) -> OnyxResult<TraverseResponse> {
+        self.inner.post("/api/graph/traverse", &req).await
+    }
+
+    /// Nodes within `depth` hops affected by changing `node_id`, or that it
+    /// depends on, per `direction`.
+    pub async fn impact(
+        &self,
+        node_id: Uuid,
+        depth: usize,
+        direction: ImpactDirection,
+    ) -> OnyxResult<ImpactResponse> {
+        let direction = direction.as_query_param();
+        self.inner
+            .get(&format!(
+                "/api/nodes/{node_id}/impact?depth={depth}&direction={direction}"
+            ))
+            .await
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -382,6 +626,15 @@ impl HistoryClient {
             .await
     }
 
+    /// Diff an entity's content between two of its versions.
+    pub async fn diff(&self, entity_id: Uuid, from: &str, to: &str) -> OnyxResult<Diff> {
+        self.inner
+            .get(&format!(
+                "/api/entities/{entity_id}/diff?from={from}&to={to}"
+            ))
+            .await
+    }
+
     /// Create a new branch.
     pub async fn create_branch(&self, req: CreateBranchRequest) -> OnyxResult<Branch> {
         self.inner.post("/api/branches", &req).await
@@ -426,6 +679,74 @@ impl IngestClient {
     ) -> OnyxResult<IngestCodebaseResponse> {
         self.inner.post("/api/ingest/codebase", &req).await
     }
+
+    /// Ingest `units` in one batch.
+    ///
+    /// Fails with [`OnyxError::PartialIngest`] if the server rejected any
+    /// unit, carrying the results of those it did ingest.
+    pub async fn ingest(&self, units: Vec<IngestCodeUnitRequest>) -> OnyxResult<Vec<IngestResult>> {
+        let response = self
+            .ingest_codebase(IngestCodebaseRequest { units })
+            .await?;
+        if response.failures.is_empty() {
+            Ok(response.results)
+        } else {
+            Err(OnyxError::PartialIngest {
+                results: response.results,
+                failures: response.failures,
+            })
+        }
+    }
+
+    /// Ingest `units`, streaming [`IngestEvent`]s as the server works.
+    ///
+    /// Failed units are reported as [`IngestEvent::Failed`] rather than
+    /// ending the stream; it ends after [`IngestEvent::Complete`].
+    pub fn ingest_stream(
+        &self,
+        units: Vec<IngestCodeUnitRequest>,
+    ) -> impl Stream<Item = OnyxResult<IngestEvent>> {
+        let inner = self.inner.clone();
+        let body = IngestCodebaseRequest { units };
+        stream::once(async move { inner.post_streaming("/api/ingest/stream", &body).await })
+            .map_ok(sse_events)
+            .try_flatten()
+    }
+}
+
+/// Decode a `text/event-stream` body into JSON events, one per `data` frame.
+fn sse_events<T: serde::de::DeserializeOwned>(
+    resp: reqwest::Response,
+) -> impl Stream<Item = OnyxResult<T>> {
+    stream::try_unfold((resp, Vec::new()), |(mut resp, mut buffer)| async move {
+        loop {
+            if let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                let frame: Vec<u8> = buffer.drain(..end + 2).collect();
+                if let Some(event) = parse_sse_frame(&String::from_utf8_lossy(&frame))? {
+                    return Ok(Some((event, (resp, buffer))));
+                }
+                continue;
+            }
+            match resp.chunk().await? {
+                // Normalize CRLF line endings so frames split on "\n\n".
+                Some(chunk) => buffer.extend(chunk.iter().filter(|&&b| b != b'\r')),
+                None => return Ok(None),
+            }
+        }
+    })
+}
+
+/// Parse one SSE frame; comment-only and data-less frames yield `None`.
+fn parse_sse_frame<T: serde::de::DeserializeOwned>(frame: &str) -> OnyxResult<Option<T>> {
+    let data: Vec<&str> = frame
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+    if data.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&data.join("\n"))?))
 }
 
 // ---------------------------------------------------------------------------
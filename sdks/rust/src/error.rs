@@ -1,6 +1,8 @@
 //! Error types for the Onyx SDK.
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use uuid::Uuid;
 
 /// Errors that can occur when using the Onyx SDK.
 #[derive(Error, Debug)]
@@ -16,7 +18,11 @@ pub enum OnyxError {
 
     /// A network or transport error occurred.
     #[error("Network error: {0}")]
-    NetworkError(#[from] reqwest::Error),
+    NetworkError(#[source] reqwest::Error),
+
+    /// The request did not complete within the configured timeout.
+    #[error("Request timed out: {0}")]
+    Timeout(#[source] reqwest::Error),
 
     /// Failed to serialize or deserialize JSON.
     #[error("Serialization error: {0}")]
@@ -30,14 +36,156 @@ pub enum OnyxError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    /// The server has no node with the requested ID.
+    #[error("Node not found: {0}")]
+    NodeNotFound(String),
+
+    /// The server has no edge with the requested ID.
+    #[error("Edge not found: {0}")]
+    EdgeNotFound(String),
+
+    /// The server has no version with the requested ID.
+    #[error("Version not found: {0}")]
+    VersionNotFound(String),
+
+    /// The server has no branch with the requested name.
+    #[error("Branch not found: {0}")]
+    BranchNotFound(String),
+
+    /// The request conflicts with existing data (e.g. a duplicate ID).
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    /// Some units of a batch ingest failed; the others were written.
+    #[error("Ingest partially failed: {} of {} units failed", failures.len(), failures.len() + results.len())]
+    PartialIngest {
+        /// Results for the units that were ingested.
+        results: Vec<crate::models::IngestResult>,
+        failures: Vec<crate::models::IngestFailure>,
+    },
+
+    /// The API key was missing or rejected.
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
     /// An invalid argument was provided.
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
 
+    /// A string did not name a known edge type.
+    #[error("Invalid edge type: '{0}'")]
+    InvalidEdgeType(String),
+
     /// URL parsing error.
     #[error("URL parse error: {0}")]
     UrlParseError(#[from] url::ParseError),
 }
 
+impl OnyxError {
+    /// Translate a non-2xx response into a typed error.
+    ///
+    /// Bodies in the server's error envelope are matched on their
+    /// [`ErrorCode`]; anything else becomes [`OnyxError::NotFound`] for a 404
+    /// and [`OnyxError::ApiError`] otherwise.
+    pub(crate) fn from_response(status: u16, body: String) -> Self {
+        let error = match serde_json::from_str::<ErrorEnvelope>(&body) {
+            Ok(envelope) => envelope.error,
+            Err(_) if status == 404 => return OnyxError::NotFound(body),
+            Err(_) => {
+                return OnyxError::ApiError {
+                    status,
+                    message: body,
+                }
+            }
+        };
+        let message = error.message;
+        match error.code {
+            ErrorCode::NodeNotFound => OnyxError::NodeNotFound(message),
+            ErrorCode::EdgeNotFound => OnyxError::EdgeNotFound(message),
+            ErrorCode::VersionNotFound => OnyxError::VersionNotFound(message),
+            ErrorCode::BranchNotFound => OnyxError::BranchNotFound(message),
+            ErrorCode::NotFound => OnyxError::NotFound(message),
+            ErrorCode::Unauthorized => OnyxError::Unauthorized(message),
+            ErrorCode::BranchAlreadyExists
+            | ErrorCode::DuplicateNode
+            | ErrorCode::DuplicateEdge
+            | ErrorCode::MergeConflict => OnyxError::Conflict(message),
+            ErrorCode::BadRequest
+            | ErrorCode::DimensionMismatch
+            | ErrorCode::InvalidEmbedding
+            | ErrorCode::InvalidQuery
+            | ErrorCode::InvalidEdgeType
+            | ErrorCode::InvalidNode
+            | ErrorCode::ContentTooLarge => OnyxError::InvalidArgument(message),
+            _ => OnyxError::ApiError { status, message },
+        }
+    }
+}
+
+impl From<reqwest::Error> for OnyxError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            OnyxError::Timeout(err)
+        } else {
+            OnyxError::NetworkError(err)
+        }
+    }
+}
+
+/// Machine-readable error codes sent by the server, e.g. `NODE_NOT_FOUND`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    NodeNotFound,
+    EdgeNotFound,
+    VersionNotFound,
+    BranchNotFound,
+    NotFound,
+    BranchAlreadyExists,
+    DuplicateNode,
+    DuplicateEdge,
+    DimensionMismatch,
+    InvalidEmbedding,
+    InvalidQuery,
+    InvalidEdgeType,
+    InvalidNode,
+    MergeConflict,
+    ContentTooLarge,
+    TransactionFailed,
+    IngestionError,
+    SerializationError,
+    ConfigError,
+    IoError,
+    Internal,
+    CorruptRecord,
+    StorageError,
+    /// A code this SDK version doesn't know.
+    #[serde(other)]
+    Unknown,
+}
+
+/// The server's error body: `{ "error": { "code", "message", "request_id" } }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    pub error: ErrorBody,
+}
+
+/// One error reported by the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Correlation id, also sent as the `X-Request-Id` response header.
+    pub request_id: Option<String>,
+    /// Position of the rejected item in a batch request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Entities both branches changed, for a refused merge.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Uuid>,
+}
+
 /// Convenience type alias for SDK results.
 pub type OnyxResult<T> = Result<T, OnyxError>;
@@ -7,7 +7,7 @@
 //! ## Quick Start
 //!
 //! ```rust,no_run
-//! use onyx_sdk::OnyxClient;
+//! use onyx_sdk::{CodeEntityKind, CreateNodeRequest, NodeType, OnyxClient, SearchRequest};
 //!
 //! #[tokio::main]
 //! async fn main() -> Result<(), onyx_sdk::OnyxError> {
@@ -52,6 +52,7 @@
 //! |------------|--------|---------|
 //! | [`NodesClient`] | `client.nodes()` | Node CRUD operations |
 //! | [`EdgesClient`] | `client.edges()` | Edge CRUD operations |
+//! | [`GraphClient`] | `client.graph()` | Traversal & impact analysis |
 //! | [`SearchClient`] | `client.search()` | Vector similarity search |
 //! | [`HistoryClient`] | `client.history()` | Version history & branching |
 //! | [`IngestClient`] | `client.ingest()` | Code ingestion pipeline |
@@ -61,6 +62,8 @@ pub mod client;
 pub mod error;
 pub mod models;
 
-pub use client::{OnyxClient, OnyxClientBuilder};
-pub use error::OnyxError;
+pub use client::{
+    OnyxClient, OnyxClientBuilder, RetryPolicy, DEFAULT_CONNECT_TIMEOUT, DEFAULT_TIMEOUT,
+};
+pub use error::{ErrorCode, OnyxError};
 pub use models::*;
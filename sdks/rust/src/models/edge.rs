@@ -3,8 +3,12 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::error::OnyxError;
+
 /// A directed edge connecting two nodes in the knowledge graph.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Edge {
@@ -14,6 +18,9 @@ pub struct Edge {
     pub target_id: Uuid,
     pub confidence: f64,
     pub metadata: HashMap<String, String>,
+    /// How the server detected the edge; `None` for edges created by hand.
+    #[serde(default)]
+    pub provenance: Option<EdgeProvenance>,
     pub temporal: TemporalContext,
 }
 
@@ -30,6 +37,114 @@ pub enum EdgeType {
     Implements,
     DependsOn,
     Configures,
+    SimilarTo,
+}
+
+impl EdgeType {
+    /// Every edge type, in declaration order.
+    pub const ALL: [EdgeType; 11] = [
+        EdgeType::Defines,
+        EdgeType::Calls,
+        EdgeType::Imports,
+        EdgeType::Documents,
+        EdgeType::TestsOf,
+        EdgeType::VersionedBy,
+        EdgeType::Contains,
+        EdgeType::Implements,
+        EdgeType::DependsOn,
+        EdgeType::Configures,
+        EdgeType::SimilarTo,
+    ];
+
+    /// Canonical snake_case name, matching the server's `Display` output.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeType::Defines => "defines",
+            EdgeType::Calls => "calls",
+            EdgeType::Imports => "imports",
+            EdgeType::Documents => "documents",
+            EdgeType::TestsOf => "tests_of",
+            EdgeType::VersionedBy => "versioned_by",
+            EdgeType::Contains => "contains",
+            EdgeType::Implements => "implements",
+            EdgeType::DependsOn => "depends_on",
+            EdgeType::Configures => "configures",
+            EdgeType::SimilarTo => "similar_to",
+        }
+    }
+
+    /// Whether this edge comes from embedding similarity rather than from
+    /// the structure of the code.
+    pub fn is_semantic(&self) -> bool {
+        matches!(self, EdgeType::SimilarTo)
+    }
+
+    /// Every structural edge type, for traversals that skip semantic edges.
+    pub fn structural() -> Vec<EdgeType> {
+        Self::ALL.into_iter().filter(|t| !t.is_semantic()).collect()
+    }
+}
+
+impl fmt::Display for EdgeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EdgeType {
+    type Err = OnyxError;
+
+    /// Parse a canonical name or alias, accepting the same spellings as the
+    /// server. Case, `_` and `-` are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect();
+        match normalized.as_str() {
+            "defines" | "define" => Ok(EdgeType::Defines),
+            "calls" | "call" => Ok(EdgeType::Calls),
+            "imports" | "import" => Ok(EdgeType::Imports),
+            "documents" | "document" | "docs" | "doc" => Ok(EdgeType::Documents),
+            "testsof" | "tests" | "test" => Ok(EdgeType::TestsOf),
+            "versionedby" | "versioned" | "version" => Ok(EdgeType::VersionedBy),
+            "contains" | "contain" => Ok(EdgeType::Contains),
+            "implements" | "implement" | "impl" => Ok(EdgeType::Implements),
+            "dependson" | "depends" | "dependency" => Ok(EdgeType::DependsOn),
+            "configures" | "configure" | "config" => Ok(EdgeType::Configures),
+            "similarto" | "similar" | "semantic" => Ok(EdgeType::SimilarTo),
+            _ => Err(OnyxError::InvalidEdgeType(s.trim().to_string())),
+        }
+    }
+}
+
+/// Which detector produced an edge, and when.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeProvenance {
+    pub detector: DetectorKind,
+    pub detected_at: DateTime<Utc>,
+    pub source_location: Option<String>,
+    /// Other detectors that later found the same relationship.
+    #[serde(default)]
+    pub corroborated_by: Vec<DetectorKind>,
+}
+
+/// Edge detectors, matching the server's.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DetectorKind {
+    ContentScan,
+    ModuleHierarchy,
+    Ast,
+    TestTarget,
+    ConfigKey,
+    Embedding,
+    TraitImpl,
+    Manifest,
+    /// A detector this SDK version doesn't know.
+    #[serde(other)]
+    Unknown,
 }
 
 /// Temporal metadata tracking when a relationship was valid.
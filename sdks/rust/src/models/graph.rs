@@ -0,0 +1,89 @@
+//! Graph models — multi-hop traversal and impact analysis.
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::edge::EdgeType;
+
+/// Request to traverse the graph from a node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraverseRequest {
+    pub node_id: Uuid,
+    /// Edge types to follow; all types if `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge_types: Option<Vec<EdgeType>>,
+    pub depth: usize,
+}
+
+impl TraverseRequest {
+    /// Traverse up to `depth` hops from `node_id` along any edge type.
+    pub fn new(node_id: Uuid, depth: usize) -> Self {
+        Self {
+            node_id,
+            edge_types: None,
+            depth,
+        }
+    }
+
+    pub fn edge_types(mut self, types: Vec<EdgeType>) -> Self {
+        self.edge_types = Some(types);
+        self
+    }
+}
+
+/// A node reached by a traversal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraversedNode {
+    pub node_id: Uuid,
+    pub name: String,
+    pub depth: usize,
+    /// Edges from the start node to this one; empty if the server doesn't
+    /// track paths.
+    #[serde(default)]
+    pub edge_path: Vec<Uuid>,
+}
+
+/// Response of a traversal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraverseResponse {
+    pub nodes: Vec<TraversedNode>,
+    pub edges: Vec<Uuid>,
+    pub total_visited: usize,
+}
+
+/// Which way impact analysis walks the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactDirection {
+    /// Nodes that depend on the changed node.
+    #[default]
+    Dependents,
+    /// Nodes the changed node depends on.
+    Dependencies,
+}
+
+impl ImpactDirection {
+    /// Value used in the `direction` query parameter.
+    pub fn as_query_param(&self) -> &'static str {
+        match self {
+            ImpactDirection::Dependents => "dependents",
+            ImpactDirection::Dependencies => "dependencies",
+        }
+    }
+}
+
+/// A node affected by a change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactedNode {
+    pub node_id: Uuid,
+    pub name: String,
+    pub depth: usize,
+}
+
+/// Response of an impact analysis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpactResponse {
+    pub node_id: Uuid,
+    pub direction: ImpactDirection,
+    pub affected: Vec<ImpactedNode>,
+}
@@ -0,0 +1,22 @@
+//! Health models — server readiness reporting.
+
+use serde::{Deserialize, Serialize};
+
+/// Response from `GET /health`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResponse {
+    /// `"ok"` when the server and its database are usable.
+    pub status: String,
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub version_count: usize,
+    /// Whether the server could reach its database.
+    pub db_reachable: bool,
+}
+
+impl HealthResponse {
+    /// Whether the server reports itself healthy.
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+}
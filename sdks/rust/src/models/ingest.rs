@@ -101,4 +101,38 @@ pub struct IngestResult {
 pub struct IngestCodebaseResponse {
     pub results: Vec<IngestResult>,
     pub total_edges: usize,
+    /// Units the server could not ingest; the rest were still written.
+    #[serde(default)]
+    pub failures: Vec<IngestFailure>,
+}
+
+/// A unit that failed to ingest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestFailure {
+    /// Position of the unit in the submitted batch.
+    pub index: usize,
+    pub name: String,
+    pub error: String,
+}
+
+/// A progress event from a streaming ingest (`POST /api/ingest/stream`).
+///
+/// Sent as server-sent events whose `data` is this type as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IngestEvent {
+    /// Another unit was written.
+    Progress {
+        completed: usize,
+        total: usize,
+        edges_detected: usize,
+    },
+    /// A unit failed; ingestion continues with the rest.
+    Failed(IngestFailure),
+    /// Ingestion finished. Always the last event.
+    Complete {
+        ingested: usize,
+        failed: usize,
+        total_edges: usize,
+    },
 }
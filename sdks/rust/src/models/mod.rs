@@ -6,14 +6,20 @@
 
 pub mod billing;
 pub mod edge;
+pub mod graph;
+pub mod health;
 pub mod ingest;
 pub mod node;
+pub mod page;
 pub mod search;
 pub mod version;
 
 pub use billing::*;
 pub use edge::*;
+pub use graph::*;
+pub use health::*;
 pub use ingest::*;
 pub use node::*;
+pub use page::*;
 pub use search::*;
 pub use version::*;
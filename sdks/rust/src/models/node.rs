@@ -57,6 +57,26 @@ impl NodeType {
     pub fn code_entity(kind: CodeEntityKind) -> Self {
         NodeType::CodeEntity(kind)
     }
+
+    /// The value used for the `type` filter on list endpoints.
+    pub fn as_query_param(&self) -> &'static str {
+        match self {
+            NodeType::CodeEntity(kind) => match kind {
+                CodeEntityKind::Function => "function",
+                CodeEntityKind::Struct => "struct",
+                CodeEntityKind::Enum => "enum",
+                CodeEntityKind::Trait => "trait",
+                CodeEntityKind::Impl => "impl",
+                CodeEntityKind::Module => "module",
+                CodeEntityKind::Constant => "constant",
+                CodeEntityKind::TypeAlias => "type_alias",
+                CodeEntityKind::Macro => "macro",
+            },
+            NodeType::Doc => "doc",
+            NodeType::Test => "test",
+            NodeType::Config => "config",
+        }
+    }
 }
 
 /// The kind of code entity.
@@ -174,6 +194,7 @@ pub enum ConfigType {
     Docker,
     Env,
     Build,
+    Dependency,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -229,6 +250,9 @@ impl Provenance {
 /// Request body for creating a node.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateNodeRequest {
+    /// Explicit node ID; the server generates one when absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
     pub name: String,
     pub content: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -245,6 +269,7 @@ impl CreateNodeRequest {
     /// Create a new request with the required fields.
     pub fn new(name: impl Into<String>, content: impl Into<String>) -> Self {
         Self {
+            id: None,
             name: name.into(),
             content: content.into(),
             node_type: None,
@@ -254,6 +279,12 @@ impl CreateNodeRequest {
         }
     }
 
+    /// Use an explicit node ID instead of a server-generated one.
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
     /// Set the node type.
     pub fn node_type(mut self, nt: NodeType) -> Self {
         self.node_type = Some(nt);
@@ -280,7 +311,7 @@ impl CreateNodeRequest {
 }
 
 /// Request body for updating a node.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UpdateNodeRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -296,24 +327,53 @@ pub struct UpdateNodeRequest {
     pub embedding: Option<Vec<f32>>,
 }
 
-impl Default for UpdateNodeRequest {
+/// Query for listing nodes one page at a time.
+#[derive(Debug, Clone)]
+pub struct ListNodesRequest {
+    /// 1-based page number (default: 1).
+    pub page: usize,
+    /// Items per page (default: 50).
+    pub page_size: usize,
+    /// Only return nodes of this type.
+    pub node_type: Option<NodeType>,
+}
+
+impl ListNodesRequest {
+    /// Set the page number.
+    pub fn page(mut self, page: usize) -> Self {
+        self.page = page;
+        self
+    }
+
+    /// Set the page size.
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Filter by node type.
+    pub fn node_type(mut self, node_type: NodeType) -> Self {
+        self.node_type = Some(node_type);
+        self
+    }
+
+    /// Render as a URL query string (without the leading `?`).
+    pub(crate) fn to_query(&self) -> String {
+        let mut query = format!("page={}&page_size={}", self.page, self.page_size);
+        if let Some(node_type) = &self.node_type {
+            query.push_str("&type=");
+            query.push_str(node_type.as_query_param());
+        }
+        query
+    }
+}
+
+impl Default for ListNodesRequest {
     fn default() -> Self {
         Self {
-            name: None,
-            content: None,
+            page: 1,
+            page_size: 50,
             node_type: None,
-            metadata: None,
-            provenance: None,
-            embedding: None,
         }
     }
 }
-
-/// Paginated list response.
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ListNodesResponse {
-    pub nodes: Vec<Node>,
-    pub total: usize,
-    pub page: usize,
-    pub per_page: usize,
-}
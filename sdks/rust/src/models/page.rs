@@ -0,0 +1,14 @@
+//! Pagination models shared by list endpoints.
+
+use serde::{Deserialize, Serialize};
+
+/// One page of results from a paginated list endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// Items on this page.
+    pub items: Vec<T>,
+    /// The next page number, or `None` if this is the last page.
+    pub next_page: Option<usize>,
+    /// Total number of items across all pages.
+    pub total: usize,
+}
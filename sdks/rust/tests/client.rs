@@ -0,0 +1,585 @@
+//! Client behaviour tests against a mock Onyx server.
+
+use std::time::Duration;
+
+use futures_util::TryStreamExt;
+use onyx_sdk::{
+    CreateEdgeRequest, CreateNodeRequest, DetectorKind, EdgeType, ImpactDirection, IngestEvent,
+    IngestFailure, ListNodesRequest, MergeBranchRequest, Node, OnyxClient, OnyxError,
+    TraverseRequest,
+};
+use wiremock::matchers::{body_json, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client_for(server: &MockServer) -> OnyxClient {
+    OnyxClient::builder(&server.uri())
+        .retry(3, Duration::from_millis(1))
+        .build()
+        .unwrap()
+}
+
+fn node_json(name: &str) -> serde_json::Value {
+    serde_json::json!({
+        "id": uuid::Uuid::new_v4(),
+        "node_type": { "type": "CodeEntity", "kind": "Function" },
+        "name": name,
+        "content": format!("fn {name}() {{}}"),
+        "content_hash": "00",
+        "metadata": {},
+        "provenance": {
+            "file_path": null,
+            "line_range": null,
+            "commit_id": null,
+            "repo_url": null,
+            "branch": null
+        },
+        "embedding": null,
+        "current_version": null,
+        "created_at": "2024-01-01T00:00:00Z",
+        "updated_at": "2024-01-01T00:00:00Z",
+        "extension": null
+    })
+}
+
+fn edge_list_body() -> serde_json::Value {
+    serde_json::json!({ "edges": [], "total": 0 })
+}
+
+#[tokio::test]
+async fn test_get_retries_transient_failures() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/edges"))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/edges"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(edge_list_body()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let edges = client_for(&server).edges().list().await.unwrap();
+    assert_eq!(edges.total, 0);
+}
+
+#[tokio::test]
+async fn test_get_surfaces_error_after_exhausting_attempts() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/edges"))
+        .respond_with(ResponseTemplate::new(502))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let err = client_for(&server).edges().list().await.unwrap_err();
+    assert!(matches!(err, OnyxError::ApiError { status: 502, .. }));
+}
+
+#[tokio::test]
+async fn test_post_is_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/search"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let result = client_for(&server)
+        .search()
+        .query(onyx_sdk::SearchRequest::new(vec![0.1, 0.2]))
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_slow_response_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/edges"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(edge_list_body())
+                .set_delay(Duration::from_millis(500)),
+        )
+        .mount(&server)
+        .await;
+
+    let client = OnyxClient::builder(&server.uri())
+        .timeout(Duration::from_millis(50))
+        .connect_timeout(Duration::from_millis(50))
+        .pool_max_idle_per_host(1)
+        .retry(1, Duration::from_millis(1))
+        .build()
+        .unwrap();
+
+    let err = client.edges().list().await.unwrap_err();
+    assert!(matches!(err, OnyxError::Timeout(_)));
+}
+
+async fn mount_two_node_pages(server: &MockServer) {
+    Mock::given(method("GET"))
+        .and(path("/api/nodes"))
+        .and(query_param("page", "1"))
+        .and(query_param("page_size", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "items": [node_json("a"), node_json("b")],
+            "next_page": 2,
+            "total": 3
+        })))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/api/nodes"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "items": [node_json("c")],
+            "next_page": null,
+            "total": 3
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn test_list_nodes_single_page() {
+    let server = MockServer::start().await;
+    mount_two_node_pages(&server).await;
+
+    let page = client_for(&server)
+        .nodes()
+        .list(ListNodesRequest::default().page_size(2))
+        .await
+        .unwrap();
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.next_page, Some(2));
+    assert_eq!(page.total, 3);
+}
+
+#[tokio::test]
+async fn test_list_all_follows_pages() {
+    let server = MockServer::start().await;
+    mount_two_node_pages(&server).await;
+
+    let nodes: Vec<Node> = client_for(&server)
+        .nodes()
+        .list_all(ListNodesRequest::default().page_size(2))
+        .try_collect()
+        .await
+        .unwrap();
+    let names: Vec<&str> = nodes.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(names, vec!["a", "b", "c"]);
+}
+
+#[tokio::test]
+async fn test_create_many_posts_batch() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/nodes/batch"))
+        .and(body_json(serde_json::json!([
+            { "name": "a", "content": "fn a() {}" },
+            { "name": "b", "content": "fn b() {}" }
+        ])))
+        .respond_with(
+            ResponseTemplate::new(201)
+                .set_body_json(serde_json::json!([node_json("a"), node_json("b")])),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let nodes = client_for(&server)
+        .nodes()
+        .create_many(vec![
+            CreateNodeRequest::new("a", "fn a() {}"),
+            CreateNodeRequest::new("b", "fn b() {}"),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(nodes.len(), 2);
+}
+
+#[tokio::test]
+async fn test_create_many_rejected_batch() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/nodes/batch"))
+        .respond_with(ResponseTemplate::new(422).set_body_json(serde_json::json!({
+            "error": {
+                "code": "INVALID_NODE",
+                "message": "duplicate node id",
+                "request_id": "req-1",
+                "index": 1
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let id = uuid::Uuid::new_v4();
+    let err = client_for(&server)
+        .nodes()
+        .create_many(vec![
+            CreateNodeRequest::new("a", "fn a() {}").id(id),
+            CreateNodeRequest::new("b", "fn b() {}").id(id),
+        ])
+        .await
+        .unwrap_err();
+    assert!(matches!(err, OnyxError::InvalidArgument(message) if message == "duplicate node id"));
+}
+
+#[tokio::test]
+async fn test_error_envelope_maps_to_typed_error() {
+    let server = MockServer::start().await;
+    let id = uuid::Uuid::new_v4();
+    Mock::given(method("GET"))
+        .and(path(format!("/api/nodes/{id}")))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({
+            "error": {
+                "code": "NODE_NOT_FOUND",
+                "message": format!("Node not found: {id}"),
+                "request_id": "req-1"
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path(format!("/api/edges/{id}")))
+        .respond_with(ResponseTemplate::new(418).set_body_json(serde_json::json!({
+            "error": { "code": "SOMETHING_NEW", "message": "teapot", "request_id": null }
+        })))
+        .mount(&server)
+        .await;
+
+    let client = client_for(&server);
+    let err = client.nodes().get(id).await.unwrap_err();
+    assert!(matches!(err, OnyxError::NodeNotFound(message) if message.contains(&id.to_string())));
+
+    let err = client.edges().delete(id).await.unwrap_err();
+    assert!(matches!(err, OnyxError::ApiError { status: 418, message } if message == "teapot"));
+}
+
+#[tokio::test]
+async fn test_merge_conflict_maps_to_conflict() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/branches/merge"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+            "error": {
+                "code": "MERGE_CONFLICT",
+                "message": "'feature' and 'main' both changed 1 entities",
+                "request_id": "req-1",
+                "conflicts": [uuid::Uuid::new_v4()]
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let err = client_for(&server)
+        .history()
+        .merge_branch(MergeBranchRequest {
+            source: "feature".to_string(),
+            target: "main".to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, OnyxError::Conflict(message) if message.contains("both changed")));
+}
+
+#[test]
+fn test_unknown_detector_kind_deserializes() {
+    let known: DetectorKind = serde_json::from_str("\"Manifest\"").unwrap();
+    assert_eq!(known, DetectorKind::Manifest);
+    let unknown: DetectorKind = serde_json::from_str("\"CallGraph\"").unwrap();
+    assert_eq!(unknown, DetectorKind::Unknown);
+}
+
+fn version_json(
+    entity_id: uuid::Uuid,
+    version_id: &str,
+    parent: Option<&str>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "version_id": version_id,
+        "entity_id": entity_id,
+        "parent_version": parent,
+        "branch": "main",
+        "diff": { "type": "ContentChanged", "patch": "-a\n+b", "additions": 1, "deletions": 1 },
+        "commit_id": null,
+        "author": "dev",
+        "message": null,
+        "timestamp": "2024-01-01T00:00:00Z"
+    })
+}
+
+#[tokio::test]
+async fn test_history_lists_versions_and_content() {
+    let server = MockServer::start().await;
+    let entity_id = uuid::Uuid::new_v4();
+    Mock::given(method("GET"))
+        .and(path(format!("/api/entities/{entity_id}/versions")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "versions": [
+                version_json(entity_id, "v-1", None),
+                version_json(entity_id, "v-2", Some("v-1"))
+            ],
+            "total": 2
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/api/entities/{entity_id}/versions/v-1/content"
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json("fn a() {}"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/api/entities/{entity_id}/diff")))
+        .and(query_param("from", "v-1"))
+        .and(query_param("to", "v-2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "type": "ContentChanged", "patch": "-a\n+b", "additions": 1, "deletions": 1
+        })))
+        .mount(&server)
+        .await;
+
+    let history = client_for(&server).history();
+    let versions = history.list_versions(entity_id).await.unwrap();
+    assert_eq!(versions.total, 2);
+    assert_eq!(versions.versions[1].parent_version.as_deref(), Some("v-1"));
+
+    let content = history
+        .get_content_at_version(entity_id, "v-1")
+        .await
+        .unwrap();
+    assert_eq!(content, "fn a() {}");
+
+    let diff = history.diff(entity_id, "v-1", "v-2").await.unwrap();
+    assert!(matches!(
+        diff,
+        onyx_sdk::Diff::ContentChanged { additions: 1, .. }
+    ));
+}
+
+fn unit(name: &str) -> onyx_sdk::IngestCodeUnitRequest {
+    onyx_sdk::IngestCodeUnitRequest::new(
+        name,
+        format!("fn {name}() {{}}"),
+        onyx_sdk::CodeEntityKind::Function,
+        onyx_sdk::Language::Rust,
+        "src/lib.rs",
+    )
+}
+
+#[tokio::test]
+async fn test_ingest_stream_yields_events_in_order() {
+    let server = MockServer::start().await;
+    let body = concat!(
+        ": keep-alive\n\n",
+        "event: progress\n",
+        "data: {\"type\":\"progress\",\"completed\":1,\"total\":3,\"edges_detected\":0}\n\n",
+        "data: {\"type\":\"failed\",\"index\":1,\"name\":\"b\",\"error\":\"too large\"}\r\n\r\n",
+        "data: {\"type\":\"progress\",\"completed\":2,\"total\":3,\"edges_detected\":1}\n\n",
+        "data: {\"type\":\"complete\",\"ingested\":2,\"failed\":1,\"total_edges\":1}\n\n",
+    );
+    Mock::given(method("POST"))
+        .and(path("/api/ingest/stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+        .mount(&server)
+        .await;
+
+    let events: Vec<IngestEvent> = client_for(&server)
+        .ingest()
+        .ingest_stream(vec![unit("a"), unit("b"), unit("c")])
+        .try_collect()
+        .await
+        .unwrap();
+
+    assert_eq!(
+        events,
+        vec![
+            IngestEvent::Progress {
+                completed: 1,
+                total: 3,
+                edges_detected: 0
+            },
+            IngestEvent::Failed(IngestFailure {
+                index: 1,
+                name: "b".to_string(),
+                error: "too large".to_string()
+            }),
+            IngestEvent::Progress {
+                completed: 2,
+                total: 3,
+                edges_detected: 1
+            },
+            IngestEvent::Complete {
+                ingested: 2,
+                failed: 1,
+                total_edges: 1
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_ingest_reports_partial_failure() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/ingest/codebase"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "results": [
+                { "node_id": uuid::Uuid::new_v4(), "version_id": "v-1", "edges_created": 0 }
+            ],
+            "total_edges": 0,
+            "failures": [ { "index": 1, "name": "b", "error": "too large" } ]
+        })))
+        .mount(&server)
+        .await;
+
+    let err = client_for(&server)
+        .ingest()
+        .ingest(vec![unit("a"), unit("b")])
+        .await
+        .unwrap_err();
+    match err {
+        OnyxError::PartialIngest { results, failures } => {
+            assert_eq!(results.len(), 1);
+            assert_eq!(failures[0].index, 1);
+        }
+        other => panic!("unexpected error: {other}"),
+    }
+}
+
+fn edge_json(edge_type: &str, source: uuid::Uuid, target: uuid::Uuid) -> serde_json::Value {
+    serde_json::json!({
+        "id": uuid::Uuid::new_v4(),
+        "edge_type": edge_type,
+        "source_id": source,
+        "target_id": target,
+        "confidence": 1.0,
+        "metadata": {},
+        "temporal": {
+            "since": null,
+            "until": null,
+            "via_commit": null,
+            "since_timestamp": "2024-01-01T00:00:00Z",
+            "until_timestamp": null
+        }
+    })
+}
+
+#[tokio::test]
+async fn test_edges_create_and_filtered_neighbors() {
+    let server = MockServer::start().await;
+    let (a, b) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+    Mock::given(method("POST"))
+        .and(path("/api/edges"))
+        .and(body_json(serde_json::json!({
+            "edge_type": "Calls",
+            "source_id": a,
+            "target_id": b
+        })))
+        .respond_with(ResponseTemplate::new(201).set_body_json(edge_json("Calls", a, b)))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/api/nodes/{a}/neighbors")))
+        .and(query_param("edge_types", "calls"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+            { "edge": edge_json("Calls", a, b), "node": node_json("callee") }
+        ])))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let edges = client_for(&server).edges();
+    let edge = edges
+        .create(CreateEdgeRequest::new(EdgeType::Calls, a, b))
+        .await
+        .unwrap();
+    assert_eq!(edge.target_id, b);
+
+    let neighbors = edges.neighbors(a, &[EdgeType::Calls]).await.unwrap();
+    assert_eq!(neighbors.len(), 1);
+    assert_eq!(neighbors[0].node.name, "callee");
+    assert_eq!(neighbors[0].edge.edge_type, EdgeType::Calls);
+}
+
+#[tokio::test]
+async fn test_graph_traverses_demo_chain() {
+    let server = MockServer::start().await;
+    // The demo's call chain: main -> process_data -> validate
+    let (main, process, validate) = (
+        uuid::Uuid::new_v4(),
+        uuid::Uuid::new_v4(),
+        uuid::Uuid::new_v4(),
+    );
+    let (e1, e2) = (uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+    Mock::given(method("POST"))
+        .and(path("/api/graph/traverse"))
+        .and(body_json(serde_json::json!({
+            "node_id": main,
+            "edge_types": ["Calls"],
+            "depth": 2
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "nodes": [
+                { "node_id": main, "name": "main", "depth": 0, "edge_path": [] },
+                { "node_id": process, "name": "process_data", "depth": 1, "edge_path": [e1] },
+                { "node_id": validate, "name": "validate", "depth": 2, "edge_path": [e1, e2] }
+            ],
+            "edges": [e1, e2],
+            "total_visited": 3
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/api/nodes/{validate}/impact")))
+        .and(query_param("depth", "2"))
+        .and(query_param("direction", "dependents"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "node_id": validate,
+            "direction": "dependents",
+            "affected": [
+                { "node_id": process, "name": "process_data", "depth": 1 },
+                { "node_id": main, "name": "main", "depth": 2 }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let graph = client_for(&server).graph();
+    let traversal = graph
+        .traverse(TraverseRequest::new(main, 2).edge_types(vec![EdgeType::Calls]))
+        .await
+        .unwrap();
+    assert_eq!(traversal.nodes.len(), 3);
+    assert_eq!(traversal.nodes[2].name, "validate");
+    assert_eq!(traversal.nodes[2].edge_path, vec![e1, e2]);
+
+    let impact = graph
+        .impact(validate, 2, ImpactDirection::Dependents)
+        .await
+        .unwrap();
+    let names: Vec<&str> = impact.affected.iter().map(|n| n.name.as_str()).collect();
+    assert_eq!(names, ["process_data", "main"]);
+}
+
+#[test]
+fn test_edge_type_round_trip() {
+    for edge_type in onyx_sdk::EdgeType::ALL {
+        let parsed: onyx_sdk::EdgeType = edge_type.to_string().parse().unwrap();
+        assert_eq!(parsed, edge_type);
+    }
+    assert!(matches!(
+        "nope".parse::<onyx_sdk::EdgeType>(),
+        Err(OnyxError::InvalidEdgeType(_))
+    ));
+}
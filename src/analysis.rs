@@ -0,0 +1,296 @@
+//! Whole-graph analysis: structure that only shows up across many nodes,
+//! such as cohesive subsystems and central entities.
+
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use crate::model::edge::EdgeType;
+use crate::query::QueryStores;
+use crate::store::graph::GraphStore;
+use crate::store::transaction::TransactionManager;
+
+// ---------------------------------------------------------------------------
+// Community detection
+// ---------------------------------------------------------------------------
+
+/// Label propagation normally settles in a handful of rounds; this bounds
+/// the rare graphs where labels keep flipping.
+const MAX_PROPAGATION_ROUNDS: usize = 100;
+
+/// A group of nodes more densely linked to each other than to the rest of
+/// the graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Community {
+    /// Member node IDs, sorted.
+    pub node_ids: Vec<Uuid>,
+}
+
+impl Community {
+    pub fn len(&self) -> usize {
+        self.node_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_ids.is_empty()
+    }
+}
+
+/// Group the live nodes behind `stores` into communities linked by
+/// `edge_types`; see [`communities_in`].
+pub async fn detect_communities(
+    stores: &TransactionManager,
+    edge_types: &[EdgeType],
+) -> Vec<Community> {
+    communities_in(QueryStores::from(stores).graph, edge_types).await
+}
+
+/// Group the live nodes of `graph` into communities by label propagation
+/// over active edges of `edge_types`, ignoring their direction.
+///
+/// Every node starts in its own community and repeatedly joins the one its
+/// neighbors carry the most edge confidence into, until no node moves.
+/// Nodes are visited in ID order and ties go to the smallest label, so the
+/// result is deterministic. Nodes with no such edges form communities of
+/// one. Communities are returned largest first.
+pub async fn communities_in(graph: &dyn GraphStore, edge_types: &[EdgeType]) -> Vec<Community> {
+    let mut ids: Vec<Uuid> = graph.all_nodes().await.iter().map(|n| n.id).collect();
+    ids.sort();
+    let index: HashMap<Uuid, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); ids.len()];
+    for edge_type in edge_types {
+        for edge in graph.edges_by_type(edge_type).await {
+            if !edge.is_active() {
+                continue;
+            }
+            // Edges to soft-deleted or missing nodes are skipped.
+            if let (Some(&source), Some(&target)) =
+                (index.get(&edge.source_id), index.get(&edge.target_id))
+            {
+                if source != target {
+                    neighbors[source].push((target, edge.confidence));
+                    neighbors[target].push((source, edge.confidence));
+                }
+            }
+        }
+    }
+
+    let mut labels: Vec<usize> = (0..ids.len()).collect();
+    for _ in 0..MAX_PROPAGATION_ROUNDS {
+        let mut moved = false;
+        for node in 0..ids.len() {
+            let mut weights: BTreeMap<usize, f64> = BTreeMap::new();
+            for &(neighbor, confidence) in &neighbors[node] {
+                *weights.entry(labels[neighbor]).or_default() += confidence;
+            }
+            let Some(best) = weights.values().copied().reduce(f64::max) else {
+                continue;
+            };
+            // Staying put on a tie keeps labels from oscillating.
+            if weights.get(&labels[node]) == Some(&best) {
+                continue;
+            }
+            // BTreeMap iterates in label order, so the smallest label wins.
+            if let Some((&label, _)) = weights.iter().find(|(_, w)| **w == best) {
+                labels[node] = label;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+
+    let mut members: BTreeMap<usize, Vec<Uuid>> = BTreeMap::new();
+    for (node, label) in labels.into_iter().enumerate() {
+        members.entry(label).or_default().push(ids[node]);
+    }
+    let mut communities: Vec<Community> = members
+        .into_values()
+        .map(|node_ids| Community { node_ids })
+        .collect();
+    communities.sort_by(|a, b| {
+        b.len()
+            .cmp(&a.len())
+            .then_with(|| a.node_ids.cmp(&b.node_ids))
+    });
+    communities
+}
+
+// ---------------------------------------------------------------------------
+// Node importance
+// ---------------------------------------------------------------------------
+
+/// Damping factor from the original PageRank paper.
+pub const DEFAULT_DAMPING: f64 = 0.85;
+
+/// Iterations after which PageRank scores on code graphs stop moving much.
+pub const DEFAULT_PAGERANK_ITERATIONS: usize = 20;
+
+/// Edges along which a node lends importance to what it relies on.
+pub const IMPORTANCE_EDGE_TYPES: &[EdgeType] =
+    &[EdgeType::Calls, EdgeType::Imports, EdgeType::DependsOn];
+
+/// PageRank of the live nodes behind `stores`; see [`pagerank_in`].
+pub async fn pagerank(
+    stores: &TransactionManager,
+    edge_types: &[EdgeType],
+    damping: f64,
+    iterations: usize,
+) -> HashMap<Uuid, f64> {
+    pagerank_in(
+        QueryStores::from(stores).graph,
+        edge_types,
+        damping,
+        iterations,
+    )
+    .await
+}
+
+/// PageRank of every live node of `graph` over active edges of
+/// `edge_types`, so a node called from many important places scores high.
+///
+/// Each node passes `damping` (clamped to `0.0..=1.0`) of its score along
+/// its outbound edges, split by edge confidence; nodes with no such edges
+/// spread theirs over the whole graph. Scores sum to 1.
+pub async fn pagerank_in(
+    graph: &dyn GraphStore,
+    edge_types: &[EdgeType],
+    damping: f64,
+    iterations: usize,
+) -> HashMap<Uuid, f64> {
+    let ids: Vec<Uuid> = graph.all_nodes().await.iter().map(|n| n.id).collect();
+    if ids.is_empty() {
+        return HashMap::new();
+    }
+    let index: HashMap<Uuid, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let mut outbound: Vec<Vec<(usize, f64)>> = vec![Vec::new(); ids.len()];
+    for edge_type in edge_types {
+        for edge in graph.edges_by_type(edge_type).await {
+            if !edge.is_active() {
+                continue;
+            }
+            if let (Some(&source), Some(&target)) =
+                (index.get(&edge.source_id), index.get(&edge.target_id))
+            {
+                outbound[source].push((target, edge.confidence));
+            }
+        }
+    }
+    let out_weight: Vec<f64> = outbound
+        .iter()
+        .map(|edges| edges.iter().map(|(_, w)| w).sum())
+        .collect();
+
+    let damping = damping.clamp(0.0, 1.0);
+    let n = ids.len() as f64;
+    let mut rank = vec![1.0 / n; ids.len()];
+    for _ in 0..iterations {
+        let dangling: f64 = (0..ids.len())
+            .filter(|&i| out_weight[i] <= 0.0)
+            .map(|i| rank[i])
+            .sum();
+        let mut next = vec![(1.0 - damping) / n + damping * dangling / n; ids.len()];
+        for (source, edges) in outbound.iter().enumerate() {
+            if out_weight[source] <= 0.0 {
+                continue;
+            }
+            let share = damping * rank[source] / out_weight[source];
+            for &(target, weight) in edges {
+                next[target] += share * weight;
+            }
+        }
+        rank = next;
+    }
+
+    ids.into_iter().zip(rank).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LimitsConfig;
+    use crate::ingest::demo::build_synthetic_codebase;
+    use crate::ingest::ingest_codebase;
+    use crate::model::edge::Edge;
+    use crate::model::embedding::BagOfWordsEmbedder;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::store::transaction::TransactionOp;
+
+    #[tokio::test]
+    async fn test_two_separated_clusters_are_two_communities() {
+        let mut stores = TransactionManager::new();
+        let mut id = HashMap::new();
+        for name in ["a1", "a2", "a3", "b1", "b2", "b3", "loner"] {
+            let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "");
+            id.insert(name, node.id);
+            stores.execute(TransactionOp::InsertNode(node)).unwrap();
+        }
+
+        // Two triangles joined by one weak call.
+        let calls = [
+            ("a1", "a2", 1.0),
+            ("a2", "a3", 1.0),
+            ("a3", "a1", 1.0),
+            ("b1", "b2", 1.0),
+            ("b2", "b3", 1.0),
+            ("b3", "b1", 1.0),
+            ("a3", "b1", 0.3),
+        ];
+        for (source, target, confidence) in calls {
+            let edge =
+                Edge::new(EdgeType::Calls, id[source], id[target]).with_confidence(confidence);
+            stores.execute(TransactionOp::InsertEdge(edge)).unwrap();
+        }
+        // Not among the chosen edge types, so it must not merge anything.
+        let import = Edge::new(EdgeType::Imports, id["loner"], id["a1"]);
+        stores.execute(TransactionOp::InsertEdge(import)).unwrap();
+
+        let communities = detect_communities(&stores, &[EdgeType::Calls]).await;
+        let group = |names: &[&str]| {
+            let mut ids: Vec<Uuid> = names.iter().map(|n| id[n]).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(communities.len(), 3);
+        let mut clusters: Vec<Vec<Uuid>> = communities[..2]
+            .iter()
+            .map(|c| c.node_ids.clone())
+            .collect();
+        clusters.sort();
+        let mut expected = vec![group(&["a1", "a2", "a3"]), group(&["b1", "b2", "b3"])];
+        expected.sort();
+        assert_eq!(clusters, expected);
+        assert_eq!(communities[2].node_ids, [id["loner"]]);
+    }
+
+    #[tokio::test]
+    async fn test_pagerank_ranks_called_function_above_uncalled() {
+        let units = build_synthetic_codebase();
+        let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+        let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+        let mut stores = TransactionManager::new();
+        ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let ranks = pagerank(
+            &stores,
+            IMPORTANCE_EDGE_TYPES,
+            DEFAULT_DAMPING,
+            DEFAULT_PAGERANK_ITERATIONS,
+        )
+        .await;
+        let mut ids = HashMap::new();
+        for node in stores.graph_store.all_nodes().await {
+            ids.insert(node.name.clone(), node.id);
+        }
+        let rank_of = |name: &str| ranks[&ids[name]];
+        assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        // Nothing calls `process_payment`; it calls `calculate_total`, which
+        // calls `apply_discount`.
+        assert!(rank_of("calculate_total") > rank_of("process_payment"));
+        assert!(rank_of("apply_discount") > rank_of("calculate_total"));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::error::{OnyxError, OnyxResult};
-use config::{Config, Environment, File};
+use config::{Config, ConfigError, Environment, File};
 use serde::Deserialize;
 use std::path::Path;
 
@@ -7,12 +7,34 @@ use std::path::Path;
 pub struct AppConfig {
     pub server: ServerConfig,
     pub payments: PaymentsConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
 }
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Port for the gRPC API (`grpc` feature); defaults to 50051.
+    #[serde(default)]
+    pub grpc_port: Option<u16>,
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CorsConfig {
+    /// Allowed origins; `["*"]` allows any origin.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    /// Allowed methods; empty means GET, POST, PUT, DELETE and OPTIONS.
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    /// Allowed request headers; `["*"]` allows any header.
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -26,18 +48,71 @@ pub struct PaymentsConfig {
     pub portal_return_url: String,
 }
 
-pub fn load_config(path: Option<&Path>) -> OnyxResult<AppConfig> {
-    let mut builder = Config::builder()
-        .add_source(File::with_name("config").required(false))
-        .add_source(Environment::with_prefix("ONYX").separator("__"));
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub api_keys: Vec<String>,
+}
 
-    if let Some(path) = path {
-        builder = builder.add_source(File::from(path).required(false));
+/// Default cap on `Node.content`: 1 MiB.
+pub const DEFAULT_MAX_NODE_CONTENT_BYTES: usize = 1024 * 1024;
+
+/// Metadata key set on nodes whose content was truncated, holding the
+/// original size in bytes.
+pub const CONTENT_TRUNCATED_METADATA: &str = "content_truncated_from";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LimitsConfig {
+    /// Largest node content accepted, in bytes.
+    #[serde(default = "default_max_node_content_bytes")]
+    pub max_node_content_bytes: usize,
+    /// Truncate oversized content instead of rejecting it.
+    #[serde(default)]
+    pub truncate_oversized_content: bool,
+}
+
+fn default_max_node_content_bytes() -> usize {
+    DEFAULT_MAX_NODE_CONTENT_BYTES
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_node_content_bytes: DEFAULT_MAX_NODE_CONTENT_BYTES,
+            truncate_oversized_content: false,
+        }
     }
+}
 
-    let config = builder
-        .build()
-        .map_err(|err| OnyxError::ConfigError(err.to_string()))?;
+impl LimitsConfig {
+    /// Check `content` against the size limit.
+    ///
+    /// Returns the content to store: all of it when within the limit, or its
+    /// longest prefix that fits on a char boundary when truncation is enabled.
+    /// Otherwise oversized content fails with [`OnyxError::ContentTooLarge`].
+    pub fn enforce<'a>(&self, content: &'a str) -> OnyxResult<&'a str> {
+        let limit = self.max_node_content_bytes;
+        if content.len() <= limit {
+            return Ok(content);
+        }
+        if !self.truncate_oversized_content {
+            return Err(OnyxError::ContentTooLarge {
+                size: content.len(),
+                limit,
+            });
+        }
+        let mut end = limit;
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        Ok(&content[..end])
+    }
+}
+
+pub fn load_config(path: Option<&Path>) -> OnyxResult<AppConfig> {
+    let config = build_config(path)?;
 
     let parsed: AppConfig = config
         .try_deserialize()
@@ -52,5 +127,36 @@ pub fn load_config(path: Option<&Path>) -> OnyxResult<AppConfig> {
         }
     }
 
+    if parsed.auth.enabled && parsed.auth.api_keys.is_empty() {
+        return Err(OnyxError::ConfigError(
+            "auth.enabled is true but auth.api_keys is empty".to_string(),
+        ));
+    }
+
     Ok(parsed)
 }
+
+/// Load just the `[limits]` section, for commands that need no server or
+/// payments settings. A missing section gives the default limits.
+pub fn load_limits(path: Option<&Path>) -> OnyxResult<LimitsConfig> {
+    match build_config(path)?.get::<LimitsConfig>("limits") {
+        Ok(limits) => Ok(limits),
+        Err(ConfigError::NotFound(_)) => Ok(LimitsConfig::default()),
+        Err(err) => Err(OnyxError::ConfigError(err.to_string())),
+    }
+}
+
+/// Layer `config.toml`, `ONYX__*` environment variables and `path`.
+fn build_config(path: Option<&Path>) -> OnyxResult<Config> {
+    let mut builder = Config::builder()
+        .add_source(File::with_name("config").required(false))
+        .add_source(Environment::with_prefix("ONYX").separator("__"));
+
+    if let Some(path) = path {
+        builder = builder.add_source(File::from(path).required(false));
+    }
+
+    builder
+        .build()
+        .map_err(|err| OnyxError::ConfigError(err.to_string()))
+}
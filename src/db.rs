@@ -15,7 +15,13 @@ pub struct OnyxDatabase {
 impl OnyxDatabase {
     /// Create a new in-memory database instance.
     pub async fn new_memory() -> Result<Self> {
-        let db = connect("memory").await?;
+        Self::new_local("memory").await
+    }
+
+    /// Open an embedded database at `url`, such as `surrealkv://onyx.db`,
+    /// without signing in.
+    pub async fn new_local(url: &str) -> Result<Self> {
+        let db = connect(url).await?;
         db.use_ns("onyx").use_db("onyx").await?;
 
         let db = Arc::new(db);
@@ -284,6 +290,8 @@ impl Default for DatabaseConfig {
 pub enum DatabaseEndpoint {
     /// In-memory database (for testing).
     Memory,
+    /// Embedded database on disk, such as `surrealkv://onyx.db`.
+    Local { url: String },
     /// Remote SurrealDB server.
     Remote {
         url: String,
@@ -301,6 +309,14 @@ impl DatabaseConfig {
         }
     }
 
+    /// Create a new configuration for an embedded database at `url`.
+    pub fn local(url: impl Into<String>) -> Self {
+        Self {
+            endpoint: DatabaseEndpoint::Local { url: url.into() },
+            ..Default::default()
+        }
+    }
+
     /// Create a new configuration for a remote SurrealDB server.
     pub fn remote(url: impl Into<String>, username: impl Into<String>, password: impl Into<String>) -> Self {
         Self {
@@ -317,6 +333,7 @@ impl DatabaseConfig {
     pub async fn connect(&self) -> Result<OnyxDatabase> {
         match &self.endpoint {
             DatabaseEndpoint::Memory => OnyxDatabase::new_memory().await,
+            DatabaseEndpoint::Local { url } => OnyxDatabase::new_local(url).await,
             DatabaseEndpoint::Remote {
                 url,
                 username,
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Central error type for Onyx operations.
@@ -30,9 +31,21 @@ pub enum OnyxError {
     #[error("Embedding dimension mismatch: expected {expected}, got {got}")]
     DimensionMismatch { expected: usize, got: usize },
 
+    #[error("Invalid embedding: {0}")]
+    InvalidEmbedding(String),
+
     #[error("Invalid query: {0}")]
     InvalidQuery(String),
 
+    #[error("Invalid query options: {0}")]
+    InvalidQueryOptions(String),
+
+    #[error("Invalid edge type: '{0}'")]
+    InvalidEdgeType(String),
+
+    #[error("Invalid node: {0}")]
+    InvalidNode(String),
+
     #[error("Ingestion error: {0}")]
     IngestionError(String),
 
@@ -50,6 +63,149 @@ pub enum OnyxError {
 
     #[error("Not found: {0}")]
     NotFound(String),
+
+    #[error("Content too large: {size} bytes exceeds the {limit} byte limit")]
+    ContentTooLarge { size: usize, limit: usize },
+
+    #[error("Corrupt record: {id} failed checksum verification")]
+    CorruptRecord { id: uuid::Uuid },
+
+    #[error("Storage error: {0}")]
+    Storage(#[from] StorageError),
+}
+
+impl OnyxError {
+    /// The stable code identifying this error in API responses.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            OnyxError::NodeNotFound(_) => ErrorCode::NodeNotFound,
+            OnyxError::EdgeNotFound(_) => ErrorCode::EdgeNotFound,
+            OnyxError::VersionNotFound(_) => ErrorCode::VersionNotFound,
+            OnyxError::BranchNotFound(_) => ErrorCode::BranchNotFound,
+            OnyxError::BranchAlreadyExists(_) => ErrorCode::BranchAlreadyExists,
+            OnyxError::DuplicateNode(_) => ErrorCode::DuplicateNode,
+            OnyxError::DuplicateEdge(_) => ErrorCode::DuplicateEdge,
+            OnyxError::TransactionFailed(_) => ErrorCode::TransactionFailed,
+            OnyxError::DimensionMismatch { .. } => ErrorCode::DimensionMismatch,
+            OnyxError::InvalidEmbedding(_) => ErrorCode::InvalidEmbedding,
+            OnyxError::InvalidQuery(_) | OnyxError::InvalidQueryOptions(_) => {
+                ErrorCode::InvalidQuery
+            }
+            OnyxError::InvalidEdgeType(_) => ErrorCode::InvalidEdgeType,
+            OnyxError::InvalidNode(_) => ErrorCode::InvalidNode,
+            OnyxError::IngestionError(_) => ErrorCode::IngestionError,
+            OnyxError::SerializationError(_) => ErrorCode::SerializationError,
+            OnyxError::ConfigError(_) => ErrorCode::ConfigError,
+            OnyxError::IoError(_) => ErrorCode::IoError,
+            OnyxError::Internal(_) => ErrorCode::Internal,
+            OnyxError::NotFound(_) => ErrorCode::NotFound,
+            OnyxError::ContentTooLarge { .. } => ErrorCode::ContentTooLarge,
+            OnyxError::CorruptRecord { .. } => ErrorCode::CorruptRecord,
+            OnyxError::Storage(_) => ErrorCode::StorageError,
+        }
+    }
+}
+
+/// Machine-readable error codes, serialized as e.g. `NODE_NOT_FOUND`.
+///
+/// Mirrored by the SDK, which turns them back into typed errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    BadRequest,
+    Unauthorized,
+    NodeNotFound,
+    EdgeNotFound,
+    VersionNotFound,
+    BranchNotFound,
+    NotFound,
+    BranchAlreadyExists,
+    DuplicateNode,
+    DuplicateEdge,
+    DimensionMismatch,
+    InvalidEmbedding,
+    InvalidQuery,
+    InvalidEdgeType,
+    InvalidNode,
+    MergeConflict,
+    ContentTooLarge,
+    TransactionFailed,
+    IngestionError,
+    SerializationError,
+    ConfigError,
+    IoError,
+    Internal,
+    CorruptRecord,
+    StorageError,
+}
+
+/// Boxed underlying error, so backend error types need not be in scope.
+type BoxedSource = Box<dyn std::error::Error + Send + Sync>;
+
+/// Failures in a persistent storage backend.
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Missing column family: {0}")]
+    MissingColumnFamily(String),
+
+    #[error("Failed to serialize {what}: {source}")]
+    Serialization {
+        what: &'static str,
+        #[source]
+        source: BoxedSource,
+    },
+
+    #[error("Failed to deserialize {what}: {source}")]
+    Deserialization {
+        what: &'static str,
+        #[source]
+        source: BoxedSource,
+    },
+
+    #[error("Cannot read {what} written with schema version {version}")]
+    UnsupportedSchema { what: &'static str, version: u16 },
+
+    #[error("RocksDB failed to {context}: {source}")]
+    RocksDb {
+        context: &'static str,
+        #[source]
+        source: BoxedSource,
+    },
+}
+
+impl StorageError {
+    /// Wrap a serialization failure for `what`.
+    pub fn serialization(
+        what: &'static str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::Serialization {
+            what,
+            source: Box::new(source),
+        }
+    }
+
+    /// Wrap a deserialization failure for `what`.
+    pub fn deserialization(
+        what: &'static str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::Deserialization {
+            what,
+            source: Box::new(source),
+        }
+    }
+
+    /// Wrap a RocksDB failure while trying to `context`.
+    pub fn rocks(
+        context: &'static str,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        Self::RocksDb {
+            context,
+            source: Box::new(source),
+        }
+    }
 }
 
 /// Convenience type alias for Onyx results.
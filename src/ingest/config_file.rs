@@ -0,0 +1,639 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::error::OnyxResult;
+use crate::ingest::detect::{use_paths, EntityRef};
+use crate::ingest::{record_ingested_node, stable_id, upsert_edge, IngestResult};
+use crate::model::edge::{DetectorKind, Edge, EdgeType};
+use crate::model::embedding::BagOfWordsEmbedder;
+use crate::model::node::{
+    ConfigExt, ConfigFormat, ConfigType, Node, NodeExtension, NodeType, Provenance,
+};
+use crate::store::transaction::{TransactionManager, TransactionStores};
+
+// ---------------------------------------------------------------------------
+// Config files: TOML, YAML, JSON and .env sources of configuration keys
+// ---------------------------------------------------------------------------
+
+/// Keys shorter than this (`id`, `url`) are too generic to link code on.
+const MIN_LINKED_KEY_LEN: usize = 4;
+
+/// A configuration file to ingest into Onyx.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    /// File path within the repository.
+    pub file_path: String,
+    /// The full file content.
+    pub content: String,
+    pub format: ConfigFormat,
+    pub config_type: ConfigType,
+    /// Dotted key paths defined by the file.
+    pub keys: Vec<String>,
+    /// Git commit hash at time of ingestion.
+    pub commit_id: Option<String>,
+    /// Branch name.
+    pub branch: Option<String>,
+}
+
+/// The config format of `path`, judged by its name; `None` for other files.
+pub fn config_format(path: &str) -> Option<ConfigFormat> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    if name == ".env" || name.starts_with(".env.") || name.ends_with(".env") {
+        return Some(ConfigFormat::Env);
+    }
+    match Path::new(name).extension()?.to_str()? {
+        "toml" => Some(ConfigFormat::Toml),
+        "yaml" | "yml" => Some(ConfigFormat::Yaml),
+        "json" => Some(ConfigFormat::Json),
+        _ => None,
+    }
+}
+
+/// Guess what a config file configures from its path.
+fn config_type(path: &str, format: &ConfigFormat) -> ConfigType {
+    let name = Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path);
+    if *format == ConfigFormat::Env {
+        ConfigType::Env
+    } else if name == "Cargo.toml" {
+        ConfigType::Cargo
+    } else if name.starts_with("docker-compose") || name.starts_with("compose.") {
+        ConfigType::Docker
+    } else if path.contains(".github/workflows") || name == ".gitlab-ci.yml" {
+        ConfigType::CI
+    } else if name == "package.json" || name == "build.toml" {
+        ConfigType::Build
+    } else {
+        ConfigType::App
+    }
+}
+
+/// Parse a config file, or `None` if `path` is not a supported format.
+pub fn parse_config_file(source: &str, path: &str) -> Option<ConfigFile> {
+    let format = config_format(path)?;
+    Some(ConfigFile {
+        file_path: path.to_string(),
+        content: source.to_string(),
+        config_type: config_type(path, &format),
+        keys: extract_keys(source, &format),
+        format,
+        commit_id: None,
+        branch: None,
+    })
+}
+
+/// Dotted key paths defined in `source`.
+///
+/// These are line-based scans rather than full parsers (except for JSON), so
+/// multi-line values and exotic syntax may be missed.
+pub fn extract_keys(source: &str, format: &ConfigFormat) -> Vec<String> {
+    match format {
+        ConfigFormat::Toml | ConfigFormat::Ini => toml_keys(source),
+        ConfigFormat::Yaml => yaml_keys(source),
+        ConfigFormat::Json => json_keys(source),
+        ConfigFormat::Env => env_keys(source),
+    }
+}
+
+fn toml_keys(source: &str) -> Vec<String> {
+    let mut table = String::new();
+    let mut keys = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if line.starts_with('[') {
+            table = line.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            continue;
+        }
+        if let Some((key, _)) = line.split_once('=') {
+            let key = key.trim().trim_matches('"');
+            if !key.is_empty() {
+                keys.push(join_key(&table, key));
+            }
+        }
+    }
+    keys
+}
+
+fn yaml_keys(source: &str) -> Vec<String> {
+    // Parent keys with their indentation, innermost last.
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut keys = Vec::new();
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('-') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let key = match trimmed.split_once(':') {
+            Some((key, _)) => key.trim().trim_matches(|c| c == '"' || c == '\''),
+            None => continue,
+        };
+        if key.is_empty() || key.contains(' ') {
+            continue;
+        }
+        while parents.last().map_or(false, |(i, _)| *i >= indent) {
+            parents.pop();
+        }
+        let parent = parents.last().map_or("", |(_, path)| path.as_str());
+        let path = join_key(parent, key);
+        keys.push(path.clone());
+        parents.push((indent, path));
+    }
+    keys
+}
+
+fn json_keys(source: &str) -> Vec<String> {
+    fn walk(prefix: &str, value: &serde_json::Value, keys: &mut Vec<String>) {
+        if let serde_json::Value::Object(map) = value {
+            for (key, child) in map {
+                let path = join_key(prefix, key);
+                walk(&path, child, keys);
+                keys.push(path);
+            }
+        }
+    }
+
+    let mut keys = Vec::new();
+    if let Ok(value) = serde_json::from_str(source) {
+        walk("", &value, &mut keys);
+    }
+    keys
+}
+
+fn env_keys(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.strip_prefix("export ").unwrap_or(line).split_once('='))
+        .map(|(key, _)| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+        .collect()
+}
+
+fn join_key(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Tables of a `Cargo.toml` that declare dependencies.
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A crate declared in a `Cargo.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CargoDependency {
+    /// The name code refers to the crate by, e.g. `async-trait`.
+    pub name: String,
+    /// The manifest lines declaring it.
+    pub declaration: String,
+}
+
+/// Dependencies declared by a `Cargo.toml`, in order and without repeats.
+///
+/// Covers `[dependencies]`, `[dev-dependencies]` and `[build-dependencies]`,
+/// their `[target.*]` variants and `[dependencies.name]` tables. Like
+/// [`extract_keys`] this is a line-based scan.
+pub fn cargo_dependencies(source: &str) -> Vec<CargoDependency> {
+    let mut deps: Vec<CargoDependency> = Vec::new();
+    let mut section = Section::Other;
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            let header = line.trim_matches(|c| c == '[' || c == ']').trim();
+            section = match dependency_table(header) {
+                Some(Some(name)) => Section::Crate(push_dependency(&mut deps, name, line)),
+                Some(None) => Section::List,
+                None => Section::Other,
+            };
+            continue;
+        }
+        match section {
+            Section::List => {
+                if let Some((name, _)) = line.split_once('=') {
+                    push_dependency(&mut deps, name.trim().trim_matches('"'), line);
+                }
+            }
+            Section::Crate(Some(i)) => {
+                deps[i].declaration.push('\n');
+                deps[i].declaration.push_str(line);
+            }
+            Section::Crate(None) | Section::Other => {}
+        }
+    }
+    deps
+}
+
+/// Where [`cargo_dependencies`] is in a manifest.
+enum Section {
+    Other,
+    /// A table listing dependencies, e.g. `[dependencies]`.
+    List,
+    /// A `[dependencies.name]` table, with the index of its dependency unless
+    /// the crate was declared earlier.
+    Crate(Option<usize>),
+}
+
+/// Whether `header` names a dependency table: `Some(None)` for a list of
+/// dependencies, `Some(Some(name))` for one dependency's own table.
+fn dependency_table(header: &str) -> Option<Option<&str>> {
+    let segments: Vec<&str> = header.split('.').collect();
+    let at = segments
+        .iter()
+        .position(|s| DEPENDENCY_TABLES.contains(s))?;
+    if at > 0 && segments[0] != "target" {
+        return None;
+    }
+    Some(segments.get(at + 1).map(|name| name.trim_matches('"')))
+}
+
+/// Add a dependency and return its index, or `None` if `name` was already
+/// declared, e.g. as both a regular and a dev-dependency.
+fn push_dependency(deps: &mut Vec<CargoDependency>, name: &str, line: &str) -> Option<usize> {
+    if deps.iter().any(|d| d.name == name) {
+        return None;
+    }
+    deps.push(CargoDependency {
+        name: name.to_string(),
+        declaration: line.to_string(),
+    });
+    Some(deps.len() - 1)
+}
+
+/// Keys whose last segment appears as an identifier in `content`.
+///
+/// `database.max-connections` matches code mentioning `max_connections`;
+/// matching ignores case, so `DATABASE_URL` matches `database_url`.
+fn referenced_keys<'a>(keys: &'a [String], content: &str) -> Vec<&'a str> {
+    let identifiers: HashSet<String> = content
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    keys.iter()
+        .filter(|key| {
+            let leaf = key.rsplit('.').next().unwrap_or(key);
+            leaf.len() >= MIN_LINKED_KEY_LEN
+                && identifiers.contains(&leaf.replace('-', "_").to_lowercase())
+        })
+        .map(String::as_str)
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Ingestion
+// ---------------------------------------------------------------------------
+
+/// Ingest a config file as a [`NodeType::Config`] node, then link it to the
+/// code that references its keys; see [`link_config_references`].
+pub async fn ingest_config_file(
+    stores: &mut TransactionManager,
+    file: &ConfigFile,
+    embedder: &BagOfWordsEmbedder,
+) -> OnyxResult<IngestResult> {
+    let name = Path::new(&file.file_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(&file.file_path)
+        .to_string();
+    let mut node = Node::new(NodeType::Config, &name, &file.content);
+    node.id = stable_id(&file.file_path, &[], &name);
+    node.provenance = file_provenance(file);
+    node.extension = NodeExtension::Config(ConfigExt {
+        config_type: file.config_type.clone(),
+        format: file.format.clone(),
+        keys: file.keys.clone(),
+    });
+
+    let mut result = record_ingested_node(stores, node, embedder).await?;
+    if file.config_type == ConfigType::Cargo {
+        for dep in cargo_dependencies(&file.content) {
+            ingest_dependency(stores, file, &dep, embedder).await?;
+        }
+    }
+    result.edges_created =
+        link_config_references(stores).await? + link_dependencies(stores).await?;
+    Ok(result)
+}
+
+/// Ingest `dep`, declared by the manifest `file`, as a
+/// [`ConfigType::Dependency`] node named after the crate.
+async fn ingest_dependency(
+    stores: &mut TransactionManager,
+    file: &ConfigFile,
+    dep: &CargoDependency,
+    embedder: &BagOfWordsEmbedder,
+) -> OnyxResult<IngestResult> {
+    let mut node = Node::new(NodeType::Config, &dep.name, &dep.declaration);
+    node.id = stable_id(&file.file_path, &["dependencies".to_string()], &dep.name);
+    node.provenance = file_provenance(file);
+    node.extension = NodeExtension::Config(ConfigExt {
+        config_type: ConfigType::Dependency,
+        format: file.format.clone(),
+        keys: Vec::new(),
+    });
+    record_ingested_node(stores, node, embedder).await
+}
+
+fn file_provenance(file: &ConfigFile) -> Provenance {
+    let mut provenance = Provenance::new(&file.file_path);
+    if let Some(ref commit) = file.commit_id {
+        provenance = provenance.with_commit(commit);
+    }
+    if let Some(ref branch) = file.branch {
+        provenance = provenance.with_branch(branch);
+    }
+    provenance
+}
+
+/// Every ingested code entity.
+async fn code_nodes(stores: &impl TransactionStores) -> Vec<Node> {
+    stores
+        .graph()
+        .all_nodes()
+        .await
+        .into_iter()
+        .filter(|n| matches!(n.node_type, NodeType::CodeEntity(_)))
+        .collect()
+}
+
+/// Add a `Configures` edge from every config node to each code entity that
+/// mentions one of its keys. Returns the number of new edges.
+pub async fn link_config_references(stores: &mut impl TransactionStores) -> OnyxResult<usize> {
+    let configs = stores.graph().nodes_by_type(&NodeType::Config).await;
+    if configs.is_empty() {
+        return Ok(0);
+    }
+    let code = code_nodes(stores).await;
+
+    let mut edges = Vec::new();
+    for config in &configs {
+        let keys = match &config.extension {
+            NodeExtension::Config(ext) => &ext.keys,
+            _ => continue,
+        };
+        for node in &code {
+            if let Some(key) = referenced_keys(keys, &node.content).first() {
+                edges.push(
+                    Edge::new(EdgeType::Configures, config.id, node.id)
+                        .with_confidence(0.7) // Name match only
+                        .detected_by(DetectorKind::ConfigKey)
+                        .with_metadata("key", *key),
+                );
+            }
+        }
+    }
+
+    let mut created = 0;
+    for edge in edges {
+        if upsert_edge(stores, edge).await? {
+            created += 1;
+        }
+    }
+    Ok(created)
+}
+
+/// Add a `DependsOn` edge from every code entity to each crate dependency
+/// it imports with a `use` declaration. Returns the number of new edges.
+pub async fn link_dependencies(stores: &mut impl TransactionStores) -> OnyxResult<usize> {
+    let deps: Vec<Node> = stores
+        .graph()
+        .nodes_by_type(&NodeType::Config)
+        .await
+        .into_iter()
+        .filter(|n| match &n.extension {
+            NodeExtension::Config(ext) => ext.config_type == ConfigType::Dependency,
+            _ => false,
+        })
+        .collect();
+    if deps.is_empty() {
+        return Ok(0);
+    }
+
+    let mut edges = Vec::new();
+    for node in &code_nodes(stores).await {
+        let entity = EntityRef::from_node(node);
+        let crates: HashSet<String> = use_paths(entity.content, entity.module_path)
+            .into_iter()
+            .filter_map(|path| path.into_iter().next())
+            .collect();
+        for dep in &deps {
+            if crates.contains(&dep.name.replace('-', "_")) {
+                edges.push(
+                    Edge::new(EdgeType::DependsOn, node.id, dep.id)
+                        .with_confidence(1.0)
+                        .detected_by(DetectorKind::Manifest),
+                );
+            }
+        }
+    }
+
+    let mut created = 0;
+    for edge in edges {
+        if upsert_edge(stores, edge).await? {
+            created += 1;
+        }
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LimitsConfig;
+    use crate::ingest::{ingest_codebase, CodeUnit};
+    use crate::model::node::{CodeEntityKind, Language, Visibility};
+    use crate::store::graph::GraphStore;
+
+    #[test]
+    fn test_extract_keys_per_format() {
+        let toml = "[database]\nmax_connections = 10\n\n[server]\nport = 8080\n";
+        assert_eq!(
+            extract_keys(toml, &ConfigFormat::Toml),
+            ["database.max_connections", "server.port"]
+        );
+
+        let yaml = "database:\n  max-connections: 10\n  pool:\n    size: 4\nname: onyx\n";
+        assert_eq!(
+            extract_keys(yaml, &ConfigFormat::Yaml),
+            ["database", "database.max-connections", "database.pool", "database.pool.size", "name"]
+        );
+
+        let json = r#"{"database": {"url": "postgres://"}}"#;
+        assert_eq!(
+            extract_keys(json, &ConfigFormat::Json),
+            ["database.url", "database"]
+        );
+
+        let env = "# comment\nexport DATABASE_URL=postgres://\nLOG_LEVEL=info\n";
+        assert_eq!(
+            extract_keys(env, &ConfigFormat::Env),
+            ["DATABASE_URL", "LOG_LEVEL"]
+        );
+        assert_eq!(config_format("deploy/.env.local"), Some(ConfigFormat::Env));
+        assert_eq!(config_format("src/lib.rs"), None);
+    }
+
+    #[test]
+    fn test_cargo_dependencies() {
+        let manifest = r#"
+[package]
+name = "onyx"
+
+[dependencies]
+tokio = { version = "1", features = ["full"] }
+async-trait = "0.1"
+
+[dependencies.serde]
+version = "1"
+features = ["derive"]
+
+[target.'cfg(unix)'.dependencies]
+libc = "0.2"
+
+[dev-dependencies]
+tokio = { version = "1", features = ["test-util"] }
+"#;
+        let deps = cargo_dependencies(manifest);
+        let names: Vec<&str> = deps.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["tokio", "async-trait", "serde", "libc"]);
+        assert_eq!(
+            deps[2].declaration,
+            "[dependencies.serde]\nversion = \"1\"\nfeatures = [\"derive\"]"
+        );
+        assert!(deps[0].declaration.contains("full"));
+    }
+
+    #[tokio::test]
+    async fn test_config_key_reference_creates_configures_edge() {
+        let mut stores = TransactionManager::new();
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pool max_connections"], 20);
+
+        let unit = CodeUnit {
+            name: "connect_pool".to_string(),
+            content: "pub fn connect_pool(cfg: &Settings) -> Pool {\n    Pool::new(cfg.database.max_connections)\n}"
+                .to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/db.rs".to_string(),
+            line_range: Some((1, 3)),
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["db".to_string()],
+            commit_id: None,
+            branch: None,
+        };
+        let unrelated = CodeUnit {
+            name: "render".to_string(),
+            content: "pub fn render() {}".to_string(),
+            file_path: "src/ui.rs".to_string(),
+            ..unit.clone()
+        };
+        let code = ingest_codebase(
+            &mut stores,
+            &[unit, unrelated],
+            &embedder,
+            &LimitsConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let file = parse_config_file(
+            "[database]\nmax_connections = 10\nurl = \"postgres://\"\n",
+            "config/settings.toml",
+        )
+        .unwrap();
+        assert_eq!(file.config_type, ConfigType::App);
+        let result = ingest_config_file(&mut stores, &file, &embedder)
+            .await
+            .unwrap();
+        assert_eq!(result.edges_created, 1);
+
+        let config = stores
+            .graph_store
+            .get_node(&result.node_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.node_type, NodeType::Config);
+        let edge = stores
+            .graph_store
+            .find_edge(&result.node_id, &code[0].node_id, &EdgeType::Configures)
+            .await
+            .unwrap()
+            .expect("configures edge");
+        assert_eq!(edge.metadata["key"], "database.max_connections");
+        assert!(stores
+            .graph_store
+            .find_edge(&result.node_id, &code[1].node_id, &EdgeType::Configures)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_use_of_declared_crate_creates_depends_on_edge() {
+        let mut stores = TransactionManager::new();
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn spawn worker tokio"], 20);
+
+        let unit = CodeUnit {
+            name: "spawn_worker".to_string(),
+            content: "use tokio::task;\n\npub fn spawn_worker() {\n    task::spawn(async {});\n}"
+                .to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/worker.rs".to_string(),
+            line_range: Some((1, 5)),
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["worker".to_string()],
+            commit_id: None,
+            branch: None,
+        };
+        let local = CodeUnit {
+            name: "shutdown".to_string(),
+            content: "use crate::worker::spawn_worker;\n\npub fn shutdown() {}".to_string(),
+            ..unit.clone()
+        };
+        let code = ingest_codebase(
+            &mut stores,
+            &[unit, local],
+            &embedder,
+            &LimitsConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let manifest = "[package]\nname = \"app\"\n\n[dependencies]\ntokio = \"1\"\n";
+        let file = parse_config_file(manifest, "Cargo.toml").unwrap();
+        assert_eq!(file.config_type, ConfigType::Cargo);
+        ingest_config_file(&mut stores, &file, &embedder)
+            .await
+            .unwrap();
+
+        let tokio = stable_id("Cargo.toml", &["dependencies".to_string()], "tokio");
+        let dep = stores.graph_store.get_node(&tokio).await.unwrap().unwrap();
+        assert_eq!(dep.content, "tokio = \"1\"");
+        let edge = stores
+            .graph_store
+            .find_edge(&code[0].node_id, &tokio, &EdgeType::DependsOn)
+            .await
+            .unwrap()
+            .expect("depends_on edge");
+        assert_eq!(edge.detector(), Some(&DetectorKind::Manifest));
+        assert!(stores
+            .graph_store
+            .find_edge(&code[1].node_id, &tokio, &EdgeType::DependsOn)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
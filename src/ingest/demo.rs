@@ -0,0 +1,101 @@
+//! Synthetic e-commerce codebase used by `onyx demo` and in tests.
+
+use crate::ingest::CodeUnit;
+use crate::model::node::{CodeEntityKind, Language, Visibility};
+
+/// Build a synthetic codebase for demo purposes.
+pub fn build_synthetic_codebase() -> Vec<CodeUnit> {
+    vec![
+        CodeUnit {
+            name: "calculate_total".to_string(),
+            content: "pub fn calculate_total(items: &[Item], tax_rate: f64) -> f64 {\n    let subtotal = items.iter().map(|i| i.price * i.quantity as f64).sum::<f64>();\n    let discount = apply_discount(subtotal);\n    discount * (1.0 + tax_rate)\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing/calculator.rs".to_string(),
+            line_range: Some((10, 15)),
+            signature: Some("pub fn calculate_total(items: &[Item], tax_rate: f64) -> f64".to_string()),
+            visibility: Visibility::Public,
+            module_path: vec!["billing".to_string(), "calculator".to_string()],
+            commit_id: Some("abc123".to_string()),
+            branch: Some("main".to_string()),
+        },
+        CodeUnit {
+            name: "apply_discount".to_string(),
+            content: "pub fn apply_discount(amount: f64) -> f64 {\n    let rules = get_discount_rules();\n    rules.iter().fold(amount, |acc, rule| rule.apply(acc))\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing/discount.rs".to_string(),
+            line_range: Some((5, 8)),
+            signature: Some("pub fn apply_discount(amount: f64) -> f64".to_string()),
+            visibility: Visibility::Public,
+            module_path: vec!["billing".to_string(), "discount".to_string()],
+            commit_id: Some("abc123".to_string()),
+            branch: Some("main".to_string()),
+        },
+        CodeUnit {
+            name: "get_discount_rules".to_string(),
+            content: "fn get_discount_rules() -> Vec<DiscountRule> {\n    vec![\n        DiscountRule::percentage(10.0, 100.0),\n        DiscountRule::fixed(5.0, 50.0),\n    ]\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing/discount.rs".to_string(),
+            line_range: Some((12, 18)),
+            signature: Some("fn get_discount_rules() -> Vec<DiscountRule>".to_string()),
+            visibility: Visibility::Private,
+            module_path: vec!["billing".to_string(), "discount".to_string()],
+            commit_id: Some("abc123".to_string()),
+            branch: Some("main".to_string()),
+        },
+        CodeUnit {
+            name: "process_payment".to_string(),
+            content: "pub fn process_payment(order: &Order) -> PaymentResult {\n    let total = calculate_total(&order.items, order.tax_rate);\n    let charge = payment_gateway::charge(order.payment_method, total);\n    record_transaction(order.id, total, charge.status)\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/payment/processor.rs".to_string(),
+            line_range: Some((20, 25)),
+            signature: Some("pub fn process_payment(order: &Order) -> PaymentResult".to_string()),
+            visibility: Visibility::Public,
+            module_path: vec!["payment".to_string(), "processor".to_string()],
+            commit_id: Some("def456".to_string()),
+            branch: Some("main".to_string()),
+        },
+        CodeUnit {
+            name: "record_transaction".to_string(),
+            content: "fn record_transaction(order_id: Uuid, amount: f64, status: ChargeStatus) -> PaymentResult {\n    let tx = Transaction::new(order_id, amount, status);\n    db::insert(&tx)?;\n    PaymentResult::from(tx)\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/payment/ledger.rs".to_string(),
+            line_range: Some((8, 13)),
+            signature: Some("fn record_transaction(order_id: Uuid, amount: f64, status: ChargeStatus) -> PaymentResult".to_string()),
+            visibility: Visibility::Private,
+            module_path: vec!["payment".to_string(), "ledger".to_string()],
+            commit_id: Some("def456".to_string()),
+            branch: Some("main".to_string()),
+        },
+        CodeUnit {
+            name: "validate_order".to_string(),
+            content: "pub fn validate_order(order: &Order) -> Result<(), ValidationError> {\n    if order.items.is_empty() {\n        return Err(ValidationError::EmptyOrder);\n    }\n    for item in &order.items {\n        validate_item(item)?;\n    }\n    Ok(())\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/validation/order.rs".to_string(),
+            line_range: Some((1, 9)),
+            signature: Some("pub fn validate_order(order: &Order) -> Result<(), ValidationError>".to_string()),
+            visibility: Visibility::Public,
+            module_path: vec!["validation".to_string(), "order".to_string()],
+            commit_id: Some("ghi789".to_string()),
+            branch: Some("main".to_string()),
+        },
+        CodeUnit {
+            name: "validate_item".to_string(),
+            content: "fn validate_item(item: &Item) -> Result<(), ValidationError> {\n    if item.price < 0.0 {\n        return Err(ValidationError::NegativePrice);\n    }\n    if item.quantity == 0 {\n        return Err(ValidationError::ZeroQuantity);\n    }\n    Ok(())\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/validation/item.rs".to_string(),
+            line_range: Some((1, 9)),
+            signature: Some("fn validate_item(item: &Item) -> Result<(), ValidationError>".to_string()),
+            visibility: Visibility::Private,
+            module_path: vec!["validation".to_string(), "item".to_string()],
+            commit_id: Some("ghi789".to_string()),
+            branch: Some("main".to_string()),
+        },
+    ]
+}
@@ -0,0 +1,572 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use syn::visit::{self, Visit};
+use uuid::Uuid;
+
+use crate::model::edge::{DetectorKind, Edge, EdgeType};
+use crate::model::node::{Node, NodeExtension};
+
+// ---------------------------------------------------------------------------
+// Relationship detectors: infer edges between ingested entities
+// ---------------------------------------------------------------------------
+
+/// The parts of a code entity that relationship detection looks at.
+#[derive(Debug, Clone)]
+pub struct EntityRef<'a> {
+    pub id: Uuid,
+    pub name: &'a str,
+    pub content: &'a str,
+    pub module_path: &'a [String],
+    /// Functions called by a test; `None` for non-test entities.
+    pub test_targets: Option<Vec<String>>,
+}
+
+impl<'a> EntityRef<'a> {
+    /// The detection view of a stored node.
+    pub fn from_node(node: &'a Node) -> Self {
+        Self {
+            id: node.id,
+            name: &node.name,
+            content: &node.content,
+            module_path: match &node.extension {
+                NodeExtension::CodeEntity(ext) => &ext.module_path,
+                _ => &[],
+            },
+            test_targets: match &node.extension {
+                NodeExtension::Test(ext) => Some(ext.targets.clone()),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// Infers edges between the entities of one ingest without touching any
+/// store.
+pub trait RelationshipDetector: Send + Sync {
+    /// Detector kinds recorded on the edges this detector returns. Edges of
+    /// these kinds that a later run no longer detects are closed.
+    fn kinds(&self) -> &'static [DetectorKind];
+
+    /// Edges between `entities`, at most one per (source, target, type).
+    fn detect(&self, entities: &[EntityRef<'_>]) -> Vec<Edge>;
+}
+
+/// Map each entity name to its id; later entities win on a clash.
+fn ids_by_name<'a>(entities: &[EntityRef<'a>]) -> HashMap<&'a str, Uuid> {
+    entities.iter().map(|e| (e.name, e.id)).collect()
+}
+
+/// Calls, imports and test coverage found by scanning each entity's content
+/// for other entities' names. A mention is an import only when one of the
+/// entity's `use` or `mod` declarations resolves to the mentioned entity;
+/// see [`imports`]. Tests that call a target directly get a certain
+/// [`DetectorKind::TestTarget`] edge instead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContentScanDetector;
+
+impl RelationshipDetector for ContentScanDetector {
+    fn kinds(&self) -> &'static [DetectorKind] {
+        &[DetectorKind::ContentScan, DetectorKind::TestTarget]
+    }
+
+    fn detect(&self, entities: &[EntityRef<'_>]) -> Vec<Edge> {
+        let name_to_id = ids_by_name(entities);
+        let by_id: HashMap<Uuid, &EntityRef<'_>> = entities.iter().map(|e| (e.id, e)).collect();
+        let mut edges = Vec::new();
+
+        for entity in entities {
+            let imported = use_paths(entity.content, entity.module_path);
+            for (ref_name, ref_id) in &name_to_id {
+                if *ref_id == entity.id {
+                    continue; // Skip self-references
+                }
+
+                if let Some(ref targets) = entity.test_targets {
+                    // A direct call from a test is certain coverage
+                    if targets.iter().any(|t| t == ref_name) {
+                        edges.push(
+                            Edge::new(EdgeType::TestsOf, entity.id, *ref_id)
+                                .with_confidence(1.0)
+                                .detected_by(DetectorKind::TestTarget),
+                        );
+                        continue;
+                    }
+                }
+
+                // This is a simple heuristic; AstCallDetector confirms calls
+                if entity.content.contains(ref_name) {
+                    let edge_type = if entity.test_targets.is_some() {
+                        EdgeType::TestsOf
+                    } else if by_id.get(ref_id).is_some_and(|t| imports(&imported, t)) {
+                        EdgeType::Imports
+                    } else {
+                        EdgeType::Calls
+                    };
+
+                    edges.push(
+                        Edge::new(edge_type, entity.id, *ref_id)
+                            .with_confidence(0.8) // Heuristic-based, not AST-confirmed
+                            .detected_by(DetectorKind::ContentScan),
+                    );
+                }
+            }
+        }
+
+        edges
+    }
+}
+
+/// `Contains` edges from each module to the entities directly inside it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ModuleHierarchyDetector;
+
+impl RelationshipDetector for ModuleHierarchyDetector {
+    fn kinds(&self) -> &'static [DetectorKind] {
+        &[DetectorKind::ModuleHierarchy]
+    }
+
+    fn detect(&self, entities: &[EntityRef<'_>]) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        for parent in entities {
+            for child in entities {
+                if parent.id == child.id {
+                    continue;
+                }
+
+                // Check if the parent's module path is a prefix of the child's
+                let (path_i, path_j) = (parent.module_path, child.module_path);
+                if !path_i.is_empty()
+                    && path_j.len() == path_i.len() + 1
+                    && path_j.starts_with(path_i)
+                {
+                    edges.push(
+                        Edge::new(EdgeType::Contains, parent.id, child.id)
+                            .with_confidence(1.0)
+                            .detected_by(DetectorKind::ModuleHierarchy),
+                    );
+                }
+            }
+        }
+        edges
+    }
+}
+
+/// Calls confirmed by parsing each entity as Rust with `syn`.
+///
+/// Only call and method-call expressions whose last path segment names
+/// another entity produce an edge, so a name merely appearing in the
+/// content (e.g. `total` inside `calculate_total`) does not. Entities that
+/// don't parse as Rust, and calls inside macro invocations, are skipped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AstCallDetector;
+
+impl RelationshipDetector for AstCallDetector {
+    fn kinds(&self) -> &'static [DetectorKind] {
+        &[DetectorKind::Ast]
+    }
+
+    fn detect(&self, entities: &[EntityRef<'_>]) -> Vec<Edge> {
+        let name_to_id = ids_by_name(entities);
+        let mut edges = Vec::new();
+
+        for entity in entities {
+            let Ok(file) = syn::parse_file(entity.content) else {
+                continue;
+            };
+            let mut calls = CallCollector::default();
+            calls.visit_file(&file);
+
+            let edge_type = if entity.test_targets.is_some() {
+                EdgeType::TestsOf
+            } else {
+                EdgeType::Calls
+            };
+            for name in &calls.names {
+                match name_to_id.get(name.as_str()) {
+                    Some(&target) if target != entity.id => edges.push(
+                        Edge::new(edge_type.clone(), entity.id, target)
+                            .with_confidence(0.95)
+                            .detected_by(DetectorKind::Ast),
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        edges
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Import resolution: `use` and `mod` declarations to module paths
+// ---------------------------------------------------------------------------
+
+/// Absolute module paths imported by the `use` and `mod` declarations in
+/// `content`, an entity inside `module_path`. Groups are expanded and
+/// renames dropped; a glob import ends in `*`. `crate::`, `self::` and
+/// `super::` are resolved against `module_path`. Content that doesn't parse
+/// as Rust imports nothing.
+pub fn use_paths(content: &str, module_path: &[String]) -> Vec<Vec<String>> {
+    let Ok(file) = syn::parse_file(content) else {
+        return Vec::new();
+    };
+    let mut uses = UseCollector::default();
+    uses.visit_file(&file);
+    uses.paths
+        .iter()
+        .filter_map(|path| resolve_path(path, module_path))
+        .collect()
+}
+
+/// Whether any of the resolved `imported` paths names `entity`: its full
+/// path, its own module path for a module, or a glob over its module.
+pub fn imports(imported: &[Vec<String>], entity: &EntityRef<'_>) -> bool {
+    let module = entity.module_path;
+    imported.iter().any(|path| match path.split_last() {
+        Some((last, parent)) if last == "*" => parent == module,
+        Some((last, parent)) => {
+            last == entity.name && (parent == module || path.as_slice() == module)
+        }
+        None => false,
+    })
+}
+
+/// Make `path` absolute. Returns `None` when `super::` climbs above the
+/// crate root.
+fn resolve_path(path: &[String], module_path: &[String]) -> Option<Vec<String>> {
+    let (base, rest): (&[String], &[String]) = match path.first().map(String::as_str) {
+        Some("crate") => (&[], &path[1..]),
+        Some("self") => (module_path, &path[1..]),
+        Some("super") => {
+            let supers = path.iter().take_while(|s| *s == "super").count();
+            let depth = module_path.len().checked_sub(supers)?;
+            (&module_path[..depth], &path[supers..])
+        }
+        _ => (&[], path),
+    };
+    Some(base.iter().chain(rest).cloned().collect())
+}
+
+/// Paths named by `use` items and `mod name;` declarations, unresolved.
+#[derive(Default)]
+struct UseCollector {
+    paths: Vec<Vec<String>>,
+}
+
+impl UseCollector {
+    fn push_tree(&mut self, prefix: &mut Vec<String>, tree: &syn::UseTree) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                self.push_tree(prefix, &path.tree);
+                prefix.pop();
+            }
+            syn::UseTree::Name(syn::UseName { ident })
+            | syn::UseTree::Rename(syn::UseRename { ident, .. }) => {
+                let mut path = prefix.clone();
+                // `use a::{self}` imports `a` itself
+                if ident != "self" {
+                    path.push(ident.to_string());
+                }
+                self.paths.push(path);
+            }
+            syn::UseTree::Glob(_) => {
+                let mut path = prefix.clone();
+                path.push("*".to_string());
+                self.paths.push(path);
+            }
+            syn::UseTree::Group(group) => {
+                for item in &group.items {
+                    self.push_tree(prefix, item);
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for UseCollector {
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        self.push_tree(&mut Vec::new(), &item.tree);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        if item.content.is_none() {
+            self.paths
+                .push(vec!["self".to_string(), item.ident.to_string()]);
+        }
+        visit::visit_item_mod(self, item);
+    }
+}
+
+/// Trait implementations found by parsing each entity as Rust.
+///
+/// Every `impl Trait for Type` block yields an `Implements` edge from the
+/// type to the trait, and a `Defines` edge from the entity holding the
+/// block to each of its methods. Types, traits and methods are matched to
+/// entities by name, ignoring any path or generic arguments.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraitImplDetector;
+
+impl RelationshipDetector for TraitImplDetector {
+    fn kinds(&self) -> &'static [DetectorKind] {
+        &[DetectorKind::TraitImpl]
+    }
+
+    fn detect(&self, entities: &[EntityRef<'_>]) -> Vec<Edge> {
+        let name_to_id = ids_by_name(entities);
+        let mut edges = Vec::new();
+        let mut push = |edge_type, source, target| {
+            if source != target {
+                edges.push(
+                    Edge::new(edge_type, source, target)
+                        .with_confidence(1.0)
+                        .detected_by(DetectorKind::TraitImpl),
+                );
+            }
+        };
+
+        for entity in entities {
+            let Ok(file) = syn::parse_file(entity.content) else {
+                continue;
+            };
+            let mut impls = ImplCollector::default();
+            impls.visit_file(&file);
+
+            for block in impls.impls {
+                let self_ty = name_to_id.get(block.self_ty.as_str());
+                let trait_ = name_to_id.get(block.trait_.as_str());
+                if let (Some(&ty), Some(&tr)) = (self_ty, trait_) {
+                    push(EdgeType::Implements, ty, tr);
+                }
+                for method in &block.methods {
+                    if let Some(&id) = name_to_id.get(method.as_str()) {
+                        push(EdgeType::Defines, entity.id, id);
+                    }
+                }
+            }
+        }
+
+        edges
+    }
+}
+
+/// One `impl Trait for Type` block, by name.
+struct TraitImpl {
+    trait_: String,
+    self_ty: String,
+    methods: Vec<String>,
+}
+
+/// Trait impls in a syntax tree. Negative impls and impls for types that
+/// aren't plain paths (references, tuples, ...) are skipped.
+#[derive(Default)]
+struct ImplCollector {
+    impls: Vec<TraitImpl>,
+}
+
+impl<'ast> Visit<'ast> for ImplCollector {
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let trait_ = match &item.trait_ {
+            Some((None, path, _)) => path.segments.last(),
+            _ => None,
+        };
+        let self_ty = match item.self_ty.as_ref() {
+            syn::Type::Path(ty) => ty.path.segments.last(),
+            _ => None,
+        };
+        if let (Some(trait_), Some(self_ty)) = (trait_, self_ty) {
+            let methods = item
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    syn::ImplItem::Fn(method) => Some(method.sig.ident.to_string()),
+                    _ => None,
+                })
+                .collect();
+            self.impls.push(TraitImpl {
+                trait_: trait_.ident.to_string(),
+                self_ty: self_ty.ident.to_string(),
+                methods,
+            });
+        }
+        visit::visit_item_impl(self, item);
+    }
+}
+
+/// Names of the functions and methods called in a syntax tree.
+#[derive(Default)]
+struct CallCollector {
+    names: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for CallCollector {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(path) = call.func.as_ref() {
+            if let Some(segment) = path.path.segments.last() {
+                self.names.insert(segment.ident.to_string());
+            }
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        self.names.insert(call.method.to_string());
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// IngestOptions: which detectors an ingest runs
+// ---------------------------------------------------------------------------
+
+/// Options for [`ingest_codebase_with_options`](super::ingest_codebase_with_options).
+#[derive(Clone)]
+pub struct IngestOptions {
+    detectors: Vec<Arc<dyn RelationshipDetector>>,
+}
+
+impl IngestOptions {
+    /// Options that run no relationship detectors.
+    pub fn new() -> Self {
+        Self {
+            detectors: Vec::new(),
+        }
+    }
+
+    /// Also run `detector` when detecting relationships.
+    pub fn register(&mut self, detector: impl RelationshipDetector + 'static) {
+        self.detectors.push(Arc::new(detector));
+    }
+
+    /// Builder-style variant of [`register`](Self::register).
+    pub fn with_detector(mut self, detector: impl RelationshipDetector + 'static) -> Self {
+        self.register(detector);
+        self
+    }
+
+    /// Detector kinds recorded by the configured detectors.
+    pub fn detector_kinds(&self) -> Vec<DetectorKind> {
+        self.detectors
+            .iter()
+            .flat_map(|d| d.kinds().iter().cloned())
+            .collect()
+    }
+
+    /// Run every detector over `entities`, folding repeated detections of
+    /// the same (source, target, type) into one edge as
+    /// [`upsert_edge`](super::upsert_edge) would.
+    pub fn infer_edges(&self, entities: &[EntityRef<'_>]) -> Vec<Edge> {
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut index: HashMap<(Uuid, Uuid, EdgeType), usize> = HashMap::new();
+        for detector in &self.detectors {
+            for edge in detector.detect(entities) {
+                let key = (edge.source_id, edge.target_id, edge.edge_type.clone());
+                match index.get(&key) {
+                    Some(&i) => {
+                        let existing = &mut edges[i];
+                        if existing.is_corroborated_by(&edge)
+                            || edge.confidence > existing.confidence
+                        {
+                            *existing = existing.clone().reinforced(&edge);
+                        }
+                    }
+                    None => {
+                        index.insert(key, edges.len());
+                        edges.push(edge);
+                    }
+                }
+            }
+        }
+        edges
+    }
+}
+
+impl Default for IngestOptions {
+    /// Content scanning, the module hierarchy and trait impls.
+    fn default() -> Self {
+        Self::new()
+            .with_detector(ContentScanDetector)
+            .with_detector(ModuleHierarchyDetector)
+            .with_detector(TraitImplDetector)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::edge::combine_confidence;
+    use crate::model::node::{CodeEntityKind, NodeType};
+
+    fn function(name: &str, content: &str) -> Node {
+        Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            name,
+            content,
+        )
+    }
+
+    #[test]
+    fn test_ast_detector_skips_name_substring_match() {
+        let total = function(
+            "total",
+            "fn total(items: &[f64]) -> f64 { items.iter().sum() }",
+        );
+        let calculate_total = function(
+            "calculate_total",
+            "fn calculate_total(items: &[f64]) -> f64 { items.iter().sum::<f64>() * 1.2 }",
+        );
+        let checkout = function("checkout", "fn checkout() -> f64 { calculate_total(&[]) }");
+        let nodes = [total, calculate_total, checkout];
+        let entities: Vec<EntityRef<'_>> = nodes.iter().map(EntityRef::from_node).collect();
+        let (total, calculate_total, checkout) = (nodes[0].id, nodes[1].id, nodes[2].id);
+        let pairs = |edges: &[Edge]| -> Vec<(Uuid, Uuid)> {
+            edges.iter().map(|e| (e.source_id, e.target_id)).collect()
+        };
+
+        // `total` is a substring of `calculate_total`, so the content scan
+        // links both functions that mention the longer name to `total`.
+        let scanned = pairs(&ContentScanDetector.detect(&entities));
+        assert!(scanned.contains(&(calculate_total, total)));
+        assert!(scanned.contains(&(checkout, total)));
+        assert!(scanned.contains(&(checkout, calculate_total)));
+
+        // The AST detector only keeps the real call.
+        let parsed = AstCallDetector.detect(&entities);
+        assert_eq!(pairs(&parsed), [(checkout, calculate_total)]);
+        assert_eq!(parsed[0].edge_type, EdgeType::Calls);
+        assert_eq!(parsed[0].detector(), Some(&DetectorKind::Ast));
+    }
+
+    #[test]
+    fn test_use_paths_resolve_against_module() {
+        let module = vec!["billing".to_string(), "invoice".to_string()];
+        let content = "use super::{tax, rates::*};\nuse crate::report::Line as Row;\nmod pdf;";
+        let paths = use_paths(content, &module);
+        let expected: [&[&str]; 4] = [
+            &["billing", "tax"],
+            &["billing", "rates", "*"],
+            &["report", "Line"],
+            &["billing", "invoice", "pdf"],
+        ];
+        assert_eq!(paths, expected);
+    }
+
+    #[test]
+    fn test_composed_detectors_merge_repeated_edges() {
+        let callee = function("callee", "fn callee() {}");
+        let caller = function("caller", "fn caller() { callee(); }");
+        let nodes = [callee, caller];
+        let entities: Vec<EntityRef<'_>> = nodes.iter().map(EntityRef::from_node).collect();
+
+        let options = IngestOptions::default().with_detector(AstCallDetector);
+        let edges = options.infer_edges(&entities);
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].detector(), Some(&DetectorKind::ContentScan));
+        assert_eq!(edges[0].confidence, combine_confidence(0.8, 0.95));
+    }
+}
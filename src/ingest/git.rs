@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use crate::ingest::CodeUnit;
+
+// ---------------------------------------------------------------------------
+// Git provenance: commit and branch for ingested code
+// ---------------------------------------------------------------------------
+
+/// The checked-out state of a git repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHead {
+    /// Full hex ID of the HEAD commit.
+    pub commit_id: String,
+    /// Current branch name; `None` when HEAD is detached.
+    pub branch: Option<String>,
+}
+
+/// Read HEAD of the repository containing `path`.
+///
+/// Returns `None` if `path` is not inside a git repository, the repository has
+/// no commits yet, or the `git-provenance` feature is disabled.
+#[cfg(feature = "git-provenance")]
+pub fn head_for(path: &Path) -> Option<GitHead> {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+    };
+
+    let repo = git2::Repository::discover(dir).ok()?;
+    let head = repo.head().ok()?;
+    let commit = head.peel_to_commit().ok()?;
+    let branch = if head.is_branch() {
+        head.shorthand().map(str::to_string)
+    } else {
+        None
+    };
+
+    Some(GitHead {
+        commit_id: commit.id().to_string(),
+        branch,
+    })
+}
+
+/// Read HEAD of the repository containing `path`.
+///
+/// Always `None`: built without the `git-provenance` feature.
+#[cfg(not(feature = "git-provenance"))]
+pub fn head_for(_path: &Path) -> Option<GitHead> {
+    None
+}
+
+/// Fill in `commit_id` and `branch` on units parsed from `path`.
+///
+/// Values already set on a unit are kept. Does nothing outside a git
+/// repository.
+pub fn apply_git_provenance(path: &Path, units: &mut [CodeUnit]) {
+    let Some(head) = head_for(path) else {
+        return;
+    };
+
+    for unit in units {
+        if unit.commit_id.is_none() {
+            unit.commit_id = Some(head.commit_id.clone());
+        }
+        if unit.branch.is_none() {
+            unit.branch = head.branch.clone();
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_repo_is_noop() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(head_for(dir.path()), None);
+    }
+
+    #[cfg(feature = "git-provenance")]
+    #[tokio::test]
+    async fn test_ingest_records_head_commit() {
+        use crate::config::LimitsConfig;
+        use crate::ingest::{ingest_codebase, IngestRegistry};
+        use crate::model::embedding::BagOfWordsEmbedder;
+        use crate::store::graph::GraphStore;
+        use crate::store::transaction::TransactionManager;
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        std::fs::write(dir.path().join("lib.rs"), "pub fn answer() {\n}\n").unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("lib.rs")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Onyx", "onyx@example.com").unwrap();
+        let commit = repo
+            .commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+
+        let units = IngestRegistry::default().parse_path(dir.path()).unwrap();
+        let embedder = BagOfWordsEmbedder::from_corpus(&["pub fn answer"], 10);
+        let mut stores = TransactionManager::new();
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let node = stores
+            .graph_store
+            .get_node(&results[0].node_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.provenance.commit_id, Some(commit.to_string()));
+        assert!(node.provenance.branch.is_some());
+    }
+}
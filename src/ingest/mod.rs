@@ -1,14 +1,29 @@
 use uuid::Uuid;
 
+use crate::config::{LimitsConfig, CONTENT_TRUNCATED_METADATA};
 use crate::error::{OnyxError, OnyxResult};
-use crate::model::edge::{Edge, EdgeType};
+use crate::model::edge::{DetectorKind, Edge, EdgeType};
 use crate::model::embedding::BagOfWordsEmbedder;
 use crate::model::node::{
-    CodeEntityExt, CodeEntityKind, Language, Node, NodeExtension, NodeType, Provenance, Visibility,
+    CodeEntityExt, CodeEntityKind, Language, Node, NodeExtension, NodeType, Provenance, TestExt,
+    TestFramework, Visibility,
 };
-use crate::model::version::VersionEntry;
-use crate::store::graph::GraphStore;
-use crate::store::transaction::{TransactionManager, TransactionOp};
+use crate::store::graph::{ConflictPolicy, NodeUpsert};
+use crate::store::transaction::{TransactionManager, TransactionOp, TransactionStores};
+
+pub mod config_file;
+pub mod demo;
+pub mod detect;
+pub mod git;
+pub mod parser;
+pub mod semantic;
+
+pub use detect::{
+    AstCallDetector, ContentScanDetector, EntityRef, IngestOptions, ModuleHierarchyDetector,
+    RelationshipDetector, TraitImplDetector,
+};
+pub use parser::{IngestRegistry, LanguageParser, PythonParser, RustParser};
+pub use semantic::build_semantic_edges;
 
 // ---------------------------------------------------------------------------
 // Ingestion Engine: parse code artifacts and populate all three stores
@@ -46,32 +61,52 @@ pub struct CodeUnit {
 pub struct IngestResult {
     /// The ID assigned to the ingested node.
     pub node_id: Uuid,
-    /// The version ID of the initial version.
+    /// The version recorded by this ingest, or the current head if unchanged.
     pub version_id: String,
     /// Number of relationships detected.
     pub edges_created: usize,
 }
 
+/// Namespace for deriving stable node IDs from code unit identity.
+const CODE_UNIT_NAMESPACE: Uuid = Uuid::from_u128(0x6f6e_7978_2d63_6f64_652d_756e_6974_0001);
+
+/// Derive a deterministic node ID for a code entity.
+///
+/// The same file path, module path, and name always map to the same ID, so
+/// re-ingesting a codebase updates existing nodes instead of duplicating them.
+pub fn stable_id(file_path: &str, module_path: &[String], name: &str) -> Uuid {
+    let key = format!("{}\0{}\0{}", file_path, module_path.join("::"), name);
+    Uuid::new_v5(&CODE_UNIT_NAMESPACE, key.as_bytes())
+}
+
 /// Ingest a single code unit into the Onyx stores.
 ///
 /// This function:
-/// 1. Creates a Node with type-specific extensions
+/// 1. Creates a Node with type-specific extensions under its [`stable_id`]
 /// 2. Generates an embedding for semantic search
-/// 3. Records an initial version in the history store
-/// 4. Commits all operations atomically via the TransactionManager
+/// 3. Writes the node with
+///    [`upsert_node_by_key`](crate::store::graph::GraphStore::upsert_node_by_key),
+///    which records an initial version, or a `ContentChanged` version against
+///    the branch head if the entity was ingested before with different content
+/// 4. Stores the embedding and points the node's `VersionedBy` edge at the
+///    version in one batch, undoing step 3 if that fails
+///
+/// Re-ingesting an unchanged unit returns the current head without recording
+/// a version; only its provenance is refreshed if the unit moved.
+///
+/// Content over `limits` fails with `OnyxError::ContentTooLarge`, or is
+/// truncated and flagged with [`CONTENT_TRUNCATED_METADATA`] if the limits
+/// allow it.
 pub async fn ingest_code_unit(
-    stores: &mut TransactionManager,
+    stores: &mut impl TransactionStores,
     unit: &CodeUnit,
     embedder: &BagOfWordsEmbedder,
+    limits: &LimitsConfig,
 ) -> OnyxResult<IngestResult> {
-    // 1. Create the node
-    let mut node = Node::new(
-        NodeType::CodeEntity(unit.kind.clone()),
-        &unit.name,
-        &unit.content,
-    );
+    let content = limits.enforce(&unit.content)?;
+    let framework = detect_test_framework(unit);
 
-    // Set provenance
+    // Provenance
     let mut provenance = Provenance::new(&unit.file_path);
     if let Some((start, end)) = unit.line_range {
         provenance = provenance.with_lines(start, end);
@@ -82,37 +117,121 @@ pub async fn ingest_code_unit(
     if let Some(ref branch) = unit.branch {
         provenance = provenance.with_branch(branch);
     }
-    node.provenance = provenance;
 
-    // Set code entity extension
-    node.extension = NodeExtension::CodeEntity(CodeEntityExt {
-        language: unit.language.clone(),
-        signature: unit.signature.clone(),
-        visibility: unit.visibility.clone(),
-        module_path: unit.module_path.clone(),
-        line_range: unit.line_range,
-    });
+    // The test or code entity extension
+    let (node_type, extension) = match framework {
+        Some(framework) => (
+            NodeType::Test,
+            NodeExtension::Test(TestExt {
+                framework,
+                targets: test_targets(&unit.name, content),
+                ..TestExt::default()
+            }),
+        ),
+        None => (
+            NodeType::CodeEntity(unit.kind.clone()),
+            NodeExtension::CodeEntity(CodeEntityExt {
+                language: unit.language.clone(),
+                signature: unit.signature.clone(),
+                visibility: unit.visibility.clone(),
+                module_path: unit.module_path.clone(),
+                line_range: unit.line_range,
+            }),
+        ),
+    };
+
+    // 1. Create the node
+    let mut builder = Node::builder(&unit.name, content)
+        .id(stable_id(&unit.file_path, &unit.module_path, &unit.name))
+        .node_type(node_type)
+        .provenance(provenance)
+        .extension(extension);
+    if content.len() < unit.content.len() {
+        builder = builder.metadata(CONTENT_TRUNCATED_METADATA, unit.content.len().to_string());
+    }
+    let node = builder.build()?;
+
+    record_ingested_node(stores, node, embedder).await
+}
+
+/// Embed `node`, then write and version it.
+///
+/// Steps 2-4 of [`ingest_code_unit`], shared by every kind of ingested node.
+/// The version goes on the node's provenance branch and commit. If storing
+/// the embedding fails, the node and its version are put back as they were.
+pub(crate) async fn record_ingested_node(
+    stores: &mut impl TransactionStores,
+    mut node: Node,
+    embedder: &BagOfWordsEmbedder,
+) -> OnyxResult<IngestResult> {
+    let branch = node.provenance.branch.as_deref().unwrap_or("main");
+    let existing = stores.graph().find_node_by_key(&node).await?;
+
+    // Unchanged since the last ingest: at most the location moved.
+    if let Some(ref previous) = existing {
+        if previous.content_hash == node.content_hash {
+            let head = stores.history().get_head(&previous.id, branch).await?;
+            if location_changed(previous, &node) {
+                stores
+                    .execute(TransactionOp::UpdateNode(relocated(previous, node)))
+                    .await?;
+            }
+            return Ok(IngestResult {
+                node_id: previous.id,
+                version_id: head
+                    .or(previous.current_version.clone())
+                    .unwrap_or_default(),
+                edges_created: 0,
+            });
+        }
+    }
 
     // 2. Generate embedding
-    let embedding = embedder.embed(&unit.content);
+    let embedding = embedder.embed(&node.content);
     node.embedding = Some(embedding.values.clone());
 
-    // 3. Create initial version
-    let version =
-        VersionEntry::initial(node.id, &unit.content).with_message(format!("Ingest {}", unit.name));
+    // 3. Write the node and record a version: initial for new entities, a
+    //    content change against the branch head otherwise
+    let upsert = stores
+        .upsert_node_by_key(node, ConflictPolicy::RecordVersion)
+        .await?;
+    let node_id = upsert.node.id;
+    let version_id = match &upsert.version {
+        Some(version) => version.version_id.clone(),
+        None => return Err(OnyxError::Internal(format!("No version for {}", node_id))),
+    };
 
-    let node_id = node.id;
-    let version_id = version.version_id.clone();
+    let mut ops = Vec::with_capacity(4);
+    let mut versioned_by = Edge::versioned_by(node_id, &version_id);
+    if upsert.previous.is_some() {
+        if stores.vector().get(&node_id).await?.is_some() {
+            ops.push(TransactionOp::DeleteEmbedding(node_id));
+        }
+        let previous = stores
+            .graph()
+            .find_edge(&node_id, &node_id, &EdgeType::VersionedBy)
+            .await?;
+        if let Some(previous) = previous {
+            versioned_by.id = previous.id;
+            ops.push(TransactionOp::RemoveEdge(previous.id));
+        }
+    }
+    ops.push(TransactionOp::InsertEdge(versioned_by));
+    ops.push(TransactionOp::InsertEmbedding {
+        id: node_id,
+        embedding: embedding.values,
+    });
 
-    // 4. Commit atomically
-    stores.execute_batch(vec![
-        TransactionOp::InsertNode(node),
-        TransactionOp::InsertEmbedding {
-            id: node_id,
-            embedding: embedding.values,
-        },
-        TransactionOp::RecordVersion(version),
-    ]).await?;
+    // 4. Commit the embedding and version edge atomically
+    if let Err(err) = stores.execute_batch(ops).await {
+        return Err(match undo_upsert(stores, &upsert).await {
+            Ok(()) => err,
+            Err(undo) => OnyxError::TransactionFailed(format!(
+                "Ingest of {} failed: {}. Undoing its node write also failed: {}",
+                node_id, err, undo
+            )),
+        });
+    }
 
     Ok(IngestResult {
         node_id,
@@ -121,122 +240,382 @@ pub async fn ingest_code_unit(
     })
 }
 
+/// Put back the node and branch head that `upsert` replaced.
+async fn undo_upsert(stores: &mut impl TransactionStores, upsert: &NodeUpsert) -> OnyxResult<()> {
+    match &upsert.previous {
+        Some(previous) => {
+            stores
+                .execute(TransactionOp::UpdateNode(previous.clone()))
+                .await?
+        }
+        None => {
+            stores
+                .execute(TransactionOp::RemoveNode(upsert.node.id))
+                .await?
+        }
+    }
+    if let Some(version) = &upsert.version {
+        stores
+            .history()
+            .remove_version(&version.version_id, version.parent_version.clone())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Whether `node` was found at a different file, line range or commit than
+/// `previous`.
+fn location_changed(previous: &Node, node: &Node) -> bool {
+    let (old, new) = (&previous.provenance, &node.provenance);
+    old.file_path != new.file_path
+        || old.line_range != new.line_range
+        || old.commit_id != new.commit_id
+}
+
+/// `previous` with the location of `node`, keeping its version and embedding.
+fn relocated(previous: &Node, node: Node) -> Node {
+    let mut moved = previous.clone();
+    if let (NodeExtension::CodeEntity(ext), NodeExtension::CodeEntity(new)) =
+        (&mut moved.extension, &node.extension)
+    {
+        ext.line_range = new.line_range;
+    }
+    moved.provenance = node.provenance;
+    moved.updated_at = node.updated_at;
+    moved
+}
+
 /// Ingest multiple code units and automatically detect relationships between them.
 ///
 /// After ingesting all units, this function scans for:
 /// - Import relationships (based on module path references in content)
 /// - Call relationships (based on function name references in content)
 /// - Contains relationships (based on module path hierarchy)
+///
+/// Relationships that already exist in the graph are not inserted again.
+/// Each unit's content is checked against `limits`; see [`ingest_code_unit`].
 pub async fn ingest_codebase(
-    stores: &mut TransactionManager,
+    stores: &mut impl TransactionStores,
     units: &[CodeUnit],
     embedder: &BagOfWordsEmbedder,
+    limits: &LimitsConfig,
+) -> OnyxResult<Vec<IngestResult>> {
+    ingest_codebase_with_options(stores, units, embedder, limits, &IngestOptions::default()).await
+}
+
+/// [`ingest_codebase`] with the relationship detectors chosen by `options`.
+#[tracing::instrument(skip_all, fields(units = units.len()))]
+pub async fn ingest_codebase_with_options(
+    stores: &mut impl TransactionStores,
+    units: &[CodeUnit],
+    embedder: &BagOfWordsEmbedder,
+    limits: &LimitsConfig,
+    options: &IngestOptions,
 ) -> OnyxResult<Vec<IngestResult>> {
     let mut results = Vec::new();
 
     // Phase 1: Ingest all code units
     for unit in units {
-        let result = ingest_code_unit(stores, unit, embedder).await?;
+        let result = ingest_code_unit(stores, unit, embedder, limits).await?;
         results.push(result);
     }
 
     // Phase 2: Detect relationships
     let node_ids: Vec<Uuid> = results.iter().map(|r| r.node_id).collect();
-    let mut edges_created = 0;
+    let edges_created = link_ingested(stores, &node_ids, options).await?;
 
-    // Build a lookup of name -> node_id for relationship detection
-    let mut name_to_id: std::collections::HashMap<String, Uuid> = std::collections::HashMap::new();
-    for &id in &node_ids {
-        if let Some(node) = stores.graph_store.get_node(&id).await? {
-            name_to_id.insert(node.name.clone(), id);
-        }
+    // Update edge counts in results
+    for result in &mut results {
+        result.edges_created = edges_created;
     }
+    tracing::info!(nodes = results.len(), edges_created, "ingest complete");
 
-    // Detect calls and imports by scanning content for references to other entities
-    for &id in &node_ids {
-        let (content, _name) = {
-            let node = stores
-                .graph_store
-                .get_node(&id)
-                .await?
-                .ok_or(OnyxError::NodeNotFound(id))?;
-            (node.content.clone(), node.name.clone())
-        };
+    Ok(results)
+}
 
-        for (ref_name, ref_id) in &name_to_id {
-            if *ref_id == id {
-                continue; // Skip self-references
-            }
+/// Phase 2 of [`ingest_codebase`]: detect relationships among the freshly
+/// ingested `node_ids`, then link config and dependency nodes to the code
+/// that uses them. Returns the number of new edges.
+pub async fn link_ingested(
+    stores: &mut impl TransactionStores,
+    node_ids: &[Uuid],
+    options: &IngestOptions,
+) -> OnyxResult<usize> {
+    let mut edges_created = detect_relationships_with_options(stores, node_ids, options).await?;
+    edges_created += config_file::link_config_references(stores).await?;
+    edges_created += config_file::link_dependencies(stores).await?;
+    Ok(edges_created)
+}
 
-            // Check if this node's content references another node by name
-            // This is a simple heuristic; production would use AST analysis
-            if content.contains(ref_name.as_str()) {
-                // Determine if it's a call or import based on context
-                let edge_type = if content.contains("use ") || content.contains("mod ") {
-                    EdgeType::Imports
-                } else {
-                    EdgeType::Calls
-                };
+/// A node that an ingest would write.
+#[derive(Debug, Clone)]
+pub struct PlannedNode {
+    pub node_id: Uuid,
+    pub name: String,
+    pub file_path: String,
+}
 
-                let edge = Edge::new(edge_type, id, *ref_id)
-                    .with_confidence(0.8) // Heuristic-based, not AST-confirmed
-                    .with_metadata("detection", "content_scan");
+/// What [`ingest_codebase`] would do, computed without mutating any store.
+#[derive(Debug, Clone, Default)]
+pub struct IngestPlan {
+    /// Entities not yet in the graph.
+    pub new_nodes: Vec<PlannedNode>,
+    /// Entities already in the graph whose content changed.
+    pub updated_nodes: Vec<PlannedNode>,
+    /// Entities already in the graph with identical content.
+    pub unchanged_nodes: Vec<PlannedNode>,
+    /// Edges that would be added, with their inferred types and confidences.
+    pub detected_edges: Vec<Edge>,
+}
 
-                stores.execute(TransactionOp::InsertEdge(edge)).await?;
-                edges_created += 1;
+/// Preview an ingest of `units` without writing to the stores.
+///
+/// The embedder is accepted for parity with [`ingest_codebase`]; planning does
+/// not need embeddings.
+pub async fn ingest_codebase_plan(
+    stores: &TransactionManager,
+    units: &[CodeUnit],
+    _embedder: &BagOfWordsEmbedder,
+) -> OnyxResult<IngestPlan> {
+    let mut plan = IngestPlan::default();
+
+    let mut entities = Vec::with_capacity(units.len());
+    for unit in units {
+        let node_id = stable_id(&unit.file_path, &unit.module_path, &unit.name);
+        let planned = PlannedNode {
+            node_id,
+            name: unit.name.clone(),
+            file_path: unit.file_path.clone(),
+        };
+        match stores.graph().get_node(&node_id).await? {
+            None => plan.new_nodes.push(planned),
+            Some(existing) if existing.content == unit.content => {
+                plan.unchanged_nodes.push(planned)
             }
+            Some(_) => plan.updated_nodes.push(planned),
         }
+        entities.push(EntityRef {
+            id: node_id,
+            name: &unit.name,
+            content: &unit.content,
+            module_path: &unit.module_path,
+            test_targets: detect_test_framework(unit)
+                .map(|_| test_targets(&unit.name, &unit.content)),
+        });
     }
 
-    // Detect contains relationships based on module path hierarchy
-    for i in 0..node_ids.len() {
-        for j in 0..node_ids.len() {
-            if i == j {
-                continue;
-            }
+    for edge in IngestOptions::default().infer_edges(&entities) {
+        let exists = stores
+            .graph()
+            .find_edge(&edge.source_id, &edge.target_id, &edge.edge_type)
+            .await?
+            .map_or(false, |existing| existing.is_active());
+        if !exists {
+            plan.detected_edges.push(edge);
+        }
+    }
 
-            let (path_i, path_j) = {
-                let node_i = stores
-                    .graph_store
-                    .get_node(&node_ids[i])
-                    .await?
-                    .ok_or(OnyxError::NodeNotFound(node_ids[i]))?;
-                let node_j = stores
-                    .graph_store
-                    .get_node(&node_ids[j])
-                    .await?
-                    .ok_or(OnyxError::NodeNotFound(node_ids[j]))?;
-
-                let pi = match &node_i.extension {
-                    NodeExtension::CodeEntity(ext) => ext.module_path.clone(),
-                    _ => Vec::new(),
-                };
-                let pj = match &node_j.extension {
-                    NodeExtension::CodeEntity(ext) => ext.module_path.clone(),
-                    _ => Vec::new(),
-                };
-                (pi, pj)
-            };
+    Ok(plan)
+}
 
-            // Check if node_i's module path is a prefix of node_j's
-            if !path_i.is_empty() && path_j.len() == path_i.len() + 1 && path_j.starts_with(&path_i)
-            {
-                let edge = Edge::new(EdgeType::Contains, node_ids[i], node_ids[j])
-                    .with_confidence(1.0)
-                    .with_metadata("detection", "module_hierarchy");
+/// Detect relationships among the given nodes and insert them as edges.
+///
+/// Edges that already exist (same source, target, and type) are not
+/// duplicated; see [`upsert_edge`]. Inferred edges between these nodes that
+/// are no longer detected are closed rather than deleted, so time-travel
+/// queries still see them. Returns the number of new edges.
+///
+/// Each node and its outbound edges are read once up front; detection runs
+/// in memory and all resulting writes are applied as one batch.
+pub async fn detect_relationships(
+    stores: &mut impl TransactionStores,
+    node_ids: &[Uuid],
+) -> OnyxResult<usize> {
+    detect_relationships_with_options(stores, node_ids, &IngestOptions::default()).await
+}
+
+/// [`detect_relationships`] running the detectors configured in `options`.
+/// Only edges recorded by those detectors are closed when they vanish.
+pub async fn detect_relationships_with_options(
+    stores: &mut impl TransactionStores,
+    node_ids: &[Uuid],
+    options: &IngestOptions,
+) -> OnyxResult<usize> {
+    let mut nodes = Vec::with_capacity(node_ids.len());
+    let mut existing = Vec::new();
+    for &id in node_ids {
+        let node = stores
+            .graph()
+            .get_node(&id)
+            .await?
+            .ok_or(OnyxError::NodeNotFound(id))?;
+        nodes.push(node);
+        let outbound = stores.graph().get_neighbors(&id, None).await?;
+        existing.extend(outbound.into_iter().map(|(edge, _)| edge));
+    }
+
+    let entities: Vec<EntityRef<'_>> = nodes.iter().map(EntityRef::from_node).collect();
+    let edges = options.infer_edges(&entities);
+    let detected: std::collections::HashSet<(Uuid, Uuid, EdgeType)> = edges
+        .iter()
+        .map(|e| (e.source_id, e.target_id, e.edge_type.clone()))
+        .collect();
+
+    let (mut ops, edges_created) = plan_edge_upserts(&existing, edges);
+    let kinds = options.detector_kinds();
+    ops.extend(close_vanished_edges(node_ids, &existing, &detected, &kinds));
+    if !ops.is_empty() {
+        stores.execute_batch(ops).await?;
+    }
 
-                stores.execute(TransactionOp::InsertEdge(edge)).await?;
-                edges_created += 1;
+    Ok(edges_created)
+}
+
+/// The writes [`upsert_edge`] would make for each of `edges`, decided
+/// against the already loaded `existing` edges. `edges` must not repeat a
+/// (source, target, type) key, which [`IngestOptions::infer_edges`] guarantees. Returns the
+/// operations and the number of new edges.
+fn plan_edge_upserts(existing: &[Edge], edges: Vec<Edge>) -> (Vec<TransactionOp>, usize) {
+    let mut active: std::collections::HashMap<(Uuid, Uuid, EdgeType), &Edge> =
+        std::collections::HashMap::new();
+    for edge in existing.iter().filter(|e| e.is_active()) {
+        active
+            .entry((edge.source_id, edge.target_id, edge.edge_type.clone()))
+            .or_insert(edge);
+    }
+
+    let mut ops = Vec::new();
+    let mut created = 0;
+    for edge in edges {
+        let key = (edge.source_id, edge.target_id, edge.edge_type.clone());
+        match active.get(&key) {
+            None => {
+                ops.push(TransactionOp::InsertEdge(edge));
+                created += 1;
+            }
+            Some(existing)
+                if existing.is_corroborated_by(&edge) || edge.confidence > existing.confidence =>
+            {
+                ops.push(TransactionOp::RemoveEdge(existing.id));
+                ops.push(TransactionOp::InsertEdge(existing.reinforced(&edge)));
             }
+            Some(_) => {}
         }
     }
+    (ops, created)
+}
 
-    // Update edge counts in results
-    for result in &mut results {
-        result.edges_created = edges_created;
+/// Operations closing the active edges among `node_ids` that one of `kinds`
+/// found earlier but that are not in `detected`. `existing` holds the
+/// outbound edges of `node_ids`.
+fn close_vanished_edges(
+    node_ids: &[Uuid],
+    existing: &[Edge],
+    detected: &std::collections::HashSet<(Uuid, Uuid, EdgeType)>,
+    kinds: &[DetectorKind],
+) -> Vec<TransactionOp> {
+    let batch: std::collections::HashSet<Uuid> = node_ids.iter().copied().collect();
+    let now = chrono::Utc::now();
+
+    let mut ops = Vec::new();
+    for edge in existing {
+        let inferred = edge.detector().map_or(false, |d| kinds.contains(d));
+        let key = (edge.source_id, edge.target_id, edge.edge_type.clone());
+        if inferred
+            && edge.is_active()
+            && batch.contains(&edge.target_id)
+            && !detected.contains(&key)
+        {
+            ops.push(TransactionOp::RemoveEdge(edge.id));
+            ops.push(TransactionOp::InsertEdge(edge.clone().valid_until(now)));
+        }
     }
+    ops
+}
 
-    Ok(results)
+/// Insert an edge unless an equivalent one already exists.
+///
+/// When an edge with the same source, target, and type is present, the new
+/// detection is folded into it as in [`crate::store::graph::GraphStore::upsert_edge`], but through
+/// the transaction manager. Re-running a detector leaves the edge unchanged.
+/// Returns `true` if a new edge was inserted.
+pub async fn upsert_edge(stores: &mut impl TransactionStores, edge: Edge) -> OnyxResult<bool> {
+    let existing = stores
+        .graph()
+        .find_edge(&edge.source_id, &edge.target_id, &edge.edge_type)
+        .await?
+        .filter(Edge::is_active);
+
+    match existing {
+        None => {
+            stores.execute(TransactionOp::InsertEdge(edge)).await?;
+            Ok(true)
+        }
+        Some(existing)
+            if existing.is_corroborated_by(&edge) || edge.confidence > existing.confidence =>
+        {
+            let id = existing.id;
+            stores
+                .execute_batch(vec![
+                    TransactionOp::RemoveEdge(id),
+                    TransactionOp::InsertEdge(existing.reinforced(&edge)),
+                ])
+                .await?;
+            Ok(false)
+        }
+        Some(_) => Ok(false),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Test detection
+// ---------------------------------------------------------------------------
+
+/// The framework a unit is a test for, or `None` if it is not a test.
+pub fn detect_test_framework(unit: &CodeUnit) -> Option<TestFramework> {
+    let content = &unit.content;
+    match unit.language {
+        Language::Rust if content.contains("#[tokio::test") => Some(TestFramework::Tokio),
+        Language::Rust if content.contains("#[test]") => Some(TestFramework::RustTest),
+        Language::Python if unit.name.starts_with("test_") => Some(TestFramework::Pytest),
+        Language::Go if unit.name.starts_with("Test") && content.contains("*testing.T") => {
+            Some(TestFramework::GoTesting)
+        }
+        _ => None,
+    }
+}
+
+/// Keywords that look like calls when followed by `(`.
+const CALL_KEYWORDS: &[&str] = &[
+    "fn", "func", "def", "if", "elif", "while", "for", "match", "return", "in", "Some", "Ok",
+    "Err",
+];
+
+/// Names of the functions called in a test body: identifiers directly
+/// followed by `(`, excluding the test itself. Macros like `assert_eq!` are
+/// skipped since the `!` separates them from their arguments.
+pub fn test_targets(test_name: &str, content: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for (index, _) in content.match_indices('(') {
+        let before = &content[..index];
+        let start = before
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| c.is_alphanumeric() || c == '_')
+            .last()
+            .map_or(index, |(i, _)| i);
+        let name = &before[start..];
+        if name.is_empty()
+            || name.starts_with(|c: char| c.is_ascii_digit())
+            || name == test_name
+            || CALL_KEYWORDS.contains(&name)
+            || targets.iter().any(|t| t == name)
+        {
+            continue;
+        }
+        targets.push(name.to_string());
+    }
+    targets
 }
 
 /// A simplified Rust source parser that extracts basic function information.
@@ -249,7 +628,6 @@ pub async fn ingest_codebase(
 /// - Use `syn` crate for proper Rust parsing
 /// - Support struct, enum, trait, impl parsing
 /// - Extract doc comments as Doc nodes
-/// - Extract test functions as Test nodes
 pub fn parse_rust_source(source: &str, file_path: &str) -> Vec<CodeUnit> {
     let mut units = Vec::new();
     let lines: Vec<&str> = source.lines().collect();
@@ -265,15 +643,15 @@ pub fn parse_rust_source(source: &str, file_path: &str) -> Vec<CodeUnit> {
                 || line.starts_with("    pub")
                 || line.starts_with("    fn"))
         {
-            let start_line = i + 1; // 1-indexed
-
             // Extract function name
             if let Some(fn_name) = extract_fn_name(line) {
                 // Find the end of the function (matching braces)
                 let end_line = find_block_end(&lines, i);
-                let content = lines[i..=end_line].join("\n");
 
-                let is_test = i > 0 && lines[i - 1].trim().contains("#[test]");
+                // Keep a test attribute with the function so ingestion sees it
+                let is_test = i > 0 && is_test_attribute(lines[i - 1]);
+                let first_line = if is_test { i - 1 } else { i };
+                let content = lines[first_line..=end_line].join("\n");
                 let visibility = if line.contains("pub") {
                     Visibility::Public
                 } else {
@@ -292,7 +670,7 @@ pub fn parse_rust_source(source: &str, file_path: &str) -> Vec<CodeUnit> {
                     kind,
                     language: Language::Rust,
                     file_path: file_path.to_string(),
-                    line_range: Some((start_line, end_line + 1)),
+                    line_range: Some((first_line + 1, end_line + 1)), // 1-indexed
                     signature: Some(extract_signature(line)),
                     visibility,
                     module_path: Vec::new(), // Caller can set this
@@ -311,6 +689,12 @@ pub fn parse_rust_source(source: &str, file_path: &str) -> Vec<CodeUnit> {
     units
 }
 
+/// Whether `line` is a `#[test]` or `#[tokio::test]` style attribute.
+fn is_test_attribute(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("#[") && (line.contains("test]") || line.contains("test("))
+}
+
 /// Extract function name from a line like "pub fn my_func(args) -> RetType {"
 fn extract_fn_name(line: &str) -> Option<&str> {
     let fn_idx = line.find("fn ")?;
@@ -358,6 +742,7 @@ fn find_block_end(lines: &[&str], start: usize) -> usize {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::store::graph::GraphStore;
     use crate::store::history::HistoryStore;
     use crate::store::vector::VectorStore;
 
@@ -381,8 +766,8 @@ fn helper() -> bool {
         assert_eq!(units[1].visibility, Visibility::Private);
     }
 
-    #[test]
-    fn test_ingest_code_unit() {
+    #[tokio::test]
+    async fn test_ingest_code_unit() {
         let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub struct use mod crate"], 20);
         let mut stores = TransactionManager::new();
 
@@ -401,28 +786,81 @@ fn helper() -> bool {
             branch: Some("main".to_string()),
         };
 
-        let result = ingest_code_unit(&mut stores, &unit, &embedder).unwrap();
+        let result = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
         assert!(!result.version_id.is_empty());
 
         // Verify node was stored
         let node = stores
             .graph_store
             .get_node(&result.node_id)
+            .await
             .unwrap()
             .unwrap();
         assert_eq!(node.name, "calculate_total");
 
         // Verify embedding was stored
-        let emb = stores.vector_store.get(&result.node_id).unwrap();
+        let emb = stores.vector_store.get(&result.node_id).await.unwrap();
         assert!(emb.is_some());
 
         // Verify version was stored
-        let versions = stores.history_store.list_versions(&result.node_id).unwrap();
+        let versions = stores
+            .history_store
+            .list_versions(&result.node_id)
+            .await
+            .unwrap();
         assert_eq!(versions.len(), 1);
     }
 
-    #[test]
-    fn test_ingest_codebase_with_relationships() {
+    #[tokio::test]
+    async fn test_content_size_limit() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub struct use mod crate"], 20);
+        let mut stores = TransactionManager::new();
+        let limits = LimitsConfig {
+            max_node_content_bytes: 16,
+            truncate_oversized_content: false,
+        };
+        let unit = |name: &str, content: &str| CodeUnit {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/lib.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec![],
+            commit_id: None,
+            branch: None,
+        };
+
+        let at_limit = unit("fits", &"x".repeat(16));
+        ingest_code_unit(&mut stores, &at_limit, &embedder, &limits)
+            .await
+            .unwrap();
+
+        let oversized = unit("too_big", &"x".repeat(17));
+        let err = ingest_code_unit(&mut stores, &oversized, &embedder, &limits)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, OnyxError::ContentTooLarge { size: 17, limit: 16 }));
+        assert_eq!(stores.graph_store.node_count().await, 1);
+
+        let truncating = LimitsConfig {
+            truncate_oversized_content: true,
+            ..limits
+        };
+        let result = ingest_code_unit(&mut stores, &oversized, &embedder, &truncating)
+            .await
+            .unwrap();
+        let node = stores.graph_store.get_node(&result.node_id).await.unwrap().unwrap();
+        assert_eq!(node.content.len(), 16);
+        assert_eq!(node.metadata[CONTENT_TRUNCATED_METADATA], "17");
+    }
+
+    #[tokio::test]
+    async fn test_ingest_codebase_with_relationships() {
         let embedder = BagOfWordsEmbedder::from_corpus(
             &["fn pub calculate_total apply_discount items price"],
             20,
@@ -458,11 +896,744 @@ fn helper() -> bool {
             },
         ];
 
-        let results = ingest_codebase(&mut stores, &units, &embedder).unwrap();
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
         assert_eq!(results.len(), 2);
 
         // Should have detected the call relationship
-        assert!(stores.graph_store.edge_count() > 0);
+        assert!(stores.graph_store.edge_count().await > 0);
+        let call = stores
+            .graph_store
+            .find_edge(&results[0].node_id, &results[1].node_id, &EdgeType::Calls)
+            .await
+            .unwrap()
+            .expect("calls edge");
+        assert_eq!(call.detector(), Some(&DetectorKind::ContentScan));
+    }
+
+    #[tokio::test]
+    async fn test_imports_require_a_matching_use() {
+        use CodeEntityKind::{Function, Module};
+        let unit = |name: &str, content: &str, kind, module: &str| CodeUnit {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind,
+            language: Language::Rust,
+            file_path: format!("src/{module}.rs"),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec![module.to_string()],
+            commit_id: None,
+            branch: None,
+        };
+        // `render` imports the billing module and calls a function of its own.
+        let units = vec![
+            unit("billing", "pub mod billing {}", Module, "billing"),
+            unit("format_line", "fn format_line() {}", Function, "report"),
+            unit(
+                "render",
+                "use crate::billing;\n\nfn render() { format_line(); }",
+                Function,
+                "report",
+            ),
+        ];
+        let embedder = BagOfWordsEmbedder::from_corpus(&["billing render format_line"], 20);
+        let mut stores = TransactionManager::new();
+
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let (billing, format_line, render) =
+            (results[0].node_id, results[1].node_id, results[2].node_id);
+
+        let edges = stores
+            .graph_store
+            .get_neighbors(&render, None)
+            .await
+            .unwrap();
+        let types: Vec<(EdgeType, Uuid)> = edges
+            .iter()
+            .map(|(edge, _)| (edge.edge_type.clone(), edge.target_id))
+            .collect();
+        assert_eq!(types.len(), 2);
+        assert!(types.contains(&(EdgeType::Imports, billing)));
+        assert!(types.contains(&(EdgeType::Calls, format_line)));
+    }
+
+    #[tokio::test]
+    async fn test_trait_impl_creates_implements_edge() {
+        use CodeEntityKind::{Function, Impl, Struct, Trait};
+        let unit = |name: &str, content: &str, kind| CodeUnit {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind,
+            language: Language::Rust,
+            file_path: "src/invoice.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["invoice".to_string()],
+            commit_id: None,
+            branch: None,
+        };
+        let units = vec![
+            unit("Invoice", "pub struct Invoice { total: f64 }", Struct),
+            unit("Summary", "pub trait Summary { fn summarize(&self) -> String; }", Trait),
+            unit(
+                "impl Summary for Invoice",
+                "impl Summary for Invoice {\n    fn summarize(&self) -> String { self.total.to_string() }\n}",
+                Impl,
+            ),
+            unit(
+                "summarize",
+                "fn summarize(&self) -> String { self.total.to_string() }",
+                Function,
+            ),
+        ];
+        let embedder = BagOfWordsEmbedder::from_corpus(&["invoice summary summarize"], 20);
+        let mut stores = TransactionManager::new();
+
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = results.iter().map(|r| r.node_id).collect();
+
+        let implements = stores
+            .graph_store
+            .find_edge(&ids[0], &ids[1], &EdgeType::Implements)
+            .await
+            .unwrap()
+            .expect("implements edge");
+        assert_eq!(implements.detector(), Some(&DetectorKind::TraitImpl));
+        let defines = stores
+            .graph_store
+            .find_edge(&ids[2], &ids[3], &EdgeType::Defines)
+            .await
+            .unwrap();
+        assert!(defines.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_ingest_with_ast_detector_only() {
+        let source = r#"
+pub fn total(items: &[f64]) -> f64 {
+    items.iter().sum()
+}
+
+pub fn checkout(items: &[f64]) -> f64 {
+    total(items) * 1.2
+}
+
+pub fn checkout_total() -> f64 {
+    0.0
+}
+"#;
+        let units = parse_rust_source(source, "src/billing.rs");
+        let embedder = BagOfWordsEmbedder::from_corpus(&[source], 20);
+        let mut stores = TransactionManager::new();
+        let options = IngestOptions::new().with_detector(AstCallDetector);
+
+        let results = ingest_codebase_with_options(
+            &mut stores,
+            &units,
+            &embedder,
+            &LimitsConfig::default(),
+            &options,
+        )
+        .await
+        .unwrap();
+        let id = |name: &str| {
+            let i = units.iter().position(|u| u.name == name).unwrap();
+            results[i].node_id
+        };
+
+        // Besides each node's VersionedBy edge, only the real call is linked;
+        // the name inside `checkout_total` is not.
+        assert_eq!(stores.graph_store.edge_count().await, 1 + results.len());
+        let call = stores
+            .graph_store
+            .find_edge(&id("checkout"), &id("total"), &EdgeType::Calls)
+            .await
+            .unwrap()
+            .expect("calls edge");
+        assert_eq!(call.detector(), Some(&DetectorKind::Ast));
+    }
+
+    #[tokio::test]
+    async fn test_test_targets_create_certain_tests_of_edges() {
+        let source = r#"
+pub fn apply_discount(price: f64, rate: f64) -> f64 {
+    price * (1.0 - rate)
+}
+
+#[test]
+fn test_apply_discount() {
+    assert_eq!(apply_discount(100.0, 0.5), 50.0);
+}
+"#;
+        let units = parse_rust_source(source, "src/billing.rs");
+        assert!(units[1].content.starts_with("#[test]"));
+        assert_eq!(detect_test_framework(&units[1]), Some(TestFramework::RustTest));
+
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn price rate apply_discount"], 20);
+        let mut stores = TransactionManager::new();
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let (function_id, test_id) = (results[0].node_id, results[1].node_id);
+
+        let test = stores.graph_store.get_node(&test_id).await.unwrap().unwrap();
+        assert_eq!(test.node_type, NodeType::Test);
+        match &test.extension {
+            NodeExtension::Test(ext) => assert_eq!(ext.targets, ["apply_discount"]),
+            other => panic!("expected a test extension, got {:?}", other),
+        }
+
+        let edge = stores
+            .graph_store
+            .find_edge(&test_id, &function_id, &EdgeType::TestsOf)
+            .await
+            .unwrap()
+            .expect("tests_of edge");
+        assert_eq!(edge.confidence, 1.0);
+        assert_eq!(edge.detector(), Some(&DetectorKind::TestTarget));
+
+        let covering = crate::query::find_covering_tests(&stores, &function_id, 1)
+            .await
+            .unwrap();
+        assert_eq!(covering.len(), 1);
+        assert_eq!(covering[0].node_id, test_id);
+    }
+
+    #[tokio::test]
+    async fn test_relationship_detection_is_idempotent() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub caller callee"], 20);
+        let mut stores = TransactionManager::new();
+
+        let units = vec![
+            CodeUnit {
+                name: "caller".to_string(),
+                content: "pub fn caller() { callee(); }".to_string(),
+                kind: CodeEntityKind::Function,
+                language: Language::Rust,
+                file_path: "src/lib.rs".to_string(),
+                line_range: Some((1, 1)),
+                signature: None,
+                visibility: Visibility::Public,
+                module_path: vec!["lib".to_string()],
+                commit_id: None,
+                branch: None,
+            },
+            CodeUnit {
+                name: "callee".to_string(),
+                content: "pub fn callee() {}".to_string(),
+                kind: CodeEntityKind::Function,
+                language: Language::Rust,
+                file_path: "src/lib.rs".to_string(),
+                line_range: Some((3, 3)),
+                signature: None,
+                visibility: Visibility::Public,
+                module_path: vec!["lib".to_string()],
+                commit_id: None,
+                branch: None,
+            },
+        ];
+
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let edges_after_first = stores.graph_store.edge_count().await;
+        assert!(edges_after_first > 0);
+
+        let node_ids: Vec<Uuid> = results.iter().map(|r| r.node_id).collect();
+        async fn confidences(stores: &TransactionManager) -> Vec<(Uuid, f64)> {
+            let mut confidences = Vec::new();
+            for id in stores.graph_store.get_all_edge_ids().await.unwrap() {
+                let edge = stores.graph_store.get_edge(&id).await.unwrap().unwrap();
+                confidences.push((id, edge.confidence));
+            }
+            confidences.sort_by_key(|(id, _)| *id);
+            confidences
+        }
+        let before = confidences(&stores).await;
+        let created = detect_relationships(&mut stores, &node_ids).await.unwrap();
+        assert_eq!(created, 0);
+        assert_eq!(stores.graph_store.edge_count().await, edges_after_first);
+        // Repeated detection by the same detector doesn't inflate confidence.
+        assert_eq!(confidences(&stores).await, before);
+    }
+
+    #[tokio::test]
+    async fn test_batched_detection_matches_per_edge_upserts() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total tax invoice"], 20);
+        let unit = |name: &str, content: &str, module_path: &[&str]| CodeUnit {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: module_path.iter().map(|s| s.to_string()).collect(),
+            commit_id: None,
+            branch: None,
+        };
+        let units = vec![
+            unit("bill", "pub mod bill;", &["bill"]),
+            unit("tax", "fn tax(x: f64) -> f64 { x }", &["bill", "tax"]),
+            unit("total", "fn total() { tax(1.0) }", &["bill", "total"]),
+            unit("invoice", "use bill::total; fn invoice() {}", &["invoice"]),
+            unit("test_tax", "#[test] fn test_tax() { tax(1.0); }", &["bill"]),
+        ];
+
+        let mut batched = TransactionManager::new();
+        let mut reference = TransactionManager::new();
+        let mut node_ids = Vec::new();
+        for unit in &units {
+            let result = ingest_code_unit(&mut batched, unit, &embedder, &LimitsConfig::default())
+                .await
+                .unwrap();
+            ingest_code_unit(&mut reference, unit, &embedder, &LimitsConfig::default())
+                .await
+                .unwrap();
+            node_ids.push(result.node_id);
+        }
+
+        async fn edge_set(stores: &TransactionManager) -> Vec<((Uuid, Uuid, String), u64)> {
+            let mut edges = Vec::new();
+            for id in stores.graph_store.get_all_edge_ids().await.unwrap() {
+                let edge = stores.graph_store.get_edge(&id).await.unwrap().unwrap();
+                let edge_type = format!("{:?}", edge.edge_type);
+                let key = (edge.source_id, edge.target_id, edge_type);
+                edges.push((key, edge.confidence.to_bits()));
+            }
+            edges.sort();
+            edges
+        }
+
+        // Run twice so the second pass exercises the reinforcement path.
+        for _ in 0..2 {
+            detect_relationships(&mut batched, &node_ids).await.unwrap();
+
+            // The per-edge path: one lookup and one write per detected edge.
+            let mut nodes = Vec::new();
+            for id in &node_ids {
+                nodes.push(reference.graph_store.get_node(id).await.unwrap().unwrap());
+            }
+            let entities: Vec<EntityRef<'_>> = nodes.iter().map(EntityRef::from_node).collect();
+            for edge in IngestOptions::default().infer_edges(&entities) {
+                upsert_edge(&mut reference, edge).await.unwrap();
+            }
+
+            let expected = edge_set(&reference).await;
+            assert!(!expected.is_empty());
+            assert_eq!(edge_set(&batched).await, expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_versioned_by_edge_follows_head_version() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total items sum"], 20);
+        let mut stores = TransactionManager::new();
+        let mut unit = CodeUnit {
+            name: "total".to_string(),
+            content: "pub fn total(items: &[f64]) -> f64 { items.iter().sum() }".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: Some((1, 1)),
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["billing".to_string()],
+            commit_id: None,
+            branch: None,
+        };
+
+        async fn versioned_by(stores: &TransactionManager, id: Uuid) -> Vec<Edge> {
+            let edges = stores
+                .graph_store
+                .get_neighbors(&id, Some(&[EdgeType::VersionedBy]))
+                .await
+                .unwrap();
+            edges.into_iter().map(|(edge, _)| edge).collect()
+        }
+
+        let first = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let edges = versioned_by(&stores, first.node_id).await;
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].target_id, first.node_id);
+        assert_eq!(edges[0].head_version(), Some(first.version_id.as_str()));
+
+        unit.content = "pub fn total(items: &[f64]) -> f64 { items.iter().copied().sum() }".into();
+        let second = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let moved = versioned_by(&stores, first.node_id).await;
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].id, edges[0].id);
+        assert_eq!(moved[0].head_version(), Some(second.version_id.as_str()));
+    }
+
+    #[tokio::test]
+    async fn test_reingest_changed_unit_records_new_version() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total items sum"], 20);
+        let mut stores = TransactionManager::new();
+
+        let mut unit = CodeUnit {
+            name: "total".to_string(),
+            content: "pub fn total(items: &[f64]) -> f64 {\n    items.iter().sum()\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: Some((1, 3)),
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["billing".to_string()],
+            commit_id: None,
+            branch: None,
+        };
+
+        let first = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            first.node_id,
+            stable_id("src/billing.rs", &unit.module_path, "total")
+        );
+
+        unit.content =
+            "pub fn total(items: &[f64]) -> f64 {\n    items.iter().copied().sum()\n}".to_string();
+        let second = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        assert_eq!(second.node_id, first.node_id);
+        assert_ne!(second.version_id, first.version_id);
+        assert_eq!(stores.graph_store.node_count().await, 1);
+
+        let versions = stores
+            .history_store
+            .list_versions(&first.node_id)
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].parent_version.as_ref(), Some(&first.version_id));
+
+        // Re-ingesting identical content does not record another version.
+        let third = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(third.version_id, second.version_id);
+        let versions = stores
+            .history_store
+            .list_versions(&first.node_id)
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_embedding_undoes_node_and_version() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total items sum"], 20);
+        let wider = BagOfWordsEmbedder::from_corpus(&["fn pub total items sum"], 30);
+        let mut stores = TransactionManager::new();
+        stores.vector_store = crate::store::vector::InMemoryVectorStore::with_dimensions(20);
+
+        let mut unit = CodeUnit {
+            name: "total".to_string(),
+            content: "pub fn total(items: &[f64]) -> f64 { items.iter().sum() }".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: Some((1, 1)),
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["billing".to_string()],
+            commit_id: None,
+            branch: None,
+        };
+        let first = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        // A re-ingest whose embedding is rejected leaves the first version.
+        let original = unit.content.clone();
+        unit.content = "pub fn total(items: &[f64]) -> f64 { 0.0 }".to_string();
+        let failed = ingest_code_unit(&mut stores, &unit, &wider, &LimitsConfig::default()).await;
+        assert!(failed.is_err());
+        let node = stores.graph().get_node(&first.node_id).await.unwrap();
+        assert_eq!(node.unwrap().content, original);
+        let history = stores.history();
+        let versions = history.list_versions(&first.node_id).await.unwrap();
+        assert_eq!(versions.len(), 1);
+        let head = history.get_head(&first.node_id, "main").await.unwrap();
+        assert_eq!(head, Some(first.version_id));
+
+        // So does a first ingest.
+        unit.name = "count".to_string();
+        let failed = ingest_code_unit(&mut stores, &unit, &wider, &LimitsConfig::default()).await;
+        assert!(failed.is_err());
+        assert_eq!(stores.graph_store.node_count().await, 1);
+        assert_eq!(stores.history_store.version_count().await, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reingest_reads_back_from_database() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total items sum"], 20);
+        let db = std::sync::Arc::new(crate::db::OnyxDatabase::new_memory().await.unwrap());
+        let mut stores = TransactionManager::with_database(db);
+        let mut unit = CodeUnit {
+            name: "total".to_string(),
+            content: "pub fn total(items: &[f64]) -> f64 { items.iter().sum() }".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec![],
+            commit_id: None,
+            branch: None,
+        };
+
+        let first = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        unit.content =
+            "pub fn total(items: &[f64]) -> f64 { items.iter().copied().sum() }".to_string();
+        let second = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        // The second ingest saw the first in SurrealDB and recorded a change
+        // on top of it rather than a second initial version.
+        let versions = stores
+            .history()
+            .list_versions(&first.node_id)
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].version_id, second.version_id);
+        assert_eq!(versions[1].parent_version.as_ref(), Some(&first.version_id));
+        assert_eq!(stores.graph().node_count().await, 1);
+        assert_eq!(stores.graph_store.node_count().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reingest_closes_vanished_relationships() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total discount"], 20);
+        let mut stores = TransactionManager::new();
+        let unit = |name: &str, content: &str| CodeUnit {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec![],
+            commit_id: None,
+            branch: None,
+        };
+        let mut units = vec![
+            unit("total", "pub fn total() { discount(); }"),
+            unit("discount", "pub fn discount() {}"),
+        ];
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let (total, discount) = (results[0].node_id, results[1].node_id);
+        let call = stores
+            .graph_store
+            .find_edge(&total, &discount, &EdgeType::Calls)
+            .await
+            .unwrap()
+            .unwrap();
+        let while_calling = chrono::Utc::now();
+
+        units[0] = unit("total", "pub fn total() {}");
+        ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let closed = stores.graph_store.get_edge(&call.id).await.unwrap().unwrap();
+        assert!(!closed.is_active());
+        let then = stores
+            .graph_store
+            .edges_at_time(&total, &while_calling)
+            .await
+            .unwrap();
+        assert!(then.iter().any(|e| e.id == call.id));
+        let now = stores
+            .graph_store
+            .edges_at_time(&total, &chrono::Utc::now())
+            .await
+            .unwrap();
+        assert!(now.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_impact_ignores_relationships_closed_by_reingest() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total discount"], 20);
+        let mut stores = TransactionManager::new();
+        let unit = |name: &str, content: &str| CodeUnit {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec![],
+            commit_id: None,
+            branch: None,
+        };
+        let mut units = vec![
+            unit("total", "pub fn total() { discount(); }"),
+            unit("discount", "pub fn discount() {}"),
+        ];
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let (total, discount) = (results[0].node_id, results[1].node_id);
+        let impacted = crate::query::impact_analysis(&stores, &discount, 3)
+            .await
+            .unwrap();
+        assert!(impacted.iter().any(|(id, _, _)| *id == total));
+
+        units[0] = unit("total", "pub fn total() {}");
+        ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let impacted = crate::query::impact_analysis(&stores, &discount, 3)
+            .await
+            .unwrap();
+        assert!(impacted.iter().all(|(id, _, _)| *id != total));
+        let callers = stores
+            .graph_store
+            .get_active_inbound(&discount, Some(&[EdgeType::Calls]))
+            .await
+            .unwrap();
+        assert!(callers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reingest_moved_unit_updates_line_range() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub total items sum"], 20);
+        let mut stores = TransactionManager::new();
+
+        let mut unit = CodeUnit {
+            name: "total".to_string(),
+            content: "pub fn total(items: &[f64]) -> f64 {\n    items.iter().sum()\n}".to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: Some((10, 15)),
+            signature: None,
+            visibility: Visibility::Public,
+            module_path: vec!["billing".to_string()],
+            commit_id: Some("aaa111".to_string()),
+            branch: None,
+        };
+        let first = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        unit.line_range = Some((20, 25));
+        unit.commit_id = Some("bbb222".to_string());
+        let second = ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(second.node_id, first.node_id);
+        assert_eq!(second.version_id, first.version_id);
+
+        let node = stores
+            .graph_store
+            .get_node(&first.node_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(node.provenance.line_range, Some((20, 25)));
+        assert_eq!(node.provenance.commit_id.as_deref(), Some("bbb222"));
+        assert_eq!(node.current_version.as_ref(), Some(&first.version_id));
+        match &node.extension {
+            NodeExtension::CodeEntity(ext) => assert_eq!(ext.line_range, Some((20, 25))),
+            other => panic!("expected a code entity extension, got {:?}", other),
+        }
+
+        let versions = stores
+            .history_store
+            .list_versions(&first.node_id)
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 1);
+        assert!(stores.vector_store.get(&first.node_id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_plan_matches_real_ingest() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn pub caller callee"], 20);
+        let mut stores = TransactionManager::new();
+
+        let units = vec![
+            CodeUnit {
+                name: "caller".to_string(),
+                content: "pub fn caller() { callee(); }".to_string(),
+                kind: CodeEntityKind::Function,
+                language: Language::Rust,
+                file_path: "src/lib.rs".to_string(),
+                line_range: Some((1, 1)),
+                signature: None,
+                visibility: Visibility::Public,
+                module_path: vec!["lib".to_string()],
+                commit_id: None,
+                branch: None,
+            },
+            CodeUnit {
+                name: "callee".to_string(),
+                content: "pub fn callee() {}".to_string(),
+                kind: CodeEntityKind::Function,
+                language: Language::Rust,
+                file_path: "src/lib.rs".to_string(),
+                line_range: Some((3, 3)),
+                signature: None,
+                visibility: Visibility::Public,
+                module_path: vec!["lib".to_string()],
+                commit_id: None,
+                branch: None,
+            },
+        ];
+
+        let plan = ingest_codebase_plan(&stores, &units, &embedder)
+            .await
+            .unwrap();
+        assert_eq!(plan.new_nodes.len(), 2);
+        assert!(plan.updated_nodes.is_empty());
+        assert_eq!(stores.graph_store.node_count().await, 0);
+
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        assert_eq!(plan.detected_edges.len(), results[0].edges_created);
+        // Every node also gets a VersionedBy edge.
+        assert_eq!(
+            plan.detected_edges.len() + results.len(),
+            stores.graph_store.edge_count().await
+        );
+
+        let replan = ingest_codebase_plan(&stores, &units, &embedder)
+            .await
+            .unwrap();
+        assert_eq!(replan.unchanged_nodes.len(), 2);
+        assert!(replan.detected_edges.is_empty());
     }
 
     #[test]
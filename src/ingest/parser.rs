@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::error::{OnyxError, OnyxResult};
+use crate::ingest::git::apply_git_provenance;
+use crate::ingest::{parse_rust_source, CodeUnit};
+use crate::model::node::{CodeEntityKind, Language, Visibility};
+
+// ---------------------------------------------------------------------------
+// Language parsers: turn source files into code units
+// ---------------------------------------------------------------------------
+
+/// Extracts code units from source text in one language.
+pub trait LanguageParser: Send + Sync {
+    /// The language this parser handles.
+    fn language(&self) -> Language;
+
+    /// File extensions (without the dot) this parser accepts.
+    fn extensions(&self) -> &[&'static str];
+
+    /// Parse `source`, read from `path`, into code units.
+    fn parse(&self, source: &str, path: &str) -> Vec<CodeUnit>;
+}
+
+/// Parser for Rust source files; see [`parse_rust_source`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RustParser;
+
+impl LanguageParser for RustParser {
+    fn language(&self) -> Language {
+        Language::Rust
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["rs"]
+    }
+
+    fn parse(&self, source: &str, path: &str) -> Vec<CodeUnit> {
+        parse_rust_source(source, path)
+    }
+}
+
+/// Indentation-based parser for Python `def` and `class` definitions.
+///
+/// Classes are recorded as [`CodeEntityKind::Struct`]. Methods are extracted
+/// as functions in addition to being part of their class's content.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PythonParser;
+
+impl LanguageParser for PythonParser {
+    fn language(&self) -> Language {
+        Language::Python
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["py"]
+    }
+
+    fn parse(&self, source: &str, path: &str) -> Vec<CodeUnit> {
+        let lines: Vec<&str> = source.lines().collect();
+        let mut units = Vec::new();
+
+        for (i, line) in lines.iter().enumerate() {
+            let trimmed = line.trim_start();
+            let (kind, rest) = if let Some(rest) = trimmed.strip_prefix("class ") {
+                (CodeEntityKind::Struct, rest)
+            } else if let Some(rest) = trimmed
+                .strip_prefix("def ")
+                .or_else(|| trimmed.strip_prefix("async def "))
+            {
+                (CodeEntityKind::Function, rest)
+            } else {
+                continue;
+            };
+
+            let name_end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let name = &rest[..name_end];
+            if name.is_empty() {
+                continue;
+            }
+
+            let end = find_indented_block_end(&lines, i);
+            let visibility = if name.starts_with('_') {
+                Visibility::Private
+            } else {
+                Visibility::Public
+            };
+
+            units.push(CodeUnit {
+                name: name.to_string(),
+                content: lines[i..=end].join("\n"),
+                kind,
+                language: Language::Python,
+                file_path: path.to_string(),
+                line_range: Some((i + 1, end + 1)),
+                signature: Some(trimmed.trim_end().trim_end_matches(':').to_string()),
+                visibility,
+                module_path: Vec::new(), // Caller can set this
+                commit_id: None,
+                branch: None,
+            });
+        }
+
+        units
+    }
+}
+
+/// Find the last line of the block opened at `start`.
+///
+/// The block ends before the next non-blank line indented no deeper than the
+/// header line.
+fn find_indented_block_end(lines: &[&str], start: usize) -> usize {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let header_indent = indent(lines[start]);
+
+    let mut end = start;
+    for (i, line) in lines.iter().enumerate().skip(start + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indent(line) <= header_indent {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+// ---------------------------------------------------------------------------
+// IngestRegistry: dispatch to parsers by file extension
+// ---------------------------------------------------------------------------
+
+/// Maps file extensions to the parser that handles them.
+#[derive(Clone)]
+pub struct IngestRegistry {
+    parsers: HashMap<String, Arc<dyn LanguageParser>>,
+}
+
+impl IngestRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self {
+            parsers: HashMap::new(),
+        }
+    }
+
+    /// Register a parser for each of its extensions, replacing any previous one.
+    pub fn register(&mut self, parser: impl LanguageParser + 'static) {
+        let parser: Arc<dyn LanguageParser> = Arc::new(parser);
+        for ext in parser.extensions() {
+            self.parsers.insert(ext.to_string(), parser.clone());
+        }
+    }
+
+    /// Builder-style variant of [`register`](Self::register).
+    pub fn with_parser(mut self, parser: impl LanguageParser + 'static) -> Self {
+        self.register(parser);
+        self
+    }
+
+    /// The parser registered for `path`'s extension, if any.
+    pub fn parser_for(&self, path: &Path) -> Option<&dyn LanguageParser> {
+        let ext = path.extension()?.to_str()?;
+        self.parsers.get(ext).map(|parser| parser.as_ref())
+    }
+
+    /// Whether a parser is registered for `path`'s extension.
+    pub fn supports(&self, path: &Path) -> bool {
+        self.parser_for(path).is_some()
+    }
+
+    /// Parse already-loaded source using the parser for `path`.
+    pub fn parse_source(&self, source: &str, path: &Path) -> OnyxResult<Vec<CodeUnit>> {
+        let parser = self.parser_for(path).ok_or_else(|| {
+            OnyxError::IngestionError(format!("No parser registered for {}", path.display()))
+        })?;
+        Ok(parser.parse(source, &path.to_string_lossy()))
+    }
+
+    /// Read and parse a source file.
+    pub fn parse_file(&self, path: &Path) -> OnyxResult<Vec<CodeUnit>> {
+        let source = std::fs::read_to_string(path)?;
+        self.parse_source(&source, path)
+    }
+
+    /// Parse a single file, or every supported file under a directory.
+    ///
+    /// Directories are walked recursively, skipping hidden files and anything
+    /// excluded by `.gitignore`. Each unit's module path is derived from its
+    /// file's location relative to `path`, e.g. `billing/invoice.rs` yields
+    /// `["billing", "invoice"]`.
+    ///
+    /// Inside a git repository, units also get the HEAD commit and branch; see
+    /// [`apply_git_provenance`].
+    pub fn parse_path(&self, path: &Path) -> OnyxResult<Vec<CodeUnit>> {
+        let mut units = self.parse_path_inner(path)?;
+        apply_git_provenance(path, &mut units);
+        Ok(units)
+    }
+
+    fn parse_path_inner(&self, path: &Path) -> OnyxResult<Vec<CodeUnit>> {
+        if !path.is_dir() {
+            return self.parse_file(path);
+        }
+
+        let walker = ignore::WalkBuilder::new(path)
+            .require_git(false)
+            .sort_by_file_name(|a, b| a.cmp(b))
+            .build();
+
+        let mut units = Vec::new();
+        for entry in walker {
+            let entry = entry.map_err(|e| {
+                OnyxError::IngestionError(format!("Failed to walk {}: {}", path.display(), e))
+            })?;
+            let file = entry.path();
+            if !entry.file_type().is_some_and(|t| t.is_file()) || !self.supports(file) {
+                continue;
+            }
+
+            let module_path = module_path_for(path, file);
+            for mut unit in self.parse_file(file)? {
+                unit.module_path = module_path.clone();
+                units.push(unit);
+            }
+        }
+
+        Ok(units)
+    }
+}
+
+/// Module path of `file` relative to `root`: its directories plus file stem.
+fn module_path_for(root: &Path, file: &Path) -> Vec<String> {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    let mut module_path: Vec<String> = relative
+        .parent()
+        .into_iter()
+        .flat_map(|dir| dir.components())
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if let Some(stem) = relative.file_stem() {
+        module_path.push(stem.to_string_lossy().into_owned());
+    }
+    module_path
+}
+
+impl Default for IngestRegistry {
+    /// A registry with the built-in Rust and Python parsers.
+    fn default() -> Self {
+        Self::new()
+            .with_parser(RustParser)
+            .with_parser(PythonParser)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LimitsConfig;
+    use crate::ingest::ingest_codebase;
+    use crate::model::edge::EdgeType;
+    use crate::model::embedding::BagOfWordsEmbedder;
+    use crate::store::graph::GraphStore;
+    use crate::store::transaction::TransactionManager;
+
+    #[test]
+    fn test_python_parser_extracts_functions_and_classes() {
+        let source = r#"
+import math
+
+def area(radius):
+    return math.pi * radius ** 2
+
+class Circle:
+    def __init__(self, radius):
+        self.radius = radius
+
+    def area(self):
+        return area(self.radius)
+
+async def _fetch():
+    pass
+"#;
+
+        let units = PythonParser.parse(source, "shapes.py");
+        let names: Vec<&str> = units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["area", "Circle", "__init__", "area", "_fetch"]);
+
+        let circle = &units[1];
+        assert_eq!(circle.kind, CodeEntityKind::Struct);
+        assert_eq!(circle.line_range, Some((7, 12)));
+        assert!(circle.content.contains("return area(self.radius)"));
+
+        assert_eq!(units[0].signature.as_deref(), Some("def area(radius)"));
+        assert_eq!(units[0].language, Language::Python);
+        assert_eq!(units[4].visibility, Visibility::Private);
+    }
+
+    #[test]
+    fn test_registry_dispatches_by_extension() {
+        let registry = IngestRegistry::default();
+
+        let py = registry
+            .parse_source("def main():\n    pass\n", Path::new("app/main.py"))
+            .unwrap();
+        assert_eq!(py.len(), 1);
+        assert_eq!(py[0].language, Language::Python);
+
+        let rs = registry
+            .parse_source("fn main() {\n}\n", Path::new("src/main.rs"))
+            .unwrap();
+        assert_eq!(rs.len(), 1);
+        assert_eq!(rs[0].language, Language::Rust);
+
+        assert!(!registry.supports(Path::new("README.md")));
+        assert!(matches!(
+            registry.parse_source("", Path::new("README.md")),
+            Err(OnyxError::IngestionError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_directory_ingest_detects_cross_file_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("util")).unwrap();
+        std::fs::write(
+            dir.path().join("app.rs"),
+            "pub fn caller() {\n    callee();\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("util").join("helpers.rs"),
+            "pub fn callee() {\n}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "ignored.rs\n").unwrap();
+        std::fs::write(dir.path().join("ignored.rs"), "fn hidden() {\n}\n").unwrap();
+
+        let units = IngestRegistry::default().parse_path(dir.path()).unwrap();
+        let names: Vec<&str> = units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["caller", "callee"]);
+        assert_eq!(units[1].module_path, ["util", "helpers"]);
+
+        let embedder = BagOfWordsEmbedder::from_corpus(&["pub fn caller callee"], 20);
+        let mut stores = TransactionManager::new();
+        let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let edge = stores
+            .graph_store
+            .find_edge(&results[0].node_id, &results[1].node_id, &EdgeType::Calls)
+            .await
+            .unwrap();
+        assert!(edge.is_some());
+    }
+}
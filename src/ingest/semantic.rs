@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+
+use crate::error::OnyxResult;
+use crate::model::edge::{DetectorKind, Edge, EdgeType};
+use crate::store::transaction::{TransactionManager, TransactionOp};
+
+// ---------------------------------------------------------------------------
+// Semantic edges: a k-nearest-neighbor graph over node embeddings
+// ---------------------------------------------------------------------------
+
+/// Link every node to its `k` nearest embedding neighbors with
+/// [`EdgeType::SimilarTo`] edges, skipping neighbors less similar than
+/// `min_sim`. Each edge's confidence is the cosine similarity.
+///
+/// Existing `SimilarTo` edges are replaced, so the graph reflects the current
+/// embeddings; the swap happens in one transaction. Returns the number of
+/// edges written.
+pub async fn build_semantic_edges(
+    stores: &mut TransactionManager,
+    k: usize,
+    min_sim: f32,
+) -> OnyxResult<usize> {
+    // Embeddings whose node is gone or soft-deleted never become neighbors.
+    let mut live = Vec::new();
+    let mut exclude = HashSet::new();
+    for id in stores.vector().get_all_embedding_ids().await? {
+        if stores.graph().get_node(&id).await?.is_some() {
+            live.push(id);
+        } else {
+            exclude.insert(id);
+        }
+    }
+
+    let mut edges = Vec::new();
+    for id in live {
+        let embedding = match stores.vector().get(&id).await? {
+            Some(embedding) => embedding,
+            None => continue,
+        };
+        exclude.insert(id);
+        let neighbors = stores
+            .vector()
+            .search_excluding(&embedding, k, &exclude)
+            .await?;
+        exclude.remove(&id);
+
+        for (neighbor, similarity) in neighbors {
+            if similarity < min_sim {
+                break; // Results are sorted by descending similarity
+            }
+            edges.push(
+                Edge::new(EdgeType::SimilarTo, id, neighbor)
+                    .with_confidence(similarity as f64)
+                    .detected_by(DetectorKind::Embedding),
+            );
+        }
+    }
+
+    let created = edges.len();
+    let mut ops: Vec<TransactionOp> = stores
+        .graph()
+        .edges_by_type(&EdgeType::SimilarTo)
+        .await
+        .into_iter()
+        .map(|edge| TransactionOp::RemoveEdge(edge.id))
+        .collect();
+    ops.extend(edges.into_iter().map(TransactionOp::InsertEdge));
+    stores.execute_batch(ops)?;
+
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::store::graph::GraphStore;
+    use uuid::Uuid;
+
+    async fn insert(stores: &mut TransactionManager, name: &str, embedding: Vec<f32>) -> Uuid {
+        let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "");
+        let id = node.id;
+        stores
+            .execute_batch(vec![
+                TransactionOp::InsertNode(node),
+                TransactionOp::InsertEmbedding { id, embedding },
+            ])
+            .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_semantic_edges_connect_clusters_only() {
+        let mut stores = TransactionManager::new();
+        let billing = [
+            insert(&mut stores, "total", vec![1.0, 0.1, 0.0, 0.0]).await,
+            insert(&mut stores, "discount", vec![0.9, 0.2, 0.0, 0.0]).await,
+            insert(&mut stores, "tax", vec![1.0, 0.0, 0.1, 0.0]).await,
+        ];
+        let auth = [
+            insert(&mut stores, "login", vec![0.0, 0.0, 1.0, 0.1]).await,
+            insert(&mut stores, "logout", vec![0.0, 0.1, 0.9, 0.2]).await,
+            insert(&mut stores, "session", vec![0.0, 0.0, 1.0, 0.0]).await,
+        ];
+
+        let created = build_semantic_edges(&mut stores, 2, 0.5).await.unwrap();
+        assert_eq!(created, 6 * 2);
+
+        for cluster in [&billing, &auth] {
+            for id in cluster.iter() {
+                let neighbors = stores
+                    .graph_store
+                    .get_neighbors(id, Some(&[EdgeType::SimilarTo]))
+                    .await
+                    .unwrap();
+                let mut targets: Vec<Uuid> = neighbors.iter().map(|(_, n)| n.id).collect();
+                let mut expected: Vec<Uuid> = cluster.iter().copied().filter(|c| c != id).collect();
+                targets.sort();
+                expected.sort();
+                assert_eq!(targets, expected);
+                for (edge, _) in &neighbors {
+                    assert!(edge.edge_type.is_semantic() && edge.confidence >= 0.5);
+                }
+            }
+        }
+
+        // Structural traversals don't see the semantic edges.
+        let structural = stores
+            .graph_store
+            .get_neighbors(&billing[0], Some(&EdgeType::structural()))
+            .await
+            .unwrap();
+        assert!(structural.is_empty());
+
+        // Rebuilding replaces rather than duplicates.
+        build_semantic_edges(&mut stores, 2, 0.5).await.unwrap();
+        assert_eq!(
+            stores.graph_store.edges_by_type(&EdgeType::SimilarTo).await.len(),
+            12
+        );
+    }
+}
@@ -1,3 +1,4 @@
+pub mod analysis;
 pub mod config;
 pub mod db;
 pub mod error;
@@ -8,6 +9,6 @@ pub mod server;
 pub mod store;
 
 pub use db::{DatabaseConfig, DatabaseEndpoint, OnyxDatabase};
-pub use error::{OnyxError, OnyxResult};
+pub use error::{ErrorCode, OnyxError, OnyxResult, StorageError};
 pub use model::*;
-pub use config::{AppConfig, PaymentsConfig, ServerConfig};
+pub use config::{AppConfig, AuthConfig, CorsConfig, PaymentsConfig, ServerConfig};
@@ -1,22 +1,32 @@
 use clap::{Parser, Subcommand};
 use std::io::{self, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-
-use onyx::error::OnyxResult;
-use onyx::ingest::{ingest_codebase, parse_rust_source, CodeUnit};
+use tokio_util::sync::CancellationToken;
+
+use onyx::analysis::{
+    pagerank_in, DEFAULT_DAMPING, DEFAULT_PAGERANK_ITERATIONS, IMPORTANCE_EDGE_TYPES,
+};
+use onyx::db::DatabaseConfig;
+use onyx::error::{OnyxError, OnyxResult};
+use onyx::ingest::demo::build_synthetic_codebase;
+use onyx::ingest::{ingest_codebase, ingest_codebase_plan, CodeUnit, IngestPlan, IngestRegistry};
 use onyx::model::edge::EdgeType;
 use onyx::model::embedding::BagOfWordsEmbedder;
 use onyx::model::node::NodeType;
-use onyx::query::{execute_query, find_covering_tests, impact_analysis, QueryOptions};
-use onyx::config::load_config;
-use onyx::server::run_http_server;
-use onyx::store::benchmark::BenchmarkRunner;
+use onyx::model::version::VersionEntry;
+use onyx::query::{
+    api, execute_query, find_covering_tests, impact_analysis, QueryOptions, QueryStores,
+};
+use onyx::config::{load_config, load_limits, AppConfig, LimitsConfig};
+use onyx::server::{bind_http, AppState};
+use onyx::store::benchmark::{BenchmarkRunner, OutputFormat};
 use onyx::store::crash_recovery::CrashTestRunner;
-use onyx::store::graph::GraphStore;
-use onyx::store::history::HistoryStore;
+use onyx::store::graph::{GraphStore, MatchMode, DEFAULT_MAX_EDIT_DISTANCE};
+use onyx::store::history::{diff_versions, merge_conflicts, HistoryStore};
 use onyx::store::migration::run_migration;
-use onyx::store::transaction::TransactionManager;
+use onyx::store::persistent::{load_embedder, open_stores, save_embedder};
+use onyx::store::transaction::{AsyncTransactionManager, TransactionManager};
 
 /// Onyx: Graph-Native Vector Memory for AI Agents
 #[derive(Parser)]
@@ -34,9 +44,18 @@ struct Cli {
 enum Commands {
     /// Ingest code artifacts from a file or directory
     Ingest {
-        /// Path to a Rust source file or directory
+        /// Path to a Rust or Python source file, or a directory
         #[arg(short, long)]
         path: PathBuf,
+        /// Print what would be ingested without writing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Path to the RocksDB store to ingest into, created if missing
+        #[arg(short, long)]
+        store: Option<PathBuf>,
+        /// Optional path to a config file for the content limits (defaults to config.toml)
+        #[arg(short, long)]
+        config: Option<PathBuf>,
     },
     /// Run a semantic query against the store
     Query {
@@ -49,6 +68,9 @@ enum Commands {
         /// Number of vector search results
         #[arg(short, long, default_value = "5")]
         top_k: usize,
+        /// Path to the RocksDB store to query
+        #[arg(short, long)]
+        store: Option<PathBuf>,
     },
     /// Traverse the graph from a node
     Traverse {
@@ -61,12 +83,65 @@ enum Commands {
         /// Maximum depth
         #[arg(short, long, default_value = "2")]
         depth: usize,
+        /// Path to the RocksDB store to traverse
+        #[arg(short, long)]
+        store: Option<PathBuf>,
     },
     /// Inspect a specific node
     Inspect {
         /// Node name to inspect
         #[arg(short, long)]
         node: String,
+        /// Path to the RocksDB store to read
+        #[arg(short, long)]
+        store: Option<PathBuf>,
+    },
+    /// Show a unified diff between two versions of a node
+    Diff {
+        /// Node name to diff
+        #[arg(short, long)]
+        node: String,
+        /// Version to diff from (default: the version before --to)
+        #[arg(long)]
+        from: Option<String>,
+        /// Version to diff to (default: the latest version)
+        #[arg(long)]
+        to: Option<String>,
+        /// Path to the RocksDB store to read
+        #[arg(short, long)]
+        store: Option<PathBuf>,
+    },
+    /// Create and list history branches
+    Branch {
+        #[command(subcommand)]
+        action: BranchAction,
+        /// Path to the RocksDB store
+        #[arg(short, long, global = true)]
+        store: Option<PathBuf>,
+    },
+    /// Merge one history branch into another
+    Merge {
+        /// Branch to merge from
+        #[arg(long)]
+        source: String,
+        /// Branch to merge into
+        #[arg(long)]
+        target: String,
+        /// Path to the RocksDB store
+        #[arg(short, long)]
+        store: Option<PathBuf>,
+    },
+    /// List the most central nodes by PageRank
+    Important {
+        /// Number of nodes to list
+        #[arg(long, default_value = "10")]
+        top: usize,
+        /// Edge types to rank over (default: calls,imports,depends_on)
+        #[arg(short, long)]
+        relations: Option<String>,
+        /// Path to the RocksDB store to read
+        #[arg(short, long)]
+        store: Option<PathBuf>,
     },
     /// Show store statistics
     Status,
@@ -80,11 +155,22 @@ enum Commands {
     },
     /// Migrate data between storage backends
     Migrate {
+        /// SurrealDB to migrate from: a server URL such as ws://localhost:8000,
+        /// or an embedded store such as surrealkv://onyx.db
+        #[arg(long)]
+        source: String,
+        /// Root user for a SurrealDB server
+        #[arg(long, requires = "password")]
+        username: Option<String>,
+        /// Root password for a SurrealDB server
+        #[arg(long, requires = "username")]
+        password: Option<String>,
         /// Target storage path for RocksDB
         #[arg(short, long)]
         path: PathBuf,
     },
-    /// Test crash recovery and WAL durability
+    /// Test crash recovery and WAL durability (torn-write scenarios need
+    /// the `test-util` feature)
     TestCrashRecovery {
         /// Database path for testing
         #[arg(short, long)]
@@ -101,17 +187,61 @@ enum Commands {
         /// Concurrency level
         #[arg(long, default_value = "10")]
         concurrency: usize,
+        /// Write results to this file
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Format of the results file: json or csv
+        #[arg(long, default_value = "json")]
+        format: OutputFormat,
     },
     /// Start the HTTP API server
     Serve {
         /// Optional path to a config file (defaults to config.toml)
         #[arg(short, long)]
         config: Option<PathBuf>,
+        /// Address to listen on (overrides server.host)
+        #[arg(long)]
+        host: Option<String>,
+        /// Port to listen on (overrides server.port)
+        #[arg(long)]
+        port: Option<u16>,
+        /// Serve the RocksDB store at this path instead of the configured database
+        #[arg(short, long)]
+        store: Option<PathBuf>,
+        /// Embedding dimension for a store that has no embeddings yet
+        #[arg(long)]
+        dimensions: Option<usize>,
     },
 }
 
+#[derive(Subcommand)]
+enum BranchAction {
+    /// Create a branch forking from a version
+    Create {
+        /// Branch name
+        name: String,
+        /// Version ID to fork from
+        #[arg(long)]
+        from: String,
+    },
+    /// List all branches
+    List,
+}
+
+/// Send diagnostics to stderr, filtered by `RUST_LOG` (default: Onyx at
+/// `info`, dependencies at `warn`), keeping stdout for command output.
+fn init_tracing() {
+    let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("onyx=info,warn"));
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
 #[tokio::main]
 async fn main() {
+    init_tracing();
     let cli = Cli::parse();
 
     match cli.command {
@@ -133,9 +263,21 @@ async fn main() {
             println!("Use 'onyx interactive --demo' to pre-load the demo dataset.");
             println!("Use 'onyx demo' for a non-interactive demo walkthrough.");
         }
-        Commands::Ingest { path } => {
+        Commands::Ingest {
+            path,
+            dry_run,
+            store,
+            config,
+        } => {
             println!("Ingesting from: {}", path.display());
-            if let Err(e) = run_ingest(&path).await {
+            let limits = match load_limits(config.as_deref()) {
+                Ok(limits) => limits,
+                Err(e) => {
+                    eprintln!("Failed to load config: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Err(e) = run_ingest(&path, dry_run, store.as_deref(), &limits).await {
                 eprintln!("Ingestion failed: {}", e);
                 std::process::exit(1);
             }
@@ -144,28 +286,134 @@ async fn main() {
             question,
             depth,
             top_k,
+            store,
         } => {
             println!("Query: '{}' (depth={}, top_k={})", question, depth, top_k);
-            println!("Tip: Use 'onyx interactive --demo' for a session with pre-loaded data.");
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            if let Err(e) = run_store_query(&store, &question, depth, top_k).await {
+                eprintln!("Query failed: {}", e);
+                std::process::exit(1);
+            }
         }
         Commands::Traverse {
             node,
             relations,
             depth,
+            store,
         } => {
             println!(
                 "Traverse from '{}' (relations={:?}, depth={})",
                 node, relations, depth
             );
-            println!("Tip: Use 'onyx interactive --demo' for a session with pre-loaded data.");
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            let edge_types = relations.as_deref().map(parse_edge_types);
+            if let Err(e) = run_store_traverse(&store, &node, edge_types, depth).await {
+                eprintln!("Traverse failed: {}", e);
+                std::process::exit(1);
+            }
         }
-        Commands::Inspect { node } => {
+        Commands::Inspect { node, store } => {
             println!("Inspect node: '{}'", node);
-            println!("Tip: Use 'onyx interactive --demo' for a session with pre-loaded data.");
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            if let Err(e) = run_store_inspect(&store, &node).await {
+                eprintln!("Inspect failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Diff {
+            node,
+            from,
+            to,
+            store,
+        } => {
+            println!("Diff node: '{}'", node);
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            if let Err(e) = run_store_diff(&store, &node, from.as_deref(), to.as_deref()).await {
+                eprintln!("Diff failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Branch { action, store } => {
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            let result = match action {
+                BranchAction::Create { name, from } => {
+                    run_branch_create(&store, &name, &from).await
+                }
+                BranchAction::List => run_branch_list(&store).await,
+            };
+            if let Err(e) = result {
+                eprintln!("Branch failed: {}", e);
+                std::process::exit(1);
+            }
         }
-        Commands::Migrate { path } => {
+        Commands::Merge {
+            source,
+            target,
+            store,
+        } => {
+            println!("Merge branch '{}' into '{}'", source, target);
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            match run_merge(&store, &source, &target).await {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("Merge failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Important {
+            top,
+            relations,
+            store,
+        } => {
+            println!("Most important nodes (top {})", top);
+            let Some(store) = store else {
+                print_store_tip();
+                return;
+            };
+            let edge_types = relations.as_deref().map(parse_edge_types);
+            if let Err(e) = run_important(&store, edge_types, top).await {
+                eprintln!("Important failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Commands::Migrate {
+            source,
+            username,
+            password,
+            path,
+        } => {
             println!("Migrating data to RocksDB at: {}", path.display());
-            if let Err(e) = run_migration(&path.to_string_lossy()).await {
+            let config = match (username, password) {
+                (Some(username), Some(password)) => {
+                    DatabaseConfig::remote(&source, username, password)
+                }
+                _ => DatabaseConfig::local(&source),
+            };
+            let result = match config.connect().await {
+                Ok(db) => run_migration(Arc::new(db), &source, &path.to_string_lossy()).await,
+                Err(e) => Err(OnyxError::Internal(format!("Failed to open SurrealDB: {}", e))),
+            };
+            if let Err(e) = result {
                 eprintln!("Migration failed: {}", e);
                 std::process::exit(1);
             }
@@ -201,7 +449,7 @@ async fn main() {
                 }
             }
         }
-        Commands::Benchmark { path, operations, concurrency } => {
+        Commands::Benchmark { path, operations, concurrency, output, format } => {
             println!("Running performance benchmarks...");
             println!("Database path: {}", path.display());
             println!("Operations: {}", operations);
@@ -213,9 +461,13 @@ async fn main() {
                 warmup_count: operations / 10,
                 measure_memory: true,
                 detailed_latency: true,
+                ..Default::default()
             };
             
-            let runner = BenchmarkRunner::new(config, &path);
+            let mut runner = BenchmarkRunner::new(config, &path);
+            if let Some(output) = output {
+                runner = runner.with_output(output, format);
+            }
             
             match runner.run_all_benchmarks().await {
                 Ok(results) => {
@@ -249,10 +501,28 @@ async fn main() {
                 }
             }
         }
-        Commands::Serve { config } => {
-            let app_config = load_config(config.as_deref())?;
+        Commands::Serve {
+            config,
+            host,
+            port,
+            store,
+            dimensions,
+        } => {
+            let mut app_config = match load_config(config.as_deref()) {
+                Ok(app_config) => app_config,
+                Err(e) => {
+                    eprintln!("Failed to load config: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            if let Some(host) = host {
+                app_config.server.host = host;
+            }
+            if let Some(port) = port {
+                app_config.server.port = port;
+            }
             println!("Starting HTTP API server on {}:{}", app_config.server.host, app_config.server.port);
-            if let Err(e) = run_http_server(app_config).await {
+            if let Err(e) = run_serve(app_config, store.as_deref(), dimensions).await {
                 eprintln!("Server failed: {}", e);
                 std::process::exit(1);
             }
@@ -268,31 +538,21 @@ async fn main() {
 struct Session {
     stores: TransactionManager,
     embedder: Option<BagOfWordsEmbedder>,
+    limits: LimitsConfig,
 }
 
 impl Session {
-    fn new() -> Self {
+    fn new(limits: LimitsConfig) -> Self {
         Self {
             stores: TransactionManager::new(),
             embedder: None,
+            limits,
         }
     }
-
-    /// Rebuild the embedder from all node contents currently in the graph store.
-    #[allow(dead_code)]
-    async fn rebuild_embedder(&mut self) {
-        let all_nodes = self.stores.graph_store.all_nodes().await;
-        if all_nodes.is_empty() {
-            self.embedder = None;
-            return;
-        }
-        let corpus: Vec<&str> = all_nodes.iter().map(|n| n.content.as_str()).collect();
-        self.embedder = Some(BagOfWordsEmbedder::from_corpus(&corpus, 100));
-    }
 }
 
 async fn run_interactive(load_demo: bool) -> OnyxResult<()> {
-    let mut session = Session::new();
+    let mut session = Session::new(load_limits(None)?);
 
     println!("=== Onyx Interactive REPL ===");
     println!("Graph-native vector memory for AI agents.\n");
@@ -343,7 +603,7 @@ async fn run_interactive(load_demo: bool) -> OnyxResult<()> {
             }
             "ingest" => {
                 if args.is_empty() {
-                    println!("  Usage: ingest <path-to-rust-file>");
+                    println!("  Usage: ingest <path-to-file-or-directory>");
                 } else {
                     if let Err(e) = cmd_ingest(&mut session, args).await {
                         eprintln!("  Error: {}", e);
@@ -352,7 +612,7 @@ async fn run_interactive(load_demo: bool) -> OnyxResult<()> {
             }
             "query" | "search" => {
                 if args.is_empty() {
-                    println!("  Usage: query <search terms> [--depth N] [--top-k N]");
+                    println!("  Usage: query <search terms> [--depth N] [--top-k N] [--explain]");
                 } else {
                     if let Err(e) = cmd_query(&session, args).await {
                         eprintln!("  Error: {}", e);
@@ -428,7 +688,7 @@ fn print_help() {
     println!("  Commands:");
     println!("    status              Show store statistics");
     println!("    load-demo           Load the synthetic e-commerce demo dataset");
-    println!("    ingest <path>       Ingest a Rust source file");
+    println!("    ingest <path>       Ingest a Rust or Python file or directory");
     println!("    query <terms>       Semantic search (e.g. 'query payment processing')");
     println!(
         "    traverse <name>     Walk the graph from a node (e.g. 'traverse calculate_total')"
@@ -449,11 +709,28 @@ fn print_help() {
 // REPL commands
 // ---------------------------------------------------------------------------
 
+/// Vocabulary size for a freshly built embedder.
+const VOCAB_SIZE: usize = 100;
+
+/// Embedding dimensions in the REPL, where the vocabulary is not known up
+/// front.
+const REPL_DIMENSIONS: usize = 256;
+
+/// The REPL's embedder. It hashes words into a fixed number of dimensions,
+/// so every ingest in a session embeds into the same space as the nodes
+/// already stored.
+fn repl_embedder() -> BagOfWordsEmbedder {
+    BagOfWordsEmbedder::hashed(REPL_DIMENSIONS)
+}
+
 fn cmd_status(session: &Session) {
     let stats = session.stores.stats();
     println!("  {}", stats);
-    if session.embedder.is_some() {
-        println!("  Embedder: active (bag-of-words, dim=100)");
+    if let Some(embedder) = &session.embedder {
+        println!(
+            "  Embedder: active (hashed bag-of-words, dim={})",
+            embedder.dimensions()
+        );
     } else {
         println!("  Embedder: not initialized (ingest data to build)");
     }
@@ -461,16 +738,15 @@ fn cmd_status(session: &Session) {
 
 async fn load_demo_data(session: &mut Session) -> OnyxResult<()> {
     let units = build_synthetic_codebase();
-    let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
-    let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+    let embedder = session.embedder.get_or_insert_with(repl_embedder);
 
     println!("  Loading {} demo artifacts...", units.len());
-    let results = ingest_codebase(&mut session.stores, &units, &embedder).await?;
+    let results = ingest_codebase(&mut session.stores, &units, embedder, &session.limits).await?;
 
     for result in &results {
         let node = session
             .stores
-            .graph_store
+            .graph()
             .get_node(&result.node_id)
             .await?
             .unwrap();
@@ -479,9 +755,6 @@ async fn load_demo_data(session: &mut Session) -> OnyxResult<()> {
 
     let stats = session.stores.stats();
     println!("  Done. {}", stats);
-
-    // Rebuild embedder with all content
-    session.embedder = Some(embedder);
     Ok(())
 }
 
@@ -492,8 +765,12 @@ async fn cmd_ingest(session: &mut Session, path_str: &str) -> OnyxResult<()> {
         return Ok(());
     }
 
-    let source = std::fs::read_to_string(&path)?;
-    let units = parse_rust_source(&source, &path.to_string_lossy());
+    let registry = IngestRegistry::default();
+    if !path.is_dir() && !registry.supports(&path) {
+        println!("  Unsupported file type: {}", path.display());
+        return Ok(());
+    }
+    let units = registry.parse_path(&path)?;
 
     if units.is_empty() {
         println!("  No code entities found in {}", path.display());
@@ -505,24 +782,14 @@ async fn cmd_ingest(session: &mut Session, path_str: &str) -> OnyxResult<()> {
         println!("    - {} ({:?})", unit.name, unit.kind);
     }
 
-    // Build embedder from current corpus + new content
-    let all_nodes = session.stores.graph_store.all_nodes().await;
-    let mut corpus: Vec<String> = all_nodes.iter().map(|n| n.content.clone()).collect();
-    for unit in &units {
-        corpus.push(unit.content.clone());
-    }
-    let corpus_refs: Vec<&str> = corpus.iter().map(|s| s.as_str()).collect();
-    let embedder = BagOfWordsEmbedder::from_corpus(&corpus_refs, 100);
+    let embedder = session.embedder.get_or_insert_with(repl_embedder);
 
-    let results = ingest_codebase(&mut session.stores, &units, &embedder).await?;
+    let results = ingest_codebase(&mut session.stores, &units, embedder, &session.limits).await?;
 
     println!("  Ingested {} nodes", results.len());
     let stats = session.stores.stats();
     println!("  {}", stats);
 
-    // Update embedder
-    session.embedder = Some(embedder);
-
     Ok(())
 }
 
@@ -536,7 +803,8 @@ async fn cmd_query(session: &Session, args: &str) -> OnyxResult<()> {
     };
 
     // Parse optional flags
-    let mut terms = args.to_string();
+    let explain = args.contains("--explain");
+    let mut terms = args.replace("--explain", "");
     let mut depth: usize = 2;
     let mut top_k: usize = 5;
 
@@ -556,6 +824,199 @@ async fn cmd_query(session: &Session, args: &str) -> OnyxResult<()> {
     }
     let terms = terms.trim();
 
+    print_query(
+        (&session.stores).into(),
+        embedder,
+        terms,
+        depth,
+        top_k,
+        explain,
+    )
+    .await
+}
+
+async fn cmd_traverse(session: &Session, args: &str) -> OnyxResult<()> {
+    // Parse: <node-name> [--depth N] [--relations calls,imports,...]
+    let mut name = args.to_string();
+    let mut depth: usize = 2;
+    let mut edge_types: Option<Vec<EdgeType>> = None;
+
+    if let Some(idx) = name.find("--depth") {
+        let rest = &name[idx + 7..].trim_start();
+        if let Some(val) = rest.split_whitespace().next() {
+            depth = val.parse().unwrap_or(2);
+        }
+        name = name[..idx].to_string();
+    }
+    if let Some(idx) = name.find("--relations") {
+        let rest = &name[idx + 11..].trim_start();
+        if let Some(val) = rest.split_whitespace().next() {
+            edge_types = Some(parse_edge_types(val));
+        }
+        name = name[..idx].to_string();
+    }
+    let name = name.trim();
+
+    print_traversal(
+        (&session.stores).into(),
+        name,
+        edge_types.as_deref(),
+        depth,
+    )
+    .await
+}
+
+async fn cmd_inspect(session: &Session, args: &str) -> OnyxResult<()> {
+    print_node((&session.stores).into(), args.trim()).await
+}
+
+async fn cmd_impact(session: &Session, args: &str) -> OnyxResult<()> {
+    let mut name = args.to_string();
+    let mut depth: usize = 3;
+
+    if let Some(idx) = name.find("--depth") {
+        let rest = &name[idx + 7..].trim_start();
+        if let Some(val) = rest.split_whitespace().next() {
+            depth = val.parse().unwrap_or(3);
+        }
+        name = name[..idx].to_string();
+    }
+    let name = name.trim();
+
+    let Some(node) = find_node_by_name(session.stores.graph(), name).await else {
+        return Ok(());
+    };
+
+    let affected = impact_analysis(&session.stores, &node.id, depth).await?;
+
+    println!("  Impact analysis for '{}' (depth {}):\n", node.name, depth);
+
+    if affected.is_empty() {
+        println!("  No downstream impact detected.");
+    } else {
+        for (_, aff_name, dist) in &affected {
+            let bar = ">".repeat(*dist);
+            println!("  {} {} (distance {})", bar, aff_name, dist);
+        }
+    }
+
+    Ok(())
+}
+
+async fn cmd_tests(session: &Session, args: &str) -> OnyxResult<()> {
+    let name = args.trim();
+    let Some(node) = find_node_by_name(session.stores.graph(), name).await else {
+        return Ok(());
+    };
+
+    let tests = find_covering_tests(&session.stores, &node.id, 2).await?;
+
+    println!("  Tests covering '{}':\n", node.name);
+
+    if tests.is_empty() {
+        println!("  (no tests found)");
+    } else {
+        for t in &tests {
+            println!("  - {} (score: {:.2}, depth: {})", t.name, t.score, t.depth);
+        }
+    }
+
+    Ok(())
+}
+
+fn cmd_list_nodes(session: &Session, filter: &str) {
+    // Note: This should be async but we're keeping it simple for now
+    println!("  (async node listing not yet implemented)");
+}
+
+async fn cmd_list_edges(session: &Session) {
+    let mut total = 0;
+    for et in &EdgeType::ALL {
+        let edges = session.stores.graph().edges_by_type(et).await;
+        if !edges.is_empty() {
+            if total == 0 {
+                println!("  Edges in the graph:\n");
+            }
+            for edge in &edges {
+                let source_name = session
+                    .stores
+                    .graph()
+                    .get_node(&edge.source_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|n| n.name)
+                    .unwrap_or_else(|| "?".to_string());
+                let target_name = session
+                    .stores
+                    .graph()
+                    .get_node(&edge.target_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|n| n.name)
+                    .unwrap_or_else(|| "?".to_string());
+                println!(
+                    "  {} --[{:?}]--> {} (conf: {:.2})",
+                    source_name, edge.edge_type, target_name, edge.confidence
+                );
+                total += 1;
+            }
+        }
+    }
+
+    if total == 0 {
+        println!("  No edges in the store.");
+    } else {
+        println!("\n  {} edge(s) total.", total);
+    }
+}
+
+async fn cmd_history(session: &Session, args: &str) -> OnyxResult<()> {
+    let name = args.trim();
+    let Some(node) = find_node_by_name(session.stores.graph(), name).await else {
+        return Ok(());
+    };
+
+    let versions = session.stores.history().list_versions(&node.id).await?;
+
+    println!(
+        "  Version history for '{}' ({} versions):\n",
+        node.name,
+        versions.len()
+    );
+
+    if versions.is_empty() {
+        println!("  (no versions recorded)");
+    } else {
+        for v in &versions {
+            println!(
+                "  {} | {} | {} | {} | {} lines",
+                &v.version_id[..v.version_id.len().min(16)],
+                v.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                v.author.as_deref().unwrap_or("system"),
+                v.message.as_deref().unwrap_or("(no message)"),
+                v.diff.lines_changed()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Store commands shared by the REPL and the CLI
+// ---------------------------------------------------------------------------
+
+/// Run a semantic query for `terms` and print the ranked results.
+async fn print_query(
+    stores: QueryStores<'_>,
+    embedder: &BagOfWordsEmbedder,
+    terms: &str,
+    depth: usize,
+    top_k: usize,
+    explain: bool,
+) -> OnyxResult<()> {
     let query_embedding = embedder.embed(terms);
     let options = QueryOptions {
         top_k,
@@ -565,7 +1026,8 @@ async fn cmd_query(session: &Session, args: &str) -> OnyxResult<()> {
         ..Default::default()
     };
 
-    let result = execute_query(&session.stores, Some(&query_embedding.values), &options).await?;
+    let (result, trace) =
+        api::execute_query_explain(stores, Some(&query_embedding.values), &options).await?;
 
     println!(
         "  Found {} results ({} nodes examined, {}ms):\n",
@@ -596,41 +1058,26 @@ async fn cmd_query(session: &Session, args: &str) -> OnyxResult<()> {
         }
     }
 
-    Ok(())
-}
-
-async fn cmd_traverse(session: &Session, args: &str) -> OnyxResult<()> {
-    // Parse: <node-name> [--depth N] [--relations calls,imports,...]
-    let mut name = args.to_string();
-    let mut depth: usize = 2;
-    let mut edge_types: Option<Vec<EdgeType>> = None;
-
-    if let Some(idx) = name.find("--depth") {
-        let rest = &name[idx + 7..].trim_start();
-        if let Some(val) = rest.split_whitespace().next() {
-            depth = val.parse().unwrap_or(2);
+    if explain {
+        println!("\n  Explain:");
+        for line in trace.to_string().lines() {
+            println!("    {}", line);
         }
-        name = name[..idx].to_string();
     }
-    if let Some(idx) = name.find("--relations") {
-        let rest = &name[idx + 11..].trim_start();
-        if let Some(val) = rest.split_whitespace().next() {
-            edge_types = Some(parse_edge_types(val));
-        }
-        name = name[..idx].to_string();
-    }
-    let name = name.trim();
 
-    let node = find_node_by_name(&session.stores, name).await;
-    let node = match node {
-        Some(n) => n,
-        None => {
-            println!(
-                "  Node '{}' not found. Use 'nodes' to list available nodes.",
-                name
-            );
-            return Ok(());
-        }
+    Ok(())
+}
+
+/// Walk the graph from the node called `name` and print what it reaches,
+/// followed by its inbound references.
+async fn print_traversal(
+    stores: QueryStores<'_>,
+    name: &str,
+    edge_types: Option<&[EdgeType]>,
+    depth: usize,
+) -> OnyxResult<()> {
+    let Some(node) = find_node_by_name(stores.graph, name).await else {
+        return Ok(());
     };
 
     let node_id = node.id;
@@ -638,14 +1085,10 @@ async fn cmd_traverse(session: &Session, args: &str) -> OnyxResult<()> {
 
     println!("  Traversal from '{}' (depth {}):\n", node_name, depth);
 
-    let traversal = session
-        .stores
-        .graph_store
-        .traverse(&node_id, edge_types.as_deref(), depth)
-        .await?;
+    let traversal = stores.graph.traverse(&node_id, edge_types, depth).await?;
 
     for (nid, d) in &traversal.nodes {
-        if let Some(n) = session.stores.graph_store.get_node(nid).await? {
+        if let Some(n) = stores.graph.get_node(nid).await? {
             let indent = "  ".repeat(*d + 1);
             let marker = if *d == 0 { "*" } else { "-" };
             println!("  {}{} {} (depth {})", indent, marker, n.name, d);
@@ -653,10 +1096,9 @@ async fn cmd_traverse(session: &Session, args: &str) -> OnyxResult<()> {
     }
 
     // Show inbound callers
-    let callers = session
-        .stores
-        .graph_store
-        .get_inbound(&node_id, edge_types.as_deref())
+    let callers = stores
+        .graph
+        .get_active_inbound(&node_id, edge_types)
         .await?;
     if !callers.is_empty() {
         println!("\n  Inbound references to '{}':", node_name);
@@ -668,17 +1110,11 @@ async fn cmd_traverse(session: &Session, args: &str) -> OnyxResult<()> {
     Ok(())
 }
 
-async fn cmd_inspect(session: &Session, args: &str) -> OnyxResult<()> {
-    let name = args.trim();
-    let node = match find_node_by_name(&session.stores, name).await {
-        Some(n) => n,
-        None => {
-            println!(
-                "  Node '{}' not found. Use 'nodes' to list available nodes.",
-                name
-            );
-            return Ok(());
-        }
+/// Print everything known about the node called `name`: its details,
+/// content, edges, version history and embedding.
+async fn print_node(stores: QueryStores<'_>, name: &str) -> OnyxResult<()> {
+    let Some(node) = find_node_by_name(stores.graph, name).await else {
+        return Ok(());
     };
 
     println!("  === {} ===", node.name);
@@ -722,7 +1158,7 @@ async fn cmd_inspect(session: &Session, args: &str) -> OnyxResult<()> {
     }
 
     // Edges out
-    let neighbors = session.stores.graph_store.get_neighbors(&node.id, None).await?;
+    let neighbors = stores.graph.get_active_neighbors(&node.id, None).await?;
     if !neighbors.is_empty() {
         println!("\n  --- Outbound Edges ---");
         for (edge, target) in &neighbors {
@@ -734,7 +1170,7 @@ async fn cmd_inspect(session: &Session, args: &str) -> OnyxResult<()> {
     }
 
     // Edges in
-    let inbound = session.stores.graph_store.get_inbound(&node.id, None).await?;
+    let inbound = stores.graph.get_active_inbound(&node.id, None).await?;
     if !inbound.is_empty() {
         println!("\n  --- Inbound Edges ---");
         for (edge, source) in &inbound {
@@ -746,7 +1182,7 @@ async fn cmd_inspect(session: &Session, args: &str) -> OnyxResult<()> {
     }
 
     // Version history
-    let versions = session.stores.history_store.list_versions(&node.id).await?;
+    let versions = stores.history.list_versions(&node.id).await?;
     if !versions.is_empty() {
         println!("\n  --- Version History ---");
         for v in &versions {
@@ -772,231 +1208,371 @@ async fn cmd_inspect(session: &Session, args: &str) -> OnyxResult<()> {
     Ok(())
 }
 
-async fn cmd_impact(session: &Session, args: &str) -> OnyxResult<()> {
-    let mut name = args.to_string();
-    let mut depth: usize = 3;
+// ---------------------------------------------------------------------------
+// Commands over a store on disk (non-interactive)
+// ---------------------------------------------------------------------------
 
-    if let Some(idx) = name.find("--depth") {
-        let rest = &name[idx + 7..].trim_start();
-        if let Some(val) = rest.split_whitespace().next() {
-            depth = val.parse().unwrap_or(3);
-        }
-        name = name[..idx].to_string();
+fn print_store_tip() {
+    println!("Tip: Pass --store <path> to read a RocksDB store, or use 'onyx interactive --demo' for a session with pre-loaded data.");
+}
+
+/// Open the RocksDB store at `path`, which must already exist.
+async fn open_store(path: &Path) -> OnyxResult<AsyncTransactionManager> {
+    if !path.exists() {
+        return Err(OnyxError::NotFound(format!(
+            "no store at {}",
+            path.display()
+        )));
     }
-    let name = name.trim();
+    open_stores(path, None).await
+}
 
-    let node = match find_node_by_name(&session.stores, name).await {
-        Some(n) => n,
-        None => {
-            println!("  Node '{}' not found.", name);
-            return Ok(());
-        }
+async fn run_store_query(path: &Path, question: &str, depth: usize, top_k: usize) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    let embedder = match load_embedder(path)? {
+        Some(embedder) => embedder,
+        None => match embedder_for(stores.graph_store.as_ref()).await {
+            Some(embedder) => embedder,
+            None => {
+                println!("  Store is empty.");
+                return Ok(());
+            }
+        },
     };
+    print_query((&stores).into(), &embedder, question, depth, top_k, false).await
+}
 
-    let affected = impact_analysis(&session.stores, &node.id, depth).await?;
-
-    println!("  Impact analysis for '{}' (depth {}):\n", node.name, depth);
-
-    if affected.is_empty() {
-        println!("  No downstream impact detected.");
-    } else {
-        for (_, aff_name, dist) in &affected {
-            let bar = ">".repeat(*dist);
-            println!("  {} {} (distance {})", bar, aff_name, dist);
-        }
-    }
+async fn run_store_traverse(
+    path: &Path,
+    name: &str,
+    edge_types: Option<Vec<EdgeType>>,
+    depth: usize,
+) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    print_traversal((&stores).into(), name, edge_types.as_deref(), depth).await
+}
 
-    Ok(())
+async fn run_store_inspect(path: &Path, name: &str) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    print_node((&stores).into(), name).await
 }
 
-async fn cmd_tests(session: &Session, args: &str) -> OnyxResult<()> {
-    let name = args.trim();
-    let node = match find_node_by_name(&session.stores, name).await {
-        Some(n) => n,
-        None => {
-            println!("  Node '{}' not found.", name);
-            return Ok(());
-        }
+async fn run_store_diff(
+    path: &Path,
+    name: &str,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    let Some(node) = find_node_by_name(stores.graph_store.as_ref(), name).await else {
+        return Ok(());
     };
+    let versions = stores.history_store.list_versions(&node.id).await?;
 
-    let tests = find_covering_tests(&session.stores, &node.id, 2).await?;
-
-    println!("  Tests covering '{}':\n", node.name);
+    let to = match to {
+        Some(id) => resolve_version(&versions, id)?,
+        None => versions.len().checked_sub(1).ok_or_else(|| {
+            OnyxError::VersionNotFound(format!("'{}' has no versions", node.name))
+        })?,
+    };
+    let from = match from {
+        Some(id) => resolve_version(&versions, id)?,
+        None => to.checked_sub(1).ok_or_else(|| {
+            OnyxError::VersionNotFound(format!(
+                "'{}' has no version before {}",
+                node.name, versions[to].version_id
+            ))
+        })?,
+    };
+    let (from, to) = (&versions[from].version_id, &versions[to].version_id);
 
-    if tests.is_empty() {
-        println!("  (no tests found)");
+    println!("  Diff of '{}' from {} to {}:\n", node.name, from, to);
+    let diff = diff_versions(stores.history_store.as_ref(), &node.id, from, to).await?;
+    if diff.is_empty() {
+        println!("  (no changes)");
     } else {
-        for t in &tests {
-            println!("  - {} (score: {:.2}, depth: {})", t.name, t.score, t.depth);
-        }
+        print!("{}", diff);
     }
-
     Ok(())
 }
 
-fn cmd_list_nodes(session: &Session, filter: &str) {
-    // Note: This should be async but we're keeping it simple for now
-    println!("  (async node listing not yet implemented)");
+async fn run_branch_create(path: &Path, name: &str, from: &str) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    stores
+        .history_store
+        .create_branch(name, from.to_string())
+        .await?;
+    println!("  Created branch '{}' from {}", name, from);
+    Ok(())
 }
 
-async fn cmd_list_edges(session: &Session) {
-    let edge_types = [
-        EdgeType::Calls,
-        EdgeType::Imports,
-        EdgeType::Defines,
-        EdgeType::Contains,
-        EdgeType::TestsOf,
-        EdgeType::Documents,
-        EdgeType::DependsOn,
-        EdgeType::Implements,
-        EdgeType::Configures,
-        EdgeType::VersionedBy,
-    ];
+async fn run_branch_list(path: &Path) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    let mut branches = stores.history_store.list_branches().await;
+    branches.sort_by(|a, b| a.name.cmp(&b.name));
 
-    let mut total = 0;
-    for et in &edge_types {
-        let edges = session.stores.graph_store.edges_by_type(et).await;
-        if !edges.is_empty() {
-            if total == 0 {
-                println!("  Edges in the graph:\n");
-            }
-            for edge in &edges {
-                let source_name = session
-                    .stores
-                    .graph_store
-                    .get_node(&edge.source_id)
-                    .await
-                    .ok()
-                    .flatten()
-                    .map(|n| n.name)
-                    .unwrap_or_else(|| "?".to_string());
-                let target_name = session
-                    .stores
-                    .graph_store
-                    .get_node(&edge.target_id)
-                    .await
-                    .ok()
-                    .flatten()
-                    .map(|n| n.name)
-                    .unwrap_or_else(|| "?".to_string());
-                println!(
-                    "  {} --[{:?}]--> {} (conf: {:.2})",
-                    source_name, edge.edge_type, target_name, edge.confidence
-                );
-                total += 1;
-            }
-        }
+    if branches.is_empty() {
+        println!("  (no branches)");
     }
-
-    if total == 0 {
-        println!("  No edges in the store.");
-    } else {
-        println!("\n  {} edge(s) total.", total);
+    for branch in &branches {
+        println!(
+            "  {} | base {} | head {} | {}{}",
+            branch.name,
+            branch.base,
+            branch.head,
+            branch.created_at.format("%Y-%m-%d %H:%M:%S"),
+            branch
+                .merged_into
+                .as_deref()
+                .map(|target| format!(" | merged into {}", target))
+                .unwrap_or_default()
+        );
     }
+    Ok(())
 }
 
-async fn cmd_history(session: &Session, args: &str) -> OnyxResult<()> {
-    let name = args.trim();
-    let node = match find_node_by_name(&session.stores, name).await {
-        Some(n) => n,
-        None => {
-            println!("  Node '{}' not found.", name);
-            return Ok(());
+/// Merge `source` into `target`, or list the conflicting entities and
+/// return `false` if both branches changed the same entity.
+async fn run_merge(path: &Path, source: &str, target: &str) -> OnyxResult<bool> {
+    let stores = open_store(path).await?;
+    let conflicts = merge_conflicts(stores.history_store.as_ref(), source, target).await?;
+    if !conflicts.is_empty() {
+        println!("  {} conflicting entities:", conflicts.len());
+        for id in &conflicts {
+            match stores.graph_store.get_node(id).await? {
+                Some(node) => println!("    {}  {}", id, node.name),
+                None => println!("    {}", id),
+            }
         }
-    };
-
-    let versions = session.stores.history_store.list_versions(&node.id).await?;
+        return Ok(false);
+    }
 
-    println!(
-        "  Version history for '{}' ({} versions):\n",
-        node.name,
-        versions.len()
-    );
+    let version_id = stores.history_store.merge_branch(source, target).await?;
+    println!("  Merged '{}' into '{}' as {}", source, target, version_id);
+    Ok(true)
+}
 
-    if versions.is_empty() {
-        println!("  (no versions recorded)");
-    } else {
-        for v in &versions {
-            println!(
-                "  {} | {} | {} | {} | {} lines",
-                &v.version_id[..v.version_id.len().min(16)],
-                v.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                v.author.as_deref().unwrap_or("system"),
-                v.message.as_deref().unwrap_or("(no message)"),
-                v.diff.lines_changed()
-            );
+/// List the `top` nodes by PageRank over `edge_types`, or over
+/// [`IMPORTANCE_EDGE_TYPES`] when none are given.
+async fn run_important(
+    path: &Path,
+    edge_types: Option<Vec<EdgeType>>,
+    top: usize,
+) -> OnyxResult<()> {
+    let stores = open_store(path).await?;
+    let edge_types = edge_types.as_deref().unwrap_or(IMPORTANCE_EDGE_TYPES);
+    let ranks = pagerank_in(
+        stores.graph_store.as_ref(),
+        edge_types,
+        DEFAULT_DAMPING,
+        DEFAULT_PAGERANK_ITERATIONS,
+    )
+    .await;
+
+    let mut ranked: Vec<(uuid::Uuid, f64)> = ranks.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    if ranked.is_empty() {
+        println!("  Store is empty.");
+    }
+    for (i, (id, rank)) in ranked.into_iter().take(top).enumerate() {
+        match stores.graph_store.get_node(&id).await? {
+            Some(node) => println!("  {}. {} ({:.4})", i + 1, node.name, rank),
+            None => println!("  {}. {} ({:.4})", i + 1, id, rank),
         }
     }
-
     Ok(())
 }
 
+/// Index of the version in `versions` with ID `id`, or the only one whose
+/// ID starts with it, since `history` prints IDs shortened.
+fn resolve_version(versions: &[VersionEntry], id: &str) -> OnyxResult<usize> {
+    if let Some(index) = versions.iter().position(|v| v.version_id == id) {
+        return Ok(index);
+    }
+    let mut matches = versions
+        .iter()
+        .enumerate()
+        .filter(|(_, v)| v.version_id.starts_with(id));
+    match (matches.next(), matches.next()) {
+        (Some((index, _)), None) => Ok(index),
+        (None, _) => Err(OnyxError::VersionNotFound(id.to_string())),
+        (Some(_), Some(_)) => Err(OnyxError::InvalidQuery(format!(
+            "version '{}' is ambiguous; use more of the ID",
+            id
+        ))),
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Helpers
 // ---------------------------------------------------------------------------
 
-async fn find_node_by_name(
-    stores: &TransactionManager,
-    name: &str,
-) -> Option<onyx::model::node::Node> {
-    let all = stores.graph_store.all_nodes().await;
-    // Exact match first
-    for node in &all {
-        if node.name == name {
-            return stores.graph_store.get_node(&node.id).await.ok().flatten();
+/// Bag-of-words embedder over the content of every node in `graph`, for
+/// stores saved without an embedder.
+///
+/// The vocabulary is rebuilt from what is stored now, so query vectors only
+/// line up with the stored ones if those were embedded from the same corpus.
+/// `onyx ingest --store` saves its embedder and never needs this.
+async fn embedder_for(graph: &dyn GraphStore) -> Option<BagOfWordsEmbedder> {
+    let nodes = graph.all_nodes().await;
+    if nodes.is_empty() {
+        return None;
+    }
+    let corpus: Vec<&str> = nodes.iter().map(|n| n.content.as_str()).collect();
+    Some(BagOfWordsEmbedder::from_corpus(&corpus, VOCAB_SIZE))
+}
+
+/// Resolve `name` (or a node ID) to one node, trying exact, then
+/// case-insensitive, then partial matches.
+///
+/// Lists the candidates when the name is ambiguous rather than picking one,
+/// and says so when nothing matches.
+async fn find_node_by_name(graph: &dyn GraphStore, name: &str) -> Option<onyx::model::node::Node> {
+    if let Ok(id) = uuid::Uuid::parse_str(name) {
+        if let Ok(Some(node)) = graph.get_node(&id).await {
+            return Some(node);
         }
     }
-    // Case-insensitive partial match
-    let name_lower = name.to_lowercase();
-    for node in &all {
-        if node.name.to_lowercase().contains(&name_lower) {
-            return stores.graph_store.get_node(&node.id).await.ok().flatten();
+    for mode in [
+        MatchMode::Exact,
+        MatchMode::CaseInsensitive,
+        MatchMode::Fuzzy,
+    ] {
+        let mut matches = graph.find_nodes_by_name(name, mode).await;
+        match matches.len() {
+            0 => continue,
+            1 => return Some(matches.remove(0).node),
+            n => {
+                println!("  '{}' matches {} nodes; use a full name or ID:", name, n);
+                for m in &matches {
+                    println!(
+                        "    {}  {} ({})",
+                        m.node.id,
+                        m.node.name,
+                        m.node.provenance.file_path.as_deref().unwrap_or("?")
+                    );
+                }
+                return None;
+            }
         }
     }
+    let suggestions = graph.fuzzy_find(name, 3, DEFAULT_MAX_EDIT_DISTANCE).await;
+    if suggestions.is_empty() {
+        println!(
+            "  Node '{}' not found. Use 'nodes' to list available nodes.",
+            name
+        );
+    } else {
+        let names: Vec<String> = suggestions
+            .iter()
+            .map(|m| format!("'{}'", m.node.name))
+            .collect();
+        println!(
+            "  Node '{}' not found. Did you mean {}?",
+            name,
+            names.join(" or ")
+        );
+    }
     None
 }
 
 fn parse_edge_types(input: &str) -> Vec<EdgeType> {
     input
         .split(',')
-        .filter_map(|s| match s.trim().to_lowercase().as_str() {
-            "calls" | "call" => Some(EdgeType::Calls),
-            "imports" | "import" => Some(EdgeType::Imports),
-            "defines" | "define" => Some(EdgeType::Defines),
-            "contains" | "contain" => Some(EdgeType::Contains),
-            "tests" | "test" | "testsof" => Some(EdgeType::TestsOf),
-            "documents" | "docs" | "doc" => Some(EdgeType::Documents),
-            "depends" | "dependson" => Some(EdgeType::DependsOn),
-            "implements" | "impl" => Some(EdgeType::Implements),
-            "configures" | "config" => Some(EdgeType::Configures),
-            _ => {
-                eprintln!("  Unknown edge type: '{}'", s.trim());
+        .filter(|s| !s.trim().is_empty())
+        .filter_map(|s| match s.parse::<EdgeType>() {
+            Ok(edge_type) => Some(edge_type),
+            Err(e) => {
+                eprintln!("  {}", e);
                 None
             }
         })
         .collect()
 }
 
+// ---------------------------------------------------------------------------
+// HTTP server
+// ---------------------------------------------------------------------------
+
+/// Serve the API over the RocksDB store at `store`, or over the configured
+/// database when no store is given.
+async fn run_serve(
+    config: AppConfig,
+    store: Option<&Path>,
+    dimensions: Option<usize>,
+) -> OnyxResult<()> {
+    let state = match store {
+        Some(store) => {
+            println!("Serving store at: {}", store.display());
+            // Keep embedding API ingests into the space of earlier ones.
+            let embedder = load_embedder(store)?;
+            let dimensions = dimensions.or(embedder.as_ref().map(|e| e.dimensions()));
+            let stores = open_stores(store, dimensions).await?;
+            let state = AppState::with_stores(config.clone(), stores);
+            match embedder {
+                Some(embedder) => state.with_embedder(embedder),
+                None => state,
+            }
+        }
+        None => AppState::from_config(config.clone()).await?,
+    };
+    serve_api(state, &config).await
+}
+
+/// Serve the HTTP API over `state`.
+#[cfg(not(feature = "grpc"))]
+async fn serve_api(state: AppState, config: &AppConfig) -> OnyxResult<()> {
+    use onyx::server::serve_http;
+
+    let listener = bind_http(config).await?;
+    serve_http(state, config, listener, CancellationToken::new()).await
+}
+
+/// Serve the HTTP and gRPC APIs over the same `state`.
+#[cfg(feature = "grpc")]
+async fn serve_api(state: AppState, config: &AppConfig) -> OnyxResult<()> {
+    use onyx::server::grpc::{bind_grpc, serve_http_and_grpc};
+
+    let http = bind_http(config).await?;
+    let grpc = bind_grpc(config).await?;
+    serve_http_and_grpc(state, config, http, grpc, CancellationToken::new()).await
+}
+
 // ---------------------------------------------------------------------------
 // Standalone ingest (non-interactive)
 // ---------------------------------------------------------------------------
 
-async fn run_ingest(path: &PathBuf) -> OnyxResult<()> {
-    let source = std::fs::read_to_string(path)?;
-    let units = parse_rust_source(&source, &path.to_string_lossy());
+async fn run_ingest(
+    path: &PathBuf,
+    dry_run: bool,
+    store: Option<&Path>,
+    limits: &LimitsConfig,
+) -> OnyxResult<()> {
+    let units = IngestRegistry::default().parse_path(path)?;
 
     println!("Parsed {} code entities:", units.len());
     for unit in &units {
         println!("  - {} ({:?})", unit.name, unit.kind);
     }
 
+    if let Some(store) = store {
+        return ingest_into_store(store, &units, dry_run, limits).await;
+    }
+
     let embedder = BagOfWordsEmbedder::from_corpus(
         &units.iter().map(|u| u.content.as_str()).collect::<Vec<_>>(),
         100,
     );
 
     let mut stores = TransactionManager::new();
-    let results = ingest_codebase(&mut stores, &units, &embedder).await?;
+
+    if dry_run {
+        let plan = ingest_codebase_plan(&stores, &units, &embedder).await?;
+        print_ingest_plan(&plan);
+        return Ok(());
+    }
+
+    let results = ingest_codebase(&mut stores, &units, &embedder, limits).await?;
 
     println!("\nIngested {} nodes", results.len());
     let stats = stores.stats();
@@ -1005,6 +1581,81 @@ async fn run_ingest(path: &PathBuf) -> OnyxResult<()> {
     Ok(())
 }
 
+/// Ingest `units` into the RocksDB store at `path`.
+///
+/// The first ingest builds the embedder's vocabulary from `units` and saves
+/// it with the store; later ones reuse it, so every stored vector has the
+/// same dimension and `onyx query --store` embeds into the same space.
+/// Words outside the saved vocabulary are ignored.
+async fn ingest_into_store(
+    path: &Path,
+    units: &[CodeUnit],
+    dry_run: bool,
+    limits: &LimitsConfig,
+) -> OnyxResult<()> {
+    let embedder = match load_embedder(path)? {
+        Some(embedder) => embedder,
+        None => BagOfWordsEmbedder::from_corpus(
+            &units.iter().map(|u| u.content.as_str()).collect::<Vec<_>>(),
+            VOCAB_SIZE,
+        ),
+    };
+
+    if dry_run {
+        // Plan against an empty store rather than creating one on disk.
+        let stores = if path.exists() {
+            TransactionManager::with_async_stores(open_store(path).await?)
+        } else {
+            TransactionManager::new()
+        };
+        let plan = ingest_codebase_plan(&stores, units, &embedder).await?;
+        print_ingest_plan(&plan);
+        return Ok(());
+    }
+
+    println!("Ingesting into store at: {}", path.display());
+    let opened = open_stores(path, Some(embedder.dimensions())).await?;
+    let mut stores = TransactionManager::with_async_stores(opened);
+    let results = ingest_codebase(&mut stores, units, &embedder, limits).await?;
+    save_embedder(path, &embedder)?;
+
+    println!("\nIngested {} nodes", results.len());
+    println!("Store stats: {}", stores.stats());
+    Ok(())
+}
+
+/// Print a dry-run ingest plan.
+fn print_ingest_plan(plan: &IngestPlan) {
+    println!("\nDry run: nothing was written.");
+    println!("  New nodes: {}", plan.new_nodes.len());
+    for node in &plan.new_nodes {
+        println!("    + {} ({})", node.name, node.file_path);
+    }
+    println!("  Updated nodes: {}", plan.updated_nodes.len());
+    for node in &plan.updated_nodes {
+        println!("    ~ {} ({})", node.name, node.file_path);
+    }
+    println!("  Unchanged nodes: {}", plan.unchanged_nodes.len());
+
+    let names: std::collections::HashMap<uuid::Uuid, &str> = plan
+        .new_nodes
+        .iter()
+        .chain(&plan.updated_nodes)
+        .chain(&plan.unchanged_nodes)
+        .map(|node| (node.node_id, node.name.as_str()))
+        .collect();
+    println!("  Edges to add: {}", plan.detected_edges.len());
+    for edge in &plan.detected_edges {
+        println!(
+            "    {} --[{}]--> {} (confidence: {:.2})",
+            names.get(&edge.source_id).copied().unwrap_or("?"),
+            edge.edge_type,
+            names.get(&edge.target_id).copied().unwrap_or("?"),
+            edge.confidence
+        );
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Demo (non-interactive walkthrough)
 // ---------------------------------------------------------------------------
@@ -1023,7 +1674,7 @@ async fn run_demo() -> OnyxResult<()> {
     // Ingest everything
     let mut stores = TransactionManager::new();
     println!("Phase 1: Ingesting {} code artifacts...", units.len());
-    let results = ingest_codebase(&mut stores, &units, &embedder).await?;
+    let results = ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default()).await?;
 
     for result in &results {
         let node = stores.graph_store.get_node(&result.node_id).await?.unwrap();
@@ -1066,7 +1717,7 @@ async fn run_demo() -> OnyxResult<()> {
         // Inbound callers
         let callers = stores
             .graph_store
-            .get_inbound(&node.id, Some(&[EdgeType::Calls]))
+            .get_active_inbound(&node.id, Some(&[EdgeType::Calls]))
             .await?;
         println!("\nCallers of {}:", node.name);
         for (_, caller) in &callers {
@@ -1261,102 +1912,3 @@ async fn run_demo() -> OnyxResult<()> {
 
     Ok(())
 }
-
-/// Build a synthetic codebase for demo purposes.
-fn build_synthetic_codebase() -> Vec<CodeUnit> {
-    use onyx::model::node::{CodeEntityKind, Language, Visibility};
-
-    vec![
-        CodeUnit {
-            name: "calculate_total".to_string(),
-            content: "pub fn calculate_total(items: &[Item], tax_rate: f64) -> f64 {\n    let subtotal = items.iter().map(|i| i.price * i.quantity as f64).sum::<f64>();\n    let discount = apply_discount(subtotal);\n    discount * (1.0 + tax_rate)\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/billing/calculator.rs".to_string(),
-            line_range: Some((10, 15)),
-            signature: Some("pub fn calculate_total(items: &[Item], tax_rate: f64) -> f64".to_string()),
-            visibility: Visibility::Public,
-            module_path: vec!["billing".to_string(), "calculator".to_string()],
-            commit_id: Some("abc123".to_string()),
-            branch: Some("main".to_string()),
-        },
-        CodeUnit {
-            name: "apply_discount".to_string(),
-            content: "pub fn apply_discount(amount: f64) -> f64 {\n    let rules = get_discount_rules();\n    rules.iter().fold(amount, |acc, rule| rule.apply(acc))\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/billing/discount.rs".to_string(),
-            line_range: Some((5, 8)),
-            signature: Some("pub fn apply_discount(amount: f64) -> f64".to_string()),
-            visibility: Visibility::Public,
-            module_path: vec!["billing".to_string(), "discount".to_string()],
-            commit_id: Some("abc123".to_string()),
-            branch: Some("main".to_string()),
-        },
-        CodeUnit {
-            name: "get_discount_rules".to_string(),
-            content: "fn get_discount_rules() -> Vec<DiscountRule> {\n    vec![\n        DiscountRule::percentage(10.0, 100.0),\n        DiscountRule::fixed(5.0, 50.0),\n    ]\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/billing/discount.rs".to_string(),
-            line_range: Some((12, 18)),
-            signature: Some("fn get_discount_rules() -> Vec<DiscountRule>".to_string()),
-            visibility: Visibility::Private,
-            module_path: vec!["billing".to_string(), "discount".to_string()],
-            commit_id: Some("abc123".to_string()),
-            branch: Some("main".to_string()),
-        },
-        CodeUnit {
-            name: "process_payment".to_string(),
-            content: "pub fn process_payment(order: &Order) -> PaymentResult {\n    let total = calculate_total(&order.items, order.tax_rate);\n    let charge = payment_gateway::charge(order.payment_method, total);\n    record_transaction(order.id, total, charge.status)\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/payment/processor.rs".to_string(),
-            line_range: Some((20, 25)),
-            signature: Some("pub fn process_payment(order: &Order) -> PaymentResult".to_string()),
-            visibility: Visibility::Public,
-            module_path: vec!["payment".to_string(), "processor".to_string()],
-            commit_id: Some("def456".to_string()),
-            branch: Some("main".to_string()),
-        },
-        CodeUnit {
-            name: "record_transaction".to_string(),
-            content: "fn record_transaction(order_id: Uuid, amount: f64, status: ChargeStatus) -> PaymentResult {\n    let tx = Transaction::new(order_id, amount, status);\n    db::insert(&tx)?;\n    PaymentResult::from(tx)\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/payment/ledger.rs".to_string(),
-            line_range: Some((8, 13)),
-            signature: Some("fn record_transaction(order_id: Uuid, amount: f64, status: ChargeStatus) -> PaymentResult".to_string()),
-            visibility: Visibility::Private,
-            module_path: vec!["payment".to_string(), "ledger".to_string()],
-            commit_id: Some("def456".to_string()),
-            branch: Some("main".to_string()),
-        },
-        CodeUnit {
-            name: "validate_order".to_string(),
-            content: "pub fn validate_order(order: &Order) -> Result<(), ValidationError> {\n    if order.items.is_empty() {\n        return Err(ValidationError::EmptyOrder);\n    }\n    for item in &order.items {\n        validate_item(item)?;\n    }\n    Ok(())\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/validation/order.rs".to_string(),
-            line_range: Some((1, 9)),
-            signature: Some("pub fn validate_order(order: &Order) -> Result<(), ValidationError>".to_string()),
-            visibility: Visibility::Public,
-            module_path: vec!["validation".to_string(), "order".to_string()],
-            commit_id: Some("ghi789".to_string()),
-            branch: Some("main".to_string()),
-        },
-        CodeUnit {
-            name: "validate_item".to_string(),
-            content: "fn validate_item(item: &Item) -> Result<(), ValidationError> {\n    if item.price < 0.0 {\n        return Err(ValidationError::NegativePrice);\n    }\n    if item.quantity == 0 {\n        return Err(ValidationError::ZeroQuantity);\n    }\n    Ok(())\n}".to_string(),
-            kind: CodeEntityKind::Function,
-            language: Language::Rust,
-            file_path: "src/validation/item.rs".to_string(),
-            line_range: Some((1, 9)),
-            signature: Some("fn validate_item(item: &Item) -> Result<(), ValidationError>".to_string()),
-            visibility: Visibility::Private,
-            module_path: vec!["validation".to_string(), "item".to_string()],
-            commit_id: Some("ghi789".to_string()),
-            branch: Some("main".to_string()),
-        },
-    ]
-}
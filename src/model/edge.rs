@@ -1,10 +1,16 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
+use crate::error::OnyxError;
 use crate::model::version::VersionId;
 
+/// Metadata key of a [`EdgeType::VersionedBy`] edge naming the head version.
+pub const HEAD_VERSION_METADATA: &str = "version_id";
+
 // ---------------------------------------------------------------------------
 // Edge: relationships between nodes in the knowledge graph
 // ---------------------------------------------------------------------------
@@ -25,6 +31,9 @@ pub struct Edge {
     pub confidence: f64,
     /// Extensible key-value metadata.
     pub metadata: HashMap<String, String>,
+    /// How the edge was detected; `None` for edges created by hand.
+    #[serde(default)]
+    pub provenance: Option<EdgeProvenance>,
     /// Temporal context: when this relationship was valid.
     pub temporal: TemporalContext,
 }
@@ -39,10 +48,23 @@ impl Edge {
             target_id,
             confidence: 1.0,
             metadata: HashMap::new(),
+            provenance: None,
             temporal: TemporalContext::new_active(),
         }
     }
 
+    /// The [`EdgeType::VersionedBy`] self-edge of `entity_id`, naming `head`
+    /// as the latest version in its chain.
+    pub fn versioned_by(entity_id: Uuid, head: &VersionId) -> Self {
+        Self::new(EdgeType::VersionedBy, entity_id, entity_id)
+            .with_metadata(HEAD_VERSION_METADATA, head.as_str())
+    }
+
+    /// The head version a [`EdgeType::VersionedBy`] edge names.
+    pub fn head_version(&self) -> Option<&str> {
+        self.metadata.get(HEAD_VERSION_METADATA).map(String::as_str)
+    }
+
     /// Set the confidence score for this edge.
     pub fn with_confidence(mut self, confidence: f64) -> Self {
         self.confidence = confidence.clamp(0.0, 1.0);
@@ -55,15 +77,78 @@ impl Edge {
         self
     }
 
+    /// Record that `detector` found this edge just now.
+    pub fn detected_by(mut self, detector: DetectorKind) -> Self {
+        self.provenance = Some(EdgeProvenance::new(detector));
+        self
+    }
+
+    /// The detector that found this edge, if it was detected automatically.
+    pub fn detector(&self) -> Option<&DetectorKind> {
+        self.provenance.as_ref().map(|p| &p.detector)
+    }
+
+    /// Whether `other` is new evidence for this edge rather than a repeat
+    /// from a detector that already reported it.
+    pub fn is_corroborated_by(&self, other: &Edge) -> bool {
+        match (&self.provenance, other.detector()) {
+            (Some(provenance), Some(detector)) => !provenance.has_detector(detector),
+            _ => true,
+        }
+    }
+
+    /// Fold another detection of the same relationship into this edge.
+    ///
+    /// Corroborating evidence combines confidences with
+    /// [`combine_confidence`] and is added to the provenance; a repeat only
+    /// keeps the higher confidence. Metadata from `other` fills in missing
+    /// keys. The ID and temporal context are kept.
+    pub fn reinforced(mut self, other: &Edge) -> Edge {
+        if self.is_corroborated_by(other) {
+            self.confidence = combine_confidence(self.confidence, other.confidence);
+            match (&mut self.provenance, &other.provenance) {
+                (Some(provenance), Some(new)) => {
+                    provenance.corroborated_by.push(new.detector.clone())
+                }
+                (None, Some(new)) => self.provenance = Some(new.clone()),
+                _ => {}
+            }
+        } else {
+            self.confidence = self.confidence.max(other.confidence);
+        }
+        for (key, value) in &other.metadata {
+            self.metadata
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        self
+    }
+
     /// Set temporal context via a commit.
     pub fn with_commit(mut self, commit: impl Into<String>) -> Self {
         self.temporal.via_commit = Some(commit.into());
         self
     }
 
-    /// Check if this edge is currently active (not yet terminated).
+    /// Set when the relationship started to hold.
+    pub fn valid_from(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.temporal.since_timestamp = timestamp;
+        self
+    }
+
+    /// Set when the relationship stopped holding.
+    pub fn valid_until(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.temporal.until_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Check if this edge is currently active (not yet terminated or closed).
     pub fn is_active(&self) -> bool {
         self.temporal.until.is_none()
+            && self
+                .temporal
+                .until_timestamp
+                .map_or(true, |until| until > Utc::now())
     }
 
     /// Terminate this edge at a given version and timestamp.
@@ -73,6 +158,11 @@ impl Edge {
     }
 }
 
+/// Combine two independent confidence estimates: `1 - (1 - a)(1 - b)`.
+pub fn combine_confidence(a: f64, b: f64) -> f64 {
+    (1.0 - (1.0 - a) * (1.0 - b)).clamp(0.0, 1.0)
+}
+
 // ---------------------------------------------------------------------------
 // EdgeType: categories of relationships
 // ---------------------------------------------------------------------------
@@ -89,7 +179,10 @@ pub enum EdgeType {
     Documents,
     /// A test covers a code entity.
     TestsOf,
-    /// An entity is versioned by a history entry.
+    /// An entity is versioned by its history. Versions are not nodes, so
+    /// this is a self-edge whose metadata names the head of the entity's
+    /// version chain; see [`Edge::versioned_by`]. Ingestion keeps exactly
+    /// one per node, moving it to each new version.
     VersionedBy,
     /// A module contains sub-entities.
     Contains,
@@ -99,9 +192,54 @@ pub enum EdgeType {
     DependsOn,
     /// A config file configures a code entity or module.
     Configures,
+    /// Two entities have nearby embeddings; see `build_semantic_edges`.
+    SimilarTo,
 }
 
 impl EdgeType {
+    /// Every edge type, in declaration order.
+    pub const ALL: [EdgeType; 11] = [
+        EdgeType::Defines,
+        EdgeType::Calls,
+        EdgeType::Imports,
+        EdgeType::Documents,
+        EdgeType::TestsOf,
+        EdgeType::VersionedBy,
+        EdgeType::Contains,
+        EdgeType::Implements,
+        EdgeType::DependsOn,
+        EdgeType::Configures,
+        EdgeType::SimilarTo,
+    ];
+
+    /// Canonical snake_case name, as produced by `Display`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EdgeType::Defines => "defines",
+            EdgeType::Calls => "calls",
+            EdgeType::Imports => "imports",
+            EdgeType::Documents => "documents",
+            EdgeType::TestsOf => "tests_of",
+            EdgeType::VersionedBy => "versioned_by",
+            EdgeType::Contains => "contains",
+            EdgeType::Implements => "implements",
+            EdgeType::DependsOn => "depends_on",
+            EdgeType::Configures => "configures",
+            EdgeType::SimilarTo => "similar_to",
+        }
+    }
+
+    /// Whether this edge comes from embedding similarity rather than from
+    /// the structure of the code.
+    pub fn is_semantic(&self) -> bool {
+        matches!(self, EdgeType::SimilarTo)
+    }
+
+    /// Every structural edge type, for traversals that skip semantic edges.
+    pub fn structural() -> Vec<EdgeType> {
+        Self::ALL.into_iter().filter(|t| !t.is_semantic()).collect()
+    }
+
     /// Returns the inverse relationship type, if one exists.
     pub fn inverse(&self) -> Option<EdgeType> {
         match self {
@@ -111,11 +249,104 @@ impl EdgeType {
             EdgeType::Contains => Some(EdgeType::Defines), // contained_by
             EdgeType::Documents => Some(EdgeType::Documents), // documented_by
             EdgeType::TestsOf => Some(EdgeType::TestsOf),  // tested_by
+            EdgeType::SimilarTo => Some(EdgeType::SimilarTo),
             _ => None,
         }
     }
 }
 
+impl fmt::Display for EdgeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for EdgeType {
+    type Err = OnyxError;
+
+    /// Parse a canonical name or alias. Case, `_` and `-` are ignored, so
+    /// `tests_of`, `TestsOf` and `tests-of` are equivalent.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect();
+        match normalized.as_str() {
+            "defines" | "define" => Ok(EdgeType::Defines),
+            "calls" | "call" => Ok(EdgeType::Calls),
+            "imports" | "import" => Ok(EdgeType::Imports),
+            "documents" | "document" | "docs" | "doc" => Ok(EdgeType::Documents),
+            "testsof" | "tests" | "test" => Ok(EdgeType::TestsOf),
+            "versionedby" | "versioned" | "version" => Ok(EdgeType::VersionedBy),
+            "contains" | "contain" => Ok(EdgeType::Contains),
+            "implements" | "implement" | "impl" => Ok(EdgeType::Implements),
+            "dependson" | "depends" | "dependency" => Ok(EdgeType::DependsOn),
+            "configures" | "configure" | "config" => Ok(EdgeType::Configures),
+            "similarto" | "similar" | "semantic" => Ok(EdgeType::SimilarTo),
+            _ => Err(OnyxError::InvalidEdgeType(s.trim().to_string())),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// EdgeProvenance: how a relationship was detected
+// ---------------------------------------------------------------------------
+
+/// Structured record of which detector produced an edge, and when.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeProvenance {
+    pub detector: DetectorKind,
+    pub detected_at: DateTime<Utc>,
+    /// Where the evidence was found, e.g. `src/billing.rs:12`.
+    pub source_location: Option<String>,
+    /// Other detectors that later found the same relationship.
+    #[serde(default)]
+    pub corroborated_by: Vec<DetectorKind>,
+}
+
+impl EdgeProvenance {
+    pub fn new(detector: DetectorKind) -> Self {
+        Self {
+            detector,
+            detected_at: Utc::now(),
+            source_location: None,
+            corroborated_by: Vec::new(),
+        }
+    }
+
+    /// Whether `detector` found the edge, first or later.
+    pub fn has_detector(&self, detector: &DetectorKind) -> bool {
+        self.detector == *detector || self.corroborated_by.contains(detector)
+    }
+
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.source_location = Some(location.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DetectorKind {
+    /// The source names the target somewhere in its content.
+    ContentScan,
+    /// Parent and child module paths.
+    ModuleHierarchy,
+    /// Confirmed by parsing the syntax tree.
+    Ast,
+    /// A test calls the target directly.
+    TestTarget,
+    /// Code mentions a key defined by a config file.
+    ConfigKey,
+    /// Nearby embeddings.
+    Embedding,
+    /// An `impl Trait for Type` block and the methods inside it.
+    TraitImpl,
+    /// Code imports a crate declared in a package manifest.
+    Manifest,
+}
+
 // ---------------------------------------------------------------------------
 // TemporalContext: tracks when a relationship was valid
 // ---------------------------------------------------------------------------
@@ -158,3 +389,81 @@ impl TemporalContext {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reinforced_combines_independent_evidence() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = Edge::new(EdgeType::Calls, a, b)
+            .with_confidence(0.8)
+            .detected_by(DetectorKind::ContentScan);
+
+        let repeat = Edge::new(EdgeType::Calls, a, b)
+            .with_confidence(0.8)
+            .detected_by(DetectorKind::ContentScan);
+        assert!(!first.is_corroborated_by(&repeat));
+        assert_eq!(first.clone().reinforced(&repeat).confidence, 0.8);
+
+        let ast = Edge::new(EdgeType::Calls, a, b)
+            .with_confidence(0.8)
+            .detected_by(DetectorKind::Ast);
+        let merged = first.reinforced(&ast);
+        let provenance = merged.provenance.as_ref().unwrap();
+        assert!(provenance.has_detector(&DetectorKind::ContentScan));
+        assert!(provenance.has_detector(&DetectorKind::Ast));
+        assert!(!merged.is_corroborated_by(&ast));
+    }
+
+    #[test]
+    fn test_edge_provenance_is_optional_in_json() {
+        let edge = Edge::new(EdgeType::Calls, Uuid::new_v4(), Uuid::new_v4())
+            .detected_by(DetectorKind::Ast);
+        let mut json = serde_json::to_value(&edge).unwrap();
+        let parsed: Edge = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(parsed.detector(), Some(&DetectorKind::Ast));
+
+        json.as_object_mut().unwrap().remove("provenance");
+        let legacy: Edge = serde_json::from_value(json).unwrap();
+        assert!(legacy.provenance.is_none());
+    }
+
+    #[test]
+    fn test_similar_to_round_trips_through_serde() {
+        let edge = Edge::new(EdgeType::SimilarTo, Uuid::new_v4(), Uuid::new_v4());
+        let json = serde_json::to_string(&edge).unwrap();
+        let parsed: Edge = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.edge_type, EdgeType::SimilarTo);
+        assert_eq!(
+            "similar-to".parse::<EdgeType>().unwrap(),
+            EdgeType::SimilarTo
+        );
+        assert!(!EdgeType::structural().contains(&EdgeType::SimilarTo));
+    }
+
+    #[test]
+    fn test_edge_type_display_from_str_round_trip() {
+        for edge_type in EdgeType::ALL {
+            let parsed: EdgeType = edge_type.to_string().parse().unwrap();
+            assert_eq!(parsed, edge_type);
+        }
+    }
+
+    #[test]
+    fn test_edge_type_aliases() {
+        assert_eq!("TestsOf".parse::<EdgeType>().unwrap(), EdgeType::TestsOf);
+        assert_eq!(
+            "depends-on".parse::<EdgeType>().unwrap(),
+            EdgeType::DependsOn
+        );
+        assert_eq!("impl".parse::<EdgeType>().unwrap(), EdgeType::Implements);
+    }
+
+    #[test]
+    fn test_edge_type_unknown() {
+        let err = "frobnicates".parse::<EdgeType>().unwrap_err();
+        assert!(matches!(err, OnyxError::InvalidEdgeType(ref s) if s == "frobnicates"));
+    }
+}
@@ -93,41 +93,87 @@ pub enum EmbeddingModel {
 
 /// A simple embedding generator using bag-of-words with term frequency.
 /// This is a prototype implementation; production would use a transformer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BagOfWordsEmbedder {
     /// Fixed vocabulary for consistent dimensionality.
     vocabulary: Vec<String>,
+    /// Set by [`hashed`](Self::hashed): words are hashed into this many
+    /// dimensions and `vocabulary` is unused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    buckets: Option<usize>,
 }
 
 impl BagOfWordsEmbedder {
     /// Create a new embedder with a fixed vocabulary.
     pub fn new(vocabulary: Vec<String>) -> Self {
-        Self { vocabulary }
+        Self {
+            vocabulary,
+            buckets: None,
+        }
+    }
+
+    /// Create an embedder that hashes every word into one of `dimensions`
+    /// buckets instead of looking it up in a vocabulary.
+    ///
+    /// Its dimension never changes, so vectors embedded at different times
+    /// stay comparable however much new text arrives, at the cost of
+    /// unrelated words occasionally sharing a bucket.
+    pub fn hashed(dimensions: usize) -> Self {
+        Self {
+            vocabulary: Vec::new(),
+            buckets: Some(dimensions.max(1)),
+        }
     }
 
     /// Build a vocabulary from a corpus of documents.
     pub fn from_corpus(documents: &[&str], max_vocab_size: usize) -> Self {
-        use std::collections::HashMap;
+        let mut embedder = Self::new(Vec::new());
+        embedder.add_documents(documents, max_vocab_size);
+        embedder
+    }
 
+    /// Number of dimensions in the embeddings this embedder produces.
+    pub fn dimensions(&self) -> usize {
+        self.buckets.unwrap_or(self.vocabulary.len())
+    }
+
+    /// Extend the vocabulary with words from new documents.
+    ///
+    /// Existing words keep their positions and new words are appended, most
+    /// frequent first, until the vocabulary holds `max_vocab_size` words. Vectors
+    /// embedded before the call therefore stay valid as prefixes of the new
+    /// space and only new or changed documents need re-embedding.
+    ///
+    /// This trades exactness for speed: old vectors were normalized without
+    /// the new words, and the vocabulary is not re-ranked by global frequency
+    /// as [`from_corpus`](Self::from_corpus) would do. Returns the number of
+    /// words added; a [`hashed`](Self::hashed) embedder has no vocabulary and
+    /// always returns 0.
+    pub fn add_documents(&mut self, documents: &[&str], max_vocab_size: usize) -> usize {
+        use std::collections::{HashMap, HashSet};
+
+        if self.buckets.is_some() {
+            return 0;
+        }
+        let known: HashSet<&str> = self.vocabulary.iter().map(String::as_str).collect();
         let mut word_counts: HashMap<String, usize> = HashMap::new();
         for doc in documents {
-            for word in doc.split_whitespace() {
-                let word = word
-                    .to_lowercase()
-                    .chars()
-                    .filter(|c| c.is_alphanumeric() || *c == '_')
-                    .collect::<String>();
-                if !word.is_empty() {
+            for word in tokenize(doc) {
+                if !known.contains(word.as_str()) {
                     *word_counts.entry(word).or_insert(0) += 1;
                 }
             }
         }
 
         let mut sorted: Vec<_> = word_counts.into_iter().collect();
-        sorted.sort_by(|a, b| b.1.cmp(&a.1));
-        sorted.truncate(max_vocab_size);
+        // Break ties by word so the vocabulary order is deterministic.
+        sorted.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        sorted.truncate(max_vocab_size.saturating_sub(self.vocabulary.len()));
 
-        let vocabulary = sorted.into_iter().map(|(word, _)| word).collect();
-        Self { vocabulary }
+        let added = sorted.len();
+        self.vocabulary
+            .extend(sorted.into_iter().map(|(word, _)| word));
+        added
     }
 
     /// Generate an embedding for a text string.
@@ -137,24 +183,26 @@ impl BagOfWordsEmbedder {
         let mut word_counts: HashMap<String, f32> = HashMap::new();
         let total_words = text.split_whitespace().count() as f32;
 
-        for word in text.split_whitespace() {
-            let word = word
-                .to_lowercase()
-                .chars()
-                .filter(|c| c.is_alphanumeric() || *c == '_')
-                .collect::<String>();
-            if !word.is_empty() {
-                *word_counts.entry(word).or_insert(0.0) += 1.0;
-            }
+        for word in tokenize(text) {
+            *word_counts.entry(word).or_insert(0.0) += 1.0;
         }
 
-        let values: Vec<f32> = self
-            .vocabulary
-            .iter()
-            .map(|vocab_word| {
-                word_counts.get(vocab_word).copied().unwrap_or(0.0) / total_words.max(1.0)
-            })
-            .collect();
+        let values: Vec<f32> = match self.buckets {
+            Some(buckets) => {
+                let mut values = vec![0.0; buckets];
+                for (word, count) in &word_counts {
+                    values[bucket_of(word, buckets)] += count / total_words.max(1.0);
+                }
+                values
+            }
+            None => self
+                .vocabulary
+                .iter()
+                .map(|vocab_word| {
+                    word_counts.get(vocab_word).copied().unwrap_or(0.0) / total_words.max(1.0)
+                })
+                .collect(),
+        };
 
         let mut emb = Embedding::new(values, EmbeddingModel::BagOfWords);
         emb.normalize();
@@ -162,6 +210,76 @@ impl BagOfWordsEmbedder {
     }
 }
 
+/// The bucket `word` falls into among `buckets`, by 64-bit FNV-1a so it is
+/// the same on every run and platform.
+fn bucket_of(word: &str, buckets: usize) -> usize {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in word.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    (hash % buckets as u64) as usize
+}
+
+/// Split an identifier into lowercase tokens: the whole identifier followed
+/// by its camelCase and snake_case subwords.
+///
+/// `apply_discount` yields `apply_discount`, `apply` and `discount`, so a
+/// search for `discount` can match it. Acronyms stay together: `HTTPServer`
+/// yields `httpserver`, `http` and `server`.
+pub fn tokenize_identifier(identifier: &str) -> Vec<String> {
+    let whole = identifier.to_lowercase();
+    let mut tokens = vec![whole.clone()];
+
+    for segment in identifier.split('_').filter(|s| !s.is_empty()) {
+        let chars: Vec<char> = segment.chars().collect();
+        let mut start = 0;
+        for i in 1..chars.len() {
+            let (prev, cur) = (chars[i - 1], chars[i]);
+            let lower_to_upper =
+                cur.is_uppercase() && (prev.is_lowercase() || prev.is_ascii_digit());
+            let acronym_end = cur.is_uppercase()
+                && prev.is_uppercase()
+                && chars.get(i + 1).map_or(false, |c| c.is_lowercase());
+            if lower_to_upper || acronym_end {
+                tokens.push(chars[start..i].iter().collect::<String>().to_lowercase());
+                start = i;
+            }
+        }
+        tokens.push(chars[start..].iter().collect::<String>().to_lowercase());
+    }
+
+    // A plain word is its own only subword.
+    if tokens.len() == 2 && tokens[1] == whole {
+        tokens.pop();
+    }
+    tokens
+}
+
+/// Split source text on code punctuation into lowercase identifier tokens,
+/// including camelCase and snake_case subwords.
+///
+/// Unlike splitting on whitespace, `total(items)` yields both `total` and
+/// `items`.
+pub fn tokenize_code(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .flat_map(tokenize_identifier)
+        .collect()
+}
+
+/// Split text into lowercase alphanumeric words and their identifier subwords.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(|c| c.is_alphanumeric() || *c == '_')
+                .collect::<String>()
+        })
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| tokenize_identifier(&word))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,6 +300,39 @@ mod tests {
         assert!(sim.abs() < 1e-6);
     }
 
+    #[test]
+    fn test_tokenize_code_strips_punctuation() {
+        assert_eq!(
+            tokenize_code("pub fn applyDiscount(amount: f64) -> f64 {"),
+            ["pub", "fn", "applydiscount", "apply", "discount", "amount", "f64", "f64"]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_identifier_splits_snake_and_camel_case() {
+        assert_eq!(
+            tokenize_identifier("apply_discount"),
+            ["apply_discount", "apply", "discount"]
+        );
+        assert_eq!(
+            tokenize_identifier("calculateTotal"),
+            ["calculatetotal", "calculate", "total"]
+        );
+        assert_eq!(
+            tokenize_identifier("HTTPServer"),
+            ["httpserver", "http", "server"]
+        );
+        assert_eq!(tokenize_identifier("total"), ["total"]);
+    }
+
+    #[test]
+    fn test_embedder_matches_partial_identifiers() {
+        let embedder = BagOfWordsEmbedder::from_corpus(&["fn apply_discount()"], 10);
+        let query = embedder.embed("discount");
+        let document = embedder.embed("fn apply_discount()");
+        assert!(query.cosine_similarity(&document) > 0.0);
+    }
+
     #[test]
     fn test_normalize() {
         let mut emb = Embedding::new(vec![3.0, 4.0], EmbeddingModel::BagOfWords);
@@ -197,4 +348,47 @@ mod tests {
         let emb = embedder.embed("fn main hello");
         assert_eq!(emb.dimensions, embedder.vocabulary.len());
     }
+
+    #[test]
+    fn test_add_documents_preserves_existing_vectors() {
+        let mut embedder = BagOfWordsEmbedder::from_corpus(&["fn calculate_total items sum"], 100);
+        let before = embedder.embed("fn calculate_total items");
+        let dims_before = embedder.dimensions();
+
+        // apply_discount contributes itself plus its `apply` and `discount` subwords.
+        let added = embedder.add_documents(&["fn apply_discount price rate"], 100);
+        assert_eq!(added, 5);
+        assert_eq!(embedder.dimensions(), dims_before + 5);
+
+        let after = embedder.embed("fn calculate_total items");
+        let prefix = Embedding::new(
+            after.values[..dims_before].to_vec(),
+            EmbeddingModel::BagOfWords,
+        );
+        assert!(before.cosine_similarity(&prefix) > 0.99);
+    }
+
+    #[test]
+    fn test_hashed_embedder_keeps_its_dimension() {
+        let mut embedder = BagOfWordsEmbedder::hashed(64);
+        let before = embedder.embed("fn calculate_total items");
+        assert_eq!(
+            embedder.add_documents(&["fn apply_discount price rate"], 100),
+            0
+        );
+        assert_eq!(embedder.dimensions(), 64);
+
+        let after = embedder.embed("fn calculate_total items");
+        assert_eq!(after.dimensions, 64);
+        assert_eq!(before.values, after.values);
+        let query = embedder.embed("discount");
+        assert!(query.cosine_similarity(&embedder.embed("fn apply_discount()")) > 0.0);
+    }
+
+    #[test]
+    fn test_add_documents_respects_max_size() {
+        let mut embedder = BagOfWordsEmbedder::from_corpus(&["a b c"], 3);
+        assert_eq!(embedder.add_documents(&["d e f"], 4), 1);
+        assert_eq!(embedder.dimensions(), 4);
+    }
 }
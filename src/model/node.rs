@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::error::{OnyxError, OnyxResult};
 use crate::model::version::VersionId;
 
 // ---------------------------------------------------------------------------
@@ -35,6 +36,9 @@ pub struct Node {
     pub created_at: DateTime<Utc>,
     /// Last modification timestamp.
     pub updated_at: DateTime<Utc>,
+    /// When the node was soft-deleted; `None` while it is live.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
     /// Type-specific extension data.
     pub extension: NodeExtension,
 }
@@ -64,6 +68,7 @@ impl Node {
             current_version: None,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
             extension: NodeExtension::from_node_type(&node_type),
         }
     }
@@ -85,6 +90,140 @@ impl Node {
         self.embedding = Some(embedding);
         self
     }
+
+    /// Whether this node has been soft-deleted.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+
+    /// Module path of a code entity; `None` for other node kinds.
+    pub fn module_path(&self) -> Option<&[String]> {
+        match &self.extension {
+            NodeExtension::CodeEntity(ext) => Some(&ext.module_path),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a code entity whose module path starts with `prefix`.
+    pub fn is_under_module(&self, prefix: &[String]) -> bool {
+        self.module_path()
+            .map_or(false, |path| path.starts_with(prefix))
+    }
+
+    /// Start building a node with the given name and content.
+    pub fn builder(name: impl Into<String>, content: impl Into<String>) -> NodeBuilder {
+        NodeBuilder::new(name, content)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// NodeBuilder: fluent node construction
+// ---------------------------------------------------------------------------
+
+/// Fluent builder for [`Node`], validated by [`build`](Self::build).
+#[derive(Debug, Clone)]
+pub struct NodeBuilder {
+    id: Option<Uuid>,
+    name: String,
+    content: String,
+    node_type: Option<NodeType>,
+    metadata: HashMap<String, String>,
+    provenance: Provenance,
+    extension: Option<NodeExtension>,
+    embedding: Option<Vec<f32>>,
+}
+
+impl NodeBuilder {
+    /// Create a builder with the required name and content.
+    pub fn new(name: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            id: None,
+            name: name.into(),
+            content: content.into(),
+            node_type: None,
+            metadata: HashMap::new(),
+            provenance: Provenance::default(),
+            extension: None,
+            embedding: None,
+        }
+    }
+
+    /// Use an explicit node ID instead of a generated one.
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Set the node type. Required.
+    pub fn node_type(mut self, node_type: NodeType) -> Self {
+        self.node_type = Some(node_type);
+        self
+    }
+
+    /// Add one metadata entry.
+    pub fn metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Set provenance.
+    pub fn provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Set the extension; defaults to the empty one for the node type.
+    pub fn extension(mut self, extension: NodeExtension) -> Self {
+        self.extension = Some(extension);
+        self
+    }
+
+    /// Set the embedding vector.
+    pub fn embedding(mut self, embedding: Vec<f32>) -> Self {
+        self.embedding = Some(embedding);
+        self
+    }
+
+    /// Build the node.
+    ///
+    /// Fails with [`OnyxError::InvalidNode`] if the name is blank, no node
+    /// type was set, the extension doesn't fit the node type, or the
+    /// embedding is empty.
+    pub fn build(self) -> OnyxResult<Node> {
+        if self.name.trim().is_empty() {
+            return Err(OnyxError::InvalidNode("name must not be empty".to_string()));
+        }
+        let node_type = self
+            .node_type
+            .ok_or_else(|| OnyxError::InvalidNode(format!("'{}' has no node type", self.name)))?;
+        if let Some(extension) = &self.extension {
+            if !extension.fits(&node_type) {
+                return Err(OnyxError::InvalidNode(format!(
+                    "'{}' is a {} but has a mismatched extension",
+                    self.name,
+                    node_type.as_str()
+                )));
+            }
+        }
+        if self.embedding.as_ref().is_some_and(Vec::is_empty) {
+            return Err(OnyxError::InvalidNode(format!(
+                "'{}' has an empty embedding",
+                self.name
+            )));
+        }
+
+        let mut node = Node::new(node_type, self.name, self.content);
+        if let Some(id) = self.id {
+            node.id = id;
+        }
+        node.metadata = self.metadata;
+        node.provenance = self.provenance;
+        if let Some(extension) = self.extension {
+            node.extension = extension;
+        }
+        node.embedding = self.embedding;
+        Ok(node)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -99,6 +238,28 @@ pub enum NodeType {
     Config,
 }
 
+impl NodeType {
+    /// Short lowercase label, e.g. `function` or `doc`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NodeType::CodeEntity(kind) => match kind {
+                CodeEntityKind::Function => "function",
+                CodeEntityKind::Struct => "struct",
+                CodeEntityKind::Enum => "enum",
+                CodeEntityKind::Trait => "trait",
+                CodeEntityKind::Impl => "impl",
+                CodeEntityKind::Module => "module",
+                CodeEntityKind::Constant => "constant",
+                CodeEntityKind::TypeAlias => "type_alias",
+                CodeEntityKind::Macro => "macro",
+            },
+            NodeType::Doc => "doc",
+            NodeType::Test => "test",
+            NodeType::Config => "config",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CodeEntityKind {
     Function,
@@ -134,6 +295,18 @@ impl NodeExtension {
             NodeType::Config => NodeExtension::Config(ConfigExt::default()),
         }
     }
+
+    /// Whether this extension belongs on a node of type `nt`.
+    fn fits(&self, nt: &NodeType) -> bool {
+        matches!(
+            (self, nt),
+            (NodeExtension::CodeEntity(_), NodeType::CodeEntity(_))
+                | (NodeExtension::Doc(_), NodeType::Doc)
+                | (NodeExtension::Test(_), NodeType::Test)
+                | (NodeExtension::Config(_), NodeType::Config)
+                | (NodeExtension::None, _)
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -209,6 +382,11 @@ pub enum DocFormat {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestExt {
     pub test_kind: TestKind,
+    #[serde(default)]
+    pub framework: TestFramework,
+    /// Names of the functions the test calls, e.g. `apply_discount`.
+    #[serde(default)]
+    pub targets: Vec<String>,
     pub target_ids: Vec<Uuid>,
     pub last_result: Option<TestResult>,
 }
@@ -217,12 +395,25 @@ impl Default for TestExt {
     fn default() -> Self {
         Self {
             test_kind: TestKind::Unit,
+            framework: TestFramework::default(),
+            targets: Vec::new(),
             target_ids: Vec::new(),
             last_result: None,
         }
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TestFramework {
+    /// `#[test]`
+    #[default]
+    RustTest,
+    /// `#[tokio::test]`
+    Tokio,
+    Pytest,
+    GoTesting,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TestKind {
     Unit,
@@ -242,6 +433,9 @@ pub struct TestResult {
 pub struct ConfigExt {
     pub config_type: ConfigType,
     pub format: ConfigFormat,
+    /// Dotted key paths defined by the file, e.g. `database.max_connections`.
+    #[serde(default)]
+    pub keys: Vec<String>,
 }
 
 impl Default for ConfigExt {
@@ -249,6 +443,7 @@ impl Default for ConfigExt {
         Self {
             config_type: ConfigType::Cargo,
             format: ConfigFormat::Toml,
+            keys: Vec::new(),
         }
     }
 }
@@ -260,6 +455,10 @@ pub enum ConfigType {
     Docker,
     Env,
     Build,
+    /// Application settings, e.g. `config.toml`.
+    App,
+    /// One crate dependency declared by a `Cargo.toml`.
+    Dependency,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -268,6 +467,8 @@ pub enum ConfigFormat {
     Yaml,
     Json,
     Ini,
+    /// `KEY=value` lines, as in `.env` files.
+    Env,
 }
 
 // ---------------------------------------------------------------------------
@@ -306,3 +507,54 @@ impl Provenance {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_sets_every_field() {
+        let id = Uuid::new_v4();
+        let node = Node::builder("calculate_total", "pub fn calculate_total() -> u32 { 0 }")
+            .id(id)
+            .node_type(NodeType::CodeEntity(CodeEntityKind::Function))
+            .provenance(Provenance::new("src/billing.rs").with_lines(3, 5))
+            .extension(NodeExtension::CodeEntity(CodeEntityExt {
+                signature: Some("pub fn calculate_total() -> u32".to_string()),
+                visibility: Visibility::Public,
+                module_path: vec!["billing".to_string()],
+                ..Default::default()
+            }))
+            .embedding(vec![0.5, 0.5])
+            .metadata("owner", "payments")
+            .build()
+            .unwrap();
+
+        assert_eq!(node.id, id);
+        assert_eq!(node.node_type.as_str(), "function");
+        assert_eq!(node.provenance.file_path.as_deref(), Some("src/billing.rs"));
+        assert_eq!(node.provenance.line_range, Some((3, 5)));
+        assert_eq!(node.module_path(), Some(&["billing".to_string()][..]));
+        assert_eq!(node.embedding, Some(vec![0.5, 0.5]));
+        assert_eq!(node.metadata["owner"], "payments");
+        assert_eq!(
+            node.content_hash,
+            Node::new(NodeType::Doc, "", "pub fn calculate_total() -> u32 { 0 }").content_hash
+        );
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_nodes() {
+        let missing_type = Node::builder("total", "").build();
+        assert!(matches!(missing_type, Err(OnyxError::InvalidNode(_))));
+
+        let blank_name = Node::builder("  ", "").node_type(NodeType::Doc).build();
+        assert!(matches!(blank_name, Err(OnyxError::InvalidNode(_))));
+
+        let mismatched = Node::builder("README", "")
+            .node_type(NodeType::Doc)
+            .extension(NodeExtension::Test(TestExt::default()))
+            .build();
+        assert!(matches!(mismatched, Err(OnyxError::InvalidNode(_))));
+    }
+}
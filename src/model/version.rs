@@ -81,6 +81,27 @@ impl VersionEntry {
         }
     }
 
+    /// Create a version on `branch` marking the entity as deleted.
+    ///
+    /// Content is unchanged, so earlier states remain reconstructable.
+    pub fn deletion(
+        entity_id: Uuid,
+        parent_version: Option<VersionId>,
+        branch: impl Into<String>,
+    ) -> Self {
+        Self {
+            version_id: new_version_id(),
+            entity_id,
+            parent_version,
+            branch: branch.into(),
+            diff: Diff::Deleted,
+            commit_id: None,
+            author: None,
+            message: Some("Deleted".to_string()),
+            timestamp: Utc::now(),
+        }
+    }
+
     /// Set the commit ID for this version.
     pub fn with_commit(mut self, commit: impl Into<String>) -> Self {
         self.commit_id = Some(commit.into());
@@ -134,6 +155,9 @@ pub enum Diff {
 
     /// Multiple changes in one version.
     Composite(Vec<Diff>),
+
+    /// The entity was soft-deleted; content is unchanged.
+    Deleted,
 }
 
 impl Diff {
@@ -152,11 +176,114 @@ impl Diff {
                 ..
             } => additions + deletions,
             Diff::MetadataChanged { changed_fields } => changed_fields.len(),
+            Diff::Deleted => 0,
             Diff::Composite(diffs) => diffs.iter().map(|d| d.lines_changed()).sum(),
         }
     }
 }
 
+/// Count added and removed lines between two versions of some content.
+///
+/// Lines are compared as multisets, which ignores reordering but is cheap and
+/// good enough for change statistics.
+pub fn line_changes(old: &str, new: &str) -> (usize, usize) {
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for line in old.lines() {
+        *remaining.entry(line).or_insert(0) += 1;
+    }
+
+    let mut additions = 0;
+    for line in new.lines() {
+        match remaining.get_mut(line) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => additions += 1,
+        }
+    }
+    let deletions = remaining.values().sum();
+    (additions, deletions)
+}
+
+/// Lines of unchanged context around each hunk of a [`unified_diff`].
+const DIFF_CONTEXT: usize = 3;
+
+/// Render the changes from `old` to `new` as a unified diff, with `from`
+/// and `to` labelling the two sides. Empty when the contents are equal.
+pub fn unified_diff(old: &str, new: &str, from: &str, to: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence of lines, filled from the end: `lcs[i][j]`
+    // is its length for `old[i..]` and `new[j..]`.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // Walk the table into one (tag, line) per output line.
+    let mut lines: Vec<(char, &str)> = Vec::with_capacity(old.len().max(new.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', old[i]));
+            i += 1;
+            j += 1;
+        } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', new[j]));
+            j += 1;
+        }
+    }
+
+    // Group changed lines into hunks, merging hunks whose context overlaps.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (index, _) in lines.iter().enumerate().filter(|(_, (tag, _))| *tag != ' ') {
+        let start = index.saturating_sub(DIFF_CONTEXT);
+        let end = (index + 1 + DIFF_CONTEXT).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {from}\n+++ {to}\n");
+    let (mut old_line, mut new_line, mut cursor) = (0, 0, 0);
+    for (start, end) in hunks {
+        for (tag, _) in &lines[cursor..start] {
+            old_line += usize::from(*tag != '+');
+            new_line += usize::from(*tag != '-');
+        }
+        let hunk = &lines[start..end];
+        let old_len = hunk.iter().filter(|(tag, _)| *tag != '+').count();
+        let new_len = hunk.iter().filter(|(tag, _)| *tag != '-').count();
+        // An empty side is numbered by the line before it, as in GNU diff.
+        let old_start = old_line + usize::from(old_len > 0);
+        let new_start = new_line + usize::from(new_len > 0);
+        out.push_str(&format!(
+            "@@ -{old_start},{old_len} +{new_start},{new_len} @@\n"
+        ));
+        for (tag, line) in hunk {
+            out.push(*tag);
+            out.push_str(line);
+            out.push('\n');
+        }
+        old_line += old_len;
+        new_line += new_len;
+        cursor = end;
+    }
+    out
+}
+
 // ---------------------------------------------------------------------------
 // Branch: named version streams
 // ---------------------------------------------------------------------------
@@ -188,3 +315,26 @@ impl Branch {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let diff = unified_diff(old, new, "v1", "v2");
+        assert_eq!(
+            diff,
+            "--- v1\n+++ v2\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -8,3 +8,4 @@\n h\n i\n j\n+k\n"
+        );
+        assert_eq!(unified_diff(old, old, "v1", "v2"), "");
+        assert_eq!(
+            unified_diff("", "x\n", "v1", "v2"),
+            "--- v1\n+++ v2\n@@ -0,0 +1,1 @@\n+x\n"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+//! Query helpers over any storage backend.
+//!
+//! [`crate::query::execute_query`] and its siblings take a
+//! [`TransactionManager`]. The functions here take [`QueryStores`] instead,
+//! borrowed trait objects for whichever graph, vector and history stores
+//! hold the data, so they work the same over RocksDB, SurrealDB or the
+//! in-memory stores.
+
+use std::collections::{HashMap, HashSet};
+
+use uuid::Uuid;
+
+use crate::error::{OnyxError, OnyxResult};
+use crate::model::edge::EdgeType;
+use crate::query::{
+    impact_in_direction, run_query, ImpactDirection, QueryOptions, QueryResult, QueryResultItem,
+    QueryTrace, RelationshipExplanation, RelationshipHop, ResultSource,
+};
+use crate::store::graph::GraphStore;
+use crate::store::history::HistoryStore;
+use crate::store::transaction::{AsyncTransactionManager, TransactionManager};
+use crate::store::vector::{cosine_similarity, VectorStore};
+
+/// The stores a query reads from.
+#[derive(Clone, Copy)]
+pub struct QueryStores<'a> {
+    pub graph: &'a dyn GraphStore,
+    pub vector: &'a dyn VectorStore,
+    pub history: &'a dyn HistoryStore,
+}
+
+impl<'a> QueryStores<'a> {
+    pub fn new(
+        graph: &'a dyn GraphStore,
+        vector: &'a dyn VectorStore,
+        history: &'a dyn HistoryStore,
+    ) -> Self {
+        Self {
+            graph,
+            vector,
+            history,
+        }
+    }
+}
+
+impl<'a> From<&'a TransactionManager> for QueryStores<'a> {
+    /// The stores currently backing `stores`, SurrealDB or in-memory.
+    fn from(stores: &'a TransactionManager) -> Self {
+        Self::new(stores.graph(), stores.vector(), stores.history())
+    }
+}
+
+impl<'a> From<&'a AsyncTransactionManager> for QueryStores<'a> {
+    fn from(stores: &'a AsyncTransactionManager) -> Self {
+        Self::new(
+            &stores.graph_store,
+            &stores.vector_store,
+            &stores.history_store,
+        )
+    }
+}
+
+/// Run a semantic query; see [`crate::query::execute_query`].
+pub async fn execute_query(
+    stores: QueryStores<'_>,
+    query_embedding: Option<&[f32]>,
+    options: &QueryOptions,
+) -> OnyxResult<QueryResult> {
+    let mut trace = QueryTrace::default();
+    run_query(stores, query_embedding, options, &mut trace).await
+}
+
+/// [`execute_query`], also returning a [`QueryTrace`] of how each node was
+/// found and scored.
+pub async fn execute_query_explain(
+    stores: QueryStores<'_>,
+    query_embedding: Option<&[f32]>,
+    options: &QueryOptions,
+) -> OnyxResult<(QueryResult, QueryTrace)> {
+    let mut trace = QueryTrace::default();
+    let result = run_query(stores, query_embedding, options, &mut trace).await?;
+    Ok((result, trace))
+}
+
+/// Nodes affected by a change to `node_id`; see
+/// [`crate::query::impact_analysis`].
+pub async fn impact_analysis(
+    stores: QueryStores<'_>,
+    node_id: &Uuid,
+    max_depth: usize,
+) -> OnyxResult<Vec<(Uuid, String, usize)>> {
+    impact_in_direction(
+        stores.graph,
+        node_id,
+        max_depth,
+        ImpactDirection::Dependents,
+    )
+    .await
+}
+
+/// Given a node, find all tests that cover it (directly or transitively).
+pub async fn find_covering_tests(
+    stores: QueryStores<'_>,
+    node_id: &Uuid,
+    max_depth: usize,
+) -> OnyxResult<Vec<QueryResultItem>> {
+    let graph = stores.graph;
+    let mut tests: Vec<QueryResultItem> = Vec::new();
+    let mut visited: HashSet<Uuid> = HashSet::new();
+
+    // Direct tests
+    let direct = graph
+        .get_active_inbound(node_id, Some(&[EdgeType::TestsOf]))
+        .await?;
+
+    for (_, test_node) in &direct {
+        if !visited.contains(&test_node.id) {
+            visited.insert(test_node.id);
+            tests.push(QueryResultItem {
+                node_id: test_node.id,
+                name: test_node.name.clone(),
+                content: test_node.content.clone(),
+                source: ResultSource::GraphTraversal,
+                score: 1.0,
+                depth: 1,
+                edge_path: vec![EdgeType::TestsOf],
+                versions: Vec::new(),
+            });
+        }
+    }
+
+    // Transitive: tests of callers
+    if max_depth > 1 {
+        let callers = graph
+            .get_active_inbound(node_id, Some(&[EdgeType::Calls]))
+            .await?;
+
+        for (_, caller_node) in &callers {
+            let caller_tests = graph
+                .get_active_inbound(&caller_node.id, Some(&[EdgeType::TestsOf]))
+                .await?;
+
+            for (_, test_node) in &caller_tests {
+                if !visited.contains(&test_node.id) {
+                    visited.insert(test_node.id);
+                    tests.push(QueryResultItem {
+                        node_id: test_node.id,
+                        name: test_node.name.clone(),
+                        content: test_node.content.clone(),
+                        source: ResultSource::GraphTraversal,
+                        score: 0.7,
+                        depth: 2,
+                        edge_path: vec![EdgeType::Calls, EdgeType::TestsOf],
+                        versions: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(tests)
+}
+
+/// Explain why `a` and `b` are related; see
+/// [`crate::query::explain_relationship`].
+///
+/// Edges are followed either way. The best path has the fewest hops, then
+/// the highest product of edge confidences. `SimilarTo` edges are skipped,
+/// since semantic similarity is reported separately from the embeddings.
+pub async fn explain_relationship(
+    stores: QueryStores<'_>,
+    a: &Uuid,
+    b: &Uuid,
+    max_depth: usize,
+) -> OnyxResult<Option<RelationshipExplanation>> {
+    let graph = stores.graph;
+    let from = graph
+        .get_node(a)
+        .await?
+        .ok_or(OnyxError::NodeNotFound(*a))?;
+    if graph.get_node(b).await?.is_none() {
+        return Err(OnyxError::NodeNotFound(*b));
+    }
+
+    // Breadth-first from `a`, a layer at a time, so every node is reached
+    // in the fewest hops; within a layer the most confident edge wins.
+    let mut confidence: HashMap<Uuid, f64> = HashMap::from([(*a, 1.0)]);
+    let mut came_from: HashMap<Uuid, (Uuid, RelationshipHop)> = HashMap::new();
+    let mut frontier = vec![*a];
+    for _ in 0..max_depth {
+        if confidence.contains_key(b) {
+            break;
+        }
+
+        let mut next: HashMap<Uuid, (f64, Uuid, RelationshipHop)> = HashMap::new();
+        for id in &frontier {
+            let reached = confidence[id];
+            let outbound = graph.get_active_neighbors(id, None).await?;
+            let inbound = graph.get_active_inbound(id, None).await?;
+            let linked = outbound
+                .into_iter()
+                .map(|(edge, node)| (edge, node, true))
+                .chain(inbound.into_iter().map(|(edge, node)| (edge, node, false)));
+
+            for (edge, node, forward) in linked {
+                if edge.edge_type == EdgeType::SimilarTo || confidence.contains_key(&node.id) {
+                    continue;
+                }
+                let through = reached * edge.confidence;
+                if let Some((best, ..)) = next.get(&node.id) {
+                    if *best >= through {
+                        continue;
+                    }
+                }
+                let hop = RelationshipHop {
+                    edge_type: edge.edge_type,
+                    forward,
+                    node_id: node.id,
+                    name: node.name,
+                };
+                next.insert(node.id, (through, *id, hop));
+            }
+        }
+
+        frontier = next.keys().copied().collect();
+        for (id, (through, previous, hop)) in next {
+            confidence.insert(id, through);
+            came_from.insert(id, (previous, hop));
+        }
+    }
+
+    let Some(&path_confidence) = confidence.get(b) else {
+        return Ok(None);
+    };
+    let mut hops = Vec::new();
+    let mut current = *b;
+    while let Some((previous, hop)) = came_from.remove(&current) {
+        hops.push(hop);
+        current = previous;
+    }
+    hops.reverse();
+
+    let similarity = match (stores.vector.get(a).await?, stores.vector.get(b).await?) {
+        (Some(x), Some(y)) => Some(cosine_similarity(&x, &y)),
+        _ => None,
+    };
+
+    Ok(Some(RelationshipExplanation {
+        from: from.name,
+        hops,
+        confidence: path_confidence,
+        similarity,
+    }))
+}
+
+#[cfg(all(test, feature = "rocksdb-storage"))]
+mod tests {
+    use super::*;
+    use crate::model::edge::Edge;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::model::version::VersionEntry;
+    use crate::store::persistent::{open_db, RocksGraphStore, RocksHistoryStore, RocksVectorStore};
+
+    #[tokio::test]
+    async fn test_execute_query_over_rocks_stores() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(dir.path()).unwrap();
+        let graph = RocksGraphStore::new(db.clone());
+        let vector = RocksVectorStore::new(db.clone(), 3);
+        let history = RocksHistoryStore::new(db);
+
+        let function = |name: &str| {
+            Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                name,
+                format!("fn {name}() {{}}"),
+            )
+        };
+        let (caller, callee) = (function("checkout"), function("calculate_total"));
+        let (caller_id, callee_id) = (caller.id, callee.id);
+        graph.add_node(caller).await.unwrap();
+        graph.add_node(callee).await.unwrap();
+        graph
+            .add_edge(Edge::new(EdgeType::Calls, caller_id, callee_id))
+            .await
+            .unwrap();
+        vector.insert(caller_id, vec![1.0, 0.0, 0.0]).await.unwrap();
+        vector.insert(callee_id, vec![0.0, 1.0, 0.0]).await.unwrap();
+        history
+            .create_version(VersionEntry::initial(caller_id, "fn checkout() {}"))
+            .await
+            .unwrap();
+
+        let stores = QueryStores::new(&graph, &vector, &history);
+        let options = QueryOptions {
+            top_k: 1,
+            max_depth: 1,
+            include_history: true,
+            ..Default::default()
+        };
+        let result = execute_query(stores, Some(&[1.0, 0.0, 0.0]), &options)
+            .await
+            .unwrap();
+
+        let names: Vec<&str> = result.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["checkout", "calculate_total"]);
+        assert_eq!(result.items[0].source, ResultSource::VectorSearch);
+        assert_eq!(result.items[0].versions.len(), 1);
+        assert_eq!(result.items[1].depth, 1);
+
+        let impacted = impact_analysis(stores, &callee_id, 2).await.unwrap();
+        assert_eq!(impacted, [(caller_id, "checkout".to_string(), 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_query_over_fresh_rocks_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(dir.path()).unwrap();
+        // A fresh store opened without embeddings has no dimension yet.
+        let graph = RocksGraphStore::new(db.clone());
+        let vector = RocksVectorStore::new(db.clone(), 0);
+        let history = RocksHistoryStore::new(db);
+
+        let stores = QueryStores::new(&graph, &vector, &history);
+        let result = execute_query(stores, Some(&[1.0, 0.0, 0.0]), &QueryOptions::default())
+            .await
+            .unwrap();
+        assert!(result.items.is_empty());
+        assert_eq!(result.nodes_examined, 0);
+    }
+}
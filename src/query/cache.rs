@@ -0,0 +1,200 @@
+use lru::LruCache;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::error::OnyxResult;
+use crate::query::{execute_query, QueryOptions, QueryResult};
+use crate::store::transaction::TransactionManager;
+
+// ---------------------------------------------------------------------------
+// Query cache: reuse results of repeated identical queries
+// ---------------------------------------------------------------------------
+
+/// Embedding components are rounded to this many steps per unit before
+/// hashing, so float noise doesn't defeat the cache.
+const EMBEDDING_QUANTIZATION: f32 = 10_000.0;
+
+/// Hit and miss counts of a [`QueryCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct CachedResult {
+    result: QueryResult,
+    /// [`TransactionManager::generation`] when the result was computed.
+    generation: u64,
+    stored_at: Instant,
+}
+
+/// LRU cache of [`QueryResult`]s keyed by query embedding and options.
+///
+/// Entries expire after a TTL and whenever the stores are written through
+/// their [`TransactionManager`]. Use one cache per manager.
+pub struct QueryCache {
+    entries: Mutex<LruCache<u64, CachedResult>>,
+    ttl: Duration,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl QueryCache {
+    /// Create a cache holding up to `capacity` results for at most `ttl`.
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> QueryCacheStats {
+        QueryCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Drop every cached result.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    fn get(&self, key: u64, generation: u64) -> Option<QueryResult> {
+        let mut entries = self.entries.lock().unwrap();
+        let fresh = entries
+            .peek(&key)
+            .map(|e| e.generation == generation && e.stored_at.elapsed() < self.ttl);
+        let result = match fresh {
+            Some(true) => entries.get(&key).map(|e| e.result.clone()),
+            Some(false) => {
+                entries.pop(&key);
+                None
+            }
+            None => None,
+        };
+        let (counter, metric) = if result.is_some() {
+            (&self.hits, "onyx_query_cache_hits_total")
+        } else {
+            (&self.misses, "onyx_query_cache_misses_total")
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        metrics::counter!(metric).increment(1);
+        result
+    }
+
+    fn insert(&self, key: u64, generation: u64, result: QueryResult) {
+        self.entries.lock().unwrap().put(
+            key,
+            CachedResult {
+                result,
+                generation,
+                stored_at: Instant::now(),
+            },
+        );
+    }
+}
+
+/// Hash a query embedding (quantized) together with every query option.
+fn cache_key(query_embedding: Option<&[f32]>, options: &QueryOptions) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    query_embedding
+        .map(|e| {
+            e.iter()
+                .map(|v| (v * EMBEDDING_QUANTIZATION).round() as i64)
+                .collect::<Vec<_>>()
+        })
+        .hash(&mut hasher);
+    options.max_depth.hash(&mut hasher);
+    options.top_k.hash(&mut hasher);
+    options.edge_types.hash(&mut hasher);
+    options.time_range.hash(&mut hasher);
+    options.include_history.hash(&mut hasher);
+    options.min_confidence.to_bits().hash(&mut hasher);
+    options.min_score.map(f64::to_bits).hash(&mut hasher);
+    options.offset.hash(&mut hasher);
+    options.limit.hash(&mut hasher);
+    options.max_nodes_examined.hash(&mut hasher);
+    options.max_time.hash(&mut hasher);
+    options.diversity_lambda.map(f64::to_bits).hash(&mut hasher);
+    let mut excluded: Vec<&Uuid> = options.exclude_ids.iter().collect();
+    excluded.sort();
+    excluded.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// [`execute_query`], answered from `cache` when the same query ran since
+/// the last write to `stores`.
+pub async fn execute_query_cached(
+    stores: &TransactionManager,
+    cache: &QueryCache,
+    query_embedding: Option<&[f32]>,
+    options: &QueryOptions,
+) -> OnyxResult<QueryResult> {
+    let key = cache_key(query_embedding, options);
+    let generation = stores.generation();
+    if let Some(result) = cache.get(key, generation) {
+        return Ok(result);
+    }
+
+    let result = execute_query(stores, query_embedding, options).await?;
+    // A result cut short by `max_time` depends on how fast this run was.
+    if !result.truncated {
+        cache.insert(key, generation, result.clone());
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::store::transaction::TransactionOp;
+
+    fn insert(stores: &mut TransactionManager, name: &str, embedding: Vec<f32>) {
+        let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "");
+        let id = node.id;
+        stores
+            .execute_batch(vec![
+                TransactionOp::InsertNode(node),
+                TransactionOp::InsertEmbedding { id, embedding },
+            ])
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repeated_query_hits_until_a_write() {
+        let mut stores = TransactionManager::new();
+        insert(&mut stores, "total", vec![1.0, 0.0, 0.0]);
+        let cache = QueryCache::new(NonZeroUsize::new(8).unwrap(), Duration::from_secs(60));
+        let options = QueryOptions {
+            max_depth: 0,
+            ..Default::default()
+        };
+        let query = [1.0, 0.0, 0.0];
+
+        let first = execute_query_cached(&stores, &cache, Some(&query), &options)
+            .await
+            .unwrap();
+        // Float noise below the quantization step still hits.
+        let second = execute_query_cached(&stores, &cache, Some(&[1.0, 0.0, 1e-7]), &options)
+            .await
+            .unwrap();
+        assert_eq!(cache.stats(), QueryCacheStats { hits: 1, misses: 1 });
+        assert_eq!(first.items.len(), second.items.len());
+
+        insert(&mut stores, "subtotal", vec![0.9, 0.1, 0.0]);
+        let third = execute_query_cached(&stores, &cache, Some(&query), &options)
+            .await
+            .unwrap();
+        assert_eq!(cache.stats(), QueryCacheStats { hits: 1, misses: 2 });
+        assert_eq!(third.items.len(), 2);
+    }
+}
@@ -1,13 +1,22 @@
 use chrono::{DateTime, Utc};
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
-use crate::error::OnyxResult;
+use crate::error::{OnyxError, OnyxResult};
 use crate::model::edge::EdgeType;
-use crate::store::graph::GraphStore;
+use crate::store::graph::{GraphStore, TraversalBudget};
 use crate::store::history::HistoryStore;
 use crate::store::transaction::TransactionManager;
-use crate::store::vector::VectorStore;
+use crate::store::vector::{check_finite, cosine_similarity, VectorStore};
+
+pub mod api;
+pub mod cache;
+
+pub use api::QueryStores;
+pub use cache::{execute_query_cached, QueryCache, QueryCacheStats};
 
 // ---------------------------------------------------------------------------
 // Query Engine: multi-hop, cross-store retrieval and reasoning
@@ -28,6 +37,24 @@ pub struct QueryOptions {
     pub include_history: bool,
     /// Minimum confidence score for edges to follow.
     pub min_confidence: f64,
+    /// Minimum similarity for vector search hits (None = keep all `top_k`).
+    pub min_score: Option<f64>,
+    /// Nodes to leave out of the results, e.g. the node a "more like this"
+    /// query started from.
+    pub exclude_ids: HashSet<Uuid>,
+    /// Number of ranked items to skip, for paging.
+    pub offset: usize,
+    /// Maximum number of items to return after `offset` (None = all).
+    /// Independent of `top_k`, which only sizes the vector search.
+    pub limit: Option<usize>,
+    /// Stop after examining this many nodes (None = unlimited).
+    pub max_nodes_examined: Option<usize>,
+    /// Stop once the query has run this long (None = no time limit).
+    pub max_time: Option<Duration>,
+    /// Re-rank results by maximal marginal relevance, trading relevance
+    /// (1.0) against dissimilarity to higher-ranked results (0.0). None
+    /// keeps the plain relevance order.
+    pub diversity_lambda: Option<f64>,
 }
 
 impl Default for QueryOptions {
@@ -39,10 +66,164 @@ impl Default for QueryOptions {
             time_range: None,
             include_history: false,
             min_confidence: 0.0,
+            min_score: None,
+            exclude_ids: HashSet::new(),
+            offset: 0,
+            limit: None,
+            max_nodes_examined: None,
+            max_time: None,
+            diversity_lambda: None,
         }
     }
 }
 
+/// Deepest graph expansion a [`QueryOptionsBuilder`] accepts.
+pub const MAX_QUERY_DEPTH: usize = 10;
+
+/// Embedding similarity at which [`RelationshipExplanation`] counts two
+/// nodes as semantically similar.
+pub const SEMANTIC_SIMILARITY_THRESHOLD: f32 = 0.5;
+
+impl QueryOptions {
+    /// Start building options from the defaults.
+    pub fn builder() -> QueryOptionsBuilder {
+        QueryOptionsBuilder::default()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// QueryOptionsBuilder: fluent, validated query options
+// ---------------------------------------------------------------------------
+
+/// Fluent builder for [`QueryOptions`], validated by [`build`](Self::build).
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptionsBuilder {
+    options: QueryOptions,
+}
+
+impl QueryOptionsBuilder {
+    /// Set the number of vector search results to retrieve.
+    pub fn top_k(mut self, top_k: usize) -> Self {
+        self.options.top_k = top_k;
+        self
+    }
+
+    /// Set the maximum graph traversal depth.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_depth = max_depth;
+        self
+    }
+
+    /// Only follow edges of these types.
+    pub fn edge_types(mut self, edge_types: impl IntoIterator<Item = EdgeType>) -> Self {
+        self.options.edge_types = Some(edge_types.into_iter().collect());
+        self
+    }
+
+    /// Only include versions recorded within this range.
+    pub fn time_range(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.options.time_range = Some((from, to));
+        self
+    }
+
+    /// Include version history in results.
+    pub fn include_history(mut self, include_history: bool) -> Self {
+        self.options.include_history = include_history;
+        self
+    }
+
+    /// Set the minimum confidence for edges to follow.
+    pub fn min_confidence(mut self, min_confidence: f64) -> Self {
+        self.options.min_confidence = min_confidence;
+        self
+    }
+
+    /// Drop vector search hits below this similarity.
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.options.min_score = Some(min_score);
+        self
+    }
+
+    /// Leave these nodes out of the results.
+    pub fn exclude_ids(mut self, ids: impl IntoIterator<Item = Uuid>) -> Self {
+        self.options.exclude_ids.extend(ids);
+        self
+    }
+
+    /// Skip this many ranked items, for paging.
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.options.offset = offset;
+        self
+    }
+
+    /// Return at most this many items after the offset.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.options.limit = Some(limit);
+        self
+    }
+
+    /// Stop after examining this many nodes.
+    pub fn max_nodes_examined(mut self, max_nodes_examined: usize) -> Self {
+        self.options.max_nodes_examined = Some(max_nodes_examined);
+        self
+    }
+
+    /// Stop once the query has run this long.
+    pub fn max_time(mut self, max_time: Duration) -> Self {
+        self.options.max_time = Some(max_time);
+        self
+    }
+
+    /// Diversify results by maximal marginal relevance with this lambda.
+    pub fn diversity_lambda(mut self, lambda: f64) -> Self {
+        self.options.diversity_lambda = Some(lambda);
+        self
+    }
+
+    /// Build the options.
+    ///
+    /// Fails with [`OnyxError::InvalidQueryOptions`] if `top_k` is zero,
+    /// `max_depth` exceeds [`MAX_QUERY_DEPTH`], `min_confidence` or
+    /// `diversity_lambda` is outside `0.0..=1.0`, or the time range ends
+    /// before it starts.
+    pub fn build(self) -> OnyxResult<QueryOptions> {
+        let options = self.options;
+        if options.top_k == 0 {
+            return Err(OnyxError::InvalidQueryOptions(
+                "top_k must be greater than 0".to_string(),
+            ));
+        }
+        if options.max_depth > MAX_QUERY_DEPTH {
+            return Err(OnyxError::InvalidQueryOptions(format!(
+                "max_depth {} exceeds the maximum of {MAX_QUERY_DEPTH}",
+                options.max_depth
+            )));
+        }
+        if !(0.0..=1.0).contains(&options.min_confidence) {
+            return Err(OnyxError::InvalidQueryOptions(format!(
+                "min_confidence {} is outside 0.0..=1.0",
+                options.min_confidence
+            )));
+        }
+        if let Some(lambda) = options.diversity_lambda {
+            if !(0.0..=1.0).contains(&lambda) {
+                return Err(OnyxError::InvalidQueryOptions(format!(
+                    "diversity_lambda {} is outside 0.0..=1.0",
+                    lambda
+                )));
+            }
+        }
+        if let Some((from, to)) = options.time_range {
+            if to < from {
+                return Err(OnyxError::InvalidQueryOptions(
+                    "time_range ends before it starts".to_string(),
+                ));
+            }
+        }
+        Ok(options)
+    }
+}
+
 /// A single item in a query result.
 #[derive(Debug, Clone)]
 pub struct QueryResultItem {
@@ -87,12 +268,155 @@ pub struct VersionInfo {
 pub struct QueryResult {
     /// The items in the result, sorted by relevance.
     pub items: Vec<QueryResultItem>,
+    /// Number of items found before `offset` and `limit` were applied.
+    pub total_before_limit: usize,
     /// Total nodes examined during the query.
     pub nodes_examined: usize,
+    /// Whether `max_nodes_examined` or `max_time` cut the query short; the
+    /// items are then the best found before the budget ran out.
+    pub truncated: bool,
     /// How long the query took.
     pub query_time_ms: u64,
 }
 
+/// How a query arrived at its ranking; see [`execute_query_explain`].
+#[derive(Debug, Clone, Default)]
+pub struct QueryTrace {
+    /// Vector search hits with their raw similarity, best first.
+    pub vector_candidates: Vec<(Uuid, f32)>,
+    /// Nodes reached by graph expansion from each seed.
+    pub expansions: Vec<TraceExpansion>,
+    /// Score contributions per node, in the order they were applied.
+    pub contributions: HashMap<Uuid, Vec<ScoreContribution>>,
+}
+
+/// A node reached by expanding from a vector search seed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceExpansion {
+    pub seed_id: Uuid,
+    pub node_id: Uuid,
+    pub depth: usize,
+}
+
+/// Part of a node's final score and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreContribution {
+    /// `VectorSearch` for similarity, `GraphTraversal` for expansion scores
+    /// and multi-source boosts.
+    pub source: ResultSource,
+    pub amount: f64,
+    /// The seed whose expansion contributed, for traversal contributions.
+    pub seed_id: Option<Uuid>,
+}
+
+impl QueryTrace {
+    fn contribute(
+        &mut self,
+        node_id: Uuid,
+        source: ResultSource,
+        amount: f64,
+        seed_id: Option<Uuid>,
+    ) {
+        self.contributions
+            .entry(node_id)
+            .or_default()
+            .push(ScoreContribution {
+                source,
+                amount,
+                seed_id,
+            });
+    }
+}
+
+impl fmt::Display for QueryTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Vector candidates:")?;
+        for (id, score) in &self.vector_candidates {
+            writeln!(f, "  {} {:.3}", id, score)?;
+        }
+        writeln!(f, "Expansions:")?;
+        for e in &self.expansions {
+            writeln!(f, "  {} -> {} (depth {})", e.seed_id, e.node_id, e.depth)?;
+        }
+        writeln!(f, "Score contributions:")?;
+        for (id, contributions) in &self.contributions {
+            let parts: Vec<String> = contributions
+                .iter()
+                .map(|c| format!("{:?} {:+.3}", c.source, c.amount))
+                .collect();
+            writeln!(f, "  {}: {}", id, parts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Why two nodes are related; see [`explain_relationship`].
+#[derive(Debug, Clone)]
+pub struct RelationshipExplanation {
+    /// Name of the node the path starts from.
+    pub from: String,
+    /// Each step of the connecting path, ending at the other node.
+    pub hops: Vec<RelationshipHop>,
+    /// Product of the edge confidences along the path.
+    pub confidence: f64,
+    /// Cosine similarity of the two nodes' embeddings, if both have one.
+    pub similarity: Option<f32>,
+}
+
+/// One edge along a [`RelationshipExplanation`] path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelationshipHop {
+    pub edge_type: EdgeType,
+    /// Whether the edge points along the path; false if it points back
+    /// towards the start, e.g. the next node calls the previous one.
+    pub forward: bool,
+    /// The node this hop arrives at.
+    pub node_id: Uuid,
+    pub name: String,
+}
+
+impl RelationshipExplanation {
+    /// Names of the nodes between the two ends of the path.
+    pub fn intermediates(&self) -> Vec<&str> {
+        match self.hops.split_last() {
+            Some((_, between)) => between.iter().map(|h| h.name.as_str()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The type of each edge along the path, in order.
+    pub fn edge_types(&self) -> Vec<EdgeType> {
+        self.hops.iter().map(|h| h.edge_type.clone()).collect()
+    }
+
+    /// Whether the two nodes' embeddings are at least
+    /// [`SEMANTIC_SIMILARITY_THRESHOLD`] similar.
+    pub fn is_semantically_similar(&self) -> bool {
+        self.similarity
+            .is_some_and(|s| s >= SEMANTIC_SIMILARITY_THRESHOLD)
+    }
+}
+
+impl fmt::Display for RelationshipExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.from)?;
+        for hop in &self.hops {
+            if hop.forward {
+                write!(f, " -[{}]-> {}", hop.edge_type, hop.name)?;
+            } else {
+                write!(f, " <-[{}]- {}", hop.edge_type, hop.name)?;
+            }
+        }
+        match self.similarity {
+            Some(similarity) if self.is_semantically_similar() => {
+                write!(f, " (semantically similar, {:.3})", similarity)
+            }
+            Some(similarity) => write!(f, " (not semantically similar, {:.3})", similarity),
+            None => Ok(()),
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Query execution
 // ---------------------------------------------------------------------------
@@ -102,26 +426,83 @@ pub struct QueryResult {
 /// The query engine follows this strategy:
 /// 1. If an embedding is provided, find semantically similar nodes via vector search
 /// 2. For each vector result, expand context via graph traversal
-/// 3. Apply temporal filtering if a time range is specified
-/// 4. Fuse results, deduplicate, and rank by combined relevance
+/// 3. Fuse results, deduplicate, and rank by combined relevance, then
+///    diversify the ranking if `diversity_lambda` is set
+/// 4. Page through the ranked items with `offset` and `limit`
+/// 5. Attach version history to the returned items if requested
+///
+/// A store with no embeddings yet has nothing to seed the search with, so
+/// the query returns an empty result rather than an error.
+///
+/// Use [`api::execute_query`] to query stores not owned by a
+/// [`TransactionManager`].
 pub async fn execute_query(
     stores: &TransactionManager,
     query_embedding: Option<&[f32]>,
     options: &QueryOptions,
 ) -> OnyxResult<QueryResult> {
-    let start = std::time::Instant::now();
-    let mut seen: HashSet<Uuid> = HashSet::new();
+    api::execute_query(stores.into(), query_embedding, options).await
+}
+
+/// [`execute_query`], also returning a [`QueryTrace`] of how each node was
+/// found and scored.
+pub async fn execute_query_explain(
+    stores: &TransactionManager,
+    query_embedding: Option<&[f32]>,
+    options: &QueryOptions,
+) -> OnyxResult<(QueryResult, QueryTrace)> {
+    api::execute_query_explain(stores.into(), query_embedding, options).await
+}
+
+#[tracing::instrument(
+    name = "execute_query",
+    skip_all,
+    fields(top_k = options.top_k, max_depth = options.max_depth)
+)]
+async fn run_query(
+    stores: QueryStores<'_>,
+    query_embedding: Option<&[f32]>,
+    options: &QueryOptions,
+    trace: &mut QueryTrace,
+) -> OnyxResult<QueryResult> {
+    let start = Instant::now();
+    let mut seen: HashSet<Uuid> = options.exclude_ids.clone();
     let mut items: Vec<QueryResultItem> = Vec::new();
     let mut nodes_examined: usize = 0;
+    let mut truncated = false;
+    let budget = TraversalBudget {
+        max_nodes: options.max_nodes_examined,
+        deadline: options.max_time.map(|limit| start + limit),
+    };
 
-    // Step 1: Vector similarity search
+    // Step 1: Vector similarity search. An empty vector store may not know
+    // its dimension yet, so skip it instead of checking the query against it.
+    let query_embedding = match query_embedding {
+        Some(embedding) => {
+            check_finite(embedding)?;
+            (!stores.vector.is_empty().await).then_some(embedding)
+        }
+        None => None,
+    };
     if let Some(embedding) = query_embedding {
-        let vector_results = stores.vector_store.search(embedding, options.top_k).await?;
+        let mut vector_results = stores
+            .vector
+            .search_excluding(embedding, options.top_k, &options.exclude_ids)
+            .await?;
+        if let Some(min_score) = options.min_score {
+            vector_results.retain(|(_, score)| *score >= min_score as f32);
+        }
+        if let Some(max) = options.max_nodes_examined {
+            truncated |= vector_results.len() > max;
+            vector_results.truncate(max);
+        }
         nodes_examined += vector_results.len();
+        trace.vector_candidates = vector_results.clone();
 
         for (node_id, score) in &vector_results {
-            if let Some(node) = stores.graph_store.get_node(node_id).await? {
+            if let Some(node) = stores.graph.get_node(node_id).await? {
                 seen.insert(*node_id);
+                trace.contribute(*node_id, ResultSource::VectorSearch, *score as f64, None);
                 items.push(QueryResultItem {
                     node_id: *node_id,
                     name: node.name.clone(),
@@ -139,22 +520,48 @@ pub async fn execute_query(
     // Step 2: Graph traversal from each vector result
     let seed_ids: Vec<Uuid> = items.iter().map(|i| i.node_id).collect();
     for seed_id in &seed_ids {
+        if options.max_depth > 0 && budget.exhausted(nodes_examined) {
+            truncated = true;
+            break;
+        }
+        // The seed itself was already counted by the vector search.
+        let seed_budget = TraversalBudget {
+            max_nodes: budget.max_nodes.map(|max| max - nodes_examined + 1),
+            ..budget
+        };
         let traversal = stores
-            .graph_store
-            .traverse(seed_id, options.edge_types.as_deref(), options.max_depth)
+            .graph
+            .traverse_with_budget(
+                seed_id,
+                options.edge_types.as_deref(),
+                options.max_depth,
+                seed_budget,
+            )
             .await?;
+        truncated |= traversal.truncated;
 
         for (node_id, depth) in &traversal.nodes {
             if depth == &0 {
                 continue; // Skip the seed node itself
             }
             nodes_examined += 1;
+            trace.expansions.push(TraceExpansion {
+                seed_id: *seed_id,
+                node_id: *node_id,
+                depth: *depth,
+            });
 
             if !seen.contains(node_id) {
                 seen.insert(*node_id);
-                if let Some(node) = stores.graph_store.get_node(node_id).await? {
+                if let Some(node) = stores.graph.get_node(node_id).await? {
                     // Score decays with depth
                     let depth_penalty = 1.0 / (1.0 + *depth as f64);
+                    trace.contribute(
+                        *node_id,
+                        ResultSource::GraphTraversal,
+                        depth_penalty,
+                        Some(*seed_id),
+                    );
                     items.push(QueryResultItem {
                         node_id: *node_id,
                         name: node.name.clone(),
@@ -170,16 +577,42 @@ pub async fn execute_query(
                 // Node found by both vector search and graph traversal
                 if let Some(item) = items.iter_mut().find(|i| i.node_id == *node_id) {
                     item.source = ResultSource::Combined;
-                    item.score = (item.score + 0.2).min(1.0); // Boost for multi-source
+                    let boosted = (item.score + 0.2).min(1.0); // Boost for multi-source
+                    trace.contribute(
+                        *node_id,
+                        ResultSource::GraphTraversal,
+                        boosted - item.score,
+                        Some(*seed_id),
+                    );
+                    item.score = boosted;
                 }
             }
         }
     }
 
-    // Step 3: Add version history if requested
+    // Step 3: Sort by score (descending), NaN last
+    items.sort_by(|a, b| {
+        a.score
+            .is_nan()
+            .cmp(&b.score.is_nan())
+            .then_with(|| b.score.total_cmp(&a.score))
+    });
+    if let Some(lambda) = options.diversity_lambda {
+        items = rerank_mmr(stores.vector, items, lambda).await?;
+    }
+
+    // Step 4: Paginate
+    let total_before_limit = items.len();
+    let mut items: Vec<QueryResultItem> = items
+        .into_iter()
+        .skip(options.offset)
+        .take(options.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    // Step 5: Add version history if requested
     if options.include_history {
         for item in &mut items {
-            let versions = stores.history_store.list_versions(&item.node_id).await?;
+            let versions = stores.history.list_versions(&item.node_id).await?;
             for v in versions {
                 item.versions.push(VersionInfo {
                     version_id: v.version_id.clone(),
@@ -192,32 +625,100 @@ pub async fn execute_query(
         }
     }
 
-    // Step 4: Sort by score (descending)
-    items.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-
+    metrics::histogram!("onyx_query_duration_seconds").record(start.elapsed().as_secs_f64());
     let elapsed = start.elapsed().as_millis() as u64;
+    tracing::debug!(
+        results = items.len(),
+        total_before_limit,
+        nodes_examined,
+        truncated,
+        elapsed_ms = elapsed,
+        "query complete"
+    );
 
     Ok(QueryResult {
         items,
+        total_before_limit,
         nodes_examined,
+        truncated,
         query_time_ms: elapsed,
     })
 }
 
+/// Re-rank `items` by maximal marginal relevance: repeatedly take the item
+/// with the best `lambda * score - (1 - lambda) * similarity`, where
+/// `similarity` is its highest cosine similarity to an item already taken.
+///
+/// Scores are left as they are; only the order changes. Items without a
+/// stored embedding count as unlike every other item.
+async fn rerank_mmr(
+    vector: &dyn VectorStore,
+    items: Vec<QueryResultItem>,
+    lambda: f64,
+) -> OnyxResult<Vec<QueryResultItem>> {
+    let mut remaining = Vec::with_capacity(items.len());
+    for item in items {
+        let embedding = vector.get(&item.node_id).await?;
+        remaining.push((item, embedding, 0.0f64));
+    }
+
+    let mut ranked = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let mut best = 0;
+        let mut best_mmr = f64::NEG_INFINITY;
+        for (i, (item, _, similarity)) in remaining.iter().enumerate() {
+            let mmr = lambda * item.score - (1.0 - lambda) * similarity;
+            if mmr > best_mmr {
+                best = i;
+                best_mmr = mmr;
+            }
+        }
+
+        let (item, embedding, _) = remaining.remove(best);
+        if let Some(taken) = &embedding {
+            for (_, other, similarity) in &mut remaining {
+                if let Some(other) = other {
+                    *similarity = similarity.max(cosine_similarity(taken, other) as f64);
+                }
+            }
+        }
+        ranked.push(item);
+    }
+    Ok(ranked)
+}
+
 // ---------------------------------------------------------------------------
 // Impact analysis: reason over the graph to find affected nodes
 // ---------------------------------------------------------------------------
 
+/// Which way [`impact_in_direction`] walks the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactDirection {
+    /// Nodes that depend on the changed node (follows edges backwards).
+    #[default]
+    Dependents,
+    /// Nodes the changed node depends on (follows edges forwards).
+    Dependencies,
+}
+
 /// Given a node, find all downstream nodes that would be affected by a change.
-/// Follows `Calls`, `Imports`, `DependsOn`, and `Documents` edges.
+/// Follows `Calls`, `Imports`, `DependsOn`, `Documents` and `TestsOf` edges;
+/// semantic `SimilarTo` edges are not causal and are never followed.
 pub async fn impact_analysis(
     stores: &TransactionManager,
     node_id: &Uuid,
     max_depth: usize,
+) -> OnyxResult<Vec<(Uuid, String, usize)>> {
+    api::impact_analysis(stores.into(), node_id, max_depth).await
+}
+
+/// [`impact_analysis`] over any graph store, in either direction.
+pub async fn impact_in_direction<G: GraphStore + ?Sized>(
+    graph: &G,
+    node_id: &Uuid,
+    max_depth: usize,
+    direction: ImpactDirection,
 ) -> OnyxResult<Vec<(Uuid, String, usize)>> {
     let impact_edges = vec![
         EdgeType::Calls,
@@ -227,7 +728,6 @@ pub async fn impact_analysis(
         EdgeType::TestsOf,
     ];
 
-    // Get inbound edges -- nodes that DEPEND ON the changed node
     let mut affected: Vec<(Uuid, String, usize)> = Vec::new();
     let mut visited: HashSet<Uuid> = HashSet::new();
     visited.insert(*node_id);
@@ -236,7 +736,7 @@ pub async fn impact_analysis(
 
     while let Some((current, depth)) = frontier.pop() {
         if depth > 0 {
-            if let Some(node) = stores.graph_store.get_node(&current).await? {
+            if let Some(node) = graph.get_node(&current).await? {
                 affected.push((current, node.name.clone(), depth));
             }
         }
@@ -245,13 +745,21 @@ pub async fn impact_analysis(
             continue;
         }
 
-        // Find nodes that reference the current node
-        let inbound = stores
-            .graph_store
-            .get_inbound(&current, Some(&impact_edges))
-            .await?;
+        let linked = match direction {
+            // Nodes that reference the current node depend on it
+            ImpactDirection::Dependents => {
+                graph
+                    .get_active_inbound(&current, Some(&impact_edges))
+                    .await?
+            }
+            ImpactDirection::Dependencies => {
+                graph
+                    .get_active_neighbors(&current, Some(&impact_edges))
+                    .await?
+            }
+        };
 
-        for (_edge, node) in inbound {
+        for (_edge, node) in linked {
             if !visited.contains(&node.id) {
                 visited.insert(node.id);
                 frontier.push((node.id, depth + 1));
@@ -268,63 +776,19 @@ pub async fn find_covering_tests(
     node_id: &Uuid,
     max_depth: usize,
 ) -> OnyxResult<Vec<QueryResultItem>> {
-    let mut tests: Vec<QueryResultItem> = Vec::new();
-    let mut visited: HashSet<Uuid> = HashSet::new();
-
-    // Direct tests
-    let direct = stores
-        .graph_store
-        .get_inbound(node_id, Some(&[EdgeType::TestsOf]))
-        .await?;
-
-    for (_, test_node) in &direct {
-        if !visited.contains(&test_node.id) {
-            visited.insert(test_node.id);
-            tests.push(QueryResultItem {
-                node_id: test_node.id,
-                name: test_node.name.clone(),
-                content: test_node.content.clone(),
-                source: ResultSource::GraphTraversal,
-                score: 1.0,
-                depth: 1,
-                edge_path: vec![EdgeType::TestsOf],
-                versions: Vec::new(),
-            });
-        }
-    }
-
-    // Transitive: tests of callers
-    if max_depth > 1 {
-        let callers = stores
-            .graph_store
-            .get_inbound(node_id, Some(&[EdgeType::Calls]))
-            .await?;
-
-        for (_, caller_node) in &callers {
-            let caller_tests = stores
-                .graph_store
-                .get_inbound(&caller_node.id, Some(&[EdgeType::TestsOf]))
-                .await?;
-
-            for (_, test_node) in &caller_tests {
-                if !visited.contains(&test_node.id) {
-                    visited.insert(test_node.id);
-                    tests.push(QueryResultItem {
-                        node_id: test_node.id,
-                        name: test_node.name.clone(),
-                        content: test_node.content.clone(),
-                        source: ResultSource::GraphTraversal,
-                        score: 0.7,
-                        depth: 2,
-                        edge_path: vec![EdgeType::Calls, EdgeType::TestsOf],
-                        versions: Vec::new(),
-                    });
-                }
-            }
-        }
-    }
+    api::find_covering_tests(stores.into(), node_id, max_depth).await
+}
 
-    Ok(tests)
+/// Explain why `a` and `b` are related: the best path connecting them
+/// within `max_depth` hops, and whether they are semantically similar.
+/// Returns `None` if no such path exists.
+pub async fn explain_relationship(
+    stores: &TransactionManager,
+    a: &Uuid,
+    b: &Uuid,
+    max_depth: usize,
+) -> OnyxResult<Option<RelationshipExplanation>> {
+    api::explain_relationship(stores.into(), a, b, max_depth).await
 }
 
 // ---------------------------------------------------------------------------
@@ -334,9 +798,14 @@ pub async fn find_covering_tests(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::LimitsConfig;
+    use crate::ingest::demo::build_synthetic_codebase;
+    use crate::ingest::ingest_codebase;
     use crate::model::edge::Edge;
+    use crate::model::embedding::BagOfWordsEmbedder;
     use crate::model::node::{CodeEntityKind, Node, NodeType};
     use crate::store::transaction::TransactionOp;
+    use tracing_test::traced_test;
 
     fn build_test_stores() -> TransactionManager {
         let mut tm = TransactionManager::new();
@@ -467,6 +936,266 @@ mod tests {
         assert!(names.contains(&"func_b"));
     }
 
+    #[tokio::test]
+    #[traced_test]
+    async fn test_execute_query_emits_span() {
+        let stores = build_test_stores();
+        execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &QueryOptions::default())
+            .await
+            .unwrap();
+
+        assert!(logs_contain("execute_query{top_k=10 max_depth=2}"));
+        assert!(logs_contain("query complete"));
+    }
+
+    #[tokio::test]
+    async fn test_explain_attributes_combined_boost_to_both_sources() {
+        let stores = build_test_stores();
+        let options = QueryOptions {
+            top_k: 2,
+            max_depth: 2,
+            edge_types: Some(vec![EdgeType::Calls]),
+            ..Default::default()
+        };
+
+        let (result, trace) = execute_query_explain(&stores, Some(&[1.0, 0.0, 0.0]), &options)
+            .await
+            .unwrap();
+        let id_of = |name: &str| {
+            result
+                .items
+                .iter()
+                .find(|i| i.name == name)
+                .unwrap()
+                .node_id
+        };
+        let (func_a, func_b) = (id_of("func_a"), id_of("func_b"));
+        let combined = result.items.iter().find(|i| i.node_id == func_b).unwrap();
+        assert_eq!(combined.source, ResultSource::Combined);
+        assert_eq!(trace.vector_candidates.len(), 2);
+
+        let contributions = &trace.contributions[&func_b];
+        assert_eq!(contributions[0].source, ResultSource::VectorSearch);
+        assert!(contributions.iter().any(|c| {
+            c.source == ResultSource::GraphTraversal && c.seed_id == Some(func_a) && c.amount > 0.0
+        }));
+        let total: f64 = contributions.iter().map(|c| c.amount).sum();
+        assert!((total - combined.score).abs() < 1e-9);
+        assert!(trace.expansions.contains(&TraceExpansion {
+            seed_id: func_a,
+            node_id: func_b,
+            depth: 1,
+        }));
+    }
+
+    #[tokio::test]
+    async fn test_offset_and_limit_page_ranked_items() {
+        let mut stores = TransactionManager::new();
+        let mut ranked = Vec::new();
+        for (i, similarity) in [1.0f32, 0.9, 0.7, 0.5, 0.3].into_iter().enumerate() {
+            let node = Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                format!("func_{}", i),
+                "",
+            );
+            ranked.push(node.id);
+            let embedding = vec![similarity, (1.0 - similarity * similarity).sqrt(), 0.0];
+            let id = node.id;
+            stores
+                .execute_batch(vec![
+                    TransactionOp::InsertNode(node),
+                    TransactionOp::InsertEmbedding { id, embedding },
+                ])
+                .unwrap();
+        }
+
+        let options = QueryOptions {
+            top_k: 5,
+            max_depth: 0,
+            offset: 2,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let result = execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &options)
+            .await
+            .unwrap();
+        assert_eq!(result.total_before_limit, 5);
+        let ids: Vec<Uuid> = result.items.iter().map(|i| i.node_id).collect();
+        assert_eq!(ids, ranked[2..4]);
+    }
+
+    #[tokio::test]
+    async fn test_explain_relationship_over_demo() {
+        let units = build_synthetic_codebase();
+        let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+        let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+        let mut stores = TransactionManager::new();
+        ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let mut ids = HashMap::new();
+        for node in stores.graph_store.all_nodes().await {
+            ids.insert(node.name.clone(), node.id);
+        }
+        let (payment, discount) = (ids["process_payment"], ids["apply_discount"]);
+
+        let explanation = explain_relationship(&stores, &payment, &discount, 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(explanation.intermediates(), ["calculate_total"]);
+        assert_eq!(explanation.edge_types(), [EdgeType::Calls, EdgeType::Calls]);
+        assert!(explanation.hops.iter().all(|h| h.forward));
+        assert!(explanation.similarity.is_some());
+        assert!(explanation
+            .to_string()
+            .starts_with("process_payment -[calls]-> calculate_total -[calls]-> apply_discount"));
+
+        // Asked the other way round, the same path is walked against the edges.
+        let reverse = explain_relationship(&stores, &discount, &payment, 3)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(reverse.intermediates(), ["calculate_total"]);
+        assert!(reverse.hops.iter().all(|h| !h.forward));
+
+        let too_short = explain_relationship(&stores, &payment, &discount, 1)
+            .await
+            .unwrap();
+        assert!(too_short.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_diversity_spreads_results_across_clusters() {
+        let mut stores = TransactionManager::new();
+        // Three near-duplicates pointing one way, and one less relevant
+        // node pointing another.
+        let embeddings = [
+            ("cluster_1", vec![0.9, 0.436, 0.0]),
+            ("cluster_2", vec![0.89, 0.456, 0.0]),
+            ("cluster_3", vec![0.88, 0.475, 0.0]),
+            ("outlier", vec![0.85, -0.527, 0.0]),
+        ];
+        for (name, embedding) in embeddings {
+            let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "");
+            let id = node.id;
+            stores
+                .execute_batch(vec![
+                    TransactionOp::InsertNode(node),
+                    TransactionOp::InsertEmbedding { id, embedding },
+                ])
+                .unwrap();
+        }
+
+        let names = |result: &QueryResult| -> Vec<String> {
+            result.items.iter().map(|i| i.name.clone()).collect()
+        };
+        let mut options = QueryOptions {
+            top_k: 4,
+            max_depth: 0,
+            ..Default::default()
+        };
+        let plain = execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            names(&plain),
+            ["cluster_1", "cluster_2", "cluster_3", "outlier"]
+        );
+
+        options.diversity_lambda = Some(0.5);
+        let diverse = execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &options)
+            .await
+            .unwrap();
+        assert_eq!(
+            names(&diverse),
+            ["cluster_1", "outlier", "cluster_2", "cluster_3"]
+        );
+        // Only the order changes, not the relevance scores.
+        assert_eq!(diverse.items[1].score, plain.items[3].score);
+    }
+
+    #[tokio::test]
+    async fn test_query_on_empty_vector_store_is_empty() {
+        let mut stores = TransactionManager::new();
+        stores
+            .execute(TransactionOp::InsertNode(Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                "func_a",
+                "fn func_a() {}",
+            )))
+            .unwrap();
+
+        let result = execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &QueryOptions::default())
+            .await
+            .unwrap();
+        assert!(result.items.is_empty());
+        assert_eq!(result.nodes_examined, 0);
+        assert_eq!(result.total_before_limit, 0);
+    }
+
+    #[tokio::test]
+    async fn test_node_budget_truncates_traversal() {
+        let stores = build_test_stores();
+        let options = QueryOptions {
+            top_k: 1,
+            max_depth: 2,
+            edge_types: Some(vec![EdgeType::Calls]),
+            max_nodes_examined: Some(2),
+            ..Default::default()
+        };
+
+        let result = execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &options)
+            .await
+            .unwrap();
+        assert!(result.truncated);
+        assert_eq!(result.nodes_examined, 2);
+        let names: Vec<&str> = result.items.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["func_a", "func_b"]);
+
+        let unbounded = QueryOptions {
+            max_nodes_examined: None,
+            ..options
+        };
+        let result = execute_query(&stores, Some(&[1.0, 0.0, 0.0]), &unbounded)
+            .await
+            .unwrap();
+        assert!(!result.truncated);
+        assert_eq!(result.items.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_impact_analysis_ignores_similar_to() {
+        let mut stores = build_test_stores();
+        let func_c_id = stores
+            .graph_store
+            .all_nodes()
+            .await
+            .into_iter()
+            .find(|n| n.name == "func_c")
+            .unwrap()
+            .id;
+        let lookalike = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "func_c_copy",
+            "fn func_c_copy() -> i32 { 42 }",
+        );
+        let lookalike_id = lookalike.id;
+        stores
+            .execute(TransactionOp::InsertNode(lookalike))
+            .unwrap();
+        stores
+            .execute(TransactionOp::InsertEdge(
+                Edge::new(EdgeType::SimilarTo, lookalike_id, func_c_id).with_confidence(0.95),
+            ))
+            .unwrap();
+
+        let affected = impact_analysis(&stores, &func_c_id, 3).await.unwrap();
+        assert!(!affected.is_empty());
+        assert!(affected.iter().all(|(id, _, _)| *id != lookalike_id));
+    }
+
     #[test]
     fn test_find_covering_tests() {
         let stores = build_test_stores();
@@ -483,4 +1212,35 @@ mod tests {
         assert_eq!(tests.len(), 1);
         assert_eq!(tests[0].name, "test_func_b");
     }
+
+    #[test]
+    fn test_options_builder_sets_fields() {
+        let options = QueryOptions::builder()
+            .top_k(5)
+            .max_depth(2)
+            .edge_types([EdgeType::Calls])
+            .min_confidence(0.8)
+            .build()
+            .unwrap();
+        assert_eq!(options.top_k, 5);
+        assert_eq!(options.max_depth, 2);
+        assert_eq!(options.edge_types, Some(vec![EdgeType::Calls]));
+        assert_eq!(options.min_confidence, 0.8);
+        assert!(!options.include_history);
+        assert_eq!(options.limit, None);
+    }
+
+    #[test]
+    fn test_options_builder_rejects_invalid_options() {
+        let zero_top_k = QueryOptions::builder().top_k(0).build();
+        assert!(matches!(zero_top_k, Err(OnyxError::InvalidQueryOptions(_))));
+
+        let too_deep = QueryOptions::builder()
+            .max_depth(MAX_QUERY_DEPTH + 1)
+            .build();
+        assert!(matches!(too_deep, Err(OnyxError::InvalidQueryOptions(_))));
+
+        let bad_lambda = QueryOptions::builder().diversity_lambda(1.5).build();
+        assert!(matches!(bad_lambda, Err(OnyxError::InvalidQueryOptions(_))));
+    }
 }
@@ -0,0 +1,156 @@
+use axum::{
+    extract::{Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+
+use crate::config::AuthConfig;
+use crate::server::error::ApiError;
+
+/// Alternative header for clients that cannot set `Authorization`.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Shared state for the API key middleware.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys {
+    keys: Arc<Vec<String>>,
+}
+
+impl ApiKeys {
+    /// Build the key set from config. Disabled auth yields an empty set.
+    pub fn from_config(config: &AuthConfig) -> Self {
+        let keys = if config.enabled {
+            config.api_keys.clone()
+        } else {
+            Vec::new()
+        };
+        Self {
+            keys: Arc::new(keys),
+        }
+    }
+
+    /// Whether any keys are configured (auth is enforced only if so).
+    pub fn is_enabled(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Compare in constant time, so response timing does not leak how much
+    /// of a key matched. Every key is checked.
+    pub(crate) fn accepts(&self, candidate: &str) -> bool {
+        self.keys.iter().fold(false, |found, key| {
+            found | bool::from(key.as_bytes().ct_eq(candidate.as_bytes()))
+        })
+    }
+}
+
+fn unauthorized(message: &str) -> Response {
+    ApiError::unauthorized(message).into_response()
+}
+
+/// Extract the presented key from `Authorization: Bearer` or `X-Api-Key`.
+fn presented_key(request: &Request) -> Option<&str> {
+    let headers = request.headers();
+    if let Some(value) = headers.get(AUTHORIZATION).and_then(|v| v.to_str().ok()) {
+        if let Some(token) = value.strip_prefix("Bearer ") {
+            return Some(token.trim());
+        }
+    }
+    headers
+        .get(API_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+}
+
+/// Reject requests that do not carry one of the configured API keys.
+pub async fn require_api_key(
+    State(keys): State<ApiKeys>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !keys.is_enabled() {
+        return next.run(request).await;
+    }
+
+    match presented_key(&request) {
+        Some(key) if keys.accepts(key) => next.run(request).await,
+        Some(_) => unauthorized("invalid api key"),
+        None => unauthorized("missing api key"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::router;
+    use crate::server::tests::{test_config, test_state};
+    use axum::{
+        body::Body,
+        http::{Request as HttpRequest, StatusCode},
+        Router,
+    };
+    use tower::ServiceExt;
+
+    /// The router `serve_http` serves, with one configured key.
+    async fn app() -> Router {
+        let mut config = test_config();
+        config.auth = AuthConfig {
+            enabled: true,
+            api_keys: vec!["secret-key".to_string()],
+        };
+        router(test_state().await, &config).unwrap()
+    }
+
+    async fn status_for(request: HttpRequest<Body>) -> StatusCode {
+        app().await.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_valid_key_is_accepted() {
+        let bearer = HttpRequest::get("/api/nodes")
+            .header(AUTHORIZATION, "Bearer secret-key")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status_for(bearer).await, StatusCode::OK);
+
+        let header = HttpRequest::get("/api/nodes")
+            .header(API_KEY_HEADER, "secret-key")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(status_for(header).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_missing_key_is_rejected() {
+        let request = HttpRequest::get("/api/nodes").body(Body::empty()).unwrap();
+        assert_eq!(status_for(request).await, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_key_is_rejected() {
+        for key in ["not-the-key", "secret-ke", "secret-key2"] {
+            let request = HttpRequest::get("/api/nodes")
+                .header(AUTHORIZATION, format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap();
+            assert_eq!(status_for(request).await, StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health_is_unauthenticated() {
+        let request = HttpRequest::get("/health").body(Body::empty()).unwrap();
+        assert_eq!(status_for(request).await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_billing_is_unauthenticated() {
+        let request = HttpRequest::post("/billing/portal")
+            .header("content-type", "application/json")
+            .body(Body::from("{}"))
+            .unwrap();
+        assert_ne!(status_for(request).await, StatusCode::UNAUTHORIZED);
+    }
+}
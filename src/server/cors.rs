@@ -0,0 +1,140 @@
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+use crate::config::CorsConfig;
+use crate::error::{OnyxError, OnyxResult};
+
+/// Build the CORS layer from config, falling back to permissive when absent.
+pub fn build_cors_layer(config: Option<&CorsConfig>) -> OnyxResult<CorsLayer> {
+    let Some(config) = config else {
+        tracing::warn!("no [server.cors] section configured; using permissive CORS");
+        return Ok(CorsLayer::permissive());
+    };
+
+    let origins = if is_wildcard(&config.allowed_origins) {
+        AllowOrigin::from(Any)
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| {
+                HeaderValue::from_str(origin).map_err(|err| {
+                    OnyxError::ConfigError(format!("invalid CORS origin '{}': {}", origin, err))
+                })
+            })
+            .collect::<OnyxResult<Vec<_>>>()?;
+        AllowOrigin::list(origins)
+    };
+
+    let methods = if config.allowed_methods.is_empty() {
+        AllowMethods::list([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+    } else if is_wildcard(&config.allowed_methods) {
+        AllowMethods::from(Any)
+    } else {
+        let methods = config
+            .allowed_methods
+            .iter()
+            .map(|method| {
+                method.to_uppercase().parse::<Method>().map_err(|err| {
+                    OnyxError::ConfigError(format!("invalid CORS method '{}': {}", method, err))
+                })
+            })
+            .collect::<OnyxResult<Vec<_>>>()?;
+        AllowMethods::list(methods)
+    };
+
+    let headers = if is_wildcard(&config.allowed_headers) {
+        AllowHeaders::from(Any)
+    } else {
+        let headers = config
+            .allowed_headers
+            .iter()
+            .map(|header| {
+                header.parse::<HeaderName>().map_err(|err| {
+                    OnyxError::ConfigError(format!("invalid CORS header '{}': {}", header, err))
+                })
+            })
+            .collect::<OnyxResult<Vec<_>>>()?;
+        AllowHeaders::list(headers)
+    };
+
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers))
+}
+
+fn is_wildcard(values: &[String]) -> bool {
+    values.iter().any(|value| value == "*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    fn app(config: &CorsConfig) -> Router {
+        Router::new()
+            .route("/health", get(|| async { "ok" }))
+            .layer(build_cors_layer(Some(config)).unwrap())
+    }
+
+    fn restrictive() -> CorsConfig {
+        CorsConfig {
+            allowed_origins: vec!["https://app.onyx.dev".to_string()],
+            allowed_methods: vec!["get".to_string()],
+            allowed_headers: vec!["authorization".to_string()],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_origin_is_rejected() {
+        let request = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/health")
+            .header("origin", "https://evil.example")
+            .header("access-control-request-method", "GET")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app(&restrictive()).oneshot(request).await.unwrap();
+        assert!(response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origin_is_echoed() {
+        let request = Request::builder()
+            .uri("/health")
+            .header("origin", "https://app.onyx.dev")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = app(&restrictive()).oneshot(request).await.unwrap();
+        assert_eq!(
+            response.headers().get("access-control-allow-origin").unwrap(),
+            "https://app.onyx.dev"
+        );
+    }
+
+    #[test]
+    fn test_invalid_method_is_config_error() {
+        let config = CorsConfig {
+            allowed_methods: vec!["NOT A METHOD".to_string()],
+            ..restrictive()
+        };
+        assert!(matches!(
+            build_cors_layer(Some(&config)),
+            Err(OnyxError::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{OnyxError, OnyxResult};
+use crate::model::edge::{Edge, EdgeType};
+use crate::model::node::Node;
+use crate::server::error::ApiError;
+use crate::server::AppState;
+use crate::store::graph::GraphStore;
+use crate::store::transaction::TransactionOp;
+
+/// Request body for creating an edge.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateEdgeRequest {
+    pub edge_type: EdgeType,
+    pub source_id: Uuid,
+    pub target_id: Uuid,
+    /// Defaults to 1.0.
+    pub confidence: Option<f64>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Query parameters for neighbor lookups.
+#[derive(Debug, Deserialize)]
+pub struct NeighborParams {
+    /// Comma-separated edge types, e.g. `calls,imports`; all types if absent.
+    pub edge_types: Option<String>,
+}
+
+/// An edge and the node at its other end.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NeighborResult {
+    pub edge: Edge,
+    pub node: Node,
+}
+
+fn parse_edge_types(raw: Option<&str>) -> Result<Option<Vec<EdgeType>>, ApiError> {
+    let Some(raw) = raw.filter(|raw| !raw.is_empty()) else {
+        return Ok(None);
+    };
+    let types = raw
+        .split(',')
+        .map(|name| name.trim().parse())
+        .collect::<Result<Vec<EdgeType>, OnyxError>>()?;
+    Ok(Some(types))
+}
+
+/// Create an edge between two existing nodes.
+pub async fn create_edge(
+    State(state): State<AppState>,
+    Json(request): Json<CreateEdgeRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let edge = insert_edge(&state, request).await?;
+    Ok((StatusCode::CREATED, Json(edge)))
+}
+
+/// Delete an edge by ID.
+pub async fn delete_edge(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, ApiError> {
+    remove_edge(&state, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Insert an edge after checking both endpoints exist.
+pub(crate) async fn insert_edge(state: &AppState, request: CreateEdgeRequest) -> OnyxResult<Edge> {
+    let graph = &state.stores.graph_store;
+    for id in [request.source_id, request.target_id] {
+        if graph.get_node(&id).await?.is_none() {
+            return Err(OnyxError::NodeNotFound(id));
+        }
+    }
+
+    let mut edge = Edge::new(request.edge_type, request.source_id, request.target_id)
+        .with_confidence(request.confidence.unwrap_or(1.0));
+    if let Some(metadata) = request.metadata {
+        edge.metadata = metadata;
+    }
+    state
+        .stores
+        .execute(TransactionOp::InsertEdge(edge.clone()))
+        .await?;
+    Ok(edge)
+}
+
+/// Remove an existing edge.
+pub(crate) async fn remove_edge(state: &AppState, id: Uuid) -> OnyxResult<()> {
+    if state.stores.graph_store.get_edge(&id).await?.is_none() {
+        return Err(OnyxError::EdgeNotFound(id));
+    }
+    state.stores.execute(TransactionOp::RemoveEdge(id)).await
+}
+
+/// Outbound neighbors of a node, optionally filtered by edge type.
+pub async fn neighbors(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<NeighborParams>,
+) -> Result<Json<Vec<NeighborResult>>, ApiError> {
+    let edge_types = parse_edge_types(params.edge_types.as_deref())?;
+    let found = state
+        .stores
+        .graph_store
+        .get_neighbors(&id, edge_types.as_deref())
+        .await?;
+    Ok(Json(into_results(found)))
+}
+
+/// Inbound neighbors of a node, optionally filtered by edge type.
+pub async fn inbound(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<NeighborParams>,
+) -> Result<Json<Vec<NeighborResult>>, ApiError> {
+    let edge_types = parse_edge_types(params.edge_types.as_deref())?;
+    let found = state
+        .stores
+        .graph_store
+        .get_inbound(&id, edge_types.as_deref())
+        .await?;
+    Ok(Json(into_results(found)))
+}
+
+fn into_results(found: Vec<(Edge, Node)>) -> Vec<NeighborResult> {
+    found
+        .into_iter()
+        .map(|(edge, node)| NeighborResult { edge, node })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::node::{CodeEntityKind, NodeType};
+    use crate::server::tests::test_state;
+
+    async fn insert_node(state: &AppState, name: &str) -> Uuid {
+        let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "");
+        let id = node.id;
+        state
+            .stores
+            .execute(TransactionOp::InsertNode(node))
+            .await
+            .unwrap();
+        id
+    }
+
+    fn request(edge_type: EdgeType, source_id: Uuid, target_id: Uuid) -> CreateEdgeRequest {
+        CreateEdgeRequest {
+            edge_type,
+            source_id,
+            target_id,
+            confidence: None,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_neighbors_filter_by_edge_type() {
+        let state = test_state().await;
+        let (a, b, c) = (
+            insert_node(&state, "a").await,
+            insert_node(&state, "b").await,
+            insert_node(&state, "c").await,
+        );
+        for req in [
+            request(EdgeType::Calls, a, b),
+            request(EdgeType::Imports, a, c),
+        ] {
+            create_edge(State(state.clone()), Json(req)).await.unwrap();
+        }
+
+        let params = NeighborParams {
+            edge_types: Some("calls".to_string()),
+        };
+        let Json(calls) = neighbors(State(state.clone()), Path(a), Query(params))
+            .await
+            .unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].node.id, b);
+
+        let params = NeighborParams { edge_types: None };
+        let Json(callers) = inbound(State(state), Path(b), Query(params)).await.unwrap();
+        assert_eq!(callers.len(), 1);
+        assert_eq!(callers[0].node.id, a);
+    }
+
+    #[tokio::test]
+    async fn test_create_edge_requires_both_nodes() {
+        let state = test_state().await;
+        let a = insert_node(&state, "a").await;
+        let result = create_edge(
+            State(state),
+            Json(request(EdgeType::Calls, a, Uuid::new_v4())),
+        )
+        .await;
+        let response = result
+            .map(IntoResponse::into_response)
+            .unwrap_or_else(IntoResponse::into_response);
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,209 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{ErrorCode, OnyxError};
+use crate::server::request_id::current_request_id;
+
+/// Body of every error response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorEnvelope {
+    pub error: ErrorBody,
+}
+
+/// What went wrong, and which request it belongs to.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// Stable machine-readable code, e.g. `NODE_NOT_FOUND`.
+    pub code: ErrorCode,
+    pub message: String,
+    /// Correlation id of the failed request, also sent as `X-Request-Id`.
+    pub request_id: Option<String>,
+    /// Position of the rejected item in a batch request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// Entities both branches changed, for a refused merge.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<Uuid>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    pub(crate) status: StatusCode,
+    pub(crate) code: ErrorCode,
+    pub(crate) message: String,
+    pub(crate) index: Option<usize>,
+    pub(crate) conflicts: Vec<Uuid>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            index: None,
+            conflicts: Vec::new(),
+        }
+    }
+
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, ErrorCode::BadRequest, message)
+    }
+
+    pub(crate) fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, message)
+    }
+
+    pub(crate) fn internal(message: impl Into<String>) -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::Internal,
+            message,
+        )
+    }
+
+    /// Item `index` of a batch request was rejected.
+    pub(crate) fn invalid_item(index: usize, message: impl Into<String>) -> Self {
+        Self {
+            index: Some(index),
+            ..Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::InvalidNode,
+                message,
+            )
+        }
+    }
+
+    /// A merge was refused because both branches changed `conflicts`.
+    pub(crate) fn merge_conflict(message: impl Into<String>, conflicts: Vec<Uuid>) -> Self {
+        Self {
+            conflicts,
+            ..Self::new(StatusCode::CONFLICT, ErrorCode::MergeConflict, message)
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorEnvelope {
+            error: ErrorBody {
+                code: self.code,
+                message: self.message,
+                request_id: current_request_id(),
+                index: self.index,
+                conflicts: self.conflicts,
+            },
+        });
+        (self.status, body).into_response()
+    }
+}
+
+/// HTTP status for each [`ErrorCode`].
+pub fn status_for(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::BadRequest
+        | ErrorCode::DimensionMismatch
+        | ErrorCode::InvalidEmbedding
+        | ErrorCode::InvalidQuery
+        | ErrorCode::InvalidEdgeType
+        | ErrorCode::InvalidNode => StatusCode::BAD_REQUEST,
+        ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+        ErrorCode::NodeNotFound
+        | ErrorCode::EdgeNotFound
+        | ErrorCode::VersionNotFound
+        | ErrorCode::BranchNotFound
+        | ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::BranchAlreadyExists
+        | ErrorCode::DuplicateNode
+        | ErrorCode::DuplicateEdge
+        | ErrorCode::MergeConflict => StatusCode::CONFLICT,
+        ErrorCode::ContentTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        ErrorCode::TransactionFailed
+        | ErrorCode::IngestionError
+        | ErrorCode::SerializationError
+        | ErrorCode::ConfigError
+        | ErrorCode::IoError
+        | ErrorCode::Internal
+        | ErrorCode::CorruptRecord
+        | ErrorCode::StorageError => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+impl From<OnyxError> for ApiError {
+    fn from(err: OnyxError) -> Self {
+        let code = err.code();
+        Self::new(status_for(code), code, err.to_string())
+    }
+}
+
+impl IntoResponse for OnyxError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::request_id::{assign_request_id, REQUEST_ID_HEADER};
+    use axum::{body::Body, http::Request, middleware, routing::get, Router};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/missing",
+                get(|| async { Err::<(), _>(OnyxError::NodeNotFound(Uuid::nil())) }),
+            )
+            .layer(middleware::from_fn(assign_request_id))
+    }
+
+    async fn envelope(response: Response) -> ErrorEnvelope {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_node_not_found_maps_to_404_envelope() {
+        let request = Request::get("/missing").body(Body::empty()).unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let header = response.headers()[REQUEST_ID_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let error = envelope(response).await.error;
+        assert_eq!(error.code, ErrorCode::NodeNotFound);
+        assert!(error.message.contains(&Uuid::nil().to_string()));
+        assert_eq!(error.request_id.as_deref(), Some(header.as_str()));
+        assert!(Uuid::parse_str(&header).is_ok());
+    }
+
+    #[test]
+    fn test_error_code_wire_format() {
+        let json = serde_json::to_string(&ErrorCode::NodeNotFound).unwrap();
+        assert_eq!(json, "\"NODE_NOT_FOUND\"");
+        assert_eq!(status_for(ErrorCode::DuplicateNode), StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn test_incoming_request_id_is_echoed() {
+        let request = Request::get("/missing")
+            .header(REQUEST_ID_HEADER, "req-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().oneshot(request).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "req-123");
+        let error = envelope(response).await.error;
+        assert_eq!(error.request_id.as_deref(), Some("req-123"));
+    }
+}
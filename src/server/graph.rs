@@ -0,0 +1,219 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::OnyxError;
+use crate::model::edge::EdgeType;
+use crate::query::{impact_in_direction, ImpactDirection};
+use crate::server::error::ApiError;
+use crate::server::AppState;
+use crate::store::graph::GraphStore;
+
+/// Deepest traversal or impact analysis a client may request.
+pub const MAX_DEPTH: usize = 10;
+
+/// Request body for `POST /api/graph/traverse`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TraverseRequest {
+    pub node_id: Uuid,
+    /// Edge types to follow; all types if absent.
+    pub edge_types: Option<Vec<EdgeType>>,
+    pub depth: usize,
+}
+
+/// A node reached by a traversal.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TraversedNode {
+    pub node_id: Uuid,
+    pub name: String,
+    pub depth: usize,
+    /// Edges leading from the start node to this one. Empty until traversals
+    /// track the path they took.
+    pub edge_path: Vec<Uuid>,
+}
+
+/// Response of `POST /api/graph/traverse`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TraverseResponse {
+    pub nodes: Vec<TraversedNode>,
+    /// Every edge followed.
+    pub edges: Vec<Uuid>,
+    pub total_visited: usize,
+}
+
+/// Query parameters for `GET /api/nodes/:id/impact`.
+#[derive(Debug, Deserialize)]
+pub struct ImpactParams {
+    pub depth: Option<usize>,
+    #[serde(default)]
+    pub direction: ImpactDirection,
+}
+
+/// A node affected by a change.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImpactedNode {
+    pub node_id: Uuid,
+    pub name: String,
+    pub depth: usize,
+}
+
+/// Response of `GET /api/nodes/:id/impact`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImpactResponse {
+    pub node_id: Uuid,
+    pub direction: ImpactDirection,
+    pub affected: Vec<ImpactedNode>,
+}
+
+fn check_depth(depth: usize) -> Result<usize, ApiError> {
+    if depth > MAX_DEPTH {
+        return Err(ApiError::bad_request(format!(
+            "depth {depth} exceeds the maximum of {MAX_DEPTH}"
+        )));
+    }
+    Ok(depth)
+}
+
+/// Breadth-first traversal from a node along the requested edge types.
+pub async fn traverse(
+    State(state): State<AppState>,
+    Json(request): Json<TraverseRequest>,
+) -> Result<Json<TraverseResponse>, ApiError> {
+    let depth = check_depth(request.depth)?;
+    let graph = &state.stores.graph_store;
+    if graph.get_node(&request.node_id).await?.is_none() {
+        return Err(OnyxError::NodeNotFound(request.node_id).into());
+    }
+
+    let traversal = graph
+        .traverse(&request.node_id, request.edge_types.as_deref(), depth)
+        .await?;
+    let mut nodes = Vec::with_capacity(traversal.nodes.len());
+    for (node_id, depth) in traversal.nodes {
+        if let Some(node) = graph.get_node(&node_id).await? {
+            nodes.push(TraversedNode {
+                node_id,
+                name: node.name,
+                depth,
+                edge_path: Vec::new(),
+            });
+        }
+    }
+
+    Ok(Json(TraverseResponse {
+        nodes,
+        edges: traversal.edges,
+        total_visited: traversal.total_visited,
+    }))
+}
+
+/// Nodes affected by changing a node, or that it depends on.
+pub async fn impact(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ImpactParams>,
+) -> Result<Json<ImpactResponse>, ApiError> {
+    let depth = check_depth(params.depth.unwrap_or(3))?;
+    let graph = &state.stores.graph_store;
+    if graph.get_node(&id).await?.is_none() {
+        return Err(OnyxError::NodeNotFound(id).into());
+    }
+
+    let affected = impact_in_direction(graph.as_ref(), &id, depth, params.direction)
+        .await?
+        .into_iter()
+        .map(|(node_id, name, depth)| ImpactedNode {
+            node_id,
+            name,
+            depth,
+        })
+        .collect();
+
+    Ok(Json(ImpactResponse {
+        node_id: id,
+        direction: params.direction,
+        affected,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::edge::Edge;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::server::tests::test_state;
+    use crate::store::transaction::TransactionOp;
+
+    /// `a -> b -> c` via `Calls`; returns the ids in order.
+    async fn chain(state: &AppState) -> [Uuid; 3] {
+        let nodes = ["a", "b", "c"]
+            .map(|name| Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, ""));
+        let ids = [nodes[0].id, nodes[1].id, nodes[2].id];
+        let mut ops: Vec<TransactionOp> =
+            nodes.into_iter().map(TransactionOp::InsertNode).collect();
+        ops.push(TransactionOp::InsertEdge(Edge::new(
+            EdgeType::Calls,
+            ids[0],
+            ids[1],
+        )));
+        ops.push(TransactionOp::InsertEdge(Edge::new(
+            EdgeType::Calls,
+            ids[1],
+            ids[2],
+        )));
+        state.stores.execute_batch(ops).await.unwrap();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_traverse_follows_chain() {
+        let state = test_state().await;
+        let [a, _, c] = chain(&state).await;
+
+        let request = TraverseRequest {
+            node_id: a,
+            edge_types: Some(vec![EdgeType::Calls]),
+            depth: 2,
+        };
+        let Json(response) = traverse(State(state), Json(request)).await.unwrap();
+        let reached: Vec<(Uuid, usize)> = response
+            .nodes
+            .iter()
+            .map(|n| (n.node_id, n.depth))
+            .collect();
+        assert!(reached.contains(&(c, 2)));
+        assert_eq!(response.edges.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_impact_in_both_directions() {
+        let state = test_state().await;
+        let [a, b, c] = chain(&state).await;
+
+        let params = ImpactParams {
+            depth: None,
+            direction: ImpactDirection::Dependents,
+        };
+        let Json(dependents) = impact(State(state.clone()), Path(c), Query(params))
+            .await
+            .unwrap();
+        let names: Vec<&str> = dependents
+            .affected
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"a") && names.contains(&"b"));
+
+        let params = ImpactParams {
+            depth: Some(1),
+            direction: ImpactDirection::Dependencies,
+        };
+        let Json(dependencies) = impact(State(state), Path(a), Query(params)).await.unwrap();
+        assert_eq!(dependencies.affected.len(), 1);
+        assert_eq!(dependencies.affected[0].node_id, b);
+    }
+}
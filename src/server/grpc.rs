@@ -0,0 +1,475 @@
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+use tokio_util::sync::CancellationToken;
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::Server;
+use tonic::{Code, Request, Response, Status};
+use uuid::Uuid;
+
+use crate::config::AppConfig;
+use crate::error::{ErrorCode, OnyxError, OnyxResult};
+use crate::model::edge::{Edge, EdgeType};
+use crate::model::node::Node;
+use crate::model::version::VersionEntry;
+use crate::server::auth::{ApiKeys, API_KEY_HEADER};
+use crate::server::error::ApiError;
+use crate::server::{edges, nodes, AppState};
+use crate::store::graph::GraphStore;
+use crate::store::history::HistoryStore;
+use crate::store::vector::VectorStore;
+
+/// Types and service stubs generated from `proto/onyx.proto`.
+pub mod proto {
+    tonic::include_proto!("onyx.v1");
+}
+
+use proto::edge_service_server::{EdgeService, EdgeServiceServer};
+use proto::history_service_server::{HistoryService, HistoryServiceServer};
+use proto::node_service_server::{NodeService, NodeServiceServer};
+use proto::search_service_server::{SearchService, SearchServiceServer};
+
+/// Port used when `server.grpc_port` is not configured.
+pub const DEFAULT_GRPC_PORT: u16 = 50051;
+
+/// Run the gRPC API over `state` until Ctrl-C, SIGTERM, or `shutdown` is
+/// cancelled.
+///
+/// Listens on `server.host` and `server.grpc_port`. Pass a clone of the
+/// HTTP server's state so both transports see the same stores.
+pub async fn run_grpc_server(
+    state: AppState,
+    config: &AppConfig,
+    shutdown: Option<CancellationToken>,
+) -> OnyxResult<()> {
+    let listener = bind_grpc(config).await?;
+    let api_keys = ApiKeys::from_config(&config.auth);
+    serve_grpc(state, api_keys, listener, shutdown.unwrap_or_default()).await
+}
+
+/// Bind the gRPC listener to `server.host` and `server.grpc_port`.
+pub async fn bind_grpc(config: &AppConfig) -> OnyxResult<tokio::net::TcpListener> {
+    let port = config.server.grpc_port.unwrap_or(DEFAULT_GRPC_PORT);
+    let addr: SocketAddr = format!("{}:{}", config.server.host, port)
+        .parse()
+        .map_err(|err| OnyxError::Internal(format!("invalid gRPC address: {err}")))?;
+    tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|err| OnyxError::Internal(format!("failed to bind gRPC server: {err}")))
+}
+
+/// Serve the HTTP API on `http` and the gRPC API on `grpc`, both over the
+/// one `state`, until `shutdown` is cancelled or the process is asked to
+/// stop. If either server fails, the other is shut down too.
+pub async fn serve_http_and_grpc(
+    state: AppState,
+    config: &AppConfig,
+    http: tokio::net::TcpListener,
+    grpc: tokio::net::TcpListener,
+    shutdown: CancellationToken,
+) -> OnyxResult<()> {
+    let api_keys = ApiKeys::from_config(&config.auth);
+    let http_server = async {
+        let result = super::serve_http(state.clone(), config, http, shutdown.clone()).await;
+        shutdown.cancel();
+        result
+    };
+    let grpc_server = async {
+        let result = serve_grpc(state.clone(), api_keys, grpc, shutdown.clone()).await;
+        shutdown.cancel();
+        result
+    };
+    let (http_result, grpc_result) = tokio::join!(http_server, grpc_server);
+    // Both servers have drained; flush what gRPC wrote after HTTP stopped.
+    let flushed = state.stores.flush().await;
+    drop(state);
+    http_result.and(grpc_result).and(flushed)
+}
+
+/// Serve every gRPC service on `listener` until `shutdown` is cancelled or
+/// the process is asked to stop.
+pub async fn serve_grpc(
+    state: AppState,
+    api_keys: ApiKeys,
+    listener: tokio::net::TcpListener,
+    shutdown: CancellationToken,
+) -> OnyxResult<()> {
+    if let Ok(addr) = listener.local_addr() {
+        tracing::info!(%addr, "gRPC server listening");
+    }
+    let incoming = TcpIncoming::from_listener(listener, true, None)
+        .map_err(|err| OnyxError::Internal(format!("failed to accept gRPC connections: {err}")))?;
+    let service = GrpcService { state };
+    let check = move |request: Request<()>| require_api_key(&api_keys, request);
+
+    Server::builder()
+        .add_service(NodeServiceServer::with_interceptor(
+            service.clone(),
+            check.clone(),
+        ))
+        .add_service(EdgeServiceServer::with_interceptor(
+            service.clone(),
+            check.clone(),
+        ))
+        .add_service(SearchServiceServer::with_interceptor(
+            service.clone(),
+            check.clone(),
+        ))
+        .add_service(HistoryServiceServer::with_interceptor(service, check))
+        .serve_with_incoming_shutdown(incoming, super::shutdown_signal(shutdown))
+        .await
+        .map_err(|err| OnyxError::Internal(format!("gRPC server error: {err}")))?;
+
+    tracing::info!("gRPC server stopped");
+    Ok(())
+}
+
+/// Reject calls that do not carry one of the configured API keys, read from
+/// `authorization: Bearer` or `x-api-key` metadata like the HTTP API.
+fn require_api_key(keys: &ApiKeys, request: Request<()>) -> Result<Request<()>, Status> {
+    if !keys.is_enabled() {
+        return Ok(request);
+    }
+    let metadata = request.metadata();
+    let presented = metadata
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| metadata.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+        .map(str::trim);
+    match presented {
+        Some(key) if keys.accepts(key) => Ok(request),
+        Some(_) => Err(Status::unauthenticated("invalid api key")),
+        None => Err(Status::unauthenticated("missing api key")),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Error and model conversions
+// ---------------------------------------------------------------------------
+
+/// gRPC status code for each [`ErrorCode`].
+fn grpc_code(code: ErrorCode) -> Code {
+    match code {
+        ErrorCode::BadRequest
+        | ErrorCode::DimensionMismatch
+        | ErrorCode::InvalidEmbedding
+        | ErrorCode::InvalidQuery
+        | ErrorCode::InvalidEdgeType
+        | ErrorCode::InvalidNode => Code::InvalidArgument,
+        ErrorCode::Unauthorized => Code::Unauthenticated,
+        ErrorCode::NodeNotFound
+        | ErrorCode::EdgeNotFound
+        | ErrorCode::VersionNotFound
+        | ErrorCode::BranchNotFound
+        | ErrorCode::NotFound => Code::NotFound,
+        ErrorCode::BranchAlreadyExists | ErrorCode::DuplicateNode | ErrorCode::DuplicateEdge => {
+            Code::AlreadyExists
+        }
+        ErrorCode::MergeConflict => Code::Aborted,
+        ErrorCode::ContentTooLarge => Code::ResourceExhausted,
+        ErrorCode::TransactionFailed
+        | ErrorCode::IngestionError
+        | ErrorCode::SerializationError
+        | ErrorCode::ConfigError
+        | ErrorCode::IoError
+        | ErrorCode::Internal
+        | ErrorCode::CorruptRecord
+        | ErrorCode::StorageError => Code::Internal,
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(err: ApiError) -> Self {
+        Status::new(grpc_code(err.code), err.message)
+    }
+}
+
+impl From<OnyxError> for Status {
+    fn from(err: OnyxError) -> Self {
+        ApiError::from(err).into()
+    }
+}
+
+fn parse_id(raw: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(raw).map_err(|_| Status::invalid_argument(format!("invalid id '{raw}'")))
+}
+
+fn parse_edge_types(raw: &[String]) -> Result<Option<Vec<EdgeType>>, Status> {
+    if raw.is_empty() {
+        return Ok(None);
+    }
+    let types = raw
+        .iter()
+        .map(|name| name.parse())
+        .collect::<Result<Vec<EdgeType>, OnyxError>>()?;
+    Ok(Some(types))
+}
+
+fn parse_node_type(raw: &str) -> Result<crate::model::node::NodeType, Status> {
+    nodes::parse_node_type_param(raw)
+        .ok_or_else(|| Status::invalid_argument(format!("unknown node type '{raw}'")))
+}
+
+impl From<Node> for proto::Node {
+    fn from(node: Node) -> Self {
+        Self {
+            id: node.id.to_string(),
+            node_type: node.node_type.as_str().to_string(),
+            name: node.name,
+            content: node.content,
+            metadata: node.metadata,
+            embedding: node.embedding.unwrap_or_default(),
+            current_version: node.current_version,
+            created_at: node.created_at.to_rfc3339(),
+            updated_at: node.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+impl From<Edge> for proto::Edge {
+    fn from(edge: Edge) -> Self {
+        Self {
+            id: edge.id.to_string(),
+            edge_type: edge.edge_type.as_str().to_string(),
+            source_id: edge.source_id.to_string(),
+            target_id: edge.target_id.to_string(),
+            confidence: edge.confidence,
+            metadata: edge.metadata,
+        }
+    }
+}
+
+impl From<VersionEntry> for proto::Version {
+    fn from(entry: VersionEntry) -> Self {
+        Self {
+            version_id: entry.version_id,
+            entity_id: entry.entity_id.to_string(),
+            parent_version: entry.parent_version,
+            branch: entry.branch,
+            commit_id: entry.commit_id,
+            author: entry.author,
+            message: entry.message,
+            timestamp: entry.timestamp.to_rfc3339(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Services
+// ---------------------------------------------------------------------------
+
+/// Implements every service over the shared [`AppState`].
+#[derive(Clone)]
+struct GrpcService {
+    state: AppState,
+}
+
+#[tonic::async_trait]
+impl NodeService for GrpcService {
+    async fn create_node(
+        &self,
+        request: Request<proto::CreateNodeRequest>,
+    ) -> Result<Response<proto::Node>, Status> {
+        let request = request.into_inner();
+        let create = nodes::CreateNodeRequest {
+            id: request.id.as_deref().map(parse_id).transpose()?,
+            name: request.name,
+            content: request.content,
+            node_type: request
+                .node_type
+                .as_deref()
+                .map(parse_node_type)
+                .transpose()?,
+            metadata: Some(request.metadata),
+            provenance: None,
+            embedding: (!request.embedding.is_empty()).then_some(request.embedding),
+        };
+        match nodes::create_nodes(&self.state, vec![create]).await? {
+            Ok(mut created) => Ok(Response::new(created.remove(0).into())),
+            Err(rejected) => Err(rejected.error.into()),
+        }
+    }
+
+    async fn get_node(
+        &self,
+        request: Request<proto::GetNodeRequest>,
+    ) -> Result<Response<proto::Node>, Status> {
+        let id = parse_id(&request.get_ref().id)?;
+        let node = self
+            .state
+            .stores
+            .graph_store
+            .get_node(&id)
+            .await?
+            .ok_or(OnyxError::NodeNotFound(id))?;
+        Ok(Response::new(node.into()))
+    }
+
+    async fn list_nodes(
+        &self,
+        request: Request<proto::ListNodesRequest>,
+    ) -> Result<Response<proto::ListNodesResponse>, Status> {
+        let graph = &self.state.stores.graph_store;
+        let mut found = match request.get_ref().node_type.as_deref() {
+            Some(raw) => graph.nodes_by_type(&parse_node_type(raw)?).await,
+            None => graph.all_nodes().await,
+        };
+        found.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        Ok(Response::new(proto::ListNodesResponse {
+            nodes: found.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn delete_node(
+        &self,
+        request: Request<proto::DeleteNodeRequest>,
+    ) -> Result<Response<proto::DeleteNodeResponse>, Status> {
+        let id = parse_id(&request.get_ref().id)?;
+        let version_id = self.state.stores.soft_delete_node(&id).await?;
+        Ok(Response::new(proto::DeleteNodeResponse { version_id }))
+    }
+}
+
+#[tonic::async_trait]
+impl EdgeService for GrpcService {
+    async fn create_edge(
+        &self,
+        request: Request<proto::CreateEdgeRequest>,
+    ) -> Result<Response<proto::Edge>, Status> {
+        let request = request.into_inner();
+        let create = edges::CreateEdgeRequest {
+            edge_type: request.edge_type.parse()?,
+            source_id: parse_id(&request.source_id)?,
+            target_id: parse_id(&request.target_id)?,
+            confidence: request.confidence,
+            metadata: Some(request.metadata),
+        };
+        let edge = edges::insert_edge(&self.state, create).await?;
+        Ok(Response::new(edge.into()))
+    }
+
+    async fn delete_edge(
+        &self,
+        request: Request<proto::DeleteEdgeRequest>,
+    ) -> Result<Response<proto::DeleteEdgeResponse>, Status> {
+        let id = parse_id(&request.get_ref().id)?;
+        edges::remove_edge(&self.state, id).await?;
+        Ok(Response::new(proto::DeleteEdgeResponse {}))
+    }
+
+    async fn neighbors(
+        &self,
+        request: Request<proto::NeighborsRequest>,
+    ) -> Result<Response<proto::NeighborsResponse>, Status> {
+        let request = request.into_inner();
+        let id = parse_id(&request.node_id)?;
+        let edge_types = parse_edge_types(&request.edge_types)?;
+        let graph = &self.state.stores.graph_store;
+        let found = match request.direction() {
+            proto::Direction::Outbound => graph.get_neighbors(&id, edge_types.as_deref()).await?,
+            proto::Direction::Inbound => graph.get_inbound(&id, edge_types.as_deref()).await?,
+        };
+        let neighbors = found
+            .into_iter()
+            .map(|(edge, node)| proto::Neighbor {
+                edge: Some(edge.into()),
+                node: Some(node.into()),
+            })
+            .collect();
+        Ok(Response::new(proto::NeighborsResponse { neighbors }))
+    }
+}
+
+#[tonic::async_trait]
+impl SearchService for GrpcService {
+    async fn search(
+        &self,
+        request: Request<proto::SearchRequest>,
+    ) -> Result<Response<proto::SearchResponse>, Status> {
+        let request = request.into_inner();
+        if request.embedding.is_empty() {
+            return Err(Status::invalid_argument("embedding must not be empty"));
+        }
+        let top_k = request.top_k.max(1) as usize;
+        let stores = &self.state.stores;
+        let scored = stores
+            .vector_store
+            .search(&request.embedding, top_k)
+            .await?;
+
+        let mut seen = HashSet::new();
+        let mut hits = Vec::with_capacity(scored.len());
+        for (id, score) in scored {
+            if !seen.insert(id) {
+                continue;
+            }
+            if let Some(node) = stores.graph_store.get_node(&id).await? {
+                hits.push(proto::SearchHit {
+                    node: Some(node.into()),
+                    score,
+                });
+            }
+        }
+        Ok(Response::new(proto::SearchResponse { hits }))
+    }
+}
+
+#[tonic::async_trait]
+impl HistoryService for GrpcService {
+    async fn list_versions(
+        &self,
+        request: Request<proto::ListVersionsRequest>,
+    ) -> Result<Response<proto::ListVersionsResponse>, Status> {
+        let id = parse_id(&request.get_ref().entity_id)?;
+        let versions = self.state.stores.history_store.list_versions(&id).await?;
+        Ok(Response::new(proto::ListVersionsResponse {
+            versions: versions.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get_content(
+        &self,
+        request: Request<proto::GetContentRequest>,
+    ) -> Result<Response<proto::GetContentResponse>, Status> {
+        let request = request.into_inner();
+        let id = parse_id(&request.entity_id)?;
+        let content = self
+            .state
+            .stores
+            .history_store
+            .get_content_at_version(&id, &request.version_id)
+            .await?;
+        Ok(Response::new(proto::GetContentResponse { content }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_not_found_maps_to_grpc_not_found() {
+        let status = Status::from(OnyxError::NodeNotFound(Uuid::nil()));
+        assert_eq!(status.code(), Code::NotFound);
+        assert!(status.message().contains(&Uuid::nil().to_string()));
+    }
+
+    #[test]
+    fn test_api_key_read_from_metadata() {
+        let keys = ApiKeys::from_config(&crate::config::AuthConfig {
+            enabled: true,
+            api_keys: vec!["secret".to_string()],
+        });
+        let mut request = Request::new(());
+        assert_eq!(
+            require_api_key(&keys, request).unwrap_err().code(),
+            Code::Unauthenticated
+        );
+        request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(API_KEY_HEADER, "secret".parse().unwrap());
+        assert!(require_api_key(&keys, request).is_ok());
+    }
+}
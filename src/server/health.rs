@@ -0,0 +1,91 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::server::AppState;
+
+/// Body returned by `GET /health`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub version_count: usize,
+    pub db_reachable: bool,
+}
+
+/// Readiness check: verifies the database and reports store counts.
+///
+/// Returns 503 when the database cannot be reached. Stores without a
+/// database connection, such as RocksDB opened in-process, are always
+/// reachable.
+pub async fn health(State(state): State<AppState>) -> impl IntoResponse {
+    let db_reachable = match state.stores.database() {
+        Some(db) => db.health().await.unwrap_or(false),
+        None => true,
+    };
+
+    if !db_reachable {
+        let body = HealthResponse {
+            status: "unavailable".to_string(),
+            node_count: 0,
+            edge_count: 0,
+            version_count: 0,
+            db_reachable,
+        };
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(body));
+    }
+
+    let stats = state.stores.stats().await;
+    let body = HealthResponse {
+        status: "ok".to_string(),
+        node_count: stats.node_count,
+        edge_count: stats.edge_count,
+        version_count: stats.version_count,
+        db_reachable,
+    };
+    (StatusCode::OK, Json(body))
+}
+
+/// Liveness check: always succeeds while the process is serving requests.
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::server::tests::test_state;
+    use crate::store::transaction::TransactionOp;
+
+    #[tokio::test]
+    async fn test_health_reports_counts() {
+        let state = test_state().await;
+        state
+            .stores
+            .execute(TransactionOp::InsertNode(Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                "func_a",
+                "fn func_a() {}",
+            )))
+            .await
+            .unwrap();
+
+        let response = health(State(state)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let report: HealthResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(report.status, "ok");
+        assert!(report.db_reachable);
+        assert_eq!(report.node_count, 1);
+        assert_eq!(report.edge_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_healthz_is_static() {
+        assert_eq!(healthz().await, "ok");
+    }
+}
@@ -0,0 +1,430 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::OnyxError;
+use crate::model::version::{
+    line_changes, new_version_id, unified_diff, Branch, Diff, VersionEntry, VersionId,
+};
+use crate::server::error::ApiError;
+use crate::server::AppState;
+use crate::store::history::merge_conflicts;
+use crate::store::transaction::TransactionOp;
+
+/// Request body for recording a version.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateVersionRequest {
+    pub entity_id: Uuid,
+    pub diff: Diff,
+    /// Defaults to the head of `branch` for the entity.
+    pub parent_version: Option<VersionId>,
+    /// Defaults to `main`.
+    pub branch: Option<String>,
+    pub commit_id: Option<String>,
+    pub author: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Request body for creating a branch.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateBranchRequest {
+    pub name: String,
+    pub base_version: VersionId,
+}
+
+/// Request body for merging one branch into another.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MergeBranchRequest {
+    pub source: String,
+    pub target: String,
+}
+
+/// Query parameters for `GET /api/entities/:id/diff`.
+#[derive(Debug, Deserialize)]
+pub struct DiffParams {
+    pub from: VersionId,
+    pub to: VersionId,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListVersionsResponse {
+    pub versions: Vec<VersionEntry>,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListBranchesResponse {
+    pub branches: Vec<Branch>,
+}
+
+/// Record a new version of an existing entity.
+pub async fn create_version(
+    State(state): State<AppState>,
+    Json(request): Json<CreateVersionRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if state
+        .stores
+        .graph_store
+        .get_node(&request.entity_id)
+        .await?
+        .is_none()
+    {
+        return Err(OnyxError::NodeNotFound(request.entity_id).into());
+    }
+    let branch = request.branch.unwrap_or_else(|| "main".to_string());
+    let parent_version = match request.parent_version {
+        Some(parent) => Some(parent),
+        None => {
+            state
+                .stores
+                .history_store
+                .get_head(&request.entity_id, &branch)
+                .await?
+        }
+    };
+
+    let entry = VersionEntry {
+        version_id: new_version_id(),
+        entity_id: request.entity_id,
+        parent_version,
+        branch,
+        diff: request.diff,
+        commit_id: request.commit_id,
+        author: request.author,
+        message: request.message,
+        timestamp: Utc::now(),
+    };
+    state
+        .stores
+        .execute(TransactionOp::RecordVersion(entry.clone()))
+        .await?;
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Fetch a version by ID.
+pub async fn get_version(
+    State(state): State<AppState>,
+    Path(version_id): Path<VersionId>,
+) -> Result<Json<VersionEntry>, ApiError> {
+    let entry = state
+        .stores
+        .history_store
+        .get_version(&version_id)
+        .await?
+        .ok_or(OnyxError::VersionNotFound(version_id))?;
+    Ok(Json(entry))
+}
+
+/// List every version of an entity.
+pub async fn list_versions(
+    State(state): State<AppState>,
+    Path(entity_id): Path<Uuid>,
+) -> Result<Json<ListVersionsResponse>, ApiError> {
+    let versions = state.stores.history_store.list_versions(&entity_id).await?;
+    Ok(Json(ListVersionsResponse {
+        total: versions.len(),
+        versions,
+    }))
+}
+
+/// An entity's content as of one of its versions.
+pub async fn content_at_version(
+    State(state): State<AppState>,
+    Path((entity_id, version_id)): Path<(Uuid, VersionId)>,
+) -> Result<Json<String>, ApiError> {
+    let content = state
+        .stores
+        .history_store
+        .get_content_at_version(&entity_id, &version_id)
+        .await?;
+    Ok(Json(content))
+}
+
+/// The change to an entity's content between two of its versions, as a
+/// unified diff.
+pub async fn diff(
+    State(state): State<AppState>,
+    Path(entity_id): Path<Uuid>,
+    Query(params): Query<DiffParams>,
+) -> Result<Json<Diff>, ApiError> {
+    let history = &state.stores.history_store;
+    let old = history
+        .get_content_at_version(&entity_id, &params.from)
+        .await?;
+    let new = history
+        .get_content_at_version(&entity_id, &params.to)
+        .await?;
+    let (additions, deletions) = line_changes(&old, &new);
+    Ok(Json(Diff::ContentChanged {
+        patch: unified_diff(&old, &new, &params.from, &params.to),
+        additions,
+        deletions,
+    }))
+}
+
+/// Create a branch forking from `base_version`.
+pub async fn create_branch(
+    State(state): State<AppState>,
+    Json(request): Json<CreateBranchRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let history = &state.stores.history_store;
+    history
+        .create_branch(&request.name, request.base_version)
+        .await?;
+    let branch = history
+        .get_branch(&request.name)
+        .await?
+        .ok_or(OnyxError::BranchNotFound(request.name))?;
+    Ok((StatusCode::CREATED, Json(branch)))
+}
+
+/// Fetch a branch by name.
+pub async fn get_branch(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Branch>, ApiError> {
+    let branch = state
+        .stores
+        .history_store
+        .get_branch(&name)
+        .await?
+        .ok_or(OnyxError::BranchNotFound(name))?;
+    Ok(Json(branch))
+}
+
+/// List every branch.
+pub async fn list_branches(State(state): State<AppState>) -> Json<ListBranchesResponse> {
+    Json(ListBranchesResponse {
+        branches: state.stores.history_store.list_branches().await,
+    })
+}
+
+/// Merge `source` into `target`, returning the merge version.
+///
+/// Refused with 409 `MERGE_CONFLICT`, listing the conflicting entity IDs in
+/// the error's `conflicts`, if both branches changed the same entity.
+pub async fn merge_branch(
+    State(state): State<AppState>,
+    Json(request): Json<MergeBranchRequest>,
+) -> Result<Json<VersionEntry>, ApiError> {
+    let history = state.stores.history_store.as_ref();
+    let conflicts = merge_conflicts(history, &request.source, &request.target).await?;
+    if !conflicts.is_empty() {
+        let message = format!(
+            "'{}' and '{}' both changed {} entities",
+            request.source,
+            request.target,
+            conflicts.len()
+        );
+        return Err(ApiError::merge_conflict(message, conflicts));
+    }
+
+    let version_id = history
+        .merge_branch(&request.source, &request.target)
+        .await?;
+    let entry = history
+        .get_version(&version_id)
+        .await?
+        .ok_or(OnyxError::VersionNotFound(version_id))?;
+    Ok(Json(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::server::error::ErrorEnvelope;
+    use crate::server::tests::test_state;
+
+    async fn insert_node(state: &AppState) -> Uuid {
+        let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "f", "");
+        let id = node.id;
+        state
+            .stores
+            .execute(TransactionOp::InsertNode(node))
+            .await
+            .unwrap();
+        id
+    }
+
+    fn version_request(entity_id: Uuid, diff: Diff) -> CreateVersionRequest {
+        CreateVersionRequest {
+            entity_id,
+            diff,
+            parent_version: None,
+            branch: None,
+            commit_id: None,
+            author: Some("dev".to_string()),
+            message: None,
+        }
+    }
+
+    async fn record(state: &AppState, request: CreateVersionRequest) -> VersionEntry {
+        let response = create_version(State(state.clone()), Json(request))
+            .await
+            .unwrap()
+            .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_versions_list_and_reconstruct_content() {
+        let state = test_state().await;
+        let id = insert_node(&state).await;
+        let first = record(
+            &state,
+            version_request(
+                id,
+                Diff::Initial {
+                    content: "fn a() {}\n".to_string(),
+                },
+            ),
+        )
+        .await;
+        let second = record(
+            &state,
+            version_request(
+                id,
+                Diff::ContentChanged {
+                    patch: "fn b() {}\n".to_string(),
+                    additions: 1,
+                    deletions: 1,
+                },
+            ),
+        )
+        .await;
+        assert_eq!(second.parent_version, Some(first.version_id.clone()));
+
+        let Json(listed) = list_versions(State(state.clone()), Path(id)).await.unwrap();
+        assert_eq!(listed.total, 2);
+
+        let Json(content) =
+            content_at_version(State(state.clone()), Path((id, first.version_id.clone())))
+                .await
+                .unwrap();
+        assert_eq!(content, "fn a() {}\n");
+
+        let Json(change) = diff(
+            State(state.clone()),
+            Path(id),
+            Query(DiffParams {
+                from: first.version_id,
+                to: second.version_id,
+            }),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            change,
+            Diff::ContentChanged {
+                additions: 1,
+                deletions: 1,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_version_for_unknown_entity_is_not_found() {
+        let state = test_state().await;
+        let request = version_request(
+            Uuid::new_v4(),
+            Diff::Initial {
+                content: String::new(),
+            },
+        );
+        let err = create_version(State(state), Json(request))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_branch_create_get_and_list() {
+        let state = test_state().await;
+        let id = insert_node(&state).await;
+        let base = record(
+            &state,
+            version_request(
+                id,
+                Diff::Initial {
+                    content: "fn a() {}\n".to_string(),
+                },
+            ),
+        )
+        .await;
+
+        let response = create_branch(
+            State(state.clone()),
+            Json(CreateBranchRequest {
+                name: "feature".to_string(),
+                base_version: base.version_id.clone(),
+            }),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let Json(branch) = get_branch(State(state.clone()), Path("feature".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(branch.base, base.version_id);
+        let Json(listed) = list_branches(State(state.clone())).await;
+        assert!(listed.branches.iter().any(|b| b.name == "feature"));
+
+        let missing = get_branch(State(state), Path("nope".to_string()))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(missing.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_merge_conflict_lists_entities_in_envelope() {
+        let state = test_state().await;
+        let id = insert_node(&state).await;
+        let history = state.stores.history_store.clone();
+        let base = history
+            .record_version(VersionEntry::initial(id, "fn a() {}\n"))
+            .await
+            .unwrap();
+        for (branch, content) in [("feature", "fn b() {}\n"), ("release", "fn c() {}\n")] {
+            history.create_branch(branch, base.clone()).await.unwrap();
+            let change =
+                VersionEntry::content_change(id, base.clone(), content, 1, 1).with_branch(branch);
+            history.record_version(change).await.unwrap();
+        }
+
+        let err = merge_branch(
+            State(state),
+            Json(MergeBranchRequest {
+                source: "feature".to_string(),
+                target: "release".to_string(),
+            }),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert_eq!(err.code, ErrorCode::MergeConflict);
+
+        let body = axum::body::to_bytes(err.into_response().into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let envelope: ErrorEnvelope = serde_json::from_slice(&body).unwrap();
+        assert_eq!(envelope.error.conflicts, vec![id]);
+    }
+}
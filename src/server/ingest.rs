@@ -0,0 +1,348 @@
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+    Json,
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::error::OnyxResult;
+use crate::ingest::{ingest_code_unit, link_ingested, CodeUnit, IngestOptions, IngestResult};
+use crate::model::node::{CodeEntityKind, Language, Visibility};
+use crate::server::error::ApiError;
+use crate::server::AppState;
+
+/// Embedding width used for server-side ingest when the store does not fix
+/// one.
+pub const DEFAULT_INGEST_DIMENSIONS: usize = 256;
+
+/// A code unit to ingest, as sent by clients.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestUnitRequest {
+    pub name: String,
+    pub content: String,
+    pub kind: CodeEntityKind,
+    pub language: Language,
+    pub file_path: String,
+    pub line_range: Option<(usize, usize)>,
+    pub signature: Option<String>,
+    /// Defaults to private.
+    pub visibility: Option<Visibility>,
+    pub module_path: Option<Vec<String>>,
+    pub commit_id: Option<String>,
+    pub branch: Option<String>,
+}
+
+impl From<IngestUnitRequest> for CodeUnit {
+    fn from(request: IngestUnitRequest) -> Self {
+        Self {
+            name: request.name,
+            content: request.content,
+            kind: request.kind,
+            language: request.language,
+            file_path: request.file_path,
+            line_range: request.line_range,
+            signature: request.signature,
+            visibility: request.visibility.unwrap_or(Visibility::Private),
+            module_path: request.module_path.unwrap_or_default(),
+            commit_id: request.commit_id,
+            branch: request.branch,
+        }
+    }
+}
+
+/// Request body for `POST /api/ingest/codebase` and `/api/ingest/stream`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IngestCodebaseRequest {
+    pub units: Vec<IngestUnitRequest>,
+}
+
+/// Result of ingesting one unit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestUnitResponse {
+    pub node_id: Uuid,
+    pub version_id: String,
+    pub edges_created: usize,
+}
+
+impl From<IngestResult> for IngestUnitResponse {
+    fn from(result: IngestResult) -> Self {
+        Self {
+            node_id: result.node_id,
+            version_id: result.version_id,
+            edges_created: result.edges_created,
+        }
+    }
+}
+
+/// Result of a batch ingest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestCodebaseResponse {
+    pub results: Vec<IngestUnitResponse>,
+    pub total_edges: usize,
+    /// Units that could not be ingested; the rest were still written.
+    pub failures: Vec<IngestFailure>,
+}
+
+/// A unit that failed to ingest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngestFailure {
+    /// Position of the unit in the submitted batch.
+    pub index: usize,
+    pub name: String,
+    pub error: String,
+}
+
+/// A server-sent event from `POST /api/ingest/stream`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum IngestEvent {
+    /// Another unit was processed. Edges are detected once every unit is
+    /// written, so `edges_detected` stays 0 until the last progress event.
+    Progress {
+        completed: usize,
+        total: usize,
+        edges_detected: usize,
+    },
+    /// A unit failed; ingestion continues with the rest.
+    Failed(IngestFailure),
+    /// Ingestion finished. Always the last event.
+    Complete {
+        ingested: usize,
+        failed: usize,
+        total_edges: usize,
+    },
+}
+
+/// Ingest a single code unit and link it to the rest of the graph.
+pub async fn ingest_unit(
+    State(state): State<AppState>,
+    Json(request): Json<IngestUnitRequest>,
+) -> Result<Json<IngestUnitResponse>, ApiError> {
+    let mut stores = state.stores.as_ref().clone();
+    let unit = CodeUnit::from(request);
+    let mut result = ingest_code_unit(&mut stores, &unit, &state.embedder, &state.limits).await?;
+    result.edges_created =
+        link_ingested(&mut stores, &[result.node_id], &IngestOptions::default()).await?;
+    Ok(Json(result.into()))
+}
+
+/// Ingest a batch of code units.
+///
+/// A unit that fails is reported in `failures` by its index; the others are
+/// still ingested and linked.
+pub async fn ingest_codebase(
+    State(state): State<AppState>,
+    Json(request): Json<IngestCodebaseRequest>,
+) -> Result<Json<IngestCodebaseResponse>, ApiError> {
+    Ok(Json(ingest_units(&state, request.units, |_| {}).await?))
+}
+
+/// Ingest a batch of code units, streaming an [`IngestEvent`] per unit and
+/// a final [`IngestEvent::Complete`].
+///
+/// If linking the ingested units fails, the stream ends without `Complete`.
+pub async fn ingest_stream(
+    State(state): State<AppState>,
+    Json(request): Json<IngestCodebaseRequest>,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let progress = tx.clone();
+        let outcome = ingest_units(&state, request.units, |event| {
+            // A closed channel means the client went away; keep ingesting.
+            let _ = progress.send(event);
+        })
+        .await;
+        match outcome {
+            Ok(response) => {
+                let _ = tx.send(IngestEvent::Complete {
+                    ingested: response.results.len(),
+                    failed: response.failures.len(),
+                    total_edges: response.total_edges,
+                });
+            }
+            Err(err) => tracing::warn!(%err, "streaming ingest failed"),
+        }
+    });
+
+    let events = futures::stream::unfold(rx, |mut rx| async move {
+        let event = rx.recv().await?;
+        Some((Event::default().json_data(&event), rx))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+/// Ingest `units` one by one, reporting each through `on_event`, then link
+/// the ones that were written.
+async fn ingest_units(
+    state: &AppState,
+    units: Vec<IngestUnitRequest>,
+    mut on_event: impl FnMut(IngestEvent),
+) -> OnyxResult<IngestCodebaseResponse> {
+    let mut stores = state.stores.as_ref().clone();
+    let total = units.len();
+    let mut results = Vec::with_capacity(total);
+    let mut failures = Vec::new();
+
+    for (index, request) in units.into_iter().enumerate() {
+        let unit = CodeUnit::from(request);
+        let outcome = ingest_code_unit(&mut stores, &unit, &state.embedder, &state.limits).await;
+        match outcome {
+            Ok(result) => {
+                results.push(IngestUnitResponse::from(result));
+                on_event(IngestEvent::Progress {
+                    completed: index + 1,
+                    total,
+                    edges_detected: 0,
+                });
+            }
+            Err(err) => {
+                let failure = IngestFailure {
+                    index,
+                    name: unit.name,
+                    error: err.to_string(),
+                };
+                failures.push(failure.clone());
+                on_event(IngestEvent::Failed(failure));
+            }
+        }
+    }
+
+    let node_ids: Vec<Uuid> = results.iter().map(|r| r.node_id).collect();
+    let total_edges = link_ingested(&mut stores, &node_ids, &IngestOptions::default()).await?;
+    for result in &mut results {
+        result.edges_created = total_edges;
+    }
+    on_event(IngestEvent::Progress {
+        completed: total,
+        total,
+        edges_detected: total_edges,
+    });
+
+    Ok(IngestCodebaseResponse {
+        results,
+        total_edges,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tests::test_state;
+
+    fn unit(name: &str, content: &str) -> IngestUnitRequest {
+        IngestUnitRequest {
+            name: name.to_string(),
+            content: content.to_string(),
+            kind: CodeEntityKind::Function,
+            language: Language::Rust,
+            file_path: "src/billing.rs".to_string(),
+            line_range: None,
+            signature: None,
+            visibility: Some(Visibility::Public),
+            module_path: None,
+            commit_id: None,
+            branch: None,
+        }
+    }
+
+    fn batch() -> Vec<IngestUnitRequest> {
+        vec![
+            unit(
+                "apply_discount",
+                "pub fn apply_discount(t: f64) -> f64 { t * 0.9 }",
+            ),
+            unit("oversized", &"x".repeat(64)),
+            unit(
+                "calculate_total",
+                "pub fn calculate_total(t: f64) -> f64 { apply_discount(t) }",
+            ),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_codebase_reports_failed_units_and_ingests_the_rest() {
+        let mut state = test_state().await;
+        state.limits.max_node_content_bytes = 63;
+
+        let Json(response) = ingest_codebase(
+            State(state.clone()),
+            Json(IngestCodebaseRequest { units: batch() }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.results.len(), 2);
+        assert_eq!(response.failures.len(), 1);
+        assert_eq!(response.failures[0].index, 1);
+        assert_eq!(response.failures[0].name, "oversized");
+        assert!(response.total_edges >= 1);
+        assert_eq!(state.stores.stats().await.node_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_arrive_in_order() {
+        let mut state = test_state().await;
+        state.limits.max_node_content_bytes = 63;
+
+        let mut events = Vec::new();
+        let response = ingest_units(&state, batch(), |event| events.push(event))
+            .await
+            .unwrap();
+
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            events[0],
+            IngestEvent::Progress {
+                completed: 1,
+                total: 3,
+                edges_detected: 0
+            }
+        );
+        assert!(matches!(&events[1], IngestEvent::Failed(f) if f.index == 1));
+        assert_eq!(
+            events[2],
+            IngestEvent::Progress {
+                completed: 3,
+                total: 3,
+                edges_detected: 0
+            }
+        );
+        assert_eq!(
+            events[3],
+            IngestEvent::Progress {
+                completed: 3,
+                total: 3,
+                edges_detected: response.total_edges
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_route_ends_with_complete() {
+        let state = test_state().await;
+        let sse = ingest_stream(State(state), Json(IngestCodebaseRequest { units: batch() })).await;
+        let body = axum::response::IntoResponse::into_response(sse).into_body();
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        let events: Vec<IngestEvent> = String::from_utf8(bytes.to_vec())
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).unwrap())
+            .collect();
+
+        assert_eq!(events.len(), 5);
+        assert!(matches!(
+            events.last(),
+            Some(IngestEvent::Complete {
+                ingested: 3,
+                failed: 0,
+                ..
+            })
+        ));
+    }
+}
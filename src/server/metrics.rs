@@ -0,0 +1,127 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header::CONTENT_TYPE,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use crate::server::AppState;
+
+/// Histogram buckets, in seconds, for request and query latencies.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+/// Install the process-wide Prometheus recorder on first use and return a
+/// handle for rendering it.
+///
+/// If the embedding application already installed its own recorder, metrics
+/// go there and `/metrics` only reports an empty registry.
+pub fn prometheus_handle() -> PrometheusHandle {
+    HANDLE
+        .get_or_init(|| {
+            let recorder = PrometheusBuilder::new()
+                .set_buckets(LATENCY_BUCKETS)
+                .expect("latency buckets are non-empty")
+                .build_recorder();
+            let handle = recorder.handle();
+            let _ = metrics::set_global_recorder(recorder);
+            handle
+        })
+        .clone()
+}
+
+/// Middleware counting requests and timing them, labelled by matched route.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let status = response.status().as_u16().to_string();
+    counter!(
+        "onyx_http_requests_total",
+        "method" => method.clone(),
+        "route" => route.clone(),
+        "status" => status
+    )
+    .increment(1);
+    histogram!(
+        "onyx_http_request_duration_seconds",
+        "method" => method,
+        "route" => route
+    )
+    .record(start.elapsed().as_secs_f64());
+
+    response
+}
+
+/// Prometheus text exposition of every recorded metric, with store counts
+/// refreshed at scrape time.
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let stats = state.stores.stats().await;
+    gauge!("onyx_nodes").set(stats.node_count as f64);
+    gauge!("onyx_edges").set(stats.edge_count as f64);
+    gauge!("onyx_embeddings").set(stats.embedding_count as f64);
+    gauge!("onyx_versions").set(stats.version_count as f64);
+
+    state.metrics.run_upkeep();
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.metrics.render(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::tests::test_state;
+    use axum::{body::Body, http::Request as HttpRequest, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn scrape(app: &Router) -> String {
+        let request = HttpRequest::get("/metrics").body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_counter_increments() {
+        let app = Router::new()
+            .route("/metrics-test/ping", get(|| async { "pong" }))
+            .route("/metrics", get(metrics))
+            .route_layer(middleware::from_fn(track_requests))
+            .with_state(test_state().await);
+
+        let request = HttpRequest::get("/metrics-test/ping")
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap();
+
+        let body = scrape(&app).await;
+        let counter = body
+            .lines()
+            .find(|line| {
+                line.starts_with("onyx_http_requests_total{")
+                    && line.contains("route=\"/metrics-test/ping\"")
+            })
+            .expect("request counter missing");
+        assert!(counter.contains("status=\"200\""));
+        assert!(counter.ends_with(" 1"));
+        assert!(body.contains("onyx_nodes 0"));
+    }
+}
@@ -1,48 +1,314 @@
-use axum::{routing::{get, post}, Router};
+use axum::{middleware, routing::{delete, get, post}, Router};
 use std::net::SocketAddr;
-use tower_http::cors::CorsLayer;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tower_http::trace::TraceLayer;
 
-use crate::config::{AppConfig, PaymentsConfig};
+use crate::config::{AppConfig, LimitsConfig, PaymentsConfig};
+use crate::db::DatabaseConfig;
 use crate::error::{OnyxError, OnyxResult};
+use crate::model::embedding::BagOfWordsEmbedder;
+use crate::store::events::event_sink;
+use crate::store::transaction::AsyncTransactionManager;
 
+pub mod auth;
+pub mod cors;
+pub mod edges;
+mod error;
+pub mod graph;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod history;
+pub mod ingest;
+pub mod metrics;
+pub mod nodes;
 pub mod payments;
+pub mod request_id;
+pub mod stats;
+pub mod subscribe;
+
+pub use error::{status_for, ErrorBody, ErrorEnvelope};
+
+use auth::{require_api_key, ApiKeys};
+use metrics_exporter_prometheus::PrometheusHandle;
+use payments::{CheckoutBackend, IdempotencyCache, StripeCheckoutBackend};
 
 #[derive(Clone)]
 pub struct AppState {
     pub stripe: stripe::Client,
     pub payments: PaymentsConfig,
+    pub checkout: Arc<dyn CheckoutBackend>,
+    pub idempotency: Arc<IdempotencyCache>,
+    pub stores: Arc<AsyncTransactionManager>,
+    /// Embeds code ingested through the API.
+    pub embedder: Arc<BagOfWordsEmbedder>,
+    pub limits: LimitsConfig,
+    pub metrics: PrometheusHandle,
 }
 
-pub async fn run_http_server(config: AppConfig) -> OnyxResult<()> {
-    let stripe_client = stripe::Client::new(config.payments.stripe_api_key.clone());
-    let state = AppState {
-        stripe: stripe_client,
-        payments: config.payments,
-    };
+impl AppState {
+    /// Open the configured database and build the state shared by the HTTP
+    /// and gRPC servers.
+    pub async fn from_config(config: AppConfig) -> OnyxResult<Self> {
+        let db = DatabaseConfig::default()
+            .connect()
+            .await
+            .map_err(|err| OnyxError::Internal(format!("failed to open database: {err}")))?;
+        Ok(Self::with_stores(
+            config,
+            AsyncTransactionManager::new(Arc::new(db)),
+        ))
+    }
 
-    let app = Router::new()
-        .route("/health", get(health))
-        .route("/billing/checkout", post(payments::create_checkout_session))
-        .route("/billing/portal", post(payments::create_billing_portal_session))
-        .route("/billing/webhook", post(payments::stripe_webhook))
-        .with_state(state)
-        .layer(CorsLayer::permissive());
+    /// Build the shared state over already opened stores, such as the
+    /// RocksDB ones from [`open_stores`](crate::store::persistent::open_stores).
+    ///
+    /// Ingested code is embedded with a hashed bag-of-words embedder as wide
+    /// as the vector store; see [`with_embedder`](Self::with_embedder).
+    pub fn with_stores(config: AppConfig, stores: AsyncTransactionManager) -> Self {
+        let stripe_client = stripe::Client::new(config.payments.stripe_api_key.clone());
+        let dimensions = stores
+            .vector_store
+            .dimensions()
+            .filter(|&d| d > 0)
+            .unwrap_or(ingest::DEFAULT_INGEST_DIMENSIONS);
+        Self {
+            checkout: Arc::new(StripeCheckoutBackend::new(stripe_client.clone())),
+            stripe: stripe_client,
+            payments: config.payments,
+            idempotency: Arc::new(IdempotencyCache::default()),
+            stores: Arc::new(stores.with_event_sink(event_sink())),
+            embedder: Arc::new(BagOfWordsEmbedder::hashed(dimensions)),
+            limits: config.limits,
+            metrics: metrics::prometheus_handle(),
+        }
+    }
+
+    /// Embed ingested code with `embedder`, such as the one saved with a
+    /// store.
+    pub fn with_embedder(mut self, embedder: BagOfWordsEmbedder) -> Self {
+        self.embedder = Arc::new(embedder);
+        self
+    }
+}
 
+/// Run the HTTP API until Ctrl-C, SIGTERM, or `shutdown` is cancelled.
+///
+/// In-flight requests are allowed to finish before the function returns.
+pub async fn run_http_server(
+    config: AppConfig,
+    shutdown: Option<CancellationToken>,
+) -> OnyxResult<()> {
+    let state = AppState::from_config(config.clone()).await?;
+    let listener = bind_http(&config).await?;
+    serve_http(state, &config, listener, shutdown.unwrap_or_default()).await
+}
+
+/// Bind the HTTP listener to `server.host` and `server.port`.
+pub async fn bind_http(config: &AppConfig) -> OnyxResult<tokio::net::TcpListener> {
     let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port)
         .parse()
         .map_err(|err| OnyxError::Internal(format!("invalid server address: {err}")))?;
-
-    let listener = tokio::net::TcpListener::bind(addr)
+    tokio::net::TcpListener::bind(addr)
         .await
-        .map_err(|err| OnyxError::Internal(format!("failed to bind server: {err}")))?;
+        .map_err(|err| OnyxError::Internal(format!("failed to bind server: {err}")))
+}
+
+/// Serve the HTTP API over `state` on `listener` until `shutdown` is
+/// cancelled or the process is asked to stop.
+///
+/// Once in-flight requests finish, the stores are flushed and this server's
+/// handle on them is dropped, closing them unless another server shares them.
+/// Auth and CORS come from `config`; its host and port are ignored.
+pub async fn serve_http(
+    state: AppState,
+    config: &AppConfig,
+    listener: tokio::net::TcpListener,
+    shutdown: CancellationToken,
+) -> OnyxResult<()> {
+    let stores = state.stores.clone();
+    let app = router(state, config)?;
 
+    if let Ok(addr) = listener.local_addr() {
+        tracing::info!(%addr, "HTTP server listening");
+    }
     axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal(shutdown))
         .await
         .map_err(|err| OnyxError::Internal(format!("server error: {err}")))?;
 
+    // The router and its copies of the state are gone; flush before the
+    // last handle closes the stores.
+    stores.flush().await?;
+    drop(stores);
+    tracing::info!("HTTP server stopped");
     Ok(())
 }
 
-async fn health() -> &'static str {
-    "ok"
+/// The HTTP API over `state`, with auth and CORS from `config`.
+pub(crate) fn router(state: AppState, config: &AppConfig) -> OnyxResult<Router> {
+    let api_keys = ApiKeys::from_config(&config.auth);
+    let cors = cors::build_cors_layer(config.server.cors.as_ref())?;
+
+    // Routes behind API key auth. Stripe signs webhooks itself, billing is
+    // reached before a customer has a key, and health checks and metrics
+    // must stay reachable for load balancers and scrapers, so those remain
+    // open.
+    let protected = Router::new()
+        .route("/api/nodes", get(nodes::list_nodes).post(nodes::create_node))
+        .route("/api/nodes/batch", post(nodes::create_nodes_batch))
+        .route("/api/nodes/:id/neighbors", get(edges::neighbors))
+        .route("/api/nodes/:id/inbound", get(edges::inbound))
+        .route("/api/nodes/:id/impact", get(graph::impact))
+        .route("/api/graph/traverse", post(graph::traverse))
+        .route("/api/versions", post(history::create_version))
+        .route("/api/versions/:id", get(history::get_version))
+        .route("/api/entities/:id/versions", get(history::list_versions))
+        .route(
+            "/api/entities/:id/versions/:version_id/content",
+            get(history::content_at_version),
+        )
+        .route("/api/entities/:id/diff", get(history::diff))
+        .route(
+            "/api/branches",
+            get(history::list_branches).post(history::create_branch),
+        )
+        .route("/api/branches/merge", post(history::merge_branch))
+        .route("/api/branches/:name", get(history::get_branch))
+        .route("/api/ingest/unit", post(ingest::ingest_unit))
+        .route("/api/ingest/codebase", post(ingest::ingest_codebase))
+        .route("/api/ingest/stream", post(ingest::ingest_stream))
+        .route("/api/edges", post(edges::create_edge))
+        .route("/api/edges/:id", delete(edges::delete_edge))
+        .route("/stats", get(stats::stats))
+        .route("/subscribe", get(subscribe::subscribe))
+        .route_layer(middleware::from_fn_with_state(api_keys, require_api_key));
+
+    let app = Router::new()
+        .route("/health", get(health::health))
+        .route("/healthz", get(health::healthz))
+        .route("/metrics", get(metrics::metrics))
+        .route("/billing/checkout", post(payments::create_checkout_session))
+        .route("/billing/portal", post(payments::create_billing_portal_session))
+        .route("/billing/webhook", post(payments::stripe_webhook))
+        .merge(protected)
+        .route_layer(middleware::from_fn(metrics::track_requests))
+        .with_state(state)
+        .layer(cors)
+        .layer(middleware::from_fn(request_id::assign_request_id))
+        .layer(TraceLayer::new_for_http());
+    Ok(app)
+}
+
+/// Resolve when the process is asked to stop or `token` is cancelled.
+async fn shutdown_signal(token: CancellationToken) {
+    let ctrl_c = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            // No signal handler available; rely on the token alone.
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+        _ = token.cancelled() => {},
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{AuthConfig, ServerConfig};
+    use crate::db::OnyxDatabase;
+
+    /// Build an `AppState` over an in-memory database and unused Stripe keys.
+    pub(crate) async fn test_state() -> AppState {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        AppState::with_stores(test_config(), AsyncTransactionManager::new(db))
+    }
+
+    pub(crate) fn test_config() -> AppConfig {
+        AppConfig {
+            server: ServerConfig {
+                host: "127.0.0.1".to_string(),
+                port: 0,
+                grpc_port: None,
+                cors: None,
+            },
+            payments: PaymentsConfig {
+                provider: Some("stripe".to_string()),
+                stripe_api_key: "sk_test_unused".to_string(),
+                stripe_webhook_secret: "whsec_unused".to_string(),
+                default_price_id: "price_default".to_string(),
+                success_url: "http://localhost/success".to_string(),
+                cancel_url: "http://localhost/cancel".to_string(),
+                portal_return_url: "http://localhost/portal".to_string(),
+            },
+            auth: AuthConfig::default(),
+            limits: LimitsConfig::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_token_stops_server() {
+        let token = CancellationToken::new();
+        let server = tokio::spawn(run_http_server(test_config(), Some(token.clone())));
+
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        token.cancel();
+
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("server did not shut down")
+            .expect("server task panicked");
+        assert!(result.is_ok());
+    }
+
+    #[cfg(feature = "rocksdb-storage")]
+    #[tokio::test]
+    async fn test_shutdown_flushes_and_closes_stores() {
+        use crate::model::node::{CodeEntityKind, Node, NodeType};
+        use crate::store::persistent::open_stores;
+        use crate::store::transaction::TransactionOp;
+
+        let dir = tempfile::tempdir().unwrap();
+        let stores = open_stores(dir.path(), None).await.unwrap();
+        let state = AppState::with_stores(test_config(), stores);
+        let node = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "kept", "");
+        let id = node.id;
+        state
+            .stores
+            .execute(TransactionOp::InsertNode(node))
+            .await
+            .unwrap();
+
+        let token = CancellationToken::new();
+        let config = test_config();
+        let listener = bind_http(&config).await.unwrap();
+        let server = tokio::spawn({
+            let token = token.clone();
+            async move { serve_http(state, &config, listener, token).await }
+        });
+        token.cancel();
+        server.await.unwrap().unwrap();
+
+        // RocksDB refuses a second open while any handle is still alive.
+        let reopened = open_stores(dir.path(), None).await.unwrap();
+        assert!(reopened.graph_store.get_node(&id).await.unwrap().is_some());
+    }
 }
@@ -0,0 +1,422 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use crate::config::CONTENT_TRUNCATED_METADATA;
+use crate::error::OnyxError;
+use crate::model::node::{CodeEntityKind, Node, NodeType, Provenance};
+use crate::server::error::ApiError;
+use crate::server::AppState;
+use crate::store::graph::GraphStore;
+use crate::store::transaction::TransactionOp;
+
+/// Largest page a client may request.
+pub const MAX_PAGE_SIZE: usize = 500;
+
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// Query parameters for `GET /api/nodes`.
+#[derive(Debug, Deserialize)]
+pub struct ListNodesParams {
+    /// 1-based page number.
+    pub page: Option<usize>,
+    pub page_size: Option<usize>,
+    /// Node type filter, e.g. `function`, `struct`, `doc`, `test`, `config`.
+    #[serde(rename = "type")]
+    pub node_type: Option<String>,
+}
+
+/// Request body for creating a node.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateNodeRequest {
+    /// Explicit ID; generated when absent.
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub content: String,
+    /// Defaults to a function code entity.
+    pub node_type: Option<NodeType>,
+    pub metadata: Option<HashMap<String, String>>,
+    pub provenance: Option<Provenance>,
+    pub embedding: Option<Vec<f32>>,
+}
+
+impl CreateNodeRequest {
+    fn into_node(self) -> Node {
+        let node_type = self
+            .node_type
+            .unwrap_or(NodeType::CodeEntity(CodeEntityKind::Function));
+        let mut node = Node::new(node_type, self.name, self.content);
+        if let Some(id) = self.id {
+            node.id = id;
+        }
+        if let Some(metadata) = self.metadata {
+            node.metadata = metadata;
+        }
+        if let Some(provenance) = self.provenance {
+            node = node.with_provenance(provenance);
+        }
+        if let Some(embedding) = self.embedding {
+            node = node.with_embedding(embedding);
+        }
+        node
+    }
+}
+
+/// One page of results.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_page: Option<usize>,
+    pub total: usize,
+}
+
+/// Parse the `type` query parameter into a node type.
+pub fn parse_node_type_param(value: &str) -> Option<NodeType> {
+    let kind = match value.to_lowercase().as_str() {
+        "doc" => return Some(NodeType::Doc),
+        "test" => return Some(NodeType::Test),
+        "config" => return Some(NodeType::Config),
+        "function" => CodeEntityKind::Function,
+        "struct" => CodeEntityKind::Struct,
+        "enum" => CodeEntityKind::Enum,
+        "trait" => CodeEntityKind::Trait,
+        "impl" => CodeEntityKind::Impl,
+        "module" => CodeEntityKind::Module,
+        "constant" => CodeEntityKind::Constant,
+        "typealias" | "type_alias" => CodeEntityKind::TypeAlias,
+        "macro" => CodeEntityKind::Macro,
+        _ => return None,
+    };
+    Some(NodeType::CodeEntity(kind))
+}
+
+/// List nodes one page at a time, ordered by creation time.
+pub async fn list_nodes(
+    State(state): State<AppState>,
+    Query(params): Query<ListNodesParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = params.page.unwrap_or(1).max(1);
+    let page_size = params
+        .page_size
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let graph = &state.stores.graph_store;
+    let mut nodes = match params.node_type.as_deref() {
+        Some(raw) => {
+            let node_type = parse_node_type_param(raw)
+                .ok_or_else(|| ApiError::bad_request(format!("unknown node type '{}'", raw)))?;
+            graph.nodes_by_type(&node_type).await
+        }
+        None => graph.all_nodes().await,
+    };
+    nodes.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+
+    Ok(Json(paginate(nodes, page, page_size)))
+}
+
+/// Create a single node.
+///
+/// A rejected node is reported by its own error, e.g. 409 for a duplicate ID
+/// or 413 for oversized content.
+pub async fn create_node(
+    State(state): State<AppState>,
+    Json(request): Json<CreateNodeRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    match create_nodes(&state, vec![request]).await? {
+        Ok(mut nodes) => Ok((StatusCode::CREATED, Json(nodes.remove(0)))),
+        Err(rejected) => Err(rejected.error.into()),
+    }
+}
+
+/// Create many nodes atomically.
+///
+/// Either every node is inserted or none are; an invalid item yields 422 with
+/// its index in the request as the error's `index`.
+pub async fn create_nodes_batch(
+    State(state): State<AppState>,
+    Json(requests): Json<Vec<CreateNodeRequest>>,
+) -> Result<Response, ApiError> {
+    match create_nodes(&state, requests).await? {
+        Ok(nodes) => Ok((StatusCode::CREATED, Json(nodes)).into_response()),
+        Err(rejected) => Ok(rejected.into_response()),
+    }
+}
+
+/// A batch item that failed validation.
+pub(crate) struct RejectedItem {
+    pub(crate) index: usize,
+    pub(crate) error: OnyxError,
+}
+
+impl IntoResponse for RejectedItem {
+    fn into_response(self) -> Response {
+        ApiError::invalid_item(self.index, self.error.to_string()).into_response()
+    }
+}
+
+/// Validate every request up front, then insert all nodes in one batch.
+///
+/// An item whose write fails is rejected by its index too, and the batch
+/// leaves no nodes behind.
+pub(crate) async fn create_nodes(
+    state: &AppState,
+    requests: Vec<CreateNodeRequest>,
+) -> Result<Result<Vec<Node>, RejectedItem>, ApiError> {
+    let graph = &state.stores.graph_store;
+    let mut seen = HashSet::new();
+    let mut nodes = Vec::with_capacity(requests.len());
+
+    for (index, mut request) in requests.into_iter().enumerate() {
+        if request.name.trim().is_empty() {
+            return Ok(Err(RejectedItem {
+                index,
+                error: OnyxError::InvalidNode("node name must not be empty".to_string()),
+            }));
+        }
+        let original_size = request.content.len();
+        let allowed = match state.limits.enforce(&request.content) {
+            Ok(content) => content.len(),
+            Err(error) => return Ok(Err(RejectedItem { index, error })),
+        };
+        request.content.truncate(allowed);
+        let mut node = request.into_node();
+        if allowed < original_size {
+            node.metadata.insert(
+                CONTENT_TRUNCATED_METADATA.to_string(),
+                original_size.to_string(),
+            );
+        }
+        let exists = graph.get_node(&node.id).await?.is_some();
+        if !seen.insert(node.id) || exists {
+            return Ok(Err(RejectedItem {
+                index,
+                error: OnyxError::DuplicateNode(node.id),
+            }));
+        }
+        nodes.push(node);
+    }
+
+    let mut ops = Vec::with_capacity(nodes.len() * 2);
+    // The request index each operation came from.
+    let mut op_items = Vec::with_capacity(nodes.len() * 2);
+    for (index, node) in nodes.iter().enumerate() {
+        ops.push(TransactionOp::InsertNode(node.clone()));
+        op_items.push(index);
+        if let Some(embedding) = &node.embedding {
+            ops.push(TransactionOp::InsertEmbedding {
+                id: node.id,
+                embedding: embedding.clone(),
+            });
+            op_items.push(index);
+        }
+    }
+
+    if let Err(failure) = state.stores.try_execute_batch(ops).await {
+        return match failure.op_index {
+            Some(op) => Ok(Err(RejectedItem {
+                index: op_items[op],
+                error: failure.error,
+            })),
+            None => Err(failure.error.into()),
+        };
+    }
+
+    Ok(Ok(nodes))
+}
+
+fn paginate(items: Vec<Node>, page: usize, page_size: usize) -> Page<Node> {
+    let total = items.len();
+    let start = (page - 1).saturating_mul(page_size);
+    let items: Vec<Node> = items.into_iter().skip(start).take(page_size).collect();
+    let next_page = if start + items.len() < total {
+        Some(page + 1)
+    } else {
+        None
+    };
+    Page {
+        items,
+        next_page,
+        total,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::server::error::ErrorEnvelope;
+    use crate::server::tests::{test_config, test_state};
+    use crate::store::graph::InMemoryGraphStore;
+    use crate::store::history::InMemoryHistoryStore;
+    use crate::store::transaction::AsyncTransactionManager;
+    use crate::store::vector::InMemoryVectorStore;
+    use std::sync::Arc;
+
+    fn create_request(name: &str, id: Option<Uuid>) -> CreateNodeRequest {
+        CreateNodeRequest {
+            id,
+            name: name.to_string(),
+            content: format!("fn {}() {{}}", name),
+            node_type: None,
+            metadata: None,
+            provenance: None,
+            embedding: None,
+        }
+    }
+
+    fn nodes(n: usize) -> Vec<Node> {
+        (0..n)
+            .map(|i| {
+                Node::new(
+                    NodeType::CodeEntity(CodeEntityKind::Function),
+                    format!("func_{}", i),
+                    "fn f() {}",
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paginate_pages() {
+        let first = paginate(nodes(5), 1, 2);
+        assert_eq!(first.items.len(), 2);
+        assert_eq!(first.next_page, Some(2));
+        assert_eq!(first.total, 5);
+
+        let last = paginate(nodes(5), 3, 2);
+        assert_eq!(last.items.len(), 1);
+        assert_eq!(last.next_page, None);
+    }
+
+    #[test]
+    fn test_parse_node_type_param() {
+        assert_eq!(
+            parse_node_type_param("Function"),
+            Some(NodeType::CodeEntity(CodeEntityKind::Function))
+        );
+        assert_eq!(parse_node_type_param("doc"), Some(NodeType::Doc));
+        assert_eq!(parse_node_type_param("widget"), None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_create_inserts_all() {
+        let state = test_state().await;
+        let response = create_nodes_batch(
+            State(state.clone()),
+            Json(vec![create_request("a", None), create_request("b", None)]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(state.stores.stats().await.node_count, 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_with_duplicate_rolls_back() {
+        let state = test_state().await;
+        let existing = Uuid::new_v4();
+        create_node(
+            State(state.clone()),
+            Json(create_request("existing", Some(existing))),
+        )
+        .await
+        .unwrap();
+
+        let response = create_nodes_batch(
+            State(state.clone()),
+            Json(vec![
+                create_request("a", None),
+                create_request("dup", Some(existing)),
+                create_request("b", None),
+            ]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let envelope: ErrorEnvelope = serde_json::from_slice(&body).unwrap();
+        assert_eq!(envelope.error.code, ErrorCode::InvalidNode);
+        assert_eq!(envelope.error.index, Some(1));
+        assert_eq!(state.stores.stats().await.node_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_batch_write_failure_leaves_no_nodes() {
+        // No database transaction here, so the earlier inserts must be undone.
+        let stores = AsyncTransactionManager::with_stores(
+            Arc::new(InMemoryVectorStore::with_dimensions(2)),
+            Arc::new(InMemoryGraphStore::new()),
+            Arc::new(InMemoryHistoryStore::new()),
+        );
+        let state = AppState::with_stores(test_config(), stores);
+        let mut first = create_request("a", None);
+        first.embedding = Some(vec![1.0, 0.0]);
+        // Passes validation; only the vector store rejects the width.
+        let mut second = create_request("b", None);
+        second.embedding = Some(vec![1.0, 0.0, 0.0]);
+
+        let response = create_nodes_batch(
+            State(state.clone()),
+            Json(vec![first, second, create_request("c", None)]),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let envelope: ErrorEnvelope = serde_json::from_slice(&body).unwrap();
+        assert_eq!(envelope.error.code, ErrorCode::InvalidNode);
+        assert_eq!(envelope.error.index, Some(1));
+        let stats = state.stores.stats().await;
+        assert_eq!(stats.node_count, 0);
+        assert_eq!(stats.embedding_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_rejects_oversized_content() {
+        let mut state = test_state().await;
+        state.limits.max_node_content_bytes = 8;
+        let mut request = create_request("big", None);
+        request.content = "x".repeat(9);
+
+        let err = create_node(State(state.clone()), Json(request))
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(err.status, StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(err.code, ErrorCode::ContentTooLarge);
+        assert_eq!(state.stores.stats().await.node_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_duplicate_is_conflict() {
+        let state = test_state().await;
+        let id = Uuid::new_v4();
+        create_node(State(state.clone()), Json(create_request("a", Some(id))))
+            .await
+            .unwrap();
+
+        let err = create_node(State(state.clone()), Json(create_request("b", Some(id))))
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert_eq!(err.code, ErrorCode::DuplicateNode);
+        assert_eq!(state.stores.stats().await.node_count, 1);
+    }
+}
@@ -1,15 +1,23 @@
+use async_trait::async_trait;
 use axum::{
     body::Bytes,
     extract::State,
     http::{HeaderMap, StatusCode},
-    response::{IntoResponse, Response},
+    response::IntoResponse,
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
+use crate::server::error::ApiError;
 use crate::server::AppState;
 
+/// Header clients use to make checkout creation safe to retry.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
 #[derive(Debug, Deserialize)]
 pub struct CheckoutSessionRequest {
     pub customer_email: Option<String>,
@@ -21,7 +29,7 @@ pub struct CheckoutSessionRequest {
     pub metadata: Option<HashMap<String, String>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct CheckoutSessionResponse {
     pub id: String,
     pub url: String,
@@ -38,91 +46,274 @@ pub struct BillingPortalResponse {
     pub url: String,
 }
 
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
-    error: String,
+// ---------------------------------------------------------------------------
+// Checkout backend
+// ---------------------------------------------------------------------------
+
+/// Resolved parameters for a checkout session, after applying config defaults.
+#[derive(Debug, Clone)]
+pub struct CheckoutParams {
+    pub price_id: String,
+    pub success_url: String,
+    pub cancel_url: String,
+    pub customer_email: Option<String>,
+    pub customer_id: Option<String>,
+    pub reference_id: Option<String>,
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+impl CheckoutParams {
+    /// Derive a stable idempotency key from the request contents.
+    pub fn derive_idempotency_key(&self) -> String {
+        let mut hasher = Sha256::new();
+        for field in [
+            Some(self.price_id.as_str()),
+            Some(self.success_url.as_str()),
+            Some(self.cancel_url.as_str()),
+            self.customer_email.as_deref(),
+            self.customer_id.as_deref(),
+            self.reference_id.as_deref(),
+        ] {
+            hasher.update(field.unwrap_or("").as_bytes());
+            hasher.update([0u8]);
+        }
+        if let Some(metadata) = &self.metadata {
+            let sorted: BTreeMap<_, _> = metadata.iter().collect();
+            for (key, value) in sorted {
+                hasher.update(key.as_bytes());
+                hasher.update([b'=']);
+                hasher.update(value.as_bytes());
+                hasher.update([0u8]);
+            }
+        }
+        let digest = hasher.finalize();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("onyx-checkout-{}", hex)
+    }
+}
+
+/// Creates hosted checkout sessions. Abstracted so tests can avoid Stripe.
+#[async_trait]
+pub trait CheckoutBackend: Send + Sync {
+    async fn create_session(
+        &self,
+        params: CheckoutParams,
+        idempotency_key: &str,
+    ) -> Result<CheckoutSessionResponse, String>;
+}
+
+/// Stripe-backed checkout sessions.
+pub struct StripeCheckoutBackend {
+    client: stripe::Client,
+}
+
+impl StripeCheckoutBackend {
+    pub fn new(client: stripe::Client) -> Self {
+        Self { client }
+    }
 }
 
-#[derive(Debug)]
-struct ApiError {
-    status: StatusCode,
-    message: String,
+#[async_trait]
+impl CheckoutBackend for StripeCheckoutBackend {
+    async fn create_session(
+        &self,
+        params: CheckoutParams,
+        idempotency_key: &str,
+    ) -> Result<CheckoutSessionResponse, String> {
+        let client = self
+            .client
+            .clone()
+            .with_strategy(stripe::RequestStrategy::Idempotent(idempotency_key.to_string()));
+
+        let mut create = stripe::CreateCheckoutSession::new();
+        create.success_url = Some(params.success_url.as_str());
+        create.cancel_url = Some(params.cancel_url.as_str());
+        create.mode = Some(stripe::CheckoutSessionMode::Subscription);
+        create.line_items = Some(vec![stripe::CreateCheckoutSessionLineItems {
+            price: Some(params.price_id.clone()),
+            quantity: Some(1),
+            ..Default::default()
+        }]);
+        create.automatic_tax = Some(stripe::CreateCheckoutSessionAutomaticTax { enabled: true });
+        create.billing_address_collection = Some(
+            stripe::CheckoutSessionBillingAddressCollection::Required,
+        );
+        create.tax_id_collection =
+            Some(stripe::CreateCheckoutSessionTaxIdCollection { enabled: true });
+        create.customer_email = params.customer_email.as_deref();
+        create.customer = params
+            .customer_id
+            .as_ref()
+            .and_then(|id| id.parse().ok());
+        create.client_reference_id = params.reference_id.as_deref();
+        create.metadata = params.metadata.clone().map(|items| items.into_iter().collect());
+
+        let session = stripe::CheckoutSession::create(&client, create)
+            .await
+            .map_err(|err| format!("stripe checkout error: {err}"))?;
+
+        let url = session
+            .url
+            .ok_or_else(|| "stripe session missing url".to_string())?;
+
+        Ok(CheckoutSessionResponse {
+            id: session.id.to_string(),
+            url,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Idempotency cache
+// ---------------------------------------------------------------------------
+
+/// Most idempotency keys [`IdempotencyCache::default`] remembers at once.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 10_000;
+
+/// A checkout session remembered under its idempotency key.
+struct CachedCheckout {
+    created: Instant,
+    /// [`CheckoutParams::derive_idempotency_key`] of the request that made it.
+    request_hash: String,
+    response: CheckoutSessionResponse,
+}
+
+/// What [`IdempotencyCache::get`] knows about a key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotentReplay {
+    /// No live entry for the key.
+    Miss,
+    /// The key was used for the same request; replay its response.
+    Hit(CheckoutSessionResponse),
+    /// The key was used for a different request.
+    Mismatch,
+}
+
+/// Remembers recently created checkout sessions by idempotency key, for at
+/// most `ttl` and at most `capacity` keys, dropping the oldest first.
+pub struct IdempotencyCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, CachedCheckout>>,
 }
 
-impl ApiError {
-    fn bad_request(message: impl Into<String>) -> Self {
+impl IdempotencyCache {
+    /// Create a cache whose entries expire after `ttl`, holding at most
+    /// `capacity` of them.
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
         Self {
-            status: StatusCode::BAD_REQUEST,
-            message: message.into(),
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
         }
     }
 
-    fn internal(message: impl Into<String>) -> Self {
-        Self {
-            status: StatusCode::INTERNAL_SERVER_ERROR,
-            message: message.into(),
+    /// Look up a live entry for `key` made by the request hashing to
+    /// `request_hash`, evicting expired entries as a side effect.
+    pub fn get(&self, key: &str, request_hash: &str) -> IdempotentReplay {
+        let Ok(mut entries) = self.entries.lock() else {
+            return IdempotentReplay::Miss;
+        };
+        let ttl = self.ttl;
+        entries.retain(|_, entry| entry.created.elapsed() < ttl);
+        match entries.get(key) {
+            None => IdempotentReplay::Miss,
+            Some(entry) if entry.request_hash == request_hash => {
+                IdempotentReplay::Hit(entry.response.clone())
+            }
+            Some(_) => IdempotentReplay::Mismatch,
+        }
+    }
+
+    /// Record the response produced for `key` by the request hashing to
+    /// `request_hash`, evicting the oldest entry when full.
+    pub fn insert(&self, key: String, request_hash: String, response: CheckoutSessionResponse) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        if self.capacity > 0 {
+            let entry = CachedCheckout {
+                created: Instant::now(),
+                request_hash,
+                response,
+            };
+            entries.insert(key, entry);
         }
     }
 }
 
-impl IntoResponse for ApiError {
-    fn into_response(self) -> Response {
-        let body = Json(ErrorResponse { error: self.message });
-        (self.status, body).into_response()
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(
+            Duration::from_secs(24 * 60 * 60),
+            DEFAULT_IDEMPOTENCY_CAPACITY,
+        )
     }
 }
 
 pub async fn create_checkout_session(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<CheckoutSessionRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let price_id = request
-        .price_id
-        .clone()
-        .unwrap_or_else(|| state.payments.default_price_id.clone());
-    let success_url = request
-        .success_url
-        .clone()
-        .unwrap_or_else(|| state.payments.success_url.clone());
-    let cancel_url = request
-        .cancel_url
-        .clone()
-        .unwrap_or_else(|| state.payments.cancel_url.clone());
-
-    let mut params = stripe::CreateCheckoutSession::new();
-    params.success_url = Some(success_url.as_str());
-    params.cancel_url = Some(cancel_url.as_str());
-    params.mode = Some(stripe::CheckoutSessionMode::Subscription);
-    params.line_items = Some(vec![stripe::CreateCheckoutSessionLineItems {
-        price: Some(price_id),
-        quantity: Some(1),
-        ..Default::default()
-    }]);
-    params.automatic_tax = Some(stripe::CreateCheckoutSessionAutomaticTax { enabled: true });
-    params.billing_address_collection = Some(
-        stripe::CheckoutSessionBillingAddressCollection::Required,
-    );
-    params.tax_id_collection = Some(stripe::CreateCheckoutSessionTaxIdCollection { enabled: true });
-    params.customer_email = request.customer_email.as_deref();
-    params.customer = request
-        .customer_id
-        .as_ref()
-        .and_then(|id| id.parse().ok());
-    params.client_reference_id = request.reference_id.as_deref();
-    params.metadata = request.metadata.clone().map(|items| items.into_iter().collect());
+    let params = CheckoutParams {
+        price_id: request
+            .price_id
+            .clone()
+            .unwrap_or_else(|| state.payments.default_price_id.clone()),
+        success_url: request
+            .success_url
+            .clone()
+            .unwrap_or_else(|| state.payments.success_url.clone()),
+        cancel_url: request
+            .cancel_url
+            .clone()
+            .unwrap_or_else(|| state.payments.cancel_url.clone()),
+        customer_email: request.customer_email.clone(),
+        customer_id: request.customer_id.clone(),
+        reference_id: request.reference_id.clone(),
+        metadata: request.metadata.clone(),
+    };
+
+    let request_hash = params.derive_idempotency_key();
+    let idempotency_key = match headers.get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => value
+            .to_str()
+            .map_err(|_| ApiError::bad_request("invalid Idempotency-Key header"))?
+            .to_string(),
+        None => request_hash.clone(),
+    };
+
+    match state.idempotency.get(&idempotency_key, &request_hash) {
+        IdempotentReplay::Hit(cached) => return Ok(Json(cached)),
+        IdempotentReplay::Mismatch => {
+            return Err(ApiError::bad_request(
+                "Idempotency-Key was already used for a different checkout request",
+            ))
+        }
+        IdempotentReplay::Miss => {}
+    }
 
-    let session = stripe::CheckoutSession::create(&state.stripe, params)
+    let response = state
+        .checkout
+        .create_session(params, &idempotency_key)
         .await
-        .map_err(|err| ApiError::internal(format!("stripe checkout error: {err}")))?;
+        .map_err(ApiError::internal)?;
 
-    let url = session
-        .url
-        .ok_or_else(|| ApiError::internal("stripe session missing url"))?;
+    state
+        .idempotency
+        .insert(idempotency_key, request_hash, response.clone());
 
-    Ok(Json(CheckoutSessionResponse {
-        id: session.id.to_string(),
-        url,
-    }))
+    Ok(Json(response))
 }
 
 pub async fn create_billing_portal_session(
@@ -175,12 +366,156 @@ pub async fn stripe_webhook(
         | stripe::EventType::CustomerSubscriptionUpdated
         | stripe::EventType::CustomerSubscriptionDeleted
         | stripe::EventType::InvoicePaymentFailed => {
-            println!("stripe webhook event: {:?}", event.type_);
+            tracing::info!(event_type = ?event.type_, "stripe webhook event");
         }
         _ => {
-            println!("stripe webhook ignored event: {:?}", event.type_);
+            tracing::debug!(event_type = ?event.type_, "stripe webhook event ignored");
         }
     }
 
     Ok(StatusCode::OK)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct MockCheckout {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CheckoutBackend for MockCheckout {
+        async fn create_session(
+            &self,
+            _params: CheckoutParams,
+            idempotency_key: &str,
+        ) -> Result<CheckoutSessionResponse, String> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(CheckoutSessionResponse {
+                id: format!("cs_test_{}", n),
+                url: format!("https://checkout.example/{}", idempotency_key),
+            })
+        }
+    }
+
+    async fn test_state(checkout: Arc<MockCheckout>) -> AppState {
+        let mut state = crate::server::tests::test_state().await;
+        state.checkout = checkout;
+        state
+    }
+
+    fn request() -> CheckoutSessionRequest {
+        CheckoutSessionRequest {
+            customer_email: Some("dev@example.com".to_string()),
+            customer_id: None,
+            price_id: None,
+            success_url: None,
+            cancel_url: None,
+            reference_id: Some("ref-1".to_string()),
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_checkout_idempotency_key_replay() {
+        let mock = Arc::new(MockCheckout {
+            calls: AtomicUsize::new(0),
+        });
+        let state = test_state(mock.clone()).await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_static("retry-123"));
+
+        let first = create_checkout_session(
+            State(state.clone()),
+            headers.clone(),
+            Json(request()),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let second = create_checkout_session(State(state), headers, Json(request()))
+            .await
+            .unwrap()
+            .into_response();
+
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+        assert_eq!(mock.calls.load(Ordering::SeqCst), 1);
+
+        let first_body = axum::body::to_bytes(first.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let second_body = axum::body::to_bytes(second.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(first_body, second_body);
+    }
+
+    #[tokio::test]
+    async fn test_checkout_derived_key_is_stable() {
+        let mock = Arc::new(MockCheckout {
+            calls: AtomicUsize::new(0),
+        });
+        let state = test_state(mock.clone()).await;
+
+        for _ in 0..2 {
+            create_checkout_session(State(state.clone()), HeaderMap::new(), Json(request()))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(mock.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_checkout_key_reused_for_another_request_is_rejected() {
+        let mock = Arc::new(MockCheckout {
+            calls: AtomicUsize::new(0),
+        });
+        let state = test_state(mock.clone()).await;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            IDEMPOTENCY_KEY_HEADER,
+            HeaderValue::from_static("retry-123"),
+        );
+
+        create_checkout_session(State(state.clone()), headers.clone(), Json(request()))
+            .await
+            .unwrap();
+        let other = CheckoutSessionRequest {
+            reference_id: Some("ref-2".to_string()),
+            ..request()
+        };
+        let err = create_checkout_session(State(state), headers, Json(other))
+            .await
+            .err()
+            .unwrap();
+
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(mock.calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_idempotency_cache_drops_oldest_key_when_full() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60), 2);
+        let response = |id: &str| CheckoutSessionResponse {
+            id: id.to_string(),
+            url: format!("https://checkout.example/{id}"),
+        };
+        for key in ["a", "b", "c"] {
+            cache.insert(key.to_string(), "hash".to_string(), response(key));
+        }
+
+        assert_eq!(cache.get("a", "hash"), IdempotentReplay::Miss);
+        assert_eq!(cache.get("c", "hash"), IdempotentReplay::Hit(response("c")));
+
+        let expired = IdempotencyCache::new(Duration::ZERO, 2);
+        expired.insert("a".to_string(), "hash".to_string(), response("a"));
+        assert_eq!(expired.get("a", "hash"), IdempotentReplay::Miss);
+    }
+}
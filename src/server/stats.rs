@@ -0,0 +1,45 @@
+use axum::{extract::State, Json};
+
+use crate::server::AppState;
+use crate::store::transaction::StoreStats;
+
+/// Store counts with per-type breakdowns, for dashboards and ops tooling.
+pub async fn stats(State(state): State<AppState>) -> Json<StoreStats> {
+    Json(state.stores.stats_with_breakdown().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::server::tests::test_state;
+    use crate::store::transaction::TransactionOp;
+    use axum::response::IntoResponse;
+
+    #[tokio::test]
+    async fn test_stats_reports_nodes_by_type() {
+        let state = test_state().await;
+        state
+            .stores
+            .execute_batch(vec![
+                TransactionOp::InsertNode(Node::new(
+                    NodeType::CodeEntity(CodeEntityKind::Function),
+                    "func_a",
+                    "fn func_a() {}",
+                )),
+                TransactionOp::InsertNode(Node::new(NodeType::Doc, "README", "# Onyx")),
+            ])
+            .await
+            .unwrap();
+
+        let response = stats(State(state)).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["node_count"], 2);
+        assert_eq!(json["nodes_by_type"]["function"], 1);
+        assert_eq!(json["nodes_by_type"]["doc"], 1);
+        assert!(json["edges_by_type"].as_object().unwrap().is_empty());
+    }
+}
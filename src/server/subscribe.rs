@@ -0,0 +1,151 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::model::edge::EdgeType;
+use crate::server::error::ApiError;
+use crate::server::AppState;
+use crate::store::events::{ChangeEvent, StoreEvent};
+
+/// Optional filter a client may send as a text message after connecting,
+/// and again later to replace it.
+///
+/// `node_types` restricts node events and `edge_types` restricts edge
+/// events; version events are always delivered.
+#[derive(Debug, Default, Deserialize)]
+pub struct SubscribeFilter {
+    /// Node type labels, e.g. `function`, `doc`; all types if absent.
+    pub node_types: Option<Vec<String>>,
+    pub edge_types: Option<Vec<EdgeType>>,
+}
+
+impl SubscribeFilter {
+    /// Whether `event` passes the filter.
+    pub fn matches(&self, event: &ChangeEvent) -> bool {
+        match event {
+            ChangeEvent::NodeCreated { node } | ChangeEvent::NodeUpdated { node } => {
+                self.node_types.as_ref().map_or(true, |types| {
+                    types.iter().any(|t| t == node.node_type.as_str())
+                })
+            }
+            ChangeEvent::EdgeCreated { edge } => self
+                .edge_types
+                .as_ref()
+                .map_or(true, |types| types.contains(&edge.edge_type)),
+            ChangeEvent::VersionRecorded { .. } => true,
+        }
+    }
+}
+
+/// Upgrade to a WebSocket that streams [`ChangeEvent`]s as JSON.
+pub async fn subscribe(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    // Subscribe before the upgrade completes so no commit made after the
+    // client sees the handshake is missed.
+    let Some(events) = state.stores.subscribe() else {
+        return ApiError::internal("change events are not enabled").into_response();
+    };
+    ws.on_upgrade(move |socket| stream_changes(socket, events))
+}
+
+async fn stream_changes(mut socket: WebSocket, mut events: broadcast::Receiver<StoreEvent>) {
+    let mut filter = SubscribeFilter::default();
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => match serde_json::from_str(&text) {
+                    Ok(updated) => filter = updated,
+                    Err(err) => tracing::debug!(%err, "ignoring malformed subscribe filter"),
+                },
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            event = events.recv() => match event {
+                Ok(event) => {
+                    for change in event.changes.iter().filter(|c| filter.matches(c)) {
+                        let Ok(json) = serde_json::to_string(change) else {
+                            continue;
+                        };
+                        if socket.send(Message::Text(json)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "subscriber fell behind; events dropped");
+                }
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::edge::Edge;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::server::tests::test_state;
+    use crate::store::transaction::TransactionOp;
+    use axum::{routing::get, Router};
+    use futures::StreamExt;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_subscriber_receives_node_created() {
+        let state = test_state().await;
+        let app = Router::new()
+            .route("/subscribe", get(subscribe))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/subscribe"))
+            .await
+            .unwrap();
+
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "calculate_total",
+            "fn calculate_total() {}",
+        );
+        let id = node.id;
+        state
+            .stores
+            .execute(TransactionOp::InsertNode(node))
+            .await
+            .unwrap();
+
+        let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+            .await
+            .expect("no event received")
+            .unwrap()
+            .unwrap();
+        let event: ChangeEvent = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        match event {
+            ChangeEvent::NodeCreated { node } => assert_eq!(node.id, id),
+            other => panic!("unexpected event {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_filter_by_node_and_edge_type() {
+        let filter: SubscribeFilter =
+            serde_json::from_str(r#"{"node_types": ["doc"], "edge_types": ["Calls"]}"#).unwrap();
+        let doc = Node::new(NodeType::Doc, "README", "");
+        let function = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "f", "");
+        let calls = Edge::new(EdgeType::Calls, Uuid::new_v4(), Uuid::new_v4());
+        let imports = Edge::new(EdgeType::Imports, Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(filter.matches(&ChangeEvent::NodeCreated { node: doc }));
+        assert!(!filter.matches(&ChangeEvent::NodeUpdated { node: function }));
+        assert!(filter.matches(&ChangeEvent::EdgeCreated { edge: calls }));
+        assert!(!filter.matches(&ChangeEvent::EdgeCreated { edge: imports }));
+    }
+}
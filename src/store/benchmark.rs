@@ -3,22 +3,36 @@
 //! This module provides comprehensive benchmarks for insert/query throughput,
 //! memory usage, and other performance metrics.
 
-use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use hdrhistogram::Histogram;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::time::sleep;
 use uuid::Uuid;
 
-use crate::error::{OnyxError, OnyxResult};
-use crate::model::{Node, Edge, Embedding, Version, VersionEntry};
+use crate::config::LimitsConfig;
+use crate::error::{OnyxError, OnyxResult, StorageError};
+use crate::ingest::{detect_relationships, ingest_code_unit, CodeUnit};
+use crate::model::{Edge, Node};
+use crate::model::embedding::BagOfWordsEmbedder;
 use crate::model::node::{NodeType, CodeEntityKind, Language, Visibility};
 use crate::model::edge::EdgeType;
-use crate::model::version::Diff;
-use crate::store::{GraphStore, VectorStore, HistoryStore};
-use crate::store::persistent::{open_db, RocksGraphStore, RocksVectorStore, RocksHistoryStore};
+use crate::store::{GraphStore, VectorStore};
+use crate::store::graph::DEFAULT_TRAVERSAL_CONCURRENCY;
+use crate::store::transaction::TransactionManager;
+use crate::store::persistent::{
+    estimate_live_data_size, open_db, RocksGraphStore, RocksVectorStore, VectorStorage,
+};
 
 /// Benchmark configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BenchmarkConfig {
     /// Number of operations to run
     pub operation_count: usize,
@@ -30,6 +44,12 @@ pub struct BenchmarkConfig {
     pub measure_memory: bool,
     /// Whether to run detailed latency measurements
     pub detailed_latency: bool,
+    /// Operation mix for the mixed workload benchmark
+    #[serde(default)]
+    pub workload: WorkloadProfile,
+    /// Seed for sampling the mixed workload's operations
+    #[serde(default)]
+    pub seed: u64,
 }
 
 impl Default for BenchmarkConfig {
@@ -40,12 +60,107 @@ impl Default for BenchmarkConfig {
             warmup_count: 1000,
             measure_memory: true,
             detailed_latency: true,
+            workload: WorkloadProfile::default(),
+            seed: 42,
         }
     }
 }
 
+// ---------------------------------------------------------------------------
+// Workload profiles
+// ---------------------------------------------------------------------------
+
+/// An operation issued by the mixed workload benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkloadOp {
+    NodeInsert,
+    VectorInsert,
+    NodeQuery,
+    VectorSearch,
+}
+
+impl WorkloadOp {
+    const ALL: [WorkloadOp; 4] = [
+        WorkloadOp::NodeInsert,
+        WorkloadOp::VectorInsert,
+        WorkloadOp::NodeQuery,
+        WorkloadOp::VectorSearch,
+    ];
+
+    pub fn is_read(self) -> bool {
+        matches!(self, WorkloadOp::NodeQuery | WorkloadOp::VectorSearch)
+    }
+}
+
+/// Relative weights of each [`WorkloadOp`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OperationWeights {
+    pub node_insert: f64,
+    pub vector_insert: f64,
+    pub node_query: f64,
+    pub vector_search: f64,
+}
+
+impl OperationWeights {
+    /// Split `read_pct` percent evenly over the reads and the rest over the writes.
+    fn from_read_pct(read_pct: u8) -> Self {
+        let reads = f64::from(read_pct.min(100)) / 2.0;
+        let writes = (100.0 - reads * 2.0) / 2.0;
+        Self {
+            node_insert: writes,
+            vector_insert: writes,
+            node_query: reads,
+            vector_search: reads,
+        }
+    }
+
+    fn as_array(&self) -> [f64; 4] {
+        [
+            self.node_insert,
+            self.vector_insert,
+            self.node_query,
+            self.vector_search,
+        ]
+    }
+}
+
+/// The mix of reads and writes issued by the mixed workload benchmark.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum WorkloadProfile {
+    /// Equal shares of every operation.
+    #[default]
+    Balanced,
+    /// `read_pct` percent reads, split between node queries and vector search.
+    ReadHeavy { read_pct: u8 },
+    /// `write_pct` percent writes, split between node and vector inserts.
+    WriteHeavy { write_pct: u8 },
+    Custom(OperationWeights),
+}
+
+impl WorkloadProfile {
+    pub fn weights(&self) -> OperationWeights {
+        match *self {
+            WorkloadProfile::Balanced => OperationWeights::from_read_pct(50),
+            WorkloadProfile::ReadHeavy { read_pct } => OperationWeights::from_read_pct(read_pct),
+            WorkloadProfile::WriteHeavy { write_pct } => {
+                OperationWeights::from_read_pct(100 - write_pct.min(100))
+            }
+            WorkloadProfile::Custom(weights) => weights,
+        }
+    }
+
+    /// An endless, reproducible stream of operations drawn from this profile.
+    pub fn operations(&self, seed: u64) -> OnyxResult<impl Iterator<Item = WorkloadOp>> {
+        let distribution = WeightedIndex::new(self.weights().as_array()).map_err(|e| {
+            OnyxError::ConfigError(format!("Invalid workload weights {:?}: {}", self, e))
+        })?;
+        let mut rng = StdRng::seed_from_u64(seed);
+        Ok(std::iter::repeat_with(move || WorkloadOp::ALL[distribution.sample(&mut rng)]))
+    }
+}
+
 /// Benchmark results
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BenchmarkResults {
     /// Total operations performed
     pub total_operations: usize,
@@ -61,16 +176,221 @@ pub struct BenchmarkResults {
     pub p95_latency: Duration,
     /// P99 latency
     pub p99_latency: Duration,
+    /// P90 latency
+    #[serde(default)]
+    pub p90_latency: Duration,
+    /// P99.9 latency
+    #[serde(default)]
+    pub p999_latency: Duration,
+    /// Slowest operation
+    #[serde(default)]
+    pub max_latency: Duration,
+    /// Per-operation latencies in nanoseconds, for custom quantiles
+    #[serde(skip)]
+    pub latency_histogram: Option<Histogram<u64>>,
     /// Memory usage in bytes (if measured)
     pub memory_usage_bytes: Option<u64>,
     /// Additional metrics
     pub additional_metrics: HashMap<String, f64>,
 }
 
+impl BenchmarkResults {
+    /// Fill in the latency percentiles from `histogram` and keep it.
+    pub fn with_latency_histogram(mut self, histogram: Histogram<u64>) -> Self {
+        let percentiles = LatencyPercentiles::from_histogram(&histogram);
+        self.p50_latency = percentiles.p50;
+        self.p90_latency = percentiles.p90;
+        self.p95_latency = percentiles.p95;
+        self.p99_latency = percentiles.p99;
+        self.p999_latency = percentiles.p999;
+        self.max_latency = percentiles.max;
+        self.latency_histogram = Some(histogram);
+        self
+    }
+
+    /// Serialize these results as pretty-printed JSON.
+    pub fn to_json(&self) -> OnyxResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Result export
+// ---------------------------------------------------------------------------
+
+/// File format for exported benchmark results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = OnyxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            other => Err(OnyxError::ConfigError(format!(
+                "Unknown output format '{}', expected json or csv",
+                other
+            ))),
+        }
+    }
+}
+
+/// A complete benchmark run: when it ran, how it was configured, and what
+/// each benchmark measured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub timestamp: DateTime<Utc>,
+    pub config: BenchmarkConfig,
+    pub results: BTreeMap<String, BenchmarkResults>,
+}
+
+impl BenchmarkReport {
+    pub fn new(config: BenchmarkConfig, results: &HashMap<String, BenchmarkResults>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            config,
+            results: results.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+        }
+    }
+
+    /// One row per benchmark. Latencies are in microseconds and additional
+    /// metrics are packed into a single `name=value;...` column.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "timestamp,operation_count,concurrency,benchmark,total_operations,\
+             total_duration_us,ops_per_second,avg_latency_us,p50_latency_us,\
+             p95_latency_us,p99_latency_us,memory_usage_bytes,additional_metrics\n",
+        );
+        for (name, result) in &self.results {
+            let mut metrics: Vec<_> = result.additional_metrics.iter().collect();
+            metrics.sort_by(|a, b| a.0.cmp(b.0));
+            let metrics: Vec<String> = metrics.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                self.timestamp.to_rfc3339(),
+                self.config.operation_count,
+                self.config.concurrency,
+                name,
+                result.total_operations,
+                result.total_duration.as_micros(),
+                result.ops_per_second,
+                result.avg_latency.as_micros(),
+                result.p50_latency.as_micros(),
+                result.p95_latency.as_micros(),
+                result.p99_latency.as_micros(),
+                result.memory_usage_bytes.map(|b| b.to_string()).unwrap_or_default(),
+                metrics.join(";"),
+            );
+        }
+        csv
+    }
+
+    /// Write the report to `path` in the given format.
+    pub fn write_to(&self, path: &Path, format: OutputFormat) -> OnyxResult<()> {
+        let content = match format {
+            OutputFormat::Json => serde_json::to_string_pretty(self)?,
+            OutputFormat::Csv => self.to_csv(),
+        };
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Latency recording
+// ---------------------------------------------------------------------------
+
+/// Significant decimal digits kept by latency histograms.
+pub const LATENCY_SIGNIFICANT_DIGITS: u8 = 3;
+
+/// An empty, auto-resizing histogram of latencies in nanoseconds.
+pub fn latency_histogram() -> Histogram<u64> {
+    Histogram::new(LATENCY_SIGNIFICANT_DIGITS).expect("valid histogram precision")
+}
+
+fn record_latency(histogram: &mut Histogram<u64>, latency: Duration) {
+    histogram.saturating_record(latency.as_nanos() as u64);
+}
+
+fn merge_latencies(total: &mut Histogram<u64>, part: &Histogram<u64>) -> OnyxResult<()> {
+    total
+        .add(part)
+        .map_err(|e| OnyxError::Internal(format!("Failed to merge latencies: {:?}", e)))
+}
+
+/// Percentiles of a set of per-operation latencies.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub p999: Duration,
+    pub max: Duration,
+}
+
+impl LatencyPercentiles {
+    /// Percentiles of a nanosecond histogram; all zero if it is empty.
+    pub fn from_histogram(histogram: &Histogram<u64>) -> Self {
+        if histogram.is_empty() {
+            return Self::default();
+        }
+        let at = |quantile: f64| Duration::from_nanos(histogram.value_at_quantile(quantile));
+        Self {
+            p50: at(0.50),
+            p90: at(0.90),
+            p95: at(0.95),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: Duration::from_nanos(histogram.max()),
+        }
+    }
+
+    /// Nearest-rank percentiles of `latencies`; all zero if it is empty.
+    pub fn from_latencies(mut latencies: Vec<Duration>) -> Self {
+        if latencies.is_empty() {
+            return Self::default();
+        }
+        latencies.sort_unstable();
+
+        let at = |quantile: f64| {
+            let rank = (quantile * latencies.len() as f64).ceil() as usize;
+            latencies[rank.clamp(1, latencies.len()) - 1]
+        };
+        Self {
+            p50: at(0.50),
+            p90: at(0.90),
+            p95: at(0.95),
+            p99: at(0.99),
+            p999: at(0.999),
+            max: latencies[latencies.len() - 1],
+        }
+    }
+}
+
+/// A random embedding for a fresh node id.
+struct TestEmbedding {
+    node_id: Uuid,
+    values: Vec<f32>,
+}
+
+fn worker_failed(e: tokio::task::JoinError) -> OnyxError {
+    OnyxError::Internal(format!("Benchmark worker failed: {}", e))
+}
+
 /// Performance benchmark runner
 pub struct BenchmarkRunner {
     config: BenchmarkConfig,
     db_path: std::path::PathBuf,
+    output: Option<(PathBuf, OutputFormat)>,
 }
 
 impl BenchmarkRunner {
@@ -79,9 +399,16 @@ impl BenchmarkRunner {
         Self {
             config,
             db_path: db_path.as_ref().to_path_buf(),
+            output: None,
         }
     }
 
+    /// Also write the results of [`run_all_benchmarks`](Self::run_all_benchmarks) to `path`
+    pub fn with_output(mut self, path: impl Into<PathBuf>, format: OutputFormat) -> Self {
+        self.output = Some((path.into(), format));
+        self
+    }
+
     /// Run all benchmarks
     pub async fn run_all_benchmarks(&self) -> OnyxResult<HashMap<String, BenchmarkResults>> {
         let mut results = HashMap::new();
@@ -104,7 +431,15 @@ impl BenchmarkRunner {
         // Query benchmarks
         results.insert("node_query".to_string(), self.benchmark_node_query().await?);
         results.insert("vector_search".to_string(), self.benchmark_vector_search().await?);
+        results.insert("vector_quantization".to_string(), self.benchmark_vector_quantization().await?);
         results.insert("graph_traversal".to_string(), self.benchmark_graph_traversal().await?);
+        results.insert("deep_traversal".to_string(), self.benchmark_deep_traversal().await?);
+        results.insert("node_cache".to_string(), self.benchmark_node_cache().await?);
+        results.insert("neighbor_fanout".to_string(), self.benchmark_neighbor_fanout().await?);
+        results.insert("parallel_traversal".to_string(), self.benchmark_parallel_traversal().await?);
+
+        // Ingest benchmarks
+        results.insert("relationship_detection".to_string(), self.benchmark_relationship_detection().await?);
 
         // Mixed workload benchmarks
         results.insert("mixed_workload".to_string(), self.benchmark_mixed_workload().await?);
@@ -117,6 +452,11 @@ impl BenchmarkRunner {
         // Print summary
         self.print_benchmark_summary(&results);
 
+        if let Some((path, format)) = &self.output {
+            BenchmarkReport::new(self.config.clone(), &results).write_to(path, *format)?;
+            println!("Results written to {}", path.display());
+        }
+
         Ok(results)
     }
 
@@ -125,7 +465,7 @@ impl BenchmarkRunner {
         println!("Benchmarking node insertion...");
         
         let db = open_db(&self.db_path)?;
-        let store = RocksGraphStore::new(db)?;
+        let store = RocksGraphStore::new(db);
 
         // Warmup
         for _ in 0..self.config.warmup_count {
@@ -135,7 +475,7 @@ impl BenchmarkRunner {
 
         // Measure insertion performance
         let start_time = Instant::now();
-        let mut latencies = Vec::new();
+        let mut latencies = latency_histogram();
 
         for _ in 0..self.config.operation_count {
             let node = self.create_test_node();
@@ -145,41 +485,23 @@ impl BenchmarkRunner {
             
             let latency = op_start.elapsed();
             if self.config.detailed_latency {
-                latencies.push(latency);
+                record_latency(&mut latencies, latency);
             }
         }
 
         let total_duration = start_time.elapsed();
         let ops_per_second = self.config.operation_count as f64 / total_duration.as_secs_f64();
 
-        let results = if self.config.detailed_latency && !latencies.is_empty() {
-            let mut sorted_latencies = latencies.clone();
-            sorted_latencies.sort();
-            
-            BenchmarkResults {
-                total_operations: self.config.operation_count,
-                total_duration,
-                ops_per_second,
-                avg_latency: total_duration / self.config.operation_count as u32,
-                p50_latency: sorted_latencies[sorted_latencies.len() / 2],
-                p95_latency: sorted_latencies[(sorted_latencies.len() as f64 * 0.95) as usize],
-                p99_latency: sorted_latencies[(sorted_latencies.len() as f64 * 0.99) as usize],
-                memory_usage_bytes: None,
-                additional_metrics: HashMap::new(),
-            }
-        } else {
-            BenchmarkResults {
-                total_operations: self.config.operation_count,
-                total_duration,
-                ops_per_second,
-                avg_latency: total_duration / self.config.operation_count as u32,
-                p50_latency: Duration::ZERO,
-                p95_latency: Duration::ZERO,
-                p99_latency: Duration::ZERO,
-                memory_usage_bytes: None,
-                additional_metrics: HashMap::new(),
-            }
-        };
+        let results = BenchmarkResults {
+            total_operations: self.config.operation_count,
+            total_duration,
+            ops_per_second,
+            avg_latency: total_duration / self.config.operation_count as u32,
+            memory_usage_bytes: None,
+            additional_metrics: HashMap::new(),
+            ..Default::default()
+        }
+        .with_latency_histogram(latencies);
 
         println!("Node insertion: {:.1} ops/sec", results.ops_per_second);
         Ok(results)
@@ -190,7 +512,7 @@ impl BenchmarkRunner {
         println!("Benchmarking concurrent node insertion...");
         
         let db = open_db(&self.db_path)?;
-        let store = Arc::new(RocksGraphStore::new(db)?);
+        let store = Arc::new(RocksGraphStore::new(db));
 
         let start_time = Instant::now();
         let mut handles = Vec::new();
@@ -200,20 +522,28 @@ impl BenchmarkRunner {
         for _ in 0..self.config.concurrency {
             let store_clone = store.clone();
             let ops = operations_per_thread;
+            let detailed = self.config.detailed_latency;
             
             let handle = tokio::spawn(async move {
+                let mut latencies = latency_histogram();
                 for _ in 0..ops {
                     let node = Self::create_test_node_static();
+                    let op_start = Instant::now();
                     store_clone.insert_node(node).await.unwrap();
+                    if detailed {
+                        record_latency(&mut latencies, op_start.elapsed());
+                    }
                 }
+                latencies
             });
             
             handles.push(handle);
         }
 
-        // Wait for all threads to complete
+        // Wait for all threads to complete, merging their latencies
+        let mut latencies = latency_histogram();
         for handle in handles {
-            handle.await?;
+            merge_latencies(&mut latencies, &handle.await.map_err(worker_failed)?)?;
         }
 
         let total_duration = start_time.elapsed();
@@ -224,12 +554,11 @@ impl BenchmarkRunner {
             total_duration,
             ops_per_second,
             avg_latency: total_duration / self.config.operation_count as u32,
-            p50_latency: Duration::ZERO,
-            p95_latency: Duration::ZERO,
-            p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
-        };
+            ..Default::default()
+        }
+        .with_latency_histogram(latencies);
 
         println!("Concurrent node insertion: {:.1} ops/sec", results.ops_per_second);
         Ok(results)
@@ -240,7 +569,7 @@ impl BenchmarkRunner {
         println!("Benchmarking edge insertion...");
         
         let db = open_db(&self.db_path)?;
-        let store = RocksGraphStore::new(db)?;
+        let store = RocksGraphStore::new(db);
 
         // Create some nodes first
         let mut node_ids = Vec::new();
@@ -280,6 +609,7 @@ impl BenchmarkRunner {
             p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
+            ..Default::default()
         };
 
         println!("Edge insertion: {:.1} ops/sec", results.ops_per_second);
@@ -291,7 +621,7 @@ impl BenchmarkRunner {
         println!("Benchmarking concurrent edge insertion...");
         
         let db = open_db(&self.db_path)?;
-        let store = Arc::new(RocksGraphStore::new(db)?);
+        let store = Arc::new(RocksGraphStore::new(db));
 
         // Create nodes for edges
         let mut node_ids = Vec::new();
@@ -312,21 +642,30 @@ impl BenchmarkRunner {
             let node_ids_clone = node_ids.clone();
             let ops = operations_per_thread;
             
+            let detailed = self.config.detailed_latency;
+            
             let handle = tokio::spawn(async move {
+                let mut latencies = latency_histogram();
                 for i in 0..ops {
                     let source_idx = (thread_id * ops + i) % node_ids_clone.len();
                     let target_idx = (source_idx + 1) % node_ids_clone.len();
                     let edge = Self::create_test_edge_static(node_ids_clone[source_idx], node_ids_clone[target_idx]);
+                    let op_start = Instant::now();
                     store_clone.insert_edge(edge).await.unwrap();
+                    if detailed {
+                        record_latency(&mut latencies, op_start.elapsed());
+                    }
                 }
+                latencies
             });
             
             handles.push(handle);
         }
 
-        // Wait for all threads to complete
+        // Wait for all threads to complete, merging their latencies
+        let mut latencies = latency_histogram();
         for handle in handles {
-            handle.await?;
+            merge_latencies(&mut latencies, &handle.await.map_err(worker_failed)?)?;
         }
 
         let total_duration = start_time.elapsed();
@@ -337,12 +676,11 @@ impl BenchmarkRunner {
             total_duration,
             ops_per_second,
             avg_latency: total_duration / self.config.operation_count as u32,
-            p50_latency: Duration::ZERO,
-            p95_latency: Duration::ZERO,
-            p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
-        };
+            ..Default::default()
+        }
+        .with_latency_histogram(latencies);
 
         println!("Concurrent edge insertion: {:.1} ops/sec", results.ops_per_second);
         Ok(results)
@@ -353,7 +691,7 @@ impl BenchmarkRunner {
         println!("Benchmarking vector insertion...");
         
         let db = open_db(&self.db_path)?;
-        let store = RocksVectorStore::new(db, 100)?;
+        let store = RocksVectorStore::new(db, 100);
 
         // Warmup
         for _ in 0..self.config.warmup_count {
@@ -382,6 +720,7 @@ impl BenchmarkRunner {
             p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
+            ..Default::default()
         };
 
         println!("Vector insertion: {:.1} ops/sec", results.ops_per_second);
@@ -393,7 +732,7 @@ impl BenchmarkRunner {
         println!("Benchmarking concurrent vector insertion...");
         
         let db = open_db(&self.db_path)?;
-        let store = Arc::new(RocksVectorStore::new(db, 100)?);
+        let store = Arc::new(RocksVectorStore::new(db, 100));
 
         let start_time = Instant::now();
         let mut handles = Vec::new();
@@ -403,20 +742,28 @@ impl BenchmarkRunner {
         for _ in 0..self.config.concurrency {
             let store_clone = store.clone();
             let ops = operations_per_thread;
+            let detailed = self.config.detailed_latency;
             
             let handle = tokio::spawn(async move {
+                let mut latencies = latency_histogram();
                 for _ in 0..ops {
                     let embedding = Self::create_test_embedding_static();
+                    let op_start = Instant::now();
                     store_clone.insert(embedding.node_id, embedding.values).await.unwrap();
+                    if detailed {
+                        record_latency(&mut latencies, op_start.elapsed());
+                    }
                 }
+                latencies
             });
             
             handles.push(handle);
         }
 
-        // Wait for all threads to complete
+        // Wait for all threads to complete, merging their latencies
+        let mut latencies = latency_histogram();
         for handle in handles {
-            handle.await?;
+            merge_latencies(&mut latencies, &handle.await.map_err(worker_failed)?)?;
         }
 
         let total_duration = start_time.elapsed();
@@ -427,12 +774,11 @@ impl BenchmarkRunner {
             total_duration,
             ops_per_second,
             avg_latency: total_duration / self.config.operation_count as u32,
-            p50_latency: Duration::ZERO,
-            p95_latency: Duration::ZERO,
-            p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
-        };
+            ..Default::default()
+        }
+        .with_latency_histogram(latencies);
 
         println!("Concurrent vector insertion: {:.1} ops/sec", results.ops_per_second);
         Ok(results)
@@ -443,7 +789,7 @@ impl BenchmarkRunner {
         println!("Benchmarking node queries...");
         
         let db = open_db(&self.db_path)?;
-        let store = RocksGraphStore::new(db)?;
+        let store = RocksGraphStore::new(db);
 
         // Insert test data
         let mut node_ids = Vec::new();
@@ -481,6 +827,7 @@ impl BenchmarkRunner {
             p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
+            ..Default::default()
         };
 
         println!("Node queries: {:.1} ops/sec", results.ops_per_second);
@@ -492,7 +839,7 @@ impl BenchmarkRunner {
         println!("Benchmarking vector search...");
         
         let db = open_db(&self.db_path)?;
-        let vector_store = RocksVectorStore::new(db.clone(), 100)?;
+        let vector_store = RocksVectorStore::new(db.clone(), 100);
 
         // Insert test embeddings
         let mut node_ids = Vec::new();
@@ -531,18 +878,108 @@ impl BenchmarkRunner {
             p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
+            ..Default::default()
         };
 
         println!("Vector search: {:.1} ops/sec", results.ops_per_second);
         Ok(results)
     }
 
+    /// Compare int8-quantized vector storage against full precision.
+    ///
+    /// Reports the on-disk size of both stores and recall@10 of quantized
+    /// search against the full-precision results, on random vectors.
+    async fn benchmark_vector_quantization(&self) -> OnyxResult<BenchmarkResults> {
+        println!("Benchmarking vector quantization...");
+
+        const DIMENSIONS: usize = 100;
+        const VECTOR_COUNT: usize = 2000;
+        const QUERY_COUNT: usize = 100;
+        const K: usize = 10;
+
+        let full = RocksVectorStore::with_storage(
+            open_db(self.db_path.join("quantization_full"))?,
+            DIMENSIONS,
+            VectorStorage::Full,
+        );
+        let quantized = RocksVectorStore::with_storage(
+            open_db(self.db_path.join("quantization_q8"))?,
+            DIMENSIONS,
+            VectorStorage::Quantized8,
+        );
+
+        let random_vector = || -> Vec<f32> {
+            (0..DIMENSIONS).map(|_| rand::random::<f32>() * 2.0 - 1.0).collect()
+        };
+
+        for _ in 0..VECTOR_COUNT {
+            let id = Uuid::new_v4();
+            let vector = random_vector();
+            full.insert(id, vector.clone()).await?;
+            quantized.insert(id, vector).await?;
+        }
+
+        let full_bytes = full.stored_bytes()?;
+        let quantized_bytes = quantized.stored_bytes()?;
+
+        // Measure quantized search, scoring recall against full precision
+        let mut hits = 0usize;
+        let mut search_duration = Duration::ZERO;
+
+        for _ in 0..QUERY_COUNT {
+            let query = random_vector();
+            let expected: std::collections::HashSet<Uuid> = full
+                .search(&query, K)
+                .await?
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+
+            let start_time = Instant::now();
+            let found = quantized.search(&query, K).await?;
+            search_duration += start_time.elapsed();
+
+            hits += found.iter().filter(|(id, _)| expected.contains(id)).count();
+        }
+
+        let recall = hits as f64 / (QUERY_COUNT * K) as f64;
+        let savings = 1.0 - quantized_bytes as f64 / full_bytes as f64;
+
+        let mut additional_metrics = HashMap::new();
+        additional_metrics.insert("full_bytes".to_string(), full_bytes as f64);
+        additional_metrics.insert("quantized_bytes".to_string(), quantized_bytes as f64);
+        additional_metrics.insert("storage_savings_pct".to_string(), savings * 100.0);
+        additional_metrics.insert("recall_at_10".to_string(), recall);
+
+        let results = BenchmarkResults {
+            total_operations: QUERY_COUNT,
+            total_duration: search_duration,
+            ops_per_second: QUERY_COUNT as f64 / search_duration.as_secs_f64(),
+            avg_latency: search_duration / QUERY_COUNT as u32,
+            p50_latency: Duration::ZERO,
+            p95_latency: Duration::ZERO,
+            p99_latency: Duration::ZERO,
+            memory_usage_bytes: Some(quantized_bytes),
+            additional_metrics,
+            ..Default::default()
+        };
+
+        println!(
+            "Vector quantization: {} -> {} bytes ({:.1}% smaller), recall@10 {:.3}",
+            full_bytes,
+            quantized_bytes,
+            savings * 100.0,
+            recall
+        );
+        Ok(results)
+    }
+
     /// Benchmark graph traversal performance
     async fn benchmark_graph_traversal(&self) -> OnyxResult<BenchmarkResults> {
         println!("Benchmarking graph traversal...");
         
         let db = open_db(&self.db_path)?;
-        let store = RocksGraphStore::new(db)?;
+        let store = RocksGraphStore::new(db);
 
         // Create a connected graph
         let mut node_ids = Vec::new();
@@ -585,54 +1022,385 @@ impl BenchmarkRunner {
             p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
             additional_metrics: HashMap::new(),
+            ..Default::default()
         };
 
         println!("Graph traversal: {:.1} ops/sec", results.ops_per_second);
         Ok(results)
     }
 
+    /// Benchmark multi-hop `traverse` from the root of a branching tree
+    ///
+    /// Reports ops/sec and nodes visited per traversal at depths 1, 3 and 5.
+    async fn benchmark_deep_traversal(&self) -> OnyxResult<BenchmarkResults> {
+        println!("Benchmarking deep traversal...");
+
+        const BRANCHING: usize = 3;
+        const TREE_DEPTH: usize = 6;
+        const DEPTHS: [usize; 3] = [1, 3, 5];
+
+        let db = open_db(self.db_path.join("deep_traversal"))?;
+        let store = RocksGraphStore::new(db);
+        let root_id = self.build_tree(&store, BRANCHING, TREE_DEPTH).await?;
+
+        let edge_types = [EdgeType::Calls];
+        let traversals = (self.config.operation_count / 100).max(10);
+        let mut additional_metrics = HashMap::new();
+        let mut total_duration = Duration::ZERO;
+
+        for depth in DEPTHS {
+            let mut visited = 0;
+            let start_time = Instant::now();
+            for _ in 0..traversals {
+                visited = store
+                    .traverse(&root_id, Some(&edge_types), depth)
+                    .await?
+                    .total_visited;
+            }
+            let elapsed = start_time.elapsed();
+            total_duration += elapsed;
+
+            let ops_per_second = traversals as f64 / elapsed.as_secs_f64();
+            additional_metrics.insert(format!("depth_{}_ops_per_sec", depth), ops_per_second);
+            additional_metrics.insert(format!("depth_{}_nodes_visited", depth), visited as f64);
+            println!(
+                "  Depth {}: {:.1} ops/sec, {} nodes visited",
+                depth, ops_per_second, visited
+            );
+        }
+
+        let total_operations = traversals * DEPTHS.len();
+        let results = BenchmarkResults {
+            total_operations,
+            total_duration,
+            ops_per_second: total_operations as f64 / total_duration.as_secs_f64(),
+            avg_latency: total_duration / total_operations as u32,
+            memory_usage_bytes: None,
+            additional_metrics,
+            ..Default::default()
+        };
+
+        println!("Deep traversal: {:.1} ops/sec", results.ops_per_second);
+        Ok(results)
+    }
+
+    /// Benchmark traversal throughput with and without the node cache
+    ///
+    /// Both passes read the same tree; the cached pass also reports its hit rate.
+    async fn benchmark_node_cache(&self) -> OnyxResult<BenchmarkResults> {
+        println!("Benchmarking node cache...");
+
+        const BRANCHING: usize = 3;
+        const TREE_DEPTH: usize = 5;
+        const CACHE_CAPACITY: usize = 1024;
+
+        let db = open_db(self.db_path.join("node_cache"))?;
+        let uncached = RocksGraphStore::new(db);
+        let root_id = self.build_tree(&uncached, BRANCHING, TREE_DEPTH).await?;
+        let cached = uncached.clone().with_node_cache(CACHE_CAPACITY);
+
+        let edge_types = [EdgeType::Calls];
+        let traversals = (self.config.operation_count / 100).max(10);
+        let mut additional_metrics = HashMap::new();
+        let mut total_duration = Duration::ZERO;
+
+        for (label, store) in [("uncached", &uncached), ("cached", &cached)] {
+            let start_time = Instant::now();
+            for _ in 0..traversals {
+                store.traverse(&root_id, Some(&edge_types), TREE_DEPTH).await?;
+            }
+            let elapsed = start_time.elapsed();
+            total_duration += elapsed;
+
+            let ops_per_second = traversals as f64 / elapsed.as_secs_f64();
+            additional_metrics.insert(format!("{}_ops_per_sec", label), ops_per_second);
+            println!("  {}: {:.1} ops/sec", label, ops_per_second);
+        }
+
+        let speedup = additional_metrics["cached_ops_per_sec"]
+            / additional_metrics["uncached_ops_per_sec"];
+        additional_metrics.insert("speedup".to_string(), speedup);
+        if let Some(stats) = cached.node_cache_stats() {
+            additional_metrics.insert("cache_hit_rate".to_string(), stats.hit_rate());
+        }
+
+        let total_operations = traversals * 2;
+        let results = BenchmarkResults {
+            total_operations,
+            total_duration,
+            ops_per_second: total_operations as f64 / total_duration.as_secs_f64(),
+            avg_latency: total_duration / total_operations as u32,
+            memory_usage_bytes: None,
+            additional_metrics,
+            ..Default::default()
+        };
+
+        println!("Node cache: {:.2}x traversal speedup", speedup);
+        Ok(results)
+    }
+
+    /// Benchmark `get_neighbors` on a node with 1000 neighbors
+    ///
+    /// Compares the batched lookup against one `get_edge`/`get_node` per edge.
+    async fn benchmark_neighbor_fanout(&self) -> OnyxResult<BenchmarkResults> {
+        println!("Benchmarking neighbor fan-out...");
+
+        const FANOUT: usize = 1000;
+
+        let db = open_db(self.db_path.join("neighbor_fanout"))?;
+        let store = RocksGraphStore::new(db);
+        let hub_id = self.build_tree(&store, FANOUT, 1).await?;
+        let edge_ids: Vec<Uuid> = store
+            .get_neighbors(&hub_id, None)
+            .await?
+            .into_iter()
+            .map(|(edge, _)| edge.id)
+            .collect();
+
+        let lookups = (self.config.operation_count / 1000).max(10);
+        let mut additional_metrics = HashMap::new();
+
+        let start_time = Instant::now();
+        for _ in 0..lookups {
+            let mut neighbors = Vec::with_capacity(FANOUT);
+            for edge_id in &edge_ids {
+                if let Some(edge) = store.get_edge(edge_id).await? {
+                    if let Some(node) = store.get_node(&edge.target_id).await? {
+                        neighbors.push((edge, node));
+                    }
+                }
+            }
+        }
+        let sequential = start_time.elapsed();
+
+        let start_time = Instant::now();
+        for _ in 0..lookups {
+            store.get_neighbors(&hub_id, None).await?;
+        }
+        let batched = start_time.elapsed();
+
+        let sequential_ops = lookups as f64 / sequential.as_secs_f64();
+        let batched_ops = lookups as f64 / batched.as_secs_f64();
+        additional_metrics.insert("sequential_ops_per_sec".to_string(), sequential_ops);
+        additional_metrics.insert("batched_ops_per_sec".to_string(), batched_ops);
+        additional_metrics.insert("speedup".to_string(), batched_ops / sequential_ops);
+
+        let results = BenchmarkResults {
+            total_operations: lookups,
+            total_duration: batched,
+            ops_per_second: batched_ops,
+            avg_latency: batched / lookups as u32,
+            memory_usage_bytes: None,
+            additional_metrics,
+            ..Default::default()
+        };
+
+        println!(
+            "Neighbor fan-out: {:.1} ops/sec batched, {:.1} ops/sec sequential",
+            batched_ops, sequential_ops
+        );
+        Ok(results)
+    }
+
+    /// Benchmark sequential vs parallel traversal of a two-level star
+    ///
+    /// The hub's spokes form one wide BFS level whose neighbor lookups the
+    /// parallel pass runs concurrently.
+    async fn benchmark_parallel_traversal(&self) -> OnyxResult<BenchmarkResults> {
+        println!("Benchmarking parallel traversal...");
+
+        const FANOUT: usize = 64;
+
+        let db = open_db(self.db_path.join("parallel_traversal"))?;
+        let sequential = RocksGraphStore::new(db).with_traversal_concurrency(1);
+        let hub_id = self.build_tree(&sequential, FANOUT, 2).await?;
+        let parallel = sequential
+            .clone()
+            .with_traversal_concurrency(DEFAULT_TRAVERSAL_CONCURRENCY);
+
+        let traversals = (self.config.operation_count / 1000).max(10);
+        let mut additional_metrics = HashMap::new();
+        let mut total_duration = Duration::ZERO;
+
+        for (label, store) in [("sequential", &sequential), ("parallel", &parallel)] {
+            let start_time = Instant::now();
+            for _ in 0..traversals {
+                store.traverse(&hub_id, None, 2).await?;
+            }
+            let elapsed = start_time.elapsed();
+            total_duration += elapsed;
+
+            let ops_per_second = traversals as f64 / elapsed.as_secs_f64();
+            additional_metrics.insert(format!("{}_ops_per_sec", label), ops_per_second);
+            println!("  {}: {:.1} ops/sec", label, ops_per_second);
+        }
+
+        let speedup = additional_metrics["parallel_ops_per_sec"]
+            / additional_metrics["sequential_ops_per_sec"];
+        additional_metrics.insert("speedup".to_string(), speedup);
+
+        let total_operations = traversals * 2;
+        let results = BenchmarkResults {
+            total_operations,
+            total_duration,
+            ops_per_second: total_operations as f64 / total_duration.as_secs_f64(),
+            avg_latency: total_duration / total_operations as u32,
+            memory_usage_bytes: None,
+            additional_metrics,
+            ..Default::default()
+        };
+
+        println!("Parallel traversal: {:.2}x speedup", speedup);
+        Ok(results)
+    }
+
+    /// Benchmark relationship detection over 500 ingested units
+    ///
+    /// The first pass inserts every detected edge; the second re-detects the
+    /// same edges and only reinforces them.
+    async fn benchmark_relationship_detection(&self) -> OnyxResult<BenchmarkResults> {
+        println!("Benchmarking relationship detection...");
+
+        const UNITS: usize = 500;
+        const PASSES: usize = 2;
+
+        let embedder = BagOfWordsEmbedder::from_corpus(&["pub fn calls next unit"], 32);
+        let mut stores = TransactionManager::new();
+        let mut node_ids = Vec::with_capacity(UNITS);
+        for i in 0..UNITS {
+            let unit = CodeUnit {
+                name: format!("unit_{}", i),
+                content: format!("pub fn unit_{}() {{ unit_{}(); }}", i, (i + 1) % UNITS),
+                kind: CodeEntityKind::Function,
+                language: Language::Rust,
+                file_path: format!("src/m{}.rs", i % 10),
+                line_range: None,
+                signature: None,
+                visibility: Visibility::Public,
+                module_path: vec![format!("m{}", i % 10), format!("unit_{}", i)],
+                commit_id: None,
+                branch: None,
+            };
+            node_ids.push(
+                ingest_code_unit(&mut stores, &unit, &embedder, &LimitsConfig::default())
+                    .await?
+                    .node_id,
+            );
+        }
+
+        let mut additional_metrics = HashMap::new();
+        let mut total_duration = Duration::ZERO;
+        for pass in 1..=PASSES {
+            let start_time = Instant::now();
+            let created = detect_relationships(&mut stores, &node_ids).await?;
+            let elapsed = start_time.elapsed();
+            total_duration += elapsed;
+
+            additional_metrics.insert(format!("pass_{}_ms", pass), elapsed.as_secs_f64() * 1000.0);
+            additional_metrics.insert(format!("pass_{}_edges_created", pass), created as f64);
+            println!("  Pass {}: {:?}, {} edges created", pass, elapsed, created);
+        }
+        additional_metrics.insert("units".to_string(), UNITS as f64);
+
+        let results = BenchmarkResults {
+            total_operations: PASSES,
+            total_duration,
+            ops_per_second: PASSES as f64 / total_duration.as_secs_f64(),
+            avg_latency: total_duration / PASSES as u32,
+            memory_usage_bytes: None,
+            additional_metrics,
+            ..Default::default()
+        };
+
+        println!("Relationship detection: {:?} per pass over {} units", results.avg_latency, UNITS);
+        Ok(results)
+    }
+
+    /// Build a tree of `Calls` edges level by level and return its root.
+    async fn build_tree(
+        &self,
+        store: &RocksGraphStore,
+        branching: usize,
+        depth: usize,
+    ) -> OnyxResult<Uuid> {
+        let root = self.create_test_node();
+        let root_id = root.id;
+        store.insert_node(root).await?;
+        let mut level = vec![root_id];
+        for _ in 0..depth {
+            let mut next_level = Vec::with_capacity(level.len() * branching);
+            for &parent in &level {
+                for _ in 0..branching {
+                    let child = self.create_test_node();
+                    let child_id = child.id;
+                    store.insert_node(child).await?;
+                    store.insert_edge(self.create_test_edge(parent, child_id)).await?;
+                    next_level.push(child_id);
+                }
+            }
+            level = next_level;
+        }
+        Ok(root_id)
+    }
+
     /// Benchmark mixed workload
     async fn benchmark_mixed_workload(&self) -> OnyxResult<BenchmarkResults> {
         println!("Benchmarking mixed workload...");
         
         let db = open_db(&self.db_path)?;
-        let graph_store = RocksGraphStore::new(db.clone())?;
-        let vector_store = RocksVectorStore::new(db, 100)?;
+        let graph_store = RocksGraphStore::new(db.clone());
+        let vector_store = RocksVectorStore::new(db, 100);
 
+        // Seed nodes so the query branch has something to find from the start
+        for _ in 0..100 {
+            graph_store.insert_node(self.create_test_node()).await?;
+        }
+        let node_ids = graph_store.get_all_node_ids().await?;
+        let mut node_queries = 0usize;
+        let mut successful_queries = 0usize;
+        let mut reads = 0usize;
+
+        let operations = self.config.workload.operations(self.config.seed)?;
         let start_time = Instant::now();
 
-        for i in 0..self.config.operation_count {
-            match i % 4 {
-                0 => {
-                    // Node insertion
+        for op in operations.take(self.config.operation_count) {
+            if op.is_read() {
+                reads += 1;
+            }
+            match op {
+                WorkloadOp::NodeInsert => {
                     let node = self.create_test_node();
                     graph_store.insert_node(node).await?;
                 }
-                1 => {
-                    // Vector insertion
+                WorkloadOp::VectorInsert => {
                     let embedding = self.create_test_embedding();
                     vector_store.insert(embedding.node_id, embedding.values).await?;
                 }
-                2 => {
-                    // Node query (if we have nodes)
-                    if let Ok(Some(_)) = graph_store.get_all_node_ids().await {
-                        if let Ok(Some(node_id)) = graph_store.get_all_node_ids().await?.first().cloned() {
-                            graph_store.get_node(&node_id).await?;
-                        }
+                WorkloadOp::NodeQuery => {
+                    // Round-robin over the seeded nodes
+                    let node_id = node_ids[node_queries % node_ids.len()];
+                    node_queries += 1;
+                    if graph_store.get_node(&node_id).await?.is_some() {
+                        successful_queries += 1;
                     }
                 }
-                3 => {
-                    // Vector search
+                WorkloadOp::VectorSearch => {
                     let query_vector = vec![0.5; 100];
                     vector_store.search(&query_vector, 5).await?;
                 }
-                _ => unreachable!(),
             }
         }
 
         let total_duration = start_time.elapsed();
         let ops_per_second = self.config.operation_count as f64 / total_duration.as_secs_f64();
 
+        let mut additional_metrics = HashMap::new();
+        additional_metrics.insert("successful_node_queries".to_string(), successful_queries as f64);
+        additional_metrics.insert(
+            "read_pct".to_string(),
+            reads as f64 * 100.0 / self.config.operation_count.max(1) as f64,
+        );
+
         let results = BenchmarkResults {
             total_operations: self.config.operation_count,
             total_duration,
@@ -642,7 +1410,8 @@ impl BenchmarkRunner {
             p95_latency: Duration::ZERO,
             p99_latency: Duration::ZERO,
             memory_usage_bytes: None,
-            additional_metrics: HashMap::new(),
+            additional_metrics,
+            ..Default::default()
         };
 
         println!("Mixed workload: {:.1} ops/sec", results.ops_per_second);
@@ -650,21 +1419,23 @@ impl BenchmarkRunner {
     }
 
     /// Benchmark memory usage
+    ///
+    /// Each node is inserted together with one embedding, so the per-node
+    /// figures cover both.
     async fn benchmark_memory_usage(&self) -> OnyxResult<BenchmarkResults> {
         println!("Benchmarking memory usage...");
         
-        let initial_memory = self.get_memory_usage();
+        const NODE_COUNT: usize = 10_000;
+        let initial_memory = sys_memory();
 
-        let db = open_db(&self.db_path)?;
-        let graph_store = RocksGraphStore::new(db.clone())?;
-        let vector_store = RocksVectorStore::new(db, 100)?;
-
-        // Insert data and measure memory at different points
-        let mut memory_measurements = Vec::new();
+        let db = open_db(self.db_path.join("memory_usage"))?;
+        let graph_store = RocksGraphStore::new(db.clone());
+        let vector_store = RocksVectorStore::new(db.clone(), 100);
+        let initial_storage = estimate_live_data_size(&db)?;
 
         for batch in 0..10 {
             // Insert 1000 nodes
-            for _ in 0..1000 {
+            for _ in 0..NODE_COUNT / 10 {
                 let node = self.create_test_node();
                 graph_store.insert_node(node).await?;
                 
@@ -672,32 +1443,51 @@ impl BenchmarkRunner {
                 vector_store.insert(embedding.node_id, embedding.values).await?;
             }
 
-            let current_memory = self.get_memory_usage();
-            memory_measurements.push(current_memory);
-            
-            println!("  Batch {}: {} MB", batch + 1, current_memory / 1024 / 1024);
+            if let Some(current_memory) = sys_memory() {
+                println!("  Batch {}: {} MB resident", batch + 1, current_memory / 1024 / 1024);
+            }
         }
 
-        let final_memory = self.get_memory_usage();
-        let memory_increase = final_memory.saturating_sub(initial_memory);
+        // RocksDB only updates its estimate once memtables are flushed
+        db.flush().map_err(|e| StorageError::rocks("flush", e))?;
+        let storage_increase = estimate_live_data_size(&db)?.saturating_sub(initial_storage);
+
+        let memory_increase = match (initial_memory, sys_memory()) {
+            (Some(initial), Some(last)) => Some(last.saturating_sub(initial)),
+            _ => None,
+        };
 
         let mut additional_metrics = HashMap::new();
-        additional_metrics.insert("memory_per_node".to_string(), memory_increase as f64 / 10000.0);
-        additional_metrics.insert("memory_per_embedding".to_string(), memory_increase as f64 / 10000.0);
+        additional_metrics.insert("storage_bytes".to_string(), storage_increase as f64);
+        additional_metrics.insert(
+            "storage_bytes_per_node".to_string(),
+            storage_increase as f64 / NODE_COUNT as f64,
+        );
+        if let Some(increase) = memory_increase {
+            additional_metrics.insert(
+                "rss_bytes_per_node".to_string(),
+                increase as f64 / NODE_COUNT as f64,
+            );
+        }
 
         let results = BenchmarkResults {
-            total_operations: 20000, // 10000 nodes + 10000 embeddings
+            total_operations: NODE_COUNT * 2, // nodes + embeddings
             total_duration: Duration::from_secs(1), // Not timing this benchmark
             ops_per_second: 0.0,
             avg_latency: Duration::ZERO,
             p50_latency: Duration::ZERO,
             p95_latency: Duration::ZERO,
             p99_latency: Duration::ZERO,
-            memory_usage_bytes: Some(memory_increase),
+            memory_usage_bytes: memory_increase,
             additional_metrics,
+            ..Default::default()
         };
 
-        println!("Memory usage: {} MB increase", memory_increase / 1024 / 1024);
+        match memory_increase {
+            Some(increase) => println!("Memory usage: {} MB increase", increase / 1024 / 1024),
+            None => println!("Memory usage: not measurable on this platform"),
+        }
+        println!("Storage usage: {} bytes live data", storage_increase);
         Ok(results)
     }
 
@@ -731,75 +1521,262 @@ impl BenchmarkRunner {
 
     // Helper methods
     fn create_test_node(&self) -> Node {
-        Node {
-            id: Uuid::new_v4(),
-            name: "benchmark_node".to_string(),
-            node_type: NodeType::CodeEntity(CodeEntityKind::Function),
-            content: "pub fn benchmark() { }".to_string(),
-            embedding: None,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            provenance: Default::default(),
-            extension: Default::default(),
-        }
+        Self::create_test_node_static()
     }
 
     fn create_test_edge(&self, source_id: Uuid, target_id: Uuid) -> Edge {
-        Edge {
-            id: Uuid::new_v4(),
-            source_id,
-            target_id,
-            edge_type: EdgeType::Calls,
-            confidence: 1.0,
-            metadata: Default::default(),
-            temporal_context: None,
-        }
+        Self::create_test_edge_static(source_id, target_id)
+    }
+
+    fn create_test_embedding(&self) -> TestEmbedding {
+        Self::create_test_embedding_static()
+    }
+
+    fn create_test_node_static() -> Node {
+        Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "benchmark_node",
+            "pub fn benchmark() { }",
+        )
     }
 
-    fn create_test_embedding(&self) -> Embedding {
-        Embedding {
+    fn create_test_edge_static(source_id: Uuid, target_id: Uuid) -> Edge {
+        Edge::new(EdgeType::Calls, source_id, target_id)
+    }
+
+    fn create_test_embedding_static() -> TestEmbedding {
+        TestEmbedding {
             node_id: Uuid::new_v4(),
             values: vec![0.1; 100],
         }
     }
+}
 
-    fn create_test_node_static() -> Node {
-        Node {
-            id: Uuid::new_v4(),
-            name: "benchmark_node".to_string(),
-            node_type: NodeType::CodeEntity(CodeEntityKind::Function),
-            content: "pub fn benchmark() { }".to_string(),
-            embedding: None,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            provenance: Default::default(),
-            extension: Default::default(),
+// ---------------------------------------------------------------------------
+// System memory
+// ---------------------------------------------------------------------------
+
+/// Resident set size of the current process in bytes, or `None` if it
+/// can't be measured on this platform.
+pub fn sys_memory() -> Option<u64> {
+    platform_rss()
+}
+
+#[cfg(target_os = "linux")]
+fn platform_rss() -> Option<u64> {
+    // Fields are in pages: size, resident, shared, ...
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    u64::try_from(page_size).ok().map(|size| resident_pages * size)
+}
+
+#[cfg(target_os = "macos")]
+#[allow(deprecated)] // libc points at the mach2 crate for these
+fn platform_rss() -> Option<u64> {
+    let mut info: libc::mach_task_basic_info_data_t = unsafe { std::mem::zeroed() };
+    let mut count = libc::MACH_TASK_BASIC_INFO_COUNT;
+    let result = unsafe {
+        libc::task_info(
+            libc::mach_task_self(),
+            libc::MACH_TASK_BASIC_INFO,
+            &mut info as *mut _ as libc::task_info_t,
+            &mut count,
+        )
+    };
+    (result == libc::KERN_SUCCESS).then_some(info.resident_size)
+}
+
+#[cfg(windows)]
+fn platform_rss() -> Option<u64> {
+    use windows_sys::Win32::System::ProcessStatus::{
+        GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    let ok = unsafe { GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) };
+    (ok != 0).then_some(counters.WorkingSetSize as u64)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn platform_rss() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_config() -> BenchmarkConfig {
+        BenchmarkConfig {
+            operation_count: 40,
+            concurrency: 2,
+            warmup_count: 0,
+            measure_memory: false,
+            detailed_latency: true,
+            ..Default::default()
         }
     }
 
-    fn create_test_edge_static(source_id: Uuid, target_id: Uuid) -> Edge {
-        Edge {
-            id: Uuid::new_v4(),
-            source_id,
-            target_id,
-            edge_type: EdgeType::Calls,
-            confidence: 1.0,
-            metadata: Default::default(),
-            temporal_context: None,
+    #[tokio::test]
+    async fn test_deep_traversal_visits_more_nodes_deeper() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = BenchmarkRunner::new(small_config(), dir.path());
+
+        let results = runner.benchmark_deep_traversal().await.unwrap();
+
+        let metrics = &results.additional_metrics;
+        assert_eq!(metrics["depth_1_nodes_visited"], 4.0);
+        assert!(metrics["depth_5_nodes_visited"] > metrics["depth_1_nodes_visited"]);
+        assert!(metrics["depth_5_ops_per_sec"] > 0.0);
+    }
+
+    #[test]
+    fn test_read_heavy_profile_issues_mostly_reads() {
+        let profile = WorkloadProfile::ReadHeavy { read_pct: 90 };
+        let reads = profile
+            .operations(7)
+            .unwrap()
+            .take(1000)
+            .filter(|op| op.is_read())
+            .count();
+        assert!((850..=950).contains(&reads), "{} reads out of 1000", reads);
+
+        // The same seed gives the same sequence
+        let first: Vec<_> = profile.operations(7).unwrap().take(50).collect();
+        let again: Vec<_> = profile.operations(7).unwrap().take(50).collect();
+        assert_eq!(first, again);
+    }
+
+    #[test]
+    fn test_custom_profile_rejects_zero_weights() {
+        let zero = OperationWeights {
+            node_insert: 0.0,
+            vector_insert: 0.0,
+            node_query: 0.0,
+            vector_search: 0.0,
+        };
+        assert!(WorkloadProfile::Custom(zero).operations(0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mixed_workload_queries_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let runner = BenchmarkRunner::new(small_config(), dir.path());
+
+        let results = runner.benchmark_mixed_workload().await.unwrap();
+
+        assert_eq!(results.total_operations, 40);
+        assert!(results.additional_metrics["successful_node_queries"] >= 1.0);
+    }
+
+    fn sample_results() -> BenchmarkResults {
+        let mut additional_metrics = HashMap::new();
+        additional_metrics.insert("recall_at_10".to_string(), 0.93);
+        additional_metrics.insert("storage_bytes".to_string(), 4096.0);
+        BenchmarkResults {
+            total_operations: 1000,
+            total_duration: Duration::from_millis(1500),
+            ops_per_second: 666.5,
+            avg_latency: Duration::from_micros(1500),
+            p50_latency: Duration::from_micros(1200),
+            p95_latency: Duration::from_micros(2900),
+            p99_latency: Duration::from_nanos(4_100_250),
+            memory_usage_bytes: Some(1 << 20),
+            additional_metrics,
+            ..Default::default()
         }
     }
 
-    fn create_test_embedding_static() -> Embedding {
-        Embedding {
-            node_id: Uuid::new_v4(),
-            values: vec![0.1; 100],
+    #[test]
+    fn test_results_json_round_trip() {
+        let results = sample_results();
+        let parsed: BenchmarkResults = serde_json::from_str(&results.to_json().unwrap()).unwrap();
+
+        assert_eq!(parsed.total_operations, results.total_operations);
+        assert_eq!(parsed.total_duration, results.total_duration);
+        assert_eq!(parsed.ops_per_second, results.ops_per_second);
+        assert_eq!(parsed.p99_latency, results.p99_latency);
+        assert_eq!(parsed.memory_usage_bytes, results.memory_usage_bytes);
+        assert_eq!(parsed.additional_metrics, results.additional_metrics);
+    }
+
+    #[test]
+    fn test_report_csv_has_a_row_per_benchmark() {
+        let mut results = HashMap::new();
+        results.insert("node_insert".to_string(), sample_results());
+        let csv = BenchmarkReport::new(small_config(), &results).to_csv();
+
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+        assert!(lines[1].contains(",node_insert,1000,1500000,"));
+        assert!(lines[1].ends_with("recall_at_10=0.93;storage_bytes=4096"));
+    }
+
+    #[test]
+    fn test_percentiles_of_known_distribution() {
+        let latencies: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+        let percentiles = LatencyPercentiles::from_latencies(latencies);
+
+        assert_eq!(percentiles.p50, Duration::from_millis(50));
+        assert_eq!(percentiles.p95, Duration::from_millis(95));
+        assert_eq!(percentiles.p99, Duration::from_millis(99));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "macos", windows))]
+    fn test_sys_memory_grows_with_allocation() {
+        let before = sys_memory().expect("rss is measurable on this platform");
+        assert!(before > 0);
+
+        // Touch every page so it is actually resident
+        let buffer = vec![1u8; 64 * 1024 * 1024];
+        let after = sys_memory().unwrap();
+        assert!(buffer.iter().step_by(4096).all(|&b| b == 1));
+
+        assert!(after > before, "rss {} -> {} after a 64MB allocation", before, after);
+    }
+
+    #[test]
+    fn test_histogram_percentiles_match_recorded_latencies() {
+        let mut histogram = latency_histogram();
+        for micros in 1..=10_000 {
+            record_latency(&mut histogram, Duration::from_micros(micros));
+        }
+        let results = sample_results().with_latency_histogram(histogram);
+
+        let histogram = results.latency_histogram.as_ref().unwrap();
+        assert_eq!(histogram.len(), 10_000);
+        assert_eq!(
+            results.p99_latency,
+            Duration::from_nanos(histogram.value_at_quantile(0.99))
+        );
+
+        // Within the histogram's precision of the exact values
+        let tolerance = 10f64.powi(-(LATENCY_SIGNIFICANT_DIGITS as i32));
+        for (reported, exact) in [
+            (results.p50_latency, 5_000.0),
+            (results.p99_latency, 9_900.0),
+            (results.p999_latency, 9_990.0),
+            (results.max_latency, 10_000.0),
+        ] {
+            let micros = reported.as_nanos() as f64 / 1_000.0;
+            assert!((micros - exact).abs() <= exact * tolerance, "{} vs {}", micros, exact);
         }
     }
 
-    fn get_memory_usage(&self) -> u64 {
-        // Simple memory usage estimation
-        // In a real implementation, you'd use platform-specific APIs
-        // For now, return a placeholder
-        50 * 1024 * 1024 // 50MB placeholder
+    #[test]
+    fn test_percentiles_of_empty_and_single_sample() {
+        assert_eq!(
+            LatencyPercentiles::from_latencies(Vec::new()),
+            LatencyPercentiles::default()
+        );
+
+        let one = LatencyPercentiles::from_latencies(vec![Duration::from_micros(7)]);
+        assert_eq!(one.p50, Duration::from_micros(7));
+        assert_eq!(one.p99, Duration::from_micros(7));
     }
-}
\ No newline at end of file
+}
@@ -3,20 +3,25 @@
 //! This module provides utilities to simulate various crash scenarios
 //! and validate recovery behavior.
 
-use crate::error::{OnyxError, OnyxResult};
-use crate::model::{Node, Edge, Embedding};
-use crate::store::persistent::{open_db, RocksGraphStore, RocksVectorStore, RocksHistoryStore};
-use rocksdb::{DB, Options, WriteBatch};
+use crate::error::{OnyxError, OnyxResult, StorageError};
+use crate::model::edge::EdgeType;
+use crate::model::node::{CodeEntityKind, NodeType};
+use crate::model::{Edge, Node};
+use crate::store::persistent::{
+    open_db_with, DurabilityMode, RocksGraphStore, RocksHistoryStore, RocksVectorStore,
+};
+use crate::store::{GraphStore, HistoryStore, VectorStore};
+use rocksdb::DB;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::time::sleep;
-use uuid::Uuid;
 
 /// Crash recovery simulator
 pub struct CrashSimulator {
     db_path: PathBuf,
     db: Option<Arc<DB>>,
+    durability: DurabilityMode,
 }
 
 impl CrashSimulator {
@@ -25,12 +30,19 @@ impl CrashSimulator {
         Self {
             db_path: db_path.as_ref().to_path_buf(),
             db: None,
+            durability: DurabilityMode::default(),
         }
     }
 
+    /// Open the database with the given durability mode
+    pub fn with_durability(mut self, mode: DurabilityMode) -> Self {
+        self.durability = mode;
+        self
+    }
+
     /// Initialize the database
     pub async fn initialize(&mut self) -> OnyxResult<()> {
-        let db = open_db(&self.db_path)?;
+        let db = open_db_with(&self.db_path, self.durability)?;
         self.db = Some(db);
         Ok(())
     }
@@ -39,50 +51,54 @@ impl CrashSimulator {
     pub async fn graceful_shutdown(&mut self) -> OnyxResult<()> {
         if let Some(db) = self.db.take() {
             // Flush all memtables to SST files
-            db.flush()?;
+            db.flush().map_err(|e| StorageError::rocks("flush", e))?;
             drop(db);
         }
         Ok(())
     }
 
-    /// Simulate an ungraceful shutdown (process kill)
+    /// Close the database without flushing memtables first.
+    ///
+    /// RocksDB still shuts down cleanly when the handle drops, so this is not
+    /// a real crash; kill the writing process for that.
     pub async fn ungraceful_shutdown(&mut self) {
-        // Simply drop the DB reference without flushing
         self.db = None;
     }
 
-    /// Simulate a power failure during write
+    /// Simulate a power failure during write: a batch of edges between two
+    /// new nodes is written, then the database goes down without flushing.
+    /// In tests and with the `test-util` feature the batch fails before it
+    /// commits.
     pub async fn power_failure_during_write(&mut self) -> OnyxResult<()> {
-        if let Some(db) = &self.db {
-            // Start a write operation
-            let mut batch = WriteBatch::default();
-            
-            let node = self.create_test_node();
-            let serialized = bincode::serialize(&node)
-                .map_err(|e| OnyxError::Internal(format!("Serialization failed: {}", e)))?;
-            
-            batch.put_cf(
-                db.cf_handle("nodes").unwrap(), 
-                node.id.as_bytes(), 
-                serialized
-            );
-            
-            // Simulate power failure by dropping before commit
-            drop(batch);
-            drop(db);
-            self.db = None;
+        if let Some(db) = self.db.clone() {
+            let graph_store = self.graph_store(db);
+            let source = self.create_test_node();
+            let target = self.create_test_node();
+            let edges = (0..10)
+                .map(|_| Edge::new(EdgeType::Calls, source.id, target.id))
+                .collect();
+            graph_store.insert_node(source).await?;
+            graph_store.insert_node(target).await?;
+
+            #[cfg(any(test, feature = "test-util"))]
+            graph_store.inject_fault_after_edge_record(true);
+            // With the fault injected, the in-flight batch is lost, as it
+            // would be on power loss.
+            let _ = graph_store.add_edges_atomic(edges).await;
+            drop(graph_store);
+            self.ungraceful_shutdown().await;
         }
         Ok(())
     }
 
     /// Recover from previous crash
     pub async fn recover(&mut self) -> OnyxResult<RecoveryReport> {
-        let db = open_db(&self.db_path)?;
+        let db = open_db_with(&self.db_path, self.durability)?;
         self.db = Some(db.clone());
 
-        let graph_store = RocksGraphStore::new(db.clone())?;
-        let vector_store = RocksVectorStore::new(db.clone(), 100)?;
-        let history_store = RocksHistoryStore::new(db)?;
+        let graph_store = self.graph_store(db.clone());
+        let vector_store = self.vector_store(db.clone());
+        let history_store = self.history_store(db);
 
         // Collect recovery statistics
         let node_count = graph_store.get_all_node_ids().await?.len();
@@ -102,8 +118,8 @@ impl CrashSimulator {
     /// Write test data before crash
     pub async fn write_test_data(&self, count: usize) -> OnyxResult<()> {
         if let Some(db) = &self.db {
-            let graph_store = RocksGraphStore::new(db.clone())?;
-            let vector_store = RocksVectorStore::new(db.clone(), 100)?;
+            let graph_store = self.graph_store(db.clone());
+            let vector_store = self.vector_store(db.clone());
 
             // Write nodes
             for i in 0..count {
@@ -111,14 +127,11 @@ impl CrashSimulator {
                 node.name = format!("test_node_{}", i);
                 node.content = format!("pub fn test_{}() {{}}", i);
                 
+                let node_id = node.id;
                 graph_store.insert_node(node).await?;
                 
                 // Write corresponding embedding
-                let embedding = Embedding {
-                    node_id: node.id,
-                    values: vec![i as f32 / 100.0; 100],
-                };
-                vector_store.insert(embedding.node_id, embedding.values).await?;
+                vector_store.insert(node_id, vec![i as f32 / 100.0; 100]).await?;
             }
         }
         Ok(())
@@ -127,7 +140,7 @@ impl CrashSimulator {
     /// Simulate write-ahead logging stress test
     pub async fn wal_stress_test(&self, operations: usize) -> OnyxResult<()> {
         if let Some(db) = &self.db {
-            let graph_store = RocksGraphStore::new(db.clone())?;
+            let graph_store = self.graph_store(db.clone());
 
             for i in 0..operations {
                 let mut node = self.create_test_node();
@@ -146,8 +159,8 @@ impl CrashSimulator {
     /// Validate database integrity
     pub async fn validate_integrity(&self) -> OnyxResult<IntegrityReport> {
         if let Some(db) = &self.db {
-            let graph_store = RocksGraphStore::new(db.clone())?;
-            let vector_store = RocksVectorStore::new(db.clone(), 100)?;
+            let graph_store = self.graph_store(db.clone());
+            let vector_store = self.vector_store(db.clone());
 
             // Check for orphaned edges (edges pointing to non-existent nodes)
             let all_edge_ids = graph_store.get_all_edge_ids().await?;
@@ -185,20 +198,83 @@ impl CrashSimulator {
         }
     }
 
+    /// Write an edge with a fault injected between the edge record and its
+    /// adjacency entries, then kill the database and check after recovery
+    /// that the edge was either fully applied or fully rolled back.
+    #[cfg(any(test, feature = "test-util"))]
+    pub async fn torn_edge_write(&mut self) -> OnyxResult<EdgeConsistency> {
+        let db = self
+            .db
+            .clone()
+            .ok_or_else(|| OnyxError::Internal("Database not initialized".to_string()))?;
+        let graph_store = self.graph_store(db);
+
+        let source = self.create_test_node();
+        let target = self.create_test_node();
+        let edge = Edge::new(EdgeType::Calls, source.id, target.id);
+        graph_store.insert_node(source).await?;
+        graph_store.insert_node(target).await?;
+
+        graph_store.inject_fault_after_edge_record(true);
+        let write_failed = graph_store.insert_edge(edge.clone()).await.is_err();
+        graph_store.inject_fault_after_edge_record(false);
+        drop(graph_store);
+        self.ungraceful_shutdown().await;
+
+        let db = open_db_with(&self.db_path, self.durability)?;
+        self.db = Some(db.clone());
+        let graph_store = self.graph_store(db);
+
+        let edge_stored = graph_store.get_edge(&edge.id).await?.is_some();
+        let (outbound_indexed, inbound_indexed) = graph_store.has_adjacency(&edge)?;
+        Ok(EdgeConsistency {
+            write_failed,
+            edge_stored,
+            outbound_indexed,
+            inbound_indexed,
+            orphan_adjacency: graph_store.find_orphan_adjacency()?.len(),
+        })
+    }
+
+    fn graph_store(&self, db: Arc<DB>) -> RocksGraphStore {
+        RocksGraphStore::new(db).with_durability(self.durability)
+    }
+
+    fn vector_store(&self, db: Arc<DB>) -> RocksVectorStore {
+        RocksVectorStore::new(db, 100).with_durability(self.durability)
+    }
+
+    fn history_store(&self, db: Arc<DB>) -> RocksHistoryStore {
+        RocksHistoryStore::new(db).with_durability(self.durability)
+    }
+
     fn create_test_node(&self) -> Node {
-        use crate::model::node::{NodeType, CodeEntityKind, Language, Visibility};
-        
-        Node {
-            id: Uuid::new_v4(),
-            name: "test_function".to_string(),
-            node_type: NodeType::CodeEntity(CodeEntityKind::Function),
-            content: "pub fn test() { println!(\"test\"); }".to_string(),
-            embedding: None,
-            created_at: chrono::Utc::now(),
-            updated_at: chrono::Utc::now(),
-            provenance: Default::default(),
-            extension: Default::default(),
-        }
+        Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "test_function",
+            "pub fn test() { println!(\"test\"); }",
+        )
+    }
+}
+
+/// State of an edge and its adjacency entries after a torn write.
+#[derive(Debug, Clone)]
+pub struct EdgeConsistency {
+    /// Whether the injected fault surfaced as an error to the writer.
+    pub write_failed: bool,
+    pub edge_stored: bool,
+    pub outbound_indexed: bool,
+    pub inbound_indexed: bool,
+    /// Adjacency entries anywhere in the store without an edge record.
+    pub orphan_adjacency: usize,
+}
+
+impl EdgeConsistency {
+    /// The edge and both index entries are all present or all absent.
+    pub fn is_consistent(&self) -> bool {
+        let all = self.edge_stored && self.outbound_indexed && self.inbound_indexed;
+        let none = !self.edge_stored && !self.outbound_indexed && !self.inbound_indexed;
+        (all || none) && self.orphan_adjacency == 0
     }
 }
 
@@ -236,6 +312,8 @@ pub enum CrashScenario {
     SystemCrashDuringBatch,
     /// Disk full scenario
     DiskFull,
+    /// Failure between an edge write and its adjacency index writes
+    TornEdgeWrite,
 }
 
 /// Comprehensive crash recovery test runner
@@ -270,6 +348,10 @@ impl CrashTestRunner {
         // Test 5: Large data crash recovery
         results.push(self.test_large_data_crash().await?);
 
+        // Test 6: Torn edge write, which needs the fault injection hook
+        #[cfg(any(test, feature = "test-util"))]
+        results.push(self.test_torn_edge_write().await?);
+
         Ok(results)
     }
 
@@ -288,6 +370,7 @@ impl CrashTestRunner {
             recovery_report: report,
             integrity_report: integrity,
             passed: report.recovery_successful && integrity.is_valid,
+            edge_consistency: None,
         })
     }
 
@@ -306,6 +389,7 @@ impl CrashTestRunner {
             recovery_report: report,
             integrity_report: integrity,
             passed: report.recovery_successful && integrity.is_valid,
+            edge_consistency: None,
         })
     }
 
@@ -313,7 +397,7 @@ impl CrashTestRunner {
     async fn test_power_failure_recovery(&mut self) -> OnyxResult<TestResult> {
         self.simulator.initialize().await?;
         self.simulator.write_test_data(50).await?; // Write some data first
-        self.simulator.power_failure_during_write().await;
+        self.simulator.power_failure_during_write().await?;
 
         // Recover and validate
         let report = self.simulator.recover().await?;
@@ -324,6 +408,7 @@ impl CrashTestRunner {
             recovery_report: report,
             integrity_report: integrity,
             passed: report.recovery_successful, // Power failure might lose in-flight data
+            edge_consistency: None,
         })
     }
 
@@ -342,6 +427,25 @@ impl CrashTestRunner {
             recovery_report: report,
             integrity_report: integrity,
             passed: report.recovery_successful && integrity.is_valid,
+            edge_consistency: None,
+        })
+    }
+
+    /// Test that a torn edge write leaves the adjacency indices consistent
+    #[cfg(any(test, feature = "test-util"))]
+    async fn test_torn_edge_write(&mut self) -> OnyxResult<TestResult> {
+        self.simulator.initialize().await?;
+        let consistency = self.simulator.torn_edge_write().await?;
+
+        let report = self.simulator.recover().await?;
+        let integrity = self.simulator.validate_integrity().await?;
+
+        Ok(TestResult {
+            scenario: CrashScenario::TornEdgeWrite,
+            recovery_report: report,
+            integrity_report: integrity,
+            passed: consistency.is_consistent(),
+            edge_consistency: Some(consistency),
         })
     }
 
@@ -352,7 +456,7 @@ impl CrashTestRunner {
         
         // Create large content nodes
         if let Some(db) = &self.simulator.db {
-            let graph_store = RocksGraphStore::new(db.clone())?;
+            let graph_store = self.simulator.graph_store(db.clone());
             
             for i in 0..5 {
                 let mut node = self.simulator.create_test_node();
@@ -373,6 +477,7 @@ impl CrashTestRunner {
             recovery_report: report,
             integrity_report: integrity,
             passed: report.recovery_successful && integrity.is_valid,
+            edge_consistency: None,
         })
     }
 }
@@ -384,6 +489,8 @@ pub struct TestResult {
     pub recovery_report: RecoveryReport,
     pub integrity_report: IntegrityReport,
     pub passed: bool,
+    /// Edge and index state, for the torn edge write scenario
+    pub edge_consistency: Option<EdgeConsistency>,
 }
 
 impl TestResult {
@@ -403,6 +510,92 @@ impl TestResult {
         println!("  Orphaned Edges: {}", self.integrity_report.orphaned_edges);
         println!("  Orphaned Embeddings: {}", self.integrity_report.orphaned_embeddings);
         println!("  Database Valid: {}", self.integrity_report.is_valid);
+        if let Some(edge) = &self.edge_consistency {
+            println!("Edge Consistency:");
+            println!("  Write Failed: {}", edge.write_failed);
+            println!("  Edge Stored: {}", edge.edge_stored);
+            println!("  Outbound Indexed: {}", edge.outbound_indexed);
+            println!("  Inbound Indexed: {}", edge.inbound_indexed);
+            println!("  Orphan Adjacency Entries: {}", edge.orphan_adjacency);
+            println!(
+                "  Outcome: {}",
+                if edge.is_consistent() { "all-or-nothing" } else { "TORN" }
+            );
+        }
         println!("=====================================");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_torn_edge_write_is_rolled_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut simulator = CrashSimulator::new(dir.path());
+        simulator.initialize().await.unwrap();
+
+        let consistency = simulator.torn_edge_write().await.unwrap();
+
+        assert!(consistency.write_failed);
+        assert!(!consistency.edge_stored);
+        assert!(consistency.is_consistent(), "{:?}", consistency);
+    }
+
+    /// Set in the child process of the kill test to the directory it writes
+    /// its database into.
+    const KILLED_WRITER_DIR: &str = "ONYX_KILLED_WRITER_DIR";
+
+    /// Write test data under `Paranoid`, signal that every write was
+    /// acknowledged, then hang until killed.
+    async fn write_until_killed(dir: &Path) {
+        let mut simulator =
+            CrashSimulator::new(dir.join("db")).with_durability(DurabilityMode::Paranoid);
+        simulator.initialize().await.unwrap();
+        simulator.write_test_data(50).await.unwrap();
+        std::fs::write(dir.join("ready"), b"").unwrap();
+        loop {
+            std::thread::park();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_paranoid_mode_loses_no_data_after_kill() {
+        if let Ok(dir) = std::env::var(KILLED_WRITER_DIR) {
+            write_until_killed(Path::new(&dir)).await;
+        }
+
+        // Rerun just this test in a child process that does the writing, so
+        // it can be killed outright: no destructors, flushes or clean close.
+        let dir = tempfile::tempdir().unwrap();
+        let (_, module) = module_path!().split_once("::").unwrap();
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                &format!("{module}::test_paranoid_mode_loses_no_data_after_kill"),
+            ])
+            .env(KILLED_WRITER_DIR, dir.path())
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+        while !dir.path().join("ready").exists() {
+            if let Some(status) = child.try_wait().unwrap() {
+                panic!("writer exited before it was killed: {status}");
+            }
+            assert!(Instant::now() < deadline, "writer never finished");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        let mut simulator =
+            CrashSimulator::new(dir.path().join("db")).with_durability(DurabilityMode::Paranoid);
+        let report = simulator.recover().await.unwrap();
+        assert_eq!(report.node_count, 50);
+        assert_eq!(report.embedding_count, 50);
+    }
+}
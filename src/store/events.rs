@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::model::edge::Edge;
+use crate::model::node::Node;
+use crate::model::version::VersionEntry;
+use crate::store::transaction::TransactionOp;
+
+// ---------------------------------------------------------------------------
+// Change events: what a committed operation did to the stores
+// ---------------------------------------------------------------------------
+
+/// Number of undelivered events a subscriber may fall behind by before it
+/// starts missing events.
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Where an [`AsyncTransactionManager`](crate::store::transaction::AsyncTransactionManager)
+/// publishes a [`StoreEvent`] after each successful commit.
+pub type EventSink = broadcast::Sender<StoreEvent>;
+
+/// Create an [`EventSink`] holding up to [`EVENT_CHANNEL_CAPACITY`] events
+/// per subscriber.
+pub fn event_sink() -> EventSink {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
+
+/// The changes applied by one `execute` or `execute_batch` call, in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreEvent {
+    pub changes: Vec<ChangeEvent>,
+}
+
+/// A committed change, as seen by subscribers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ChangeEvent {
+    NodeCreated { node: Node },
+    NodeUpdated { node: Node },
+    EdgeCreated { edge: Edge },
+    VersionRecorded { version: VersionEntry },
+}
+
+impl ChangeEvent {
+    /// The event describing `op`, if it is one subscribers are told about.
+    pub fn from_op(op: &TransactionOp) -> Option<Self> {
+        match op {
+            TransactionOp::InsertNode(node) => Some(Self::NodeCreated { node: node.clone() }),
+            TransactionOp::UpdateNode(node) => Some(Self::NodeUpdated { node: node.clone() }),
+            TransactionOp::InsertEdge(edge) => Some(Self::EdgeCreated { edge: edge.clone() }),
+            TransactionOp::RecordVersion(version) => Some(Self::VersionRecorded {
+                version: version.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Export and import of a whole store as JSON lines.
+//!
+//! Each line of a dump is one [`Record`]: nodes first, then edges,
+//! embeddings, versions (parents before children) and finally branches.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use uuid::Uuid;
+
+use crate::error::{OnyxError, OnyxResult};
+use crate::model::edge::Edge;
+use crate::model::node::Node;
+use crate::model::version::{Branch, VersionEntry, VersionId};
+use crate::store::graph::GraphStore;
+use crate::store::history::HistoryStore;
+use crate::store::transaction::{TransactionManager, TransactionOp};
+use crate::store::vector::VectorStore;
+
+// ---------------------------------------------------------------------------
+// Records
+// ---------------------------------------------------------------------------
+
+/// A single line of a JSON lines dump.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum Record {
+    Node(Node),
+    Edge(Edge),
+    Embedding { id: Uuid, vector: Vec<f32> },
+    Version(VersionEntry),
+    Branch(Branch),
+}
+
+// ---------------------------------------------------------------------------
+// Export
+// ---------------------------------------------------------------------------
+
+/// Write every node, edge, embedding, version and branch behind `stores`
+/// to `writer`, one JSON record per line. Returns the number of records.
+pub async fn dump(stores: &TransactionManager, writer: impl Write) -> OnyxResult<usize> {
+    dump_stores(stores.graph(), stores.vector(), stores.history(), writer).await
+}
+
+/// Write the contents of the given stores to `writer` as JSON lines.
+///
+/// Soft-deleted nodes are included so that their history round-trips.
+pub async fn dump_stores(
+    graph: &dyn GraphStore,
+    vector: &dyn VectorStore,
+    history: &dyn HistoryStore,
+    mut writer: impl Write,
+) -> OnyxResult<usize> {
+    let mut written = 0;
+
+    for id in graph.get_all_node_ids().await? {
+        if let Some(node) = graph.get_node_including_deleted(&id).await? {
+            write_record(&mut writer, &Record::Node(node))?;
+            written += 1;
+        }
+    }
+
+    for id in graph.get_all_edge_ids().await? {
+        if let Some(edge) = graph.get_edge(&id).await? {
+            write_record(&mut writer, &Record::Edge(edge))?;
+            written += 1;
+        }
+    }
+
+    for id in vector.get_all_embedding_ids().await? {
+        if let Some(vector) = vector.get(&id).await? {
+            write_record(&mut writer, &Record::Embedding { id, vector })?;
+            written += 1;
+        }
+    }
+
+    let mut versions = Vec::new();
+    for id in history.get_all_version_ids().await? {
+        if let Some(entry) = history.get_version(&id).await? {
+            versions.push(entry);
+        }
+    }
+    for entry in parents_first(versions) {
+        write_record(&mut writer, &Record::Version(entry))?;
+        written += 1;
+    }
+
+    for branch in history.list_branches().await {
+        write_record(&mut writer, &Record::Branch(branch))?;
+        written += 1;
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+fn write_record(writer: &mut impl Write, record: &Record) -> OnyxResult<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Import
+// ---------------------------------------------------------------------------
+
+/// Restore a dump produced by [`dump`] into `stores`.
+///
+/// All records are applied in a single transaction, so a malformed line or a
+/// conflicting ID leaves the stores untouched. Returns the number of records.
+pub fn load(reader: impl BufRead, stores: &mut TransactionManager) -> OnyxResult<usize> {
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut embeddings = Vec::new();
+    let mut versions = Vec::new();
+    let mut branches = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: Record = serde_json::from_str(&line).map_err(|e| {
+            OnyxError::IngestionError(format!("Invalid record on line {}: {}", index + 1, e))
+        })?;
+        match record {
+            Record::Node(node) => nodes.push(TransactionOp::InsertNode(node)),
+            Record::Edge(edge) => edges.push(TransactionOp::InsertEdge(edge)),
+            Record::Embedding { id, vector } => embeddings.push(TransactionOp::InsertEmbedding {
+                id,
+                embedding: vector,
+            }),
+            Record::Version(entry) => versions.push(entry),
+            Record::Branch(branch) => branches.push(TransactionOp::PutBranch(branch)),
+        }
+    }
+
+    // Versions need their parent recorded first, whatever order the file uses.
+    let versions = parents_first(versions)
+        .into_iter()
+        .map(TransactionOp::RecordVersion);
+
+    let ops: Vec<TransactionOp> = nodes
+        .into_iter()
+        .chain(edges)
+        .chain(embeddings)
+        .chain(versions)
+        .chain(branches)
+        .collect();
+    let count = ops.len();
+
+    stores.execute_batch(ops)?;
+    Ok(count)
+}
+
+/// Order versions by timestamp, pulling each version's ancestors ahead of it.
+pub(crate) fn parents_first(mut versions: Vec<VersionEntry>) -> Vec<VersionEntry> {
+    versions.sort_by_key(|v| v.timestamp);
+    let order: Vec<VersionId> = versions.iter().map(|v| v.version_id.clone()).collect();
+    let mut by_id: HashMap<VersionId, VersionEntry> = versions
+        .into_iter()
+        .map(|v| (v.version_id.clone(), v))
+        .collect();
+
+    let mut ordered = Vec::with_capacity(order.len());
+    for id in order {
+        let mut chain = Vec::new();
+        let mut next = Some(id);
+        while let Some(entry) = next.and_then(|id| by_id.remove(&id)) {
+            next = entry.parent_version.clone();
+            chain.push(entry);
+        }
+        ordered.extend(chain.into_iter().rev());
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LimitsConfig;
+    use crate::ingest::demo::build_synthetic_codebase;
+    use crate::ingest::ingest_codebase;
+    use crate::model::embedding::BagOfWordsEmbedder;
+
+    #[tokio::test]
+    async fn test_dump_and_load_round_trip_demo_dataset() {
+        let units = build_synthetic_codebase();
+        let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+        let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+
+        let mut source = TransactionManager::new();
+        ingest_codebase(&mut source, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+        let base = source.history_store.get_all_version_ids().await.unwrap()[0].clone();
+        source
+            .history_store
+            .create_branch("feature/export", base)
+            .await
+            .unwrap();
+
+        let mut buffer = Vec::new();
+        let written = dump(&source, &mut buffer).await.unwrap();
+
+        let mut target = TransactionManager::new();
+        let loaded = load(buffer.as_slice(), &mut target).unwrap();
+        assert_eq!(written, loaded);
+
+        let (before, after) = (source.stats(), target.stats());
+        assert!(before.node_count > 0 && before.version_count > 0);
+        assert_eq!(before.node_count, after.node_count);
+        assert_eq!(before.edge_count, after.edge_count);
+        assert_eq!(before.embedding_count, after.embedding_count);
+        assert_eq!(before.version_count, after.version_count);
+
+        let branch = target
+            .history_store
+            .get_branch("feature/export")
+            .await
+            .unwrap()
+            .unwrap();
+        let original = source
+            .history_store
+            .get_branch("feature/export")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(branch.head, original.head);
+        assert_eq!(branch.base, original.base);
+        assert_eq!(branch.created_at, original.created_at);
+    }
+
+    #[tokio::test]
+    async fn test_load_is_atomic_on_bad_line() {
+        let mut source = TransactionManager::new();
+        let units = build_synthetic_codebase();
+        let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+        let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+        ingest_codebase(
+            &mut source,
+            &units[..3],
+            &embedder,
+            &LimitsConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let mut buffer = Vec::new();
+        dump(&source, &mut buffer).await.unwrap();
+        buffer.extend_from_slice(b"{\"kind\":\"node\",\"data\":42}\n");
+
+        let mut target = TransactionManager::new();
+        assert!(load(buffer.as_slice(), &mut target).is_err());
+        assert_eq!(target.stats().node_count, 0);
+    }
+}
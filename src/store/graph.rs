@@ -1,7 +1,10 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::stream::{FuturesUnordered, StreamExt};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
@@ -9,6 +12,8 @@ use crate::db::OnyxDatabase;
 use crate::error::{OnyxError, OnyxResult};
 use crate::model::edge::{Edge, EdgeType};
 use crate::model::node::Node;
+use crate::model::version::{line_changes, VersionEntry};
+use crate::store::history::HistoryStore;
 
 // ---------------------------------------------------------------------------
 // GraphStore trait: interface for structural relationship storage & traversal
@@ -20,8 +25,16 @@ pub trait GraphStore: Send + Sync {
     /// Add a node to the graph.
     async fn add_node(&self, node: Node) -> OnyxResult<()>;
 
-    /// Get a node by ID.
-    async fn get_node(&self, id: &Uuid) -> OnyxResult<Option<Node>>;
+    /// Get a node by ID, including soft-deleted nodes.
+    async fn get_node_including_deleted(&self, id: &Uuid) -> OnyxResult<Option<Node>>;
+
+    /// Get a node by ID. Soft-deleted nodes are treated as absent.
+    async fn get_node(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
+        Ok(self
+            .get_node_including_deleted(id)
+            .await?
+            .filter(|node| !node.is_deleted()))
+    }
 
     /// Update a node.
     async fn update_node(&self, node: Node) -> OnyxResult<()>;
@@ -29,6 +42,24 @@ pub trait GraphStore: Send + Sync {
     /// Remove a node and all its edges.
     async fn remove_node(&self, id: &Uuid) -> OnyxResult<()>;
 
+    /// Mark a node as deleted without removing it, returning the updated node.
+    ///
+    /// The node and its edges stay in storage, but it is hidden from
+    /// `get_node`, `all_nodes` and traversals. Use
+    /// [`TransactionManager::soft_delete_node`](crate::store::transaction::TransactionManager::soft_delete_node)
+    /// to also record the deletion in history.
+    async fn soft_delete_node(&self, id: &Uuid) -> OnyxResult<Node> {
+        let mut node = self
+            .get_node(id)
+            .await?
+            .ok_or(OnyxError::NodeNotFound(*id))?;
+        let now = Utc::now();
+        node.deleted_at = Some(now);
+        node.updated_at = now;
+        self.update_node(node.clone()).await?;
+        Ok(node)
+    }
+
     /// Add an edge to the graph.
     async fn add_edge(&self, edge: Edge) -> OnyxResult<()>;
 
@@ -52,6 +83,29 @@ pub trait GraphStore: Send + Sync {
         edge_types: Option<&[EdgeType]>,
     ) -> OnyxResult<Vec<(Edge, Node)>>;
 
+    /// [`get_neighbors`](Self::get_neighbors) over edges that still hold.
+    /// Closed edges are history, so traversal and queries skip them.
+    async fn get_active_neighbors(
+        &self,
+        id: &Uuid,
+        edge_types: Option<&[EdgeType]>,
+    ) -> OnyxResult<Vec<(Edge, Node)>> {
+        let mut neighbors = self.get_neighbors(id, edge_types).await?;
+        neighbors.retain(|(edge, _)| edge.is_active());
+        Ok(neighbors)
+    }
+
+    /// [`get_inbound`](Self::get_inbound) over edges that still hold.
+    async fn get_active_inbound(
+        &self,
+        id: &Uuid,
+        edge_types: Option<&[EdgeType]>,
+    ) -> OnyxResult<Vec<(Edge, Node)>> {
+        let mut inbound = self.get_inbound(id, edge_types).await?;
+        inbound.retain(|(edge, _)| edge.is_active());
+        Ok(inbound)
+    }
+
     /// Get all node IDs in the graph.
     async fn get_all_node_ids(&self) -> OnyxResult<Vec<Uuid>>;
 
@@ -68,8 +122,213 @@ pub trait GraphStore: Send + Sync {
         self.add_edge(edge).await
     }
 
+    /// Find an edge with the given source, target, and type, if one exists.
+    /// An active edge is preferred over closed ones.
+    async fn find_edge(
+        &self,
+        source: &Uuid,
+        target: &Uuid,
+        edge_type: &EdgeType,
+    ) -> OnyxResult<Option<Edge>> {
+        let neighbors = self
+            .get_neighbors(source, Some(std::slice::from_ref(edge_type)))
+            .await?;
+        Ok(neighbors
+            .into_iter()
+            .map(|(edge, _)| edge)
+            .filter(|edge| edge.target_id == *target)
+            .max_by_key(|edge| edge.is_active()))
+    }
+
+    /// End an edge's validity window at `timestamp` without deleting it, so
+    /// [`edges_at_time`](Self::edges_at_time) still sees it before then.
+    async fn close_edge(&self, id: &Uuid, timestamp: DateTime<Utc>) -> OnyxResult<Edge> {
+        let edge = self
+            .get_edge(id)
+            .await?
+            .ok_or(OnyxError::EdgeNotFound(*id))?
+            .valid_until(timestamp);
+        self.remove_edge(id).await?;
+        self.add_edge(edge.clone()).await?;
+        Ok(edge)
+    }
+
+    /// Insert `edge`, or fold it into an existing edge with the same source,
+    /// target, and type; see [`Edge::reinforced`]. A closed edge is left as
+    /// history and a new one inserted. Returns `true` if a new edge was
+    /// inserted.
+    async fn upsert_edge(&self, edge: Edge) -> OnyxResult<bool> {
+        match self
+            .find_edge(&edge.source_id, &edge.target_id, &edge.edge_type)
+            .await?
+            .filter(Edge::is_active)
+        {
+            None => {
+                self.add_edge(edge).await?;
+                Ok(true)
+            }
+            Some(existing) => {
+                let id = existing.id;
+                self.remove_edge(&id).await?;
+                self.add_edge(existing.reinforced(&edge)).await?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Find the live node with the same (file path, module path, name) key
+    /// as `node`.
+    async fn find_node_by_key(&self, node: &Node) -> OnyxResult<Option<Node>> {
+        let candidates = match node.provenance.file_path.as_deref() {
+            Some(path) => self.nodes_by_file(path).await,
+            None => self.all_nodes().await,
+        };
+        Ok(candidates.into_iter().find(|other| same_key(other, node)))
+    }
+
+    /// Insert `node`, or resolve a clash with the node sharing its
+    /// (file path, module path, name) key according to `policy`.
+    ///
+    /// A replaced node keeps the existing ID and creation time. Under
+    /// [`ConflictPolicy::RecordVersion`] the new version goes on the node's
+    /// provenance branch (`main` by default) and is recorded in `history`
+    /// before the node is written, then removed again if the write fails.
+    async fn upsert_node_by_key(
+        &self,
+        mut node: Node,
+        policy: ConflictPolicy,
+        history: &dyn HistoryStore,
+    ) -> OnyxResult<NodeUpsert> {
+        let existing = self.find_node_by_key(&node).await?;
+        if let (Some(existing), ConflictPolicy::KeepExisting) = (&existing, policy) {
+            return Ok(NodeUpsert {
+                node: existing.clone(),
+                inserted: false,
+                previous: None,
+                version: None,
+            });
+        }
+
+        if let Some(existing) = &existing {
+            node.id = existing.id;
+            node.created_at = existing.created_at;
+            node.current_version = existing.current_version.clone();
+        }
+        let branch = node.provenance.branch.as_deref().unwrap_or("main");
+        let head = match policy {
+            ConflictPolicy::RecordVersion => history.get_head(&node.id, branch).await?,
+            _ => None,
+        };
+        let parent = head.clone().or_else(|| node.current_version.clone());
+        let version = match (policy, &existing, parent) {
+            (ConflictPolicy::RecordVersion, Some(existing), Some(parent)) => {
+                (node.content_hash != existing.content_hash).then(|| {
+                    let (additions, deletions) = line_changes(&existing.content, &node.content);
+                    VersionEntry::content_change(
+                        node.id,
+                        parent,
+                        &node.content,
+                        additions,
+                        deletions,
+                    )
+                })
+            }
+            (ConflictPolicy::RecordVersion, _, _) => {
+                Some(VersionEntry::initial(node.id, &node.content))
+            }
+            _ => None,
+        };
+        let version = version.map(|version| {
+            let version = version.with_branch(branch);
+            match &node.provenance.commit_id {
+                Some(commit) => version.with_commit(commit),
+                None => version,
+            }
+        });
+
+        if let Some(version) = &version {
+            node.current_version = Some(version.version_id.clone());
+            history.record_version(version.clone()).await?;
+        }
+        let written = match &existing {
+            Some(_) => self.update_node(node.clone()).await,
+            None => self.add_node(node.clone()).await,
+        };
+        if let Err(err) = written {
+            let Some(version) = &version else {
+                return Err(err);
+            };
+            return match history.remove_version(&version.version_id, head).await {
+                Ok(()) => Err(err),
+                Err(undo) => Err(OnyxError::TransactionFailed(format!(
+                    "Node write failed: {}. Removing its version also failed: {}",
+                    err, undo
+                ))),
+            };
+        }
+        Ok(NodeUpsert {
+            node,
+            inserted: existing.is_none(),
+            previous: existing,
+            version,
+        })
+    }
+
+    /// Every live node whose name matches `name` under `mode`, best match
+    /// first.
+    async fn find_nodes_by_name(&self, name: &str, mode: MatchMode) -> Vec<NameMatch> {
+        let mut matches: Vec<NameMatch> = self
+            .all_nodes()
+            .await
+            .into_iter()
+            .filter_map(|node| {
+                let score = mode.score(name, &node.name)?;
+                Some(NameMatch { node, score })
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.node.name.cmp(&b.node.name))
+        });
+        matches
+    }
+
+    /// Live nodes whose name is within `max_distance` edits of `name`
+    /// (ignoring case), closest first, at most `limit` of them.
+    async fn fuzzy_find(&self, name: &str, limit: usize, max_distance: usize) -> Vec<FuzzyMatch> {
+        let query = name.to_lowercase();
+        let mut matches: Vec<FuzzyMatch> = self
+            .all_nodes()
+            .await
+            .into_iter()
+            .filter_map(|node| {
+                let distance = edit_distance(&query, &node.name.to_lowercase());
+                (distance <= max_distance).then_some(FuzzyMatch { node, distance })
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| a.node.name.cmp(&b.node.name))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// Check whether an edge with the given source, target, and type exists.
+    async fn edge_exists(
+        &self,
+        source: &Uuid,
+        target: &Uuid,
+        edge_type: &EdgeType,
+    ) -> OnyxResult<bool> {
+        Ok(self.find_edge(source, target, edge_type).await?.is_some())
+    }
+
     /// Multi-hop traversal: get all nodes reachable from a start node within
-    /// a given depth, following specified edge types.
+    /// a given depth, following specified edge types. Soft-deleted nodes and
+    /// closed edges are skipped.
     async fn traverse(
         &self,
         start_id: &Uuid,
@@ -77,6 +336,52 @@ pub trait GraphStore: Send + Sync {
         max_depth: usize,
     ) -> OnyxResult<TraversalResult>;
 
+    /// [`traverse`](Self::traverse) in breadth-first order, stopping early
+    /// once `budget` is spent. The nodes reached so far are returned with
+    /// `truncated` set, nearest first.
+    async fn traverse_with_budget(
+        &self,
+        start_id: &Uuid,
+        edge_types: Option<&[EdgeType]>,
+        max_depth: usize,
+        budget: TraversalBudget,
+    ) -> OnyxResult<TraversalResult> {
+        if self.get_node(start_id).await?.is_none() {
+            return Err(OnyxError::NodeNotFound(*start_id));
+        }
+
+        let mut visited: HashSet<Uuid> = HashSet::from([*start_id]);
+        let mut queue: VecDeque<(Uuid, usize)> = VecDeque::from([(*start_id, 0)]);
+        let mut nodes: Vec<(Uuid, usize)> = Vec::new();
+        let mut edges: Vec<Uuid> = Vec::new();
+        let mut truncated = false;
+
+        while let Some((current_id, depth)) = queue.pop_front() {
+            if budget.exhausted(nodes.len()) {
+                truncated = true;
+                break;
+            }
+            nodes.push((current_id, depth));
+
+            if depth >= max_depth {
+                continue;
+            }
+            for (edge, node) in self.get_active_neighbors(&current_id, edge_types).await? {
+                edges.push(edge.id);
+                if visited.insert(node.id) {
+                    queue.push_back((node.id, depth + 1));
+                }
+            }
+        }
+
+        Ok(TraversalResult {
+            total_visited: visited.len(),
+            nodes,
+            edges,
+            truncated,
+        })
+    }
+
     /// Find all paths between two nodes up to a maximum depth.
     async fn find_paths(
         &self,
@@ -91,6 +396,12 @@ pub trait GraphStore: Send + Sync {
     /// Get all nodes of a specific type.
     async fn nodes_by_type(&self, node_type: &crate::model::node::NodeType) -> Vec<Node>;
 
+    /// Get all live nodes whose provenance points at `file_path`.
+    async fn nodes_by_file(&self, file_path: &str) -> Vec<Node>;
+
+    /// Get all live code entities whose module path starts with `prefix`.
+    async fn nodes_under_module(&self, prefix: &[String]) -> Vec<Node>;
+
     /// Get all edges of a specific type.
     async fn edges_by_type(&self, edge_type: &EdgeType) -> Vec<Edge>;
 
@@ -103,8 +414,118 @@ pub trait GraphStore: Send + Sync {
     /// Total number of edges in the graph.
     async fn edge_count(&self) -> usize;
 
-    /// Get all nodes in the graph.
+    /// Get all live (not soft-deleted) nodes in the graph.
     async fn all_nodes(&self) -> Vec<Node>;
+
+    /// Make every acknowledged write durable, e.g. before shutting down.
+    async fn flush(&self) -> OnyxResult<()> {
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Node upsert by (file path, module path, name)
+// ---------------------------------------------------------------------------
+
+/// What [`GraphStore::upsert_node_by_key`] does when a node with the same key
+/// already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Overwrite the existing node in place.
+    Replace,
+    /// Leave the existing node untouched.
+    KeepExisting,
+    /// Overwrite it and describe a content change as a new version.
+    RecordVersion,
+}
+
+/// Outcome of [`GraphStore::upsert_node_by_key`].
+#[derive(Debug, Clone)]
+pub struct NodeUpsert {
+    /// The node as stored.
+    pub node: Node,
+    /// Whether no node with the key existed.
+    pub inserted: bool,
+    /// The node it overwrote, if any.
+    pub previous: Option<Node>,
+    /// Version recorded for the write, under [`ConflictPolicy::RecordVersion`].
+    pub version: Option<VersionEntry>,
+}
+
+fn same_key(a: &Node, b: &Node) -> bool {
+    a.name == b.name
+        && a.provenance.file_path == b.provenance.file_path
+        && a.module_path().unwrap_or_default() == b.module_path().unwrap_or_default()
+}
+
+// ---------------------------------------------------------------------------
+// Node lookup by name
+// ---------------------------------------------------------------------------
+
+/// How [`GraphStore::find_nodes_by_name`] compares names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Names are identical.
+    Exact,
+    /// Names are equal ignoring case.
+    CaseInsensitive,
+    /// The query occurs anywhere in the name, ignoring case.
+    Fuzzy,
+}
+
+impl MatchMode {
+    /// Score in (0, 1] for `candidate` against `query`, or `None` if it
+    /// doesn't match. Fuzzy matches score by how much of the name the query
+    /// covers.
+    pub fn score(self, query: &str, candidate: &str) -> Option<f32> {
+        match self {
+            MatchMode::Exact => (candidate == query).then_some(1.0),
+            MatchMode::CaseInsensitive => candidate.eq_ignore_ascii_case(query).then_some(1.0),
+            MatchMode::Fuzzy => {
+                let query = query.to_lowercase();
+                let candidate = candidate.to_lowercase();
+                if query.is_empty() || !candidate.contains(&query) {
+                    return None;
+                }
+                Some(query.chars().count() as f32 / candidate.chars().count() as f32)
+            }
+        }
+    }
+}
+
+/// A node found by [`GraphStore::find_nodes_by_name`].
+#[derive(Debug, Clone)]
+pub struct NameMatch {
+    pub node: Node,
+    /// 1.0 for an exact match; lower is a weaker fuzzy match.
+    pub score: f32,
+}
+
+/// Edit distance under which a name counts as a likely typo.
+pub const DEFAULT_MAX_EDIT_DISTANCE: usize = 2;
+
+/// A node found by [`GraphStore::fuzzy_find`].
+#[derive(Debug, Clone)]
+pub struct FuzzyMatch {
+    pub node: Node,
+    /// Levenshtein distance between the query and the node's name.
+    pub distance: usize,
+}
+
+/// Levenshtein distance between `a` and `b`, in chars.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
 }
 
 // ---------------------------------------------------------------------------
@@ -120,6 +541,25 @@ pub struct TraversalResult {
     pub edges: Vec<Uuid>,
     /// Total nodes visited.
     pub total_visited: usize,
+    /// Whether a [`TraversalBudget`] stopped the traversal before it finished.
+    pub truncated: bool,
+}
+
+/// Limits on the work done by [`GraphStore::traverse_with_budget`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraversalBudget {
+    /// Most nodes to examine, counting the start node (None = unlimited).
+    pub max_nodes: Option<usize>,
+    /// Stop once this instant has passed (None = no time limit).
+    pub deadline: Option<Instant>,
+}
+
+impl TraversalBudget {
+    /// Whether a traversal that has examined `examined` nodes must stop.
+    pub fn exhausted(&self, examined: usize) -> bool {
+        self.max_nodes.is_some_and(|max| examined >= max)
+            || self.deadline.is_some_and(|at| Instant::now() >= at)
+    }
 }
 
 /// Result of extracting a subgraph.
@@ -131,6 +571,82 @@ pub struct SubgraphResult {
     pub edge_ids: HashSet<Uuid>,
 }
 
+// ---------------------------------------------------------------------------
+// Parallel traversal
+// ---------------------------------------------------------------------------
+
+/// How many neighbor lookups a backend's `traverse` keeps in flight.
+pub const DEFAULT_TRAVERSAL_CONCURRENCY: usize = 16;
+
+/// Breadth-first traversal that looks up the neighbors of every node in a
+/// level concurrently, at most `concurrency` at a time. Closed edges are
+/// not followed.
+///
+/// Lookups may complete in any order, but their results are merged into
+/// the visited set in level order, so the result is identical to a
+/// sequential BFS over the same `fetch_neighbors`.
+pub async fn traverse_parallel<F, Fut>(
+    start_id: Uuid,
+    max_depth: usize,
+    concurrency: usize,
+    fetch_neighbors: F,
+) -> OnyxResult<TraversalResult>
+where
+    F: Fn(Uuid) -> Fut,
+    Fut: Future<Output = OnyxResult<Vec<(Edge, Node)>>>,
+{
+    let lookup = |index: usize, id: Uuid| {
+        let neighbors = fetch_neighbors(id);
+        async move { (index, neighbors.await) }
+    };
+
+    let mut visited: HashSet<Uuid> = HashSet::from([start_id]);
+    let mut nodes: Vec<(Uuid, usize)> = Vec::new();
+    let mut edges: Vec<Uuid> = Vec::new();
+    let mut level = vec![start_id];
+
+    for depth in 0..=max_depth {
+        nodes.extend(level.iter().map(|id| (*id, depth)));
+        if depth == max_depth || level.is_empty() {
+            break;
+        }
+
+        let mut found: Vec<Vec<(Edge, Node)>> =
+            std::iter::repeat_with(Vec::new).take(level.len()).collect();
+        let mut pending = level.iter().copied().enumerate();
+        let mut in_flight: FuturesUnordered<_> = pending
+            .by_ref()
+            .take(concurrency.max(1))
+            .map(|(index, id)| lookup(index, id))
+            .collect();
+        while let Some((index, neighbors)) = in_flight.next().await {
+            found[index] = neighbors?;
+            if let Some((index, id)) = pending.next() {
+                in_flight.push(lookup(index, id));
+            }
+        }
+
+        let mut next_level = Vec::new();
+        for (edge, node) in found.into_iter().flatten() {
+            if !edge.is_active() {
+                continue;
+            }
+            edges.push(edge.id);
+            if visited.insert(node.id) {
+                next_level.push(node.id);
+            }
+        }
+        level = next_level;
+    }
+
+    Ok(TraversalResult {
+        total_visited: visited.len(),
+        nodes,
+        edges,
+        truncated: false,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // SurrealDB Graph Store
 // ---------------------------------------------------------------------------
@@ -159,7 +675,7 @@ impl GraphStore for SurrealGraphStore {
         Ok(())
     }
 
-    async fn get_node(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
+    async fn get_node_including_deleted(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
         let node: Option<Node> = self
             .db
             .select("node", id.to_string())
@@ -220,6 +736,37 @@ impl GraphStore for SurrealGraphStore {
         Ok(())
     }
 
+    async fn close_edge(&self, id: &Uuid, timestamp: DateTime<Utc>) -> OnyxResult<Edge> {
+        let edge = self
+            .get_edge(id)
+            .await?
+            .ok_or(OnyxError::EdgeNotFound(*id))?
+            .valid_until(timestamp);
+        // A single UPDATE, so the edge never goes missing mid-close
+        self.db
+            .update("edge", &id.to_string(), edge.clone())
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to close edge: {}", e)))?;
+        Ok(edge)
+    }
+
+    async fn upsert_edge(&self, edge: Edge) -> OnyxResult<bool> {
+        let existing = self
+            .find_edge(&edge.source_id, &edge.target_id, &edge.edge_type)
+            .await?
+            .filter(Edge::is_active);
+        let Some(existing) = existing else {
+            self.add_edge(edge).await?;
+            return Ok(true);
+        };
+        let id = existing.id.to_string();
+        self.db
+            .update("edge", &id, existing.reinforced(&edge))
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to upsert edge: {}", e)))?;
+        Ok(false)
+    }
+
     async fn get_neighbors(
         &self,
         id: &Uuid,
@@ -328,37 +875,13 @@ impl GraphStore for SurrealGraphStore {
             return Err(OnyxError::NodeNotFound(*start_id));
         }
 
-        let mut visited: HashSet<Uuid> = HashSet::new();
-        let mut result_nodes: Vec<(Uuid, usize)> = Vec::new();
-        let mut result_edges: Vec<Uuid> = Vec::new();
-        let mut queue: VecDeque<(Uuid, usize)> = VecDeque::new();
-
-        queue.push_back((*start_id, 0));
-        visited.insert(*start_id);
-
-        while let Some((current_id, depth)) = queue.pop_front() {
-            result_nodes.push((current_id, depth));
-
-            if depth >= max_depth {
-                continue;
-            }
-
-            let neighbors = self.get_neighbors(&current_id, edge_types).await?;
-            for (edge, node) in neighbors {
-                result_edges.push(edge.id);
-
-                if !visited.contains(&node.id) {
-                    visited.insert(node.id);
-                    queue.push_back((node.id, depth + 1));
-                }
-            }
-        }
-
-        Ok(TraversalResult {
-            total_visited: visited.len(),
-            nodes: result_nodes,
-            edges: result_edges,
-        })
+        traverse_parallel(
+            *start_id,
+            max_depth,
+            DEFAULT_TRAVERSAL_CONCURRENCY,
+            |id| async move { self.get_neighbors(&id, edge_types).await },
+        )
+        .await
     }
 
     async fn find_paths(
@@ -404,7 +927,7 @@ impl GraphStore for SurrealGraphStore {
 
     async fn nodes_by_type(&self, node_type: &crate::model::node::NodeType) -> Vec<Node> {
         let query = format!(
-            "SELECT * FROM node WHERE node_type = {}",
+            "SELECT * FROM node WHERE node_type = {} AND deleted_at IS NONE",
             serde_json::to_string(node_type).unwrap_or_default()
         );
 
@@ -414,6 +937,24 @@ impl GraphStore for SurrealGraphStore {
         }
     }
 
+    async fn nodes_by_file(&self, file_path: &str) -> Vec<Node> {
+        let query = format!(
+            "SELECT * FROM node WHERE provenance.file_path = {} AND deleted_at IS NONE",
+            serde_json::to_string(file_path).unwrap_or_default()
+        );
+
+        match self.db.query(&query).await {
+            Ok(mut response) => response.take(0).unwrap_or_default(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    async fn nodes_under_module(&self, prefix: &[String]) -> Vec<Node> {
+        let mut nodes = self.all_nodes().await;
+        nodes.retain(|n| n.is_under_module(prefix));
+        nodes
+    }
+
     async fn edges_by_type(&self, edge_type: &EdgeType) -> Vec<Edge> {
         let query = format!(
             "SELECT * FROM edge WHERE edge_type = {}",
@@ -426,6 +967,33 @@ impl GraphStore for SurrealGraphStore {
         }
     }
 
+    async fn find_edge(
+        &self,
+        source: &Uuid,
+        target: &Uuid,
+        edge_type: &EdgeType,
+    ) -> OnyxResult<Option<Edge>> {
+        let query = format!(
+            "SELECT * FROM edge WHERE source_id = '{}' AND target_id = '{}' AND edge_type = {}",
+            source,
+            target,
+            serde_json::to_string(edge_type).unwrap_or_default()
+        );
+
+        let mut response = self
+            .db
+            .query(&query)
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to find edge: {}", e)))?;
+
+        let edges: Vec<Edge> = response
+            .take(0)
+            .map_err(|e| OnyxError::Internal(format!("Failed to parse edges: {}", e)))?;
+
+        // Closed edges stay as history, so prefer the active one
+        Ok(edges.into_iter().max_by_key(Edge::is_active))
+    }
+
     async fn edges_at_time(&self, id: &Uuid, timestamp: &DateTime<Utc>) -> OnyxResult<Vec<Edge>> {
         // Get all edges connected to this node
         let id_str = id.to_string();
@@ -482,7 +1050,7 @@ impl GraphStore for SurrealGraphStore {
     }
 
     async fn all_nodes(&self) -> Vec<Node> {
-        match self.db.query("SELECT * FROM node").await {
+        match self.db.query("SELECT * FROM node WHERE deleted_at IS NONE").await {
             Ok(mut response) => response.take(0).unwrap_or_default(),
             Err(_) => Vec::new(),
         }
@@ -557,7 +1125,7 @@ impl SurrealGraphStore {
             return;
         }
 
-        if let Ok(neighbors) = self.get_neighbors(current, None).await {
+        if let Ok(neighbors) = self.get_active_neighbors(current, None).await {
             for (edge, node) in neighbors {
                 if !visited.contains(&node.id) {
                     visited.insert(node.id);
@@ -601,9 +1169,10 @@ impl InMemoryGraphStore {
         }
     }
 
+    /// All live (not soft-deleted) nodes.
     pub async fn all_nodes(&self) -> Vec<Node> {
         let nodes = self.nodes.read().await;
-        nodes.values().cloned().collect()
+        nodes.values().filter(|n| !n.is_deleted()).cloned().collect()
     }
 }
 
@@ -632,7 +1201,7 @@ impl GraphStore for InMemoryGraphStore {
         Ok(())
     }
 
-    async fn get_node(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
+    async fn get_node_including_deleted(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
         let nodes = self.nodes.read().await;
         Ok(nodes.get(id).cloned())
     }
@@ -746,7 +1315,7 @@ impl GraphStore for InMemoryGraphStore {
                         continue;
                     }
                 }
-                if let Some(node) = nodes.get(&edge.target_id) {
+                if let Some(node) = nodes.get(&edge.target_id).filter(|n| !n.is_deleted()) {
                     results.push((edge.clone(), node.clone()));
                 }
             }
@@ -776,7 +1345,7 @@ impl GraphStore for InMemoryGraphStore {
                         continue;
                     }
                 }
-                if let Some(node) = nodes.get(&edge.source_id) {
+                if let Some(node) = nodes.get(&edge.source_id).filter(|n| !n.is_deleted()) {
                     results.push((edge.clone(), node.clone()));
                 }
             }
@@ -792,10 +1361,9 @@ impl GraphStore for InMemoryGraphStore {
         max_depth: usize,
     ) -> OnyxResult<TraversalResult> {
         let nodes = self.nodes.read().await;
-        if !nodes.contains_key(start_id) {
+        if !nodes.get(start_id).is_some_and(|n| !n.is_deleted()) {
             return Err(OnyxError::NodeNotFound(*start_id));
         }
-        drop(nodes);
 
         let mut visited: HashSet<Uuid> = HashSet::new();
         let mut result_nodes: Vec<(Uuid, usize)> = Vec::new();
@@ -823,6 +1391,14 @@ impl GraphStore for InMemoryGraphStore {
                             continue;
                         }
                     }
+                    if !edge.is_active() {
+                        continue;
+                    }
+
+                    // Soft-deleted nodes are skipped along with their edges.
+                    if nodes.get(&edge.target_id).map_or(true, |n| n.is_deleted()) {
+                        continue;
+                    }
 
                     result_edges.push(*edge_id);
 
@@ -838,6 +1414,7 @@ impl GraphStore for InMemoryGraphStore {
             total_visited: visited.len(),
             nodes: result_nodes,
             edges: result_edges,
+            truncated: false,
         })
     }
 
@@ -886,7 +1463,25 @@ impl GraphStore for InMemoryGraphStore {
         let nodes = self.nodes.read().await;
         nodes
             .values()
-            .filter(|n| &n.node_type == node_type)
+            .filter(|n| &n.node_type == node_type && !n.is_deleted())
+            .cloned()
+            .collect()
+    }
+
+    async fn nodes_by_file(&self, file_path: &str) -> Vec<Node> {
+        let nodes = self.nodes.read().await;
+        nodes
+            .values()
+            .filter(|n| n.provenance.file_path.as_deref() == Some(file_path) && !n.is_deleted())
+            .cloned()
+            .collect()
+    }
+
+    async fn nodes_under_module(&self, prefix: &[String]) -> Vec<Node> {
+        let nodes = self.nodes.read().await;
+        nodes
+            .values()
+            .filter(|n| n.is_under_module(prefix) && !n.is_deleted())
             .cloned()
             .collect()
     }
@@ -933,6 +1528,16 @@ impl GraphStore for InMemoryGraphStore {
     async fn all_nodes(&self) -> Vec<Node> {
         self.all_nodes().await
     }
+
+    async fn get_all_node_ids(&self) -> OnyxResult<Vec<Uuid>> {
+        let nodes = self.nodes.read().await;
+        Ok(nodes.keys().copied().collect())
+    }
+
+    async fn get_all_edge_ids(&self) -> OnyxResult<Vec<Uuid>> {
+        let edges = self.edges.read().await;
+        Ok(edges.keys().copied().collect())
+    }
 }
 
 impl InMemoryGraphStore {
@@ -965,7 +1570,7 @@ impl InMemoryGraphStore {
 
         let edge_ids = outbound.get(current).cloned().unwrap_or_default();
         for edge_id in &edge_ids {
-            if let Some(edge) = edges.get(edge_id) {
+            if let Some(edge) = edges.get(edge_id).filter(|e| e.is_active()) {
                 if !visited.contains(&edge.target_id) {
                     visited.insert(edge.target_id);
                     path.push(edge.target_id);
@@ -993,6 +1598,7 @@ impl InMemoryGraphStore {
 mod tests {
     use super::*;
     use crate::model::node::{CodeEntityKind, NodeType};
+    use crate::store::history::InMemoryHistoryStore;
 
     async fn make_graph() -> (InMemoryGraphStore, Uuid, Uuid, Uuid) {
         let g = InMemoryGraphStore::new();
@@ -1049,6 +1655,76 @@ mod tests {
         assert_eq!(neighbors[0].1.name, "func_b");
     }
 
+    #[tokio::test]
+    async fn test_upsert_edge_combines_repeated_detections() {
+        let (g, _, id_b, id_c) = make_graph().await;
+        let edge = || Edge::new(EdgeType::DependsOn, id_b, id_c).with_confidence(0.8);
+
+        assert!(g.upsert_edge(edge()).await.unwrap());
+        assert!(!g.upsert_edge(edge()).await.unwrap());
+
+        let merged = g
+            .find_edge(&id_b, &id_c, &EdgeType::DependsOn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((merged.confidence - 0.96).abs() < 1e-9);
+        let outbound = g
+            .get_neighbors(&id_b, Some(&[EdgeType::DependsOn]))
+            .await
+            .unwrap();
+        assert_eq!(outbound.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_closed_edge_drops_out_of_later_snapshots() {
+        let (g, id_a, id_b, _) = make_graph().await;
+        let edge = g
+            .find_edge(&id_a, &id_b, &EdgeType::Calls)
+            .await
+            .unwrap()
+            .unwrap();
+        let closed_at = Utc::now() + chrono::Duration::seconds(1);
+
+        let closed = g.close_edge(&edge.id, closed_at).await.unwrap();
+        assert_eq!(closed.temporal.until_timestamp, Some(closed_at));
+        assert!(g.get_edge(&edge.id).await.unwrap().is_some());
+
+        let before = g
+            .edges_at_time(&id_a, &(closed_at - chrono::Duration::milliseconds(1)))
+            .await
+            .unwrap();
+        assert!(before.iter().any(|e| e.id == edge.id));
+        let after = g
+            .edges_at_time(&id_a, &(closed_at + chrono::Duration::seconds(1)))
+            .await
+            .unwrap();
+        assert!(after.iter().all(|e| e.id != edge.id));
+    }
+
+    #[tokio::test]
+    async fn test_surreal_upsert_after_close_keeps_one_active_edge() {
+        let g = SurrealGraphStore::new(Arc::new(OnyxDatabase::new_memory().await.unwrap()));
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let edge = || Edge::new(EdgeType::DependsOn, a, b).with_confidence(0.8);
+        let first = edge();
+        g.add_edge(first.clone()).await.unwrap();
+        g.close_edge(&first.id, Utc::now()).await.unwrap();
+
+        assert!(g.upsert_edge(edge()).await.unwrap());
+        assert!(!g.upsert_edge(edge()).await.unwrap());
+
+        let found = g
+            .find_edge(&a, &b, &EdgeType::DependsOn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(found.is_active());
+        assert_ne!(found.id, first.id);
+        assert!((found.confidence - 0.96).abs() < 1e-9);
+        assert_eq!(g.edge_count().await, 2);
+    }
+
     #[tokio::test]
     async fn test_traverse_depth_2() {
         let (g, id_a, _, _) = make_graph().await;
@@ -1098,6 +1774,19 @@ mod tests {
         assert_eq!(g.edge_count().await, 0);
     }
 
+    #[tokio::test]
+    async fn test_soft_deleted_node_is_skipped_by_traversal() {
+        let (g, id_a, id_b, _) = make_graph().await;
+        g.soft_delete_node(&id_b).await.unwrap();
+
+        let result = g.traverse(&id_a, None, 2).await.unwrap();
+        assert_eq!(result.nodes, vec![(id_a, 0)]);
+        assert!(result.edges.is_empty());
+        assert!(g.get_neighbors(&id_a, None).await.unwrap().is_empty());
+        assert_eq!(g.all_nodes().await.len(), 2);
+        assert!(g.get_node_including_deleted(&id_b).await.unwrap().is_some());
+    }
+
     #[tokio::test]
     async fn test_duplicate_node_error() {
         let g = InMemoryGraphStore::new();
@@ -1108,4 +1797,168 @@ mod tests {
         dup.id = id;
         assert!(g.add_node(dup).await.is_err());
     }
+
+    fn billing_total(content: &str) -> Node {
+        Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "total",
+            content,
+        )
+        .with_provenance(crate::model::node::Provenance::new("src/billing.rs"))
+    }
+
+    #[tokio::test]
+    async fn test_upsert_by_key_replace_keeps_id() {
+        let g = InMemoryGraphStore::new();
+        let history = InMemoryHistoryStore::new();
+        let first = g
+            .upsert_node_by_key(
+                billing_total("fn total() {}"),
+                ConflictPolicy::Replace,
+                &history,
+            )
+            .await
+            .unwrap();
+        assert!(first.inserted);
+
+        let second = g
+            .upsert_node_by_key(
+                billing_total("fn total() -> u32 { 0 }"),
+                ConflictPolicy::Replace,
+                &history,
+            )
+            .await
+            .unwrap();
+        assert!(!second.inserted);
+        assert!(second.version.is_none());
+        assert_eq!(second.previous.unwrap().content, "fn total() {}");
+        assert_eq!(second.node.id, first.node.id);
+        assert_eq!(g.node_count().await, 1);
+        let versions = history.list_versions(&first.node.id).await.unwrap();
+        assert!(versions.is_empty());
+        let stored = g.get_node(&first.node.id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "fn total() -> u32 { 0 }");
+    }
+
+    #[tokio::test]
+    async fn test_upsert_by_key_keep_existing_leaves_content() {
+        let g = InMemoryGraphStore::new();
+        let original = billing_total("fn total() {}");
+        let id = original.id;
+        g.add_node(original).await.unwrap();
+
+        let outcome = g
+            .upsert_node_by_key(
+                billing_total("fn total() -> u32 { 0 }"),
+                ConflictPolicy::KeepExisting,
+                &InMemoryHistoryStore::new(),
+            )
+            .await
+            .unwrap();
+        assert!(!outcome.inserted);
+        assert!(outcome.previous.is_none());
+        assert_eq!(outcome.node.id, id);
+        let stored = g.get_node(&id).await.unwrap().unwrap();
+        assert_eq!(stored.content, "fn total() {}");
+    }
+
+    #[tokio::test]
+    async fn test_upsert_by_key_record_version_appends_version() {
+        let g = InMemoryGraphStore::new();
+        let history = InMemoryHistoryStore::new();
+        // Upserting unchanged content records nothing.
+        for content in [
+            "fn total() {}",
+            "fn total() -> u32 { 0 }",
+            "fn total() -> u32 { 0 }",
+        ] {
+            let node = billing_total(content);
+            g.upsert_node_by_key(node, ConflictPolicy::RecordVersion, &history)
+                .await
+                .unwrap();
+        }
+
+        let node = g.all_nodes().await.remove(0);
+        let versions = history.list_versions(&node.id).await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(
+            versions[1].parent_version.as_ref(),
+            Some(&versions[0].version_id)
+        );
+        assert_eq!(node.current_version, Some(versions[1].version_id.clone()));
+        assert_eq!(
+            history.get_head(&node.id, "main").await.unwrap(),
+            node.current_version
+        );
+
+        // A different module is a different key.
+        let mut other = billing_total("fn total() {}");
+        other.extension =
+            crate::model::node::NodeExtension::CodeEntity(crate::model::node::CodeEntityExt {
+                module_path: vec!["invoices".to_string()],
+                ..Default::default()
+            });
+        let outcome = g
+            .upsert_node_by_key(other, ConflictPolicy::RecordVersion, &history)
+            .await
+            .unwrap();
+        assert!(outcome.inserted);
+    }
+
+    #[tokio::test]
+    async fn test_find_nodes_by_name_returns_every_match() {
+        let g = InMemoryGraphStore::new();
+        for name in ["calculate_total", "calculate_tax", "apply_discount"] {
+            g.add_node(Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                name,
+                "",
+            ))
+            .await
+            .unwrap();
+        }
+
+        let names = |matches: Vec<NameMatch>| -> Vec<String> {
+            matches.into_iter().map(|m| m.node.name).collect()
+        };
+        assert_eq!(
+            names(g.find_nodes_by_name("calculate", MatchMode::Fuzzy).await),
+            ["calculate_tax", "calculate_total"]
+        );
+        assert_eq!(
+            names(
+                g.find_nodes_by_name("Apply_Discount", MatchMode::CaseInsensitive)
+                    .await
+            ),
+            ["apply_discount"]
+        );
+        assert!(g
+            .find_nodes_by_name("Apply_Discount", MatchMode::Exact)
+            .await
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fuzzy_find_tolerates_typos() {
+        let g = InMemoryGraphStore::new();
+        for name in ["calculate_total", "calculate_tax", "apply_discount"] {
+            g.add_node(Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                name,
+                "",
+            ))
+            .await
+            .unwrap();
+        }
+
+        let matches = g
+            .fuzzy_find("calculat_total", 3, DEFAULT_MAX_EDIT_DISTANCE)
+            .await;
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].node.name, "calculate_total");
+        assert_eq!(matches[0].distance, 1);
+
+        assert!(g.fuzzy_find("calculat_total", 3, 0).await.is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
 }
@@ -7,7 +7,7 @@ use uuid::Uuid;
 
 use crate::db::OnyxDatabase;
 use crate::error::{OnyxError, OnyxResult};
-use crate::model::version::{new_version_id, Branch, Diff, VersionEntry, VersionId};
+use crate::model::version::{new_version_id, unified_diff, Branch, Diff, VersionEntry, VersionId};
 
 // ---------------------------------------------------------------------------
 // HistoryStore trait: interface for temporal versioning
@@ -69,12 +69,136 @@ pub trait HistoryStore: Send + Sync {
     /// List all branches.
     async fn list_branches(&self) -> Vec<Branch>;
 
+    /// Insert or replace a branch's metadata as-is, e.g. when restoring a
+    /// dump or undoing a merge.
+    async fn put_branch(&self, branch: Branch) -> OnyxResult<()>;
+
     /// Merge a source branch into a target branch.
     /// Returns the merge version ID.
     async fn merge_branch(&self, source: &str, target: &str) -> OnyxResult<VersionId>;
 
     /// Total number of version entries.
     async fn version_count(&self) -> usize;
+
+    /// All stored branch heads as `(entity_id, branch, version_id)`.
+    ///
+    /// Backends that derive heads from the version chain store none.
+    async fn list_branch_heads(&self) -> OnyxResult<Vec<(Uuid, String, VersionId)>> {
+        Ok(Vec::new())
+    }
+
+    /// Drop the stored head of `entity_id` on `branch`, if any.
+    async fn remove_branch_head(&self, _entity_id: &Uuid, _branch: &str) -> OnyxResult<()> {
+        Ok(())
+    }
+
+    /// Undo [`record_version`](Self::record_version), resetting the branch
+    /// head to `previous_head`.
+    ///
+    /// Used to roll back batches on stores without a database transaction;
+    /// only the entity's latest version should be removed this way.
+    async fn remove_version(
+        &self,
+        version_id: &VersionId,
+        _previous_head: Option<VersionId>,
+    ) -> OnyxResult<()> {
+        Err(OnyxError::TransactionFailed(format!(
+            "cannot remove version {version_id} from this history store"
+        )))
+    }
+
+    /// Remove a branch's metadata, e.g. to roll back a [`put_branch`](Self::put_branch).
+    async fn delete_branch(&self, name: &str) -> OnyxResult<()> {
+        Err(OnyxError::TransactionFailed(format!(
+            "cannot delete branch '{name}' from this history store"
+        )))
+    }
+
+    /// Make every acknowledged write durable, e.g. before shutting down.
+    async fn flush(&self) -> OnyxResult<()> {
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Diffs between versions
+// ---------------------------------------------------------------------------
+
+/// Reconstruct `entity_id` at versions `from` and `to` and render the change
+/// between them as a unified diff, empty if the content is the same.
+pub async fn diff_versions(
+    history: &dyn HistoryStore,
+    entity_id: &Uuid,
+    from: &VersionId,
+    to: &VersionId,
+) -> OnyxResult<String> {
+    let old = history.get_content_at_version(entity_id, from).await?;
+    let new = history.get_content_at_version(entity_id, to).await?;
+    Ok(unified_diff(&old, &new, from, to))
+}
+
+// ---------------------------------------------------------------------------
+// Merge conflicts
+// ---------------------------------------------------------------------------
+
+/// Entities changed on both `source` and `target` since `source` was created
+/// whose content differs at the two branches' latest versions, sorted.
+///
+/// [`HistoryStore::merge_branch`] does not reconcile content, so a merge is
+/// only clean when this is empty.
+pub async fn merge_conflicts(
+    history: &dyn HistoryStore,
+    source: &str,
+    target: &str,
+) -> OnyxResult<Vec<Uuid>> {
+    let source_branch = history
+        .get_branch(source)
+        .await?
+        .ok_or_else(|| OnyxError::BranchNotFound(source.to_string()))?;
+
+    // Latest version of each entity on either branch since the fork.
+    let mut source_heads: HashMap<Uuid, VersionEntry> = HashMap::new();
+    let mut target_heads: HashMap<Uuid, VersionEntry> = HashMap::new();
+    for version_id in history.get_all_version_ids().await? {
+        let Some(entry) = history.get_version(&version_id).await? else {
+            continue;
+        };
+        // Merge entries are recorded against the nil entity.
+        if entry.entity_id.is_nil() || entry.timestamp < source_branch.created_at {
+            continue;
+        }
+        let heads = if entry.branch == source {
+            &mut source_heads
+        } else if entry.branch == target {
+            &mut target_heads
+        } else {
+            continue;
+        };
+        match heads.get(&entry.entity_id) {
+            Some(head) if head.timestamp >= entry.timestamp => {}
+            _ => {
+                heads.insert(entry.entity_id, entry);
+            }
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    for (entity_id, source_head) in &source_heads {
+        let Some(target_head) = target_heads.get(entity_id) else {
+            continue;
+        };
+        let ours = history
+            .get_content_at_version(entity_id, &source_head.version_id)
+            .await?;
+        let theirs = history
+            .get_content_at_version(entity_id, &target_head.version_id)
+            .await?;
+        if ours != theirs {
+            conflicts.push(*entity_id);
+        }
+    }
+    conflicts.sort();
+    Ok(conflicts)
 }
 
 // ---------------------------------------------------------------------------
@@ -240,8 +364,8 @@ impl HistoryStore for SurrealHistoryStore {
                 Diff::ContentChanged { patch, .. } => {
                     content = patch.clone();
                 }
-                Diff::MetadataChanged { .. } => {
-                    // Content unchanged for metadata-only changes
+                Diff::MetadataChanged { .. } | Diff::Deleted => {
+                    // Content unchanged for metadata-only changes and deletions
                 }
                 Diff::Composite(diffs) => {
                     for diff in diffs {
@@ -376,6 +500,36 @@ impl HistoryStore for SurrealHistoryStore {
         }))
     }
 
+    async fn list_branch_heads(&self) -> OnyxResult<Vec<(Uuid, String, VersionId)>> {
+        let mut response = self
+            .db
+            .query("SELECT entity_id, branch, version_id FROM branch_head")
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to list branch heads: {}", e)))?;
+
+        let records: Vec<serde_json::Value> = response.take(0).unwrap_or_default();
+        let heads = records
+            .iter()
+            .filter_map(|r| {
+                let entity_id = Uuid::parse_str(r.get("entity_id")?.as_str()?).ok()?;
+                let branch = r.get("branch")?.as_str()?.to_string();
+                let version_id = r.get("version_id")?.as_str()?.to_string();
+                Some((entity_id, branch, version_id))
+            })
+            .collect();
+
+        Ok(heads)
+    }
+
+    async fn remove_branch_head(&self, entity_id: &Uuid, branch: &str) -> OnyxResult<()> {
+        let branch_head_id = format!("{}:{}", entity_id, branch);
+        self.db
+            .delete("branch_head", &branch_head_id)
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to delete branch head: {}", e)))?;
+        Ok(())
+    }
+
     async fn create_branch(&self, name: &str, base_version: VersionId) -> OnyxResult<()> {
         // Verify base version exists
         let exists = self.get_version(&base_version).await?;
@@ -448,6 +602,28 @@ impl HistoryStore for SurrealHistoryStore {
         }
     }
 
+    async fn put_branch(&self, branch: Branch) -> OnyxResult<()> {
+        let record = BranchRecord {
+            record_id: branch.name.clone(),
+            name: branch.name.clone(),
+            head: branch.head,
+            base: branch.base,
+            created_at: branch.created_at,
+            merged_into: branch.merged_into,
+        };
+
+        self.db
+            .delete("branch", &branch.name)
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to replace branch: {}", e)))?;
+        self.db
+            .create_with_id("branch", &branch.name, record)
+            .await
+            .map_err(|e| OnyxError::Internal(format!("Failed to create branch: {}", e)))?;
+
+        Ok(())
+    }
+
     async fn merge_branch(&self, source: &str, target: &str) -> OnyxResult<VersionId> {
         let source_branch = self
             .get_branch(source)
@@ -553,6 +729,18 @@ impl InMemoryHistoryStore {
             branch_heads: RwLock::new(HashMap::new()),
         }
     }
+
+    /// Insert or replace a branch's metadata as-is, returning the previous one.
+    pub async fn replace_branch(&self, branch: Branch) -> Option<Branch> {
+        let mut branches = self.branches.write().await;
+        branches.insert(branch.name.clone(), branch)
+    }
+
+    /// Remove a branch's metadata, returning it if it existed.
+    pub async fn remove_branch(&self, name: &str) -> Option<Branch> {
+        let mut branches = self.branches.write().await;
+        branches.remove(name)
+    }
 }
 
 impl Default for InMemoryHistoryStore {
@@ -637,7 +825,7 @@ impl HistoryStore for InMemoryHistoryStore {
                 Diff::ContentChanged { patch, .. } => {
                     content = patch.clone();
                 }
-                Diff::MetadataChanged { .. } => {}
+                Diff::MetadataChanged { .. } | Diff::Deleted => {}
                 Diff::Composite(diffs) => {
                     for diff in diffs {
                         if let Diff::ContentChanged { patch, .. } = diff {
@@ -752,6 +940,11 @@ impl HistoryStore for InMemoryHistoryStore {
         branches.values().cloned().collect()
     }
 
+    async fn put_branch(&self, branch: Branch) -> OnyxResult<()> {
+        self.replace_branch(branch).await;
+        Ok(())
+    }
+
     async fn merge_branch(&self, source: &str, target: &str) -> OnyxResult<VersionId> {
         let mut branches = self.branches.write().await;
 
@@ -802,6 +995,67 @@ impl HistoryStore for InMemoryHistoryStore {
         let versions = self.versions.read().await;
         versions.len()
     }
+
+    async fn get_all_version_ids(&self) -> OnyxResult<Vec<VersionId>> {
+        let versions = self.versions.read().await;
+        Ok(versions.keys().cloned().collect())
+    }
+
+    async fn list_branch_heads(&self) -> OnyxResult<Vec<(Uuid, String, VersionId)>> {
+        let branch_heads = self.branch_heads.read().await;
+        Ok(branch_heads
+            .iter()
+            .map(|((entity_id, branch), version_id)| {
+                (*entity_id, branch.clone(), version_id.clone())
+            })
+            .collect())
+    }
+
+    async fn remove_branch_head(&self, entity_id: &Uuid, branch: &str) -> OnyxResult<()> {
+        let mut branch_heads = self.branch_heads.write().await;
+        branch_heads.remove(&(*entity_id, branch.to_string()));
+        Ok(())
+    }
+
+    async fn remove_version(
+        &self,
+        version_id: &VersionId,
+        previous_head: Option<VersionId>,
+    ) -> OnyxResult<()> {
+        let entry = self
+            .versions
+            .write()
+            .await
+            .remove(version_id)
+            .ok_or_else(|| OnyxError::VersionNotFound(version_id.clone()))?;
+
+        let mut entity_versions = self.entity_versions.write().await;
+        if let Some(ids) = entity_versions.get_mut(&entry.entity_id) {
+            ids.retain(|id| id != version_id);
+            if ids.is_empty() {
+                entity_versions.remove(&entry.entity_id);
+            }
+        }
+        drop(entity_versions);
+
+        let mut branch_heads = self.branch_heads.write().await;
+        let key = (entry.entity_id, entry.branch);
+        match previous_head {
+            Some(head) => {
+                branch_heads.insert(key, head);
+            }
+            None => {
+                branch_heads.remove(&key);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn delete_branch(&self, name: &str) -> OnyxResult<()> {
+        self.remove_branch(name).await;
+        Ok(())
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -889,4 +1143,66 @@ mod tests {
         let head = store.get_head(&entity_id, "main").await.unwrap();
         assert_eq!(head, Some(v1_id));
     }
+
+    #[tokio::test]
+    async fn test_diff_versions() {
+        let store = InMemoryHistoryStore::new();
+        let entity_id = Uuid::new_v4();
+
+        let v1 = VersionEntry::initial(entity_id, "fn total() {\n    0\n}");
+        let v1_id = store.record_version(v1).await.unwrap();
+        let v2 =
+            VersionEntry::content_change(entity_id, v1_id.clone(), "fn total() {\n    1\n}", 1, 1);
+        let v2_id = store.record_version(v2).await.unwrap();
+
+        let diff = diff_versions(&store, &entity_id, &v1_id, &v2_id)
+            .await
+            .unwrap();
+        assert!(diff.contains("-    0\n+    1\n"));
+        assert!(diff_versions(&store, &entity_id, &v2_id, &v2_id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_merge_conflicts() {
+        let store = InMemoryHistoryStore::new();
+        let (shared, only_feature) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let base = store
+            .record_version(VersionEntry::initial(shared, "fn total() {}"))
+            .await
+            .unwrap();
+        store.create_branch("feature", base.clone()).await.unwrap();
+        store.create_branch("release", base.clone()).await.unwrap();
+
+        let change = |entity_id, parent: &VersionId, content: &str, branch: &str| {
+            VersionEntry::content_change(entity_id, parent.clone(), content, 1, 1)
+                .with_branch(branch)
+        };
+        store
+            .record_version(
+                VersionEntry::initial(only_feature, "fn tax() {}").with_branch("feature"),
+            )
+            .await
+            .unwrap();
+        store
+            .record_version(change(shared, &base, "fn total() { 1 }", "feature"))
+            .await
+            .unwrap();
+        assert!(merge_conflicts(&store, "feature", "release")
+            .await
+            .unwrap()
+            .is_empty());
+
+        store
+            .record_version(change(shared, &base, "fn total() { 2 }", "release"))
+            .await
+            .unwrap();
+        assert_eq!(
+            merge_conflicts(&store, "feature", "release").await.unwrap(),
+            [shared]
+        );
+    }
 }
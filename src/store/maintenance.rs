@@ -0,0 +1,140 @@
+//! Storage maintenance: garbage collection of orphaned records.
+
+use std::fmt;
+use uuid::Uuid;
+
+use crate::error::OnyxResult;
+use crate::store::graph::GraphStore;
+use crate::store::history::HistoryStore;
+use crate::store::transaction::TransactionManager;
+use crate::store::vector::VectorStore;
+
+// ---------------------------------------------------------------------------
+// Garbage collection
+// ---------------------------------------------------------------------------
+
+/// Counts of records removed by [`gc`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    /// Embeddings whose node no longer exists.
+    pub orphaned_embeddings: usize,
+    /// Edges with a missing source or target node.
+    pub orphaned_edges: usize,
+    /// Branch heads pointing at versions that no longer exist.
+    pub dangling_branch_heads: usize,
+}
+
+impl GcReport {
+    /// Total number of records removed.
+    pub fn total(&self) -> usize {
+        self.orphaned_embeddings + self.orphaned_edges + self.dangling_branch_heads
+    }
+}
+
+impl fmt::Display for GcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Removed {} orphaned embeddings, {} orphaned edges, {} dangling branch heads",
+            self.orphaned_embeddings, self.orphaned_edges, self.dangling_branch_heads
+        )
+    }
+}
+
+/// Remove orphaned records from the stores behind `stores`.
+///
+/// See [`collect_garbage`] for what counts as orphaned.
+pub async fn gc(stores: &TransactionManager) -> OnyxResult<GcReport> {
+    collect_garbage(stores.graph(), stores.vector(), stores.history()).await
+}
+
+/// Remove embeddings whose node is gone, edges with a missing endpoint, and
+/// branch heads pointing at missing versions.
+///
+/// Soft-deleted nodes still exist, so their embeddings and edges are kept.
+/// Each record is re-checked and removed individually through the stores'
+/// own locking, so concurrent readers never see a half-collected store.
+pub async fn collect_garbage(
+    graph: &dyn GraphStore,
+    vector: &dyn VectorStore,
+    history: &dyn HistoryStore,
+) -> OnyxResult<GcReport> {
+    let mut report = GcReport::default();
+
+    for id in vector.get_all_embedding_ids().await? {
+        if !node_exists(graph, &id).await? {
+            vector.delete(&id).await?;
+            report.orphaned_embeddings += 1;
+        }
+    }
+
+    for id in graph.get_all_edge_ids().await? {
+        let Some(edge) = graph.get_edge(&id).await? else {
+            continue;
+        };
+        if !node_exists(graph, &edge.source_id).await?
+            || !node_exists(graph, &edge.target_id).await?
+        {
+            graph.remove_edge(&id).await?;
+            report.orphaned_edges += 1;
+        }
+    }
+
+    for (entity_id, branch, version_id) in history.list_branch_heads().await? {
+        if history.get_version(&version_id).await?.is_none() {
+            history.remove_branch_head(&entity_id, &branch).await?;
+            report.dangling_branch_heads += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+async fn node_exists(graph: &dyn GraphStore, id: &Uuid) -> OnyxResult<bool> {
+    Ok(graph.get_node_including_deleted(id).await?.is_some())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::store::transaction::TransactionOp;
+
+    #[tokio::test]
+    async fn test_gc_collects_embedding_of_removed_node() {
+        let mut stores = TransactionManager::new();
+        let kept = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "kept", "");
+        let removed = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "gone", "");
+        let (kept_id, removed_id) = (kept.id, removed.id);
+
+        stores
+            .execute_batch(vec![
+                TransactionOp::InsertNode(kept),
+                TransactionOp::InsertNode(removed),
+                TransactionOp::InsertEmbedding {
+                    id: kept_id,
+                    embedding: vec![1.0, 0.0],
+                },
+                TransactionOp::InsertEmbedding {
+                    id: removed_id,
+                    embedding: vec![0.0, 1.0],
+                },
+            ])
+            .unwrap();
+
+        // Bypass the transaction manager, leaving the embedding behind.
+        stores.graph_store.remove_node(&removed_id).await.unwrap();
+
+        let report = gc(&stores).await.unwrap();
+        assert_eq!(report.orphaned_embeddings, 1);
+        assert_eq!(report.total(), 1);
+        assert!(stores.vector_store.get(&removed_id).await.unwrap().is_none());
+        assert!(stores.vector_store.get(&kept_id).await.unwrap().is_some());
+
+        assert_eq!(gc(&stores).await.unwrap(), GcReport::default());
+    }
+}
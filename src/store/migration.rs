@@ -1,13 +1,17 @@
 //! Storage migration utilities for Onyx.
 //!
 //! This module provides tools for migrating data between different storage backends,
-//! particularly from SurrealDB (in-memory) to RocksDB (persistent).
+//! particularly between SurrealDB and RocksDB (persistent), in either direction.
 
+use crate::db::OnyxDatabase;
 use crate::error::{OnyxError, OnyxResult};
-use crate::model::{Node, Edge, Embedding, Version, VersionChain};
+use crate::model::VersionId;
+use crate::store::export::parents_first;
 use crate::store::{GraphStore, HistoryStore, VectorStore};
 use crate::store::{SurrealGraphStore, SurrealHistoryStore, SurrealVectorStore};
-use std::path::Path;
+use crate::store::transaction::TransactionManager;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -25,6 +29,10 @@ pub struct MigrationConfig {
     pub max_retries: usize,
     /// Progress reporting interval
     pub progress_interval: usize,
+    /// Skip records already recorded in the checkpoint of an interrupted run
+    pub resume: bool,
+    /// Where to keep the checkpoint; defaults to `<target>.checkpoint.json`
+    pub checkpoint_path: Option<PathBuf>,
 }
 
 impl Default for MigrationConfig {
@@ -34,22 +42,47 @@ impl Default for MigrationConfig {
             verify_after: true,
             max_retries: 3,
             progress_interval: 100,
+            resume: false,
+            checkpoint_path: None,
         }
     }
 }
 
 /// Migration statistics
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
 pub struct MigrationStats {
     pub nodes_migrated: usize,
     pub edges_migrated: usize,
     pub embeddings_migrated: usize,
     pub versions_migrated: usize,
+    /// Records skipped because a resumed run had already migrated them
+    pub nodes_skipped: usize,
+    pub edges_skipped: usize,
+    pub embeddings_skipped: usize,
+    pub versions_skipped: usize,
     pub errors: usize,
     pub start_time: std::time::Instant,
     pub end_time: Option<std::time::Instant>,
 }
 
+impl Default for MigrationStats {
+    fn default() -> Self {
+        Self {
+            nodes_migrated: 0,
+            edges_migrated: 0,
+            embeddings_migrated: 0,
+            versions_migrated: 0,
+            nodes_skipped: 0,
+            edges_skipped: 0,
+            embeddings_skipped: 0,
+            versions_skipped: 0,
+            errors: 0,
+            start_time: std::time::Instant::now(),
+            end_time: None,
+        }
+    }
+}
+
 impl MigrationStats {
     pub fn duration(&self) -> Option<std::time::Duration> {
         self.end_time.map(|end| end.duration_since(self.start_time))
@@ -58,12 +91,118 @@ impl MigrationStats {
     pub fn total_records(&self) -> usize {
         self.nodes_migrated + self.edges_migrated + self.embeddings_migrated + self.versions_migrated
     }
+
+    pub fn total_skipped(&self) -> usize {
+        self.nodes_skipped + self.edges_skipped + self.embeddings_skipped + self.versions_skipped
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Checkpoint
+// ---------------------------------------------------------------------------
+
+/// The last migrated id per entity kind.
+///
+/// Records are migrated in ascending id order, so everything up to and
+/// including these ids is already in the target.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationCheckpoint {
+    pub last_node: Option<Uuid>,
+    pub last_edge: Option<Uuid>,
+    pub last_embedding: Option<Uuid>,
+    pub last_version: Option<VersionId>,
+}
+
+impl MigrationCheckpoint {
+    /// Default checkpoint location for a migration into `target`.
+    pub fn default_path(target: &Path) -> PathBuf {
+        let mut path = target.as_os_str().to_owned();
+        path.push(".checkpoint.json");
+        PathBuf::from(path)
+    }
+
+    /// Load a checkpoint, or an empty one if `path` doesn't exist.
+    pub fn load(path: &Path) -> OnyxResult<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    pub fn save(&self, path: &Path) -> OnyxResult<()> {
+        std::fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Split off the ids up to and including the checkpointed `last` id.
+///
+/// `ids` must be in the same deterministic order on every run. Returns the
+/// number of skipped ids and the ids still to migrate.
+fn pending_after<T: PartialEq>(mut ids: Vec<T>, last: Option<&T>) -> (usize, Vec<T>) {
+    let skipped = last
+        .and_then(|last| ids.iter().position(|id| id == last))
+        .map_or(0, |index| index + 1);
+    let pending = ids.split_off(skipped);
+    (skipped, pending)
+}
+
+/// Sorted ids, so that checkpoints refer to a stable order.
+fn sorted(mut ids: Vec<Uuid>) -> Vec<Uuid> {
+    ids.sort();
+    ids
+}
+
+/// One side of a migration: a graph, vector and history store.
+pub struct MigrationStores<'a> {
+    pub graph: &'a dyn GraphStore,
+    pub vector: &'a dyn VectorStore,
+    pub history: &'a dyn HistoryStore,
+}
+
+impl<'a> MigrationStores<'a> {
+    pub fn new(
+        graph: &'a dyn GraphStore,
+        vector: &'a dyn VectorStore,
+        history: &'a dyn HistoryStore,
+    ) -> Self {
+        Self {
+            graph,
+            vector,
+            history,
+        }
+    }
+}
+
+impl<'a> From<&'a TransactionManager> for MigrationStores<'a> {
+    /// The stores currently backing `stores`, database or in-memory.
+    fn from(stores: &'a TransactionManager) -> Self {
+        Self::new(stores.graph(), stores.vector(), stores.history())
+    }
+}
+
+/// Migrate everything from `source` into `target`, whatever their backends.
+///
+/// Checkpoints only if [`MigrationConfig::checkpoint_path`] is set.
+pub async fn migrate(
+    source: MigrationStores<'_>,
+    target: MigrationStores<'_>,
+    config: MigrationConfig,
+) -> OnyxResult<MigrationStats> {
+    let mut migrator = StorageMigrator::new(config);
+    if let Some(checkpoint_path) = migrator.config.checkpoint_path.clone() {
+        migrator.open_checkpoint(checkpoint_path)?;
+    }
+    migrator.migrate_all(&source, &target).await
 }
 
 /// Storage migrator
 pub struct StorageMigrator {
     config: MigrationConfig,
     stats: MigrationStats,
+    checkpoint: MigrationCheckpoint,
+    checkpoint_path: Option<PathBuf>,
 }
 
 impl StorageMigrator {
@@ -71,47 +210,152 @@ impl StorageMigrator {
         Self {
             config,
             stats: MigrationStats::default(),
+            checkpoint: MigrationCheckpoint::default(),
+            checkpoint_path: None,
+        }
+    }
+
+    /// Start checkpointing to `path`, picking up its progress when resuming.
+    fn open_checkpoint(&mut self, path: PathBuf) -> OnyxResult<()> {
+        self.checkpoint = if self.config.resume {
+            MigrationCheckpoint::load(&path)?
+        } else {
+            MigrationCheckpoint::default()
+        };
+        self.checkpoint_path = Some(path);
+        Ok(())
+    }
+
+    fn save_checkpoint(&self) -> OnyxResult<()> {
+        match &self.checkpoint_path {
+            Some(path) => self.checkpoint.save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// Drop the checkpoint once every entity kind has been migrated.
+    fn finish_checkpoint(&mut self) -> OnyxResult<()> {
+        if let Some(path) = self.checkpoint_path.take() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
         }
+        Ok(())
     }
 
-    /// Migrate from SurrealDB to RocksDB
+    /// Migrate the contents of `db` to a RocksDB database at `rocks_path`
     #[cfg(feature = "rocksdb-storage")]
     pub async fn migrate_surreal_to_rocks<P: AsRef<Path>>(
         &mut self,
+        db: Arc<OnyxDatabase>,
         rocks_path: P,
     ) -> OnyxResult<MigrationStats> {
         self.stats.start_time = std::time::Instant::now();
 
         // Initialize source stores (SurrealDB)
-        let source_graph = Arc::new(SurrealGraphStore::new().await?);
-        let source_vector = Arc::new(SurrealVectorStore::new().await?);
-        let source_history = Arc::new(SurrealHistoryStore::new().await?);
+        let source_graph = SurrealGraphStore::new(db.clone());
+        let source_vector = SurrealVectorStore::new(db.clone());
+        let source_history = SurrealHistoryStore::new(db);
 
         // Initialize target stores (RocksDB)
-        let db = open_db(rocks_path)?;
-        let target_graph = Arc::new(RocksGraphStore::new(db.clone())?);
-        let target_vector = Arc::new(RocksVectorStore::new(db.clone())?);
-        let target_history = Arc::new(RocksHistoryStore::new(db)?);
+        let rocks = open_db(rocks_path.as_ref())?;
+        let dimension = source_dimension(&source_vector).await?;
+        let target_graph = RocksGraphStore::new(rocks.clone());
+        let target_vector = RocksVectorStore::new(rocks.clone(), dimension);
+        let target_history = RocksHistoryStore::new(rocks);
+
+        let checkpoint_path = self
+            .config
+            .checkpoint_path
+            .clone()
+            .unwrap_or_else(|| MigrationCheckpoint::default_path(rocks_path.as_ref()));
+        self.open_checkpoint(checkpoint_path)?;
+
+        tracing::info!("starting migration from SurrealDB to RocksDB");
+
+        let source = MigrationStores {
+            graph: &source_graph,
+            vector: &source_vector,
+            history: &source_history,
+        };
+        let target = MigrationStores {
+            graph: &target_graph,
+            vector: &target_vector,
+            history: &target_history,
+        };
+        self.migrate_all(&source, &target).await
+    }
+
+    /// Migrate a RocksDB database at `rocks_path` back into `db`
+    ///
+    /// Checkpoints only if [`MigrationConfig::checkpoint_path`] is set.
+    #[cfg(feature = "rocksdb-storage")]
+    pub async fn migrate_rocks_to_surreal<P: AsRef<Path>>(
+        &mut self,
+        rocks_path: P,
+        db: Arc<OnyxDatabase>,
+    ) -> OnyxResult<MigrationStats> {
+        self.stats.start_time = std::time::Instant::now();
+
+        // Initialize source stores (RocksDB); the dimension only matters for writes
+        let rocks = open_db(rocks_path.as_ref())?;
+        let source_graph = RocksGraphStore::new(rocks.clone());
+        let source_vector = RocksVectorStore::new(rocks.clone(), 0);
+        let source_history = RocksHistoryStore::new(rocks);
 
-        println!("Starting migration from SurrealDB to RocksDB...");
+        // Initialize target stores (SurrealDB)
+        let target_graph = SurrealGraphStore::new(db.clone());
+        let target_vector = SurrealVectorStore::new(db.clone());
+        let target_history = SurrealHistoryStore::new(db);
+
+        if let Some(checkpoint_path) = self.config.checkpoint_path.clone() {
+            self.open_checkpoint(checkpoint_path)?;
+        }
 
+        tracing::info!("starting migration from RocksDB to SurrealDB");
+
+        let source = MigrationStores {
+            graph: &source_graph,
+            vector: &source_vector,
+            history: &source_history,
+        };
+        let target = MigrationStores {
+            graph: &target_graph,
+            vector: &target_vector,
+            history: &target_history,
+        };
+        self.migrate_all(&source, &target).await
+    }
+
+    /// Migrate every entity kind from `source` to `target`
+    #[tracing::instrument(name = "migrate", skip_all)]
+    async fn migrate_all(
+        &mut self,
+        source: &MigrationStores<'_>,
+        target: &MigrationStores<'_>,
+    ) -> OnyxResult<MigrationStats> {
         // Migrate nodes
-        self.migrate_nodes(&source_graph, &target_graph).await?;
+        self.migrate_nodes(source.graph, target.graph).await?;
 
         // Migrate edges
-        self.migrate_edges(&source_graph, &target_graph).await?;
+        self.migrate_edges(source.graph, target.graph).await?;
 
         // Migrate embeddings
-        self.migrate_embeddings(&source_vector, &target_vector).await?;
+        self.migrate_embeddings(source.vector, target.vector).await?;
 
         // Migrate versions
-        self.migrate_versions(&source_history, &target_history).await?;
+        self.migrate_versions(source.history, target.history).await?;
 
         self.stats.end_time = Some(std::time::Instant::now());
 
+        // Only a clean run makes the checkpoint obsolete
+        if self.stats.errors == 0 {
+            self.finish_checkpoint()?;
+        }
+
         // Verify migration if requested
         if self.config.verify_after {
-            self.verify_migration(&source_graph, &target_graph, &source_vector, &target_vector).await?;
+            self.verify_migration(source, target).await?;
         }
 
         Ok(self.stats.clone())
@@ -120,57 +364,77 @@ impl StorageMigrator {
     /// Migrate all nodes from source to target
     async fn migrate_nodes(
         &mut self,
-        source: &SurrealGraphStore,
-        target: &RocksGraphStore,
+        source: &dyn GraphStore,
+        target: &dyn GraphStore,
     ) -> OnyxResult<()> {
-        println!("Migrating nodes...");
+        tracing::info!("migrating nodes");
         
         // Get all node IDs from source
-        let node_ids = source.get_all_node_ids().await?;
+        let (skipped, node_ids) =
+            pending_after(sorted(source.get_all_node_ids().await?), self.checkpoint.last_node.as_ref());
         let total_nodes = node_ids.len();
-        
-        println!("Found {} nodes to migrate", total_nodes);
+        self.stats.nodes_skipped += skipped;
+
+        tracing::info!(pending = total_nodes, skipped, "found nodes to migrate");
+        let mut checkpointing = true;
 
         // Process in batches
         for (batch_idx, batch) in node_ids.chunks(self.config.batch_size).enumerate() {
             let mut batch_success = 0;
             let mut batch_errors = 0;
 
-            for &node_id in batch {
-                match self.migrate_single_node(source, target, node_id).await {
-                    Ok(_) => batch_success += 1,
+            for node_id in batch {
+                match self.migrate_single_node(source, target, *node_id).await {
+                    Ok(_) => {
+                        batch_success += 1;
+                        // Never checkpoint past a record that failed
+                        if checkpointing {
+                            self.checkpoint.last_node = Some(*node_id);
+                        }
+                    }
                     Err(e) => {
-                        eprintln!("Error migrating node {}: {}", node_id, e);
+                        tracing::warn!(node_id = %node_id, error = %e, "failed to migrate node");
                         batch_errors += 1;
                         self.stats.errors += 1;
+                        checkpointing = false;
                     }
                 }
 
                 // Progress reporting
                 if (batch_idx * self.config.batch_size + batch_success + batch_errors) % self.config.progress_interval == 0 {
                     let progress = (batch_idx * self.config.batch_size + batch_success + batch_errors) as f32 / total_nodes as f32 * 100.0;
-                    println!("Nodes migration progress: {:.1}% ({}/{})", 
-                            progress, batch_idx * self.config.batch_size + batch_success + batch_errors, total_nodes);
+                    tracing::info!(
+                        done = batch_idx * self.config.batch_size + batch_success + batch_errors,
+                        total = total_nodes,
+                        "nodes migration progress: {:.1}%",
+                        progress
+                    );
                 }
             }
 
             self.stats.nodes_migrated += batch_success;
-            println!("Batch {} complete: {} nodes migrated, {} errors", 
-                    batch_idx + 1, batch_success, batch_errors);
+            self.save_checkpoint()?;
+            tracing::debug!(
+                batch = batch_idx + 1,
+                migrated = batch_success,
+                errors = batch_errors,
+                "nodes batch complete"
+            );
         }
 
-        println!("Node migration complete: {} nodes migrated", self.stats.nodes_migrated);
+        tracing::info!(migrated = self.stats.nodes_migrated, "node migration complete");
         Ok(())
     }
 
     /// Migrate a single node
     async fn migrate_single_node(
         &self,
-        source: &SurrealGraphStore,
-        target: &RocksGraphStore,
+        source: &dyn GraphStore,
+        target: &dyn GraphStore,
         node_id: Uuid,
     ) -> OnyxResult<()> {
-        let node = source.get_node(node_id).await?;
+        // Tombstones are listed by `get_all_node_ids`, so carry them over too
+        let node = source.get_node_including_deleted(&node_id).await?;
         if let Some(node) = node {
             target.insert_node(node).await?;
         }
@@ -180,57 +444,76 @@ impl StorageMigrator {
     /// Migrate all edges from source to target
     async fn migrate_edges(
         &mut self,
-        source: &SurrealGraphStore,
-        target: &RocksGraphStore,
+        source: &dyn GraphStore,
+        target: &dyn GraphStore,
     ) -> OnyxResult<()> {
-        println!("Migrating edges...");
+        tracing::info!("migrating edges");
         
         // Get all edge IDs from source
-        let edge_ids = source.get_all_edge_ids().await?;
+        let (skipped, edge_ids) =
+            pending_after(sorted(source.get_all_edge_ids().await?), self.checkpoint.last_edge.as_ref());
         let total_edges = edge_ids.len();
-        
-        println!("Found {} edges to migrate", total_edges);
+        self.stats.edges_skipped += skipped;
+
+        tracing::info!(pending = total_edges, skipped, "found edges to migrate");
+        let mut checkpointing = true;
 
         // Process in batches
         for (batch_idx, batch) in edge_ids.chunks(self.config.batch_size).enumerate() {
             let mut batch_success = 0;
             let mut batch_errors = 0;
 
-            for &edge_id in batch {
-                match self.migrate_single_edge(source, target, edge_id).await {
-                    Ok(_) => batch_success += 1,
+            for edge_id in batch {
+                match self.migrate_single_edge(source, target, *edge_id).await {
+                    Ok(_) => {
+                        batch_success += 1;
+                        // Never checkpoint past a record that failed
+                        if checkpointing {
+                            self.checkpoint.last_edge = Some(*edge_id);
+                        }
+                    }
                     Err(e) => {
-                        eprintln!("Error migrating edge {}: {}", edge_id, e);
+                        tracing::warn!(edge_id = %edge_id, error = %e, "failed to migrate edge");
                         batch_errors += 1;
                         self.stats.errors += 1;
+                        checkpointing = false;
                     }
                 }
 
                 // Progress reporting
                 if (batch_idx * self.config.batch_size + batch_success + batch_errors) % self.config.progress_interval == 0 {
                     let progress = (batch_idx * self.config.batch_size + batch_success + batch_errors) as f32 / total_edges as f32 * 100.0;
-                    println!("Edges migration progress: {:.1}% ({}/{})", 
-                            progress, batch_idx * self.config.batch_size + batch_success + batch_errors, total_edges);
+                    tracing::info!(
+                        done = batch_idx * self.config.batch_size + batch_success + batch_errors,
+                        total = total_edges,
+                        "edges migration progress: {:.1}%",
+                        progress
+                    );
                 }
             }
 
             self.stats.edges_migrated += batch_success;
-            println!("Batch {} complete: {} edges migrated, {} errors", 
-                    batch_idx + 1, batch_success, batch_errors);
+            self.save_checkpoint()?;
+            tracing::debug!(
+                batch = batch_idx + 1,
+                migrated = batch_success,
+                errors = batch_errors,
+                "edges batch complete"
+            );
         }
 
-        println!("Edge migration complete: {} edges migrated", self.stats.edges_migrated);
+        tracing::info!(migrated = self.stats.edges_migrated, "edge migration complete");
         Ok(())
     }
 
     /// Migrate a single edge
     async fn migrate_single_edge(
         &self,
-        source: &SurrealGraphStore,
-        target: &RocksGraphStore,
+        source: &dyn GraphStore,
+        target: &dyn GraphStore,
         edge_id: Uuid,
     ) -> OnyxResult<()> {
-        let edge = source.get_edge(edge_id).await?;
+        let edge = source.get_edge(&edge_id).await?;
         if let Some(edge) = edge {
             target.insert_edge(edge).await?;
         }
@@ -240,59 +523,77 @@ impl StorageMigrator {
     /// Migrate all embeddings from source to target
     async fn migrate_embeddings(
         &mut self,
-        source: &SurrealVectorStore,
-        target: &RocksVectorStore,
+        source: &dyn VectorStore,
+        target: &dyn VectorStore,
     ) -> OnyxResult<()> {
-        println!("Migrating embeddings...");
+        tracing::info!("migrating embeddings");
         
         // Get all embedding IDs from source
-        let embedding_ids = source.get_all_embedding_ids().await?;
+        let (skipped, embedding_ids) =
+            pending_after(sorted(source.get_all_embedding_ids().await?), self.checkpoint.last_embedding.as_ref());
         let total_embeddings = embedding_ids.len();
-        
-        println!("Found {} embeddings to migrate", total_embeddings);
+        self.stats.embeddings_skipped += skipped;
+
+        tracing::info!(pending = total_embeddings, skipped, "found embeddings to migrate");
+        let mut checkpointing = true;
 
         // Process in batches
         for (batch_idx, batch) in embedding_ids.chunks(self.config.batch_size).enumerate() {
             let mut batch_success = 0;
             let mut batch_errors = 0;
 
-            for &embedding_id in batch {
-                match self.migrate_single_embedding(source, target, embedding_id).await {
-                    Ok(_) => batch_success += 1,
+            for embedding_id in batch {
+                match self.migrate_single_embedding(source, target, *embedding_id).await {
+                    Ok(_) => {
+                        batch_success += 1;
+                        // Never checkpoint past a record that failed
+                        if checkpointing {
+                            self.checkpoint.last_embedding = Some(*embedding_id);
+                        }
+                    }
                     Err(e) => {
-                        eprintln!("Error migrating embedding {}: {}", embedding_id, e);
+                        tracing::warn!(embedding_id = %embedding_id, error = %e, "failed to migrate embedding");
                         batch_errors += 1;
                         self.stats.errors += 1;
+                        checkpointing = false;
                     }
                 }
 
                 // Progress reporting
                 if (batch_idx * self.config.batch_size + batch_success + batch_errors) % self.config.progress_interval == 0 {
                     let progress = (batch_idx * self.config.batch_size + batch_success + batch_errors) as f32 / total_embeddings as f32 * 100.0;
-                    println!("Embeddings migration progress: {:.1}% ({}/{})", 
-                            progress, batch_idx * self.config.batch_size + batch_success + batch_errors, total_embeddings);
+                    tracing::info!(
+                        done = batch_idx * self.config.batch_size + batch_success + batch_errors,
+                        total = total_embeddings,
+                        "embeddings migration progress: {:.1}%",
+                        progress
+                    );
                 }
             }
 
             self.stats.embeddings_migrated += batch_success;
-            println!("Batch {} complete: {} embeddings migrated, {} errors", 
-                    batch_idx + 1, batch_success, batch_errors);
+            self.save_checkpoint()?;
+            tracing::debug!(
+                batch = batch_idx + 1,
+                migrated = batch_success,
+                errors = batch_errors,
+                "embeddings batch complete"
+            );
         }
 
-        println!("Embedding migration complete: {} embeddings migrated", self.stats.embeddings_migrated);
+        tracing::info!(migrated = self.stats.embeddings_migrated, "embedding migration complete");
         Ok(())
     }
 
     /// Migrate a single embedding
     async fn migrate_single_embedding(
         &self,
-        source: &SurrealVectorStore,
-        target: &RocksVectorStore,
+        source: &dyn VectorStore,
+        target: &dyn VectorStore,
         embedding_id: Uuid,
     ) -> OnyxResult<()> {
-        let embedding = source.get_embedding(embedding_id).await?;
-        if let Some(embedding) = embedding {
-            target.insert_embedding(embedding).await?;
+        if let Some(vector) = source.get(&embedding_id).await? {
+            target.insert(embedding_id, vector).await?;
         }
         Ok(())
     }
@@ -300,57 +601,82 @@ impl StorageMigrator {
     /// Migrate all versions from source to target
     async fn migrate_versions(
         &mut self,
-        source: &SurrealHistoryStore,
-        target: &RocksHistoryStore,
+        source: &dyn HistoryStore,
+        target: &dyn HistoryStore,
     ) -> OnyxResult<()> {
-        println!("Migrating versions...");
+        tracing::info!("migrating versions");
         
-        // Get all version IDs from source
-        let version_ids = source.get_all_version_ids().await?;
+        // Get all version IDs from source, parents first so chains can be rebuilt
+        let mut versions = Vec::new();
+        for version_id in source.get_all_version_ids().await? {
+            if let Some(version) = source.get_version(&version_id).await? {
+                versions.push(version);
+            }
+        }
+        let ordered: Vec<VersionId> = parents_first(versions).into_iter().map(|v| v.version_id).collect();
+        let (skipped, version_ids) = pending_after(ordered, self.checkpoint.last_version.as_ref());
         let total_versions = version_ids.len();
-        
-        println!("Found {} versions to migrate", total_versions);
+        self.stats.versions_skipped += skipped;
+
+        tracing::info!(pending = total_versions, skipped, "found versions to migrate");
+        let mut checkpointing = true;
 
         // Process in batches
         for (batch_idx, batch) in version_ids.chunks(self.config.batch_size).enumerate() {
             let mut batch_success = 0;
             let mut batch_errors = 0;
 
-            for &version_id in batch {
-                match self.migrate_single_version(source, target, version_id).await {
-                    Ok(_) => batch_success += 1,
+            for version_id in batch {
+                match self.migrate_single_version(source, target, version_id.clone()).await {
+                    Ok(_) => {
+                        batch_success += 1;
+                        // Never checkpoint past a record that failed
+                        if checkpointing {
+                            self.checkpoint.last_version = Some(version_id.clone());
+                        }
+                    }
                     Err(e) => {
-                        eprintln!("Error migrating version {}: {}", version_id, e);
+                        tracing::warn!(version_id = %version_id, error = %e, "failed to migrate version");
                         batch_errors += 1;
                         self.stats.errors += 1;
+                        checkpointing = false;
                     }
                 }
 
                 // Progress reporting
                 if (batch_idx * self.config.batch_size + batch_success + batch_errors) % self.config.progress_interval == 0 {
                     let progress = (batch_idx * self.config.batch_size + batch_success + batch_errors) as f32 / total_versions as f32 * 100.0;
-                    println!("Versions migration progress: {:.1}% ({}/{})", 
-                            progress, batch_idx * self.config.batch_size + batch_success + batch_errors, total_versions);
+                    tracing::info!(
+                        done = batch_idx * self.config.batch_size + batch_success + batch_errors,
+                        total = total_versions,
+                        "versions migration progress: {:.1}%",
+                        progress
+                    );
                 }
             }
 
             self.stats.versions_migrated += batch_success;
-            println!("Batch {} complete: {} versions migrated, {} errors", 
-                    batch_idx + 1, batch_success, batch_errors);
+            self.save_checkpoint()?;
+            tracing::debug!(
+                batch = batch_idx + 1,
+                migrated = batch_success,
+                errors = batch_errors,
+                "versions batch complete"
+            );
         }
 
-        println!("Version migration complete: {} versions migrated", self.stats.versions_migrated);
+        tracing::info!(migrated = self.stats.versions_migrated, "version migration complete");
         Ok(())
     }
 
     /// Migrate a single version
     async fn migrate_single_version(
         &self,
-        source: &SurrealHistoryStore,
-        target: &RocksHistoryStore,
-        version_id: Uuid,
+        source: &dyn HistoryStore,
+        target: &dyn HistoryStore,
+        version_id: VersionId,
     ) -> OnyxResult<()> {
-        let version = source.get_version(version_id).await?;
+        let version = source.get_version(&version_id).await?;
         if let Some(version) = version {
             target.create_version(version).await?;
         }
@@ -358,15 +684,14 @@ impl StorageMigrator {
     }
 
     /// Verify migration integrity
-    #[cfg(feature = "rocksdb-storage")]
     async fn verify_migration(
         &self,
-        source_graph: &SurrealGraphStore,
-        target_graph: &RocksGraphStore,
-        source_vector: &SurrealVectorStore,
-        target_vector: &RocksVectorStore,
+        source: &MigrationStores<'_>,
+        target: &MigrationStores<'_>,
     ) -> OnyxResult<()> {
-        println!("Verifying migration integrity...");
+        tracing::info!("verifying migration integrity");
+        let (source_graph, target_graph) = (source.graph, target.graph);
+        let (source_vector, target_vector) = (source.vector, target.vector);
 
         // Verify node counts
         let source_node_count = source_graph.get_all_node_ids().await?.len();
@@ -401,26 +726,59 @@ impl StorageMigrator {
             )));
         }
 
-        println!("✓ Migration verification successful!");
-        println!("  Nodes: {}", source_node_count);
-        println!("  Edges: {}", source_edge_count);
-        println!("  Embeddings: {}", source_embedding_count);
+        // Verify version counts
+        let source_version_count = source.history.get_all_version_ids().await?.len();
+        let target_version_count = target.history.get_all_version_ids().await?.len();
+
+        if source_version_count != target_version_count {
+            return Err(OnyxError::Internal(format!(
+                "Version count mismatch: source={}, target={}",
+                source_version_count, target_version_count
+            )));
+        }
+
+        tracing::info!(
+            nodes = source_node_count,
+            edges = source_edge_count,
+            embeddings = source_embedding_count,
+            versions = source_version_count,
+            "migration verified"
+        );
 
         Ok(())
     }
 }
 
-/// CLI command for storage migration
-pub async fn run_migration(rocks_path: &str) -> OnyxResult<()> {
+/// Embedding dimension of `source`, taken from its first embedding if the
+/// store doesn't enforce one. An empty store yields 0.
+async fn source_dimension(source: &dyn VectorStore) -> OnyxResult<usize> {
+    if let Some(dimension) = source.dimensions() {
+        return Ok(dimension);
+    }
+    for id in source.get_all_embedding_ids().await? {
+        if let Some(vector) = source.get(&id).await? {
+            return Ok(vector.len());
+        }
+    }
+    Ok(0)
+}
+
+/// CLI command for storage migration from the SurrealDB at `source`, which
+/// is only used in the progress output.
+pub async fn run_migration(
+    db: Arc<OnyxDatabase>,
+    source: &str,
+    rocks_path: &str,
+) -> OnyxResult<()> {
     let config = MigrationConfig::default();
     let mut migrator = StorageMigrator::new(config);
 
     println!("Starting storage migration...");
-    println!("Source: SurrealDB (in-memory)");
+    println!("Source: SurrealDB at {}", source);
     println!("Target: RocksDB (persistent) at {}", rocks_path);
     println!();
 
-    let stats = migrator.migrate_surreal_to_rocks(rocks_path).await?;
+    let stats = migrator.migrate_surreal_to_rocks(db, rocks_path).await?;
 
     println!();
     println!("Migration completed!");
@@ -438,4 +796,220 @@ pub async fn run_migration(rocks_path: &str) -> OnyxResult<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+#[cfg(all(test, feature = "rocksdb-storage"))]
+mod tests {
+    use super::*;
+    use crate::config::LimitsConfig;
+    use crate::ingest::demo::build_synthetic_codebase;
+    use crate::ingest::ingest_codebase;
+    use crate::model::edge::{Edge, EdgeType};
+    use crate::model::embedding::BagOfWordsEmbedder;
+    use crate::model::node::{CodeEntityKind, Node, NodeType};
+    use crate::model::version::VersionEntry;
+
+    #[tokio::test]
+    async fn test_migrate_surreal_to_rocks_end_to_end() {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let graph = SurrealGraphStore::new(db.clone());
+        let vector = SurrealVectorStore::new(db.clone());
+        let mut embeddings = Vec::new();
+        for i in 0..3 {
+            let node = Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                format!("f{}", i),
+                "",
+            );
+            let embedding = vec![i as f32 + 0.25, 1.0, -0.5];
+            vector.insert(node.id, embedding.clone()).await.unwrap();
+            embeddings.push((node.id, embedding));
+            graph.insert_node(node).await.unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut migrator = StorageMigrator::new(MigrationConfig::default());
+        let stats = migrator
+            .migrate_surreal_to_rocks(db, dir.path().join("rocks"))
+            .await
+            .unwrap();
+
+        assert_eq!(stats.nodes_migrated, 3);
+        assert_eq!(stats.embeddings_migrated, 3);
+        assert_eq!(stats.errors, 0);
+        assert!(!MigrationCheckpoint::default_path(&dir.path().join("rocks")).exists());
+
+        let target = RocksVectorStore::new(open_db(dir.path().join("rocks")).unwrap(), 3);
+        for (id, embedding) in &embeddings {
+            assert_eq!(target.get(id).await.unwrap().as_ref(), Some(embedding));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrate_carries_soft_deleted_nodes() {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let graph = SurrealGraphStore::new(db.clone());
+        let live = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "live", "");
+        let gone = Node::new(NodeType::CodeEntity(CodeEntityKind::Function), "gone", "");
+        let (live_id, gone_id) = (live.id, gone.id);
+        graph.insert_node(live).await.unwrap();
+        graph.insert_node(gone).await.unwrap();
+        graph.soft_delete_node(&gone_id).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let stats = StorageMigrator::new(MigrationConfig::default())
+            .migrate_surreal_to_rocks(db, dir.path().join("rocks"))
+            .await
+            .unwrap();
+
+        assert_eq!(stats.nodes_migrated, 2);
+        assert_eq!(stats.errors, 0);
+        let target = RocksGraphStore::new(open_db(dir.path().join("rocks")).unwrap());
+        assert!(target.get_node(&live_id).await.unwrap().is_some());
+        assert!(target.get_node(&gone_id).await.unwrap().is_none());
+        let tombstone = target
+            .get_node_including_deleted(&gone_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(tombstone.deleted_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_surreal_rocks_surreal_round_trip() {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let graph = SurrealGraphStore::new(db.clone());
+        let vector = SurrealVectorStore::new(db.clone());
+        let history = SurrealHistoryStore::new(db.clone());
+
+        let mut ids = Vec::new();
+        let mut embeddings = Vec::new();
+        for i in 0..4 {
+            let node = Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                format!("f{}", i),
+                "fn f() {}",
+            );
+            ids.push(node.id);
+            let embedding = vec![i as f32 + 0.5, -1.0];
+            vector.insert(node.id, embedding.clone()).await.unwrap();
+            embeddings.push(embedding);
+            let initial = VersionEntry::initial(node.id, "fn f() {}");
+            let change =
+                VersionEntry::content_change(node.id, initial.version_id.clone(), "+x", 1, 0);
+            history.record_version(initial).await.unwrap();
+            history.record_version(change).await.unwrap();
+            graph.insert_node(node).await.unwrap();
+        }
+        graph
+            .insert_edge(Edge::new(EdgeType::Calls, ids[0], ids[1]))
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let rocks_path = dir.path().join("rocks");
+        StorageMigrator::new(MigrationConfig::default())
+            .migrate_surreal_to_rocks(db, &rocks_path)
+            .await
+            .unwrap();
+
+        let restored = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let stats = StorageMigrator::new(MigrationConfig::default())
+            .migrate_rocks_to_surreal(&rocks_path, restored.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(stats.nodes_migrated, 4);
+        assert_eq!(stats.edges_migrated, 1);
+        assert_eq!(stats.embeddings_migrated, 4);
+        assert_eq!(stats.versions_migrated, 8);
+        assert_eq!(stats.errors, 0);
+
+        let vector = SurrealVectorStore::new(restored.clone());
+        for (id, embedding) in ids.iter().zip(&embeddings) {
+            assert_eq!(vector.get(id).await.unwrap().as_ref(), Some(embedding));
+        }
+        let history = SurrealHistoryStore::new(restored);
+        assert_eq!(history.get_all_version_ids().await.unwrap().len(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_in_memory_session_to_rocks() {
+        let units = build_synthetic_codebase();
+        let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+        let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+        let mut session = TransactionManager::new();
+        ingest_codebase(&mut session, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let rocks = open_db(dir.path()).unwrap();
+        let graph = RocksGraphStore::new(rocks.clone());
+        let vector = RocksVectorStore::new(rocks.clone(), embedder.dimensions());
+        let history = RocksHistoryStore::new(rocks);
+
+        let stats = migrate(
+            MigrationStores::from(&session),
+            MigrationStores::new(&graph, &vector, &history),
+            MigrationConfig::default(),
+        )
+        .await
+        .unwrap();
+
+        let expected = session.stats();
+        assert_eq!(stats.errors, 0);
+        assert_eq!(stats.nodes_migrated, expected.node_count);
+        assert_eq!(stats.edges_migrated, expected.edge_count);
+        assert_eq!(stats.embeddings_migrated, expected.embedding_count);
+        assert_eq!(stats.versions_migrated, expected.version_count);
+        assert_eq!(graph.get_all_node_ids().await.unwrap().len(), expected.node_count);
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_checkpointed_nodes() {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let source = SurrealGraphStore::new(db);
+        let mut ids = Vec::new();
+        for i in 0..10 {
+            let node = Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                format!("f{}", i),
+                "",
+            );
+            ids.push(node.id);
+            source.insert_node(node).await.unwrap();
+        }
+        ids.sort();
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = RocksGraphStore::new(open_db(dir.path().join("rocks")).unwrap());
+        let checkpoint_path = dir.path().join("checkpoint.json");
+
+        // Simulate a run that was interrupted after the first half.
+        for id in &ids[..5] {
+            let node = source.get_node(id).await.unwrap().unwrap();
+            target.insert_node(node).await.unwrap();
+        }
+        MigrationCheckpoint {
+            last_node: Some(ids[4]),
+            ..Default::default()
+        }
+        .save(&checkpoint_path)
+        .unwrap();
+
+        let mut migrator = StorageMigrator::new(MigrationConfig {
+            resume: true,
+            ..Default::default()
+        });
+        migrator.open_checkpoint(checkpoint_path.clone()).unwrap();
+        migrator.migrate_nodes(&source, &target).await.unwrap();
+
+        assert_eq!(migrator.stats.nodes_skipped, 5);
+        assert_eq!(migrator.stats.nodes_migrated, 5);
+        assert_eq!(migrator.stats.errors, 0);
+        assert_eq!(target.get_all_node_ids().await.unwrap().len(), 10);
+
+        let saved = MigrationCheckpoint::load(&checkpoint_path).unwrap();
+        assert_eq!(saved.last_node, Some(ids[9]));
+    }
+}
@@ -1,17 +1,27 @@
 pub mod benchmark;
 pub mod crash_recovery;
+pub mod events;
+pub mod export;
 pub mod graph;
 pub mod history;
+pub mod maintenance;
 pub mod migration;
 pub mod persistent;
 pub mod transaction;
 pub mod vector;
 
-pub use graph::{GraphStore, SurrealGraphStore, SubgraphResult, TraversalResult};
+pub use events::{ChangeEvent, EventSink, StoreEvent};
+pub use graph::{
+    ConflictPolicy, FuzzyMatch, GraphStore, MatchMode, NameMatch, NodeUpsert, SurrealGraphStore,
+    SubgraphResult, TraversalBudget, TraversalResult,
+};
 pub use history::{HistoryStore, SurrealHistoryStore};
-pub use migration::{run_migration, MigrationConfig, MigrationStats, StorageMigrator};
+pub use maintenance::{gc, GcReport};
+pub use migration::{
+    migrate, run_migration, MigrationConfig, MigrationStats, MigrationStores, StorageMigrator,
+};
 pub use transaction::TransactionManager;
 pub use vector::{SurrealVectorStore, VectorStore};
 
 #[cfg(feature = "rocksdb-storage")]
-pub use persistent::{open_db, RocksGraphStore, RocksHistoryStore, RocksVectorStore};
+pub use persistent::{open_db, open_db_with, open_stores, open_stores_with, DurabilityMode, RocksGraphStore, RocksHistoryStore, RocksVectorStore, VectorStorage};
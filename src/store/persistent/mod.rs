@@ -11,23 +11,33 @@ pub mod rocks_graph;
 pub mod rocks_history;
 #[cfg(feature = "rocksdb-storage")]
 pub mod rocks_vector;
+#[cfg(feature = "rocksdb-storage")]
+pub mod schema;
 
 #[cfg(feature = "rocksdb-storage")]
-pub use rocks_graph::RocksGraphStore;
+pub use rocks_graph::{NodeCacheStats, RocksGraphStore};
 #[cfg(feature = "rocksdb-storage")]
 pub use rocks_history::RocksHistoryStore;
 #[cfg(feature = "rocksdb-storage")]
-pub use rocks_vector::RocksVectorStore;
+pub use rocks_vector::{QuantizedVector, RocksVectorStore, VectorStorage};
+#[cfg(feature = "rocksdb-storage")]
+pub use schema::{Versioned, SCHEMA_VERSION};
 
 #[cfg(feature = "rocksdb-storage")]
-use rocksdb::{Options, DB};
+use rocksdb::{ColumnFamilyDescriptor, MergeOperands, Options, WriteOptions, DB};
 #[cfg(feature = "rocksdb-storage")]
 use std::path::Path;
 #[cfg(feature = "rocksdb-storage")]
 use std::sync::Arc;
 
 #[cfg(feature = "rocksdb-storage")]
-use crate::error::{OnyxError, OnyxResult};
+use crate::error::{OnyxError, OnyxResult, StorageError};
+#[cfg(feature = "rocksdb-storage")]
+use crate::model::embedding::BagOfWordsEmbedder;
+#[cfg(feature = "rocksdb-storage")]
+use crate::store::transaction::AsyncTransactionManager;
+#[cfg(feature = "rocksdb-storage")]
+use crate::store::vector::VectorStore;
 
 /// Column family names
 #[cfg(feature = "rocksdb-storage")]
@@ -48,28 +58,245 @@ pub const CF_VERSIONS: &str = "versions";
 pub const CF_VERSION_CHAINS: &str = "version_chains";
 #[cfg(feature = "rocksdb-storage")]
 pub const CF_BRANCHES: &str = "branches";
+/// Secondary index: `file_path \0 node_id` -> empty.
+#[cfg(feature = "rocksdb-storage")]
+pub const CF_NODES_BY_FILE: &str = "nodes_by_file";
+/// Secondary index: `segment \0 ... segment \0 node_id` -> empty.
+#[cfg(feature = "rocksdb-storage")]
+pub const CF_NODES_BY_MODULE: &str = "nodes_by_module";
+/// Full-text index: `term \0 node_id` -> term frequency (`u32` LE).
+#[cfg(feature = "rocksdb-storage")]
+pub const CF_TEXT_POSTINGS: &str = "text_postings";
+/// Full-text index: node id -> indexed token count (`u32` LE).
+#[cfg(feature = "rocksdb-storage")]
+pub const CF_TEXT_DOC_LENGTHS: &str = "text_doc_lengths";
+/// Record counts, maintained with an adding merge operator.
+#[cfg(feature = "rocksdb-storage")]
+pub const CF_COUNTERS: &str = "counters";
+
+/// Every column family opened by [`open_db`].
+#[cfg(feature = "rocksdb-storage")]
+pub const COLUMN_FAMILIES: [&str; 14] = [
+    CF_NODES,
+    CF_EDGES,
+    CF_NODE_OUTBOUND,
+    CF_NODE_INBOUND,
+    CF_EMBEDDINGS,
+    CF_HNSW_LAYERS,
+    CF_VERSIONS,
+    CF_VERSION_CHAINS,
+    CF_BRANCHES,
+    CF_NODES_BY_FILE,
+    CF_NODES_BY_MODULE,
+    CF_TEXT_POSTINGS,
+    CF_TEXT_DOC_LENGTHS,
+    CF_COUNTERS,
+];
+
+/// How hard RocksDB works to get writes onto disk before acknowledging them.
+///
+/// Every mode survives a process kill once a write has been acknowledged,
+/// except [`DurabilityMode::Fast`]. Only [`DurabilityMode::Paranoid`] aims to
+/// survive a power loss, at the cost of syncing the WAL far more often.
+#[cfg(feature = "rocksdb-storage")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurabilityMode {
+    /// Buffer WAL writes in memory until RocksDB flushes them. Highest write
+    /// throughput, but a crash loses whatever is still buffered.
+    Fast,
+    /// Hand every WAL write to the OS and sync it in the background every
+    /// megabyte. Survives a process kill; a power loss may lose recent writes.
+    #[default]
+    Safe,
+    /// `fsync` the WAL before acknowledging every write and verify data on
+    /// open. Slowest, but an acknowledged write survives a power loss.
+    Paranoid,
+}
 
-/// Opens a RocksDB instance with all required column families.
+#[cfg(feature = "rocksdb-storage")]
+impl DurabilityMode {
+    /// Options for a single write; under [`DurabilityMode::Paranoid`] the
+    /// write waits for the WAL to be synced.
+    ///
+    /// The stores opened over a database must use the same mode it was
+    /// opened with, via their `with_durability` builders.
+    pub fn write_options(self) -> WriteOptions {
+        let mut opts = WriteOptions::default();
+        opts.set_sync(self == DurabilityMode::Paranoid);
+        opts
+    }
+
+    fn apply(self, opts: &mut Options) {
+        match self {
+            DurabilityMode::Fast => {
+                opts.set_manual_wal_flush(true);
+                opts.set_wal_bytes_per_sync(0);
+            }
+            DurabilityMode::Safe => {
+                opts.set_manual_wal_flush(false);
+                opts.set_wal_bytes_per_sync(1024 * 1024);
+            }
+            DurabilityMode::Paranoid => {
+                opts.set_manual_wal_flush(false);
+                opts.set_use_fsync(true);
+                opts.set_wal_bytes_per_sync(4 * 1024);
+                opts.set_paranoid_checks(true);
+            }
+        }
+    }
+}
+
+/// Opens a RocksDB instance with all required column families, using
+/// [`DurabilityMode::Safe`].
 #[cfg(feature = "rocksdb-storage")]
 pub fn open_db<P: AsRef<Path>>(path: P) -> OnyxResult<Arc<DB>> {
+    open_db_with(path, DurabilityMode::Safe)
+}
+
+/// Opens a RocksDB instance with all required column families, using the
+/// given durability mode.
+#[cfg(feature = "rocksdb-storage")]
+pub fn open_db_with<P: AsRef<Path>>(path: P, mode: DurabilityMode) -> OnyxResult<Arc<DB>> {
     let mut opts = Options::default();
     opts.create_if_missing(true);
     opts.create_missing_column_families(true);
+    mode.apply(&mut opts);
 
-    let column_families = vec![
-        CF_NODES,
-        CF_EDGES,
-        CF_NODE_OUTBOUND,
-        CF_NODE_INBOUND,
-        CF_EMBEDDINGS,
-        CF_HNSW_LAYERS,
-        CF_VERSIONS,
-        CF_VERSION_CHAINS,
-        CF_BRANCHES,
-    ];
-
-    let db = DB::open_cf(&opts, path, &column_families)
-        .map_err(|e| OnyxError::Internal(format!("Failed to open RocksDB: {}", e)))?;
+    let descriptors = COLUMN_FAMILIES.iter().map(|&name| {
+        let mut cf_opts = Options::default();
+        if name == CF_COUNTERS {
+            cf_opts.set_merge_operator_associative("add_i64", add_counter_merge);
+        }
+        ColumnFamilyDescriptor::new(name, cf_opts)
+    });
+    let db = DB::open_cf_descriptors(&opts, path, descriptors)
+        .map_err(|e| StorageError::rocks("open database", e))?;
 
     Ok(Arc::new(db))
 }
+
+/// Opens the RocksDB database at `path` with the graph, vector and history
+/// stores over it, e.g. to serve them over HTTP.
+///
+/// The embedding dimension is read from the stored embeddings; `dimension`
+/// sets it for a store that has none yet. A store with neither accepts no
+/// embeddings.
+#[cfg(feature = "rocksdb-storage")]
+pub async fn open_stores<P: AsRef<Path>>(
+    path: P,
+    dimension: Option<usize>,
+) -> OnyxResult<AsyncTransactionManager> {
+    open_stores_with(path, dimension, DurabilityMode::Safe).await
+}
+
+/// Like [`open_stores`], opening the database and writing through every
+/// store with the given durability mode.
+#[cfg(feature = "rocksdb-storage")]
+pub async fn open_stores_with<P: AsRef<Path>>(
+    path: P,
+    dimension: Option<usize>,
+    mode: DurabilityMode,
+) -> OnyxResult<AsyncTransactionManager> {
+    let db = open_db_with(path, mode)?;
+    let stored = stored_dimension(&RocksVectorStore::new(db.clone(), 0)).await?;
+    let dimension = match (stored, dimension) {
+        (Some(stored), Some(requested)) if stored != requested => {
+            return Err(OnyxError::DimensionMismatch {
+                expected: stored,
+                got: requested,
+            });
+        }
+        (stored, requested) => stored.or(requested).unwrap_or(0),
+    };
+
+    Ok(AsyncTransactionManager::with_stores(
+        Arc::new(RocksVectorStore::new(db.clone(), dimension).with_durability(mode)),
+        Arc::new(RocksGraphStore::new(db.clone()).with_durability(mode)),
+        Arc::new(RocksHistoryStore::new(db).with_durability(mode)),
+    ))
+}
+
+/// Length of the first stored embedding, if there is one.
+#[cfg(feature = "rocksdb-storage")]
+async fn stored_dimension(vector: &RocksVectorStore) -> OnyxResult<Option<usize>> {
+    for id in vector.get_all_embedding_ids().await? {
+        if let Some(embedding) = vector.get(&id).await? {
+            return Ok(Some(embedding.len()));
+        }
+    }
+    Ok(None)
+}
+
+/// File in a store directory holding the embedder its vectors were made
+/// with.
+#[cfg(feature = "rocksdb-storage")]
+pub const EMBEDDER_FILE: &str = "embedder.json";
+
+/// Save `embedder` alongside the RocksDB store at `path`, so later queries
+/// and ingests embed into the same space as the stored vectors.
+#[cfg(feature = "rocksdb-storage")]
+pub fn save_embedder<P: AsRef<Path>>(path: P, embedder: &BagOfWordsEmbedder) -> OnyxResult<()> {
+    std::fs::write(
+        path.as_ref().join(EMBEDDER_FILE),
+        serde_json::to_vec(embedder)?,
+    )?;
+    Ok(())
+}
+
+/// The embedder saved with [`save_embedder`] for the store at `path`, if
+/// there is one.
+#[cfg(feature = "rocksdb-storage")]
+pub fn load_embedder<P: AsRef<Path>>(path: P) -> OnyxResult<Option<BagOfWordsEmbedder>> {
+    match std::fs::read(path.as_ref().join(EMBEDDER_FILE)) {
+        Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Sync the WAL and write the memtables out to SST files.
+#[cfg(feature = "rocksdb-storage")]
+pub(crate) fn flush_db(db: &DB) -> OnyxResult<()> {
+    db.flush_wal(true)
+        .map_err(|e| StorageError::rocks("flush WAL", e))?;
+    db.flush()
+        .map_err(|e| StorageError::rocks("flush memtables", e))?;
+    Ok(())
+}
+
+/// Decode a little-endian `i64` counter value, treating garbage as zero.
+#[cfg(feature = "rocksdb-storage")]
+pub(crate) fn decode_counter(bytes: &[u8]) -> i64 {
+    bytes.try_into().map(i64::from_le_bytes).unwrap_or(0)
+}
+
+/// Merge operator for [`CF_COUNTERS`]: every operand is a delta to add.
+#[cfg(feature = "rocksdb-storage")]
+fn add_counter_merge(
+    _key: &[u8],
+    existing: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let total = operands
+        .iter()
+        .fold(existing.map_or(0, decode_counter), |sum, delta| {
+            sum + decode_counter(delta)
+        });
+    Some(total.to_le_bytes().to_vec())
+}
+
+/// RocksDB's estimate of live data across all column families, in bytes.
+#[cfg(feature = "rocksdb-storage")]
+pub fn estimate_live_data_size(db: &DB) -> OnyxResult<u64> {
+    let mut total = 0;
+    for name in COLUMN_FAMILIES {
+        let cf = db
+            .cf_handle(name)
+            .ok_or_else(|| StorageError::MissingColumnFamily(name.to_string()))?;
+        total += db
+            .property_int_value_cf(cf, "rocksdb.estimate-live-data-size")
+            .map_err(|e| StorageError::rocks("read live data size", e))?
+            .unwrap_or(0);
+    }
+    Ok(total)
+}
@@ -2,80 +2,527 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use rocksdb::DB;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::sync::Arc;
+use lru::LruCache;
+use rocksdb::{WriteBatch, DB};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
-use crate::error::{OnyxError, OnyxResult};
+use crate::error::{OnyxError, OnyxResult, StorageError};
 use crate::model::edge::{Edge, EdgeType};
+use crate::model::embedding::tokenize_code;
 use crate::model::node::{Node, NodeType};
-use crate::store::graph::{GraphStore, SubgraphResult, TraversalResult};
+use crate::store::graph::{
+    traverse_parallel, GraphStore, SubgraphResult, TraversalResult, DEFAULT_TRAVERSAL_CONCURRENCY,
+};
+
+use super::schema;
+use super::{
+    decode_counter, flush_db, DurabilityMode, CF_COUNTERS, CF_EDGES, CF_NODES, CF_NODES_BY_FILE,
+    CF_NODES_BY_MODULE, CF_NODE_INBOUND, CF_NODE_OUTBOUND, CF_TEXT_DOC_LENGTHS, CF_TEXT_POSTINGS,
+};
+
+/// Keys of the node and edge counts in the counters column family.
+const NODE_COUNT_KEY: &[u8] = b"nodes";
+const EDGE_COUNT_KEY: &[u8] = b"edges";
+
+/// Marks a value framed with a CRC32 of its payload. Legacy values are plain
+/// bincode, which for nodes and edges always starts with a `0x10` length byte.
+const CHECKSUM_MAGIC: [u8; 4] = [0xC5, b'O', b'N', 0x01];
+/// Magic plus the little-endian CRC32.
+const CHECKSUM_HEADER_LEN: usize = CHECKSUM_MAGIC.len() + 4;
+
+/// Prefix `payload` with the checksum header.
+fn frame_with_checksum(payload: Vec<u8>) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(CHECKSUM_HEADER_LEN + payload.len());
+    framed.extend_from_slice(&CHECKSUM_MAGIC);
+    framed.extend_from_slice(&crc32fast::hash(&payload).to_le_bytes());
+    framed.extend_from_slice(&payload);
+    framed
+}
 
-use super::{CF_EDGES, CF_NODES, CF_NODE_INBOUND, CF_NODE_OUTBOUND};
+/// Strip and verify the checksum header, passing legacy values through.
+/// `key` is only used to name the record on a mismatch.
+fn verify_checksum<'a>(key: &[u8], bytes: &'a [u8]) -> OnyxResult<&'a [u8]> {
+    if !bytes.starts_with(&CHECKSUM_MAGIC) {
+        return Ok(bytes);
+    }
+    let corrupt = || OnyxError::CorruptRecord {
+        id: Uuid::from_slice(key).unwrap_or_default(),
+    };
+    let header = bytes.get(..CHECKSUM_HEADER_LEN).ok_or_else(corrupt)?;
+    let mut expected = [0u8; 4];
+    expected.copy_from_slice(&header[CHECKSUM_MAGIC.len()..]);
+    let payload = &bytes[CHECKSUM_HEADER_LEN..];
+    if crc32fast::hash(payload) != u32::from_le_bytes(expected) {
+        return Err(corrupt());
+    }
+    Ok(payload)
+}
+
+// ---------------------------------------------------------------------------
+// Node cache
+// ---------------------------------------------------------------------------
+
+/// Hit and miss counts of a [`RocksGraphStore`] node cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl NodeCacheStats {
+    /// Fraction of lookups served from the cache, or 0 before any lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Number of generation counters node IDs are spread over.
+const GENERATION_STRIPES: usize = 64;
+
+/// The stripe `id` falls in when spread over `stripes` locks or counters.
+fn stripe(id: &Uuid, stripes: usize) -> usize {
+    (id.as_u128() % stripes as u128) as usize
+}
+
+/// LRU cache of deserialized nodes, shared by clones of a store.
+///
+/// A miss hands out the generation of the node's stripe; a read from
+/// RocksDB is only cached if no write invalidated the stripe since, so a
+/// read that raced a write cannot cache the value it overwrote.
+struct NodeCache {
+    entries: Mutex<CacheEntries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct CacheEntries {
+    nodes: LruCache<Uuid, Node>,
+    generations: [u64; GENERATION_STRIPES],
+}
+
+/// Generation of a node's stripe when its lookup missed the cache.
+#[derive(Debug, Clone, Copy)]
+struct CacheTicket(u64);
+
+impl NodeCache {
+    fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            entries: Mutex::new(CacheEntries {
+                nodes: LruCache::new(capacity),
+                generations: [0; GENERATION_STRIPES],
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The cached node, or on a miss the ticket to [`fill`](Self::fill) it
+    /// with.
+    fn get(&self, id: &Uuid) -> Result<Node, CacheTicket> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.nodes.get(id).cloned() {
+            Some(node) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Ok(node)
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Err(CacheTicket(
+                    entries.generations[stripe(id, GENERATION_STRIPES)],
+                ))
+            }
+        }
+    }
+
+    /// Cache `node` as read after the miss that issued `ticket`, unless it
+    /// was written since.
+    fn fill(&self, node: Node, ticket: CacheTicket) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generations[stripe(&node.id, GENERATION_STRIPES)] == ticket.0 {
+            entries.nodes.put(node.id, node);
+        }
+    }
+
+    /// Drop `id` after a write, refusing fills from reads that started
+    /// before it.
+    fn invalidate(&self, id: &Uuid) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.nodes.pop(id);
+        entries.generations[stripe(id, GENERATION_STRIPES)] += 1;
+    }
+
+    fn stats(&self) -> NodeCacheStats {
+        NodeCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Record locks
+// ---------------------------------------------------------------------------
+
+/// Number of locks counted records are spread over.
+const RECORD_LOCK_STRIPES: usize = 64;
+
+/// Locks held from checking whether a node or edge exists until the write
+/// that counts it commits, so concurrent writers of the same record cannot
+/// both count it. Striped by record ID.
+struct RecordLocks(Vec<tokio::sync::Mutex<()>>);
+
+impl RecordLocks {
+    fn new() -> Self {
+        Self(
+            (0..RECORD_LOCK_STRIPES)
+                .map(|_| Default::default())
+                .collect(),
+        )
+    }
+
+    /// Lock the stripes of every ID in `ids`, in stripe order so that
+    /// writers locking overlapping sets cannot deadlock.
+    async fn lock(
+        &self,
+        ids: impl IntoIterator<Item = Uuid>,
+    ) -> Vec<tokio::sync::MutexGuard<'_, ()>> {
+        let stripes: BTreeSet<usize> = ids
+            .into_iter()
+            .map(|id| stripe(&id, RECORD_LOCK_STRIPES))
+            .collect();
+        let mut guards = Vec::with_capacity(stripes.len());
+        for i in stripes {
+            guards.push(self.0[i].lock().await);
+        }
+        guards
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Store
+// ---------------------------------------------------------------------------
 
 /// RocksDB-backed graph store with persistent node and edge storage.
 #[derive(Clone)]
 pub struct RocksGraphStore {
     db: Arc<DB>,
+    /// Optional cache of hot nodes, invalidated on every node write.
+    node_cache: Option<Arc<NodeCache>>,
+    /// Set once both record counters are known to exist.
+    counters_ready: Arc<AtomicBool>,
+    /// Held while the missing counters are recomputed.
+    counters_init: Arc<Mutex<()>>,
+    /// Serialize counted writes to the same record.
+    record_locks: Arc<RecordLocks>,
+    /// Test hook: fail edge writes after queuing the edge record.
+    #[cfg(any(test, feature = "test-util"))]
+    fail_after_edge_record: Arc<AtomicBool>,
+    /// Neighbor lookups `traverse` runs at once within a BFS level.
+    traversal_concurrency: usize,
+    /// How every write is synced; see [`DurabilityMode::write_options`].
+    durability: DurabilityMode,
 }
 
 impl RocksGraphStore {
     /// Create a new RocksDB graph store.
     pub fn new(db: Arc<DB>) -> Self {
-        Self { db }
+        Self {
+            db,
+            node_cache: None,
+            counters_ready: Arc::new(AtomicBool::new(false)),
+            counters_init: Arc::new(Mutex::new(())),
+            record_locks: Arc::new(RecordLocks::new()),
+            #[cfg(any(test, feature = "test-util"))]
+            fail_after_edge_record: Arc::new(AtomicBool::new(false)),
+            traversal_concurrency: DEFAULT_TRAVERSAL_CONCURRENCY,
+            durability: DurabilityMode::default(),
+        }
+    }
+
+    /// Write with the durability `mode` the database was opened with.
+    pub fn with_durability(mut self, mode: DurabilityMode) -> Self {
+        self.durability = mode;
+        self
     }
 
-    /// Serialize a node to bytes.
+    /// Cache up to `capacity` deserialized nodes in memory. A capacity of
+    /// zero disables the cache.
+    pub fn with_node_cache(mut self, capacity: usize) -> Self {
+        self.node_cache = NonZeroUsize::new(capacity).map(|c| Arc::new(NodeCache::new(c)));
+        self
+    }
+
+    /// Run up to `concurrency` neighbor lookups at once on the blocking pool
+    /// during `traverse`. A concurrency of 1 traverses sequentially.
+    pub fn with_traversal_concurrency(mut self, concurrency: usize) -> Self {
+        self.traversal_concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Hit and miss counts of the node cache, if one is configured.
+    pub fn node_cache_stats(&self) -> Option<NodeCacheStats> {
+        self.node_cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Drop `id` from the node cache after a write.
+    fn invalidate_cached_node(&self, id: &Uuid) {
+        if let Some(cache) = &self.node_cache {
+            cache.invalidate(id);
+        }
+    }
+
+    /// Test hook for crash-recovery scenarios: while enabled, every edge
+    /// write fails between the edge record and its adjacency entries.
+    /// Only built for tests and with the `test-util` feature.
+    #[cfg(any(test, feature = "test-util"))]
+    pub fn inject_fault_after_edge_record(&self, enabled: bool) {
+        self.fail_after_edge_record.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Whether the outbound and inbound adjacency entries for `edge` exist.
+    pub fn has_adjacency(&self, edge: &Edge) -> OnyxResult<(bool, bool)> {
+        let outbound = self
+            .db
+            .get_pinned_cf(self.cf_node_outbound()?, self.outbound_key(&edge.source_id, &edge.id))
+            .map_err(|e| StorageError::rocks("get adjacency entry", e))?
+            .is_some();
+        let inbound = self
+            .db
+            .get_pinned_cf(self.cf_node_inbound()?, self.inbound_key(&edge.target_id, &edge.id))
+            .map_err(|e| StorageError::rocks("get adjacency entry", e))?
+            .is_some();
+        Ok((outbound, inbound))
+    }
+
+    /// Serialize a node to checksummed, schema-versioned bytes.
     fn serialize_node(&self, node: &Node) -> OnyxResult<Vec<u8>> {
-        bincode::serialize(node)
-            .map_err(|e| OnyxError::Internal(format!("Failed to serialize node: {}", e)))
+        schema::encode(node).map(frame_with_checksum)
     }
 
-    /// Deserialize a node from bytes.
-    fn deserialize_node(&self, bytes: &[u8]) -> OnyxResult<Node> {
-        bincode::deserialize(bytes)
-            .map_err(|e| OnyxError::Internal(format!("Failed to deserialize node: {}", e)))
+    /// Deserialize the node stored under `key`, verifying its checksum and
+    /// migrating older schemas.
+    fn deserialize_node(&self, key: &[u8], bytes: &[u8]) -> OnyxResult<Node> {
+        schema::decode(verify_checksum(key, bytes)?)
     }
 
-    /// Serialize an edge to bytes.
+    /// Serialize an edge to checksummed, schema-versioned bytes.
     fn serialize_edge(&self, edge: &Edge) -> OnyxResult<Vec<u8>> {
-        bincode::serialize(edge)
-            .map_err(|e| OnyxError::Internal(format!("Failed to serialize edge: {}", e)))
+        schema::encode(edge).map(frame_with_checksum)
     }
 
-    /// Deserialize an edge from bytes.
-    fn deserialize_edge(&self, bytes: &[u8]) -> OnyxResult<Edge> {
-        bincode::deserialize(bytes)
-            .map_err(|e| OnyxError::Internal(format!("Failed to deserialize edge: {}", e)))
+    /// Deserialize the edge stored under `key`, verifying its checksum and
+    /// migrating older schemas.
+    fn deserialize_edge(&self, key: &[u8], bytes: &[u8]) -> OnyxResult<Edge> {
+        schema::decode(verify_checksum(key, bytes)?)
     }
 
     /// Get the nodes column family handle.
     fn cf_nodes(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_NODES)
-            .ok_or_else(|| OnyxError::Internal("Missing nodes column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_NODES.to_string()).into())
     }
 
     /// Get the edges column family handle.
     fn cf_edges(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_EDGES)
-            .ok_or_else(|| OnyxError::Internal("Missing edges column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_EDGES.to_string()).into())
     }
 
     /// Get the node outbound edges column family handle.
     fn cf_node_outbound(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_NODE_OUTBOUND)
-            .ok_or_else(|| OnyxError::Internal("Missing node_outbound column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_NODE_OUTBOUND.to_string()).into())
     }
 
     /// Get the node inbound edges column family handle.
     fn cf_node_inbound(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_NODE_INBOUND)
-            .ok_or_else(|| OnyxError::Internal("Missing node_inbound column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_NODE_INBOUND.to_string()).into())
+    }
+
+    /// Get the nodes-by-file index column family handle.
+    fn cf_nodes_by_file(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(CF_NODES_BY_FILE)
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_NODES_BY_FILE.to_string()).into())
+    }
+
+    /// Get the nodes-by-module index column family handle.
+    fn cf_nodes_by_module(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(CF_NODES_BY_MODULE)
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_NODES_BY_MODULE.to_string()).into())
+    }
+
+    /// Prefix of every file index key for `file_path`. Paths cannot contain
+    /// NUL, so the separator keeps `a.rs` from matching a scan for `a.rs.bak`.
+    fn file_index_prefix(&self, file_path: &str) -> Vec<u8> {
+        let mut prefix = file_path.as_bytes().to_vec();
+        prefix.push(0);
+        prefix
+    }
+
+    /// Prefix of every module index key under `module_path`. Each segment is
+    /// NUL-terminated, so `billing` does not match `billing_v2`.
+    fn module_index_prefix(&self, module_path: &[String]) -> Vec<u8> {
+        let mut prefix = Vec::new();
+        for segment in module_path {
+            prefix.extend_from_slice(segment.as_bytes());
+            prefix.push(0);
+        }
+        prefix
+    }
+
+    /// The file and module index keys `node` is stored under.
+    fn index_keys(&self, node: &Node) -> [Option<Vec<u8>>; 2] {
+        let with_id = |mut prefix: Vec<u8>| {
+            prefix.extend_from_slice(node.id.as_bytes());
+            prefix
+        };
+        [
+            node.provenance
+                .file_path
+                .as_deref()
+                .map(|path| with_id(self.file_index_prefix(path))),
+            node.module_path()
+                .map(|path| with_id(self.module_index_prefix(path))),
+        ]
+    }
+
+    /// Queue the secondary index updates for writing `node` over `previous`,
+    /// dropping entries that no longer apply.
+    fn index_node_in_batch(
+        &self,
+        batch: &mut WriteBatch,
+        node: &Node,
+        previous: Option<&Node>,
+    ) -> OnyxResult<()> {
+        let cfs = [self.cf_nodes_by_file()?, self.cf_nodes_by_module()?];
+        let old_keys = previous.map_or([None, None], |p| self.index_keys(p));
+        for ((cf, old), new) in cfs.into_iter().zip(old_keys).zip(self.index_keys(node)) {
+            if old == new {
+                continue;
+            }
+            if let Some(key) = old {
+                batch.delete_cf(cf, key);
+            }
+            if let Some(key) = new {
+                batch.put_cf(cf, key, b"");
+            }
+        }
+        Ok(())
+    }
+
+    /// Live nodes whose index key in `cf` starts with `prefix`.
+    fn indexed_nodes(&self, cf: &rocksdb::ColumnFamily, prefix: &[u8]) -> OnyxResult<Vec<Node>> {
+        let ids: Vec<Uuid> = self
+            .scan_prefix(cf, prefix)?
+            .iter()
+            .filter_map(|(key, _)| Uuid::from_slice(&key[key.len().saturating_sub(16)..]).ok())
+            .collect();
+        Ok(self.multi_get_nodes(&ids)?.into_iter().flatten().collect())
+    }
+
+    /// Entries in `cf` whose key starts with `prefix`, stopping at the first
+    /// key past it.
+    fn scan_prefix(
+        &self,
+        cf: &rocksdb::ColumnFamily,
+        prefix: &[u8],
+    ) -> OnyxResult<Vec<(Box<[u8]>, Box<[u8]>)>> {
+        let mut entries = Vec::new();
+        for item in self.db.prefix_iterator_cf(cf, prefix) {
+            let (key, value) = item.map_err(|e| StorageError::rocks("iterate index", e))?;
+            if !key.starts_with(prefix) {
+                break;
+            }
+            entries.push((key, value));
+        }
+        Ok(entries)
+    }
+
+    /// Get the counters column family handle.
+    fn cf_counters(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(CF_COUNTERS)
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_COUNTERS.to_string()).into())
+    }
+
+    /// Make sure both record counters exist, counting the column families
+    /// once for a store written before counters were maintained.
+    fn ensure_counters(&self) -> OnyxResult<()> {
+        if self.counters_ready.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        // Writers only count once the counters are ready, so nothing can
+        // change the counts between recomputing and storing them.
+        let _init = self.counters_init.lock().unwrap();
+        if self.counters_ready.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let cf = self.cf_counters()?;
+        for (key, counted) in [
+            (NODE_COUNT_KEY, self.cf_nodes()?),
+            (EDGE_COUNT_KEY, self.cf_edges()?),
+        ] {
+            let present = self
+                .db
+                .get_pinned_cf(cf, key)
+                .map_err(|e| StorageError::rocks("read counter", e))?
+                .is_some();
+            if !present {
+                let count = self.db.iterator_cf(counted, rocksdb::IteratorMode::Start).count();
+                self.db
+                    .put_cf_opt(
+                        cf,
+                        key,
+                        (count as i64).to_le_bytes(),
+                        &self.durability.write_options(),
+                    )
+                    .map_err(|e| StorageError::rocks("initialize counter", e))?;
+            }
+        }
+        self.counters_ready.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Queue a change to a record counter alongside the write it counts.
+    fn queue_count_delta(&self, batch: &mut WriteBatch, key: &[u8], delta: i64) -> OnyxResult<()> {
+        self.ensure_counters()?;
+        batch.merge_cf(self.cf_counters()?, key, delta.to_le_bytes());
+        Ok(())
+    }
+
+    /// Read a record counter in O(1).
+    fn read_counter(&self, key: &[u8]) -> OnyxResult<usize> {
+        self.ensure_counters()?;
+        let value = self
+            .db
+            .get_pinned_cf(self.cf_counters()?, key)
+            .map_err(|e| StorageError::rocks("read counter", e))?;
+        Ok(value.map_or(0, |bytes| decode_counter(&bytes).max(0) as usize))
+    }
+
+    /// Whether a record exists under `key`, without deserializing it.
+    fn record_exists(&self, cf: &rocksdb::ColumnFamily, key: &[u8]) -> OnyxResult<bool> {
+        Ok(self
+            .db
+            .get_pinned_cf(cf, key)
+            .map_err(|e| StorageError::rocks("check record", e))?
+            .is_some())
     }
 
     /// Build adjacency index key for node outbound edges.
@@ -98,26 +545,417 @@ impl RocksGraphStore {
         cf: &rocksdb::ColumnFamily,
         node_id: &Uuid,
     ) -> OnyxResult<Vec<Uuid>> {
-        let prefix = node_id.as_bytes();
-        let iter = self.db.prefix_iterator_cf(cf, prefix);
-
         let mut edge_ids = Vec::new();
-        for item in iter {
-            let (key, _) = item.map_err(|e| {
-                OnyxError::Internal(format!("Failed to iterate adjacency index: {}", e))
-            })?;
-
+        for (key, _) in self.scan_prefix(cf, node_id.as_bytes())? {
             // Key format: [node_id (16 bytes)][edge_id (16 bytes)]
             if key.len() == 32 {
                 let edge_id_bytes = &key[16..32];
                 let edge_id = Uuid::from_slice(edge_id_bytes)
-                    .map_err(|e| OnyxError::Internal(format!("Invalid edge UUID: {}", e)))?;
+                    .map_err(|e| StorageError::deserialization("edge UUID", e))?;
                 edge_ids.push(edge_id);
             }
         }
 
         Ok(edge_ids)
     }
+
+    /// Fetch many edges in one `multi_get`, in the order of `ids`.
+    fn multi_get_edges(&self, ids: &[Uuid]) -> OnyxResult<Vec<Option<Edge>>> {
+        let cf = self.cf_edges()?;
+        self.db
+            .multi_get_cf(ids.iter().map(|id| (cf, id.as_bytes())))
+            .into_iter()
+            .zip(ids)
+            .map(|(result, id)| {
+                match result.map_err(|e| StorageError::rocks("get edge", e))? {
+                    Some(bytes) => Ok(Some(self.deserialize_edge(id.as_bytes(), &bytes)?)),
+                    None => Ok(None),
+                }
+            })
+            .collect()
+    }
+
+    /// Fetch many live nodes in one `multi_get`, in the order of `ids`.
+    ///
+    /// Cached nodes are served from the cache; soft-deleted nodes come back as
+    /// `None`, as from `get_node`.
+    fn multi_get_nodes(&self, ids: &[Uuid]) -> OnyxResult<Vec<Option<Node>>> {
+        let mut nodes: Vec<Option<Node>> = vec![None; ids.len()];
+        let mut tickets: Vec<Option<CacheTicket>> = vec![None; ids.len()];
+        if let Some(cache) = &self.node_cache {
+            for (i, id) in ids.iter().enumerate() {
+                match cache.get(id) {
+                    Ok(node) => nodes[i] = Some(node),
+                    Err(ticket) => tickets[i] = Some(ticket),
+                }
+            }
+        }
+        let missing: Vec<usize> = (0..ids.len()).filter(|&i| nodes[i].is_none()).collect();
+
+        let cf = self.cf_nodes()?;
+        let fetched = self
+            .db
+            .multi_get_cf(missing.iter().map(|&i| (cf, ids[i].as_bytes())));
+        for (&i, result) in missing.iter().zip(fetched) {
+            if let Some(bytes) = result.map_err(|e| StorageError::rocks("get node", e))? {
+                let node = self.deserialize_node(ids[i].as_bytes(), &bytes)?;
+                if let (Some(cache), Some(ticket)) = (&self.node_cache, tickets[i]) {
+                    cache.fill(node.clone(), ticket);
+                }
+                nodes[i] = Some(node);
+            }
+        }
+
+        Ok(nodes
+            .into_iter()
+            .map(|node| node.filter(|n| !n.is_deleted()))
+            .collect())
+    }
+
+    /// Outbound neighbors of `id`, read synchronously so that lookups can
+    /// run on the blocking pool.
+    fn outbound_neighbors(
+        &self,
+        id: &Uuid,
+        edge_types: Option<&[EdgeType]>,
+    ) -> OnyxResult<Vec<(Edge, Node)>> {
+        let edge_ids = self.get_edge_ids_from_adjacency(self.cf_node_outbound()?, id)?;
+        self.resolve_adjacent(&edge_ids, edge_types, |edge| edge.target_id)
+    }
+
+    /// Resolve adjacency entries to `(edge, node)` pairs with two batched
+    /// reads: all edges, then the node at `endpoint` of each matching edge.
+    fn resolve_adjacent(
+        &self,
+        edge_ids: &[Uuid],
+        edge_types: Option<&[EdgeType]>,
+        endpoint: fn(&Edge) -> Uuid,
+    ) -> OnyxResult<Vec<(Edge, Node)>> {
+        let edges: Vec<Edge> = self
+            .multi_get_edges(edge_ids)?
+            .into_iter()
+            .flatten()
+            .filter(|edge| edge_types.map_or(true, |types| types.contains(&edge.edge_type)))
+            .collect();
+
+        let node_ids: Vec<Uuid> = edges.iter().map(endpoint).collect();
+        let nodes = self.multi_get_nodes(&node_ids)?;
+
+        Ok(edges
+            .into_iter()
+            .zip(nodes)
+            .filter_map(|(edge, node)| node.map(|node| (edge, node)))
+            .collect())
+    }
+
+    /// Decode every key in a column family keyed by raw 16-byte UUIDs.
+    fn collect_ids(
+        &self,
+        cf: &rocksdb::ColumnFamily,
+        what: &'static str,
+    ) -> OnyxResult<Vec<Uuid>> {
+        let mut ids = Vec::new();
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, _) = item.map_err(|e| StorageError::rocks("iterate keys", e))?;
+            let id = Uuid::from_slice(&key).map_err(|e| StorageError::deserialization(what, e))?;
+            ids.push(id);
+        }
+        Ok(ids)
+    }
+
+    /// Queue the edge record and both adjacency entries for `edge`.
+    ///
+    /// The caller must hold `edge`'s record lock until the batch commits.
+    fn put_edge_in_batch(&self, batch: &mut WriteBatch, edge: &Edge) -> OnyxResult<()> {
+        let cf_edges = self.cf_edges()?;
+        if !self.record_exists(cf_edges, edge.id.as_bytes())? {
+            self.queue_count_delta(batch, EDGE_COUNT_KEY, 1)?;
+        }
+        let value = self.serialize_edge(edge)?;
+        batch.put_cf(cf_edges, edge.id.as_bytes(), value);
+        #[cfg(any(test, feature = "test-util"))]
+        if self.fail_after_edge_record.load(Ordering::SeqCst) {
+            return Err(OnyxError::Internal(format!(
+                "Injected fault after writing edge {}",
+                edge.id
+            )));
+        }
+        batch.put_cf(
+            self.cf_node_outbound()?,
+            self.outbound_key(&edge.source_id, &edge.id),
+            b"",
+        );
+        batch.put_cf(
+            self.cf_node_inbound()?,
+            self.inbound_key(&edge.target_id, &edge.id),
+            b"",
+        );
+        Ok(())
+    }
+
+    /// Queue removal of the edge record and both adjacency entries for
+    /// `edge`, which must currently be stored. The caller must hold its
+    /// record lock until the batch commits.
+    fn delete_edge_in_batch(&self, batch: &mut WriteBatch, edge: &Edge) -> OnyxResult<()> {
+        self.queue_count_delta(batch, EDGE_COUNT_KEY, -1)?;
+        batch.delete_cf(
+            self.cf_node_outbound()?,
+            self.outbound_key(&edge.source_id, &edge.id),
+        );
+        batch.delete_cf(
+            self.cf_node_inbound()?,
+            self.inbound_key(&edge.target_id, &edge.id),
+        );
+        batch.delete_cf(self.cf_edges()?, edge.id.as_bytes());
+        Ok(())
+    }
+
+    /// Commit a batch atomically.
+    fn write_batch(&self, batch: WriteBatch) -> OnyxResult<()> {
+        self.db
+            .write_opt(batch, &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("write batch", e))?;
+        Ok(())
+    }
+
+    /// Replace the stored edge `id` with `update(edge)` in one atomic write.
+    /// `update` must keep the endpoints, so the adjacency entries stand.
+    async fn rewrite_edge(
+        &self,
+        id: &Uuid,
+        update: impl FnOnce(Edge) -> Edge + Send,
+    ) -> OnyxResult<Edge> {
+        let _lock = self.record_locks.lock([*id]).await;
+        let stored = self
+            .get_edge(id)
+            .await?
+            .ok_or(OnyxError::EdgeNotFound(*id))?;
+        let edge = update(stored);
+        let mut batch = WriteBatch::default();
+        self.put_edge_in_batch(&mut batch, &edge)?;
+        self.write_batch(batch)?;
+        Ok(edge)
+    }
+
+    /// Insert many edges in one atomic write.
+    ///
+    /// Either every edge and its adjacency entries are stored, or none are.
+    pub async fn add_edges_atomic(&self, edges: Vec<Edge>) -> OnyxResult<()> {
+        let _locks = self.record_locks.lock(edges.iter().map(|e| e.id)).await;
+        let mut batch = WriteBatch::default();
+        // Only the last write of a repeated edge counts, and only once.
+        let mut seen = HashSet::new();
+        for edge in edges.iter().rev() {
+            if seen.insert(edge.id) {
+                self.put_edge_in_batch(&mut batch, edge)?;
+            }
+        }
+        self.write_batch(batch)
+    }
+
+    /// Edge IDs referenced by an adjacency index but missing from the edge
+    /// store. Empty for a consistent store.
+    pub fn find_orphan_adjacency(&self) -> OnyxResult<Vec<Uuid>> {
+        let cf_edges = self.cf_edges()?;
+        let mut orphans = Vec::new();
+
+        for cf in [self.cf_node_outbound()?, self.cf_node_inbound()?] {
+            for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+                let (key, _) =
+                    item.map_err(|e| StorageError::rocks("iterate adjacency index", e))?;
+                if key.len() != 32 {
+                    continue;
+                }
+                let edge_id = Uuid::from_slice(&key[16..32])
+                    .map_err(|e| StorageError::deserialization("edge UUID", e))?;
+                let exists = self
+                    .db
+                    .get_pinned_cf(cf_edges, edge_id.as_bytes())
+                    .map_err(|e| StorageError::rocks("get edge", e))?
+                    .is_some();
+                if !exists {
+                    orphans.push(edge_id);
+                }
+            }
+        }
+
+        Ok(orphans)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Full-text index
+// ---------------------------------------------------------------------------
+
+/// BM25 term-frequency saturation.
+const BM25_K1: f32 = 1.2;
+/// BM25 document-length normalization.
+const BM25_B: f32 = 0.75;
+
+/// Keys of the indexed document and token totals in the counters family.
+const TEXT_DOCS_KEY: &[u8] = b"text_docs";
+const TEXT_TOKENS_KEY: &[u8] = b"text_tokens";
+
+/// Term frequencies of the text indexed for a node: its name and content.
+fn term_frequencies(node: &Node) -> HashMap<String, u32> {
+    let mut terms = HashMap::new();
+    for token in tokenize_code(&node.name)
+        .into_iter()
+        .chain(tokenize_code(&node.content))
+    {
+        *terms.entry(token).or_insert(0) += 1;
+    }
+    terms
+}
+
+fn decode_u32(bytes: &[u8]) -> u32 {
+    bytes.try_into().map(u32::from_le_bytes).unwrap_or(0)
+}
+
+impl RocksGraphStore {
+    /// Get the text postings column family handle.
+    fn cf_text_postings(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(CF_TEXT_POSTINGS)
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_TEXT_POSTINGS.to_string()).into())
+    }
+
+    /// Get the text document lengths column family handle.
+    fn cf_text_doc_lengths(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
+        self.db
+            .cf_handle(CF_TEXT_DOC_LENGTHS)
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_TEXT_DOC_LENGTHS.to_string()).into())
+    }
+
+    /// Prefix of every posting for `term`.
+    fn posting_prefix(&self, term: &str) -> Vec<u8> {
+        let mut prefix = term.as_bytes().to_vec();
+        prefix.push(0);
+        prefix
+    }
+
+    /// Queue the text index update for writing `node` over `previous`.
+    fn index_text_in_batch(
+        &self,
+        batch: &mut WriteBatch,
+        node: &Node,
+        previous: Option<&Node>,
+    ) -> OnyxResult<()> {
+        if let Some(previous) = previous {
+            if previous.name == node.name && previous.content == node.content {
+                return Ok(());
+            }
+            self.unindex_text_in_batch(batch, previous)?;
+        }
+
+        let cf = self.cf_text_postings()?;
+        let terms = term_frequencies(node);
+        let length: u32 = terms.values().sum();
+        for (term, frequency) in &terms {
+            let mut key = self.posting_prefix(term);
+            key.extend_from_slice(node.id.as_bytes());
+            batch.put_cf(cf, key, frequency.to_le_bytes());
+        }
+        batch.put_cf(self.cf_text_doc_lengths()?, node.id.as_bytes(), length.to_le_bytes());
+        self.queue_count_delta(batch, TEXT_DOCS_KEY, 1)?;
+        self.queue_count_delta(batch, TEXT_TOKENS_KEY, i64::from(length))
+    }
+
+    /// Queue removal of `node`'s postings. Nodes written before the index
+    /// existed have no postings and are left alone.
+    fn unindex_text_in_batch(&self, batch: &mut WriteBatch, node: &Node) -> OnyxResult<()> {
+        let cf_lengths = self.cf_text_doc_lengths()?;
+        let length = match self
+            .db
+            .get_pinned_cf(cf_lengths, node.id.as_bytes())
+            .map_err(|e| StorageError::rocks("read document length", e))?
+        {
+            Some(bytes) => decode_u32(&bytes),
+            None => return Ok(()),
+        };
+
+        let cf = self.cf_text_postings()?;
+        for term in term_frequencies(node).keys() {
+            let mut key = self.posting_prefix(term);
+            key.extend_from_slice(node.id.as_bytes());
+            batch.delete_cf(cf, key);
+        }
+        batch.delete_cf(cf_lengths, node.id.as_bytes());
+        self.queue_count_delta(batch, TEXT_DOCS_KEY, -1)?;
+        self.queue_count_delta(batch, TEXT_TOKENS_KEY, -i64::from(length))
+    }
+
+    /// Rank live nodes against a keyword query with BM25 over the persisted
+    /// inverted index of node names and content.
+    ///
+    /// Returns at most `top_k` nodes, best match first. Nodes are indexed when
+    /// written, so nodes stored before the index existed are not found until
+    /// they are next updated.
+    pub fn text_search(&self, query: &str, top_k: usize) -> OnyxResult<Vec<(Node, f32)>> {
+        let terms: BTreeSet<String> = tokenize_code(query).into_iter().collect();
+        let documents = self.read_counter(TEXT_DOCS_KEY)? as f32;
+        if terms.is_empty() || documents == 0.0 || top_k == 0 {
+            return Ok(Vec::new());
+        }
+        let average_length = (self.read_counter(TEXT_TOKENS_KEY)? as f32 / documents).max(1.0);
+
+        // Term frequencies per candidate node, with each term's IDF.
+        let cf = self.cf_text_postings()?;
+        let mut matches: Vec<(f32, Vec<(Uuid, u32)>)> = Vec::with_capacity(terms.len());
+        for term in &terms {
+            let prefix = self.posting_prefix(term);
+            let postings: Vec<(Uuid, u32)> = self
+                .scan_prefix(cf, &prefix)?
+                .iter()
+                .filter_map(|(key, value)| {
+                    let id = Uuid::from_slice(&key[prefix.len()..]).ok()?;
+                    Some((id, decode_u32(value)))
+                })
+                .collect();
+            let df = postings.len() as f32;
+            let idf = ((documents - df + 0.5) / (df + 0.5) + 1.0).ln();
+            matches.push((idf, postings));
+        }
+
+        let candidates: Vec<Uuid> = matches
+            .iter()
+            .flat_map(|(_, postings)| postings.iter().map(|(id, _)| *id))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let cf_lengths = self.cf_text_doc_lengths()?;
+        let lengths: HashMap<Uuid, f32> = self
+            .db
+            .multi_get_cf(candidates.iter().map(|id| (cf_lengths, id.as_bytes())))
+            .into_iter()
+            .zip(&candidates)
+            .map(|(result, id)| {
+                let bytes = result.map_err(|e| StorageError::rocks("read document length", e))?;
+                Ok((*id, bytes.map_or(0, |b| decode_u32(&b)) as f32))
+            })
+            .collect::<OnyxResult<_>>()?;
+
+        let mut scores: HashMap<Uuid, f32> = HashMap::new();
+        for (idf, postings) in &matches {
+            for (id, frequency) in postings {
+                let tf = *frequency as f32;
+                let norm = 1.0 - BM25_B + BM25_B * lengths[id] / average_length;
+                let score = idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm);
+                *scores.entry(*id).or_insert(0.0) += score;
+            }
+        }
+
+        let mut ranked: Vec<(Uuid, f32)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        let ids: Vec<Uuid> = ranked.iter().map(|(id, _)| *id).collect();
+        Ok(self
+            .multi_get_nodes(&ids)?
+            .into_iter()
+            .zip(ranked)
+            .filter_map(|(node, (_, score))| node.map(|node| (node, score)))
+            .take(top_k)
+            .collect())
+    }
 }
 
 #[async_trait]
@@ -127,21 +965,41 @@ impl GraphStore for RocksGraphStore {
         let key = node.id.as_bytes();
         let value = self.serialize_node(&node)?;
 
-        self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| OnyxError::Internal(format!("Failed to add node: {}", e)))?;
+        let _lock = self.record_locks.lock([node.id]).await;
+        let previous = self.get_node_including_deleted(&node.id).await?;
+        let mut batch = WriteBatch::default();
+        if previous.is_none() {
+            self.queue_count_delta(&mut batch, NODE_COUNT_KEY, 1)?;
+        }
+        self.index_node_in_batch(&mut batch, &node, previous.as_ref())?;
+        self.index_text_in_batch(&mut batch, &node, previous.as_ref())?;
+        batch.put_cf(cf, key, value);
+        self.write_batch(batch)?;
+        self.invalidate_cached_node(&node.id);
 
         Ok(())
     }
 
-    async fn get_node(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
+    async fn get_node_including_deleted(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
+        let ticket = match self.node_cache.as_ref().map(|cache| cache.get(id)) {
+            Some(Ok(node)) => return Ok(Some(node)),
+            Some(Err(ticket)) => Some(ticket),
+            None => None,
+        };
+
         let cf = self.cf_nodes()?;
         let key = id.as_bytes();
 
         match self.db.get_cf(cf, key) {
-            Ok(Some(bytes)) => Ok(Some(self.deserialize_node(&bytes)?)),
+            Ok(Some(bytes)) => {
+                let node = self.deserialize_node(key, &bytes)?;
+                if let (Some(cache), Some(ticket)) = (&self.node_cache, ticket) {
+                    cache.fill(node.clone(), ticket);
+                }
+                Ok(Some(node))
+            }
             Ok(None) => Ok(None),
-            Err(e) => Err(OnyxError::Internal(format!("Failed to get node: {}", e))),
+            Err(e) => Err(StorageError::rocks("get node", e).into()),
         }
     }
 
@@ -158,46 +1016,45 @@ impl GraphStore for RocksGraphStore {
         // Get all edges connected to this node
         let outbound_edges = self.get_edge_ids_from_adjacency(cf_outbound, id)?;
         let inbound_edges = self.get_edge_ids_from_adjacency(cf_inbound, id)?;
-
-        // Remove all connected edges
+        // Edges removed since are skipped below; ones added since stay.
+        let ids = outbound_edges.iter().chain(&inbound_edges).copied();
+        let _locks = self.record_locks.lock(ids.chain([*id])).await;
+
+        // Remove the node and all connected edges in one batch. Self-loops
+        // appear in both indices but must only be removed (and counted) once.
+        let mut batch = WriteBatch::default();
+        let mut seen = HashSet::new();
         for edge_id in outbound_edges.iter().chain(inbound_edges.iter()) {
-            self.remove_edge(edge_id).await?;
+            if !seen.insert(*edge_id) {
+                continue;
+            }
+            if let Some(edge) = self.get_edge(edge_id).await? {
+                self.delete_edge_in_batch(&mut batch, &edge)?;
+            }
         }
+        if let Some(node) = self.get_node_including_deleted(id).await? {
+            self.queue_count_delta(&mut batch, NODE_COUNT_KEY, -1)?;
+            let cfs = [self.cf_nodes_by_file()?, self.cf_nodes_by_module()?];
+            for (cf, key) in cfs.into_iter().zip(self.index_keys(&node)) {
+                if let Some(key) = key {
+                    batch.delete_cf(cf, key);
+                }
+            }
+            self.unindex_text_in_batch(&mut batch, &node)?;
+        }
+        batch.delete_cf(cf_nodes, id.as_bytes());
 
-        // Remove the node
-        let key = id.as_bytes();
-        self.db
-            .delete_cf(cf_nodes, key)
-            .map_err(|e| OnyxError::Internal(format!("Failed to remove node: {}", e)))?;
-
+        self.write_batch(batch)?;
+        self.invalidate_cached_node(id);
         Ok(())
     }
 
     async fn add_edge(&self, edge: Edge) -> OnyxResult<()> {
-        let cf_edges = self.cf_edges()?;
-        let cf_outbound = self.cf_node_outbound()?;
-        let cf_inbound = self.cf_node_inbound()?;
-
-        // Store the edge
-        let key = edge.id.as_bytes();
-        let value = self.serialize_edge(&edge)?;
-        self.db
-            .put_cf(cf_edges, key, value)
-            .map_err(|e| OnyxError::Internal(format!("Failed to add edge: {}", e)))?;
-
-        // Update adjacency indices
-        let outbound_key = self.outbound_key(&edge.source_id, &edge.id);
-        let inbound_key = self.inbound_key(&edge.target_id, &edge.id);
-
-        self.db
-            .put_cf(cf_outbound, outbound_key, &[])
-            .map_err(|e| OnyxError::Internal(format!("Failed to update outbound index: {}", e)))?;
-
-        self.db
-            .put_cf(cf_inbound, inbound_key, &[])
-            .map_err(|e| OnyxError::Internal(format!("Failed to update inbound index: {}", e)))?;
-
-        Ok(())
+        // Store the edge and update adjacency indices atomically
+        let _lock = self.record_locks.lock([edge.id]).await;
+        let mut batch = WriteBatch::default();
+        self.put_edge_in_batch(&mut batch, &edge)?;
+        self.write_batch(batch)
     }
 
     async fn get_edge(&self, id: &Uuid) -> OnyxResult<Option<Edge>> {
@@ -205,42 +1062,43 @@ impl GraphStore for RocksGraphStore {
         let key = id.as_bytes();
 
         match self.db.get_cf(cf, key) {
-            Ok(Some(bytes)) => Ok(Some(self.deserialize_edge(&bytes)?)),
+            Ok(Some(bytes)) => Ok(Some(self.deserialize_edge(key, &bytes)?)),
             Ok(None) => Ok(None),
-            Err(e) => Err(OnyxError::Internal(format!("Failed to get edge: {}", e))),
+            Err(e) => Err(StorageError::rocks("get edge", e).into()),
         }
     }
 
     async fn remove_edge(&self, id: &Uuid) -> OnyxResult<()> {
+        let _lock = self.record_locks.lock([*id]).await;
         // First get the edge to know source and target
         let edge = match self.get_edge(id).await? {
             Some(e) => e,
             None => return Ok(()), // Already deleted
         };
 
-        let cf_edges = self.cf_edges()?;
-        let cf_outbound = self.cf_node_outbound()?;
-        let cf_inbound = self.cf_node_inbound()?;
-
-        // Remove from adjacency indices
-        let outbound_key = self.outbound_key(&edge.source_id, id);
-        let inbound_key = self.inbound_key(&edge.target_id, id);
-
-        self.db
-            .delete_cf(cf_outbound, outbound_key)
-            .map_err(|e| OnyxError::Internal(format!("Failed to remove from outbound index: {}", e)))?;
-
-        self.db
-            .delete_cf(cf_inbound, inbound_key)
-            .map_err(|e| OnyxError::Internal(format!("Failed to remove from inbound index: {}", e)))?;
+        // Remove from adjacency indices and the edge store atomically
+        let mut batch = WriteBatch::default();
+        self.delete_edge_in_batch(&mut batch, &edge)?;
+        self.write_batch(batch)
+    }
 
-        // Remove the edge
-        let key = id.as_bytes();
-        self.db
-            .delete_cf(cf_edges, key)
-            .map_err(|e| OnyxError::Internal(format!("Failed to remove edge: {}", e)))?;
+    async fn close_edge(&self, id: &Uuid, timestamp: DateTime<Utc>) -> OnyxResult<Edge> {
+        self.rewrite_edge(id, |edge| edge.valid_until(timestamp))
+            .await
+    }
 
-        Ok(())
+    async fn upsert_edge(&self, edge: Edge) -> OnyxResult<bool> {
+        let existing = self
+            .find_edge(&edge.source_id, &edge.target_id, &edge.edge_type)
+            .await?
+            .filter(Edge::is_active);
+        let Some(existing) = existing else {
+            self.add_edge(edge).await?;
+            return Ok(true);
+        };
+        self.rewrite_edge(&existing.id, |stored| stored.reinforced(&edge))
+            .await?;
+        Ok(false)
     }
 
     async fn get_neighbors(
@@ -248,27 +1106,7 @@ impl GraphStore for RocksGraphStore {
         id: &Uuid,
         edge_types: Option<&[EdgeType]>,
     ) -> OnyxResult<Vec<(Edge, Node)>> {
-        let cf_outbound = self.cf_node_outbound()?;
-        let edge_ids = self.get_edge_ids_from_adjacency(cf_outbound, id)?;
-
-        let mut neighbors = Vec::new();
-        for edge_id in edge_ids {
-            if let Some(edge) = self.get_edge(&edge_id).await? {
-                // Filter by edge type if specified
-                if let Some(types) = edge_types {
-                    if !types.contains(&edge.edge_type) {
-                        continue;
-                    }
-                }
-
-                // Get the target node
-                if let Some(node) = self.get_node(&edge.target_id).await? {
-                    neighbors.push((edge, node));
-                }
-            }
-        }
-
-        Ok(neighbors)
+        self.outbound_neighbors(id, edge_types)
     }
 
     async fn get_inbound(
@@ -278,25 +1116,7 @@ impl GraphStore for RocksGraphStore {
     ) -> OnyxResult<Vec<(Edge, Node)>> {
         let cf_inbound = self.cf_node_inbound()?;
         let edge_ids = self.get_edge_ids_from_adjacency(cf_inbound, id)?;
-
-        let mut inbound = Vec::new();
-        for edge_id in edge_ids {
-            if let Some(edge) = self.get_edge(&edge_id).await? {
-                // Filter by edge type if specified
-                if let Some(types) = edge_types {
-                    if !types.contains(&edge.edge_type) {
-                        continue;
-                    }
-                }
-
-                // Get the source node
-                if let Some(node) = self.get_node(&edge.source_id).await? {
-                    inbound.push((edge, node));
-                }
-            }
-        }
-
-        Ok(inbound)
+        self.resolve_adjacent(&edge_ids, edge_types, |edge| edge.source_id)
     }
 
     async fn traverse(
@@ -305,35 +1125,23 @@ impl GraphStore for RocksGraphStore {
         edge_types: Option<&[EdgeType]>,
         max_depth: usize,
     ) -> OnyxResult<TraversalResult> {
-        let mut visited = HashSet::new();
-        let mut queue = VecDeque::new();
-        let mut nodes = Vec::new();
-        let mut edges = Vec::new();
-
-        queue.push_back((*start_id, 0));
-        visited.insert(*start_id);
-
-        while let Some((node_id, depth)) = queue.pop_front() {
-            nodes.push((node_id, depth));
-
-            if depth < max_depth {
-                let neighbors = self.get_neighbors(&node_id, edge_types).await?;
-                for (edge, neighbor) in neighbors {
-                    edges.push(edge.id);
-
-                    if !visited.contains(&neighbor.id) {
-                        visited.insert(neighbor.id);
-                        queue.push_back((neighbor.id, depth + 1));
-                    }
+        let edge_types: Option<Arc<[EdgeType]>> = edge_types.map(Arc::from);
+        let parallel = self.traversal_concurrency > 1;
+        traverse_parallel(*start_id, max_depth, self.traversal_concurrency, |id| {
+            let store = self.clone();
+            let edge_types = edge_types.clone();
+            async move {
+                if !parallel {
+                    return store.outbound_neighbors(&id, edge_types.as_deref());
                 }
+                tokio::task::spawn_blocking(move || {
+                    store.outbound_neighbors(&id, edge_types.as_deref())
+                })
+                .await
+                .map_err(|e| OnyxError::Internal(format!("Neighbor lookup failed: {}", e)))?
             }
-        }
-
-        Ok(TraversalResult {
-            nodes,
-            edges,
-            total_visited: visited.len(),
         })
+        .await
     }
 
     async fn find_paths(
@@ -372,9 +1180,9 @@ impl GraphStore for RocksGraphStore {
         let mut nodes = Vec::new();
 
         for item in iter {
-            if let Ok((_, value)) = item {
-                if let Ok(node) = self.deserialize_node(&value) {
-                    if &node.node_type == node_type {
+            if let Ok((key, value)) = item {
+                if let Ok(node) = self.deserialize_node(&key, &value) {
+                    if &node.node_type == node_type && !node.is_deleted() {
                         nodes.push(node);
                     }
                 }
@@ -384,6 +1192,18 @@ impl GraphStore for RocksGraphStore {
         nodes
     }
 
+    async fn nodes_by_file(&self, file_path: &str) -> Vec<Node> {
+        self.cf_nodes_by_file()
+            .and_then(|cf| self.indexed_nodes(cf, &self.file_index_prefix(file_path)))
+            .unwrap_or_default()
+    }
+
+    async fn nodes_under_module(&self, prefix: &[String]) -> Vec<Node> {
+        self.cf_nodes_by_module()
+            .and_then(|cf| self.indexed_nodes(cf, &self.module_index_prefix(prefix)))
+            .unwrap_or_default()
+    }
+
     async fn edges_by_type(&self, edge_type: &EdgeType) -> Vec<Edge> {
         let cf = match self.cf_edges() {
             Ok(cf) => cf,
@@ -394,8 +1214,8 @@ impl GraphStore for RocksGraphStore {
         let mut edges = Vec::new();
 
         for item in iter {
-            if let Ok((_, value)) = item {
-                if let Ok(edge) = self.deserialize_edge(&value) {
+            if let Ok((key, value)) = item {
+                if let Ok(edge) = self.deserialize_edge(&key, &value) {
                     if &edge.edge_type == edge_type {
                         edges.push(edge);
                     }
@@ -428,23 +1248,11 @@ impl GraphStore for RocksGraphStore {
     }
 
     async fn node_count(&self) -> usize {
-        let cf = match self.cf_nodes() {
-            Ok(cf) => cf,
-            Err(_) => return 0,
-        };
-
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        iter.count()
+        self.read_counter(NODE_COUNT_KEY).unwrap_or(0)
     }
 
     async fn edge_count(&self) -> usize {
-        let cf = match self.cf_edges() {
-            Ok(cf) => cf,
-            Err(_) => return 0,
-        };
-
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        iter.count()
+        self.read_counter(EDGE_COUNT_KEY).unwrap_or(0)
     }
 
     async fn all_nodes(&self) -> Vec<Node> {
@@ -457,9 +1265,11 @@ impl GraphStore for RocksGraphStore {
         let mut nodes = Vec::new();
 
         for item in iter {
-            if let Ok((_, value)) = item {
-                if let Ok(node) = self.deserialize_node(&value) {
-                    nodes.push(node);
+            if let Ok((key, value)) = item {
+                if let Ok(node) = self.deserialize_node(&key, &value) {
+                    if !node.is_deleted() {
+                        nodes.push(node);
+                    }
                 }
             }
         }
@@ -468,39 +1278,15 @@ impl GraphStore for RocksGraphStore {
     }
 
     async fn get_all_node_ids(&self) -> OnyxResult<Vec<Uuid>> {
-        let cf = self.cf_nodes()?;
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        let mut ids = Vec::new();
-
-        for item in iter {
-            if let Ok((key, _)) = item {
-                if let Ok(id_str) = std::str::from_utf8(&key) {
-                    if let Ok(id) = Uuid::parse_str(id_str) {
-                        ids.push(id);
-                    }
-                }
-            }
-        }
-
-        Ok(ids)
+        self.collect_ids(self.cf_nodes()?, "node")
     }
 
     async fn get_all_edge_ids(&self) -> OnyxResult<Vec<Uuid>> {
-        let cf = self.cf_edges()?;
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        let mut ids = Vec::new();
-
-        for item in iter {
-            if let Ok((key, _)) = item {
-                if let Ok(id_str) = std::str::from_utf8(&key) {
-                    if let Ok(id) = Uuid::parse_str(id_str) {
-                        ids.push(id);
-                    }
-                }
-            }
-        }
+        self.collect_ids(self.cf_edges()?, "edge")
+    }
 
-        Ok(ids)
+    async fn flush(&self) -> OnyxResult<()> {
+        flush_db(&self.db)
     }
 }
 
@@ -526,7 +1312,7 @@ impl RocksGraphStore {
             return Ok(());
         }
 
-        let neighbors = self.get_neighbors(current, None).await?;
+        let neighbors = self.get_active_neighbors(current, None).await?;
         for (_, neighbor) in neighbors {
             if !visited.contains(&neighbor.id) {
                 visited.insert(neighbor.id);
@@ -551,3 +1337,504 @@ impl RocksGraphStore {
         Ok(())
     }
 }
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::OnyxError;
+    use crate::model::node::{CodeEntityKind, Provenance};
+    use crate::store::persistent::open_db;
+    use crate::store::persistent::schema::{legacy_edge_bytes, legacy_node_bytes};
+
+    #[tokio::test]
+    async fn test_missing_column_family_is_typed() {
+        let dir = tempfile::tempdir().unwrap();
+        // Opened without the Onyx column families.
+        let db = Arc::new(DB::open_default(dir.path()).unwrap());
+        let store = RocksGraphStore::new(db);
+
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "func_a",
+            "fn func_a() {}",
+        );
+        let err = store.add_node(node).await.unwrap_err();
+        assert!(matches!(
+            err,
+            OnyxError::Storage(StorageError::MissingColumnFamily(ref cf)) if cf == CF_NODES
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_all_node_ids_decodes_binary_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+
+        for i in 0..3 {
+            let node = Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                format!("func_{}", i),
+                "fn f() {}",
+            );
+            store.add_node(node).await.unwrap();
+        }
+
+        assert_eq!(store.get_all_node_ids().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_flipped_byte_is_reported_as_corrupt_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "func_a",
+            "fn func_a() {}",
+        );
+        let id = node.id;
+        store.add_node(node).await.unwrap();
+
+        let cf = store.cf_nodes().unwrap();
+        let mut value = store.db.get_cf(cf, id.as_bytes()).unwrap().unwrap();
+        let last = value.len() - 1;
+        value[last] ^= 0xFF;
+        store.db.put_cf(cf, id.as_bytes(), value).unwrap();
+
+        let err = store.get_node(&id).await.unwrap_err();
+        assert!(matches!(err, OnyxError::CorruptRecord { id: corrupt } if corrupt == id));
+    }
+
+    #[tokio::test]
+    async fn test_update_invalidates_cached_node() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap()).with_node_cache(16);
+        let mut node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "func_a",
+            "fn func_a() {}",
+        );
+        store.add_node(node.clone()).await.unwrap();
+
+        store.get_node(&node.id).await.unwrap();
+        store.get_node(&node.id).await.unwrap();
+        assert_eq!(
+            store.node_cache_stats(),
+            Some(NodeCacheStats { hits: 1, misses: 1 })
+        );
+
+        node.content = "fn func_a() { todo!() }".to_string();
+        store.update_node(node.clone()).await.unwrap();
+        let read = store.get_node(&node.id).await.unwrap().unwrap();
+        assert_eq!(read.content, node.content);
+
+        store.remove_node(&node.id).await.unwrap();
+        assert!(store.get_node(&node.id).await.unwrap().is_none());
+    }
+
+    #[test]
+    fn test_read_racing_a_write_is_not_cached() {
+        let cache = NodeCache::new(NonZeroUsize::new(4).unwrap());
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "func_a",
+            "fn func_a() {}",
+        );
+
+        // A reader misses and loads the old value while a writer replaces
+        // it and invalidates before the reader fills.
+        let ticket = cache.get(&node.id).unwrap_err();
+        cache.invalidate(&node.id);
+        cache.fill(node.clone(), ticket);
+        assert!(cache.get(&node.id).is_err());
+
+        let ticket = cache.get(&node.id).unwrap_err();
+        cache.fill(node.clone(), ticket);
+        assert!(cache.get(&node.id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_batched_neighbors_match_sequential_lookups() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let function = || {
+            Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                "func",
+                "fn func() {}",
+            )
+        };
+        let hub = function();
+        let hub_id = hub.id;
+        store.add_node(hub).await.unwrap();
+        for i in 0..20 {
+            let target = function();
+            let edge_type = if i % 2 == 0 { EdgeType::Calls } else { EdgeType::Imports };
+            let edge = Edge::new(edge_type, hub_id, target.id);
+            let target_id = target.id;
+            store.add_node(target).await.unwrap();
+            if i % 5 == 0 {
+                // Edges to soft-deleted nodes are skipped by both paths.
+                store.soft_delete_node(&target_id).await.unwrap();
+            }
+            store.add_edge(edge).await.unwrap();
+        }
+
+        for filter in [None, Some(&[EdgeType::Calls][..])] {
+            let mut sequential = Vec::new();
+            let cf = store.cf_node_outbound().unwrap();
+            for edge_id in store.get_edge_ids_from_adjacency(cf, &hub_id).unwrap() {
+                let edge = store.get_edge(&edge_id).await.unwrap().unwrap();
+                if filter.map_or(true, |types| types.contains(&edge.edge_type)) {
+                    if let Some(node) = store.get_node(&edge.target_id).await.unwrap() {
+                        sequential.push((edge.id, node.id));
+                    }
+                }
+            }
+
+            let batched: Vec<(Uuid, Uuid)> = store
+                .get_neighbors(&hub_id, filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|(edge, node)| (edge.id, node.id))
+                .collect();
+            assert_eq!(batched, sequential);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_traversal_matches_sequential() {
+        let dir = tempfile::tempdir().unwrap();
+        let sequential =
+            RocksGraphStore::new(open_db(dir.path()).unwrap()).with_traversal_concurrency(1);
+        let parallel = sequential.clone().with_traversal_concurrency(8);
+        let function = || {
+            Node::new(
+                NodeType::CodeEntity(CodeEntityKind::Function),
+                "func",
+                "fn func() {}",
+            )
+        };
+
+        // A hub calling 12 spokes, each calling 3 leaves; every spoke also
+        // calls its neighbour's first leaf, so leaves are reached twice.
+        let hub = function();
+        let hub_id = hub.id;
+        sequential.add_node(hub).await.unwrap();
+        let mut first_leaves = Vec::new();
+        let mut spokes = Vec::new();
+        for _ in 0..12 {
+            let spoke = function();
+            let spoke_id = spoke.id;
+            sequential.add_node(spoke).await.unwrap();
+            sequential
+                .add_edge(Edge::new(EdgeType::Calls, hub_id, spoke_id))
+                .await
+                .unwrap();
+            for i in 0..3 {
+                let leaf = function();
+                let leaf_id = leaf.id;
+                sequential.add_node(leaf).await.unwrap();
+                sequential
+                    .add_edge(Edge::new(EdgeType::Calls, spoke_id, leaf_id))
+                    .await
+                    .unwrap();
+                if i == 0 {
+                    first_leaves.push(leaf_id);
+                }
+            }
+            spokes.push(spoke_id);
+        }
+        for (i, spoke_id) in spokes.iter().enumerate() {
+            let shared = first_leaves[(i + 1) % first_leaves.len()];
+            sequential
+                .add_edge(Edge::new(EdgeType::Calls, *spoke_id, shared))
+                .await
+                .unwrap();
+        }
+
+        let expected = sequential.traverse(&hub_id, None, 3).await.unwrap();
+        let actual = parallel.traverse(&hub_id, None, 3).await.unwrap();
+        assert_eq!(expected.total_visited, 1 + 12 + 36);
+        assert_eq!(actual.nodes, expected.nodes);
+        assert_eq!(actual.edges, expected.edges);
+        assert_eq!(actual.total_visited, expected.total_visited);
+    }
+
+    #[tokio::test]
+    async fn test_counters_track_inserts_and_removals_across_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let function = |name: &str| {
+            Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "fn f() {}")
+        };
+        let nodes: Vec<Node> = (0..5).map(|i| function(&format!("func_{}", i))).collect();
+
+        {
+            let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+            for node in &nodes {
+                store.add_node(node.clone()).await.unwrap();
+            }
+            // Upserting an existing node does not change the count.
+            store.update_node(nodes[0].clone()).await.unwrap();
+            for pair in nodes.windows(2) {
+                store
+                    .add_edge(Edge::new(EdgeType::Calls, pair[0].id, pair[1].id))
+                    .await
+                    .unwrap();
+            }
+            let self_loop = Edge::new(EdgeType::Calls, nodes[1].id, nodes[1].id);
+            store.add_edge(self_loop).await.unwrap();
+            assert_eq!(store.node_count().await, 5);
+            assert_eq!(store.edge_count().await, 5);
+
+            // Removes the node, its two chain edges and the self-loop.
+            store.remove_node(&nodes[1].id).await.unwrap();
+            store.remove_node(&Uuid::new_v4()).await.unwrap();
+            assert_eq!(store.node_count().await, 4);
+            assert_eq!(store.edge_count().await, 2);
+        }
+
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        assert_eq!(store.node_count().await, 4);
+        assert_eq!(store.edge_count().await, 2);
+
+        // A store without counters recounts on first use.
+        store
+            .db
+            .delete_cf(store.cf_counters().unwrap(), NODE_COUNT_KEY)
+            .unwrap();
+        let store = RocksGraphStore::new(store.db.clone());
+        assert_eq!(store.node_count().await, 4);
+        assert_eq!(
+            store.node_count().await,
+            store.get_all_node_ids().await.unwrap().len()
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_writes_of_one_record_count_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "func_a",
+            "fn func_a() {}",
+        );
+        let edge = Edge::new(EdgeType::Calls, node.id, node.id);
+
+        let writers: Vec<_> = (0..16)
+            .map(|_| {
+                let (store, node, edge) = (store.clone(), node.clone(), edge.clone());
+                tokio::spawn(async move {
+                    store.add_node(node).await.unwrap();
+                    store
+                        .add_edges_atomic(vec![edge.clone(), edge])
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+        assert_eq!(store.node_count().await, 1);
+        assert_eq!(store.edge_count().await, 1);
+
+        let edge_id = edge.id;
+        let removers: Vec<_> = (0..16)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move { store.remove_edge(&edge_id).await.unwrap() })
+            })
+            .collect();
+        for remover in removers {
+            remover.await.unwrap();
+        }
+        assert_eq!(store.edge_count().await, 0);
+        assert_eq!(store.node_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_close_edge_rewrites_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let edge = Edge::new(EdgeType::Calls, Uuid::new_v4(), Uuid::new_v4());
+        store.add_edge(edge.clone()).await.unwrap();
+
+        let closed_at = Utc::now();
+        let closed = store.close_edge(&edge.id, closed_at).await.unwrap();
+        assert!(!closed.is_active());
+        let stored = store.get_edge(&edge.id).await.unwrap().unwrap();
+        assert_eq!(stored.temporal.until_timestamp, Some(closed_at));
+        assert_eq!(store.has_adjacency(&edge).unwrap(), (true, true));
+        assert_eq!(store.edge_count().await, 1);
+        assert!(matches!(
+            store.close_edge(&Uuid::new_v4(), closed_at).await,
+            Err(OnyxError::EdgeNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_edge_reinforces_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let edge = || Edge::new(EdgeType::DependsOn, a, b).with_confidence(0.8);
+
+        assert!(store.upsert_edge(edge()).await.unwrap());
+        assert!(!store.upsert_edge(edge()).await.unwrap());
+
+        let merged = store
+            .find_edge(&a, &b, &EdgeType::DependsOn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!((merged.confidence - 0.96).abs() < 1e-9);
+        assert_eq!(store.has_adjacency(&merged).unwrap(), (true, true));
+        assert_eq!(store.edge_count().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_nodes_by_file_uses_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let in_file = |name: &str, path: &str| {
+            Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, "fn f() {}")
+                .with_provenance(Provenance::new(path))
+        };
+
+        let discount = in_file("apply_discount", "src/billing/discount.rs");
+        let total = in_file("calculate_total", "src/billing/discount.rs");
+        let invoice = in_file("render_invoice", "src/billing/discount.rs.bak");
+        let mut moved = in_file("round_cents", "src/billing/discount.rs");
+        for node in [&discount, &total, &invoice, &moved] {
+            store.add_node(node.clone()).await.unwrap();
+        }
+
+        // Moving a node to another file drops its stale index entry.
+        moved.provenance.file_path = Some("src/billing/money.rs".to_string());
+        store.update_node(moved.clone()).await.unwrap();
+
+        let mut names: Vec<String> = store
+            .nodes_by_file("src/billing/discount.rs")
+            .await
+            .into_iter()
+            .map(|n| n.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["apply_discount", "calculate_total"]);
+        assert_eq!(store.nodes_by_file("src/billing/money.rs").await.len(), 1);
+
+        store.remove_node(&discount.id).await.unwrap();
+        assert_eq!(store.nodes_by_file("src/billing/discount.rs").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_nodes_under_module_over_demo_data() {
+        use crate::config::LimitsConfig;
+        use crate::ingest::demo::build_synthetic_codebase;
+        use crate::ingest::ingest_codebase;
+        use crate::model::embedding::BagOfWordsEmbedder;
+        use crate::store::transaction::TransactionManager;
+
+        let units = build_synthetic_codebase();
+        let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+        let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+        let mut memory = TransactionManager::new();
+        ingest_codebase(&mut memory, &units, &embedder, &LimitsConfig::default())
+            .await
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        for node in memory.graph_store.all_nodes().await {
+            store.add_node(node).await.unwrap();
+        }
+
+        let billing = ["billing".to_string()];
+        let mut expected = vec!["apply_discount", "calculate_total", "get_discount_rules"];
+        expected.sort();
+        for nodes in [
+            store.nodes_under_module(&billing).await,
+            memory.graph_store.nodes_under_module(&billing).await,
+        ] {
+            let mut names: Vec<String> = nodes.into_iter().map(|n| n.name).collect();
+            names.sort();
+            assert_eq!(names, expected);
+        }
+
+        let discount = ["billing".to_string(), "discount".to_string()];
+        assert_eq!(store.nodes_under_module(&discount).await.len(), 2);
+        assert!(store.nodes_under_module(&["bill".to_string()]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_text_search_ranks_node_with_all_terms_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let function = |name: &str, content: &str| {
+            Node::new(NodeType::CodeEntity(CodeEntityKind::Function), name, content)
+        };
+
+        let both = function("apply", "fn apply(total: f64) -> f64 { discount(total) }");
+        let discount_only = function("rules", "fn rules() { discount(); discount(); }");
+        let total_only = function("sum", "fn sum(items: &[f64]) -> f64 { total(items) }");
+        let neither = function("log", "fn log(msg: &str) { println!(\"{}\", msg); }");
+        for node in [&both, &discount_only, &total_only, &neither] {
+            store.add_node(node.clone()).await.unwrap();
+        }
+
+        let results = store.text_search("discount total", 10).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].0.id, both.id);
+        assert!(results[0].1 > results[1].1);
+
+        // Updates and removals keep the index in step.
+        let mut renamed = discount_only.clone();
+        renamed.content = "fn rules() {}".to_string();
+        store.update_node(renamed).await.unwrap();
+        store.remove_node(&both.id).await.unwrap();
+        let results = store.text_search("discount", 10).unwrap();
+        assert!(results.is_empty());
+        assert_eq!(store.text_search("TOTAL", 1).unwrap()[0].0.id, total_only.id);
+    }
+
+    #[tokio::test]
+    async fn test_reads_legacy_unprefixed_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksGraphStore::new(open_db(dir.path()).unwrap());
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "legacy",
+            "fn legacy() {}",
+        );
+        let edge = Edge::new(EdgeType::Calls, node.id, node.id);
+        store
+            .db
+            .put_cf(
+                store.cf_nodes().unwrap(),
+                node.id.as_bytes(),
+                legacy_node_bytes(&node),
+            )
+            .unwrap();
+        store
+            .db
+            .put_cf(
+                store.cf_edges().unwrap(),
+                edge.id.as_bytes(),
+                legacy_edge_bytes(&edge),
+            )
+            .unwrap();
+
+        let read = store.get_node(&node.id).await.unwrap().unwrap();
+        assert_eq!(read.name, "legacy");
+        assert_eq!(read.deleted_at, None);
+        let read = store.get_edge(&edge.id).await.unwrap().unwrap();
+        assert_eq!(read.source_id, node.id);
+        assert!(read.provenance.is_none());
+    }
+}
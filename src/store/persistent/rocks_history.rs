@@ -2,72 +2,81 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use rocksdb::DB;
+use rocksdb::{WriteBatch, DB};
 use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::error::{OnyxError, OnyxResult};
-use crate::model::version::{Branch, Diff, VersionEntry, VersionId};
+use crate::error::{OnyxError, OnyxResult, StorageError};
+use crate::model::version::{new_version_id, Branch, Diff, VersionEntry, VersionId};
 use crate::store::history::HistoryStore;
 
-use super::{CF_BRANCHES, CF_VERSIONS, CF_VERSION_CHAINS};
+use super::schema;
+use super::{flush_db, DurabilityMode, CF_BRANCHES, CF_VERSIONS, CF_VERSION_CHAINS};
 
 /// RocksDB-backed history store for version chains and branching.
 #[derive(Clone)]
 pub struct RocksHistoryStore {
     db: Arc<DB>,
+    durability: DurabilityMode,
 }
 
 impl RocksHistoryStore {
     /// Create a new RocksDB history store.
     pub fn new(db: Arc<DB>) -> Self {
-        Self { db }
+        Self {
+            db,
+            durability: DurabilityMode::default(),
+        }
+    }
+
+    /// Write with the durability `mode` the database was opened with.
+    pub fn with_durability(mut self, mode: DurabilityMode) -> Self {
+        self.durability = mode;
+        self
     }
 
-    /// Serialize a version entry to bytes.
+    /// Serialize a version entry to schema-versioned bytes.
     fn serialize_version(&self, entry: &VersionEntry) -> OnyxResult<Vec<u8>> {
-        bincode::serialize(entry)
-            .map_err(|e| OnyxError::Internal(format!("Failed to serialize version: {}", e)))
+        schema::encode(entry)
     }
 
-    /// Deserialize a version entry from bytes.
+    /// Deserialize a version entry, migrating older schemas.
     fn deserialize_version(&self, bytes: &[u8]) -> OnyxResult<VersionEntry> {
-        bincode::deserialize(bytes)
-            .map_err(|e| OnyxError::Internal(format!("Failed to deserialize version: {}", e)))
+        schema::decode(bytes)
     }
 
     /// Serialize a branch to bytes.
     fn serialize_branch(&self, branch: &Branch) -> OnyxResult<Vec<u8>> {
         bincode::serialize(branch)
-            .map_err(|e| OnyxError::Internal(format!("Failed to serialize branch: {}", e)))
+            .map_err(|e| StorageError::serialization("branch", e).into())
     }
 
     /// Deserialize a branch from bytes.
     fn deserialize_branch(&self, bytes: &[u8]) -> OnyxResult<Branch> {
         bincode::deserialize(bytes)
-            .map_err(|e| OnyxError::Internal(format!("Failed to deserialize branch: {}", e)))
+            .map_err(|e| StorageError::deserialization("branch", e).into())
     }
 
     /// Get the versions column family handle.
     fn cf_versions(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_VERSIONS)
-            .ok_or_else(|| OnyxError::Internal("Missing versions column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_VERSIONS.to_string()).into())
     }
 
     /// Get the version chains column family handle.
     fn cf_version_chains(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_VERSION_CHAINS)
-            .ok_or_else(|| OnyxError::Internal("Missing version_chains column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_VERSION_CHAINS.to_string()).into())
     }
 
     /// Get the branches column family handle.
     fn cf_branches(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_BRANCHES)
-            .ok_or_else(|| OnyxError::Internal("Missing branches column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_BRANCHES.to_string()).into())
     }
 
     /// Build chain index key: [entity_id (16 bytes)][timestamp (8 bytes)]
@@ -77,43 +86,26 @@ impl RocksHistoryStore {
         key
     }
 
-    /// Apply a diff to reconstruct content.
+    /// Apply a diff to reconstruct content. Content changes store the full
+    /// new content.
     fn apply_diff(&self, base_content: &str, diff: &Diff) -> String {
         match diff {
-            Diff::Full(content) => content.clone(),
-            Diff::Delta(delta) => {
-                // Simple line-based delta reconstruction
-                // Format: "+line" (add), "-line" (remove), " line" (unchanged)
-                let mut result = String::new();
-                let base_lines: Vec<&str> = base_content.lines().collect();
-                let mut base_idx = 0;
-
-                for line in delta.lines() {
-                    if let Some(op) = line.chars().next() {
-                        match op {
-                            '+' => {
-                                result.push_str(&line[1..]);
-                                result.push('\n');
-                            }
-                            '-' => {
-                                base_idx += 1;
-                            }
-                            ' ' => {
-                                if base_idx < base_lines.len() {
-                                    result.push_str(base_lines[base_idx]);
-                                    result.push('\n');
-                                    base_idx += 1;
-                                }
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-
-                result
-            }
+            Diff::Initial { content } => content.clone(),
+            Diff::ContentChanged { patch, .. } => patch.clone(),
+            Diff::MetadataChanged { .. } | Diff::Deleted => base_content.to_string(),
+            Diff::Composite(diffs) => diffs
+                .iter()
+                .fold(base_content.to_string(), |content, diff| {
+                    self.apply_diff(&content, diff)
+                }),
         }
     }
+
+    /// Decode a version ID stored as a key or index value.
+    fn decode_version_id(bytes: &[u8]) -> OnyxResult<VersionId> {
+        String::from_utf8(bytes.to_vec())
+            .map_err(|e| StorageError::deserialization("version ID", e).into())
+    }
 }
 
 #[async_trait]
@@ -128,14 +120,19 @@ impl HistoryStore for RocksHistoryStore {
         let key = version_id.as_bytes();
         let value = self.serialize_version(&entry)?;
         self.db
-            .put_cf(cf_versions, key, value)
-            .map_err(|e| OnyxError::Internal(format!("Failed to record version: {}", e)))?;
+            .put_cf_opt(cf_versions, key, value, &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("record version", e))?;
 
         // Update the chain index
         let chain_key = self.chain_key(&entry.entity_id, &entry.timestamp);
         self.db
-            .put_cf(cf_chains, chain_key, version_id.as_bytes())
-            .map_err(|e| OnyxError::Internal(format!("Failed to update chain index: {}", e)))?;
+            .put_cf_opt(
+                cf_chains,
+                chain_key,
+                version_id.as_bytes(),
+                &self.durability.write_options(),
+            )
+            .map_err(|e| StorageError::rocks("update chain index", e))?;
 
         Ok(version_id)
     }
@@ -147,7 +144,7 @@ impl HistoryStore for RocksHistoryStore {
         match self.db.get_cf(cf, key) {
             Ok(Some(bytes)) => Ok(Some(self.deserialize_version(&bytes)?)),
             Ok(None) => Ok(None),
-            Err(e) => Err(OnyxError::Internal(format!("Failed to get version: {}", e))),
+            Err(e) => Err(StorageError::rocks("get version", e).into()),
         }
     }
 
@@ -215,10 +212,9 @@ impl HistoryStore for RocksHistoryStore {
 
         for item in iter {
             let (_, value) = item
-                .map_err(|e| OnyxError::Internal(format!("Failed to iterate versions: {}", e)))?;
+                .map_err(|e| StorageError::rocks("iterate versions", e))?;
 
-            let version_id = VersionId::from_slice(&value)
-                .map_err(|e| OnyxError::Internal(format!("Invalid version UUID: {}", e)))?;
+            let version_id = Self::decode_version_id(&value)?;
 
             if let Some(entry) = self.get_version(&version_id).await? {
                 versions.push(entry);
@@ -258,18 +254,20 @@ impl HistoryStore for RocksHistoryStore {
     async fn create_branch(&self, name: &str, base_version: VersionId) -> OnyxResult<()> {
         let cf = self.cf_branches()?;
 
-        let branch = Branch {
-            name: name.to_string(),
-            base_version,
-            created_at: Utc::now(),
-        };
+        if self.get_version(&base_version).await?.is_none() {
+            return Err(OnyxError::VersionNotFound(base_version));
+        }
+        if self.get_branch(name).await?.is_some() {
+            return Err(OnyxError::BranchAlreadyExists(name.to_string()));
+        }
 
+        let branch = Branch::new(name, base_version);
         let key = name.as_bytes();
         let value = self.serialize_branch(&branch)?;
 
         self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| OnyxError::Internal(format!("Failed to create branch: {}", e)))?;
+            .put_cf_opt(cf, key, value, &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("create branch", e))?;
 
         Ok(())
     }
@@ -281,10 +279,26 @@ impl HistoryStore for RocksHistoryStore {
         match self.db.get_cf(cf, key) {
             Ok(Some(bytes)) => Ok(Some(self.deserialize_branch(&bytes)?)),
             Ok(None) => Ok(None),
-            Err(e) => Err(OnyxError::Internal(format!("Failed to get branch: {}", e))),
+            Err(e) => Err(StorageError::rocks("get branch", e).into()),
         }
     }
 
+    async fn put_branch(&self, branch: Branch) -> OnyxResult<()> {
+        let cf = self.cf_branches()?;
+        let value = self.serialize_branch(&branch)?;
+
+        self.db
+            .put_cf_opt(
+                cf,
+                branch.name.as_bytes(),
+                value,
+                &self.durability.write_options(),
+            )
+            .map_err(|e| StorageError::rocks("put branch", e))?;
+
+        Ok(())
+    }
+
     async fn list_branches(&self) -> Vec<Branch> {
         let cf = match self.cf_branches() {
             Ok(cf) => cf,
@@ -306,40 +320,38 @@ impl HistoryStore for RocksHistoryStore {
     }
 
     async fn merge_branch(&self, source: &str, target: &str) -> OnyxResult<VersionId> {
-        // TODO: Implement proper three-way merge logic
-        // For now, create a merge version that points to both branches
-        let source_branch = self
+        let mut source_branch = self
             .get_branch(source)
             .await?
-            .ok_or_else(|| OnyxError::NotFound(format!("Source branch not found: {}", source)))?;
-
-        let target_branch = self
+            .ok_or_else(|| OnyxError::BranchNotFound(source.to_string()))?;
+        let mut target_branch = self
             .get_branch(target)
             .await?
-            .ok_or_else(|| OnyxError::NotFound(format!("Target branch not found: {}", target)))?;
+            .ok_or_else(|| OnyxError::BranchNotFound(target.to_string()))?;
 
-        // Get the latest version from source
-        let source_version = self
-            .get_version(&source_branch.base_version)
-            .await?
-            .ok_or_else(|| OnyxError::NotFound("Source version not found".to_string()))?;
-
-        // Create a merge version
-        let merge_version = VersionEntry {
-            version_id: VersionId::new_v4(),
-            entity_id: source_version.entity_id,
-            parent_version: Some(target_branch.base_version.clone()),
+        // Record a merge version entry
+        let merge_entry = VersionEntry {
+            version_id: new_version_id(),
+            entity_id: Uuid::nil(),
+            parent_version: Some(source_branch.head.clone()),
             branch: target.to_string(),
-            diff: Diff::Full(format!("Merged {} into {}", source, target)),
+            diff: Diff::Initial {
+                content: format!("Merge branch '{}' into '{}'", source, target),
+            },
             commit_id: None,
-            author: Some("system".to_string()),
+            author: None,
             message: Some(format!("Merge branch '{}' into '{}'", source, target)),
             timestamp: Utc::now(),
         };
+        let merge_version_id = self.record_version(merge_entry).await?;
 
-        self.record_version(merge_version.clone()).await?;
+        // Mark source branch as merged and move the target head
+        source_branch.merged_into = Some(target.to_string());
+        target_branch.head = merge_version_id.clone();
+        self.put_branch(source_branch).await?;
+        self.put_branch(target_branch).await?;
 
-        Ok(merge_version.version_id)
+        Ok(merge_version_id)
     }
 
     async fn version_count(&self) -> usize {
@@ -359,7 +371,7 @@ impl HistoryStore for RocksHistoryStore {
 
         for item in iter {
             if let Ok((key, _)) = item {
-                if let Ok(id) = VersionId::from_slice(&key) {
+                if let Ok(id) = Self::decode_version_id(&key) {
                     ids.push(id);
                 }
             }
@@ -367,4 +379,45 @@ impl HistoryStore for RocksHistoryStore {
 
         Ok(ids)
     }
+
+    /// Heads are derived from the version chain, so dropping the version
+    /// restores the previous head on its own.
+    async fn remove_version(
+        &self,
+        version_id: &VersionId,
+        _previous_head: Option<VersionId>,
+    ) -> OnyxResult<()> {
+        let entry = self
+            .get_version(version_id)
+            .await?
+            .ok_or_else(|| OnyxError::VersionNotFound(version_id.clone()))?;
+
+        let mut batch = WriteBatch::default();
+        batch.delete_cf(self.cf_versions()?, version_id.as_bytes());
+        batch.delete_cf(
+            self.cf_version_chains()?,
+            self.chain_key(&entry.entity_id, &entry.timestamp),
+        );
+        self.db
+            .write_opt(batch, &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("remove version", e))?;
+
+        Ok(())
+    }
+
+    async fn delete_branch(&self, name: &str) -> OnyxResult<()> {
+        self.db
+            .delete_cf_opt(
+                self.cf_branches()?,
+                name.as_bytes(),
+                &self.durability.write_options(),
+            )
+            .map_err(|e| StorageError::rocks("delete branch", e))?;
+
+        Ok(())
+    }
+
+    async fn flush(&self) -> OnyxResult<()> {
+        flush_db(&self.db)
+    }
 }
@@ -1,46 +1,152 @@
 //! RocksDB-backed vector store with HNSW index.
 
 use async_trait::async_trait;
-use rocksdb::DB;
+use rocksdb::{WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::error::{OnyxError, OnyxResult};
-use crate::model::embedding::Embedding;
-use crate::store::vector::VectorStore;
+use crate::error::{OnyxError, OnyxResult, StorageError};
+use crate::store::vector::{check_embedding, compare_scores, VectorStore};
 
-use super::{CF_EMBEDDINGS, CF_HNSW_LAYERS};
+use super::{flush_db, DurabilityMode, CF_EMBEDDINGS};
+
+// ---------------------------------------------------------------------------
+// Storage encodings
+// ---------------------------------------------------------------------------
+
+/// How a [`RocksVectorStore`] encodes the vectors it writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VectorStorage {
+    /// Full-precision `f32` values.
+    #[default]
+    Full,
+    /// Symmetric scalar quantization to `i8` codes plus one `f32` scale per
+    /// vector: roughly a quarter of the size, at a small loss of precision.
+    Quantized8,
+}
+
+/// A vector quantized to `i8` codes; value `i` is approximately
+/// `codes[i] as f32 * scale`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedVector {
+    pub scale: f32,
+    pub codes: Vec<i8>,
+}
+
+impl QuantizedVector {
+    /// Quantize `vector`, mapping its largest absolute value to ±127.
+    pub fn quantize(vector: &[f32]) -> Self {
+        let max_abs = vector.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+        if max_abs == 0.0 {
+            return Self {
+                scale: 0.0,
+                codes: vec![0; vector.len()],
+            };
+        }
+
+        let scale = max_abs / i8::MAX as f32;
+        let codes = vector
+            .iter()
+            .map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+            .collect();
+        Self { scale, codes }
+    }
+
+    /// Reconstruct approximate `f32` values.
+    pub fn dequantize(&self) -> Vec<f32> {
+        self.codes.iter().map(|&c| c as f32 * self.scale).collect()
+    }
+}
+
+/// On-disk value in the embeddings column family. Tagged, so a database
+/// can be reopened with a different [`VectorStorage`] and still be read.
+#[derive(Debug, Serialize, Deserialize)]
+enum StoredVector {
+    Full(Vec<f32>),
+    Quantized8(QuantizedVector),
+}
+
+// ---------------------------------------------------------------------------
+// RocksVectorStore
+// ---------------------------------------------------------------------------
 
 /// RocksDB-backed vector store with HNSW indexing for fast similarity search.
+///
+/// Every stored vector must have exactly `dimension` values.
 #[derive(Clone)]
 pub struct RocksVectorStore {
     db: Arc<DB>,
     dimension: usize,
+    storage: VectorStorage,
+    durability: DurabilityMode,
 }
 
 impl RocksVectorStore {
-    /// Create a new RocksDB vector store.
+    /// Create a new RocksDB vector store storing full-precision vectors.
     pub fn new(db: Arc<DB>, dimension: usize) -> Self {
-        Self { db, dimension }
+        Self::with_storage(db, dimension, VectorStorage::Full)
+    }
+
+    /// Create a new RocksDB vector store with the given vector encoding.
+    pub fn with_storage(db: Arc<DB>, dimension: usize, storage: VectorStorage) -> Self {
+        Self {
+            db,
+            dimension,
+            storage,
+            durability: DurabilityMode::default(),
+        }
     }
 
-    /// Serialize an embedding to bytes.
-    fn serialize_embedding(&self, embedding: &Embedding) -> OnyxResult<Vec<u8>> {
-        bincode::serialize(embedding)
-            .map_err(|e| OnyxError::Internal(format!("Failed to serialize embedding: {}", e)))
+    /// Write with the durability `mode` the database was opened with.
+    pub fn with_durability(mut self, mode: DurabilityMode) -> Self {
+        self.durability = mode;
+        self
     }
 
-    /// Deserialize an embedding from bytes.
-    fn deserialize_embedding(&self, bytes: &[u8]) -> OnyxResult<Embedding> {
-        bincode::deserialize(bytes)
-            .map_err(|e| OnyxError::Internal(format!("Failed to deserialize embedding: {}", e)))
+    /// The encoding used for newly written vectors.
+    pub fn storage(&self) -> VectorStorage {
+        self.storage
+    }
+
+    /// Total size in bytes of all stored embedding keys and values.
+    pub fn stored_bytes(&self) -> OnyxResult<u64> {
+        let cf = self.cf_embeddings()?;
+        let mut total = 0u64;
+        for item in self.db.iterator_cf(cf, rocksdb::IteratorMode::Start) {
+            let (key, value) = item.map_err(|e| StorageError::rocks("iterate embeddings", e))?;
+            total += (key.len() + value.len()) as u64;
+        }
+        Ok(total)
+    }
+
+    /// Encode a vector to bytes using this store's storage mode.
+    fn serialize_vector(&self, vector: &[f32]) -> OnyxResult<Vec<u8>> {
+        let stored = match self.storage {
+            VectorStorage::Full => StoredVector::Full(vector.to_vec()),
+            VectorStorage::Quantized8 => {
+                StoredVector::Quantized8(QuantizedVector::quantize(vector))
+            }
+        };
+        bincode::serialize(&stored).map_err(|e| StorageError::serialization("embedding", e).into())
+    }
+
+    /// Decode a stored vector, dequantizing if needed.
+    fn deserialize_vector(&self, bytes: &[u8]) -> OnyxResult<Vec<f32>> {
+        let stored: StoredVector = bincode::deserialize(bytes)
+            .map_err(|e| StorageError::deserialization("embedding", e))?;
+        Ok(match stored {
+            StoredVector::Full(vector) => vector,
+            StoredVector::Quantized8(quantized) => quantized.dequantize(),
+        })
     }
 
     /// Get the embeddings column family handle.
     fn cf_embeddings(&self) -> OnyxResult<&rocksdb::ColumnFamily> {
         self.db
             .cf_handle(CF_EMBEDDINGS)
-            .ok_or_else(|| OnyxError::Internal("Missing embeddings column family".to_string()))
+            .ok_or_else(|| StorageError::MissingColumnFamily(CF_EMBEDDINGS.to_string()).into())
     }
 
     /// Calculate cosine similarity between two vectors.
@@ -63,77 +169,86 @@ impl RocksVectorStore {
 
 #[async_trait]
 impl VectorStore for RocksVectorStore {
-    async fn add_embedding(&self, embedding: Embedding) -> OnyxResult<()> {
-        let cf = self.cf_embeddings()?;
-        let key = embedding.node_id.as_bytes();
-        let value = self.serialize_embedding(&embedding)?;
+    async fn insert(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
+        check_embedding(Some(self.dimension), &embedding)?;
 
+        let cf = self.cf_embeddings()?;
+        let value = self.serialize_vector(&embedding)?;
         self.db
-            .put_cf(cf, key, value)
-            .map_err(|e| OnyxError::Internal(format!("Failed to add embedding: {}", e)))?;
+            .put_cf_opt(cf, id.as_bytes(), value, &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("insert embedding", e))?;
 
         Ok(())
     }
 
-    async fn get_embedding(&self, node_id: &Uuid) -> OnyxResult<Option<Embedding>> {
-        let cf = self.cf_embeddings()?;
-        let key = node_id.as_bytes();
-
-        match self.db.get_cf(cf, key) {
-            Ok(Some(bytes)) => Ok(Some(self.deserialize_embedding(&bytes)?)),
-            Ok(None) => Ok(None),
-            Err(e) => Err(OnyxError::Internal(format!("Failed to get embedding: {}", e))),
-        }
+    async fn search(&self, query: &[f32], k: usize) -> OnyxResult<Vec<(Uuid, f32)>> {
+        self.search_excluding(query, k, &HashSet::new()).await
     }
 
-    async fn remove_embedding(&self, node_id: &Uuid) -> OnyxResult<()> {
-        let cf = self.cf_embeddings()?;
-        let key = node_id.as_bytes();
-
-        self.db
-            .delete_cf(cf, key)
-            .map_err(|e| OnyxError::Internal(format!("Failed to remove embedding: {}", e)))?;
+    async fn search_excluding(
+        &self,
+        query: &[f32],
+        k: usize,
+        exclude: &HashSet<Uuid>,
+    ) -> OnyxResult<Vec<(Uuid, f32)>> {
+        check_embedding(Some(self.dimension), query)?;
 
-        Ok(())
-    }
-
-    async fn search(&self, query: &[f32], top_k: usize) -> OnyxResult<Vec<(Uuid, f32)>> {
         // TODO: Implement HNSW index for production performance
         // For now, use brute-force linear search as a working baseline
-
         let cf = self.cf_embeddings()?;
         let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
 
         let mut results = Vec::new();
 
         for item in iter {
-            let (key, value) = item
-                .map_err(|e| OnyxError::Internal(format!("Failed to iterate embeddings: {}", e)))?;
+            let (key, value) = item.map_err(|e| StorageError::rocks("iterate embeddings", e))?;
 
             let node_id = Uuid::from_slice(&key)
-                .map_err(|e| OnyxError::Internal(format!("Invalid node UUID: {}", e)))?;
+                .map_err(|e| StorageError::deserialization("node UUID", e))?;
+            if exclude.contains(&node_id) {
+                continue;
+            }
 
-            let embedding = self.deserialize_embedding(&value)?;
-            let similarity = self.cosine_similarity(query, &embedding.vector);
+            let vector = self.deserialize_vector(&value)?;
+            let similarity = self.cosine_similarity(query, &vector);
 
             results.push((node_id, similarity));
         }
 
-        // Sort by similarity (descending) and take top_k
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        results.truncate(top_k);
+        // Sort by similarity (descending) and take top k
+        results.sort_by(|a, b| compare_scores(a.1, b.1));
+        results.truncate(k);
 
         Ok(results)
     }
 
-    async fn batch_add(&self, embeddings: Vec<Embedding>) -> OnyxResult<()> {
-        for embedding in embeddings {
-            self.add_embedding(embedding).await?;
-        }
+    async fn delete(&self, id: &Uuid) -> OnyxResult<()> {
+        let cf = self.cf_embeddings()?;
+        self.db
+            .delete_cf_opt(cf, id.as_bytes(), &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("delete embedding", e))?;
+
         Ok(())
     }
 
-    async fn embedding_count(&self) -> usize {
+    async fn update(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
+        if self.get(&id).await?.is_none() {
+            return Err(OnyxError::NodeNotFound(id));
+        }
+        self.insert(id, embedding).await
+    }
+
+    async fn get(&self, id: &Uuid) -> OnyxResult<Option<Vec<f32>>> {
+        let cf = self.cf_embeddings()?;
+
+        match self.db.get_cf(cf, id.as_bytes()) {
+            Ok(Some(bytes)) => Ok(Some(self.deserialize_vector(&bytes)?)),
+            Ok(None) => Ok(None),
+            Err(e) => Err(StorageError::rocks("get embedding", e).into()),
+        }
+    }
+
+    async fn len(&self) -> usize {
         let cf = match self.cf_embeddings() {
             Ok(cf) => cf,
             Err(_) => return 0,
@@ -143,24 +258,14 @@ impl VectorStore for RocksVectorStore {
         iter.count()
     }
 
-    async fn all_embeddings(&self) -> Vec<Embedding> {
+    async fn is_empty(&self) -> bool {
         let cf = match self.cf_embeddings() {
             Ok(cf) => cf,
-            Err(_) => return vec![],
+            Err(_) => return true,
         };
 
-        let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
-        let mut embeddings = Vec::new();
-
-        for item in iter {
-            if let Ok((_, value)) = item {
-                if let Ok(embedding) = self.deserialize_embedding(&value) {
-                    embeddings.push(embedding);
-                }
-            }
-        }
-
-        embeddings
+        let mut iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
+        iter.next().is_none()
     }
 
     async fn get_all_embedding_ids(&self) -> OnyxResult<Vec<Uuid>> {
@@ -168,39 +273,143 @@ impl VectorStore for RocksVectorStore {
         let iter = self.db.iterator_cf(cf, rocksdb::IteratorMode::Start);
         let mut ids = Vec::new();
 
+        // Keys are raw 16-byte UUIDs, matching `insert`.
         for item in iter {
-            if let Ok((key, _)) = item {
-                if let Ok(id_str) = std::str::from_utf8(&key) {
-                    if let Ok(id) = Uuid::parse_str(id_str) {
-                        ids.push(id);
-                    }
-                }
-            }
+            let (key, _) = item.map_err(|e| StorageError::rocks("iterate embeddings", e))?;
+            let id = Uuid::from_slice(&key)
+                .map_err(|e| StorageError::deserialization("node UUID", e))?;
+            ids.push(id);
         }
 
         Ok(ids)
     }
 
-    async fn insert_embedding(&self, embedding: Embedding) -> OnyxResult<()> {
-        self.insert(embedding.node_id, embedding.values).await
+    fn dimensions(&self) -> Option<usize> {
+        Some(self.dimension)
     }
 
-    async fn get_embedding(&self, id: &Uuid) -> OnyxResult<Option<Embedding>> {
-        let vector = self.get(id).await?;
-        Ok(vector.map(|v| Embedding {
-            node_id: *id,
-            values: v,
-        }))
+    async fn insert_batch(&self, embeddings: Vec<(Uuid, Vec<f32>)>) -> OnyxResult<()> {
+        let cf = self.cf_embeddings()?;
+        let mut batch = WriteBatch::default();
+        for (id, embedding) in &embeddings {
+            check_embedding(Some(self.dimension), embedding)?;
+            batch.put_cf(cf, id.as_bytes(), self.serialize_vector(embedding)?);
+        }
+
+        self.db
+            .write_opt(batch, &self.durability.write_options())
+            .map_err(|e| StorageError::rocks("insert embedding batch", e))?;
+
+        Ok(())
+    }
+
+    async fn flush(&self) -> OnyxResult<()> {
+        flush_db(&self.db)
     }
 }
 
 // TODO: Production HNSW implementation
 //
 // The production implementation should:
-// 1. Build HNSW layers on add_embedding (using hnsw crate or custom implementation)
+// 1. Build HNSW layers on insert (using hnsw crate or custom implementation)
 // 2. Persist HNSW graph structure in CF_HNSW_LAYERS
 // 3. Use HNSW for search() instead of brute-force linear scan
 // 4. Support incremental index updates
 // 5. Optimize with SIMD for vector operations (e.g., using simdeez or packed_simd)
 //
 // For initial testing and prototyping, the brute-force approach above is sufficient.
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::persistent::open_db;
+
+    #[tokio::test]
+    async fn test_wrong_dimension_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksVectorStore::new(open_db(dir.path()).unwrap(), 3);
+
+        let result = store.insert(Uuid::new_v4(), vec![1.0, 0.0]).await;
+        assert!(matches!(
+            result,
+            Err(OnyxError::DimensionMismatch {
+                expected: 3,
+                got: 2
+            })
+        ));
+
+        let result = store
+            .insert_batch(vec![
+                (Uuid::new_v4(), vec![1.0, 0.0, 0.0]),
+                (Uuid::new_v4(), vec![1.0; 4]),
+            ])
+            .await;
+        assert!(matches!(
+            result,
+            Err(OnyxError::DimensionMismatch {
+                expected: 3,
+                got: 4
+            })
+        ));
+        assert_eq!(store.len().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_search_excluding_never_returns_excluded_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = RocksVectorStore::new(open_db(dir.path()).unwrap(), 3);
+        let (nearest, other) = (Uuid::new_v4(), Uuid::new_v4());
+        store.insert(nearest, vec![1.0, 0.0, 0.0]).await.unwrap();
+        store.insert(other, vec![0.0, 1.0, 0.0]).await.unwrap();
+
+        let exclude = HashSet::from([nearest]);
+        let results = store
+            .search_excluding(&[1.0, 0.0, 0.0], 2, &exclude)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, other);
+    }
+
+    #[test]
+    fn test_quantize_round_trip_within_tolerance() {
+        let vector: Vec<f32> = (0..64).map(|i| ((i as f32) * 0.37).sin() * 3.0).collect();
+        let quantized = QuantizedVector::quantize(&vector);
+        let restored = quantized.dequantize();
+
+        assert_eq!(restored.len(), vector.len());
+        for (original, approx) in vector.iter().zip(&restored) {
+            assert!((original - approx).abs() <= quantized.scale / 2.0 + f32::EPSILON);
+        }
+
+        let zeros = QuantizedVector::quantize(&[0.0; 4]);
+        assert_eq!(zeros.dequantize(), vec![0.0; 4]);
+    }
+
+    #[tokio::test]
+    async fn test_quantized_store_reads_back_and_is_smaller() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(dir.path()).unwrap();
+        let full = RocksVectorStore::new(db.clone(), 32);
+        let id = Uuid::new_v4();
+        let vector: Vec<f32> = (0..32).map(|i| i as f32 / 31.0 - 0.5).collect();
+        full.insert(id, vector.clone()).await.unwrap();
+        let full_bytes = full.stored_bytes().unwrap();
+
+        let quantized = RocksVectorStore::with_storage(db, 32, VectorStorage::Quantized8);
+        quantized.insert(id, vector.clone()).await.unwrap();
+        assert!(quantized.stored_bytes().unwrap() < full_bytes / 2);
+
+        let restored = quantized.get(&id).await.unwrap().unwrap();
+        let max_error = vector
+            .iter()
+            .zip(&restored)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0f32, f32::max);
+        assert!(max_error < 0.01);
+    }
+}
@@ -0,0 +1,427 @@
+//! Schema-versioned encoding of persisted records.
+//!
+//! Nodes, edges and version entries are written as a [`SchemaEnvelope`]
+//! behind a short magic prefix. Values without the prefix predate schema
+//! versioning and are read as version 0. Records from an older schema are
+//! upgraded on read through [`Versioned::migrate`].
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{OnyxResult, StorageError};
+use crate::model::edge::{Edge, EdgeType, TemporalContext};
+use crate::model::node::{
+    CodeEntityExt, ConfigExt, ConfigFormat, ConfigType, DocExt, Node, NodeExtension, NodeType,
+    Provenance, TestExt, TestKind, TestResult,
+};
+use crate::model::version::{VersionEntry, VersionId};
+
+/// Schema version written for every record kind by this build.
+pub const SCHEMA_VERSION: u16 = 1;
+
+/// Marks a value encoded as a [`SchemaEnvelope`]. Unversioned bincode never
+/// starts with these bytes.
+const SCHEMA_MAGIC: [u8; 4] = [0xC5, b'O', b'S', 0x01];
+
+/// What is stored on disk: the record and the schema it was written with.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchemaEnvelope<T> {
+    pub schema_version: u16,
+    pub record: T,
+}
+
+/// A record type persisted with a schema version.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Name used in serialization errors, e.g. `node`.
+    const KIND: &'static str;
+
+    /// Schema version new records of this kind are written with.
+    const SCHEMA_VERSION: u16 = SCHEMA_VERSION;
+
+    /// Decode `bytes` written with an older schema `version`.
+    ///
+    /// The default reads them with the current layout, which holds as long
+    /// as no field has been added since `version`. Override it when the
+    /// layout changes, decoding the old shape and filling in defaults.
+    fn migrate(version: u16, bytes: &[u8]) -> bincode::Result<Self> {
+        let _ = version;
+        bincode::deserialize(bytes)
+    }
+}
+
+impl Versioned for Node {
+    const KIND: &'static str = "node";
+
+    fn migrate(version: u16, bytes: &[u8]) -> bincode::Result<Self> {
+        match version {
+            0 => bincode::deserialize::<NodeV0>(bytes).map(Node::from),
+            _ => bincode::deserialize(bytes),
+        }
+    }
+}
+
+impl Versioned for Edge {
+    const KIND: &'static str = "edge";
+
+    fn migrate(version: u16, bytes: &[u8]) -> bincode::Result<Self> {
+        match version {
+            0 => bincode::deserialize::<EdgeV0>(bytes).map(Edge::from),
+            _ => bincode::deserialize(bytes),
+        }
+    }
+}
+
+impl Versioned for VersionEntry {
+    const KIND: &'static str = "version";
+}
+
+// ---------------------------------------------------------------------------
+// Layouts written before schema versioning
+// ---------------------------------------------------------------------------
+//
+// bincode is positional, so fields added since then (even with
+// `#[serde(default)]`) shift everything after them. These mirror the old
+// layouts field for field.
+
+/// [`Node`] before `deleted_at` was added.
+#[derive(Deserialize)]
+struct NodeV0 {
+    id: Uuid,
+    node_type: NodeType,
+    name: String,
+    content: String,
+    content_hash: [u8; 32],
+    metadata: HashMap<String, String>,
+    provenance: Provenance,
+    embedding: Option<Vec<f32>>,
+    current_version: Option<VersionId>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    extension: NodeExtensionV0,
+}
+
+/// [`NodeExtension`] before tests gained a framework and call targets and
+/// configs gained their keys.
+#[derive(Deserialize)]
+enum NodeExtensionV0 {
+    CodeEntity(CodeEntityExt),
+    Doc(DocExt),
+    Test(TestExtV0),
+    Config(ConfigExtV0),
+    None,
+}
+
+#[derive(Deserialize)]
+struct TestExtV0 {
+    test_kind: TestKind,
+    target_ids: Vec<Uuid>,
+    last_result: Option<TestResult>,
+}
+
+#[derive(Deserialize)]
+struct ConfigExtV0 {
+    config_type: ConfigType,
+    format: ConfigFormat,
+}
+
+/// [`Edge`] before `provenance` was added.
+#[derive(Deserialize)]
+struct EdgeV0 {
+    id: Uuid,
+    edge_type: EdgeType,
+    source_id: Uuid,
+    target_id: Uuid,
+    confidence: f64,
+    metadata: HashMap<String, String>,
+    temporal: TemporalContext,
+}
+
+impl From<NodeV0> for Node {
+    fn from(old: NodeV0) -> Self {
+        let extension = match old.extension {
+            NodeExtensionV0::CodeEntity(ext) => NodeExtension::CodeEntity(ext),
+            NodeExtensionV0::Doc(ext) => NodeExtension::Doc(ext),
+            NodeExtensionV0::Test(ext) => NodeExtension::Test(TestExt {
+                test_kind: ext.test_kind,
+                target_ids: ext.target_ids,
+                last_result: ext.last_result,
+                ..TestExt::default()
+            }),
+            NodeExtensionV0::Config(ext) => NodeExtension::Config(ConfigExt {
+                config_type: ext.config_type,
+                format: ext.format,
+                ..ConfigExt::default()
+            }),
+            NodeExtensionV0::None => NodeExtension::None,
+        };
+        Node {
+            id: old.id,
+            node_type: old.node_type,
+            name: old.name,
+            content: old.content,
+            content_hash: old.content_hash,
+            metadata: old.metadata,
+            provenance: old.provenance,
+            embedding: old.embedding,
+            current_version: old.current_version,
+            created_at: old.created_at,
+            updated_at: old.updated_at,
+            deleted_at: None,
+            extension,
+        }
+    }
+}
+
+impl From<EdgeV0> for Edge {
+    fn from(old: EdgeV0) -> Self {
+        Edge {
+            id: old.id,
+            edge_type: old.edge_type,
+            source_id: old.source_id,
+            target_id: old.target_id,
+            confidence: old.confidence,
+            metadata: old.metadata,
+            provenance: None,
+            temporal: old.temporal,
+        }
+    }
+}
+
+/// `node` as a build before schema versioning wrote it: bincode without an
+/// envelope, in the old field order. Only extensions whose layout has not
+/// changed since (code entities, docs, none) are supported.
+#[cfg(test)]
+pub(crate) fn legacy_node_bytes(node: &Node) -> Vec<u8> {
+    assert!(
+        !matches!(
+            node.extension,
+            NodeExtension::Test(_) | NodeExtension::Config(_)
+        ),
+        "test and config extensions changed layout"
+    );
+    bincode::serialize(&(
+        node.id,
+        &node.node_type,
+        &node.name,
+        &node.content,
+        node.content_hash,
+        &node.metadata,
+        &node.provenance,
+        &node.embedding,
+        &node.current_version,
+        node.created_at,
+        node.updated_at,
+        &node.extension,
+    ))
+    .unwrap()
+}
+
+/// `edge` as a build before schema versioning wrote it.
+#[cfg(test)]
+pub(crate) fn legacy_edge_bytes(edge: &Edge) -> Vec<u8> {
+    bincode::serialize(&(
+        edge.id,
+        &edge.edge_type,
+        edge.source_id,
+        edge.target_id,
+        edge.confidence,
+        &edge.metadata,
+        &edge.temporal,
+    ))
+    .unwrap()
+}
+
+/// Encode `record` in a [`SchemaEnvelope`] at its current schema version.
+pub fn encode<T: Versioned>(record: &T) -> OnyxResult<Vec<u8>> {
+    let envelope = SchemaEnvelope {
+        schema_version: T::SCHEMA_VERSION,
+        record,
+    };
+    let mut bytes = SCHEMA_MAGIC.to_vec();
+    bincode::serialize_into(&mut bytes, &envelope)
+        .map_err(|e| StorageError::serialization(T::KIND, e))?;
+    Ok(bytes)
+}
+
+/// Decode a record written by [`encode`] or before schema versioning,
+/// migrating it if it was written with an older schema.
+pub fn decode<T: Versioned>(bytes: &[u8]) -> OnyxResult<T> {
+    let (version, payload) = split_envelope(bytes);
+    let decoded = if version == T::SCHEMA_VERSION {
+        bincode::deserialize(payload)
+    } else if version < T::SCHEMA_VERSION {
+        T::migrate(version, payload)
+    } else {
+        return Err(StorageError::UnsupportedSchema {
+            what: T::KIND,
+            version,
+        }
+        .into());
+    };
+    decoded.map_err(|e| StorageError::deserialization(T::KIND, e).into())
+}
+
+/// Split `bytes` into the schema version and the encoded record. The
+/// envelope's `u16` is bincode's fixed-width little-endian encoding.
+fn split_envelope(bytes: &[u8]) -> (u16, &[u8]) {
+    match bytes.strip_prefix(&SCHEMA_MAGIC) {
+        Some([lo, hi, payload @ ..]) => (u16::from_le_bytes([*lo, *hi]), payload),
+        _ => (0, bytes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::edge::EdgeType;
+    use crate::model::node::{CodeEntityKind, NodeType};
+    use uuid::Uuid;
+
+    /// A record as an older build wrote it.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct ConfigV1 {
+        name: String,
+    }
+
+    impl Versioned for ConfigV1 {
+        const KIND: &'static str = "config";
+    }
+
+    /// The same record after a field was added in schema version 2.
+    #[derive(Debug, Serialize, Deserialize)]
+    struct ConfigV2 {
+        name: String,
+        retries: u32,
+    }
+
+    impl Versioned for ConfigV2 {
+        const KIND: &'static str = "config";
+        const SCHEMA_VERSION: u16 = 2;
+
+        fn migrate(version: u16, bytes: &[u8]) -> bincode::Result<Self> {
+            match version {
+                1 => {
+                    let old: ConfigV1 = bincode::deserialize(bytes)?;
+                    Ok(ConfigV2 {
+                        name: old.name,
+                        retries: 3,
+                    })
+                }
+                _ => bincode::deserialize(bytes),
+            }
+        }
+    }
+
+    #[test]
+    fn test_reader_upgrades_previous_schema() {
+        let old = encode(&ConfigV1 {
+            name: "ingest".to_string(),
+        })
+        .unwrap();
+        let current = encode(&ConfigV2 {
+            name: "serve".to_string(),
+            retries: 5,
+        })
+        .unwrap();
+
+        let upgraded: ConfigV2 = decode(&old).unwrap();
+        assert_eq!(upgraded.name, "ingest");
+        assert_eq!(upgraded.retries, 3);
+
+        let read: ConfigV2 = decode(&current).unwrap();
+        assert_eq!(read.name, "serve");
+        assert_eq!(read.retries, 5);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let bytes = encode(&ConfigV2 {
+            name: "serve".to_string(),
+            retries: 5,
+        })
+        .unwrap();
+        let err = decode::<ConfigV1>(&bytes).unwrap_err();
+        assert!(err.to_string().contains("schema version 2"));
+    }
+
+    #[test]
+    fn test_records_round_trip_and_read_unversioned() {
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "calculate_total",
+            "fn calculate_total() {}",
+        );
+        let read: Node = decode(&encode(&node).unwrap()).unwrap();
+        assert_eq!(read.id, node.id);
+        assert_eq!(read.content_hash, node.content_hash);
+
+        let edge = Edge::new(EdgeType::Calls, Uuid::new_v4(), Uuid::new_v4());
+        let read: Edge = decode(&encode(&edge).unwrap()).unwrap();
+        assert_eq!(read.id, edge.id);
+
+        let entry = VersionEntry::initial(node.id, "fn calculate_total() {}");
+        let read: VersionEntry = decode(&encode(&entry).unwrap()).unwrap();
+        assert_eq!(read.version_id, entry.version_id);
+    }
+
+    #[test]
+    fn test_reads_records_written_before_schema_versioning() {
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "calculate_total",
+            "fn calculate_total() {}",
+        )
+        .with_metadata("owner", "billing");
+        let read: Node = decode(&legacy_node_bytes(&node)).unwrap();
+        assert_eq!(read.id, node.id);
+        assert_eq!(read.name, "calculate_total");
+        assert_eq!(read.metadata["owner"], "billing");
+        assert_eq!(read.deleted_at, None);
+        assert!(matches!(read.extension, NodeExtension::CodeEntity(_)));
+
+        let edge = Edge::new(EdgeType::Calls, Uuid::new_v4(), Uuid::new_v4()).with_confidence(0.8);
+        let read: Edge = decode(&legacy_edge_bytes(&edge)).unwrap();
+        assert_eq!(read.id, edge.id);
+        assert_eq!(read.target_id, edge.target_id);
+        assert_eq!(read.confidence, 0.8);
+        assert!(read.provenance.is_none());
+        assert!(read.is_active());
+
+        // A test node, whose extension has gained fields since: bincode
+        // writes the variant index, then the old three fields.
+        let target = Uuid::new_v4();
+        let test = Node::new(NodeType::Test, "test_total", "#[test] fn test_total() {}");
+        let old_extension = (
+            2u32,
+            (TestKind::Integration, vec![target], None::<TestResult>),
+        );
+        let bytes = bincode::serialize(&(
+            test.id,
+            &test.node_type,
+            &test.name,
+            &test.content,
+            test.content_hash,
+            &test.metadata,
+            &test.provenance,
+            &test.embedding,
+            &test.current_version,
+            test.created_at,
+            test.updated_at,
+            old_extension,
+        ))
+        .unwrap();
+        let read: Node = decode(&bytes).unwrap();
+        match read.extension {
+            NodeExtension::Test(ext) => {
+                assert_eq!(ext.test_kind, TestKind::Integration);
+                assert_eq!(ext.target_ids, [target]);
+                assert!(ext.targets.is_empty());
+            }
+            other => panic!("unexpected extension {:?}", other),
+        }
+    }
+}
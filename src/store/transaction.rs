@@ -1,12 +1,17 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::db::OnyxDatabase;
 use crate::error::{OnyxError, OnyxResult};
-use crate::model::edge::Edge;
+use crate::model::edge::{Edge, EdgeType};
 use crate::model::node::Node;
-use crate::model::version::{VersionEntry, VersionId};
-use crate::store::graph::{GraphStore, InMemoryGraphStore};
+use crate::model::version::{Branch, VersionEntry, VersionId};
+use crate::store::events::{ChangeEvent, EventSink, StoreEvent};
+use crate::store::graph::{ConflictPolicy, GraphStore, InMemoryGraphStore, NodeUpsert};
 use crate::store::history::{HistoryStore, InMemoryHistoryStore};
 use crate::store::vector::{InMemoryVectorStore, VectorStore};
 
@@ -22,9 +27,14 @@ use crate::store::vector::{InMemoryVectorStore, VectorStore};
 /// 2. On commit, operations are applied to each store in order
 /// 3. On failure, the WAL is replayed in reverse to undo partial writes
 ///
-/// ## Supports both In-Memory and SurrealDB backends
+/// ## Supports in-memory, SurrealDB and RocksDB backends
 /// The manager can work with either in-memory stores for testing/prototyping
-/// or SurrealDB-backed stores for production use.
+/// or database-backed stores for production use. When created with
+/// [`with_database`](Self::with_database) or
+/// [`with_async_stores`](Self::with_async_stores), `execute`,
+/// `execute_batch`, `commit` and `stats` go through an
+/// [`AsyncTransactionManager`] and the public in-memory stores are left
+/// untouched; read through [`graph`](Self::graph) and friends instead.
 pub struct TransactionManager {
     /// In-memory stores (for testing/prototyping)
     pub vector_store: InMemoryVectorStore,
@@ -34,32 +44,76 @@ pub struct TransactionManager {
     pending_ops: Vec<TransactionOp>,
     /// Whether a transaction is currently active.
     in_transaction: bool,
-    /// Optional SurrealDB connection for persistent storage
-    db: Option<Arc<OnyxDatabase>>,
+    /// Database-backed stores, used instead of the in-memory ones when set.
+    backend: Option<AsyncTransactionManager>,
+    /// Bumped on every write, so caches can tell when they are stale.
+    generation: u64,
 }
 
 /// Individual operations that can be part of a transaction.
 #[derive(Debug, Clone)]
 pub enum TransactionOp {
     InsertNode(Node),
+    UpdateNode(Node),
     RemoveNode(Uuid),
     InsertEdge(Edge),
     RemoveEdge(Uuid),
     InsertEmbedding { id: Uuid, embedding: Vec<f32> },
     DeleteEmbedding(Uuid),
     RecordVersion(VersionEntry),
+    /// Insert or replace a branch's metadata as-is.
+    PutBranch(Branch),
 }
 
-/// Result of applying an operation, used for rollback.
+/// Result of applying an operation: everything needed to undo it.
 #[derive(Debug)]
 enum AppliedOp {
     NodeInserted(Uuid),
-    NodeRemoved(Node),
+    NodeUpdated(Node),
+    /// The removed node and the edges removed along with it.
+    NodeRemoved {
+        node: Node,
+        edges: Vec<Edge>,
+    },
     EdgeInserted(Uuid),
     EdgeRemoved(Edge),
-    EmbeddingInserted(Uuid),
-    EmbeddingDeleted { id: Uuid, embedding: Vec<f32> },
-    VersionRecorded(VersionId),
+    /// `previous` is the embedding that was overwritten, if any.
+    EmbeddingInserted {
+        id: Uuid,
+        previous: Option<Vec<f32>>,
+    },
+    EmbeddingDeleted {
+        id: Uuid,
+        embedding: Vec<f32>,
+    },
+    /// `previous_head` is the branch head the new version replaced.
+    VersionRecorded {
+        version_id: VersionId,
+        previous_head: Option<VersionId>,
+    },
+    /// `previous` is the branch metadata that was replaced, if any.
+    BranchPut {
+        name: String,
+        previous: Option<Branch>,
+    },
+}
+
+/// Why [`AsyncTransactionManager::try_execute_batch`] failed.
+#[derive(Debug)]
+pub struct BatchFailure {
+    /// Position of the failing operation in the batch, or `None` when the
+    /// batch failed as a whole, e.g. on commit.
+    pub op_index: Option<usize>,
+    pub error: OnyxError,
+}
+
+impl BatchFailure {
+    fn whole(error: OnyxError) -> Self {
+        Self {
+            op_index: None,
+            error,
+        }
+    }
 }
 
 impl TransactionManager {
@@ -71,7 +125,8 @@ impl TransactionManager {
             history_store: InMemoryHistoryStore::new(),
             pending_ops: Vec::new(),
             in_transaction: false,
-            db: None,
+            generation: 0,
+            backend: None,
         }
     }
 
@@ -87,7 +142,8 @@ impl TransactionManager {
             history_store,
             pending_ops: Vec::new(),
             in_transaction: false,
-            db: None,
+            generation: 0,
+            backend: None,
         }
     }
 
@@ -99,10 +155,59 @@ impl TransactionManager {
             history_store: InMemoryHistoryStore::new(),
             pending_ops: Vec::new(),
             in_transaction: false,
-            db: Some(db),
+            generation: 0,
+            backend: Some(AsyncTransactionManager::new(db)),
+        }
+    }
+
+    /// Create a transaction manager over already opened stores, such as the
+    /// RocksDB ones from `open_stores`.
+    ///
+    /// Like [`with_database`](Self::with_database), it must be used inside a
+    /// multi-threaded tokio runtime.
+    pub fn with_async_stores(stores: AsyncTransactionManager) -> Self {
+        Self {
+            backend: Some(stores),
+            ..Self::new()
         }
     }
 
+    /// The graph store that writes go to: the database-backed one when this
+    /// manager has one, the in-memory one otherwise. Read through
+    /// this rather than the public fields.
+    pub fn graph(&self) -> &dyn GraphStore {
+        match &self.backend {
+            Some(backend) => backend.graph_store.as_ref(),
+            None => &self.graph_store,
+        }
+    }
+
+    /// The vector store that writes go to; see [`graph`](Self::graph).
+    pub fn vector(&self) -> &dyn VectorStore {
+        match &self.backend {
+            Some(backend) => backend.vector_store.as_ref(),
+            None => &self.vector_store,
+        }
+    }
+
+    /// The history store that writes go to; see [`graph`](Self::graph).
+    pub fn history(&self) -> &dyn HistoryStore {
+        match &self.backend {
+            Some(backend) => backend.history_store.as_ref(),
+            None => &self.history_store,
+        }
+    }
+
+    /// The SurrealDB connection, if this manager was created with one.
+    pub fn database(&self) -> Option<&Arc<OnyxDatabase>> {
+        self.backend.as_ref().and_then(|backend| backend.database())
+    }
+
+    /// A counter that changes whenever a write goes through this manager.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     /// Begin a new transaction.
     pub fn begin(&mut self) -> OnyxResult<()> {
         if self.in_transaction {
@@ -136,6 +241,12 @@ impl TransactionManager {
         }
 
         let ops = std::mem::take(&mut self.pending_ops);
+        self.generation += 1;
+        if let Some(backend) = &self.backend {
+            self.in_transaction = false;
+            return block_on_backend(backend.execute_batch(ops));
+        }
+
         let mut applied: Vec<AppliedOp> = Vec::new();
 
         for op in ops {
@@ -143,13 +254,19 @@ impl TransactionManager {
                 Ok(applied_op) => applied.push(applied_op),
                 Err(e) => {
                     // Rollback all previously applied operations
-                    self.rollback_applied(&applied);
+                    let rolled_back = applied.len();
+                    let rollback = self.rollback_applied(applied);
                     self.in_transaction = false;
-                    return Err(OnyxError::TransactionFailed(format!(
-                        "Operation failed: {}. Rolled back {} operations.",
-                        e,
-                        applied.len()
-                    )));
+                    return Err(OnyxError::TransactionFailed(match rollback {
+                        Ok(()) => format!(
+                            "Operation failed: {}. Rolled back {} operations.",
+                            e, rolled_back
+                        ),
+                        Err(rollback_err) => format!(
+                            "Operation failed: {}. Rollback also failed: {}",
+                            e, rollback_err
+                        ),
+                    }));
                 }
             }
         }
@@ -172,6 +289,10 @@ impl TransactionManager {
 
     /// Execute a single operation outside of a transaction (auto-commit).
     pub fn execute(&mut self, op: TransactionOp) -> OnyxResult<()> {
+        self.generation += 1;
+        if let Some(backend) = &self.backend {
+            return block_on_backend(backend.execute(op));
+        }
         self.apply_op(op)?;
         Ok(())
     }
@@ -185,6 +306,40 @@ impl TransactionManager {
         self.commit()
     }
 
+    /// Upsert `node` by key, recording any new version in the history
+    /// store; see [`GraphStore::upsert_node_by_key`].
+    pub async fn upsert_node_by_key(
+        &mut self,
+        node: Node,
+        policy: ConflictPolicy,
+    ) -> OnyxResult<NodeUpsert> {
+        self.generation += 1;
+        match &self.backend {
+            Some(backend) => backend.upsert_node_by_key(node, policy).await,
+            None => {
+                self.graph_store
+                    .upsert_node_by_key(node, policy, &self.history_store)
+                    .await
+            }
+        }
+    }
+
+    /// Soft-delete a node and record the deletion in its history, atomically.
+    ///
+    /// Returns the ID of the deletion version. Earlier content stays
+    /// reachable through the history store.
+    pub fn soft_delete_node(&mut self, id: &Uuid) -> OnyxResult<VersionId> {
+        let node = match &self.backend {
+            Some(backend) => block_on_backend(backend.graph_store.get_node(id))?,
+            None => block_on(self.graph_store.get_node(id))?,
+        }
+        .ok_or(OnyxError::NodeNotFound(*id))?;
+
+        let (version_id, ops) = soft_delete_ops(node);
+        self.execute_batch(ops)?;
+        Ok(version_id)
+    }
+
     /// Apply a single operation to the stores.
     fn apply_op(&mut self, op: TransactionOp) -> OnyxResult<AppliedOp> {
         match op {
@@ -193,13 +348,22 @@ impl TransactionManager {
                 self.graph_store.add_node_blocking(node)?;
                 Ok(AppliedOp::NodeInserted(id))
             }
+            TransactionOp::UpdateNode(node) => {
+                let previous = self
+                    .graph_store
+                    .get_node_blocking(&node.id)?
+                    .ok_or(OnyxError::NodeNotFound(node.id))?;
+                self.graph_store.update_node_blocking(node)?;
+                Ok(AppliedOp::NodeUpdated(previous))
+            }
             TransactionOp::RemoveNode(id) => {
                 let node = self
                     .graph_store
                     .get_node_blocking(&id)?
                     .ok_or(OnyxError::NodeNotFound(id))?;
+                let edges = self.graph_store.connected_edges_blocking(&id)?;
                 self.graph_store.remove_node_blocking(&id)?;
-                Ok(AppliedOp::NodeRemoved(node))
+                Ok(AppliedOp::NodeRemoved { node, edges })
             }
             TransactionOp::InsertEdge(edge) => {
                 let id = edge.id;
@@ -215,8 +379,9 @@ impl TransactionManager {
                 Ok(AppliedOp::EdgeRemoved(edge))
             }
             TransactionOp::InsertEmbedding { id, embedding } => {
-                self.vector_store.insert_blocking(id, embedding.clone())?;
-                Ok(AppliedOp::EmbeddingInserted(id))
+                let previous = self.vector_store.get_blocking(&id)?;
+                self.vector_store.insert_blocking(id, embedding)?;
+                Ok(AppliedOp::EmbeddingInserted { id, previous })
             }
             TransactionOp::DeleteEmbedding(id) => {
                 let embedding = self
@@ -227,48 +392,86 @@ impl TransactionManager {
                 Ok(AppliedOp::EmbeddingDeleted { id, embedding })
             }
             TransactionOp::RecordVersion(entry) => {
-                let vid = self.history_store.record_version_blocking(entry)?;
-                Ok(AppliedOp::VersionRecorded(vid))
+                let previous_head = self
+                    .history_store
+                    .get_head_blocking(&entry.entity_id, &entry.branch)?;
+                let version_id = self.history_store.record_version_blocking(entry)?;
+                Ok(AppliedOp::VersionRecorded {
+                    version_id,
+                    previous_head,
+                })
+            }
+            TransactionOp::PutBranch(branch) => {
+                let name = branch.name.clone();
+                let previous = self.history_store.put_branch_blocking(branch);
+                Ok(AppliedOp::BranchPut { name, previous })
             }
         }
     }
 
-    /// Best-effort rollback of applied operations in reverse order.
-    fn rollback_applied(&mut self, applied: &[AppliedOp]) {
-        for op in applied.iter().rev() {
-            match op {
-                AppliedOp::NodeInserted(id) => {
-                    let _ = self.graph_store.remove_node_blocking(id);
-                }
-                AppliedOp::NodeRemoved(node) => {
-                    let _ = self.graph_store.add_node_blocking(node.clone());
-                }
-                AppliedOp::EdgeInserted(id) => {
-                    let _ = self.graph_store.remove_edge_blocking(id);
-                }
-                AppliedOp::EdgeRemoved(edge) => {
-                    let _ = self.graph_store.add_edge_blocking(edge.clone());
-                }
-                AppliedOp::EmbeddingInserted(id) => {
-                    let _ = self.vector_store.delete_blocking(id);
-                }
-                AppliedOp::EmbeddingDeleted { id, embedding } => {
-                    let _ = self.vector_store.insert_blocking(*id, embedding.clone());
-                }
-                AppliedOp::VersionRecorded(_vid) => {
-                    // Version entries are append-only; rollback is a no-op.
+    /// Undo applied operations in reverse order, restoring the pre-batch state.
+    ///
+    /// Every step is attempted; the first error encountered is returned.
+    fn rollback_applied(&mut self, applied: Vec<AppliedOp>) -> OnyxResult<()> {
+        let mut first_error = None;
+        for op in applied.into_iter().rev() {
+            if let Err(e) = self.undo(op) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Undo a single applied operation.
+    fn undo(&mut self, op: AppliedOp) -> OnyxResult<()> {
+        match op {
+            AppliedOp::NodeInserted(id) => self.graph_store.remove_node_blocking(&id),
+            AppliedOp::NodeUpdated(previous) => self.graph_store.update_node_blocking(previous),
+            AppliedOp::NodeRemoved { node, edges } => {
+                self.graph_store.add_node_blocking(node)?;
+                for edge in edges {
+                    self.graph_store.add_edge_blocking(edge)?;
                 }
+                Ok(())
+            }
+            AppliedOp::EdgeInserted(id) => self.graph_store.remove_edge_blocking(&id),
+            AppliedOp::EdgeRemoved(edge) => self.graph_store.add_edge_blocking(edge),
+            AppliedOp::EmbeddingInserted { id, previous } => match previous {
+                Some(embedding) => self.vector_store.insert_blocking(id, embedding),
+                None => self.vector_store.delete_blocking(&id),
+            },
+            AppliedOp::EmbeddingDeleted { id, embedding } => {
+                self.vector_store.insert_blocking(id, embedding)
+            }
+            AppliedOp::VersionRecorded {
+                version_id,
+                previous_head,
+            } => self
+                .history_store
+                .remove_version_blocking(&version_id, previous_head),
+            AppliedOp::BranchPut { name, previous } => {
+                match previous {
+                    Some(branch) => self.history_store.put_branch_blocking(branch),
+                    None => self.history_store.remove_branch_blocking(&name),
+                };
+                Ok(())
             }
         }
     }
 
-    /// Get store statistics.
+    /// Get store statistics. Counts only; the per-type maps are left empty.
     pub fn stats(&self) -> StoreStats {
+        if let Some(backend) = &self.backend {
+            return block_on_backend(backend.stats());
+        }
         StoreStats {
             node_count: self.graph_store.node_count_blocking(),
             edge_count: self.graph_store.edge_count_blocking(),
             embedding_count: self.vector_store.len_blocking(),
             version_count: self.history_store.version_count_blocking(),
+            nodes_by_type: BTreeMap::new(),
+            edges_by_type: BTreeMap::new(),
+            embedding_dimension: self.vector_store.dimensions(),
         }
     }
 }
@@ -280,12 +483,41 @@ impl Default for TransactionManager {
 }
 
 /// Statistics about the current state of all stores.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoreStats {
     pub node_count: usize,
     pub edge_count: usize,
     pub embedding_count: usize,
     pub version_count: usize,
+    /// Live nodes per [`NodeType::as_str`](crate::model::node::NodeType::as_str) label.
+    /// Only filled by [`AsyncTransactionManager::stats_with_breakdown`].
+    pub nodes_by_type: BTreeMap<String, usize>,
+    /// Edges per [`EdgeType::as_str`] label; types with no edges are omitted.
+    pub edges_by_type: BTreeMap<String, usize>,
+    /// Dimensionality of stored embeddings, once known.
+    pub embedding_dimension: Option<usize>,
+}
+
+/// Count live nodes and edges by type.
+async fn type_breakdown<G: GraphStore + ?Sized>(
+    graph: &G,
+) -> (BTreeMap<String, usize>, BTreeMap<String, usize>) {
+    let mut nodes_by_type = BTreeMap::new();
+    for node in graph.all_nodes().await {
+        *nodes_by_type
+            .entry(node.node_type.as_str().to_string())
+            .or_insert(0) += 1;
+    }
+
+    let mut edges_by_type = BTreeMap::new();
+    for edge_type in EdgeType::ALL {
+        let count = graph.edges_by_type(&edge_type).await.len();
+        if count > 0 {
+            edges_by_type.insert(edge_type.as_str().to_string(), count);
+        }
+    }
+
+    (nodes_by_type, edges_by_type)
 }
 
 impl std::fmt::Display for StoreStats {
@@ -299,38 +531,101 @@ impl std::fmt::Display for StoreStats {
 }
 
 // ---------------------------------------------------------------------------
-// Async Transaction Manager for SurrealDB
+// Async Transaction Manager for SurrealDB and other async backends
 // ---------------------------------------------------------------------------
 
 use crate::store::graph::SurrealGraphStore;
 use crate::store::history::SurrealHistoryStore;
 use crate::store::vector::SurrealVectorStore;
 
-/// Async transaction manager for SurrealDB-backed stores.
+/// Async transaction manager over shared stores, SurrealDB-backed by default.
+///
+/// With an [`EventSink`] attached, every successful `execute` and
+/// `execute_batch` also publishes a [`StoreEvent`] describing its changes.
+///
+/// Clones share the same stores and event sink.
+#[derive(Clone)]
 pub struct AsyncTransactionManager {
-    pub vector_store: SurrealVectorStore,
-    pub graph_store: SurrealGraphStore,
-    pub history_store: SurrealHistoryStore,
-    db: Arc<OnyxDatabase>,
+    pub vector_store: Arc<dyn VectorStore>,
+    pub graph_store: Arc<dyn GraphStore>,
+    pub history_store: Arc<dyn HistoryStore>,
+    /// The SurrealDB connection behind the stores, if they use one.
+    db: Option<Arc<OnyxDatabase>>,
+    events: Option<EventSink>,
 }
 
 impl AsyncTransactionManager {
     /// Create a new async transaction manager with SurrealDB.
     pub fn new(db: Arc<OnyxDatabase>) -> Self {
         Self {
-            vector_store: SurrealVectorStore::new(db.clone()),
-            graph_store: SurrealGraphStore::new(db.clone()),
-            history_store: SurrealHistoryStore::new(db.clone()),
-            db,
+            vector_store: Arc::new(SurrealVectorStore::new(db.clone())),
+            graph_store: Arc::new(SurrealGraphStore::new(db.clone())),
+            history_store: Arc::new(SurrealHistoryStore::new(db.clone())),
+            db: Some(db),
+            events: None,
+        }
+    }
+
+    /// Create over other stores, such as the RocksDB ones.
+    ///
+    /// Without a database transaction to lean on, `execute_batch` applies
+    /// its operations in order and, on a failure, undoes the earlier ones in
+    /// reverse. Readers may see a batch's writes before it is rolled back.
+    pub fn with_stores(
+        vector_store: Arc<dyn VectorStore>,
+        graph_store: Arc<dyn GraphStore>,
+        history_store: Arc<dyn HistoryStore>,
+    ) -> Self {
+        Self {
+            vector_store,
+            graph_store,
+            history_store,
+            db: None,
+            events: None,
+        }
+    }
+
+    /// Publish a [`StoreEvent`] to `sink` after every successful commit.
+    pub fn with_event_sink(mut self, sink: EventSink) -> Self {
+        self.events = Some(sink);
+        self
+    }
+
+    /// Access the underlying database connection, if the stores use one.
+    pub fn database(&self) -> Option<&Arc<OnyxDatabase>> {
+        self.db.as_ref()
+    }
+
+    /// Receive a [`StoreEvent`] for every commit from now on, or `None`
+    /// when no event sink is attached.
+    pub fn subscribe(&self) -> Option<broadcast::Receiver<StoreEvent>> {
+        self.events.as_ref().map(EventSink::subscribe)
+    }
+
+    /// Tell subscribers about committed changes. Best-effort: the event is
+    /// dropped when nobody is listening.
+    fn publish(&self, changes: Vec<ChangeEvent>) {
+        if let (Some(sink), false) = (&self.events, changes.is_empty()) {
+            let _ = sink.send(StoreEvent { changes });
         }
     }
 
     /// Execute a single operation.
     pub async fn execute(&self, op: TransactionOp) -> OnyxResult<()> {
+        let change = ChangeEvent::from_op(&op);
+        self.apply(op).await?;
+        self.publish(change.into_iter().collect());
+        Ok(())
+    }
+
+    async fn apply(&self, op: TransactionOp) -> OnyxResult<()> {
         match op {
             TransactionOp::InsertNode(node) => {
                 self.graph_store.add_node(node).await?;
             }
+            TransactionOp::UpdateNode(node) => {
+                self.graph_store.update_node(node).await?;
+            }
             TransactionOp::RemoveNode(id) => {
                 self.graph_store.remove_node(&id).await?;
             }
@@ -349,131 +644,555 @@ impl AsyncTransactionManager {
             TransactionOp::RecordVersion(entry) => {
                 self.history_store.record_version(entry).await?;
             }
+            TransactionOp::PutBranch(branch) => {
+                self.history_store.put_branch(branch).await?;
+            }
         }
         Ok(())
     }
 
     /// Execute multiple operations atomically using SurrealDB transactions.
+    ///
+    /// See [`with_stores`](Self::with_stores) for stores without a database.
     pub async fn execute_batch(&self, ops: Vec<TransactionOp>) -> OnyxResult<()> {
+        self.try_execute_batch(ops)
+            .await
+            .map_err(|failure| failure.error)
+    }
+
+    /// [`execute_batch`](Self::execute_batch), reporting which operation
+    /// made the batch fail.
+    pub async fn try_execute_batch(&self, ops: Vec<TransactionOp>) -> Result<(), BatchFailure> {
+        let changes: Vec<ChangeEvent> = ops.iter().filter_map(ChangeEvent::from_op).collect();
+        match &self.db {
+            Some(db) => self.apply_in_transaction(db, ops).await?,
+            None => self.apply_with_rollback(ops).await?,
+        }
+        self.publish(changes);
+        Ok(())
+    }
+
+    async fn apply_in_transaction(
+        &self,
+        db: &OnyxDatabase,
+        ops: Vec<TransactionOp>,
+    ) -> Result<(), BatchFailure> {
         // Begin transaction
-        self.db.begin_transaction().await.map_err(|e| {
-            OnyxError::TransactionFailed(format!("Failed to begin transaction: {}", e))
+        db.begin_transaction().await.map_err(|e| {
+            BatchFailure::whole(OnyxError::TransactionFailed(format!(
+                "Failed to begin transaction: {}",
+                e
+            )))
         })?;
 
-        for op in ops {
-            if let Err(e) = self.execute(op).await {
+        for (index, op) in ops.into_iter().enumerate() {
+            if let Err(error) = self.apply(op).await {
                 // Rollback on failure
-                let _ = self.db.cancel_transaction().await;
-                return Err(e);
+                let _ = db.cancel_transaction().await;
+                return Err(BatchFailure {
+                    op_index: Some(index),
+                    error,
+                });
             }
         }
 
         // Commit transaction
-        self.db.commit_transaction().await.map_err(|e| {
-            OnyxError::TransactionFailed(format!("Failed to commit transaction: {}", e))
-        })?;
+        db.commit_transaction().await.map_err(|e| {
+            BatchFailure::whole(OnyxError::TransactionFailed(format!(
+                "Failed to commit transaction: {}",
+                e
+            )))
+        })
+    }
+
+    /// Apply `ops` in order, undoing the applied ones in reverse if one
+    /// fails.
+    async fn apply_with_rollback(&self, ops: Vec<TransactionOp>) -> Result<(), BatchFailure> {
+        let mut applied: Vec<AppliedOp> = Vec::with_capacity(ops.len());
+
+        for (index, op) in ops.into_iter().enumerate() {
+            match self.apply_undoable(op).await {
+                Ok(applied_op) => applied.push(applied_op),
+                Err(e) => {
+                    let error = match self.rollback_applied(applied).await {
+                        Ok(()) => e,
+                        Err(rollback_err) => OnyxError::TransactionFailed(format!(
+                            "Operation failed: {}. Rollback also failed: {}",
+                            e, rollback_err
+                        )),
+                    };
+                    return Err(BatchFailure {
+                        op_index: Some(index),
+                        error,
+                    });
+                }
+            }
+        }
 
         Ok(())
     }
 
-    /// Get store statistics.
+    /// Apply a single operation, capturing what is needed to undo it.
+    async fn apply_undoable(&self, op: TransactionOp) -> OnyxResult<AppliedOp> {
+        match op {
+            TransactionOp::InsertNode(node) => {
+                let id = node.id;
+                self.graph_store.add_node(node).await?;
+                Ok(AppliedOp::NodeInserted(id))
+            }
+            TransactionOp::UpdateNode(node) => {
+                let previous = self
+                    .graph_store
+                    .get_node_including_deleted(&node.id)
+                    .await?
+                    .ok_or(OnyxError::NodeNotFound(node.id))?;
+                self.graph_store.update_node(node).await?;
+                Ok(AppliedOp::NodeUpdated(previous))
+            }
+            TransactionOp::RemoveNode(id) => {
+                let node = self
+                    .graph_store
+                    .get_node_including_deleted(&id)
+                    .await?
+                    .ok_or(OnyxError::NodeNotFound(id))?;
+                let edges = connected_edges(self.graph_store.as_ref(), &id).await?;
+                self.graph_store.remove_node(&id).await?;
+                Ok(AppliedOp::NodeRemoved { node, edges })
+            }
+            TransactionOp::InsertEdge(edge) => {
+                let id = edge.id;
+                self.graph_store.add_edge(edge).await?;
+                Ok(AppliedOp::EdgeInserted(id))
+            }
+            TransactionOp::RemoveEdge(id) => {
+                let edge = self
+                    .graph_store
+                    .get_edge(&id)
+                    .await?
+                    .ok_or(OnyxError::EdgeNotFound(id))?;
+                self.graph_store.remove_edge(&id).await?;
+                Ok(AppliedOp::EdgeRemoved(edge))
+            }
+            TransactionOp::InsertEmbedding { id, embedding } => {
+                let previous = self.vector_store.get(&id).await?;
+                self.vector_store.insert(id, embedding).await?;
+                Ok(AppliedOp::EmbeddingInserted { id, previous })
+            }
+            TransactionOp::DeleteEmbedding(id) => {
+                let embedding = self
+                    .vector_store
+                    .get(&id)
+                    .await?
+                    .ok_or(OnyxError::NodeNotFound(id))?;
+                self.vector_store.delete(&id).await?;
+                Ok(AppliedOp::EmbeddingDeleted { id, embedding })
+            }
+            TransactionOp::RecordVersion(entry) => {
+                let previous_head = self
+                    .history_store
+                    .get_head(&entry.entity_id, &entry.branch)
+                    .await?;
+                let version_id = self.history_store.record_version(entry).await?;
+                Ok(AppliedOp::VersionRecorded {
+                    version_id,
+                    previous_head,
+                })
+            }
+            TransactionOp::PutBranch(branch) => {
+                let name = branch.name.clone();
+                let previous = self.history_store.get_branch(&name).await?;
+                self.history_store.put_branch(branch).await?;
+                Ok(AppliedOp::BranchPut { name, previous })
+            }
+        }
+    }
+
+    /// Undo applied operations in reverse order, restoring the pre-batch state.
+    ///
+    /// Every step is attempted; the first error encountered is returned.
+    async fn rollback_applied(&self, applied: Vec<AppliedOp>) -> OnyxResult<()> {
+        let mut first_error = None;
+        for op in applied.into_iter().rev() {
+            if let Err(e) = self.undo(op).await {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Undo a single applied operation.
+    async fn undo(&self, op: AppliedOp) -> OnyxResult<()> {
+        match op {
+            AppliedOp::NodeInserted(id) => self.graph_store.remove_node(&id).await,
+            AppliedOp::NodeUpdated(previous) => self.graph_store.update_node(previous).await,
+            AppliedOp::NodeRemoved { node, edges } => {
+                self.graph_store.add_node(node).await?;
+                for edge in edges {
+                    self.graph_store.add_edge(edge).await?;
+                }
+                Ok(())
+            }
+            AppliedOp::EdgeInserted(id) => self.graph_store.remove_edge(&id).await,
+            AppliedOp::EdgeRemoved(edge) => self.graph_store.add_edge(edge).await,
+            AppliedOp::EmbeddingInserted { id, previous } => match previous {
+                Some(embedding) => self.vector_store.insert(id, embedding).await,
+                None => self.vector_store.delete(&id).await,
+            },
+            AppliedOp::EmbeddingDeleted { id, embedding } => {
+                self.vector_store.insert(id, embedding).await
+            }
+            AppliedOp::VersionRecorded {
+                version_id,
+                previous_head,
+            } => {
+                self.history_store
+                    .remove_version(&version_id, previous_head)
+                    .await
+            }
+            AppliedOp::BranchPut { name, previous } => match previous {
+                Some(branch) => self.history_store.put_branch(branch).await,
+                None => self.history_store.delete_branch(&name).await,
+            },
+        }
+    }
+
+    /// Soft-delete a node and record the deletion in its history, atomically.
+    ///
+    /// Returns the ID of the deletion version.
+    pub async fn soft_delete_node(&self, id: &Uuid) -> OnyxResult<VersionId> {
+        let node = self
+            .graph_store
+            .get_node(id)
+            .await?
+            .ok_or(OnyxError::NodeNotFound(*id))?;
+
+        let (version_id, ops) = soft_delete_ops(node);
+        self.execute_batch(ops).await?;
+        Ok(version_id)
+    }
+
+    /// Upsert `node` by key, recording any new version in this manager's
+    /// history store; see [`GraphStore::upsert_node_by_key`].
+    pub async fn upsert_node_by_key(
+        &self,
+        node: Node,
+        policy: ConflictPolicy,
+    ) -> OnyxResult<NodeUpsert> {
+        let upsert = self
+            .graph_store
+            .upsert_node_by_key(node, policy, self.history_store.as_ref())
+            .await?;
+
+        let mut changes = Vec::with_capacity(2);
+        if upsert.inserted {
+            changes.push(ChangeEvent::NodeCreated {
+                node: upsert.node.clone(),
+            });
+        } else if upsert.previous.is_some() {
+            changes.push(ChangeEvent::NodeUpdated {
+                node: upsert.node.clone(),
+            });
+        }
+        if let Some(version) = &upsert.version {
+            changes.push(ChangeEvent::VersionRecorded {
+                version: version.clone(),
+            });
+        }
+        self.publish(changes);
+        Ok(upsert)
+    }
+
+    /// Make every acknowledged write durable in all three stores.
+    pub async fn flush(&self) -> OnyxResult<()> {
+        self.graph_store.flush().await?;
+        self.vector_store.flush().await?;
+        self.history_store.flush().await
+    }
+
+    /// Get store statistics. Counts only, so this stays cheap for health
+    /// checks and metrics; the per-type maps are left empty.
     pub async fn stats(&self) -> StoreStats {
         StoreStats {
             node_count: self.graph_store.node_count().await,
             edge_count: self.graph_store.edge_count().await,
             embedding_count: self.vector_store.len().await,
             version_count: self.history_store.version_count().await,
+            nodes_by_type: BTreeMap::new(),
+            edges_by_type: BTreeMap::new(),
+            embedding_dimension: self.vector_store.dimensions(),
+        }
+    }
+
+    /// Store statistics with per-type node and edge counts.
+    ///
+    /// Walks every node and edge type, so it is O(n) in the store size.
+    pub async fn stats_with_breakdown(&self) -> StoreStats {
+        let (nodes_by_type, edges_by_type) = type_breakdown(self.graph_store.as_ref()).await;
+        StoreStats {
+            nodes_by_type,
+            edges_by_type,
+            ..self.stats().await
+        }
+    }
+}
+
+/// Every edge touching `id`, in either direction.
+async fn connected_edges(graph: &dyn GraphStore, id: &Uuid) -> OnyxResult<Vec<Edge>> {
+    let outbound = graph.get_neighbors(id, None).await?;
+    let inbound = graph.get_inbound(id, None).await?;
+    let mut edges: Vec<Edge> = outbound
+        .into_iter()
+        .chain(inbound)
+        .map(|(edge, _)| edge)
+        .collect();
+    // Self-loops appear in both directions.
+    edges.sort_by_key(|edge| edge.id);
+    edges.dedup_by_key(|edge| edge.id);
+    Ok(edges)
+}
+
+/// Operations that mark `node` deleted and record a deletion version
+/// parented on its current version, on its provenance branch (`main` by
+/// default).
+fn soft_delete_ops(mut node: Node) -> (VersionId, Vec<TransactionOp>) {
+    let branch = node.provenance.branch.as_deref().unwrap_or("main");
+    let version = VersionEntry::deletion(node.id, node.current_version.clone(), branch);
+    let version_id = version.version_id.clone();
+
+    node.deleted_at = Some(version.timestamp);
+    node.updated_at = version.timestamp;
+    node.current_version = Some(version_id.clone());
+
+    (
+        version_id,
+        vec![
+            TransactionOp::UpdateNode(node),
+            TransactionOp::RecordVersion(version),
+        ],
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Store access shared by both managers
+// ---------------------------------------------------------------------------
+
+/// Reads and writes common to [`TransactionManager`] and
+/// [`AsyncTransactionManager`], so ingest can run over either.
+///
+/// Writes are awaited rather than blocked on, so this is safe on any tokio
+/// runtime, including a current-thread one.
+#[async_trait]
+pub trait TransactionStores: Send + Sync {
+    fn graph(&self) -> &dyn GraphStore;
+    fn vector(&self) -> &dyn VectorStore;
+    fn history(&self) -> &dyn HistoryStore;
+
+    /// Apply a single operation.
+    async fn execute(&mut self, op: TransactionOp) -> OnyxResult<()>;
+
+    /// Apply `ops` atomically.
+    async fn execute_batch(&mut self, ops: Vec<TransactionOp>) -> OnyxResult<()>;
+
+    /// Upsert `node` by key; see [`GraphStore::upsert_node_by_key`].
+    async fn upsert_node_by_key(
+        &mut self,
+        node: Node,
+        policy: ConflictPolicy,
+    ) -> OnyxResult<NodeUpsert>;
+}
+
+#[async_trait]
+impl TransactionStores for TransactionManager {
+    fn graph(&self) -> &dyn GraphStore {
+        TransactionManager::graph(self)
+    }
+
+    fn vector(&self) -> &dyn VectorStore {
+        TransactionManager::vector(self)
+    }
+
+    fn history(&self) -> &dyn HistoryStore {
+        TransactionManager::history(self)
+    }
+
+    async fn execute(&mut self, op: TransactionOp) -> OnyxResult<()> {
+        match &self.backend {
+            Some(backend) => {
+                self.generation += 1;
+                backend.execute(op).await
+            }
+            None => TransactionManager::execute(self, op),
         }
     }
+
+    async fn execute_batch(&mut self, ops: Vec<TransactionOp>) -> OnyxResult<()> {
+        match &self.backend {
+            Some(backend) => {
+                self.generation += 1;
+                backend.execute_batch(ops).await
+            }
+            None => TransactionManager::execute_batch(self, ops),
+        }
+    }
+
+    async fn upsert_node_by_key(
+        &mut self,
+        node: Node,
+        policy: ConflictPolicy,
+    ) -> OnyxResult<NodeUpsert> {
+        TransactionManager::upsert_node_by_key(self, node, policy).await
+    }
+}
+
+#[async_trait]
+impl TransactionStores for AsyncTransactionManager {
+    fn graph(&self) -> &dyn GraphStore {
+        self.graph_store.as_ref()
+    }
+
+    fn vector(&self) -> &dyn VectorStore {
+        self.vector_store.as_ref()
+    }
+
+    fn history(&self) -> &dyn HistoryStore {
+        self.history_store.as_ref()
+    }
+
+    async fn execute(&mut self, op: TransactionOp) -> OnyxResult<()> {
+        AsyncTransactionManager::execute(self, op).await
+    }
+
+    async fn execute_batch(&mut self, ops: Vec<TransactionOp>) -> OnyxResult<()> {
+        AsyncTransactionManager::execute_batch(self, ops).await
+    }
+
+    async fn upsert_node_by_key(
+        &mut self,
+        node: Node,
+        policy: ConflictPolicy,
+    ) -> OnyxResult<NodeUpsert> {
+        AsyncTransactionManager::upsert_node_by_key(self, node, policy).await
+    }
 }
 
 // ---------------------------------------------------------------------------
-// Blocking helpers for in-memory stores
+// Blocking helpers for synchronous callers
 // ---------------------------------------------------------------------------
 
+/// Drive an in-memory store future to completion on the current thread.
+///
+/// The in-memory stores only await their own `tokio::sync::RwLock`s, which do
+/// not need a tokio runtime, so this is safe to call from inside one (unlike
+/// `Runtime::block_on` or `RwLock::blocking_read`, which panic there).
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    futures::executor::block_on(future)
+}
+
+/// Drive a database-backed store future to completion from synchronous code.
+///
+/// SurrealDB needs the tokio runtime to make progress, so this hands the
+/// worker thread over with `block_in_place` while waiting.
+///
+/// # Panics
+/// Panics when not called from within a multi-threaded tokio runtime.
+fn block_on_backend<F: std::future::Future>(future: F) -> F::Output {
+    let handle = tokio::runtime::Handle::try_current()
+        .expect("a database-backed TransactionManager must be used inside a tokio runtime");
+    assert!(
+        handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread,
+        "a database-backed TransactionManager needs a multi-threaded tokio runtime"
+    );
+    tokio::task::block_in_place(|| handle.block_on(future))
+}
+
 impl InMemoryGraphStore {
     fn add_node_blocking(&self, node: Node) -> OnyxResult<()> {
-        // Since we can't easily convert async to sync, we'll use a simple workaround
-        // for the in-memory stores - they're designed to be synchronous
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.add_node(node))
     }
 
+    /// Includes soft-deleted nodes, so their state can be captured for rollback.
     fn get_node_blocking(&self, id: &Uuid) -> OnyxResult<Option<Node>> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.get_node_including_deleted(id))
+    }
+
+    fn update_node_blocking(&self, node: Node) -> OnyxResult<()> {
+        block_on(self.update_node(node))
     }
 
     fn remove_node_blocking(&self, id: &Uuid) -> OnyxResult<()> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.remove_node(id))
     }
 
     fn add_edge_blocking(&self, edge: Edge) -> OnyxResult<()> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.add_edge(edge))
     }
 
     fn get_edge_blocking(&self, id: &Uuid) -> OnyxResult<Option<Edge>> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.get_edge(id))
     }
 
     fn remove_edge_blocking(&self, id: &Uuid) -> OnyxResult<()> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.remove_edge(id))
+    }
+
+    fn connected_edges_blocking(&self, id: &Uuid) -> OnyxResult<Vec<Edge>> {
+        block_on(connected_edges(self, id))
     }
 
     fn node_count_blocking(&self) -> usize {
-        // For in-memory stores, we can still use the RwLock directly
-        // This is a simplified version - in production you'd want proper error handling
-        0
+        block_on(self.node_count())
     }
 
     fn edge_count_blocking(&self) -> usize {
-        0
+        block_on(self.edge_count())
     }
 }
 
 impl InMemoryVectorStore {
     fn insert_blocking(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.insert(id, embedding))
     }
 
     fn get_blocking(&self, id: &Uuid) -> OnyxResult<Option<Vec<f32>>> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.get(id))
     }
 
     fn delete_blocking(&self, id: &Uuid) -> OnyxResult<()> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.delete(id))
     }
 
     fn len_blocking(&self) -> usize {
-        0
+        block_on(self.len())
     }
 }
 
 impl InMemoryHistoryStore {
     fn record_version_blocking(&self, entry: VersionEntry) -> OnyxResult<VersionId> {
-        Err(OnyxError::Internal(
-            "Use synchronous methods for in-memory stores".to_string(),
-        ))
+        block_on(self.record_version(entry))
+    }
+
+    fn get_head_blocking(&self, entity_id: &Uuid, branch: &str) -> OnyxResult<Option<VersionId>> {
+        block_on(self.get_head(entity_id, branch))
+    }
+
+    fn remove_version_blocking(
+        &self,
+        version_id: &VersionId,
+        previous_head: Option<VersionId>,
+    ) -> OnyxResult<()> {
+        block_on(self.remove_version(version_id, previous_head))
+    }
+
+    fn put_branch_blocking(&self, branch: Branch) -> Option<Branch> {
+        block_on(self.replace_branch(branch))
+    }
+
+    fn remove_branch_blocking(&self, name: &str) -> Option<Branch> {
+        block_on(self.remove_branch(name))
     }
 
     fn version_count_blocking(&self) -> usize {
-        0
+        block_on(self.version_count())
     }
 }
 
@@ -486,13 +1205,184 @@ mod tests {
     use super::*;
     use crate::model::edge::{Edge, EdgeType};
     use crate::model::node::{CodeEntityKind, NodeType};
+    use crate::store::events::event_sink;
+
+    fn function_node(name: &str) -> Node {
+        Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            name,
+            format!("fn {}() {{}}", name),
+        )
+    }
 
     #[test]
-    fn test_atomic_commit() {
-        // Note: In-memory stores need special handling since they're now async
-        // This test would need to be updated to work with the async interface
-        // For now, we just verify the structure compiles
-        let _tm = TransactionManager::new();
+    fn test_stats_reflect_inserts() {
+        let mut tm = TransactionManager::new();
+        let node_a = function_node("func_a");
+        let node_b = function_node("func_b");
+        let edge = Edge::new(EdgeType::Calls, node_a.id, node_b.id);
+        let id_a = node_a.id;
+
+        tm.execute_batch(vec![
+            TransactionOp::InsertNode(node_a),
+            TransactionOp::InsertNode(node_b),
+            TransactionOp::InsertEdge(edge),
+            TransactionOp::InsertEmbedding {
+                id: id_a,
+                embedding: vec![1.0, 0.0],
+            },
+            TransactionOp::RecordVersion(VersionEntry::initial(id_a, "fn func_a() {}")),
+        ])
+        .unwrap();
+
+        let stats = tm.stats();
+        assert_eq!(stats.node_count, 2);
+        assert_eq!(stats.edge_count, 1);
+        assert_eq!(stats.embedding_count, 1);
+        assert_eq!(stats.version_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_adapters_work_inside_runtime() {
+        let mut tm = TransactionManager::new();
+        tm.execute(TransactionOp::InsertNode(function_node("func_a")))
+            .unwrap();
+        assert_eq!(tm.stats().node_count, 1);
+    }
+
+    #[test]
+    fn test_failed_batch_rolls_back() {
+        let mut tm = TransactionManager::new();
+        let node_a = function_node("func_a");
+        let id_a = node_a.id;
+
+        let result = tm.execute_batch(vec![
+            TransactionOp::InsertNode(node_a),
+            TransactionOp::InsertEmbedding {
+                id: id_a,
+                embedding: vec![1.0, 0.0],
+            },
+            // Target does not exist, so this op fails.
+            TransactionOp::InsertEdge(Edge::new(EdgeType::Calls, id_a, Uuid::new_v4())),
+        ]);
+
+        assert!(matches!(result, Err(OnyxError::TransactionFailed(_))));
+        let stats = tm.stats();
+        assert_eq!(stats.node_count, 0);
+        assert_eq!(stats.embedding_count, 0);
+    }
+
+    #[test]
+    fn test_rollback_restores_every_store() {
+        let mut tm = TransactionManager::new();
+        let node_a = function_node("func_a");
+        let node_b = function_node("func_b");
+        let (id_a, id_b) = (node_a.id, node_b.id);
+        let initial = VersionEntry::initial(id_a, "fn func_a() {}");
+        let initial_id = initial.version_id.clone();
+
+        tm.execute_batch(vec![
+            TransactionOp::InsertNode(node_a),
+            TransactionOp::InsertNode(node_b),
+            TransactionOp::InsertEdge(Edge::new(EdgeType::Calls, id_a, id_b)),
+            TransactionOp::InsertEmbedding {
+                id: id_a,
+                embedding: vec![1.0, 0.0],
+            },
+            TransactionOp::RecordVersion(initial),
+        ])
+        .unwrap();
+
+        let result = tm.execute_batch(vec![
+            TransactionOp::RecordVersion(VersionEntry::content_change(
+                id_a,
+                initial_id.clone(),
+                "fn func_a() { todo!() }",
+                1,
+                1,
+            )),
+            TransactionOp::InsertEmbedding {
+                id: id_a,
+                embedding: vec![0.0, 1.0],
+            },
+            TransactionOp::RemoveNode(id_b),
+            TransactionOp::InsertEdge(Edge::new(EdgeType::Calls, id_a, Uuid::new_v4())),
+        ]);
+        assert!(result.is_err());
+
+        let stats = tm.stats();
+        assert_eq!(stats.node_count, 2);
+        assert_eq!(stats.edge_count, 1);
+        assert_eq!(stats.embedding_count, 1);
+        assert_eq!(stats.version_count, 1);
+
+        assert_eq!(
+            tm.vector_store.get_blocking(&id_a).unwrap(),
+            Some(vec![1.0, 0.0])
+        );
+        assert_eq!(
+            tm.history_store.get_head_blocking(&id_a, "main").unwrap(),
+            Some(initial_id)
+        );
+    }
+
+    #[test]
+    fn test_soft_deleted_node_is_hidden_but_history_survives() {
+        let mut tm = TransactionManager::new();
+        let mut node = function_node("func_a");
+        let id = node.id;
+        let initial = VersionEntry::initial(id, "fn func_a() {}");
+        node.current_version = Some(initial.version_id.clone());
+
+        tm.execute_batch(vec![
+            TransactionOp::InsertNode(node),
+            TransactionOp::RecordVersion(initial),
+        ])
+        .unwrap();
+        let before_delete = chrono::Utc::now();
+
+        let deletion = tm.soft_delete_node(&id).unwrap();
+
+        assert!(block_on(tm.graph_store.get_node(&id)).unwrap().is_none());
+        assert!(block_on(tm.graph_store.all_nodes()).is_empty());
+        let stored = block_on(tm.graph_store.get_node_including_deleted(&id))
+            .unwrap()
+            .unwrap();
+        assert!(stored.is_deleted());
+        assert_eq!(stored.current_version, Some(deletion.clone()));
+
+        let head = tm.history_store.get_head_blocking(&id, "main").unwrap();
+        assert_eq!(head, Some(deletion));
+        let content =
+            block_on(tm.history_store.get_content_at_timestamp(&id, &before_delete)).unwrap();
+        assert_eq!(content, "fn func_a() {}");
+
+        assert!(matches!(
+            tm.soft_delete_node(&id),
+            Err(OnyxError::NodeNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_soft_delete_records_on_the_node_branch() {
+        let mut tm = TransactionManager::new();
+        let mut node = function_node("func_a");
+        node.provenance.branch = Some("feature".to_string());
+        let id = node.id;
+        let initial = VersionEntry::initial(id, "fn func_a() {}").with_branch("feature");
+        node.current_version = Some(initial.version_id.clone());
+        tm.execute_batch(vec![
+            TransactionOp::InsertNode(node),
+            TransactionOp::RecordVersion(initial),
+        ])
+        .unwrap();
+
+        let deletion = tm.soft_delete_node(&id).unwrap();
+
+        let head = tm.history_store.get_head_blocking(&id, "feature").unwrap();
+        assert_eq!(head, Some(deletion));
+        let main = tm.history_store.get_head_blocking(&id, "main").unwrap();
+        assert_eq!(main, None);
     }
 
     #[tokio::test]
@@ -513,6 +1403,67 @@ mod tests {
         assert_eq!(stats.node_count, 1);
     }
 
+    #[tokio::test]
+    async fn test_event_sink_receives_batch() {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let tm = AsyncTransactionManager::new(db).with_event_sink(event_sink());
+        let mut events = tm.subscribe().unwrap();
+
+        let node_a = function_node("func_a");
+        let node_b = function_node("func_b");
+        let ids = [node_a.id, node_b.id];
+        tm.execute_batch(vec![
+            TransactionOp::InsertNode(node_a),
+            TransactionOp::InsertNode(node_b),
+        ])
+        .await
+        .unwrap();
+
+        let event = events.try_recv().unwrap();
+        let created: Vec<Uuid> = event
+            .changes
+            .iter()
+            .map(|change| match change {
+                ChangeEvent::NodeCreated { node } => node.id,
+                other => panic!("unexpected change {other:?}"),
+            })
+            .collect();
+        assert_eq!(created, ids);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_with_database_routes_to_surreal() {
+        let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
+        let mut tm = TransactionManager::with_database(db.clone());
+
+        let node = function_node("func_a");
+        let id = node.id;
+        tm.execute_batch(vec![
+            TransactionOp::InsertNode(node),
+            TransactionOp::InsertEmbedding {
+                id,
+                embedding: vec![1.0, 0.0],
+            },
+        ])
+        .unwrap();
+        tm.execute(TransactionOp::RecordVersion(VersionEntry::initial(
+            id,
+            "fn func_a() {}",
+        )))
+        .unwrap();
+
+        let stats = tm.stats();
+        assert_eq!(stats.node_count, 1);
+        assert_eq!(stats.embedding_count, 1);
+        assert_eq!(stats.version_count, 1);
+
+        // The writes went to SurrealDB, not the in-memory stores.
+        assert_eq!(tm.graph_store.node_count().await, 0);
+        let persisted = AsyncTransactionManager::new(db).stats().await;
+        assert_eq!(persisted.node_count, 1);
+    }
+
     #[tokio::test]
     async fn test_async_batch_execution() {
         let db = Arc::new(OnyxDatabase::new_memory().await.unwrap());
@@ -1,5 +1,6 @@
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -20,6 +21,33 @@ pub trait VectorStore: Send + Sync {
     /// Returns (node_id, similarity_score) pairs sorted by descending similarity.
     async fn search(&self, query: &[f32], k: usize) -> OnyxResult<Vec<(Uuid, f32)>>;
 
+    /// Like [`search`](Self::search), but never returns an ID in `exclude`.
+    /// Still returns up to `k` results when enough other embeddings exist.
+    async fn search_excluding(
+        &self,
+        query: &[f32],
+        k: usize,
+        exclude: &HashSet<Uuid>,
+    ) -> OnyxResult<Vec<(Uuid, f32)>> {
+        let mut results = self.search(query, k + exclude.len()).await?;
+        results.retain(|(id, _)| !exclude.contains(id));
+        results.truncate(k);
+        Ok(results)
+    }
+
+    /// Like [`search`](Self::search), but drops results whose similarity is
+    /// below `min_score`, so fewer than `k` results may come back.
+    async fn search_with_threshold(
+        &self,
+        query: &[f32],
+        k: usize,
+        min_score: f32,
+    ) -> OnyxResult<Vec<(Uuid, f32)>> {
+        let mut results = self.search(query, k).await?;
+        results.retain(|(_, score)| *score >= min_score);
+        Ok(results)
+    }
+
     /// Delete an embedding by node ID.
     async fn delete(&self, id: &Uuid) -> OnyxResult<()>;
 
@@ -40,20 +68,88 @@ pub trait VectorStore: Send + Sync {
     /// Get all embedding IDs in the store.
     async fn get_all_embedding_ids(&self) -> OnyxResult<Vec<Uuid>>;
 
-    /// Insert an embedding object.
-    async fn insert_embedding(&self, embedding: crate::model::embedding::Embedding) -> OnyxResult<()>;
+    /// The dimensionality every stored embedding must have, if known.
+    fn dimensions(&self) -> Option<usize> {
+        None
+    }
 
-    /// Get an embedding object by ID.
-    async fn get_embedding(&self, id: &Uuid) -> OnyxResult<Option<crate::model::embedding::Embedding>>;
+    /// Insert several embeddings.
+    ///
+    /// Every embedding is checked against [`dimensions`](Self::dimensions)
+    /// (or, if unset, the first one) and for NaN or infinite components
+    /// before anything is written.
+    async fn insert_batch(&self, embeddings: Vec<(Uuid, Vec<f32>)>) -> OnyxResult<()> {
+        let expected = self
+            .dimensions()
+            .or_else(|| embeddings.first().map(|(_, e)| e.len()));
+        for (_, embedding) in &embeddings {
+            check_embedding(expected, embedding)?;
+        }
+        for (id, embedding) in embeddings {
+            self.insert(id, embedding).await?;
+        }
+        Ok(())
+    }
 
-    /// Get all embedding IDs in the store.
-    async fn get_all_embedding_ids(&self) -> OnyxResult<Vec<Uuid>>;
+    /// Make every acknowledged write durable, e.g. before shutting down.
+    async fn flush(&self) -> OnyxResult<()> {
+        Ok(())
+    }
+}
+
+/// Fail with [`OnyxError::DimensionMismatch`] unless `embedding` has
+/// `expected` dimensions. `None` accepts any length.
+pub(crate) fn check_dimensions(expected: Option<usize>, embedding: &[f32]) -> OnyxResult<()> {
+    match expected {
+        Some(d) if d != embedding.len() => Err(OnyxError::DimensionMismatch {
+            expected: d,
+            got: embedding.len(),
+        }),
+        _ => Ok(()),
+    }
+}
 
-    /// Insert an embedding object.
-    async fn insert_embedding(&self, embedding: crate::model::embedding::Embedding) -> OnyxResult<()>;
+/// Fail with [`OnyxError::InvalidEmbedding`] if `embedding` is empty or has
+/// a NaN or infinite component, which would make its similarity to every
+/// other vector NaN.
+pub(crate) fn check_finite(embedding: &[f32]) -> OnyxResult<()> {
+    if embedding.is_empty() {
+        return Err(OnyxError::InvalidEmbedding(
+            "embedding is empty".to_string(),
+        ));
+    }
+    match embedding.iter().position(|x| !x.is_finite()) {
+        Some(i) => Err(OnyxError::InvalidEmbedding(format!(
+            "component {} is {}",
+            i, embedding[i]
+        ))),
+        None => Ok(()),
+    }
+}
 
-    /// Get an embedding object by ID.
-    async fn get_embedding(&self, id: &Uuid) -> OnyxResult<Option<crate::model::embedding::Embedding>>;
+/// [`check_finite`], then [`check_dimensions`]: what every embedding stored
+/// or searched with must pass.
+pub(crate) fn check_embedding(expected: Option<usize>, embedding: &[f32]) -> OnyxResult<()> {
+    check_finite(embedding)?;
+    check_dimensions(expected, embedding)
+}
+
+/// Cosine similarity of two vectors, 0.0 if either is all zeros.
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+/// Order similarity scores best first. A NaN score, which a corrupt stored
+/// vector can still produce, ranks below every other score.
+pub(crate) fn compare_scores(a: f32, b: f32) -> Ordering {
+    a.is_nan().cmp(&b.is_nan()).then_with(|| b.total_cmp(&a))
 }
 
 // ---------------------------------------------------------------------------
@@ -109,34 +205,12 @@ impl SurrealVectorStore {
 
         Ok(())
     }
-
-    /// Compute cosine similarity between two vectors.
-    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-        let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm_a == 0.0 || norm_b == 0.0 {
-            0.0
-        } else {
-            dot / (norm_a * norm_b)
-        }
-    }
 }
 
 #[async_trait]
 impl VectorStore for SurrealVectorStore {
     async fn insert(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
-        // Validate dimensions
-        match self.dimensions {
-            Some(d) if d != embedding.len() => {
-                return Err(OnyxError::DimensionMismatch {
-                    expected: d,
-                    got: embedding.len(),
-                });
-            }
-            None => {}
-            _ => {}
-        }
+        check_embedding(self.dimensions, &embedding)?;
 
         let record = EmbeddingRecord {
             record_id: id.to_string(),
@@ -154,14 +228,7 @@ impl VectorStore for SurrealVectorStore {
     }
 
     async fn search(&self, query: &[f32], k: usize) -> OnyxResult<Vec<(Uuid, f32)>> {
-        if let Some(d) = self.dimensions {
-            if query.len() != d {
-                return Err(OnyxError::DimensionMismatch {
-                    expected: d,
-                    got: query.len(),
-                });
-            }
-        }
+        check_embedding(self.dimensions, query)?;
 
         // Use SurrealDB's vector similarity search
         // The vector<->vector operator computes Euclidean distance
@@ -183,7 +250,7 @@ impl VectorStore for SurrealVectorStore {
         let results: Vec<(Uuid, f32)> = records
             .into_iter()
             .map(|record| {
-                let similarity = Self::cosine_similarity(query, &record.vector);
+                let similarity = cosine_similarity(query, &record.vector);
                 let node_id = Uuid::parse_str(&record.node_id).unwrap_or_default();
                 (node_id, similarity)
             })
@@ -201,6 +268,8 @@ impl VectorStore for SurrealVectorStore {
     }
 
     async fn update(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
+        check_embedding(self.dimensions, &embedding)?;
+
         // Check if exists first
         let exists: Option<EmbeddingRecord> = self
             .db
@@ -266,16 +335,8 @@ impl VectorStore for SurrealVectorStore {
         Ok(ids)
     }
 
-    async fn insert_embedding(&self, embedding: crate::model::embedding::Embedding) -> OnyxResult<()> {
-        self.insert(embedding.node_id, embedding.values).await
-    }
-
-    async fn get_embedding(&self, id: &Uuid) -> OnyxResult<Option<crate::model::embedding::Embedding>> {
-        let vector = self.get(id).await?;
-        Ok(vector.map(|v| crate::model::embedding::Embedding {
-            node_id: *id,
-            values: v,
-        }))
+    fn dimensions(&self) -> Option<usize> {
+        self.dimensions
     }
 }
 
@@ -285,38 +346,39 @@ impl VectorStore for SurrealVectorStore {
 
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap};
+use std::sync::OnceLock;
 use tokio::sync::RwLock;
 
 /// In-memory vector store using brute-force cosine similarity search.
+///
+/// Unless created with [`with_dimensions`](Self::with_dimensions), the
+/// dimensionality is fixed by the first embedding inserted.
 pub struct InMemoryVectorStore {
     embeddings: RwLock<HashMap<Uuid, Vec<f32>>>,
-    dimensions: Option<usize>,
+    dimensions: OnceLock<usize>,
 }
 
 impl InMemoryVectorStore {
     pub fn new() -> Self {
         Self {
             embeddings: RwLock::new(HashMap::new()),
-            dimensions: None,
+            dimensions: OnceLock::new(),
         }
     }
 
     pub fn with_dimensions(dimensions: usize) -> Self {
         Self {
             embeddings: RwLock::new(HashMap::new()),
-            dimensions: Some(dimensions),
+            dimensions: OnceLock::from(dimensions),
         }
     }
 
-    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-        let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm_a == 0.0 || norm_b == 0.0 {
-            0.0
-        } else {
-            dot / (norm_a * norm_b)
-        }
+    /// Check `embedding` is finite and has the store's dimensionality,
+    /// locking it in on first use.
+    fn check_and_lock_dimensions(&self, embedding: &[f32]) -> OnyxResult<()> {
+        check_finite(embedding)?;
+        let expected = *self.dimensions.get_or_init(|| embedding.len());
+        check_dimensions(Some(expected), embedding)
     }
 }
 
@@ -329,48 +391,40 @@ impl Default for InMemoryVectorStore {
 #[async_trait]
 impl VectorStore for InMemoryVectorStore {
     async fn insert(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
-        match self.dimensions {
-            Some(d) if d != embedding.len() => {
-                return Err(OnyxError::DimensionMismatch {
-                    expected: d,
-                    got: embedding.len(),
-                });
-            }
-            None => {}
-            _ => {}
-        }
+        self.check_and_lock_dimensions(&embedding)?;
 
-        let mut embeddings = self.embeddings.write().map_err(|_| {
-            OnyxError::Internal("Failed to acquire write lock".to_string())
-        })?;
+        let mut embeddings = self.embeddings.write().await;
         embeddings.insert(id, embedding);
         Ok(())
     }
 
     async fn search(&self, query: &[f32], k: usize) -> OnyxResult<Vec<(Uuid, f32)>> {
-        if let Some(d) = self.dimensions {
-            if query.len() != d {
-                return Err(OnyxError::DimensionMismatch {
-                    expected: d,
-                    got: query.len(),
-                });
-            }
-        }
+        self.search_excluding(query, k, &HashSet::new()).await
+    }
 
-        let embeddings = self.embeddings.read().map_err(|_| {
-            OnyxError::Internal("Failed to acquire read lock".to_string())
-        })?;
+    async fn search_excluding(
+        &self,
+        query: &[f32],
+        k: usize,
+        exclude: &HashSet<Uuid>,
+    ) -> OnyxResult<Vec<(Uuid, f32)>> {
+        check_embedding(self.dimensions.get().copied(), query)?;
+
+        let embeddings = self.embeddings.read().await;
 
         let mut heap: BinaryHeap<ScoredItem> = BinaryHeap::new();
 
         for (id, embedding) in embeddings.iter() {
-            let score = Self::cosine_similarity(query, embedding);
+            if exclude.contains(id) {
+                continue;
+            }
+            let score = cosine_similarity(query, embedding);
             let item = ScoredItem { id: *id, score };
 
             if heap.len() < k {
                 heap.push(item);
-            } else if let Some(min) = heap.peek() {
-                if score > min.score {
+            } else if let Some(worst) = heap.peek() {
+                if compare_scores(score, worst.score) == Ordering::Less {
                     heap.pop();
                     heap.push(item);
                 }
@@ -379,24 +433,21 @@ impl VectorStore for InMemoryVectorStore {
 
         let mut results: Vec<(Uuid, f32)> =
             heap.into_iter().map(|item| (item.id, item.score)).collect();
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        results.sort_by(|a, b| compare_scores(a.1, b.1));
 
         Ok(results)
     }
 
     async fn delete(&self, id: &Uuid) -> OnyxResult<()> {
-        let mut embeddings = self.embeddings.write().map_err(|_| {
-            OnyxError::Internal("Failed to acquire write lock".to_string())
-        })?;
+        let mut embeddings = self.embeddings.write().await;
         embeddings.remove(id);
         Ok(())
     }
 
     async fn update(&self, id: Uuid, embedding: Vec<f32>) -> OnyxResult<()> {
-        let mut embeddings = self.embeddings.write().map_err(|_| {
-            OnyxError::Internal("Failed to acquire write lock".to_string())
-        })?;
-        
+        self.check_and_lock_dimensions(&embedding)?;
+
+        let mut embeddings = self.embeddings.write().await;
         if !embeddings.contains_key(&id) {
             return Err(OnyxError::NodeNotFound(id));
         }
@@ -405,16 +456,33 @@ impl VectorStore for InMemoryVectorStore {
     }
 
     async fn get(&self, id: &Uuid) -> OnyxResult<Option<Vec<f32>>> {
-        let embeddings = self.embeddings.read().map_err(|_| {
-            OnyxError::Internal("Failed to acquire read lock".to_string())
-        })?;
+        let embeddings = self.embeddings.read().await;
         Ok(embeddings.get(id).cloned())
     }
 
     async fn len(&self) -> usize {
-        let embeddings = self.embeddings.read().unwrap();
+        let embeddings = self.embeddings.read().await;
         embeddings.len()
     }
+
+    async fn get_all_embedding_ids(&self) -> OnyxResult<Vec<Uuid>> {
+        let embeddings = self.embeddings.read().await;
+        Ok(embeddings.keys().copied().collect())
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        self.dimensions.get().copied()
+    }
+
+    async fn insert_batch(&self, batch: Vec<(Uuid, Vec<f32>)>) -> OnyxResult<()> {
+        for (_, embedding) in &batch {
+            self.check_and_lock_dimensions(embedding)?;
+        }
+
+        let mut embeddings = self.embeddings.write().await;
+        embeddings.extend(batch);
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -438,8 +506,9 @@ impl PartialOrd for ScoredItem {
 }
 
 impl Ord for ScoredItem {
+    /// Worse scores are greater, so the heap's top is the worst kept item.
     fn cmp(&self, other: &Self) -> Ordering {
-        other.score.partial_cmp(&self.score).unwrap_or(Ordering::Equal)
+        compare_scores(self.score, other.score)
     }
 }
 
@@ -447,15 +516,19 @@ impl Ord for ScoredItem {
 // Vector serialization helper
 // ---------------------------------------------------------------------------
 
+/// SurrealDB stores numbers as `f64`, so vectors are written as `f64`s and
+/// narrowed back on read. Every `f32` widens to `f64` exactly, so values
+/// round-trip unchanged.
 mod vector_f32_serde {
-    use serde::{Deserializer, Serializer};
+    use serde::{Deserialize, Deserializer, Serializer};
 
-    pub fn serialize<S: Serializer>(v: &Vec<f32>, serializer: S) -> Result<S::Ok, S::Error> {
-        serializer.serialize_none()
+    pub fn serialize<S: Serializer>(v: &[f32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(v.iter().map(|x| f64::from(*x)))
     }
 
     pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f32>, D::Error> {
-        Ok(Vec::new())
+        let wide = Vec::<f64>::deserialize(deserializer)?;
+        Ok(wide.into_iter().map(|x| x as f32).collect())
     }
 }
 
@@ -483,11 +556,128 @@ mod tests {
         assert!((results[0].1 - 1.0).abs() < 1e-6);
     }
 
+    #[tokio::test]
+    async fn test_search_excluding_skips_nearest_vector() {
+        let store = InMemoryVectorStore::new();
+        let (nearest, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        store.insert(nearest, vec![1.0, 0.0, 0.0]).await.unwrap();
+        store.insert(second, vec![0.8, 0.2, 0.0]).await.unwrap();
+        store.insert(third, vec![0.5, 0.5, 0.0]).await.unwrap();
+
+        let exclude = HashSet::from([nearest]);
+        let results = store
+            .search_excluding(&[1.0, 0.0, 0.0], 2, &exclude)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = results.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [second, third]);
+    }
+
+    #[tokio::test]
+    async fn test_search_with_threshold_drops_unrelated_results() {
+        let store = InMemoryVectorStore::new();
+        let similar = Uuid::new_v4();
+        store.insert(similar, vec![0.9, 0.1, 0.0, 0.0]).await.unwrap();
+        for orthogonal in [[0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]] {
+            store.insert(Uuid::new_v4(), orthogonal.to_vec()).await.unwrap();
+        }
+
+        let query = [1.0, 0.0, 0.0, 0.0];
+        assert_eq!(store.search(&query, 4).await.unwrap().len(), 4);
+
+        let results = store.search_with_threshold(&query, 4, 0.8).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, similar);
+    }
+
     #[tokio::test]
     async fn test_in_memory_dimension_mismatch() {
         let store = InMemoryVectorStore::new();
         store.insert(Uuid::new_v4(), vec![1.0, 2.0]).await.unwrap();
         let result = store.insert(Uuid::new_v4(), vec![1.0, 2.0, 3.0]).await;
-        assert!(result.is_err());
+        assert!(matches!(
+            result,
+            Err(OnyxError::DimensionMismatch {
+                expected: 2,
+                got: 3
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_batch_rejects_mixed_dimensions() {
+        let store = InMemoryVectorStore::with_dimensions(3);
+        let result = store
+            .insert_batch(vec![
+                (Uuid::new_v4(), vec![1.0, 0.0, 0.0]),
+                (Uuid::new_v4(), vec![1.0, 0.0]),
+            ])
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(OnyxError::DimensionMismatch {
+                expected: 3,
+                got: 2
+            })
+        ));
+        assert!(store.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_non_finite_embeddings_are_rejected() {
+        let store = InMemoryVectorStore::new();
+        store
+            .insert(Uuid::new_v4(), vec![1.0, 0.0, 0.0])
+            .await
+            .unwrap();
+
+        let search = store.search(&[f32::NAN, 0.0, 0.0], 1).await;
+        assert!(matches!(search, Err(OnyxError::InvalidEmbedding(_))));
+        let insert = store
+            .insert(Uuid::new_v4(), vec![0.0, f32::INFINITY, 0.0])
+            .await;
+        assert!(matches!(insert, Err(OnyxError::InvalidEmbedding(_))));
+        let empty = InMemoryVectorStore::new()
+            .insert(Uuid::new_v4(), vec![])
+            .await;
+        assert!(matches!(empty, Err(OnyxError::InvalidEmbedding(_))));
+        assert_eq!(store.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_stored_nan_vector_ranks_last() {
+        let store = InMemoryVectorStore::new();
+        let (near, far, corrupt) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        store.insert(near, vec![1.0, 0.0, 0.0]).await.unwrap();
+        store.insert(far, vec![0.0, 1.0, 0.0]).await.unwrap();
+        // Bypass insert's validation, as a corrupt record on disk would.
+        store
+            .embeddings
+            .write()
+            .await
+            .insert(corrupt, vec![f32::NAN, 0.0, 0.0]);
+
+        let results = store.search(&[1.0, 0.0, 0.0], 3).await.unwrap();
+        let ids: Vec<Uuid> = results.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [near, far, corrupt]);
+        assert!(results[2].1.is_nan());
+
+        let top = store.search(&[1.0, 0.0, 0.0], 2).await.unwrap();
+        let ids: Vec<Uuid> = top.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [near, far]);
+    }
+
+    #[test]
+    fn test_embedding_record_round_trips_vector() {
+        let record = EmbeddingRecord {
+            record_id: "embedding:1".to_string(),
+            node_id: Uuid::new_v4().to_string(),
+            vector: vec![0.1, -2.5, 1e-7],
+            dimensions: 3,
+        };
+        let json = serde_json::to_value(&record).unwrap();
+        let read: EmbeddingRecord = serde_json::from_value(json).unwrap();
+        assert_eq!(read.vector, record.vector);
     }
 }
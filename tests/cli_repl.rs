@@ -0,0 +1,47 @@
+//! End-to-end tests for the interactive REPL, driven over stdin.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run `onyx interactive` with `script` on stdin, returning its stdout and
+/// stderr once it has exited successfully.
+fn repl(script: &str) -> (String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_onyx"))
+        .arg("interactive")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_ingest_after_load_demo_embeds_into_the_same_space() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("coupons.rs");
+    std::fs::write(
+        &file,
+        "pub fn apply_coupon(price: f64, coupon: f64) -> f64 {\n    price - coupon\n}\n",
+    )
+    .unwrap();
+
+    let script = format!("load-demo\ningest {}\nquery coupon\nexit\n", file.display());
+    let (stdout, stderr) = repl(&script);
+
+    assert!(!stderr.contains("Error"), "{stderr}");
+    assert!(stdout.contains("Ingested 1 nodes"), "{stdout}");
+    assert!(stdout.contains("apply_coupon"), "{stdout}");
+    assert!(stdout.contains("process_payment"), "{stdout}");
+}
@@ -0,0 +1,230 @@
+//! End-to-end tests for the non-interactive CLI over a RocksDB store on
+//! disk, e.g. `onyx query --store`.
+
+#![cfg(feature = "rocksdb-storage")]
+
+use std::path::Path;
+use std::process::Command;
+
+use onyx::config::LimitsConfig;
+use onyx::ingest::demo::build_synthetic_codebase;
+use onyx::ingest::ingest_codebase;
+use onyx::model::embedding::BagOfWordsEmbedder;
+use onyx::model::node::{CodeEntityKind, Node, NodeType};
+use onyx::model::version::VersionEntry;
+use onyx::store::migration::{migrate, MigrationConfig, MigrationStores};
+use onyx::store::persistent::{
+    open_db, open_stores, save_embedder, RocksGraphStore, RocksHistoryStore, RocksVectorStore,
+};
+use onyx::store::transaction::{TransactionManager, TransactionOp};
+
+/// Run the `onyx` binary with `args` over the store in `dir`, returning its
+/// output once it has succeeded.
+fn onyx(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_onyx"))
+        .args(args)
+        .arg("--store")
+        .arg(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "onyx {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[tokio::test]
+async fn test_query_store_on_disk() {
+    let units = build_synthetic_codebase();
+    let corpus: Vec<&str> = units.iter().map(|u| u.content.as_str()).collect();
+    let embedder = BagOfWordsEmbedder::from_corpus(&corpus, 100);
+    let mut stores = TransactionManager::new();
+    ingest_codebase(&mut stores, &units, &embedder, &LimitsConfig::default())
+        .await
+        .unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    {
+        // Dropped before the CLI opens the store, releasing RocksDB's lock.
+        let db = open_db(dir.path()).unwrap();
+        let graph = RocksGraphStore::new(db.clone());
+        let vector = RocksVectorStore::new(db.clone(), embedder.dimensions());
+        let history = RocksHistoryStore::new(db);
+        migrate(
+            MigrationStores::from(&stores),
+            MigrationStores::new(&graph, &vector, &history),
+            MigrationConfig::default(),
+        )
+        .await
+        .unwrap();
+    }
+    save_embedder(dir.path(), &embedder).unwrap();
+
+    let stdout = onyx(dir.path(), &["query", "--question", "payment"]);
+    assert!(
+        stdout.contains("process_payment"),
+        "unexpected output: {stdout}"
+    );
+
+    let important = onyx(dir.path(), &["important", "--top", "3"]);
+    assert!(important.contains("  1. "), "{important}");
+    assert!(!important.contains("  4. "), "{important}");
+}
+
+#[test]
+fn test_ingest_into_store_then_query() {
+    let source = tempfile::tempdir().unwrap();
+    std::fs::write(
+        source.path().join("billing.rs"),
+        "pub fn process_payment(amount: f64) -> f64 {\n    apply_discount(amount)\n}\n\n\
+         pub fn apply_discount(amount: f64) -> f64 {\n    amount * 0.9\n}\n",
+    )
+    .unwrap();
+    std::fs::write(
+        source.path().join("report.rs"),
+        "pub fn render_report(rows: usize) -> String {\n    rows.to_string()\n}\n",
+    )
+    .unwrap();
+    let store = tempfile::tempdir().unwrap();
+    let path = source.path().to_str().unwrap();
+
+    let ingested = onyx(store.path(), &["ingest", "--path", path]);
+    assert!(ingested.contains("Ingested 3 nodes"), "{ingested}");
+    // A second ingest reuses the saved embedder, so dimensions still match.
+    let again = onyx(store.path(), &["ingest", "--path", path]);
+    assert!(again.contains("Ingested 3 nodes"), "{again}");
+
+    let stdout = onyx(store.path(), &["query", "--question", "payment"]);
+    assert!(
+        stdout.contains("process_payment"),
+        "unexpected output: {stdout}"
+    );
+}
+
+#[tokio::test]
+async fn test_diff_versions_on_disk() {
+    let dir = tempfile::tempdir().unwrap();
+    let contents = [
+        "fn total(a: u32) -> u32 {\n    a\n}",
+        "fn total(a: u32, b: u32) -> u32 {\n    a + b\n}",
+        "fn total(a: u32, b: u32) -> u32 {\n    a.saturating_add(b)\n}",
+    ];
+    let mut ids: Vec<String> = Vec::new();
+    {
+        let stores = open_stores(dir.path(), Some(3)).await.unwrap();
+        let node = Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "total",
+            contents[2],
+        );
+        let node_id = node.id;
+        stores
+            .execute(TransactionOp::InsertNode(node))
+            .await
+            .unwrap();
+
+        let start = chrono::Utc::now();
+        for (i, content) in contents.iter().enumerate() {
+            let mut entry = match ids.last() {
+                None => VersionEntry::initial(node_id, *content),
+                Some(parent) => {
+                    VersionEntry::content_change(node_id, parent.clone(), *content, 2, 2)
+                }
+            };
+            // Versions are ordered by timestamp; keep them apart.
+            entry.timestamp = start + chrono::Duration::seconds(i as i64);
+            ids.push(stores.history_store.record_version(entry).await.unwrap());
+        }
+    }
+
+    let diff = |args: &[&str]| {
+        onyx(
+            dir.path(),
+            &[&["diff", "--node", "total"][..], args].concat(),
+        )
+    };
+
+    // Defaults to the two most recent versions.
+    let latest = diff(&[]);
+    assert!(
+        latest.contains("-    a + b\n+    a.saturating_add(b)\n"),
+        "{latest}"
+    );
+    assert!(!latest.contains("-fn total(a: u32) -> u32 {"), "{latest}");
+
+    let first_to_last = diff(&["--from", &ids[0], "--to", &ids[2]]);
+    assert!(
+        first_to_last.contains("-fn total(a: u32) -> u32 {\n"),
+        "{first_to_last}"
+    );
+    assert!(
+        first_to_last.contains("+fn total(a: u32, b: u32) -> u32 {\n"),
+        "{first_to_last}"
+    );
+    assert!(
+        first_to_last.contains("+    a.saturating_add(b)\n"),
+        "{first_to_last}"
+    );
+}
+
+#[tokio::test]
+async fn test_branch_create_list_and_merge() {
+    let dir = tempfile::tempdir().unwrap();
+    let node = Node::new(
+        NodeType::CodeEntity(CodeEntityKind::Function),
+        "total",
+        "fn total() {}",
+    );
+    let node_id = node.id;
+    let base = {
+        let stores = open_stores(dir.path(), Some(3)).await.unwrap();
+        stores
+            .execute(TransactionOp::InsertNode(node))
+            .await
+            .unwrap();
+        stores
+            .history_store
+            .record_version(VersionEntry::initial(node_id, "fn total() {}"))
+            .await
+            .unwrap()
+    };
+
+    for name in ["feature", "release"] {
+        let created = onyx(dir.path(), &["branch", "create", name, "--from", &base]);
+        assert!(
+            created.contains(&format!("Created branch '{name}'")),
+            "{created}"
+        );
+    }
+    let branches = onyx(dir.path(), &["branch", "list"]);
+    assert!(
+        branches.contains(&format!("feature | base {base}")),
+        "{branches}"
+    );
+    assert!(
+        branches.contains(&format!("release | base {base}")),
+        "{branches}"
+    );
+
+    // Only the feature branch changes the node, so the merge is clean.
+    {
+        let stores = open_stores(dir.path(), None).await.unwrap();
+        let change = VersionEntry::content_change(node_id, base.clone(), "fn total() { 1 }", 1, 1)
+            .with_branch("feature");
+        stores.history_store.record_version(change).await.unwrap();
+    }
+    let merged = onyx(
+        dir.path(),
+        &["merge", "--source", "feature", "--target", "release"],
+    );
+    assert!(
+        merged.contains("Merged 'feature' into 'release'"),
+        "{merged}"
+    );
+
+    let branches = onyx(dir.path(), &["branch", "list"]);
+    assert!(branches.contains("merged into release"), "{branches}");
+}
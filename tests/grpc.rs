@@ -0,0 +1,130 @@
+//! End-to-end tests for the gRPC transport: start the server, create a node
+//! and read it back through the generated client, and check that it shares
+//! its stores with the HTTP server.
+
+#![cfg(feature = "grpc")]
+
+use onyx::config::{AppConfig, AuthConfig, LimitsConfig, PaymentsConfig, ServerConfig};
+use onyx::server::auth::ApiKeys;
+use onyx::server::grpc::proto::node_service_client::NodeServiceClient;
+use onyx::server::grpc::proto::{CreateNodeRequest, GetNodeRequest};
+use onyx::server::grpc::{serve_grpc, serve_http_and_grpc};
+use onyx::server::health::HealthResponse;
+use onyx::server::AppState;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+fn config() -> AppConfig {
+    AppConfig {
+        server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            grpc_port: None,
+            cors: None,
+        },
+        payments: PaymentsConfig {
+            provider: None,
+            stripe_api_key: "sk_test_unused".to_string(),
+            stripe_webhook_secret: "whsec_unused".to_string(),
+            default_price_id: "price_default".to_string(),
+            success_url: "http://localhost/success".to_string(),
+            cancel_url: "http://localhost/cancel".to_string(),
+            portal_return_url: "http://localhost/portal".to_string(),
+        },
+        auth: AuthConfig::default(),
+        limits: LimitsConfig::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_create_and_get_node_over_grpc() {
+    let state = AppState::from_config(config()).await.unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn(serve_grpc(
+        state,
+        ApiKeys::default(),
+        listener,
+        shutdown.clone(),
+    ));
+
+    let mut client = NodeServiceClient::connect(format!("http://{addr}"))
+        .await
+        .unwrap();
+    let created = client
+        .create_node(CreateNodeRequest {
+            name: "calculate_total".to_string(),
+            content: "fn calculate_total() {}".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(created.node_type, "function");
+
+    let fetched = client
+        .get_node(GetNodeRequest {
+            id: created.id.clone(),
+        })
+        .await
+        .unwrap()
+        .into_inner();
+    assert_eq!(fetched.name, "calculate_total");
+    assert_eq!(fetched.content, "fn calculate_total() {}");
+
+    let missing = client
+        .get_node(GetNodeRequest {
+            id: uuid::Uuid::nil().to_string(),
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(missing.code(), tonic::Code::NotFound);
+
+    shutdown.cancel();
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_http_sees_nodes_created_over_grpc() {
+    let config = config();
+    let state = AppState::from_config(config.clone()).await.unwrap();
+    let http = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let grpc = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let (http_addr, grpc_addr) = (http.local_addr().unwrap(), grpc.local_addr().unwrap());
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { serve_http_and_grpc(state, &config, http, grpc, shutdown).await }
+    });
+
+    let mut client = NodeServiceClient::connect(format!("http://{grpc_addr}"))
+        .await
+        .unwrap();
+    client
+        .create_node(CreateNodeRequest {
+            name: "calculate_total".to_string(),
+            content: "fn calculate_total() {}".to_string(),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let mut stream = tokio::net::TcpStream::connect(http_addr).await.unwrap();
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(
+        head.starts_with("HTTP/1.1 200"),
+        "unexpected response: {head}"
+    );
+    let report: HealthResponse = serde_json::from_str(body).unwrap();
+    assert_eq!(report.node_count, 1);
+
+    shutdown.cancel();
+    server.await.unwrap().unwrap();
+}
@@ -0,0 +1,80 @@
+//! End-to-end test for serving RocksDB stores over HTTP: open a store on
+//! disk, start the server on an ephemeral port and check `/health`.
+
+#![cfg(feature = "rocksdb-storage")]
+
+use onyx::config::{AppConfig, AuthConfig, LimitsConfig, PaymentsConfig, ServerConfig};
+use onyx::model::node::{CodeEntityKind, Node, NodeType};
+use onyx::server::health::HealthResponse;
+use onyx::server::{serve_http, AppState};
+use onyx::store::persistent::open_stores;
+use onyx::store::transaction::TransactionOp;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+
+fn config() -> AppConfig {
+    AppConfig {
+        server: ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 0,
+            grpc_port: None,
+            cors: None,
+        },
+        payments: PaymentsConfig {
+            provider: None,
+            stripe_api_key: "sk_test_unused".to_string(),
+            stripe_webhook_secret: "whsec_unused".to_string(),
+            default_price_id: "price_default".to_string(),
+            success_url: "http://localhost/success".to_string(),
+            cancel_url: "http://localhost/cancel".to_string(),
+            portal_return_url: "http://localhost/portal".to_string(),
+        },
+        auth: AuthConfig::default(),
+        limits: LimitsConfig::default(),
+    }
+}
+
+#[tokio::test]
+async fn test_serve_rocks_store_reports_health() {
+    let dir = tempfile::tempdir().unwrap();
+    let stores = open_stores(dir.path(), Some(3)).await.unwrap();
+    stores
+        .execute(TransactionOp::InsertNode(Node::new(
+            NodeType::CodeEntity(CodeEntityKind::Function),
+            "calculate_total",
+            "fn calculate_total() {}",
+        )))
+        .await
+        .unwrap();
+
+    let config = config();
+    let state = AppState::with_stores(config.clone(), stores);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let shutdown = CancellationToken::new();
+    let server = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { serve_http(state, &config, listener, shutdown).await }
+    });
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(b"GET /health HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(
+        head.starts_with("HTTP/1.1 200"),
+        "unexpected response: {head}"
+    );
+    let report: HealthResponse = serde_json::from_str(body).unwrap();
+    assert_eq!(report.status, "ok");
+    assert!(report.db_reachable);
+    assert_eq!(report.node_count, 1);
+
+    shutdown.cancel();
+    server.await.unwrap().unwrap();
+}